@@ -0,0 +1,145 @@
+use std::{net::SocketAddr, path::PathBuf, time::Duration};
+
+use clap::{builder::RangedU64ValueParser, Parser, Subcommand};
+use ziggurat_xrpl::setup::network::NetworkProfile;
+
+use crate::{metrics::GraphExport, scheduler::CrawlSettings};
+
+#[derive(Debug, Parser)]
+#[clap(author, version, about, long_about = None)]
+pub(super) struct Args {
+    #[clap(subcommand)]
+    pub(super) command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+pub(super) enum Command {
+    /// Crawl the network until interrupted
+    Crawl(CrawlArgs),
+
+    /// Crawl every reachable node once, then print the network summary and exit
+    Once {
+        #[clap(flatten)]
+        crawl: CrawlArgs,
+
+        /// The longest time in seconds the crawl may take, the summary covers the nodes crawled
+        /// so far when it's reached
+        #[clap(long, value_parser, default_value_t = 10 * 60)]
+        duration: u64,
+    },
+
+    /// Print the network summary of a crawl state saved with --resume
+    Report(ReportArgs),
+}
+
+#[derive(Debug, clap::Args)]
+pub(super) struct CrawlArgs {
+    /// The initial addresses to connect to, the network's published hubs are used if not set
+    #[clap(short, long, value_parser, num_args = 1..)]
+    pub(super) seed_addrs: Vec<SocketAddr>,
+
+    /// The network to crawl: mainnet, testnet, devnet or local
+    #[clap(short, long, value_parser, default_value_t = NetworkProfile::MAINNET)]
+    pub(super) network: NetworkProfile,
+
+    /// If present, start an RPC server at the specified address
+    #[clap(short, long, value_parser)]
+    pub(super) rpc_addr: Option<SocketAddr>,
+
+    /// If present, serve the network summary in Prometheus format at http://<addr>/metrics
+    #[clap(short, long, value_parser)]
+    pub(super) metrics_addr: Option<SocketAddr>,
+
+    /// If present, the crawl state is periodically saved to the file and reloaded from it on start
+    #[clap(long, value_parser)]
+    pub(super) resume: Option<PathBuf>,
+
+    /// If present, nodes are located with the MaxMind GeoIP2/GeoLite2 City database at the path
+    #[clap(long, value_parser)]
+    pub(super) geoip_city_db: Option<PathBuf>,
+
+    /// If present, nodes' autonomous systems are resolved with the MaxMind ASN database at the path
+    #[clap(long, value_parser)]
+    pub(super) geoip_asn_db: Option<PathBuf>,
+
+    /// The maximum number of nodes crawled at the same time
+    #[clap(long, value_parser = RangedU64ValueParser::<usize>::new().range(1..), default_value_t = 64)]
+    pub(super) max_concurrency: usize,
+
+    /// The delay in seconds before crawling a node again after a successful crawl
+    #[clap(long, value_parser, default_value_t = 3 * 60)]
+    pub(super) retry_min: u64,
+
+    /// The maximum delay in seconds before crawling a node again, reached by doubling the delay
+    /// after each failed crawl
+    #[clap(long, value_parser, default_value_t = 5 * 60)]
+    pub(super) retry_max: u64,
+
+    /// If present, only nodes at most this many hops away from the seed nodes are crawled
+    #[clap(long, value_parser)]
+    pub(super) max_depth: Option<u32>,
+
+    /// If present, the overlay graph is periodically written to the file in Graphviz DOT format
+    #[clap(long, value_parser)]
+    pub(super) export_dot: Option<PathBuf>,
+
+    /// If present, the overlay graph is periodically written to the file in JSON format
+    #[clap(long, value_parser)]
+    pub(super) export_json: Option<PathBuf>,
+
+    /// If present, peers of nodes which don't serve /crawl are discovered from the endpoints they
+    /// gossip over the peer protocol
+    #[clap(long, value_parser)]
+    pub(super) peer_protocol_fallback: bool,
+
+    /// If present, the validator lists of the publishers each node trusts are fetched from its
+    /// /vl endpoint and verified
+    #[clap(long, value_parser)]
+    pub(super) fetch_validator_lists: bool,
+}
+
+impl CrawlArgs {
+    /// Returns the settings of the crawl, nodes are crawled again after a delay if `revisit` is set.
+    pub(super) fn crawl_settings(&self, revisit: bool) -> CrawlSettings {
+        CrawlSettings {
+            max_concurrency: self.max_concurrency,
+            retry_min: Duration::from_secs(self.retry_min),
+            retry_max: Duration::from_secs(self.retry_max),
+            max_depth: self.max_depth,
+            peer_protocol_fallback: self.peer_protocol_fallback,
+            fetch_validator_lists: self.fetch_validator_lists,
+            revisit,
+        }
+    }
+
+    pub(super) fn graph_export(&self) -> GraphExport {
+        GraphExport {
+            dot: self.export_dot.clone(),
+            json: self.export_json.clone(),
+        }
+    }
+}
+
+#[derive(Debug, clap::Args)]
+pub(super) struct ReportArgs {
+    /// The crawl state file
+    #[clap(value_parser)]
+    pub(super) state_file: PathBuf,
+
+    /// If present, the overlay graph is written to the file in Graphviz DOT format
+    #[clap(long, value_parser)]
+    pub(super) export_dot: Option<PathBuf>,
+
+    /// If present, the overlay graph is written to the file in JSON format
+    #[clap(long, value_parser)]
+    pub(super) export_json: Option<PathBuf>,
+}
+
+impl ReportArgs {
+    pub(super) fn graph_export(&self) -> GraphExport {
+        GraphExport {
+            dot: self.export_dot.clone(),
+            json: self.export_json.clone(),
+        }
+    }
+}
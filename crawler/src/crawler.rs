@@ -0,0 +1,439 @@
+use std::{
+    collections::{HashMap, HashSet},
+    io,
+    net::{IpAddr, SocketAddr},
+    path::Path,
+    sync::Arc,
+    time::Duration,
+};
+
+use pea2pea::protocols::{Handshake, Reading};
+use reqwest::Client;
+use tokio::{
+    sync::{
+        mpsc::{self, Receiver},
+        Mutex,
+    },
+    task::JoinSet,
+    time::{timeout, Instant},
+};
+use tracing::{debug, trace, warn};
+use ziggurat_xrpl::{
+    protocol::{
+        codecs::message::{BinaryMessage, Payload},
+        handshake::HandshakeRejected,
+    },
+    setup::network::NetworkProfile,
+    tools::{
+        crawl::{get_crawl_response, get_validator_list, CrawlResponse, Peer, Unl},
+        endpoints::parse_endpoints,
+        inner_node::InnerNode,
+    },
+};
+
+use crate::{
+    geoip::GeoIp,
+    network::{HandshakeMetadata, KnownNetwork},
+    scheduler::{CrawlJob, CrawlSettings, Scheduler},
+    Limiter,
+};
+
+const CRAWLER_DEFAULT_PORT: u16 = NetworkProfile::MAINNET.peer_port;
+const PROTOCOL_DEFAULT_PORT: u16 = 2459;
+
+/// Time to wait for a node to gossip its peers over the peer protocol.
+const ENDPOINTS_TIMEOUT: Duration = Duration::from_secs(60);
+
+pub(super) struct Crawler {
+    pub(super) known_network: Arc<KnownNetwork>,
+}
+
+impl Crawler {
+    pub(super) async fn new(geoip: Option<GeoIp>) -> Self {
+        Self::with_network(KnownNetwork::default(), geoip)
+    }
+
+    /// Creates the crawler with the network state saved by a previous run.
+    pub(super) async fn resume(path: &Path, geoip: Option<GeoIp>) -> io::Result<Self> {
+        Ok(Self::with_network(KnownNetwork::load(path)?, geoip))
+    }
+
+    fn with_network(known_network: KnownNetwork, geoip: Option<GeoIp>) -> Self {
+        let known_network = match geoip {
+            Some(geoip) => known_network.with_geoip(geoip),
+            None => known_network,
+        };
+
+        Self {
+            known_network: Arc::new(known_network),
+        }
+    }
+}
+
+/// The workers of a running crawl.
+pub(super) struct CrawlHandle {
+    scheduler: Arc<Scheduler>,
+    workers: JoinSet<()>,
+}
+
+impl CrawlHandle {
+    /// Waits until every node is crawled, which never happens if nodes are revisited.
+    pub(super) async fn until_done(&self) {
+        self.scheduler.until_idle().await;
+    }
+
+    /// Stops the workers, the crawls in progress are dropped.
+    pub(super) async fn stop(mut self) {
+        self.workers.shutdown().await;
+    }
+}
+
+/// Starts the workers crawling the scheduled nodes, along with the seeds and the nodes known from
+/// a previous run.
+pub(super) async fn start(
+    settings: CrawlSettings,
+    client: Client,
+    limiter: Arc<Limiter>,
+    known_network: Arc<KnownNetwork>,
+    seed_addrs: &[SocketAddr],
+    resumed_addrs: &[SocketAddr],
+) -> CrawlHandle {
+    let scheduler = Arc::new(Scheduler::new(settings));
+    let context = Arc::new(CrawlContext {
+        client,
+        limiter,
+        known_network,
+        scheduler: scheduler.clone(),
+    });
+
+    let mut workers = JoinSet::new();
+    for _ in 0..context.scheduler.settings.max_concurrency {
+        workers.spawn(crawl_worker(context.clone()));
+    }
+
+    for addr in seed_addrs {
+        discover(&context, addr.ip(), Some(addr.port()), 0).await;
+    }
+    for addr in resumed_addrs {
+        context
+            .scheduler
+            .schedule(CrawlJob::new(addr.ip(), Some(addr.port()), 0));
+    }
+
+    CrawlHandle { scheduler, workers }
+}
+
+/// The state shared by the crawl workers.
+struct CrawlContext {
+    client: Client,
+    limiter: Arc<Limiter>,
+    known_network: Arc<KnownNetwork>,
+    scheduler: Arc<Scheduler>,
+}
+
+/// Crawls the scheduled nodes one after another, as they become due.
+async fn crawl_worker(context: Arc<CrawlContext>) {
+    loop {
+        let job = context.scheduler.next_job().await;
+        crawl_node(&context, job).await;
+        context.scheduler.done();
+    }
+}
+
+/// Schedules the crawl of a node seen for the first time.
+async fn discover(context: &CrawlContext, ip: IpAddr, port: Option<u16>, depth: u32) {
+    // Nodes too far away stay unknown, they may still be found through a shorter path.
+    if !context.scheduler.settings.is_within_depth(depth) {
+        return;
+    }
+
+    let addr = SocketAddr::new(ip, port.unwrap_or(CRAWLER_DEFAULT_PORT));
+    if !context.known_network.new_node(addr).await {
+        trace!("Skip crawling a known node {ip}");
+        return;
+    }
+
+    context.scheduler.schedule(CrawlJob::new(ip, port, depth));
+}
+
+/// Crawls the node and schedules its next crawl, unless it failed to respond too many times.
+async fn crawl_node(context: &CrawlContext, mut job: CrawlJob) {
+    let ip = job.ip;
+    trace!("Crawling {ip}");
+
+    // The results are recorded under the address the node is known by until it's relocated to
+    // the port it responded on.
+    let known_network = &context.known_network;
+    let key = known_network
+        .resolve(SocketAddr::new(
+            ip,
+            job.port.unwrap_or(CRAWLER_DEFAULT_PORT),
+        ))
+        .await;
+
+    let mut responded_on = None;
+    for port in get_ports_to_try(job.port) {
+        context.limiter.until_ready().await;
+
+        let addr = SocketAddr::new(ip, port);
+        let (connection, crawled) = tokio::join!(
+            try_handshake(context, addr, key, job.depth),
+            try_crawling(context, key, port, job.depth),
+        );
+        let mut success = crawled;
+        if let Some(connection) = connection {
+            // Nodes with the /crawl endpoint disabled still gossip their peers.
+            if !success && context.scheduler.settings.peer_protocol_fallback {
+                success = try_crawling_peer_protocol(context, key, &connection, job.depth).await;
+            }
+            connection.node.shut_down().await;
+        }
+        if success {
+            responded_on = Some(SocketAddr::new(ip, port));
+            break;
+        }
+    }
+
+    let failures = match responded_on {
+        Some(addr) => {
+            known_network.relocate(key, addr).await;
+            job.port = Some(addr.port());
+            0
+        }
+        None => known_network.increase_connection_failures(key).await,
+    };
+    if failures == u8::MAX {
+        warn!("Giving up connecting to {ip}");
+        return;
+    }
+    if !context.scheduler.settings.revisit {
+        return;
+    }
+
+    // Even if connection was successful - try again after a while to update peers.
+    let delay = context.scheduler.settings.retry_delay(failures);
+    context.scheduler.schedule(job.after(delay));
+}
+
+fn get_ports_to_try(from_response: Option<u16>) -> HashSet<u16> {
+    let mut ports = HashSet::new();
+    if let Some(port) = from_response {
+        ports.insert(port);
+    } else {
+        ports.insert(CRAWLER_DEFAULT_PORT);
+        ports.insert(PROTOCOL_DEFAULT_PORT);
+    }
+    ports
+}
+
+/// A connection established by [try_handshake].
+struct PeerConnection {
+    node: InnerNode,
+    addr: SocketAddr,
+    receiver: Mutex<Receiver<(SocketAddr, BinaryMessage)>>,
+    connecting_time: Duration,
+    metadata: HandshakeMetadata,
+}
+
+/// Performs the handshake with the node and records its metadata under `key`.
+///
+/// Returns the connection if successful, it's up to the caller to shut it down. The endpoints a
+/// full node redirects to are crawled too.
+async fn try_handshake(
+    context: &CrawlContext,
+    addr: SocketAddr,
+    key: SocketAddr,
+    depth: u32,
+) -> Option<PeerConnection> {
+    let known_network = &context.known_network;
+    let (sender, receiver) = mpsc::channel(1024);
+    let node = InnerNode::new(&Default::default(), sender).await;
+    node.enable_handshake().await;
+    node.enable_reading().await;
+
+    let start = Instant::now();
+    let result = node.connect(addr).await;
+    let connecting_time = start.elapsed();
+    let metadata = match result {
+        Ok(()) => node.peer_handshake_info(addr).map(HandshakeMetadata::from),
+        Err(e) => {
+            // The redirect only lists endpoints the node knows of, not its peers.
+            let redirect = HandshakeRejected::from_error(&e).and_then(HandshakeRejected::peer_ips);
+            for peer in redirect.unwrap_or_default() {
+                discover(context, peer.ip(), Some(peer.port()), depth + 1).await;
+            }
+            None
+        }
+    };
+    known_network
+        .set_handshake_result(key, metadata.clone())
+        .await;
+
+    match metadata {
+        Some(metadata) => {
+            trace!("Successful handshake to {}", addr);
+            Some(PeerConnection {
+                node,
+                addr,
+                receiver: Mutex::new(receiver),
+                connecting_time,
+                metadata,
+            })
+        }
+        None => {
+            trace!("Unsuccessful handshake to {}", addr);
+            node.shut_down().await;
+            None
+        }
+    }
+}
+
+/// Discovers the node's peers from the [TmEndpoints](ziggurat_xrpl::protocol::proto::TmEndpoints)
+/// it gossips, for nodes which don't serve /crawl.
+///
+/// The endpoints one hop away are the node's peers, the ones further away are only crawled.
+async fn try_crawling_peer_protocol(
+    context: &CrawlContext,
+    key: SocketAddr,
+    connection: &PeerConnection,
+    depth: u32,
+) -> bool {
+    let mut receiver = connection.receiver.lock().await;
+    let endpoints = timeout(ENDPOINTS_TIMEOUT, async {
+        while let Some((_, message)) = receiver.recv().await {
+            if let Payload::TmEndpoints(endpoints) = message.payload {
+                return Some(parse_endpoints(&endpoints));
+            }
+        }
+        None
+    })
+    .await;
+    let Ok(Some(endpoints)) = endpoints else {
+        debug!("No endpoints gossiped by {}", connection.addr);
+        return false;
+    };
+
+    let known_network = &context.known_network;
+    known_network
+        .update_stats(
+            key,
+            connection.connecting_time,
+            connection.metadata.server.clone().unwrap_or_default(),
+            None,
+            connection.metadata.network(),
+        )
+        .await;
+    let peers = endpoints
+        .iter()
+        .filter(|endpoint| endpoint.hops == 1)
+        .map(|endpoint| endpoint.addr)
+        .collect::<Vec<_>>();
+    known_network.insert_connections(key, &peers).await;
+    for endpoint in endpoints {
+        let hops = endpoint.hops.max(1);
+        discover(
+            context,
+            endpoint.addr.ip(),
+            Some(endpoint.addr.port()),
+            depth.saturating_add(hops),
+        )
+        .await;
+    }
+    true
+}
+
+/// Crawls the node's /crawl endpoint on the port, recording the results under `key`.
+async fn try_crawling(context: &CrawlContext, key: SocketAddr, port: u16, depth: u32) -> bool {
+    let known_network = &context.known_network;
+    let ip = key.ip();
+    match get_crawl_response(context.client.clone(), SocketAddr::new(ip, port)).await {
+        Ok((response, connecting_time)) => {
+            let addresses = extract_known_nodes(&response).await;
+            known_network
+                .update_stats(
+                    key,
+                    connecting_time,
+                    response.server.build_version,
+                    Some(response.server.uptime),
+                    // Like the 'Network-ID' handshake field, it's missing for the main network.
+                    Some(
+                        response
+                            .server
+                            .network_id
+                            .unwrap_or(NetworkProfile::MAINNET.network_id),
+                    ),
+                )
+                .await;
+            let peers = addresses
+                .iter()
+                .map(|(ip, port)| SocketAddr::new(*ip, port.unwrap_or(CRAWLER_DEFAULT_PORT)))
+                .collect::<Vec<_>>();
+            known_network.insert_connections(key, &peers).await;
+            for (ip, port) in addresses {
+                discover(context, ip, port, depth + 1).await;
+            }
+            if context.scheduler.settings.fetch_validator_lists {
+                let unl = response.unl.unwrap_or_default();
+                let lists = fetch_validator_lists(context, SocketAddr::new(ip, port), &unl).await;
+                known_network.set_validator_lists(key, lists).await;
+            }
+            true
+        }
+        Err(e) => {
+            debug!("Unable to get crawl response from {}: {:?}", ip, e);
+            false
+        }
+    }
+}
+
+/// Fetches the validator lists of the publishers the node trusts and verifies them.
+///
+/// Returns the highest sequence among the blobs of each list, `None` if the list failed
+/// verification. The lists the node doesn't serve are left out.
+async fn fetch_validator_lists(
+    context: &CrawlContext,
+    addr: SocketAddr,
+    unl: &Unl,
+) -> HashMap<String, Option<u32>> {
+    let mut lists = HashMap::new();
+    for publisher_key in unl.publisher_keys() {
+        let list = match get_validator_list(context.client.clone(), addr, publisher_key).await {
+            Ok(list) => list,
+            Err(e) => {
+                debug!("Unable to get the validator list of {publisher_key} from {addr}: {e}");
+                continue;
+            }
+        };
+
+        let verified = match list.public_key.eq_ignore_ascii_case(publisher_key) {
+            true => list.verify().map_err(|e| e.to_string()),
+            false => Err(format!("the list is published by {}", list.public_key)),
+        };
+        let sequence = match verified {
+            Ok(blobs) => blobs.iter().map(|blob| blob.list.sequence).max(),
+            Err(e) => {
+                warn!("Invalid validator list of {publisher_key} served by {addr}: {e}");
+                None
+            }
+        };
+        lists.insert(publisher_key.to_uppercase(), sequence);
+    }
+    lists
+}
+
+/// Extract addresses from /crawl response.
+async fn extract_known_nodes(response: &CrawlResponse) -> Vec<(IpAddr, Option<u16>)> {
+    response
+        .peerlist
+        .active
+        .iter()
+        .filter_map(parse_peer_addr)
+        .collect()
+}
+
+/// Tries to parse address information from response.
+/// On success returns optional tuple of Ip address, and optional port.
+fn parse_peer_addr(peer: &Peer) -> Option<(IpAddr, Option<u16>)> {
+    let ip = peer.ip.as_ref()?.parse().ok()?;
+    Some((ip, peer.port()))
+}
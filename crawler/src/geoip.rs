@@ -0,0 +1,71 @@
+use std::{net::IpAddr, path::Path};
+
+use maxminddb::{geoip2, MaxMindDBError, Reader};
+use serde::{Deserialize, Serialize};
+
+/// The location and network of a node's address.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct GeoInfo {
+    /// ISO 3166-1 country code, e.g. `DE`.
+    pub country: Option<String>,
+    /// The city's English name.
+    pub city: Option<String>,
+    /// The number of the autonomous system announcing the address.
+    pub asn: Option<u32>,
+    /// The organization running the autonomous system.
+    pub as_org: Option<String>,
+}
+
+impl GeoInfo {
+    /// The key the node is counted under in the per-ASN distribution, e.g. `AS24940`.
+    pub fn asn_key(&self) -> Option<String> {
+        self.asn.map(|asn| format!("AS{asn}"))
+    }
+}
+
+/// Resolves addresses with MaxMind's GeoIP2/GeoLite2 City and ASN databases.
+pub struct GeoIp {
+    city: Option<Reader<Vec<u8>>>,
+    asn: Option<Reader<Vec<u8>>>,
+}
+
+impl GeoIp {
+    /// Opens the databases, either of them can be omitted.
+    pub fn open(city_db: Option<&Path>, asn_db: Option<&Path>) -> Result<Self, MaxMindDBError> {
+        Ok(Self {
+            city: city_db.map(Reader::open_readfile).transpose()?,
+            asn: asn_db.map(Reader::open_readfile).transpose()?,
+        })
+    }
+
+    /// Returns `None` if none of the databases know the address.
+    pub fn lookup(&self, ip: IpAddr) -> Option<GeoInfo> {
+        let mut info = GeoInfo::default();
+
+        if let Some(Ok(city)) = self
+            .city
+            .as_ref()
+            .map(|reader| reader.lookup::<geoip2::City>(ip))
+        {
+            info.country = city
+                .country
+                .and_then(|country| country.iso_code)
+                .map(str::to_owned);
+            info.city = city
+                .city
+                .and_then(|city| city.names)
+                .and_then(|names| names.get("en").map(|name| name.to_string()));
+        }
+
+        if let Some(Ok(asn)) = self
+            .asn
+            .as_ref()
+            .map(|reader| reader.lookup::<geoip2::Asn>(ip))
+        {
+            info.asn = asn.autonomous_system_number;
+            info.as_org = asn.autonomous_system_organization.map(str::to_owned);
+        }
+
+        (info != GeoInfo::default()).then_some(info)
+    }
+}
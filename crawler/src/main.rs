@@ -0,0 +1,260 @@
+use std::{
+    num::NonZeroU32,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use clap::{error::ErrorKind, CommandFactory, Parser};
+use governor::{
+    clock::{QuantaClock, QuantaInstant},
+    middleware::NoOpMiddleware,
+    state::{InMemoryState, NotKeyed},
+    Jitter, Quota, RateLimiter,
+};
+use jsonrpsee::server::ServerHandle;
+use reqwest::Client;
+use tokio::{
+    signal,
+    time::{timeout, Instant},
+};
+use tracing::{info, warn};
+use tracing_subscriber::filter::{EnvFilter, LevelFilter};
+
+use crate::{
+    args::{Args, Command, CrawlArgs},
+    crawler::{CrawlHandle, Crawler},
+    geoip::GeoIp,
+    metrics::CrawlSummary,
+    network::{persist_network_task, summarize, update_summary_snapshot_task, KnownNetwork},
+    prometheus::initialize_metrics_server,
+    rpc::{initialize_rpc_server, RpcContext},
+};
+
+mod args;
+mod crawler;
+mod geoip;
+mod metrics;
+mod network;
+mod prometheus;
+mod rpc;
+mod scheduler;
+
+const CRAWLER_TIMEOUT: Duration = Duration::from_secs(10);
+const MAX_REQUESTS_PER_SEC: u32 = 25;
+const JITTER_MAX_SEC: u64 = 30;
+
+fn start_logger(default_level: LevelFilter) {
+    let filter = match EnvFilter::try_from_default_env() {
+        Ok(filter) => filter
+            .add_directive("tokio_util=off".parse().unwrap())
+            .add_directive("mio=off".parse().unwrap()),
+        _ => EnvFilter::default()
+            .add_directive(default_level.into())
+            .add_directive("tokio_util=off".parse().unwrap())
+            .add_directive("mio=off".parse().unwrap()),
+    };
+
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_target(false)
+        .init();
+}
+
+struct Limiter {
+    limiter: RateLimiter<NotKeyed, InMemoryState, QuantaClock, NoOpMiddleware<QuantaInstant>>,
+    jitter: Jitter,
+}
+
+impl Limiter {
+    /// Wrapper function around `governor::RateLimiter::until_ready_with_jitter`, using
+    /// the self contained `Jitter`.
+    async fn until_ready(&self) {
+        self.limiter.until_ready_with_jitter(self.jitter).await;
+    }
+}
+
+impl Default for Limiter {
+    fn default() -> Self {
+        Self {
+            limiter: RateLimiter::direct(Quota::per_second(
+                NonZeroU32::new(MAX_REQUESTS_PER_SEC).unwrap(),
+            )),
+            jitter: Jitter::up_to(Duration::from_secs(JITTER_MAX_SEC)),
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let args = Args::parse();
+    // Checked before anything is started, as clap can't relate the values of two arguments.
+    if let Command::Crawl(crawl) | Command::Once { crawl, .. } = &args.command {
+        if crawl.retry_min > crawl.retry_max {
+            Args::command()
+                .error(
+                    ErrorKind::ArgumentConflict,
+                    "--retry-min must not exceed --retry-max",
+                )
+                .exit();
+        }
+    }
+
+    start_logger(LevelFilter::INFO);
+
+    match args.command {
+        Command::Crawl(args) => {
+            let crawl = Crawl::start(args, true).await;
+            signal::ctrl_c()
+                .await
+                .expect("unable to listen for the interrupt signal");
+            info!("Stopping the crawl");
+            crawl.stop().await;
+        }
+        Command::Once { crawl, duration } => {
+            let start = Instant::now();
+            let graph_export = crawl.graph_export();
+            let crawl = Crawl::start(crawl, false).await;
+            if timeout(Duration::from_secs(duration), crawl.handle.until_done())
+                .await
+                .is_err()
+            {
+                warn!("The crawl didn't finish within {duration} seconds");
+            }
+            let known_network = crawl.stop().await;
+            print_summary(&summarize(known_network, &graph_export, start.elapsed()).await);
+        }
+        Command::Report(args) => {
+            let known_network =
+                KnownNetwork::load(&args.state_file).expect("unable to load the crawl state");
+            let summary = summarize(
+                Arc::new(known_network),
+                &args.graph_export(),
+                Duration::ZERO,
+            )
+            .await;
+            print_summary(&summary);
+        }
+    }
+}
+
+fn print_summary(summary: &CrawlSummary) {
+    let json = serde_json::to_string_pretty(summary).expect("unable to serialize the summary");
+    println!("{json}");
+}
+
+/// A running crawl, along with the servers and tasks exposing its state.
+struct Crawl {
+    handle: CrawlHandle,
+    known_network: Arc<KnownNetwork>,
+    resume: Option<PathBuf>,
+    _rpc_handle: Option<ServerHandle>,
+}
+
+impl Crawl {
+    /// Starts crawling from the seeds, nodes are crawled again after a delay if `revisit` is set.
+    async fn start(args: CrawlArgs, revisit: bool) -> Self {
+        let summary_snapshot = Arc::new(Mutex::new(CrawlSummary::default()));
+        let rpc_handle = if let Some(addr) = args.rpc_addr {
+            let rpc_context = RpcContext::new(summary_snapshot.clone());
+            let rpc_handle = initialize_rpc_server(addr, rpc_context).await;
+            Some(rpc_handle)
+        } else {
+            None
+        };
+
+        if let Some(addr) = args.metrics_addr {
+            initialize_metrics_server(addr, summary_snapshot.clone())
+                .await
+                .expect("unable to start the metrics server");
+        }
+
+        info!("Crawler starting with args: {:?}", args);
+        let geoip = if args.geoip_city_db.is_some() || args.geoip_asn_db.is_some() {
+            let geoip = GeoIp::open(args.geoip_city_db.as_deref(), args.geoip_asn_db.as_deref())
+                .expect("unable to open the GeoIP databases");
+            Some(geoip)
+        } else {
+            None
+        };
+        let crawler = match &args.resume {
+            Some(path) if path.exists() => Crawler::resume(path, geoip)
+                .await
+                .expect("unable to load the saved crawl state"),
+            _ => Crawler::new(geoip).await,
+        };
+        let resumed_nodes = crawler.known_network.nodes().await;
+        if !resumed_nodes.is_empty() {
+            info!("Resuming the crawl of {} known nodes", resumed_nodes.len());
+        }
+        if let Some(path) = args.resume.clone() {
+            tokio::spawn(persist_network_task(crawler.known_network.clone(), path));
+        }
+
+        let settings = args.crawl_settings(revisit);
+        let client = Client::builder()
+            .danger_accept_invalid_certs(true)
+            .timeout(CRAWLER_TIMEOUT)
+            .build()
+            .expect("unable to build the web client");
+        let limiter = Arc::new(Limiter::default());
+
+        tokio::spawn(update_summary_snapshot_task(
+            crawler.known_network.clone(),
+            summary_snapshot,
+            args.graph_export(),
+        ));
+        let seed_addrs = if args.seed_addrs.is_empty() {
+            args.network
+                .seed_addrs()
+                .await
+                .expect("unable to resolve the network's seed hosts")
+        } else {
+            args.seed_addrs
+        };
+        if seed_addrs.is_empty() && resumed_nodes.is_empty() {
+            panic!(
+                "no seed addresses, the {} network doesn't publish any",
+                args.network
+            );
+        }
+
+        // Nodes the previous run gave up on stay abandoned.
+        let resumed_addrs = resumed_nodes
+            .into_iter()
+            .filter(|(_, node)| node.connection_failures < u8::MAX)
+            .map(|(addr, _)| addr)
+            .collect::<Vec<_>>();
+        let handle = crawler::start(
+            settings,
+            client,
+            limiter,
+            crawler.known_network.clone(),
+            &seed_addrs,
+            &resumed_addrs,
+        )
+        .await;
+
+        Self {
+            handle,
+            known_network: crawler.known_network,
+            resume: args.resume,
+            _rpc_handle: rpc_handle,
+        }
+    }
+
+    /// Stops crawling and saves the crawl state, if it's persisted.
+    async fn stop(self) -> Arc<KnownNetwork> {
+        self.handle.stop().await;
+        if let Some(path) = &self.resume {
+            if let Err(e) = self.known_network.save(path).await {
+                warn!(
+                    "Unable to save the crawl state to {}: {}",
+                    path.display(),
+                    e
+                );
+            }
+        }
+        self.known_network
+    }
+}
@@ -0,0 +1,336 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt::Write,
+    fs, io,
+    net::SocketAddr,
+    path::PathBuf,
+    sync::Arc,
+    time::Duration,
+};
+
+use serde::Serialize;
+use spectre::{edge::Edge, graph::Graph};
+use tracing::warn;
+use ziggurat_core_crawler::summary::NetworkSummary;
+
+use crate::network::{KnownNetwork, KnownNode};
+
+/// The elapsed time before a connection should be regarded as inactive.
+pub const LAST_SEEN_CUTOFF: u64 = 10 * 60;
+
+/// The [NetworkSummary] with the XRPL-specific metrics, served by the RPC server.
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct CrawlSummary {
+    #[serde(flatten)]
+    pub network: NetworkSummary,
+    /// The number of good nodes in each country, keyed by the ISO country code.
+    pub countries: HashMap<String, usize>,
+    /// The number of good nodes in each autonomous system, keyed by e.g. `AS24940`.
+    pub asns: HashMap<String, usize>,
+    /// The number of nodes advertising each 'Server' field in their handshake.
+    pub handshake_servers: HashMap<String, usize>,
+    /// The number of nodes selecting each protocol version in their handshake.
+    pub protocol_versions: HashMap<String, usize>,
+    /// The number of nodes advertising each network ID in their handshake.
+    pub network_ids: HashMap<String, usize>,
+    /// The public keys of the nodes which completed a handshake.
+    pub node_public_keys: HashMap<SocketAddr, String>,
+    /// The number of hosts running the good nodes, several nodes may share a host.
+    pub num_good_hosts: usize,
+    /// The number of distinct public keys among the nodes, a node reachable on several addresses
+    /// is counted once.
+    pub num_public_keys: usize,
+    /// The validator lists served by the nodes, keyed by the publisher's master key in hex.
+    pub validator_lists: HashMap<String, ValidatorListSummary>,
+    /// The summary of the nodes of each network, keyed by the network ID. The nodes which haven't
+    /// responded yet aren't part of any, and only the connections within a network are counted.
+    pub networks: HashMap<u32, NetworkSummary>,
+}
+
+/// The validator lists of a publisher, as served by the nodes.
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct ValidatorListSummary {
+    /// The number of nodes serving each sequence of the list.
+    pub sequences: HashMap<u32, usize>,
+    /// The number of nodes serving a list which failed verification.
+    pub invalid: usize,
+}
+
+#[derive(Default)]
+pub struct NetworkMetrics {
+    graph: Graph<SocketAddr>,
+}
+
+impl NetworkMetrics {
+    /// Updates the network graph with new connections.
+    pub(super) async fn update_graph(&mut self, known_network: Arc<KnownNetwork>) {
+        for connection in known_network.connections().await {
+            let edge = Edge::new(connection.a, connection.b);
+            if connection.last_seen.elapsed().as_secs() > LAST_SEEN_CUTOFF {
+                self.graph.remove(&edge);
+            } else {
+                self.graph.insert(edge);
+            }
+        }
+    }
+
+    /// Returns the current graph, with the attributes of the known nodes. Nodes which are only
+    /// known from the connections have none.
+    pub(super) fn overlay_graph(&self, nodes: &HashMap<SocketAddr, KnownNode>) -> OverlayGraph {
+        let mut graph_nodes = nodes
+            .iter()
+            .map(|(addr, node)| (*addr, GraphNode::new(*addr, Some(node))))
+            .collect::<BTreeMap<_, _>>();
+        let mut edges = self
+            .graph
+            .edges()
+            .iter()
+            .map(|edge| {
+                let (a, b) = (*edge.source(), *edge.target());
+                (a.min(b), a.max(b))
+            })
+            .collect::<Vec<_>>();
+        edges.sort_unstable();
+        edges.dedup();
+
+        for addr in edges.iter().flat_map(|(a, b)| [*a, *b]) {
+            graph_nodes
+                .entry(addr)
+                .or_insert_with(|| GraphNode::new(addr, None));
+        }
+
+        OverlayGraph {
+            nodes: graph_nodes.into_values().collect(),
+            edges,
+        }
+    }
+}
+
+/// A node of the [OverlayGraph].
+#[derive(Debug, Clone, Serialize)]
+pub struct GraphNode {
+    pub addr: SocketAddr,
+    /// The node's server version.
+    pub version: Option<String>,
+    /// The node's uptime in seconds.
+    pub uptime: Option<u32>,
+    pub handshake_successful: bool,
+}
+
+impl GraphNode {
+    fn new(addr: SocketAddr, node: Option<&KnownNode>) -> Self {
+        Self {
+            addr,
+            version: node.and_then(|node| node.server.clone()),
+            uptime: node.and_then(|node| node.uptime),
+            handshake_successful: node.is_some_and(|node| node.handshake_successful),
+        }
+    }
+}
+
+/// The overlay graph, exported for visualization.
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct OverlayGraph {
+    /// The nodes, sorted by their addresses.
+    pub nodes: Vec<GraphNode>,
+    /// The undirected connections between the nodes, each listed once.
+    pub edges: Vec<(SocketAddr, SocketAddr)>,
+}
+
+impl OverlayGraph {
+    /// Renders the graph in Graphviz DOT format, the nodes which completed a handshake are green.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("graph overlay {\n    node [shape=box];\n");
+        for node in &self.nodes {
+            let version = node.version.as_deref().unwrap_or("unknown");
+            let _ = write!(
+                dot,
+                "    \"{addr}\" [label=\"{addr}\\n{version}\", version=\"{version}\"",
+                addr = node.addr,
+                version = version.replace('"', "\\\""),
+            );
+            if let Some(uptime) = node.uptime {
+                let _ = write!(dot, ", uptime={uptime}");
+            }
+            let color = if node.handshake_successful {
+                "green"
+            } else {
+                "gray"
+            };
+            let _ = writeln!(
+                dot,
+                ", handshake_successful={}, color={color}];",
+                node.handshake_successful
+            );
+        }
+        for (a, b) in &self.edges {
+            let _ = writeln!(dot, "    \"{a}\" -- \"{b}\";");
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+/// The files the [OverlayGraph] is written to.
+#[derive(Debug, Default, Clone)]
+pub struct GraphExport {
+    /// Written in Graphviz DOT format.
+    pub dot: Option<PathBuf>,
+    /// Written in JSON format.
+    pub json: Option<PathBuf>,
+}
+
+impl GraphExport {
+    pub fn is_empty(&self) -> bool {
+        self.dot.is_none() && self.json.is_none()
+    }
+
+    /// Writes the graph to the files, failures are only logged.
+    pub(super) fn write(&self, graph: &OverlayGraph) {
+        if let Some(path) = &self.dot {
+            if let Err(e) = fs::write(path, graph.to_dot()) {
+                warn!("Unable to write the graph to {}: {}", path.display(), e);
+            }
+        }
+        if let Some(path) = &self.json {
+            let json = serde_json::to_vec(graph).map_err(io::Error::from);
+            if let Err(e) = json.and_then(|json| fs::write(path, json)) {
+                warn!("Unable to write the graph to {}: {}", path.display(), e);
+            }
+        }
+    }
+}
+
+/// Builds a new [CrawlSummary] out of current state of [KnownNetwork]
+pub(super) async fn new_network_summary(
+    known_network: Arc<KnownNetwork>,
+    metrics: &mut NetworkMetrics,
+    crawler_runtime: Duration,
+) -> CrawlSummary {
+    let nodes = known_network.nodes().await;
+    let connections = known_network.connections().await;
+    let good_nodes = get_good_nodes(&nodes);
+    let countries = count_by(&good_nodes, |node| node.geo.as_ref()?.country.clone());
+    let asns = count_by(&good_nodes, |node| node.geo.as_ref()?.asn_key());
+    let num_good_hosts = good_nodes
+        .keys()
+        .map(SocketAddr::ip)
+        .collect::<HashSet<_>>()
+        .len();
+    let handshake_servers = count_by(&nodes, |node| node.handshake.as_ref()?.server.clone());
+    let protocol_versions = count_by(&nodes, |node| {
+        node.handshake.as_ref()?.protocol_version.clone()
+    });
+    let network_ids = count_by(&nodes, |node| node.handshake.as_ref()?.network_id.clone());
+    let node_public_keys: HashMap<_, _> = nodes
+        .iter()
+        .filter_map(|(addr, node)| Some((*addr, node.handshake.as_ref()?.public_key.clone())))
+        .collect();
+
+    let num_public_keys = node_public_keys.values().collect::<HashSet<_>>().len();
+
+    let mut validator_lists = HashMap::<String, ValidatorListSummary>::new();
+    for (publisher, sequence) in nodes.values().flat_map(|node| &node.validator_lists) {
+        let summary = validator_lists.entry(publisher.clone()).or_default();
+        match sequence {
+            Some(sequence) => *summary.sequences.entry(*sequence).or_default() += 1,
+            None => summary.invalid += 1,
+        }
+    }
+
+    let mut networks = HashMap::<u32, HashMap<SocketAddr, KnownNode>>::new();
+    for (addr, node) in &nodes {
+        if let Some(network_id) = node.network_id {
+            networks
+                .entry(network_id)
+                .or_default()
+                .insert(*addr, node.clone());
+        }
+    }
+    let networks = networks
+        .into_iter()
+        .map(|(network_id, nodes)| {
+            let connections = connections
+                .iter()
+                .filter(|connection| {
+                    nodes.contains_key(&connection.a) && nodes.contains_key(&connection.b)
+                })
+                .count();
+            let summary = summarize_nodes(&nodes, connections, metrics, crawler_runtime);
+            (network_id, summary)
+        })
+        .collect();
+
+    CrawlSummary {
+        network: summarize_nodes(&nodes, connections.len(), metrics, crawler_runtime),
+        countries,
+        asns,
+        handshake_servers,
+        protocol_versions,
+        network_ids,
+        node_public_keys,
+        num_good_hosts,
+        num_public_keys,
+        validator_lists,
+        networks,
+    }
+}
+
+/// Builds the [NetworkSummary] of the nodes, the adjacency indices only cover the connections
+/// between their good nodes.
+fn summarize_nodes(
+    nodes: &HashMap<SocketAddr, KnownNode>,
+    num_known_connections: usize,
+    metrics: &mut NetworkMetrics,
+    crawler_runtime: Duration,
+) -> NetworkSummary {
+    let good_nodes = get_good_nodes(nodes).into_keys().collect();
+    let nodes_indices = metrics.graph.get_filtered_adjacency_indices(&good_nodes);
+
+    NetworkSummary {
+        num_known_nodes: nodes.len(),
+        num_good_nodes: good_nodes.len(),
+        num_known_connections,
+        node_addrs: good_nodes,
+        user_agents: get_server_versions(nodes),
+        crawler_runtime,
+        nodes_indices,
+        ..Default::default()
+    }
+}
+
+/// Counts the nodes under the keys, nodes without one aren't counted.
+fn count_by(
+    nodes: &HashMap<SocketAddr, KnownNode>,
+    key: impl Fn(&KnownNode) -> Option<String>,
+) -> HashMap<String, usize> {
+    nodes
+        .values()
+        .filter_map(key)
+        .fold(HashMap::new(), |mut map, key| {
+            *map.entry(key).or_insert(0) += 1;
+            map
+        })
+}
+
+fn get_server_versions(nodes: &HashMap<SocketAddr, KnownNode>) -> HashMap<String, usize> {
+    nodes.iter().fold(HashMap::new(), |mut map, (_, node)| {
+        node.server.clone().map(|version| {
+            map.entry(version)
+                .and_modify(|count| *count += 1)
+                .or_insert(1)
+        });
+        map
+    })
+}
+
+fn get_good_nodes(nodes: &HashMap<SocketAddr, KnownNode>) -> HashMap<SocketAddr, KnownNode> {
+    nodes
+        .iter()
+        .filter_map(|(addr, node)| {
+            node.last_connected
+                .filter(|last| last.elapsed().as_secs() < LAST_SEEN_CUTOFF)
+                .map(|_| (*addr, node.clone()))
+        })
+        .collect()
+}
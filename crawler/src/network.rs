@@ -0,0 +1,446 @@
+use std::{
+    collections::{hash_map::Entry, HashMap, HashSet},
+    fs, io,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+use tokio::{
+    sync::RwLock,
+    time::{sleep, Instant},
+};
+use tracing::{debug, warn};
+use ziggurat_core_crawler::connection::KnownConnection;
+use ziggurat_xrpl::{protocol::handshake::HandshakeInfo, setup::network::NetworkProfile};
+
+use crate::{
+    geoip::{GeoInfo, GeoIp},
+    metrics::{new_network_summary, CrawlSummary, GraphExport, NetworkMetrics},
+};
+
+const SUMMARY_LOOP_INTERVAL: Duration = Duration::from_secs(10);
+const PERSIST_LOOP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The nodes and connections discovered so far.
+///
+/// Nodes are keyed by the address they listen on for the peer protocol, so several nodes on the
+/// same host are told apart. A node advertised without a port is known by the default port until
+/// it responds on another one, see [KnownNetwork::relocate].
+#[derive(Default)]
+pub struct KnownNetwork {
+    nodes: RwLock<HashMap<SocketAddr, KnownNode>>,
+    connections: RwLock<HashSet<KnownConnection>>,
+    /// The addresses nodes were advertised with, mapped to the ones they responded on.
+    aliases: RwLock<HashMap<SocketAddr, SocketAddr>>,
+    geoip: Option<GeoIp>,
+}
+
+impl KnownNetwork {
+    /// Locates new nodes with the GeoIP databases, as well as the already known nodes that aren't
+    /// located yet.
+    pub(super) fn with_geoip(mut self, geoip: GeoIp) -> Self {
+        for (addr, node) in self.nodes.get_mut().iter_mut() {
+            if node.geo.is_none() {
+                node.geo = geoip.lookup(addr.ip());
+            }
+        }
+        self.geoip = Some(geoip);
+        self
+    }
+
+    /// Returns the address the node advertised at `addr` is known by.
+    pub(super) async fn resolve(&self, addr: SocketAddr) -> SocketAddr {
+        self.aliases
+            .read()
+            .await
+            .get(&addr)
+            .copied()
+            .unwrap_or(addr)
+    }
+
+    /// Moves the node known by the address it was advertised with to the one it responded on,
+    /// along with its connections. Later mentions of the advertised address refer to the node.
+    pub(super) async fn relocate(&self, advertised: SocketAddr, actual: SocketAddr) {
+        if advertised == actual {
+            return;
+        }
+        self.aliases.write().await.insert(advertised, actual);
+
+        let mut nodes = self.nodes.write().await;
+        if let Some(node) = nodes.remove(&advertised) {
+            nodes.entry(actual).or_insert(node);
+        }
+        drop(nodes);
+
+        let mut connections = self.connections.write().await;
+        let moved = connections
+            .iter()
+            .filter(|connection| connection.a == advertised || connection.b == advertised)
+            .map(|connection| (connection.a, connection.b, connection.last_seen))
+            .collect::<Vec<_>>();
+        let rename = |addr| if addr == advertised { actual } else { addr };
+        for (a, b, last_seen) in moved {
+            connections.remove(&KnownConnection::new(a, b));
+            let mut connection = KnownConnection::new(rename(a), rename(b));
+            connection.last_seen = last_seen;
+            connections.insert(connection);
+        }
+    }
+
+    /// Inserts addr to known_nodes if not yet present (so to avoid overriding the node's statistics).
+    /// Returns true if it's a new node, false otherwise.
+    pub(super) async fn new_node(&self, addr: SocketAddr) -> bool {
+        let addr = self.resolve(addr).await;
+        let mut nodes = self.nodes.write().await;
+        if let Entry::Vacant(e) = nodes.entry(addr) {
+            e.insert(KnownNode {
+                geo: self
+                    .geoip
+                    .as_ref()
+                    .and_then(|geoip| geoip.lookup(addr.ip())),
+                ..Default::default()
+            });
+            debug!("Known nodes: {}", nodes.len());
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Inserts connection from `from` to `peers`, the peers are known by their advertised
+    /// addresses.
+    pub(super) async fn insert_connections(&self, from: SocketAddr, peers: &[SocketAddr]) {
+        let aliases = self.aliases.read().await;
+        let mut connections = self.connections.write().await;
+        peers.iter().for_each(|peer| {
+            let peer = aliases.get(peer).unwrap_or(peer);
+            connections.insert(KnownConnection::new(from, *peer));
+        });
+    }
+
+    /// Updates stats for `peer`.
+    pub(super) async fn update_stats(
+        &self,
+        peer: SocketAddr,
+        connecting_time: Duration,
+        server_version: String,
+        uptime: Option<u32>,
+        network_id: Option<u32>,
+    ) {
+        let mut nodes = self.nodes.write().await;
+        let Some(node) = nodes.get_mut(&peer) else {
+            return;
+        };
+        node.last_connected = Some(Instant::now());
+        node.connection_failures = 0;
+        node.connecting_time = Some(connecting_time);
+        node.server = Some(server_version);
+        node.uptime = uptime;
+        if network_id.is_some() {
+            node.network_id = network_id;
+        }
+    }
+
+    /// Increases connection failures to the `addr` and returns its new value.
+    pub(super) async fn increase_connection_failures(&self, addr: SocketAddr) -> u8 {
+        let mut nodes = self.nodes.write().await;
+        let mut node = nodes.get_mut(&addr).unwrap();
+        node.connection_failures = node.connection_failures.saturating_add(1);
+        node.connection_failures
+    }
+
+    /// Replaces the validator lists served by the node.
+    pub(super) async fn set_validator_lists(
+        &self,
+        addr: SocketAddr,
+        validator_lists: HashMap<String, Option<u32>>,
+    ) {
+        if let Some(node) = self.nodes.write().await.get_mut(&addr) {
+            node.validator_lists = validator_lists;
+        }
+    }
+
+    /// Records the outcome of a handshake, keeping the metadata of the last successful one.
+    pub(super) async fn set_handshake_result(
+        &self,
+        addr: SocketAddr,
+        metadata: Option<HandshakeMetadata>,
+    ) {
+        let mut nodes = self.nodes.write().await;
+        if let Some(node) = nodes.get_mut(&addr) {
+            node.handshake_successful = metadata.is_some();
+            if let Some(metadata) = metadata {
+                node.network_id = metadata.network().or(node.network_id);
+                node.handshake = Some(metadata);
+            }
+        }
+    }
+
+    /// Returns a snapshot of the known connections.
+    pub async fn connections(&self) -> HashSet<KnownConnection> {
+        self.connections.read().await.clone()
+    }
+
+    /// Returns a snapshot of the known nodes.
+    pub async fn nodes(&self) -> HashMap<SocketAddr, KnownNode> {
+        self.nodes.read().await.clone()
+    }
+
+    /// Writes the known nodes and connections to the file, replacing it only once fully written.
+    pub(super) async fn save(&self, path: &Path) -> io::Result<()> {
+        let state = SavedNetwork {
+            nodes: self
+                .nodes()
+                .await
+                .into_iter()
+                .map(|(addr, node)| SavedNode {
+                    addr,
+                    last_connected: node.last_connected.map(|t| to_unix_secs(t.into_std())),
+                    connecting_time: node.connecting_time,
+                    server: node.server,
+                    uptime: node.uptime,
+                    connection_failures: node.connection_failures,
+                    handshake_successful: node.handshake_successful,
+                    handshake: node.handshake,
+                    geo: node.geo,
+                    network_id: node.network_id,
+                    validator_lists: node.validator_lists,
+                })
+                .collect(),
+            connections: self
+                .connections()
+                .await
+                .into_iter()
+                .map(|connection| SavedConnection {
+                    a: connection.a,
+                    b: connection.b,
+                    last_seen: to_unix_secs(connection.last_seen),
+                })
+                .collect(),
+            aliases: self.aliases.read().await.clone().into_iter().collect(),
+        };
+
+        let mut tmp_path = path.as_os_str().to_owned();
+        tmp_path.push(".tmp");
+        let tmp_path = PathBuf::from(tmp_path);
+        fs::write(&tmp_path, serde_json::to_vec(&state)?)?;
+        fs::rename(tmp_path, path)
+    }
+
+    /// Reads the known nodes and connections saved by [KnownNetwork::save].
+    pub(super) fn load(path: &Path) -> io::Result<Self> {
+        let state: SavedNetwork = serde_json::from_slice(&fs::read(path)?)?;
+
+        let nodes = state
+            .nodes
+            .into_iter()
+            .map(|node| {
+                let known_node = KnownNode {
+                    last_connected: node
+                        .last_connected
+                        .and_then(from_unix_secs)
+                        .map(Instant::from_std),
+                    connecting_time: node.connecting_time,
+                    server: node.server,
+                    uptime: node.uptime,
+                    connection_failures: node.connection_failures,
+                    handshake_successful: node.handshake_successful,
+                    handshake: node.handshake,
+                    geo: node.geo,
+                    network_id: node.network_id,
+                    validator_lists: node.validator_lists,
+                };
+                (node.addr, known_node)
+            })
+            .collect();
+        let connections = state
+            .connections
+            .into_iter()
+            .map(|saved| {
+                let mut connection = KnownConnection::new(saved.a, saved.b);
+                if let Some(last_seen) = from_unix_secs(saved.last_seen) {
+                    connection.last_seen = last_seen;
+                }
+                connection
+            })
+            .collect();
+
+        Ok(Self {
+            nodes: RwLock::new(nodes),
+            connections: RwLock::new(connections),
+            aliases: RwLock::new(state.aliases.into_iter().collect()),
+            geoip: None,
+        })
+    }
+}
+
+/// The on-disk form of [KnownNetwork], instants are stored as UNIX timestamps in seconds.
+#[derive(Serialize, Deserialize)]
+struct SavedNetwork {
+    nodes: Vec<SavedNode>,
+    connections: Vec<SavedConnection>,
+    /// The advertised addresses of the nodes and the ones they responded on.
+    #[serde(default)]
+    aliases: Vec<(SocketAddr, SocketAddr)>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedNode {
+    addr: SocketAddr,
+    last_connected: Option<u64>,
+    connecting_time: Option<Duration>,
+    server: Option<String>,
+    #[serde(default)]
+    uptime: Option<u32>,
+    connection_failures: u8,
+    handshake_successful: bool,
+    #[serde(default)]
+    handshake: Option<HandshakeMetadata>,
+    #[serde(default)]
+    geo: Option<GeoInfo>,
+    #[serde(default)]
+    network_id: Option<u32>,
+    #[serde(default)]
+    validator_lists: HashMap<String, Option<u32>>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedConnection {
+    a: SocketAddr,
+    b: SocketAddr,
+    last_seen: u64,
+}
+
+fn to_unix_secs(instant: std::time::Instant) -> u64 {
+    (SystemTime::now() - instant.elapsed())
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// Returns `None` if the timestamp predates the monotonic clock's start, e.g. after a reboot.
+fn from_unix_secs(secs: u64) -> Option<std::time::Instant> {
+    let age = SystemTime::now()
+        .duration_since(UNIX_EPOCH + Duration::from_secs(secs))
+        .unwrap_or_default();
+    std::time::Instant::now().checked_sub(age)
+}
+
+/// Saves the known network to the file periodically, so a later run can resume the crawl.
+pub(super) async fn persist_network_task(known_network: Arc<KnownNetwork>, path: PathBuf) {
+    loop {
+        sleep(PERSIST_LOOP_INTERVAL).await;
+        if let Err(e) = known_network.save(&path).await {
+            warn!(
+                "Unable to save the crawl state to {}: {}",
+                path.display(),
+                e
+            );
+        }
+    }
+}
+
+/// Builds the summary of the known network at once, writing the overlay graph to the files.
+pub(super) async fn summarize(
+    known_network: Arc<KnownNetwork>,
+    graph_export: &GraphExport,
+    crawler_runtime: Duration,
+) -> CrawlSummary {
+    let mut network_metrics = NetworkMetrics::default();
+    network_metrics.update_graph(known_network.clone()).await;
+    if !graph_export.is_empty() {
+        let graph = network_metrics.overlay_graph(&known_network.nodes().await);
+        graph_export.write(&graph);
+    }
+    new_network_summary(known_network, &mut network_metrics, crawler_runtime).await
+}
+
+pub(super) async fn update_summary_snapshot_task(
+    known_network: Arc<KnownNetwork>,
+    summary_snapshot: Arc<Mutex<CrawlSummary>>,
+    graph_export: GraphExport,
+) {
+    let start_time = Instant::now();
+    let mut network_metrics = NetworkMetrics::default();
+    loop {
+        sleep(SUMMARY_LOOP_INTERVAL).await;
+        network_metrics.update_graph(known_network.clone()).await;
+        if !graph_export.is_empty() {
+            let graph = network_metrics.overlay_graph(&known_network.nodes().await);
+            graph_export.write(&graph);
+        }
+        let new_network_summary = new_network_summary(
+            known_network.clone(),
+            &mut network_metrics,
+            start_time.elapsed(),
+        )
+        .await;
+        *summary_snapshot
+            .lock()
+            .expect("unable to take `summary_snapshot` lock") = new_network_summary;
+    }
+}
+
+/// A node encountered in the network or obtained from one of the peers.
+#[derive(Debug, Default, Clone)]
+pub struct KnownNode {
+    // // The address is omitted, as it's a key in the owning HashMap.
+    /// The last time the node was successfully connected to.
+    pub last_connected: Option<Instant>,
+    /// The time it took to complete a connection.
+    pub connecting_time: Option<Duration>,
+    /// The node's server version.
+    pub server: Option<String>,
+    /// The node's uptime in seconds, as of the last successful crawl of /crawl.
+    pub uptime: Option<u32>,
+    /// The number of subsequent connection errors.
+    pub connection_failures: u8,
+    /// Status for binary protocol connection/handshake attempt.
+    pub handshake_successful: bool,
+    /// What the node advertised in its latest successful handshake.
+    pub handshake: Option<HandshakeMetadata>,
+    /// The node's location, if GeoIP databases were given.
+    pub geo: Option<GeoInfo>,
+    /// The ID of the network the node is part of, known once it responded.
+    pub network_id: Option<u32>,
+    /// The highest sequence of each validator list the node serves, keyed by the publisher's
+    /// master key in hex. `None` if the list failed verification.
+    pub validator_lists: HashMap<String, Option<u32>>,
+}
+
+/// The metadata a node reveals in its handshake response, used to fingerprint implementations.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HandshakeMetadata {
+    /// The 'Server' field.
+    pub server: Option<String>,
+    /// The protocol version the node selected, e.g. `XRPL/2.2`.
+    pub protocol_version: Option<String>,
+    /// The node's public key, e.g. `n9KPZKMN...`.
+    pub public_key: String,
+    /// The 'Network-ID' field, missing for the main network.
+    pub network_id: Option<String>,
+}
+
+impl HandshakeMetadata {
+    /// Returns the ID of the network the node is part of, `None` if the field isn't a number.
+    pub fn network(&self) -> Option<u32> {
+        match &self.network_id {
+            Some(network_id) => network_id.parse().ok(),
+            None => Some(NetworkProfile::MAINNET.network_id),
+        }
+    }
+}
+
+impl From<HandshakeInfo> for HandshakeMetadata {
+    fn from(info: HandshakeInfo) -> Self {
+        Self {
+            network_id: info.header("Network-ID").map(str::to_owned),
+            server: info.ident,
+            protocol_version: info.protocol_version.map(|version| version.to_string()),
+            public_key: info.public_key,
+        }
+    }
+}
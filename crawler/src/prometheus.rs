@@ -0,0 +1,252 @@
+use std::{
+    collections::HashMap,
+    fmt::Write,
+    io,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+use tracing::{debug, warn};
+use ziggurat_core_crawler::summary::NetworkSummary;
+
+use crate::metrics::CrawlSummary;
+
+/// The largest request head read, scrapers only send a short GET request.
+const MAX_REQUEST_LEN: usize = 4096;
+
+/// The content type of the Prometheus text exposition format.
+const CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+
+/// Starts serving the network summary in Prometheus format at `http://<addr>/metrics`.
+pub async fn initialize_metrics_server(
+    addr: SocketAddr,
+    summary_snapshot: Arc<Mutex<CrawlSummary>>,
+) -> io::Result<()> {
+    let listener = TcpListener::bind(addr).await?;
+    debug!("Starting metrics server at {:?}", listener.local_addr()?);
+
+    tokio::spawn(async move {
+        loop {
+            let stream = match listener.accept().await {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    warn!("Unable to accept a metrics connection: {}", e);
+                    continue;
+                }
+            };
+
+            let summary_snapshot = summary_snapshot.clone();
+            tokio::spawn(async move {
+                if let Err(e) = serve(stream, summary_snapshot).await {
+                    debug!("Unable to serve metrics: {}", e);
+                }
+            });
+        }
+    });
+
+    Ok(())
+}
+
+async fn serve(
+    mut stream: TcpStream,
+    summary_snapshot: Arc<Mutex<CrawlSummary>>,
+) -> io::Result<()> {
+    let mut request = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+        let n = stream.read(&mut buf).await?;
+        if n == 0 || request.len() + n > MAX_REQUEST_LEN {
+            return Ok(());
+        }
+        request.extend_from_slice(&buf[..n]);
+    }
+
+    let request_line = String::from_utf8_lossy(&request);
+    let mut parts = request_line.split_whitespace();
+    let response = match (parts.next(), parts.next()) {
+        (Some("GET"), Some("/metrics")) => {
+            let body = render(&summary_snapshot.lock().unwrap());
+            format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: {CONTENT_TYPE}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len()
+            )
+        }
+        _ => "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_owned(),
+    };
+
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+/// Renders the summary in the Prometheus text exposition format.
+fn render(crawl_summary: &CrawlSummary) -> String {
+    let summary = &crawl_summary.network;
+    let mut out = String::new();
+
+    gauge(
+        &mut out,
+        "xrpl_crawler_known_nodes",
+        "Nodes discovered in the network.",
+        summary.num_known_nodes,
+    );
+    gauge(
+        &mut out,
+        "xrpl_crawler_good_nodes",
+        "Nodes successfully connected to recently.",
+        summary.num_good_nodes,
+    );
+    gauge(
+        &mut out,
+        "xrpl_crawler_good_hosts",
+        "Hosts running the good nodes.",
+        crawl_summary.num_good_hosts,
+    );
+    gauge(
+        &mut out,
+        "xrpl_crawler_public_keys",
+        "Distinct public keys of the nodes which completed a handshake.",
+        crawl_summary.num_public_keys,
+    );
+    gauge(
+        &mut out,
+        "xrpl_crawler_known_connections",
+        "Connections between nodes discovered in the network.",
+        summary.num_known_connections,
+    );
+    gauge(
+        &mut out,
+        "xrpl_crawler_runtime_seconds",
+        "Time the crawler has been running for.",
+        summary.crawler_runtime.as_secs_f64(),
+    );
+
+    labeled_gauge(
+        &mut out,
+        "xrpl_crawler_server_versions",
+        "Nodes running each server version.",
+        "version",
+        &summary.user_agents,
+    );
+    labeled_gauge(
+        &mut out,
+        "xrpl_crawler_country_nodes",
+        "Good nodes located in each country.",
+        "country",
+        &crawl_summary.countries,
+    );
+    labeled_gauge(
+        &mut out,
+        "xrpl_crawler_asn_nodes",
+        "Good nodes in each autonomous system.",
+        "asn",
+        &crawl_summary.asns,
+    );
+    labeled_gauge(
+        &mut out,
+        "xrpl_crawler_handshake_servers",
+        "Nodes advertising each server in their handshake.",
+        "server",
+        &crawl_summary.handshake_servers,
+    );
+    labeled_gauge(
+        &mut out,
+        "xrpl_crawler_protocol_versions",
+        "Nodes selecting each protocol version in their handshake.",
+        "version",
+        &crawl_summary.protocol_versions,
+    );
+    labeled_gauge(
+        &mut out,
+        "xrpl_crawler_network_ids",
+        "Nodes advertising each network ID in their handshake.",
+        "network_id",
+        &crawl_summary.network_ids,
+    );
+
+    let per_network = |count: fn(&NetworkSummary) -> usize| {
+        crawl_summary
+            .networks
+            .iter()
+            .map(|(network_id, summary)| (network_id.to_string(), count(summary)))
+            .collect::<HashMap<_, _>>()
+    };
+    labeled_gauge(
+        &mut out,
+        "xrpl_crawler_network_known_nodes",
+        "Nodes discovered in each network, by network ID.",
+        "network_id",
+        &per_network(|summary| summary.num_known_nodes),
+    );
+    labeled_gauge(
+        &mut out,
+        "xrpl_crawler_network_good_nodes",
+        "Nodes of each network successfully connected to recently, by network ID.",
+        "network_id",
+        &per_network(|summary| summary.num_good_nodes),
+    );
+
+    // The degrees are computed over the good nodes only, as are the adjacency indices.
+    let degrees = summary
+        .nodes_indices
+        .iter()
+        .map(|peers| peers.len())
+        .collect::<Vec<_>>();
+    let average = if degrees.is_empty() {
+        0.0
+    } else {
+        degrees.iter().sum::<usize>() as f64 / degrees.len() as f64
+    };
+    gauge(
+        &mut out,
+        "xrpl_crawler_degree_average",
+        "Average number of peers of the good nodes.",
+        average,
+    );
+    gauge(
+        &mut out,
+        "xrpl_crawler_degree_min",
+        "Smallest number of peers of the good nodes.",
+        degrees.iter().min().copied().unwrap_or_default(),
+    );
+    gauge(
+        &mut out,
+        "xrpl_crawler_degree_max",
+        "Largest number of peers of the good nodes.",
+        degrees.iter().max().copied().unwrap_or_default(),
+    );
+
+    out
+}
+
+fn gauge(out: &mut String, name: &str, help: &str, value: impl std::fmt::Display) {
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} gauge");
+    let _ = writeln!(out, "{name} {value}");
+}
+
+fn labeled_gauge(
+    out: &mut String,
+    name: &str,
+    help: &str,
+    label: &str,
+    values: &HashMap<String, usize>,
+) {
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} gauge");
+    let mut values = values.iter().collect::<Vec<_>>();
+    values.sort();
+    for (value, count) in values {
+        let _ = writeln!(out, "{name}{{{label}=\"{}\"}} {count}", escape_label(value));
+    }
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
@@ -12,7 +12,8 @@ use jsonrpsee::{
 };
 use serde::{Deserialize, Serialize};
 use tracing::{debug, warn};
-use ziggurat_core_crawler::summary::NetworkSummary;
+
+use crate::metrics::CrawlSummary;
 
 #[derive(Default, Clone, Deserialize, Serialize)]
 pub struct DumpSummary {
@@ -24,11 +25,11 @@ pub struct DumpSummary {
     pub message: String,
 }
 
-pub struct RpcContext(Arc<Mutex<NetworkSummary>>);
+pub struct RpcContext(Arc<Mutex<CrawlSummary>>);
 
 impl RpcContext {
     /// Creates a new RpcContext.
-    pub(crate) fn new(network_summary: Arc<Mutex<NetworkSummary>>) -> RpcContext {
+    pub(crate) fn new(network_summary: Arc<Mutex<CrawlSummary>>) -> RpcContext {
         RpcContext(network_summary)
     }
 }
@@ -56,7 +57,7 @@ fn create_rpc_module(rpc_context: RpcContext) -> RpcModule<RpcContext> {
             let report_params = params.parse::<ReportParams>()?;
             if let Some(path) = report_params.file {
                 let content = serde_json::to_string(rpc_context.0.lock().unwrap().deref())?;
-                // Wrap our NetworkSummary in a JSON-RPC response envelope
+                // Wrap our CrawlSummary in a JSON-RPC response envelope
                 let response =
                     "{\"jsonrpc\":\"2.0\",\"result\":".to_owned() + &content + ",\"id\":0}";
                 let length = response.len() as i32;
@@ -87,9 +88,9 @@ fn create_rpc_module(rpc_context: RpcContext) -> RpcModule<RpcContext> {
     module
 }
 
-/// Represents how to return [NetworkSummary].
+/// Represents how to return [CrawlSummary].
 #[derive(Deserialize, Debug)]
 pub struct ReportParams {
-    /// If present then [NetworkSummary] will be written to given file.
+    /// If present then [CrawlSummary] will be written to given file.
     file: Option<PathBuf>,
 }
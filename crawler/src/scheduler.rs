@@ -0,0 +1,170 @@
+//! Schedules the crawls of the known nodes, which a bounded pool of workers picks up when due.
+
+use std::{
+    cmp::Reverse,
+    collections::BinaryHeap,
+    net::IpAddr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+
+use rand::Rng;
+use tokio::{
+    sync::Notify,
+    time::{sleep_until, Instant},
+};
+
+/// Limits of the crawl, set from the command line.
+#[derive(Debug, Clone)]
+pub(super) struct CrawlSettings {
+    /// The number of nodes crawled at the same time.
+    pub(super) max_concurrency: usize,
+    /// The delay before crawling a node again after a successful crawl.
+    pub(super) retry_min: Duration,
+    /// The longest delay before crawling a node again, however many crawls failed before.
+    pub(super) retry_max: Duration,
+    /// How many hops away from the seed nodes the crawl goes, unlimited if not set.
+    pub(super) max_depth: Option<u32>,
+    /// Whether to discover the peers of nodes without /crawl from their endpoint gossip.
+    pub(super) peer_protocol_fallback: bool,
+    /// Whether to fetch and verify the validator lists the nodes serve.
+    pub(super) fetch_validator_lists: bool,
+    /// Whether nodes are crawled again after a delay, otherwise each node is crawled once.
+    pub(super) revisit: bool,
+}
+
+impl CrawlSettings {
+    /// Returns the delay before the next crawl of a node, doubled with each consecutive failure.
+    ///
+    /// The delay is randomly stretched up to twice its length, but no further than
+    /// [retry_max](Self::retry_max), so the nodes discovered together aren't crawled together.
+    pub(super) fn retry_delay(&self, failures: u8) -> Duration {
+        let backoff = 1u32.checked_shl(failures.into()).unwrap_or(u32::MAX);
+        let delay = self.retry_min.saturating_mul(backoff).min(self.retry_max);
+        let longest = delay.saturating_mul(2).min(self.retry_max);
+
+        rand::thread_rng().gen_range(delay..=longest)
+    }
+
+    /// Returns `true` if nodes this many hops away from the seeds should be crawled.
+    pub(super) fn is_within_depth(&self, depth: u32) -> bool {
+        self.max_depth.is_none_or(|max_depth| depth <= max_depth)
+    }
+}
+
+/// A pending crawl of a node.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub(super) struct CrawlJob {
+    // The jobs are ordered by the time they're due first.
+    pub(super) due: Instant,
+    pub(super) ip: IpAddr,
+    /// The port the node was advertised with, the default ports are tried if unknown.
+    pub(super) port: Option<u16>,
+    /// The number of hops from the seed nodes.
+    pub(super) depth: u32,
+}
+
+impl CrawlJob {
+    /// Creates a job due right away.
+    pub(super) fn new(ip: IpAddr, port: Option<u16>, depth: u32) -> Self {
+        Self {
+            due: Instant::now(),
+            ip,
+            port,
+            depth,
+        }
+    }
+
+    /// Postpones the job by the delay.
+    pub(super) fn after(mut self, delay: Duration) -> Self {
+        self.due = Instant::now() + delay;
+        self
+    }
+}
+
+/// A queue of crawl jobs ordered by the time they're due.
+pub(super) struct Scheduler {
+    pub(super) settings: CrawlSettings,
+    jobs: Mutex<BinaryHeap<Reverse<CrawlJob>>>,
+    // Wakes up a worker waiting for a job when a new one is scheduled.
+    wake: Notify,
+    // The jobs taken off the queue and not done yet, only changed with the queue locked.
+    running: AtomicUsize,
+    // Wakes up the task waiting for the queue to run out of jobs.
+    idle: Notify,
+}
+
+impl Scheduler {
+    pub(super) fn new(settings: CrawlSettings) -> Self {
+        Self {
+            settings,
+            jobs: Default::default(),
+            wake: Notify::new(),
+            running: AtomicUsize::new(0),
+            idle: Notify::new(),
+        }
+    }
+
+    /// Queues the job, unless the node is beyond the maximum depth.
+    pub(super) fn schedule(&self, job: CrawlJob) {
+        if !self.settings.is_within_depth(job.depth) {
+            return;
+        }
+
+        self.jobs.lock().unwrap().push(Reverse(job));
+        self.wake.notify_one();
+    }
+
+    /// Waits for the earliest job to become due and takes it off the queue, the worker reports
+    /// it's done with [Scheduler::done].
+    pub(super) async fn next_job(&self) -> CrawlJob {
+        loop {
+            let next_due = {
+                let mut jobs = self.jobs.lock().unwrap();
+                match jobs.peek() {
+                    Some(Reverse(job)) if job.due <= Instant::now() => {
+                        self.running.fetch_add(1, Ordering::SeqCst);
+                        return jobs.pop().unwrap().0;
+                    }
+                    Some(Reverse(job)) => Some(job.due),
+                    None => None,
+                }
+            };
+
+            // A newly scheduled job may be due before the earliest one seen.
+            match next_due {
+                Some(due) => {
+                    tokio::select! {
+                        _ = sleep_until(due) => {},
+                        _ = self.wake.notified() => {},
+                    }
+                }
+                None => self.wake.notified().await,
+            }
+        }
+    }
+    /// Marks a job taken with [Scheduler::next_job] as done, after the jobs it led to are
+    /// scheduled.
+    pub(super) fn done(&self) {
+        let jobs = self.jobs.lock().unwrap();
+        if self.running.fetch_sub(1, Ordering::SeqCst) == 1 && jobs.is_empty() {
+            self.idle.notify_one();
+        }
+    }
+
+    /// Waits until no job is queued nor running, which only happens if nodes aren't revisited.
+    pub(super) async fn until_idle(&self) {
+        loop {
+            {
+                let jobs = self.jobs.lock().unwrap();
+                if jobs.is_empty() && self.running.load(Ordering::SeqCst) == 0 {
+                    return;
+                }
+            }
+            self.idle.notified().await;
+        }
+    }
+}
@@ -17,7 +17,7 @@
         #   test-ignored = "cargo test --ignored";
         # } // ziggurat-core.scripts;
         scripts = {
-          check-crawler = "cargo check --features=crawler";
+          check-crawler = "cargo check -p crawler";
         } // ziggurat-core.scripts;
       in
       {
@@ -0,0 +1,260 @@
+//! Targeted corruption of encoded messages.
+//!
+//! A valid payload is encoded and one of its top-level protobuf fields is corrupted. The frame
+//! header is kept consistent with the corrupted body, so the node gets past framing and has to
+//! deal with the malformed protobuf itself.
+
+use bytes::BytesMut;
+use rand::prelude::{IteratorRandom, Rng, SliceRandom};
+use rand_chacha::ChaCha8Rng;
+use tokio_util::codec::Encoder;
+use tracing::Span;
+
+use super::set_body_len;
+use crate::protocol::codecs::message::{MessageCodec, Payload, HEADER_LEN_UNCOMPRESSED};
+
+// Protobuf wire types.
+const WIRE_TYPE_VARINT: u8 = 0;
+const WIRE_TYPE_FIXED64: u8 = 1;
+const WIRE_TYPE_LENGTH_DELIMITED: u8 = 2;
+const WIRE_TYPE_FIXED32: u8 = 5;
+const WIRE_TYPES: [u8; 8] = [0, 1, 2, 3, 4, 5, 6, 7];
+
+/// The longest valid varint encoding.
+const MAX_VARINT_LEN: usize = 10;
+
+/// A corruption applied to a single field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Corruption {
+    /// Removes the field, which breaks the message if the field is required.
+    RemoveField,
+    /// Cuts the message off in the middle of the field.
+    TruncateField,
+    /// Encodes the field's tag as a varint longer than protobuf allows.
+    OverlongVarint,
+    /// Changes the wire type in the field's tag.
+    WrongWireType,
+}
+
+impl Corruption {
+    /// All the corruption kinds.
+    pub const ALL: [Corruption; 4] = [
+        Corruption::RemoveField,
+        Corruption::TruncateField,
+        Corruption::OverlongVarint,
+        Corruption::WrongWireType,
+    ];
+}
+
+/// Encodes the payload and applies the corruption to a randomly picked field.
+///
+/// Returns the whole frame, ready for `unicast_bytes`, or `None` if the payload has no fields
+/// to corrupt.
+pub fn corrupt_payload(
+    rng: &mut ChaCha8Rng,
+    payload: Payload,
+    corruption: Corruption,
+) -> Option<Vec<u8>> {
+    let mut frame = BytesMut::new();
+    MessageCodec::new(Span::none())
+        .encode(payload, &mut frame)
+        .ok()?;
+
+    let body = &frame[HEADER_LEN_UNCOMPRESSED..];
+    let field = *parse_fields(body).choose(rng)?;
+
+    let mut corrupted = Vec::with_capacity(body.len() + MAX_VARINT_LEN);
+    corrupted.extend_from_slice(&body[..field.start]);
+    match corruption {
+        Corruption::RemoveField => {
+            corrupted.extend_from_slice(&body[field.end..]);
+        }
+        Corruption::TruncateField => {
+            // Keep the tag and possibly a part of the value.
+            let cut = rng.gen_range(field.value_start..field.end);
+            corrupted.extend_from_slice(&body[field.start..cut]);
+        }
+        Corruption::OverlongVarint => {
+            let tag = encode_varint(field.tag());
+            corrupted.extend_from_slice(&make_overlong(&tag));
+            corrupted.extend_from_slice(&body[field.value_start..]);
+        }
+        Corruption::WrongWireType => {
+            let wire_type = WIRE_TYPES
+                .into_iter()
+                .filter(|wire_type| *wire_type != field.wire_type)
+                .choose(rng)
+                .unwrap();
+            let tag = (field.number << 3) | wire_type as u64;
+            corrupted.extend_from_slice(&encode_varint(tag));
+            corrupted.extend_from_slice(&body[field.value_start..]);
+        }
+    }
+
+    Some(build_frame(&frame[..HEADER_LEN_UNCOMPRESSED], &corrupted))
+}
+
+/// Returns `n` frames, each with a random corruption applied to a random field of one of the
+/// payloads.
+pub fn random_corruptions(rng: &mut ChaCha8Rng, payloads: &[Payload], n: usize) -> Vec<Vec<u8>> {
+    let mut frames = Vec::with_capacity(n);
+    // Payloads without fields can't be corrupted, so cap the number of attempts.
+    for _ in 0..n * 2 {
+        if frames.len() == n {
+            break;
+        }
+
+        let Some(payload) = payloads.choose(rng) else {
+            break;
+        };
+        let corruption = *Corruption::ALL.choose(rng).unwrap();
+        if let Some(frame) = corrupt_payload(rng, payload.clone(), corruption) {
+            frames.push(frame);
+        }
+    }
+    frames
+}
+
+/// The position of a top-level field in an encoded message.
+#[derive(Debug, Clone, Copy)]
+struct Field {
+    number: u64,
+    wire_type: u8,
+    start: usize,
+    value_start: usize,
+    end: usize,
+}
+
+impl Field {
+    fn tag(&self) -> u64 {
+        (self.number << 3) | self.wire_type as u64
+    }
+}
+
+// Stops at the first field which can't be parsed, the input is expected to be valid.
+fn parse_fields(body: &[u8]) -> Vec<Field> {
+    let mut fields = Vec::new();
+    let mut pos = 0;
+
+    while pos < body.len() {
+        let start = pos;
+        let Some((tag, len)) = decode_varint(&body[pos..]) else {
+            break;
+        };
+        pos += len;
+        let value_start = pos;
+
+        let wire_type = (tag & 0x7) as u8;
+        let value_len = match wire_type {
+            WIRE_TYPE_VARINT => match decode_varint(&body[pos..]) {
+                Some((_, len)) => len,
+                None => break,
+            },
+            WIRE_TYPE_FIXED64 => 8,
+            WIRE_TYPE_LENGTH_DELIMITED => match decode_varint(&body[pos..]) {
+                Some((value_len, len)) => len + value_len as usize,
+                None => break,
+            },
+            WIRE_TYPE_FIXED32 => 4,
+            _ => break,
+        };
+        pos += value_len;
+        if pos > body.len() {
+            break;
+        }
+
+        fields.push(Field {
+            number: tag >> 3,
+            wire_type,
+            start,
+            value_start,
+            end: pos,
+        });
+    }
+
+    fields
+}
+
+fn decode_varint(buf: &[u8]) -> Option<(u64, usize)> {
+    let mut value = 0u64;
+    for (i, byte) in buf.iter().take(MAX_VARINT_LEN).enumerate() {
+        value |= ((byte & 0x7f) as u64) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Some((value, i + 1));
+        }
+    }
+    None
+}
+
+pub(super) fn encode_varint(mut value: u64) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(MAX_VARINT_LEN);
+    while value >= 0x80 {
+        buf.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8);
+    buf
+}
+
+// Pads the varint with zero-valued continuation bytes until it's longer than allowed.
+fn make_overlong(varint: &[u8]) -> Vec<u8> {
+    let mut buf = varint.to_vec();
+    let last = buf.len() - 1;
+    buf[last] |= 0x80;
+    buf.resize(MAX_VARINT_LEN, 0x80);
+    buf.push(0);
+    buf
+}
+
+fn build_frame(header: &[u8], body: &[u8]) -> Vec<u8> {
+    let mut frame = [header, body].concat();
+    set_body_len(&mut frame, body.len() as u32);
+    frame
+}
+
+#[cfg(test)]
+mod tests {
+    use prost::Message;
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::protocol::proto::{tm_ping::PingType, TmPing};
+
+    fn ping() -> Payload {
+        Payload::TmPing(TmPing {
+            r#type: PingType::PtPing as i32,
+            seq: None,
+            ping_time: None,
+            net_time: None,
+        })
+    }
+
+    #[test]
+    fn corrupted_field_fails_to_decode() {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+
+        for corruption in Corruption::ALL {
+            let frame = corrupt_payload(&mut rng, ping(), corruption).unwrap();
+            let body = &frame[HEADER_LEN_UNCOMPRESSED..];
+
+            assert_eq!(
+                u32::from_be_bytes(frame[..4].try_into().unwrap()) as usize,
+                body.len()
+            );
+            // Prost doesn't enforce required fields, so only check the field is gone.
+            if corruption == Corruption::RemoveField {
+                assert!(body.is_empty());
+            } else {
+                assert!(TmPing::decode(body).is_err(), "{corruption:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn overlong_varint_is_too_long() {
+        let overlong = make_overlong(&encode_varint(8));
+
+        assert_eq!(overlong.len(), MAX_VARINT_LEN + 1);
+        assert!(decode_varint(&overlong).is_none());
+    }
+}
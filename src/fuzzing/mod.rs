@@ -0,0 +1,82 @@
+//! Useful helper functions for fuzzing.
+//!
+//! The frame generators ([corrupt], [mutate], [oversized] and [payload]) share the seeded
+//! randomness and the header helpers defined here.
+
+pub mod corrupt;
+pub mod mutate;
+pub mod oversized;
+pub mod payload;
+pub mod runner;
+
+use std::{env, sync::OnceLock};
+
+use rand::{distributions::Standard, prelude::Rng, thread_rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::protocol::codecs::message::{HEADER_LEN_UNCOMPRESSED, UNCOMPRESSED_SIZE_MASK};
+
+/// Environment variable setting the seed of the test run, to replay a failing run.
+pub const SEED_ENV: &str = "ZIGGURAT_SEED";
+
+/// Returns the seed shared by the whole test run.
+///
+/// The seed is read from [SEED_ENV] or picked randomly, and printed the first time it's used.
+pub fn test_seed() -> u64 {
+    static SEED: OnceLock<u64> = OnceLock::new();
+
+    *SEED.get_or_init(|| {
+        let seed = match env::var(SEED_ENV) {
+            Ok(seed) => seed
+                .parse()
+                .unwrap_or_else(|_| panic!("{SEED_ENV} should be a number, got {seed}")),
+            Err(_) => thread_rng().gen(),
+        };
+
+        // We print the seed for reproducibility.
+        println!("Seed for RNG: {seed}, run with {SEED_ENV}={seed} to replay");
+        seed
+    })
+}
+
+/// Returns a `ChaCha8Rng` instance seeded with the test run's seed, useful for making tests
+/// reproducible.
+///
+/// Every call starts the same sequence, use [seeded_rng_stream] where independent sequences are
+/// needed.
+pub fn seeded_rng() -> ChaCha8Rng {
+    seeded_rng_stream(0)
+}
+
+/// Returns a `ChaCha8Rng` instance seeded with the test run's seed, producing one of the
+/// independent sequences, e.g. one for each of the peers.
+pub fn seeded_rng_stream(stream: u64) -> ChaCha8Rng {
+    // Isn't cryptographically secure but adequate enough as a general source of seeded randomness.
+    let mut rng = ChaCha8Rng::seed_from_u64(test_seed());
+    rng.set_stream(stream);
+    rng
+}
+
+/// Returns `n` random length sets of random bytes.
+pub fn random_bytes(rng: &mut ChaCha8Rng, n: usize) -> Vec<Vec<u8>> {
+    (0..n)
+        .map(|_| {
+            let random_len: usize = rng.gen_range(1..(64 * 1024));
+            let random_payload: Vec<u8> = rng.sample_iter(Standard).take(random_len).collect();
+
+            random_payload
+        })
+        .collect()
+}
+
+/// Sets the body length declared by an uncompressed frame's header.
+///
+/// The header's size field is 26 bits wide, the top bits must stay clear.
+pub(crate) fn set_body_len(frame: &mut [u8], len: u32) {
+    frame[..4].copy_from_slice(&(len & UNCOMPRESSED_SIZE_MASK).to_be_bytes());
+}
+
+/// Sets the message type declared by an uncompressed frame's header.
+pub(crate) fn set_message_type(frame: &mut [u8], message_type: u16) {
+    frame[4..HEADER_LEN_UNCOMPRESSED].copy_from_slice(&message_type.to_be_bytes());
+}
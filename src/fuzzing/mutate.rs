@@ -0,0 +1,162 @@
+//! Blind mutation of encoded messages.
+//!
+//! Unlike [corrupt](super::corrupt), the mutations don't look at the protobuf structure. Any
+//! payload is encoded and then its frame is damaged: bits of the body are flipped, the body is
+//! cut short, the header lies about the body's length or names a message type which doesn't
+//! exist. Invalid enum values are left to [payload](super::payload), which generates them
+//! within well-formed messages.
+
+use bytes::BytesMut;
+use rand::prelude::{Rng, SliceRandom};
+use rand_chacha::ChaCha8Rng;
+use tokio_util::codec::Encoder;
+use tracing::Span;
+
+use super::{set_body_len, set_message_type};
+use crate::protocol::codecs::message::{
+    MessageCodec, Payload, HEADER_LEN_UNCOMPRESSED, MESSAGE_TYPES, UNCOMPRESSED_SIZE_MASK,
+};
+
+/// The maximum number of bits flipped in a single body.
+const MAX_BIT_FLIPS: usize = 8;
+
+/// A mutation applied to an encoded frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mutation {
+    /// Flips a few random bits of the body.
+    BitFlip,
+    /// Cuts the body short, the header is adjusted to the shorter body.
+    Truncate,
+    /// Declares a longer body than sent, so the following frame is read as the rest of it.
+    OverstatedLength,
+    /// Declares a shorter body than sent, so the rest is read as the following frame's header.
+    UnderstatedLength,
+    /// Sets a message type which isn't part of the protocol.
+    UnknownMessageType,
+}
+
+impl Mutation {
+    /// All the mutation kinds.
+    pub const ALL: [Mutation; 5] = [
+        Mutation::BitFlip,
+        Mutation::Truncate,
+        Mutation::OverstatedLength,
+        Mutation::UnderstatedLength,
+        Mutation::UnknownMessageType,
+    ];
+}
+
+/// Encodes the payload and applies the mutation to its frame.
+///
+/// Returns the whole frame, ready for `unicast_bytes`, or `None` if the mutation needs a body
+/// and the payload encodes to an empty one.
+pub fn mutate_payload(
+    rng: &mut ChaCha8Rng,
+    payload: Payload,
+    mutation: Mutation,
+) -> Option<Vec<u8>> {
+    let mut frame = BytesMut::new();
+    MessageCodec::new(Span::none())
+        .encode(payload, &mut frame)
+        .ok()?;
+    let mut frame = frame.to_vec();
+    let body_len = frame.len() - HEADER_LEN_UNCOMPRESSED;
+
+    match mutation {
+        Mutation::BitFlip => {
+            if body_len == 0 {
+                return None;
+            }
+            for _ in 0..rng.gen_range(1..=MAX_BIT_FLIPS) {
+                let byte = rng.gen_range(HEADER_LEN_UNCOMPRESSED..frame.len());
+                frame[byte] ^= 1 << rng.gen_range(0..8);
+            }
+        }
+        Mutation::Truncate => {
+            if body_len == 0 {
+                return None;
+            }
+            let len = rng.gen_range(0..body_len);
+            frame.truncate(HEADER_LEN_UNCOMPRESSED + len);
+            set_body_len(&mut frame, len as u32);
+        }
+        Mutation::OverstatedLength => {
+            let len = rng.gen_range(body_len as u32 + 1..=UNCOMPRESSED_SIZE_MASK);
+            set_body_len(&mut frame, len);
+        }
+        Mutation::UnderstatedLength => {
+            if body_len == 0 {
+                return None;
+            }
+            let len = rng.gen_range(0..body_len as u32);
+            set_body_len(&mut frame, len);
+        }
+        Mutation::UnknownMessageType => {
+            let message_type = loop {
+                let message_type: u16 = rng.gen();
+                if !MESSAGE_TYPES
+                    .iter()
+                    .any(|known| *known as u16 == message_type)
+                {
+                    break message_type;
+                }
+            };
+            set_message_type(&mut frame, message_type);
+        }
+    }
+
+    Some(frame)
+}
+
+/// Returns `n` frames, each with a random mutation applied to one of the payloads.
+pub fn random_mutations(rng: &mut ChaCha8Rng, payloads: &[Payload], n: usize) -> Vec<Vec<u8>> {
+    let mut frames = Vec::with_capacity(n);
+    // Payloads with empty bodies can't be mutated in every way, so cap the number of attempts.
+    for _ in 0..n * 2 {
+        if frames.len() == n {
+            break;
+        }
+
+        let Some(payload) = payloads.choose(rng) else {
+            break;
+        };
+        let mutation = *Mutation::ALL.choose(rng).unwrap();
+        if let Some(frame) = mutate_payload(rng, payload.clone(), mutation) {
+            frames.push(frame);
+        }
+    }
+    frames
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::protocol::proto::{tm_ping::PingType, TmPing};
+
+    #[test]
+    fn header_matches_mutation() {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let ping = Payload::TmPing(TmPing {
+            r#type: PingType::PtPing as i32,
+            seq: Some(42),
+            ping_time: None,
+            net_time: None,
+        });
+
+        for mutation in Mutation::ALL {
+            let frame = mutate_payload(&mut rng, ping.clone(), mutation).unwrap();
+            let declared = u32::from_be_bytes(frame[..4].try_into().unwrap()) as usize;
+            let body_len = frame.len() - HEADER_LEN_UNCOMPRESSED;
+
+            match mutation {
+                Mutation::BitFlip | Mutation::Truncate | Mutation::UnknownMessageType => {
+                    assert_eq!(declared, body_len, "{mutation:?}")
+                }
+                Mutation::OverstatedLength => assert!(declared > body_len),
+                Mutation::UnderstatedLength => assert!(declared < body_len),
+            }
+        }
+    }
+}
@@ -0,0 +1,258 @@
+//! Frames testing the node's message size limit.
+//!
+//! rippled drops peers sending frames which declare a payload larger than
+//! [MAX_PAYLOAD_SIZE], without reading the payload. Such frames can only be declared with the
+//! compressed header, as the uncompressed header's size field is too narrow. Frames just under
+//! the limit are padded with an unknown protobuf field, so they still decode to a valid message.
+//!
+//! [FrameHeader] declares arbitrary sizes regardless of the body following it, which the codec
+//! can't produce.
+
+use bytes::BytesMut;
+use tokio_util::codec::Encoder;
+use tracing::Span;
+
+use super::{corrupt::encode_varint, set_body_len};
+pub use crate::protocol::codecs::message::MAX_PAYLOAD_SIZE;
+use crate::protocol::{
+    codecs::message::{
+        MessageCodec, Payload, COMPRESSED_SIZE_MASK, COMPRESSION_LZ4, HEADER_LEN_COMPRESSED,
+        HEADER_LEN_UNCOMPRESSED, UNCOMPRESSED_SIZE_MASK,
+    },
+    proto::MessageType,
+};
+
+/// The field number used for padding, the highest one protobuf allows, so it's unknown to every
+/// message type.
+const PADDING_FIELD: u64 = (1 << 29) - 1;
+const WIRE_TYPE_LENGTH_DELIMITED: u64 = 2;
+
+/// The longest length prefix of a padding field.
+const MAX_PREFIX_LEN: usize = 5;
+
+/// The size a frame declares above the limit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Oversize {
+    /// The payload's wire size is over the limit.
+    WireSize,
+    /// The payload's wire size is small, but its uncompressed size is over the limit.
+    UncompressedSize,
+}
+
+impl Oversize {
+    /// All the ways to declare an oversized payload.
+    pub const ALL: [Oversize; 2] = [Oversize::WireSize, Oversize::UncompressedSize];
+}
+
+/// A frame header declaring any sizes, whatever the body following it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameHeader {
+    /// The 6-byte header, the size is cut to the 26 bits of its field.
+    Uncompressed { message_type: u16, size: u32 },
+    /// The 10-byte LZ4 header, the wire size is cut to the 28 bits of its field.
+    Compressed {
+        message_type: u16,
+        wire_size: u32,
+        uncompressed_size: u32,
+    },
+}
+
+impl FrameHeader {
+    pub fn encode(&self) -> Vec<u8> {
+        match *self {
+            Self::Uncompressed { message_type, size } => {
+                let mut header = vec![0; HEADER_LEN_UNCOMPRESSED];
+                set_body_len(&mut header, size);
+                header[4..].copy_from_slice(&message_type.to_be_bytes());
+                header
+            }
+            Self::Compressed {
+                message_type,
+                wire_size,
+                uncompressed_size,
+            } => {
+                let mut header = Vec::with_capacity(HEADER_LEN_COMPRESSED);
+                header.extend_from_slice(&(wire_size & COMPRESSED_SIZE_MASK).to_be_bytes());
+                header[0] |= COMPRESSION_LZ4;
+                header.extend_from_slice(&message_type.to_be_bytes());
+                header.extend_from_slice(&uncompressed_size.to_be_bytes());
+                header
+            }
+        }
+    }
+
+    /// Returns the header followed by the body, ready for `unicast_bytes`.
+    pub fn frame(&self, body: &[u8]) -> Vec<u8> {
+        let mut frame = self.encode();
+        frame.extend_from_slice(body);
+        frame
+    }
+}
+
+/// Returns a frame of the message type declaring a payload over the limit, followed by the body.
+///
+/// The node is expected to drop the connection after reading the header, so the body can be
+/// much shorter than declared.
+pub fn oversized_frame(message_type: MessageType, oversize: Oversize, body: &[u8]) -> Vec<u8> {
+    let (wire_size, uncompressed_size) = match oversize {
+        Oversize::WireSize => (MAX_PAYLOAD_SIZE + 1, MAX_PAYLOAD_SIZE + 1),
+        Oversize::UncompressedSize => (body.len() as u32, MAX_PAYLOAD_SIZE + 1),
+    };
+
+    FrameHeader::Compressed {
+        message_type: message_type as u16,
+        wire_size,
+        uncompressed_size,
+    }
+    .frame(body)
+}
+
+/// Returns an LZ4 frame of the payload, declaring the uncompressed size instead of the actual
+/// one, so decompression fails or comes up short.
+pub fn mismatched_lz4_frame(payload: Payload, uncompressed_size: u32) -> Option<Vec<u8>> {
+    let mut encoded = BytesMut::new();
+    MessageCodec::new(Span::none())
+        .encode(payload, &mut encoded)
+        .ok()?;
+    let message_type = u16::from_be_bytes([encoded[4], encoded[5]]);
+    let compressed = lz4_flex::block::compress(&encoded[HEADER_LEN_UNCOMPRESSED..]);
+
+    let header = FrameHeader::Compressed {
+        message_type,
+        wire_size: compressed.len() as u32,
+        uncompressed_size,
+    };
+    Some(header.frame(&compressed))
+}
+
+/// Encodes the payload into a frame with a body of exactly `size` bytes, padding it with an
+/// unknown field.
+///
+/// Returns `None` if the payload is already larger or the size can't be declared by the
+/// uncompressed header.
+pub fn padded_frame(payload: Payload, size: u32) -> Option<Vec<u8>> {
+    if size > UNCOMPRESSED_SIZE_MASK {
+        return None;
+    }
+
+    let mut encoded = BytesMut::new();
+    MessageCodec::new(Span::none())
+        .encode(payload, &mut encoded)
+        .ok()?;
+    let mut missing = (size as usize).checked_sub(encoded.len() - HEADER_LEN_UNCOMPRESSED)?;
+
+    let mut frame = encoded.to_vec();
+    while missing > 0 {
+        let field = padding_field(missing)?;
+        missing -= field.len();
+        frame.extend_from_slice(&field);
+    }
+    set_body_len(&mut frame, size);
+    Some(frame)
+}
+
+/// Returns a frame of the payload with a body just under the limit, the largest the uncompressed
+/// header can declare.
+pub fn just_under_limit_frame(payload: Payload) -> Option<Vec<u8>> {
+    padded_frame(payload, UNCOMPRESSED_SIZE_MASK)
+}
+
+// Returns a padding field of exactly `len` bytes if possible. As the length prefix takes more
+// bytes for longer values, some lengths can't be hit with a single field, an empty field is
+// returned instead so the next one can make up the rest.
+fn padding_field(len: usize) -> Option<Vec<u8>> {
+    let mut field = encode_varint((PADDING_FIELD << 3) | WIRE_TYPE_LENGTH_DELIMITED);
+    let empty_len = field.len() + 1;
+
+    let value_len = (1..=MAX_PREFIX_LEN)
+        .filter_map(|prefix_len| len.checked_sub(field.len() + prefix_len))
+        .find(|value_len| field.len() + encode_varint(*value_len as u64).len() + value_len == len);
+    let value_len = match value_len {
+        Some(value_len) => value_len,
+        // Leave enough room for another field.
+        None if len >= 2 * empty_len => 0,
+        None => return None,
+    };
+
+    field.extend_from_slice(&encode_varint(value_len as u64));
+    field.resize(field.len() + value_len, 0);
+    Some(field)
+}
+
+#[cfg(test)]
+mod tests {
+    use prost::Message;
+
+    use super::*;
+    use crate::protocol::proto::{tm_ping::PingType, TmPing};
+
+    #[test]
+    fn padded_frame_has_exact_size_and_decodes() {
+        let ping = TmPing {
+            r#type: PingType::PtPing as i32,
+            seq: Some(7),
+            ping_time: None,
+            net_time: None,
+        };
+
+        for size in [20, 133, 140, 16_390, 100_000] {
+            let frame = padded_frame(Payload::TmPing(ping.clone()), size).unwrap();
+            let body = &frame[HEADER_LEN_UNCOMPRESSED..];
+
+            assert_eq!(body.len(), size as usize);
+            assert_eq!(u32::from_be_bytes(frame[..4].try_into().unwrap()), size);
+            assert_eq!(TmPing::decode(body).unwrap(), ping);
+        }
+        assert!(padded_frame(Payload::TmPing(ping), UNCOMPRESSED_SIZE_MASK + 1).is_none());
+    }
+
+    #[test]
+    fn oversized_frame_declares_sizes_over_the_limit() {
+        let frame = oversized_frame(MessageType::MtPing, Oversize::UncompressedSize, &[0; 4]);
+
+        assert_eq!(frame[0] & 0xf0, COMPRESSION_LZ4);
+        assert_eq!(
+            u32::from_be_bytes(frame[..4].try_into().unwrap()) & COMPRESSED_SIZE_MASK,
+            4
+        );
+        assert_eq!(u16::from_be_bytes(frame[4..6].try_into().unwrap()), 3);
+        assert_eq!(
+            u32::from_be_bytes(frame[6..10].try_into().unwrap()),
+            MAX_PAYLOAD_SIZE + 1
+        );
+    }
+
+    #[test]
+    fn headers_declare_the_sizes() {
+        let header = FrameHeader::Uncompressed {
+            message_type: 3,
+            size: UNCOMPRESSED_SIZE_MASK,
+        };
+        assert_eq!(header.encode(), [0x03, 0xff, 0xff, 0xff, 0x00, 0x03]);
+
+        let header = FrameHeader::Compressed {
+            message_type: 3,
+            wire_size: COMPRESSED_SIZE_MASK,
+            uncompressed_size: 1,
+        };
+        assert_eq!(
+            header.frame(&[7]),
+            [0x9f, 0xff, 0xff, 0xff, 0, 3, 0, 0, 0, 1, 7]
+        );
+
+        let ping = TmPing {
+            r#type: PingType::PtPing as i32,
+            seq: Some(7),
+            ping_time: None,
+            net_time: None,
+        };
+        let frame = mismatched_lz4_frame(Payload::TmPing(ping.clone()), 1).unwrap();
+        let wire_size = u32::from_be_bytes(frame[..4].try_into().unwrap()) & COMPRESSED_SIZE_MASK;
+        assert_eq!(wire_size as usize, frame.len() - HEADER_LEN_COMPRESSED);
+        assert_eq!(
+            lz4_flex::block::decompress(&frame[HEADER_LEN_COMPRESSED..], ping.encoded_len())
+                .unwrap(),
+            ping.encode_to_vec()
+        );
+    }
+}
@@ -0,0 +1,315 @@
+//! Structure-aware generation of peer messages.
+//!
+//! The generated payloads always encode to valid protobuf for their message type, but their
+//! fields are filled with values the node is unlikely to expect: boundary integers, invalid enum
+//! values, empty and oversized byte fields and long repeated fields.
+
+use rand::{distributions::Standard, prelude::Rng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::protocol::{
+    codecs::message::Payload,
+    proto::{tm_endpoints::TmEndpointv2, tm_peer_shard_info_v2::TmIncomplete, *},
+};
+
+/// The maximum number of elements in a generated repeated field.
+const MAX_REPEATED: usize = 16;
+
+/// The maximum length of a generated byte or string field.
+const MAX_BYTES_LEN: usize = 4 * 1024;
+
+/// The number of [Payload] variants that can be generated.
+const PAYLOAD_VARIANTS: usize = 23;
+
+/// Returns `n` random payloads, each of a randomly picked message type.
+pub fn random_payloads(rng: &mut ChaCha8Rng, n: usize) -> Vec<Payload> {
+    (0..n).map(|_| random_payload(rng)).collect()
+}
+
+/// Returns a payload of a randomly picked message type with weird field values.
+#[allow(deprecated)] // Deprecated fields are still decoded by the node.
+pub fn random_payload(rng: &mut ChaCha8Rng) -> Payload {
+    let mut gen = FieldGenerator { rng };
+
+    match gen.rng.gen_range(0..PAYLOAD_VARIANTS) {
+        0 => Payload::TmManifests(TmManifests {
+            list: gen.repeated(|gen| TmManifest {
+                stobject: gen.bytes(),
+            }),
+            history: gen.optional(|gen| gen.rng.gen()),
+        }),
+        1 => Payload::TmPing(TmPing {
+            r#type: gen.enumeration(),
+            seq: gen.optional(|gen| gen.u32()),
+            ping_time: gen.optional(|gen| gen.u64()),
+            net_time: gen.optional(|gen| gen.u64()),
+        }),
+        2 => Payload::TmCluster(TmCluster {
+            cluster_nodes: gen.repeated(|gen| TmClusterNode {
+                public_key: gen.string(),
+                report_time: gen.u32(),
+                node_load: gen.u32(),
+                node_name: gen.optional(|gen| gen.string()),
+                address: gen.optional(|gen| gen.string()),
+            }),
+            load_sources: gen.repeated(|gen| TmLoadSource {
+                name: gen.string(),
+                cost: gen.u32(),
+                count: gen.optional(|gen| gen.u32()),
+            }),
+        }),
+        3 => Payload::TmEndpoints(TmEndpoints {
+            version: gen.u32(),
+            endpoints_v2: gen.repeated(|gen| TmEndpointv2 {
+                endpoint: gen.string(),
+                hops: gen.u32(),
+            }),
+        }),
+        4 => Payload::TmTransaction(gen.transaction()),
+        5 => Payload::TmGetLedger(TmGetLedger {
+            itype: gen.enumeration(),
+            ltype: gen.optional(|gen| gen.enumeration()),
+            ledger_hash: gen.optional(|gen| gen.bytes()),
+            ledger_seq: gen.optional(|gen| gen.u32()),
+            node_i_ds: gen.repeated(|gen| gen.bytes()),
+            request_cookie: gen.optional(|gen| gen.u64()),
+            query_type: gen.optional(|gen| gen.enumeration()),
+            query_depth: gen.optional(|gen| gen.u32()),
+        }),
+        6 => Payload::TmLedgerData(TmLedgerData {
+            ledger_hash: gen.bytes(),
+            ledger_seq: gen.u32(),
+            r#type: gen.enumeration(),
+            nodes: gen.repeated(|gen| TmLedgerNode {
+                nodedata: gen.bytes(),
+                nodeid: gen.optional(|gen| gen.bytes()),
+            }),
+            request_cookie: gen.optional(|gen| gen.u32()),
+            error: gen.optional(|gen| gen.enumeration()),
+        }),
+        7 => Payload::TmProposeLedger(TmProposeSet {
+            propose_seq: gen.u32(),
+            current_tx_hash: gen.bytes(),
+            node_pub_key: gen.bytes(),
+            close_time: gen.u32(),
+            signature: gen.bytes(),
+            previousledger: gen.bytes(),
+            added_transactions: gen.repeated(|gen| gen.bytes()),
+            removed_transactions: gen.repeated(|gen| gen.bytes()),
+            checked_signature: gen.optional(|gen| gen.rng.gen()),
+            hops: gen.optional(|gen| gen.u32()),
+        }),
+        8 => Payload::TmStatusChange(TmStatusChange {
+            new_status: gen.optional(|gen| gen.enumeration()),
+            new_event: gen.optional(|gen| gen.enumeration()),
+            ledger_seq: gen.optional(|gen| gen.u32()),
+            ledger_hash: gen.optional(|gen| gen.bytes()),
+            ledger_hash_previous: gen.optional(|gen| gen.bytes()),
+            network_time: gen.optional(|gen| gen.u64()),
+            first_seq: gen.optional(|gen| gen.u32()),
+            last_seq: gen.optional(|gen| gen.u32()),
+        }),
+        9 => Payload::TmHaveTransactions(TmHaveTransactions {
+            hashes: gen.repeated(|gen| gen.bytes()),
+        }),
+        10 => Payload::TmHaveSet(TmHaveTransactionSet {
+            status: gen.enumeration(),
+            hash: gen.bytes(),
+        }),
+        11 => Payload::TmValidation(TmValidation {
+            validation: gen.bytes(),
+            checked_signature: gen.optional(|gen| gen.rng.gen()),
+            hops: gen.optional(|gen| gen.u32()),
+        }),
+        12 => Payload::TmGetObjectByHash(TmGetObjectByHash {
+            r#type: gen.enumeration(),
+            query: gen.rng.gen(),
+            seq: gen.optional(|gen| gen.u32()),
+            ledger_hash: gen.optional(|gen| gen.bytes()),
+            fat: gen.optional(|gen| gen.rng.gen()),
+            objects: gen.repeated(|gen| TmIndexedObject {
+                hash: gen.optional(|gen| gen.bytes()),
+                node_id: gen.optional(|gen| gen.bytes()),
+                index: gen.optional(|gen| gen.bytes()),
+                data: gen.optional(|gen| gen.bytes()),
+                ledger_seq: gen.optional(|gen| gen.u32()),
+            }),
+        }),
+        13 => Payload::TmValidatorList(TmValidatorList {
+            manifest: gen.bytes(),
+            blob: gen.bytes(),
+            signature: gen.bytes(),
+            version: gen.u32(),
+        }),
+        14 => Payload::TmSquelch(TmSquelch {
+            squelch: gen.rng.gen(),
+            validator_pub_key: gen.bytes(),
+            squelch_duration: gen.optional(|gen| gen.u32()),
+        }),
+        15 => Payload::TmValidatorListCollection(TmValidatorListCollection {
+            version: gen.u32(),
+            manifest: gen.bytes(),
+            blobs: gen.repeated(|gen| ValidatorBlobInfo {
+                manifest: gen.optional(|gen| gen.bytes()),
+                blob: gen.bytes(),
+                signature: gen.bytes(),
+            }),
+        }),
+        16 => Payload::TmProofPathRequest(TmProofPathRequest {
+            key: gen.bytes(),
+            ledger_hash: gen.bytes(),
+            r#type: gen.enumeration(),
+        }),
+        17 => Payload::TmProofPathResponse(TmProofPathResponse {
+            key: gen.bytes(),
+            ledger_hash: gen.bytes(),
+            r#type: gen.enumeration(),
+            ledger_header: gen.optional(|gen| gen.bytes()),
+            path: gen.repeated(|gen| gen.bytes()),
+            error: gen.optional(|gen| gen.enumeration()),
+        }),
+        18 => Payload::TmReplayDeltaRequest(TmReplayDeltaRequest {
+            ledger_hash: gen.bytes(),
+        }),
+        19 => Payload::TmReplayDeltaResponse(TmReplayDeltaResponse {
+            ledger_hash: gen.bytes(),
+            ledger_header: gen.optional(|gen| gen.bytes()),
+            transaction: gen.repeated(|gen| gen.bytes()),
+            error: gen.optional(|gen| gen.enumeration()),
+        }),
+        20 => Payload::TmGetPeerShardInfoV2(TmGetPeerShardInfoV2 {
+            peer_chain: gen.repeated(|gen| TmPublicKey {
+                public_key: gen.bytes(),
+            }),
+            relays: gen.u32(),
+        }),
+        21 => Payload::TmPeerShardInfoV2(TmPeerShardInfoV2 {
+            timestamp: gen.u32(),
+            incomplete: gen.repeated(|gen| TmIncomplete {
+                shard_index: gen.u32(),
+                state: gen.u32(),
+                progress: gen.optional(|gen| gen.u32()),
+            }),
+            finalized: gen.optional(|gen| gen.string()),
+            public_key: gen.bytes(),
+            signature: gen.bytes(),
+            peer_chain: gen.repeated(|gen| TmPublicKey {
+                public_key: gen.bytes(),
+            }),
+        }),
+        22 => Payload::TmTransactions(TmTransactions {
+            transactions: gen.repeated(|gen| gen.transaction()),
+        }),
+        _ => unreachable!(),
+    }
+}
+
+/// Generates field values, favouring edge cases over uniformly random values.
+struct FieldGenerator<'a> {
+    rng: &'a mut ChaCha8Rng,
+}
+
+impl FieldGenerator<'_> {
+    fn u32(&mut self) -> u32 {
+        match self.rng.gen_range(0..4) {
+            0 => *[0, 1, u32::MAX, u32::MAX - 1, i32::MAX as u32]
+                .get(self.rng.gen_range(0..5))
+                .unwrap(),
+            1 => self.rng.gen_range(0..256),
+            _ => self.rng.gen(),
+        }
+    }
+
+    fn u64(&mut self) -> u64 {
+        match self.rng.gen_range(0..4) {
+            0 => *[0, 1, u64::MAX, u64::MAX - 1, i64::MAX as u64]
+                .get(self.rng.gen_range(0..5))
+                .unwrap(),
+            1 => self.rng.gen_range(0..256),
+            _ => self.rng.gen(),
+        }
+    }
+
+    // Enumerations are encoded as plain integers, so out-of-range values can be sent as well.
+    fn enumeration(&mut self) -> i32 {
+        match self.rng.gen_range(0..4) {
+            0 => *[-1, i32::MIN, i32::MAX]
+                .get(self.rng.gen_range(0..3))
+                .unwrap(),
+            1 => self.rng.gen(),
+            _ => self.rng.gen_range(0..8),
+        }
+    }
+
+    fn bytes(&mut self) -> Vec<u8> {
+        let len = match self.rng.gen_range(0..4) {
+            0 => 0,
+            // Common sizes of hashes, keys and signatures.
+            1 => *[20, 32, 33, 64, 72].get(self.rng.gen_range(0..5)).unwrap(),
+            2 => self.rng.gen_range(1..MAX_BYTES_LEN),
+            _ => self.rng.gen_range(1..64),
+        };
+
+        self.rng.sample_iter(Standard).take(len).collect()
+    }
+
+    fn string(&mut self) -> String {
+        let len = self.rng.gen_range(0..64);
+        match self.rng.gen_range(0..3) {
+            0 => String::new(),
+            1 => "9".repeat(self.rng.gen_range(1..MAX_BYTES_LEN)),
+            _ => self
+                .rng
+                .sample_iter::<char, _>(Standard)
+                .take(len)
+                .collect(),
+        }
+    }
+
+    fn optional<T>(&mut self, generate: impl FnOnce(&mut Self) -> T) -> Option<T> {
+        if self.rng.gen() {
+            Some(generate(self))
+        } else {
+            None
+        }
+    }
+
+    fn repeated<T>(&mut self, mut generate: impl FnMut(&mut Self) -> T) -> Vec<T> {
+        let len = self.rng.gen_range(0..=MAX_REPEATED);
+        (0..len).map(|_| generate(self)).collect()
+    }
+
+    fn transaction(&mut self) -> TmTransaction {
+        TmTransaction {
+            raw_transaction: self.bytes(),
+            status: self.enumeration(),
+            receive_timestamp: self.optional(|gen| gen.u64()),
+            deferred: self.optional(|gen| gen.rng.gen()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::BytesMut;
+    use rand::SeedableRng;
+    use tokio_util::codec::{Decoder, Encoder};
+    use tracing::Span;
+
+    use super::*;
+    use crate::protocol::codecs::message::MessageCodec;
+
+    #[test]
+    fn generated_payloads_can_be_decoded() {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let mut codec = MessageCodec::new(Span::none());
+
+        for payload in random_payloads(&mut rng, 200) {
+            let mut buf = BytesMut::new();
+            codec.encode(payload, &mut buf).unwrap();
+
+            assert!(codec.decode(&mut buf).unwrap().is_some());
+            assert!(buf.is_empty());
+        }
+    }
+}
@@ -0,0 +1,138 @@
+//! Streams fuzzed messages at a running node while monitoring its health.
+
+use std::{fmt, fs, time::Duration};
+
+use crate::{
+    protocol::{codecs::message::Payload, writing::MessageOrBytes},
+    setup::node::Node,
+    tools::{config::SynthNodeCfg, synth_node::SyntheticNode},
+};
+
+/// Time to wait after each message, giving the node a chance to process it or to disconnect.
+const SEND_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Severities of rippled's log lines which are reported.
+const LOG_ERROR_SEVERITIES: [&str; 2] = [":ERR ", ":FTL "];
+
+/// The outcome of a fuzzing run.
+#[derive(Debug, Default)]
+pub struct FuzzReport {
+    /// The number of messages sent to the node.
+    pub sent: usize,
+    /// The number of times the node dropped the connection.
+    pub disconnects: usize,
+    /// Whether the node refused to connect again after dropping the connection.
+    pub reconnect_refused: bool,
+    /// Whether the node's process exited during the run.
+    pub crashed: bool,
+    /// The error and fatal lines from the node's log.
+    pub log_errors: Vec<String>,
+}
+
+impl fmt::Display for FuzzReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "sent: {}", self.sent)?;
+        writeln!(f, "disconnects: {}", self.disconnects)?;
+        writeln!(f, "reconnect refused: {}", self.reconnect_refused)?;
+        writeln!(f, "crashed: {}", self.crashed)?;
+        writeln!(f, "log errors: {}", self.log_errors.len())?;
+        for line in &self.log_errors {
+            writeln!(f, "  {line}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Sends the payloads to the node one by one from a synthetic node.
+///
+/// A new synthetic node is connected whenever the node drops the connection. The run stops
+/// early if the node crashes or refuses to connect again.
+pub async fn stream_payloads(
+    node: &mut Node,
+    payloads: Vec<Payload>,
+    cfg: &SynthNodeCfg,
+) -> FuzzReport {
+    let messages = payloads.into_iter().map(MessageOrBytes::Payload).collect();
+    stream_messages(node, messages, cfg).await
+}
+
+/// Sends the raw frames to the node one by one from a synthetic node, the same way as
+/// [stream_payloads].
+pub async fn stream_frames(
+    node: &mut Node,
+    frames: Vec<Vec<u8>>,
+    cfg: &SynthNodeCfg,
+) -> FuzzReport {
+    let messages = frames.into_iter().map(MessageOrBytes::Bytes).collect();
+    stream_messages(node, messages, cfg).await
+}
+
+async fn stream_messages(
+    node: &mut Node,
+    messages: Vec<MessageOrBytes>,
+    cfg: &SynthNodeCfg,
+) -> FuzzReport {
+    let mut report = FuzzReport::default();
+    let mut synth_node: Option<SyntheticNode> = None;
+
+    for message in messages {
+        if !node.is_running() {
+            report.crashed = true;
+            break;
+        }
+
+        let connected = match &synth_node {
+            Some(synth_node) => synth_node.is_connected(node.addr()),
+            None => false,
+        };
+        if !connected {
+            if let Some(synth_node) = synth_node.take() {
+                report.disconnects += 1;
+                synth_node.shut_down().await;
+            }
+
+            let new_synth_node = SyntheticNode::new(cfg).await;
+            if new_synth_node.connect(node.addr()).await.is_err() {
+                report.reconnect_refused = true;
+                new_synth_node.shut_down().await;
+                break;
+            }
+            synth_node = Some(new_synth_node);
+        }
+
+        let current = synth_node.as_mut().unwrap();
+        let sent = match message {
+            MessageOrBytes::Payload(payload) => current.unicast(node.addr(), payload),
+            MessageOrBytes::Bytes(bytes) => current.unicast_bytes(node.addr(), bytes),
+        };
+        if sent.is_ok() {
+            report.sent += 1;
+        }
+
+        // Drain the node's replies so the inbound queue never fills up.
+        while current.recv_message_timeout(SEND_INTERVAL).await.is_ok() {}
+    }
+
+    if let Some(synth_node) = synth_node {
+        synth_node.shut_down().await;
+    }
+
+    report.crashed |= !node.is_running();
+    report.log_errors = read_log_errors(node);
+    report
+}
+
+fn read_log_errors(node: &Node) -> Vec<String> {
+    let Ok(log) = fs::read_to_string(node.log_path()) else {
+        return Vec::new();
+    };
+
+    log.lines()
+        .filter(|line| {
+            LOG_ERROR_SEVERITIES
+                .iter()
+                .any(|severity| line.contains(severity))
+        })
+        .map(String::from)
+        .collect()
+}
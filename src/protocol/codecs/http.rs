@@ -11,6 +11,28 @@ pub enum HttpMsg {
     Response,
 }
 
+// A decoded HTTP message.
+pub struct HttpMessage {
+    // The status code, in case of a response.
+    pub status: Option<u16>,
+    // The reason phrase, in case of a response.
+    pub reason: Option<String>,
+    // The header fields' names and values, in the order received.
+    pub headers: Vec<(String, String)>,
+    // Any bytes following the headers.
+    pub body: BytesMut,
+}
+
+impl HttpMessage {
+    // Returns the value of the first header with the name, which is matched case-insensitively.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
 // A codec used to handle HTTP messages.
 pub struct HttpCodec {
     // The underlying codec.
@@ -32,28 +54,31 @@ impl HttpCodec {
 }
 
 impl Decoder for HttpCodec {
-    type Item = BytesMut;
+    type Item = HttpMessage;
     type Error = io::Error;
 
     fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
-        let mut raw_bytes = if let Some(bytes) = self.codec.decode(src)? {
-            bytes
-        } else {
+        if src.is_empty() {
             return Ok(None);
-        };
+        }
 
-        trace!(parent: &self.span, "got some raw bytes: {:?}", raw_bytes);
+        trace!(parent: &self.span, "got some raw bytes: {:?}", src);
 
         let mut headers = [httparse::EMPTY_HEADER; 16];
 
+        let mut status = None;
+        let mut reason = None;
         let res = match self.expecting {
             HttpMsg::Request => {
                 let mut req = httparse::Request::new(&mut headers);
-                req.parse(&raw_bytes)
+                req.parse(src)
             }
             HttpMsg::Response => {
                 let mut resp = httparse::Response::new(&mut headers);
-                resp.parse(&raw_bytes)
+                let res = resp.parse(src);
+                status = resp.code;
+                reason = resp.reason.map(str::to_owned);
+                res
             }
         }
         .map_err(|e| {
@@ -63,14 +88,47 @@ impl Decoder for HttpCodec {
 
         match res {
             httparse::Status::Partial => {
-                // TODO: check if openssl ensures the completeness of requests
-                warn!(parent: &self.span, "unexpected partial HTTP response");
+                trace!(parent: &self.span, "partial HTTP message, waiting for more bytes");
                 Ok(None)
             }
             httparse::Status::Complete(header_length) => {
+                let headers = headers
+                    .iter()
+                    .take_while(|header| *header != &httparse::EMPTY_HEADER)
+                    .map(|header| {
+                        (
+                            header.name.to_owned(),
+                            String::from_utf8_lossy(header.value).into_owned(),
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                let message = HttpMessage {
+                    status,
+                    reason,
+                    headers,
+                    body: BytesMut::new(),
+                };
+
+                // Without a 'Content-Length' field, the body is whatever follows the headers.
+                let body_length = match message.header("Content-Length") {
+                    Some(length) => length.trim().parse().map_err(|_| {
+                        error!(parent: &self.span, "invalid Content-Length: {length}");
+                        io::ErrorKind::InvalidData
+                    })?,
+                    None => src.len() - header_length,
+                };
+                if src.len() < header_length + body_length {
+                    trace!(parent: &self.span, "partial HTTP body, waiting for more bytes");
+                    return Ok(None);
+                }
+
+                let mut raw_bytes = src.split_to(header_length + body_length);
                 raw_bytes.advance(header_length);
 
-                Ok(Some(raw_bytes))
+                Ok(Some(HttpMessage {
+                    body: raw_bytes,
+                    ..message
+                }))
             }
         }
     }
@@ -83,3 +141,30 @@ impl Encoder<Bytes> for HttpCodec {
         self.codec.encode(message, dst)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn response_body_is_awaited() {
+        let mut codec = HttpCodec::new(Span::none(), HttpMsg::Response);
+        let response = b"HTTP/1.1 503 Service Unavailable\r\n\
+            Content-Type: application/json\r\n\
+            Content-Length: 18\r\n\
+            \r\n\
+            {\"peer-ips\": [ ]}\n";
+
+        let mut src = BytesMut::from(&response[..response.len() - 5]);
+        assert!(codec.decode(&mut src).unwrap().is_none());
+        src.extend_from_slice(&response[response.len() - 5..]);
+        src.extend_from_slice(b"trailing");
+
+        let message = codec.decode(&mut src).unwrap().unwrap();
+        assert_eq!(message.status, Some(503));
+        assert_eq!(message.reason.as_deref(), Some("Service Unavailable"));
+        assert_eq!(message.header("content-type"), Some("application/json"));
+        assert_eq!(&message.body[..], b"{\"peer-ips\": [ ]}\n");
+        assert_eq!(&src[..], b"trailing");
+    }
+}
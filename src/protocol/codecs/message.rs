@@ -1,4 +1,4 @@
-use std::io;
+use std::{collections::HashSet, io};
 
 use bytes::{Buf, BufMut, BytesMut};
 use prost::Message;
@@ -7,13 +7,22 @@ use tracing::*;
 
 use crate::protocol::proto::*;
 
-const HEADER_LEN_COMPRESSED: u32 = 10;
+/// Length of the compressed frame header.
+pub(crate) const HEADER_LEN_COMPRESSED: usize = 10;
 
-const HEADER_LEN_UNCOMPRESSED: u32 = 6;
+/// Length of the uncompressed frame header.
+pub const HEADER_LEN_UNCOMPRESSED: usize = 6;
+
+/// The payload size field of the uncompressed frame header, the top six bits must be clear.
+///
+/// A compressed header with the protocol error bits set has a payload size field as wide, the
+/// error bits take its top two bits.
+pub const UNCOMPRESSED_SIZE_MASK: u32 = 0x03ffffff;
 
 const COMPRESSION_ALGO: u8 = 0xf0;
 
-const COMPRESSION_LZ4: u8 = 0x90;
+/// The compressed header flags of the LZ4 algorithm, with the compressed bit set.
+pub(crate) const COMPRESSION_LZ4: u8 = 0x90;
 
 const COMPRESSED_TRUE: u8 = 0x80;
 
@@ -21,26 +30,99 @@ const COMPRESSED_FALSE: u8 = 0xfc;
 
 const PROTOCOL_ERROR: u8 = 0x0c;
 
+// The bits of a compressed header's first byte which aren't part of the payload size.
+const HEADER_FLAGS: u8 = 0xfc;
+
+/// The payload size field of the compressed header, the top four bits are the compression
+/// flags.
+pub(crate) const COMPRESSED_SIZE_MASK: u32 = 0x0fffffff;
+
+/// The largest payload accepted by rippled, `maximiumMessageSize` in ripple/overlay/Message.h.
+pub const MAX_PAYLOAD_SIZE: u32 = 64 * 1024 * 1024;
+
+/// The smallest payload rippled compresses, smaller ones rarely get any shorter.
+pub const LZ4_DEFAULT_THRESHOLD: u32 = 70;
+
 #[derive(Debug)]
 enum Compression {
     None,
     LZ4,
+    // The protocol error bits are set, along with the rest of the header's flags.
+    ProtocolError(u8),
+}
+
+/// LZ4 compression of outbound messages.
+///
+/// rippled negotiates compression with `compr=lz4` in the `X-Protocol-Ctl` handshake field, but
+/// the codec compresses regardless, so it's up to the test whether to advertise it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Lz4Compression {
+    /// Payloads of at least this many bytes are compressed, if it makes them shorter.
+    pub threshold: u32,
+}
+
+impl Default for Lz4Compression {
+    fn default() -> Self {
+        Self {
+            threshold: LZ4_DEFAULT_THRESHOLD,
+        }
+    }
 }
 
 #[derive(Debug)]
 pub struct Header {
-    #[allow(dead_code)]
     total_wire_size: u32,
     #[allow(dead_code)]
     header_size: u32,
     payload_wire_size: u32,
-    #[allow(dead_code)]
     uncompressed_size: u32,
     message_type: u16,
-    #[allow(dead_code)]
     compression: Compression,
 }
 
+impl Header {
+    /// Returns the message type, as sent.
+    pub fn message_type(&self) -> u16 {
+        self.message_type
+    }
+
+    /// Returns the size of the message on the wire, header included.
+    pub fn total_wire_size(&self) -> u32 {
+        self.total_wire_size
+    }
+}
+
+/// Every message type of the peer protocol.
+pub const MESSAGE_TYPES: [MessageType; 27] = [
+    MessageType::MtManifests,
+    MessageType::MtPing,
+    MessageType::MtCluster,
+    MessageType::MtEndpoints,
+    MessageType::MtTransaction,
+    MessageType::MtGetLedger,
+    MessageType::MtLedgerData,
+    MessageType::MtProposeLedger,
+    MessageType::MtStatusChange,
+    MessageType::MtHaveSet,
+    MessageType::MtValidation,
+    MessageType::MtGetObjects,
+    MessageType::MtGetShardInfo,
+    MessageType::MtShardInfo,
+    MessageType::MtGetPeerShardInfo,
+    MessageType::MtPeerShardInfo,
+    MessageType::MtValidatorlist,
+    MessageType::MtSquelch,
+    MessageType::MtValidatorlistcollection,
+    MessageType::MtProofPathReq,
+    MessageType::MtProofPathResponse,
+    MessageType::MtReplayDeltaReq,
+    MessageType::MtReplayDeltaResponse,
+    MessageType::MtGetPeerShardInfoV2,
+    MessageType::MtPeerShardInfoV2,
+    MessageType::MtHaveTransactions,
+    MessageType::MtTransactions,
+];
+
 #[derive(Debug, Clone)]
 #[non_exhaustive]
 pub enum Payload {
@@ -67,6 +149,55 @@ pub enum Payload {
     TmGetPeerShardInfoV2(TmGetPeerShardInfoV2),
     TmPeerShardInfoV2(TmPeerShardInfoV2),
     TmTransactions(TmTransactions),
+    /// A message of a type the codec doesn't know, kept as it was received.
+    Unknown {
+        message_type: u16,
+        raw_bytes: Vec<u8>,
+    },
+    /// A frame with the protocol error bits of its compressed header set, which rippled treats as
+    /// a protocol error. The payload isn't decompressed nor decoded.
+    ///
+    /// When encoded, the compression and protocol error bits are set regardless of the flags.
+    ProtocolError {
+        /// The top six bits of the header, i.e. the compression algorithm and the error bits.
+        flags: u8,
+        message_type: u16,
+        uncompressed_size: u32,
+        raw_bytes: Vec<u8>,
+    },
+}
+
+impl Payload {
+    /// Returns the name of the payload's variant, e.g. `TmPing`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::TmManifests(_) => "TmManifests",
+            Self::TmPing(_) => "TmPing",
+            Self::TmCluster(_) => "TmCluster",
+            Self::TmEndpoints(_) => "TmEndpoints",
+            Self::TmTransaction(_) => "TmTransaction",
+            Self::TmGetLedger(_) => "TmGetLedger",
+            Self::TmLedgerData(_) => "TmLedgerData",
+            Self::TmProposeLedger(_) => "TmProposeLedger",
+            Self::TmStatusChange(_) => "TmStatusChange",
+            Self::TmHaveTransactions(_) => "TmHaveTransactions",
+            Self::TmHaveSet(_) => "TmHaveSet",
+            Self::TmValidation(_) => "TmValidation",
+            Self::TmGetObjectByHash(_) => "TmGetObjectByHash",
+            Self::TmValidatorList(_) => "TmValidatorList",
+            Self::TmSquelch(_) => "TmSquelch",
+            Self::TmValidatorListCollection(_) => "TmValidatorListCollection",
+            Self::TmProofPathRequest(_) => "TmProofPathRequest",
+            Self::TmProofPathResponse(_) => "TmProofPathResponse",
+            Self::TmReplayDeltaRequest(_) => "TmReplayDeltaRequest",
+            Self::TmReplayDeltaResponse(_) => "TmReplayDeltaResponse",
+            Self::TmGetPeerShardInfoV2(_) => "TmGetPeerShardInfoV2",
+            Self::TmPeerShardInfoV2(_) => "TmPeerShardInfoV2",
+            Self::TmTransactions(_) => "TmTransactions",
+            Self::Unknown { .. } => "Unknown",
+            Self::ProtocolError { .. } => "ProtocolError",
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -77,6 +208,10 @@ pub struct BinaryMessage {
 
 pub struct MessageCodec {
     current_msg_header: Option<Header>,
+    // Compression of encoded messages, if any.
+    compression: Option<Lz4Compression>,
+    // The message types decoded, all of the known ones if not set.
+    allowed_types: Option<HashSet<u16>>,
     // The associated node's span.
     span: Span,
 }
@@ -85,9 +220,23 @@ impl MessageCodec {
     pub fn new(span: Span) -> Self {
         Self {
             current_msg_header: None,
+            compression: None,
+            allowed_types: None,
             span,
         }
     }
+
+    /// Restricts decoding to the message types, others are kept as [Payload::Unknown].
+    pub fn with_allowed_types(mut self, allowed_types: Option<HashSet<u16>>) -> Self {
+        self.allowed_types = allowed_types;
+        self
+    }
+
+    /// Sets the compression of encoded messages, decoding handles compressed messages anyway.
+    pub fn with_compression(mut self, compression: Option<Lz4Compression>) -> Self {
+        self.compression = compression;
+        self
+    }
 }
 
 impl Decoder for MessageCodec {
@@ -104,32 +253,51 @@ impl Decoder for MessageCodec {
             if src[0] & COMPRESSED_TRUE != 0 {
                 trace!(parent: &self.span, "processing a compressed message");
 
-                let header_size = HEADER_LEN_COMPRESSED;
+                let header_size = HEADER_LEN_COMPRESSED as u32;
                 if src.remaining() < header_size as usize {
                     return Ok(None);
                 }
 
-                // protocol error
-                if (src[0] & PROTOCOL_ERROR) != 0 {
-                    unimplemented!();
-                }
-
-                let header_bytes = src.split_to(header_size as usize);
-                let mut iter = header_bytes.into_iter();
-
                 let compression = src[0] & COMPRESSION_ALGO;
                 trace!(parent: &self.span, "compression: {:x}", compression);
 
-                // only LZ4 is currently supported
-                if compression != COMPRESSION_LZ4 {
-                    unimplemented!();
+                // protocol error, the frame is kept raw for the test to judge
+                let protocol_error = src[0] & PROTOCOL_ERROR != 0;
+                if protocol_error {
+                    warn!(parent: &self.span, "the protocol error bits are set");
+                } else if compression != COMPRESSION_LZ4 {
+                    // only LZ4 is currently supported
+                    error!(parent: &self.span, "unsupported compression algorithm: {compression:x}");
+
+                    return Err(io::ErrorKind::InvalidData.into());
                 }
 
+                let flags = src[0] & HEADER_FLAGS;
+                let header_bytes = src.split_to(header_size as usize);
+                let mut iter = header_bytes.into_iter();
+
                 let mut payload_wire_size = 0;
                 for _ in 0..4 {
                     payload_wire_size = (payload_wire_size << 8u32) + iter.next().unwrap() as u32;
                 }
-                payload_wire_size &= 0x0FFFFFFF; // clear the top four bits (the compression bits)
+                payload_wire_size &= match protocol_error {
+                    true => UNCOMPRESSED_SIZE_MASK, // clear the flags
+                    false => COMPRESSED_SIZE_MASK, // clear the top four bits (the compression bits)
+                };
+                // The payload is buffered until complete, so the size is checked first. The
+                // protocol error bits overlap the size field, which keeps it under the limit.
+                if payload_wire_size > MAX_PAYLOAD_SIZE {
+                    error!(
+                        parent: &self.span,
+                        "the payload size {payload_wire_size} exceeds the limit"
+                    );
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!(
+                            "payload size {payload_wire_size} exceeds {MAX_PAYLOAD_SIZE} bytes"
+                        ),
+                    ));
+                }
 
                 let total_wire_size = header_size + payload_wire_size;
 
@@ -149,14 +317,17 @@ impl Decoder for MessageCodec {
                     payload_wire_size,
                     uncompressed_size,
                     message_type,
-                    compression: Compression::LZ4,
+                    compression: match protocol_error {
+                        true => Compression::ProtocolError(flags),
+                        false => Compression::LZ4,
+                    },
                 };
 
                 self.current_msg_header = Some(header);
             } else if src[0] & COMPRESSED_FALSE == 0 {
                 trace!(parent: &self.span, "processing an uncompressed message");
 
-                let header_size = HEADER_LEN_UNCOMPRESSED;
+                let header_size = HEADER_LEN_UNCOMPRESSED as u32;
                 if src.remaining() < header_size as usize {
                     return Ok(None);
                 }
@@ -203,33 +374,84 @@ impl Decoder for MessageCodec {
             }
 
             let header = self.current_msg_header.take().unwrap();
-            let mut payload = src.split_to(payload_wire_size as usize);
-
-            let payload = match header.message_type {
-                2 => Payload::TmManifests(Message::decode(&mut payload)?),
-                3 => Payload::TmPing(Message::decode(&mut payload)?),
-                5 => Payload::TmCluster(Message::decode(&mut payload)?),
-                15 => Payload::TmEndpoints(Message::decode(&mut payload)?),
-                30 => Payload::TmTransaction(Message::decode(&mut payload)?),
-                31 => Payload::TmGetLedger(Message::decode(&mut payload)?),
-                32 => Payload::TmLedgerData(Message::decode(&mut payload)?),
-                33 => Payload::TmProposeLedger(Message::decode(&mut payload)?),
-                34 => Payload::TmStatusChange(Message::decode(&mut payload)?),
-                35 => Payload::TmHaveSet(Message::decode(&mut payload)?),
-                41 => Payload::TmValidation(Message::decode(&mut payload)?),
-                42 => Payload::TmGetObjectByHash(Message::decode(&mut payload)?),
-                54 => Payload::TmValidatorList(Message::decode(&mut payload)?),
-                55 => Payload::TmSquelch(Message::decode(&mut payload)?),
-                56 => Payload::TmValidatorListCollection(Message::decode(&mut payload)?),
-                57 => Payload::TmProofPathRequest(Message::decode(&mut payload)?),
-                58 => Payload::TmProofPathResponse(Message::decode(&mut payload)?),
-                59 => Payload::TmReplayDeltaRequest(Message::decode(&mut payload)?),
-                60 => Payload::TmReplayDeltaResponse(Message::decode(&mut payload)?),
-                61 => Payload::TmGetPeerShardInfoV2(Message::decode(&mut payload)?),
-                62 => Payload::TmPeerShardInfoV2(Message::decode(&mut payload)?),
-                63 => Payload::TmHaveTransactions(Message::decode(&mut payload)?),
-                64 => Payload::TmTransactions(Message::decode(&mut payload)?),
-                _ => unimplemented!(),
+            let payload = src.split_to(payload_wire_size as usize);
+            if let Compression::ProtocolError(flags) = header.compression {
+                let payload = Payload::ProtocolError {
+                    flags,
+                    message_type: header.message_type,
+                    uncompressed_size: header.uncompressed_size,
+                    raw_bytes: payload.to_vec(),
+                };
+                debug!(parent: &self.span, "decoded a protocol error header: {:?}", header);
+
+                return Ok(Some(BinaryMessage { header, payload }));
+            }
+
+            let mut payload = match header.compression {
+                Compression::None | Compression::ProtocolError(_) => payload,
+                Compression::LZ4 => {
+                    // The decompression buffer is sized by the peer, so the size is checked first.
+                    if header.uncompressed_size > MAX_PAYLOAD_SIZE {
+                        error!(
+                            parent: &self.span,
+                            "the uncompressed size {} exceeds the limit", header.uncompressed_size
+                        );
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            format!(
+                                "uncompressed size {} exceeds {MAX_PAYLOAD_SIZE} bytes",
+                                header.uncompressed_size
+                            ),
+                        ));
+                    }
+                    let decompressed =
+                        lz4_flex::block::decompress(&payload, header.uncompressed_size as usize)
+                            .map_err(|e| {
+                                error!(parent: &self.span, "unable to decompress the payload: {e}");
+                                io::Error::new(io::ErrorKind::InvalidData, e)
+                            })?;
+                    BytesMut::from(&decompressed[..])
+                }
+            };
+
+            let message_type = match &self.allowed_types {
+                Some(allowed) if !allowed.contains(&header.message_type) => None,
+                _ => Some(header.message_type),
+            };
+            let payload = match message_type {
+                Some(2) => Payload::TmManifests(Message::decode(&mut payload)?),
+                Some(3) => Payload::TmPing(Message::decode(&mut payload)?),
+                Some(5) => Payload::TmCluster(Message::decode(&mut payload)?),
+                Some(15) => Payload::TmEndpoints(Message::decode(&mut payload)?),
+                Some(30) => Payload::TmTransaction(Message::decode(&mut payload)?),
+                Some(31) => Payload::TmGetLedger(Message::decode(&mut payload)?),
+                Some(32) => Payload::TmLedgerData(Message::decode(&mut payload)?),
+                Some(33) => Payload::TmProposeLedger(Message::decode(&mut payload)?),
+                Some(34) => Payload::TmStatusChange(Message::decode(&mut payload)?),
+                Some(35) => Payload::TmHaveSet(Message::decode(&mut payload)?),
+                Some(41) => Payload::TmValidation(Message::decode(&mut payload)?),
+                Some(42) => Payload::TmGetObjectByHash(Message::decode(&mut payload)?),
+                Some(54) => Payload::TmValidatorList(Message::decode(&mut payload)?),
+                Some(55) => Payload::TmSquelch(Message::decode(&mut payload)?),
+                Some(56) => Payload::TmValidatorListCollection(Message::decode(&mut payload)?),
+                Some(57) => Payload::TmProofPathRequest(Message::decode(&mut payload)?),
+                Some(58) => Payload::TmProofPathResponse(Message::decode(&mut payload)?),
+                Some(59) => Payload::TmReplayDeltaRequest(Message::decode(&mut payload)?),
+                Some(60) => Payload::TmReplayDeltaResponse(Message::decode(&mut payload)?),
+                Some(61) => Payload::TmGetPeerShardInfoV2(Message::decode(&mut payload)?),
+                Some(62) => Payload::TmPeerShardInfoV2(Message::decode(&mut payload)?),
+                Some(63) => Payload::TmHaveTransactions(Message::decode(&mut payload)?),
+                Some(64) => Payload::TmTransactions(Message::decode(&mut payload)?),
+                message_type => {
+                    if message_type.is_some() {
+                        warn!(parent: &self.span, "unknown message type: {}", header.message_type);
+                    }
+
+                    Payload::Unknown {
+                        message_type: header.message_type,
+                        raw_bytes: payload.to_vec(),
+                    }
+                }
             };
 
             let message = BinaryMessage { header, payload };
@@ -256,6 +478,32 @@ impl Encoder<Payload> for MessageCodec {
 
     // Based on Ripple's `Message::Message` (ripple/overlay/impl/Message.cpp)
     fn encode(&mut self, message: Payload, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        if let Payload::ProtocolError {
+            flags,
+            message_type,
+            uncompressed_size,
+            raw_bytes,
+        } = message
+        {
+            if raw_bytes.len() > UNCOMPRESSED_SIZE_MASK as usize {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "the payload doesn't fit a protocol error header",
+                ));
+            }
+
+            let mut header_bytes = [0u8; HEADER_LEN_COMPRESSED];
+            header_bytes[..4].copy_from_slice(&(raw_bytes.len() as u32).to_be_bytes());
+            header_bytes[0] |= flags & HEADER_FLAGS | COMPRESSED_TRUE | PROTOCOL_ERROR;
+            header_bytes[4..6].copy_from_slice(&message_type.to_be_bytes());
+            header_bytes[6..].copy_from_slice(&uncompressed_size.to_be_bytes());
+
+            dst.put(&header_bytes[..]);
+            dst.put(&raw_bytes[..]);
+
+            return Ok(());
+        }
+
         let (payload_len, msg_type) = match &message {
             Payload::TmManifests(msg) => {
                 (msg.encoded_len() as u32, MessageType::MtManifests as i32)
@@ -327,28 +575,14 @@ impl Encoder<Payload> for MessageCodec {
             Payload::TmTransactions(msg) => {
                 (msg.encoded_len() as u32, MessageType::MtTransactions as i32)
             }
+            Payload::Unknown {
+                message_type,
+                raw_bytes,
+            } => (raw_bytes.len() as u32, *message_type as i32),
+            Payload::ProtocolError { .. } => unreachable!("protocol errors are encoded raw"),
         };
 
-        let header_size = HEADER_LEN_UNCOMPRESSED;
-        let _header = Header {
-            total_wire_size: header_size + payload_len,
-            header_size,
-            payload_wire_size: payload_len,
-            uncompressed_size: payload_len,
-            message_type: msg_type as u16,
-            compression: Compression::None, // TODO: are compressed messages used?
-        };
-
-        let mut header_bytes = [0u8; HEADER_LEN_UNCOMPRESSED as usize];
-
-        pack(&mut header_bytes, payload_len);
-
-        header_bytes[4] = ((msg_type >> 8) & 0xff) as u8;
-        header_bytes[5] = (msg_type & 0xff) as u8;
-
-        let mut bytes = BytesMut::new();
-
-        bytes.put(&header_bytes[..]);
+        let mut bytes = BytesMut::with_capacity(payload_len as usize);
 
         match message {
             Payload::TmManifests(msg) => (msg.encode(&mut bytes).unwrap(),),
@@ -374,9 +608,38 @@ impl Encoder<Payload> for MessageCodec {
             Payload::TmPeerShardInfoV2(msg) => (msg.encode(&mut bytes).unwrap(),),
             Payload::TmTransactions(msg) => (msg.encode(&mut bytes).unwrap(),),
             Payload::TmHaveTransactions(msg) => (msg.encode(&mut bytes).unwrap(),),
+            Payload::Unknown { raw_bytes, .. } => (bytes.put(&raw_bytes[..]),),
+            Payload::ProtocolError { .. } => unreachable!("protocol errors are encoded raw"),
         };
 
-        dst.put(&*bytes);
+        let compressed = self
+            .compression
+            .filter(|compression| payload_len >= compression.threshold)
+            .map(|_| lz4_flex::block::compress(&bytes))
+            .filter(|compressed| compressed.len() < bytes.len());
+
+        match compressed {
+            Some(compressed) => {
+                let mut header_bytes = [0u8; HEADER_LEN_COMPRESSED];
+                pack(&mut header_bytes, compressed.len() as u32);
+                header_bytes[0] |= COMPRESSION_LZ4;
+                header_bytes[4] = ((msg_type >> 8) & 0xff) as u8;
+                header_bytes[5] = (msg_type & 0xff) as u8;
+                header_bytes[6..].copy_from_slice(&payload_len.to_be_bytes());
+
+                dst.put(&header_bytes[..]);
+                dst.put(&compressed[..]);
+            }
+            None => {
+                let mut header_bytes = [0u8; HEADER_LEN_UNCOMPRESSED];
+                pack(&mut header_bytes, payload_len);
+                header_bytes[4] = ((msg_type >> 8) & 0xff) as u8;
+                header_bytes[5] = (msg_type & 0xff) as u8;
+
+                dst.put(&header_bytes[..]);
+                dst.put(&*bytes);
+            }
+        }
 
         Ok(())
     }
@@ -408,4 +671,153 @@ mod tests {
 
         assert_eq!(raw, encoded);
     }
+
+    #[test]
+    fn unknown_message_type_is_kept() {
+        let raw = BytesMut::from(&b"\0\0\0\x03\0\x63abc"[..]);
+
+        let mut codec = MessageCodec::new(Span::none());
+        let msg = codec.decode(&mut raw.clone()).unwrap().unwrap();
+        assert!(matches!(
+            &msg.payload,
+            Payload::Unknown { message_type: 99, raw_bytes } if raw_bytes == b"abc"
+        ));
+
+        let mut encoded = BytesMut::new();
+        codec.encode(msg.payload, &mut encoded).unwrap();
+        assert_eq!(raw, encoded);
+    }
+
+    #[test]
+    fn disallowed_message_type_is_kept_raw() {
+        let ping = Payload::TmPing(TmPing {
+            r#type: tm_ping::PingType::PtPing as i32,
+            seq: Some(1),
+            ping_time: None,
+            net_time: None,
+        });
+        let mut raw = BytesMut::new();
+        MessageCodec::new(Span::none())
+            .encode(ping, &mut raw)
+            .unwrap();
+
+        let allowed = HashSet::from([MessageType::MtEndpoints as u16]);
+        let mut codec = MessageCodec::new(Span::none()).with_allowed_types(Some(allowed));
+        let msg = codec.decode(&mut raw.clone()).unwrap().unwrap();
+        assert!(matches!(
+            msg.payload,
+            Payload::Unknown { message_type, .. } if message_type == MessageType::MtPing as u16
+        ));
+
+        let mut encoded = BytesMut::new();
+        codec.encode(msg.payload, &mut encoded).unwrap();
+        assert_eq!(raw, encoded);
+    }
+
+    #[test]
+    fn compressed_roundtrip() {
+        let payload = Payload::TmValidatorList(TmValidatorList {
+            manifest: vec![7; 200],
+            blob: vec![8; 300],
+            signature: vec![9; 64],
+            version: 1,
+        });
+        let mut codec =
+            MessageCodec::new(Span::none()).with_compression(Some(Lz4Compression::default()));
+
+        let mut encoded = BytesMut::new();
+        codec.encode(payload.clone(), &mut encoded).unwrap();
+        assert_eq!(encoded[0] & COMPRESSION_ALGO, COMPRESSION_LZ4);
+
+        let msg = codec.decode(&mut encoded).unwrap().unwrap();
+        assert!(matches!(msg.header.compression, Compression::LZ4));
+        assert!(encoded.is_empty());
+
+        let mut uncompressed = BytesMut::new();
+        MessageCodec::new(Span::none())
+            .encode(payload, &mut uncompressed)
+            .unwrap();
+        let mut reencoded = BytesMut::new();
+        MessageCodec::new(Span::none())
+            .encode(msg.payload, &mut reencoded)
+            .unwrap();
+        assert_eq!(uncompressed, reencoded);
+
+        // Small payloads are left uncompressed.
+        let ping = Payload::TmPing(TmPing {
+            r#type: tm_ping::PingType::PtPing as i32,
+            seq: Some(1),
+            ping_time: None,
+            net_time: None,
+        });
+        let mut encoded = BytesMut::new();
+        codec.encode(ping, &mut encoded).unwrap();
+        assert_eq!(encoded[0] & COMPRESSED_TRUE, 0);
+    }
+
+    #[test]
+    fn oversized_uncompressed_size_is_rejected() {
+        // An LZ4 header with an empty payload, declaring the largest uncompressed size.
+        let mut raw = BytesMut::from(&b"\x90\0\0\0\0\x03\xff\xff\xff\xff"[..]);
+
+        let mut codec = MessageCodec::new(Span::none());
+        let error = codec.decode(&mut raw).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn oversized_wire_size_is_not_buffered() {
+        // LZ4 headers with every size bit set, without the payload. The protocol error bits
+        // overlap the size field, so the declared size never exceeds the limit.
+        for raw in [
+            &b"\x9f\xff\xff\xff\0\x03\0\0\0\x01"[..],
+            &b"\x93\xff\xff\xff\0\x03\0\0\0\x01"[..],
+        ] {
+            let mut codec = MessageCodec::new(Span::none());
+            assert!(codec.decode(&mut BytesMut::from(raw)).unwrap().is_none());
+
+            let header = codec.current_msg_header.as_ref().unwrap();
+            assert!(header.payload_wire_size <= MAX_PAYLOAD_SIZE);
+        }
+    }
+
+    #[test]
+    fn protocol_error_is_kept_raw() {
+        let error = Payload::ProtocolError {
+            flags: COMPRESSION_LZ4,
+            message_type: MessageType::MtPing as u16,
+            uncompressed_size: 100,
+            raw_bytes: b"abc".to_vec(),
+        };
+        let mut codec = MessageCodec::new(Span::none());
+        let mut encoded = BytesMut::new();
+        codec.encode(error, &mut encoded).unwrap();
+        assert_eq!(&encoded[..], b"\x9c\0\0\x03\0\x03\0\0\0\x64abc");
+
+        // Followed by a regular message, which is still decoded.
+        let raw = encoded.clone();
+        encoded.extend_from_slice(b"\0\0\0\x03\0\x63def");
+        let msg = codec.decode(&mut encoded).unwrap().unwrap();
+        assert_eq!(msg.payload.name(), "ProtocolError");
+        assert!(matches!(
+            &msg.payload,
+            Payload::ProtocolError {
+                flags: 0x9c,
+                message_type: 3,
+                uncompressed_size: 100,
+                raw_bytes,
+            } if raw_bytes == b"abc"
+        ));
+        assert!(matches!(
+            codec.decode(&mut encoded).unwrap().unwrap().payload,
+            Payload::Unknown {
+                message_type: 99,
+                ..
+            }
+        ));
+
+        let mut reencoded = BytesMut::new();
+        codec.encode(msg.payload, &mut reencoded).unwrap();
+        assert_eq!(raw, reencoded);
+    }
 }
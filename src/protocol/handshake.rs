@@ -34,38 +34,343 @@
 //! > \r\n"
 //! ---------------------
 
-use std::{io, pin::Pin};
+use std::{
+    fmt, io,
+    net::SocketAddr,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
 use base64::{engine::general_purpose::STANDARD, Engine};
 use bytes::Bytes;
 use futures_util::{sink::SinkExt, TryStreamExt};
 use openssl::ssl::Ssl;
 use pea2pea::{protocols::Handshake, Connection, ConnectionSide, Pea2Pea};
-use rand::{thread_rng, Rng};
+use rand::Rng;
+use rand_chacha::ChaCha8Rng;
 use sha2::{Digest, Sha512};
+use thiserror::Error;
+use tokio::{
+    io::{AsyncRead, AsyncWrite, AsyncWriteExt},
+    net::TcpStream,
+    time::{sleep, timeout, Instant},
+};
 use tokio_openssl::SslStream;
 use tokio_util::codec::Framed;
 use tracing::*;
 
 use crate::{
-    protocol::codecs::http::{HttpCodec, HttpMsg},
-    tools::inner_node::{Crypto, InnerNode},
+    fuzzing::seeded_rng,
+    protocol::{
+        codecs::http::{HttpCodec, HttpMessage, HttpMsg},
+        version::{format_versions, negotiate, parse_versions, ProtocolVersion},
+    },
+    tools::{
+        inner_node::{ConnectionEvent, Crypto, InnerNode},
+        ripple_time,
+        validator::{
+            encode_node_public_key, verify_digest, KeyType, ValidatorKey, TOKEN_NODE_PUBLIC,
+        },
+    },
 };
 
 // Default handshake header values.
 const CONNECTION: &str = "Upgrade";
-const UPGRADE_REQ: &str = "XRPL/2.0, XRPL/2.1, XRPL/2.2";
-const UPGRADE_RSP: &str = "XRPL/2.2";
 const CONNECT_AS: &str = "Peer";
-// txrr - enables transaction relay
-// ledgerreplay - enables ledger replay
-const X_PROTOCOL_CTL: &str = "txrr=1;ledgerreplay=1";
-
-#[repr(u8)]
-enum NodeType {
-    Public = 28,
-    #[allow(dead_code)]
-    Private = 32,
+
+// The features negotiated in the X-Protocol-Ctl field, as named by rippled.
+const FEATURE_COMPR: &str = "compr";
+const FEATURE_VPRR: &str = "vprr";
+const FEATURE_TXRR: &str = "txrr";
+const FEATURE_LEDGER_REPLAY: &str = "ledgerreplay";
+
+/// The optional protocol features listed in the 'X-Protocol-Ctl' field, e.g.
+/// `compr=lz4;txrr=1;ledgerreplay=1`.
+///
+/// A peer requests the features it wants to use, the response lists the ones it enables, i.e.
+/// the requested features it supports and has configured.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProtocolFeatures {
+    /// `compr` - LZ4 compression of messages.
+    pub compression: bool,
+    /// `vprr` - reduced relaying of validations and proposals, peers can be squelched.
+    pub vp_reduce_relay: bool,
+    /// `txrr` - reduced relaying of transactions, some peers get [TmHaveTransactions] instead.
+    ///
+    /// [TmHaveTransactions]: crate::protocol::proto::TmHaveTransactions
+    pub tx_reduce_relay: bool,
+    /// `ledgerreplay` - serving ledger replay requests, e.g.
+    /// [TmReplayDeltaRequest](crate::protocol::proto::TmReplayDeltaRequest).
+    pub ledger_replay: bool,
+}
+
+impl ProtocolFeatures {
+    /// No features, i.e. an empty field.
+    pub const NONE: Self = Self {
+        compression: false,
+        vp_reduce_relay: false,
+        tx_reduce_relay: false,
+        ledger_replay: false,
+    };
+
+    /// All the features.
+    pub const ALL: Self = Self {
+        compression: true,
+        vp_reduce_relay: true,
+        tx_reduce_relay: true,
+        ledger_replay: true,
+    };
+
+    /// Parses the field's value. Unknown features and features with other values, e.g.
+    /// `txrr=0`, are considered disabled.
+    pub fn parse(value: &str) -> Self {
+        let mut features = Self::NONE;
+        for feature in value.split(';') {
+            let Some((name, values)) = feature.split_once('=') else {
+                continue;
+            };
+            let has = |expected: &str| values.split(',').any(|value| value.trim() == expected);
+            match name.trim() {
+                FEATURE_COMPR => features.compression = has("lz4"),
+                FEATURE_VPRR => features.vp_reduce_relay = has("1"),
+                FEATURE_TXRR => features.tx_reduce_relay = has("1"),
+                FEATURE_LEDGER_REPLAY => features.ledger_replay = has("1"),
+                _ => (),
+            }
+        }
+        features
+    }
+}
+
+impl Default for ProtocolFeatures {
+    /// Transaction relay reduction and ledger replay.
+    fn default() -> Self {
+        Self {
+            tx_reduce_relay: true,
+            ledger_replay: true,
+            ..Self::NONE
+        }
+    }
+}
+
+impl fmt::Display for ProtocolFeatures {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let features = [
+            (self.compression, FEATURE_COMPR, "lz4"),
+            (self.vp_reduce_relay, FEATURE_VPRR, "1"),
+            (self.tx_reduce_relay, FEATURE_TXRR, "1"),
+            (self.ledger_replay, FEATURE_LEDGER_REPLAY, "1"),
+        ];
+        let enabled = features
+            .iter()
+            .filter(|(enabled, _, _)| *enabled)
+            .map(|(_, name, value)| format!("{name}={value}"))
+            .collect::<Vec<_>>();
+        f.write_str(&enabled.join(";"))
+    }
+}
+
+/// A change to the header fields of the handshake request or response, see
+/// [HandshakeCfg::http_header_edits].
+///
+/// Field names are matched case-insensitively.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HeaderEdit {
+    /// Removes the fields with the name, e.g. a mandatory one.
+    Remove(String),
+    /// Repeats the fields with the name, each right after itself.
+    Duplicate(String),
+    /// Appends a field with the name and value.
+    Append(String, String),
+    /// Appends the line as is, e.g. one without a colon or containing line breaks.
+    AppendRaw(String),
+}
+
+impl HeaderEdit {
+    fn apply(&self, headers: &mut Vec<String>) {
+        let has_name = |line: &String, name: &str| {
+            line.split_once(':')
+                .is_some_and(|(field, _)| field.trim().eq_ignore_ascii_case(name))
+        };
+
+        match self {
+            Self::Remove(name) => headers.retain(|line| !has_name(line, name)),
+            Self::Duplicate(name) => {
+                *headers = headers
+                    .drain(..)
+                    .flat_map(|line| match has_name(&line, name) {
+                        true => vec![line.clone(), line],
+                        false => vec![line],
+                    })
+                    .collect();
+            }
+            Self::Append(name, value) => headers.push(format!("{name}: {value}")),
+            Self::AppendRaw(line) => headers.push(line.clone()),
+        }
+    }
+}
+
+/// How the 'Session-Signature' handshake field is produced.
+#[derive(Clone, Default)]
+pub enum SessionSignature {
+    /// The shared value signed with the node's key.
+    #[default]
+    Valid,
+    /// The shared value signed with a key other than the one in the 'Public-Key' field.
+    ForeignKey(Arc<ValidatorKey>),
+    /// The signature of the first session, sent again in the later ones.
+    Replayed(Arc<Mutex<Option<String>>>),
+    /// The value sent as is, e.g. one which isn't base64.
+    Raw(String),
+}
+
+impl SessionSignature {
+    /// Signs with a freshly generated key.
+    pub fn foreign_key() -> Self {
+        Self::ForeignKey(Arc::new(ValidatorKey::generate(KeyType::Secp256k1)))
+    }
+
+    /// Captures the signature of the first session, configurations cloned from this one share it.
+    pub fn replayed() -> Self {
+        Self::Replayed(Default::default())
+    }
+
+    fn create(&self, crypto: &Crypto, shared_value: &[u8]) -> String {
+        match self {
+            Self::Valid => create_session_signature(&crypto.key, shared_value),
+            Self::ForeignKey(key) => create_session_signature(key, shared_value),
+            Self::Replayed(captured) => captured
+                .lock()
+                .unwrap()
+                .get_or_insert_with(|| create_session_signature(&crypto.key, shared_value))
+                .clone(),
+            Self::Raw(value) => value.clone(),
+        }
+    }
+}
+
+/// How the handshake request is sent, see [HandshakeCfg::http_request_pacing].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RequestPacing {
+    /// All at once.
+    #[default]
+    Whole,
+    /// One byte at a time, with the delay after each byte.
+    Trickle(Duration),
+    /// All but the empty line ending the header, so the request never completes.
+    Unterminated,
+}
+
+impl RequestPacing {
+    // Writes the request, counting the bytes written so far.
+    async fn write<S>(&self, stream: &mut S, req: &[u8], bytes_sent: &mut usize) -> io::Result<()>
+    where
+        S: AsyncWrite + Unpin,
+    {
+        match self {
+            Self::Whole => {
+                stream.write_all(req).await?;
+                *bytes_sent += req.len();
+            }
+            Self::Trickle(delay) => {
+                for byte in req {
+                    stream.write_all(&[*byte]).await?;
+                    stream.flush().await?;
+                    *bytes_sent += 1;
+                    sleep(*delay).await;
+                }
+            }
+            Self::Unterminated => {
+                let req = req.strip_suffix(b"\r\n").unwrap_or(req);
+                stream.write_all(req).await?;
+                *bytes_sent += req.len();
+            }
+        }
+        stream.flush().await
+    }
+}
+
+/// How a connection held by [InnerNode::hold_handshake] ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HoldEnd {
+    /// The peer responded, with the status code if the response could be parsed.
+    Responded(Option<u16>),
+    /// The peer closed or reset the connection without responding.
+    Closed,
+    /// The connection was still open when the time limit was reached.
+    TimeLimit,
+}
+
+/// The outcome of [InnerNode::hold_handshake].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeldHandshake {
+    /// The time from the end of the TLS handshake until the connection ended.
+    pub held_for: Duration,
+    /// The bytes of the request sent by then.
+    pub bytes_sent: usize,
+    pub end: HoldEnd,
+}
+
+/// The error of a handshake the peer answered with a status other than `101 Switching Protocols`.
+///
+/// rippled explains handshake failures in the reason phrase, e.g. `Bad Request (...)`, and lists
+/// other peers in the body of `503 Service Unavailable` responses.
+#[derive(Debug, Error)]
+#[error("the peer rejected the handshake with {status} {reason}: {body:?}")]
+pub struct HandshakeRejected {
+    pub status: u16,
+    pub reason: String,
+    /// The header fields' names and values, in the order received.
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl HandshakeRejected {
+    fn new(response: HttpMessage) -> Self {
+        Self {
+            status: response.status.unwrap_or_default(),
+            reason: response.reason.clone().unwrap_or_default(),
+            body: String::from_utf8_lossy(&response.body).into_owned(),
+            headers: response.headers,
+        }
+    }
+
+    /// Returns the value of the first header field with the name, matched case-insensitively.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Parses the body as JSON.
+    pub fn json(&self) -> serde_json::Result<serde_json::Value> {
+        serde_json::from_str(&self.body)
+    }
+
+    /// Returns the endpoints a full node redirects to in the `peer-ips` list of its
+    /// `503 Service Unavailable` response, or `None` if the response isn't a redirect.
+    ///
+    /// Entries which aren't socket addresses are skipped.
+    pub fn peer_ips(&self) -> Option<Vec<SocketAddr>> {
+        if self.status != 503 {
+            return None;
+        }
+        let json = self.json().ok()?;
+        let ips = json.get("peer-ips")?.as_array()?;
+        Some(
+            ips.iter()
+                .filter_map(|ip| ip.as_str()?.parse().ok())
+                .collect(),
+        )
+    }
+
+    /// Returns the rejection the error wraps, e.g. one returned by
+    /// [SyntheticNode::connect](crate::tools::synth_node::SyntheticNode::connect).
+    pub fn from_error(error: &io::Error) -> Option<&Self> {
+        error.get_ref()?.downcast_ref()
+    }
 }
 
 /// Handshake configuration allows some customization of the handshake procedure.
@@ -77,20 +382,39 @@ pub struct HandshakeCfg {
     /// Will flip a random bit in a random byte of the public key.
     pub bitflip_pub_key: bool,
 
+    /// Picks the flipped bits, seeded with the test run's seed so the flips can be replayed.
+    pub bitflip_rng: Arc<Mutex<ChaCha8Rng>>,
+
+    /// How the session signature is produced, e.g. to forge it.
+    pub session_signature: SessionSignature,
+
     /// Identification header to be set during a handshake.
     /// Either 'User-Agent' or 'Server' depending on connection side.
     pub http_ident: String,
 
+    /// The method of the handshake request.
+    pub http_method: String,
+
+    /// The path of the handshake request.
+    pub http_path: String,
+
+    /// The HTTP version of the handshake request.
+    pub http_version: String,
+
     /// A handshake field for the connection type.
     pub http_connection: String,
 
+    /// The protocol versions offered in a handshake request, or accepted from the peer's offer when
+    /// responding, in which case the highest common version is chosen.
+    pub protocol_versions: Vec<ProtocolVersion>,
+
     /// A handshake field for the connection upgrade field - available versions sent
-    /// in the handshake request.
-    pub http_upgrade_req: String,
+    /// in the handshake request, `protocol_versions` are listed if not set.
+    pub http_upgrade_req: Option<String>,
 
     /// A handshake field for the connection upgrade field - a chosen version sent in
-    /// the handshake response.
-    pub http_upgrade_rsp: String,
+    /// the handshake response, the negotiated version is sent if not set.
+    pub http_upgrade_rsp: Option<String>,
 
     /// A handshake field for the connector name.
     pub http_connect_as: String,
@@ -104,6 +428,13 @@ pub struct HandshakeCfg {
     /// A handshake field for the network time.
     pub http_network_time: Option<String>,
 
+    /// Sends the network time at the moment of the handshake shifted by the number of seconds,
+    /// takes precedence over `http_network_time`.
+    pub http_network_time_skew: Option<i64>,
+
+    /// A handshake field for the network's id, see [NetworkProfile](crate::setup::network::NetworkProfile).
+    pub http_network_id: Option<String>,
+
     /// A handshake field which contains a hash for the closed ledger.
     pub http_closed_ledger: Option<String>,
 
@@ -112,6 +443,12 @@ pub struct HandshakeCfg {
 
     /// A random field for testing HTTP headers.
     pub http_unexpected_extra_field_and_value: Option<String>,
+
+    /// Changes to the generated header fields of the request or response, applied in order.
+    pub http_header_edits: Vec<HeaderEdit>,
+
+    /// How the handshake request is sent, slow requests are subject to the handshake timeout.
+    pub http_request_pacing: RequestPacing,
 }
 
 impl Default for HandshakeCfg {
@@ -120,47 +457,166 @@ impl Default for HandshakeCfg {
             // Handshake procedure options.
             bitflip_shared_val: false,
             bitflip_pub_key: false,
+            bitflip_rng: Arc::new(Mutex::new(seeded_rng())),
+            session_signature: SessionSignature::Valid,
+
+            // Protocol version negotiation.
+            protocol_versions: ProtocolVersion::SUPPORTED.to_vec(),
+
+            // The request line.
+            http_method: "GET".into(),
+            http_path: "/".into(),
+            http_version: "HTTP/1.1".into(),
 
             // Mandatory handshake HTTP fields.
             http_ident: "rippled-1.9.4".into(),
             http_connection: CONNECTION.to_owned(),
-            http_upgrade_req: UPGRADE_REQ.to_owned(),
-            http_upgrade_rsp: UPGRADE_RSP.to_owned(),
+            http_upgrade_req: None,
+            http_upgrade_rsp: None,
             http_connect_as: CONNECT_AS.to_owned(),
-            http_x_protocol_ctl: X_PROTOCOL_CTL.to_owned(),
+            http_x_protocol_ctl: ProtocolFeatures::default().to_string(),
 
             // Optional handshake HTTP fields.
             http_crawl: None,
             http_network_time: None,
+            http_network_time_skew: None,
+            http_network_id: None,
             http_closed_ledger: None,
             http_prev_ledger: None,
 
             // A random field.
             http_unexpected_extra_field_and_value: None,
+            http_header_edits: vec![],
+            http_request_pacing: RequestPacing::Whole,
+        }
+    }
+}
+
+impl HandshakeCfg {
+    /// Requests the features, replacing the 'X-Protocol-Ctl' field.
+    pub fn with_features(mut self, features: ProtocolFeatures) -> Self {
+        self.http_x_protocol_ctl = features.to_string();
+        self
+    }
+
+    // Serializes the request or response with the header fields, after applying the edits.
+    fn build_http_message(&self, start_line: String, mut headers: Vec<String>) -> Bytes {
+        if let Some(ref header) = self.http_unexpected_extra_field_and_value {
+            headers.push(header.clone());
+        }
+        for edit in &self.http_header_edits {
+            edit.apply(&mut headers);
+        }
+
+        let mut message = start_line;
+        for header in headers {
+            message.push_str("\r\n");
+            message.push_str(&header);
+        }
+        // An HTTP header ends with an empty line.
+        message.push_str("\r\n\r\n");
+        message.into()
+    }
+
+    // Used to populate the Network-Time field.
+    fn network_time(&self) -> Option<String> {
+        match self.http_network_time_skew {
+            Some(skew) => Some((ripple_time::now() as i64 + skew).max(0).to_string()),
+            None => self.http_network_time.clone(),
         }
     }
 }
 
-// Used to populate the Public-Key field.
-fn encode_base58(node_type: NodeType, public_key: &[u8]) -> String {
-    let mut payload = Vec::with_capacity(1 + public_key.len());
+/// The identity the peer claimed in its handshake, the session signature is verified.
+#[derive(Debug, Clone)]
+pub struct HandshakeInfo {
+    /// The 'Public-Key' field, the peer's node public key, e.g. `n9KPZKMN...`.
+    pub public_key: String,
+    /// The 'Session-Signature' field.
+    pub session_signature: String,
+    /// The 'Connect-As' field.
+    pub connect_as: Option<String>,
+    /// The 'X-Protocol-Ctl' field.
+    pub x_protocol_ctl: Option<String>,
+    /// The 'Server' field, or the 'User-Agent' field if the peer initiated the connection.
+    pub ident: Option<String>,
+    /// The version selected by the peer in its response, or by us from the peer's offer.
+    pub protocol_version: Option<ProtocolVersion>,
+    /// All the header fields, in the order received.
+    pub headers: Vec<(String, String)>,
+}
+
+impl HandshakeInfo {
+    /// Returns the value of the first header field with the name, matched case-insensitively.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Returns the features listed in the 'X-Protocol-Ctl' field, i.e. the ones the peer
+    /// enabled if it responded to our request, none if the field is missing.
+    pub fn features(&self) -> ProtocolFeatures {
+        self.x_protocol_ctl
+            .as_deref()
+            .map(ProtocolFeatures::parse)
+            .unwrap_or(ProtocolFeatures::NONE)
+    }
+
+    // Parses the peer's request or response and verifies the peer signed the shared value.
+    fn verify(message: HttpMessage, ident: &str, shared_value: &[u8]) -> io::Result<Self> {
+        let field = |name: &str| message.header(name).map(str::to_owned);
+        let missing = |name: &str| {
+            io::Error::new(io::ErrorKind::InvalidData, format!("missing {name} field"))
+        };
 
-    payload.push(node_type as u8);
-    payload.extend_from_slice(public_key);
+        let public_key = field("Public-Key").ok_or_else(|| missing("Public-Key"))?;
+        let session_signature =
+            field("Session-Signature").ok_or_else(|| missing("Session-Signature"))?;
+        verify_session_signature(&public_key, &session_signature, shared_value)?;
 
-    bs58::encode(payload)
+        Ok(Self {
+            connect_as: field("Connect-As"),
+            x_protocol_ctl: field("X-Protocol-Ctl"),
+            ident: field(ident),
+            protocol_version: None,
+            public_key,
+            session_signature,
+            headers: message.headers,
+        })
+    }
+}
+
+// Used to check the peer's Session-Signature field.
+fn verify_session_signature(
+    public_key: &str,
+    signature: &str,
+    shared_value: &[u8],
+) -> io::Result<()> {
+    let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_owned());
+
+    let public_key = bs58::decode(public_key)
         .with_alphabet(bs58::Alphabet::RIPPLE)
-        .with_check()
-        .into_string()
+        .with_check(Some(TOKEN_NODE_PUBLIC))
+        .into_vec()
+        .map_err(|_| invalid("invalid Public-Key field"))?;
+    let signature = STANDARD
+        .decode(signature)
+        .map_err(|_| invalid("invalid Session-Signature field"))?;
+
+    if verify_digest(&public_key[1..], shared_value, &signature) {
+        Ok(())
+    } else {
+        Err(invalid(
+            "the session signature doesn't match the public key",
+        ))
+    }
 }
 
 // Used to populate the Session-Signature field.
-fn create_session_signature(crypto: &Crypto, shared_value: &[u8]) -> String {
-    let message = secp256k1::Message::from_slice(shared_value).unwrap();
-    let signature = crypto.engine.sign_ecdsa(&message, &crypto.private_key);
-    let serialized = signature.serialize_der();
-
-    STANDARD.encode(serialized)
+fn create_session_signature(key: &ValidatorKey, shared_value: &[u8]) -> String {
+    STANDARD.encode(key.sign_digest(shared_value))
 }
 
 // Used as input for create_session_signature.
@@ -191,6 +647,138 @@ fn get_shared_value<S>(tls_stream: &SslStream<S>) -> io::Result<Vec<u8>> {
     Ok(hash)
 }
 
+impl InnerNode {
+    // Performs the TLS handshake as the client.
+    async fn connect_tls<S>(&self, stream: S) -> io::Result<SslStream<S>>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        let ssl = self
+            .tls
+            .connector
+            .configure()
+            .unwrap()
+            .into_ssl("domain") // is SNI and hostname verification enabled?
+            .unwrap();
+        let mut tls_stream = SslStream::new(ssl, stream).unwrap();
+
+        Pin::new(&mut tls_stream).connect().await.map_err(|e| {
+            error!(parent: self.node().span(), "TLS handshake error: {e}");
+            io::ErrorKind::InvalidData
+        })?;
+
+        Ok(tls_stream)
+    }
+
+    // Builds the handshake HTTP request for the session with the shared value.
+    fn handshake_request(&self, hs_cfg: &HandshakeCfg, mut shared_value: Vec<u8>) -> Bytes {
+        let public_key = &mut self.crypto.key.public_key();
+        // introduce intentional errors into handshake if needed
+        if hs_cfg.bitflip_shared_val {
+            randomly_flip_bit(&mut shared_value, &hs_cfg.bitflip_rng);
+        }
+        if hs_cfg.bitflip_pub_key {
+            randomly_flip_bit(public_key.as_mut_slice(), &hs_cfg.bitflip_rng);
+        }
+
+        // base58-encode the public key and create the session signature
+        let base58_pk = encode_node_public_key(public_key);
+        let sig = hs_cfg.session_signature.create(&self.crypto, &shared_value);
+
+        // prepare the HTTP request message
+        let upgrade = hs_cfg
+            .http_upgrade_req
+            .clone()
+            .unwrap_or_else(|| format_versions(&hs_cfg.protocol_versions));
+        let mut headers = vec![
+            format!("User-Agent: {}", hs_cfg.http_ident),
+            format!("Upgrade: {upgrade}"),
+            format!("Connection: {}", hs_cfg.http_connection),
+            format!("Connect-As: {}", hs_cfg.http_connect_as),
+        ];
+        if let Some(ref crawl) = hs_cfg.http_crawl {
+            headers.push(format!("Crawl: {crawl}"));
+        };
+        headers.push(format!("X-Protocol-Ctl: {}", hs_cfg.http_x_protocol_ctl));
+        if let Some(time) = hs_cfg.network_time() {
+            headers.push(format!("Network-Time: {time}"));
+        };
+        if let Some(ref network_id) = hs_cfg.http_network_id {
+            headers.push(format!("Network-ID: {network_id}"));
+        };
+        headers.push(format!("Public-Key: {base58_pk}"));
+        headers.push(format!("Session-Signature: {sig}"));
+        if let Some(ref ledger) = hs_cfg.http_closed_ledger {
+            headers.push(format!("Closed-Ledger: {ledger}"));
+        };
+        if let Some(ref ledger) = hs_cfg.http_prev_ledger {
+            headers.push(format!("Previous-Ledger: {ledger}"));
+        };
+        let request_line = format!(
+            "{} {} {}",
+            hs_cfg.http_method, hs_cfg.http_path, hs_cfg.http_version
+        );
+        hs_cfg.build_http_message(request_line, headers)
+    }
+
+    /// Connects to the peer and sends the handshake request, paced as configured, then holds
+    /// the connection until the peer responds or closes it, or the time limit is reached.
+    ///
+    /// Unlike [Handshake], this isn't subject to a handshake timeout, so it measures how long
+    /// the peer tolerates slow or unterminated requests.
+    pub async fn hold_handshake(
+        &self,
+        addr: SocketAddr,
+        time_limit: Duration,
+    ) -> io::Result<HeldHandshake> {
+        let hs_cfg = self
+            .handshake_cfg
+            .as_ref()
+            .expect("a handshake config is not set");
+
+        let stream = TcpStream::connect(addr).await?;
+        let mut tls_stream = self.connect_tls(stream).await?;
+        let req = self.handshake_request(hs_cfg, get_shared_value(&tls_stream)?);
+
+        let start = Instant::now();
+        let mut bytes_sent = 0;
+        let end = timeout(time_limit, async {
+            hs_cfg
+                .http_request_pacing
+                .write(&mut tls_stream, &req, &mut bytes_sent)
+                .await?;
+
+            let codec = HttpCodec::new(self.node().span().clone(), HttpMsg::Response);
+            match Framed::new(&mut tls_stream, codec).try_next().await? {
+                Some(response) => Ok(HoldEnd::Responded(response.status)),
+                None => Ok(HoldEnd::Closed),
+            }
+        })
+        .await;
+
+        let end = match end {
+            Ok(Ok(end)) => end,
+            Ok(Err(e)) if is_closed_by_peer(&e) => HoldEnd::Closed,
+            Ok(Err(e)) => return Err(e),
+            Err(_) => HoldEnd::TimeLimit,
+        };
+
+        Ok(HeldHandshake {
+            held_for: start.elapsed(),
+            bytes_sent,
+            end,
+        })
+    }
+}
+
+// Whether the error means the peer closed or reset the connection.
+fn is_closed_by_peer(error: &io::Error) -> bool {
+    matches!(
+        error.kind(),
+        io::ErrorKind::ConnectionReset | io::ErrorKind::BrokenPipe | io::ErrorKind::UnexpectedEof
+    )
+}
+
 #[async_trait::async_trait]
 impl Handshake for InnerNode {
     async fn perform_handshake(&self, mut conn: Connection) -> io::Result<Connection> {
@@ -206,80 +794,37 @@ impl Handshake for InnerNode {
 
         let tls_stream = match own_conn_side {
             ConnectionSide::Initiator => {
-                let ssl = self
-                    .tls
-                    .connector
-                    .configure()
-                    .unwrap()
-                    .into_ssl("domain") // is SNI and hostname verification enabled?
-                    .unwrap();
-                let mut tls_stream = SslStream::new(ssl, stream).unwrap();
-
-                Pin::new(&mut tls_stream).connect().await.map_err(|e| {
-                    error!(parent: self.node().span(), "TLS handshake error: {e}");
-                    io::ErrorKind::InvalidData
-                })?;
+                let mut tls_stream = self.connect_tls(stream).await?;
 
                 // get the shared value based on the TLS handshake
-                let mut shared_value = get_shared_value(&tls_stream)?;
-
-                let public_key = &mut self.crypto.public_key.serialize().clone();
-                // introduce intentional errors into handshake if needed
-                if hs_cfg.bitflip_shared_val {
-                    randomly_flip_bit(&mut shared_value);
-                }
-                if hs_cfg.bitflip_pub_key {
-                    randomly_flip_bit(public_key.as_mut_slice());
-                }
+                let shared_value = get_shared_value(&tls_stream)?;
+                let req = self.handshake_request(hs_cfg, shared_value.clone());
 
-                // base58-encode the public key and create the session signature
-                let base58_pk = encode_base58(NodeType::Public, public_key);
-                let sig = create_session_signature(&self.crypto, &shared_value);
-
-                // prepare the HTTP request message
-                let mut req = Vec::new();
-                let mut req_header = |mut header: String| {
-                    // Append `\r\n' to every header.
-                    header.push_str("\r\n");
-                    req.extend_from_slice(header.as_bytes());
-                };
-
-                req_header("GET / HTTP/1.1".into());
-                req_header(format!("User-Agent: {}", hs_cfg.http_ident));
-                req_header(format!("Upgrade: {}", hs_cfg.http_upgrade_req));
-                req_header(format!("Connection: {}", hs_cfg.http_connection));
-                req_header(format!("Connect-As: {}", hs_cfg.http_connect_as));
-                if let Some(ref crawl) = hs_cfg.http_crawl {
-                    req_header(format!("Crawl: {crawl}"))
-                };
-                req_header(format!("X-Protocol-Ctl: {}", hs_cfg.http_x_protocol_ctl));
-                if let Some(ref time) = hs_cfg.http_network_time {
-                    req_header(format!("Network-Time: {time}"))
-                };
-                req_header(format!("Public-Key: {base58_pk}"));
-                req_header(format!("Session-Signature: {sig}"));
-                if let Some(ref ledger) = hs_cfg.http_closed_ledger {
-                    req_header(format!("Closed-Ledger: {ledger}"))
-                };
-                if let Some(ref ledger) = hs_cfg.http_prev_ledger {
-                    req_header(format!("Previous-Ledger: {ledger}"))
-                };
-                if let Some(ref header) = hs_cfg.http_unexpected_extra_field_and_value {
-                    req_header(header.clone())
-                };
-                req_header("".into()); // An HTTP header ends with '\r\n'
+                // send the handshake HTTP request message
+                trace!(parent: self.node().span(), "sending a request to {addr}: {req:?}");
+                hs_cfg
+                    .http_request_pacing
+                    .write(&mut tls_stream, &req, &mut 0)
+                    .await?;
 
-                // use the HTTP codec to read/write the (post-TLS) handshake messages
-                let req = Bytes::from(req);
+                // use the HTTP codec to read the (post-TLS) handshake response
                 let codec = HttpCodec::new(self.node().span().clone(), HttpMsg::Response);
                 let mut framed = Framed::new(&mut tls_stream, codec);
 
-                // send the handshake HTTP request message
-                trace!(parent: self.node().span(), "sending a request to {addr}: {req:?}");
-                framed.send(req).await?;
-
-                // read the HTTP request message (there should only be headers)
-                let _ = framed.try_next().await?.ok_or(io::ErrorKind::InvalidData)?;
+                // read the HTTP response message (there should only be headers)
+                let response = framed.try_next().await?.ok_or(io::ErrorKind::InvalidData)?;
+                if response.status != Some(101) {
+                    let rejection = HandshakeRejected::new(response);
+                    error!(parent: self.node().span(), "{addr} rejected the handshake: {rejection}");
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, rejection));
+                }
+                let mut info = HandshakeInfo::verify(response, "Server", &shared_value)
+                    .map_err(|e| {
+                        error!(parent: self.node().span(), "invalid handshake response from {addr}: {e}");
+                        e
+                    })?;
+                info.protocol_version = info.header("Upgrade").and_then(|v| v.parse().ok());
+                self.peer_handshakes.lock().unwrap().insert(addr, info);
 
                 tls_stream
             }
@@ -294,77 +839,214 @@ impl Handshake for InnerNode {
 
                 // get the shared value based on the TLS handshake
                 let mut shared_value = get_shared_value(&tls_stream)?;
+                let peer_shared_value = shared_value.clone();
 
                 // use the HTTP codec to read/write the (post-TLS) handshake messages
                 let codec = HttpCodec::new(self.node().span().clone(), HttpMsg::Request);
                 let mut framed = Framed::new(&mut tls_stream, codec);
 
                 // read the HTTP request message (there should only be headers)
-                let request_body = framed.try_next().await?.ok_or(io::ErrorKind::InvalidData)?;
-                if !request_body.is_empty() {
-                    warn!(parent: self.node().span(), "trailing bytes in the handshake request from {addr}: {request_body:?}");
+                let request = framed.try_next().await?.ok_or(io::ErrorKind::InvalidData)?;
+                if !request.body.is_empty() {
+                    warn!(parent: self.node().span(), "trailing bytes in the handshake request from {addr}: {:?}", request.body);
                 }
+                let mut info = HandshakeInfo::verify(request, "User-Agent", &peer_shared_value)
+                    .map_err(|e| {
+                        error!(parent: self.node().span(), "invalid handshake request from {addr}: {e}");
+                        e
+                    })?;
+                let offered = parse_versions(info.header("Upgrade").unwrap_or_default());
+                info.protocol_version = negotiate(&hs_cfg.protocol_versions, &offered);
+                let upgrade = match (&hs_cfg.http_upgrade_rsp, info.protocol_version) {
+                    (Some(upgrade), _) => upgrade.clone(),
+                    (None, Some(version)) => version.to_string(),
+                    (None, None) => {
+                        error!(parent: self.node().span(), "no common protocol version with {addr}, offered: {offered:?}");
+                        return Err(io::ErrorKind::InvalidData.into());
+                    }
+                };
 
-                let public_key = &mut self.crypto.public_key.serialize().clone();
+                let public_key = &mut self.crypto.key.public_key();
                 // introduce intentional errors into handshake if needed
                 if hs_cfg.bitflip_shared_val {
-                    randomly_flip_bit(&mut shared_value);
+                    randomly_flip_bit(&mut shared_value, &hs_cfg.bitflip_rng);
                 }
                 if hs_cfg.bitflip_pub_key {
-                    randomly_flip_bit(public_key.as_mut_slice());
+                    randomly_flip_bit(public_key.as_mut_slice(), &hs_cfg.bitflip_rng);
                 }
                 // base58-encode the public key and create the session signature
-                let base58_pk = encode_base58(NodeType::Public, public_key);
-                let sig = create_session_signature(&self.crypto, &shared_value);
+                let base58_pk = encode_node_public_key(public_key);
+                let sig = hs_cfg.session_signature.create(&self.crypto, &shared_value);
 
                 // prepare the response
-                let mut rsp = Vec::new();
-                let mut rsp_header = |mut header: String| {
-                    header.push_str("\r\n");
-                    rsp.extend_from_slice(header.as_bytes());
-                };
-
-                rsp_header("HTTP/1.1 101 Switching Protocols".into());
-                rsp_header(format!("Connection: {}", hs_cfg.http_connection));
-                rsp_header(format!("Upgrade: {}", hs_cfg.http_upgrade_rsp));
-                rsp_header(format!("Connect-As: {}", hs_cfg.http_connect_as));
-                rsp_header(format!("Server: {}", hs_cfg.http_ident));
+                let mut headers = vec![
+                    format!("Connection: {}", hs_cfg.http_connection),
+                    format!("Upgrade: {upgrade}"),
+                    format!("Connect-As: {}", hs_cfg.http_connect_as),
+                    format!("Server: {}", hs_cfg.http_ident),
+                ];
                 if let Some(ref crawl) = hs_cfg.http_crawl {
-                    rsp_header(format!("Crawl: {crawl}"))
+                    headers.push(format!("Crawl: {crawl}"));
                 };
-                rsp_header(format!("X-Protocol-Ctl: {}", hs_cfg.http_x_protocol_ctl));
-                if let Some(ref time) = hs_cfg.http_network_time {
-                    rsp_header(format!("Network-Time: {time}"))
+                headers.push(format!("X-Protocol-Ctl: {}", hs_cfg.http_x_protocol_ctl));
+                if let Some(time) = hs_cfg.network_time() {
+                    headers.push(format!("Network-Time: {time}"));
                 };
-                rsp_header(format!("Public-Key: {base58_pk}"));
-                rsp_header(format!("Session-Signature: {sig}"));
+                if let Some(ref network_id) = hs_cfg.http_network_id {
+                    headers.push(format!("Network-ID: {network_id}"));
+                };
+                headers.push(format!("Public-Key: {base58_pk}"));
+                headers.push(format!("Session-Signature: {sig}"));
                 if let Some(ref ledger) = hs_cfg.http_closed_ledger {
-                    rsp_header(format!("Closed-Ledger: {ledger}"))
+                    headers.push(format!("Closed-Ledger: {ledger}"));
                 };
                 if let Some(ref ledger) = hs_cfg.http_prev_ledger {
-                    rsp_header(format!("Previous-Ledger: {ledger}"))
-                };
-                if let Some(ref header) = hs_cfg.http_unexpected_extra_field_and_value {
-                    rsp_header(header.clone())
+                    headers.push(format!("Previous-Ledger: {ledger}"));
                 };
-                rsp_header("".into()); // An HTTP header ends with '\r\n'
+                let rsp =
+                    hs_cfg.build_http_message("HTTP/1.1 101 Switching Protocols".into(), headers);
 
                 // send the handshake HTTP response message
-                let rsp = Bytes::from(rsp);
                 trace!(parent: self.node().span(), "responding to {addr} with {rsp:?}");
                 framed.send(rsp).await?;
+                self.peer_handshakes.lock().unwrap().insert(addr, info);
 
                 tls_stream
             }
         };
 
         self.return_stream(&mut conn, tls_stream);
+        self.notify(ConnectionEvent::Connected(addr));
 
         Ok(conn)
     }
 }
 
-fn randomly_flip_bit(arr: &mut [u8]) {
-    let idx = thread_rng().gen_range(0..arr.len());
-    arr[idx] ^= 1 << thread_rng().gen_range(0..8);
+fn randomly_flip_bit(arr: &mut [u8], rng: &Mutex<ChaCha8Rng>) {
+    let mut rng = rng.lock().unwrap();
+    let idx = rng.gen_range(0..arr.len());
+    arr[idx] ^= 1 << rng.gen_range(0..8);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn features_are_composed_and_parsed() {
+        assert_eq!(
+            ProtocolFeatures::default().to_string(),
+            "txrr=1;ledgerreplay=1"
+        );
+        assert_eq!(
+            ProtocolFeatures::ALL.to_string(),
+            "compr=lz4;vprr=1;txrr=1;ledgerreplay=1"
+        );
+        assert_eq!(ProtocolFeatures::NONE.to_string(), "");
+
+        // rippled ends the field with a delimiter.
+        assert_eq!(
+            ProtocolFeatures::parse("ledgerreplay=1;txrr=1;"),
+            ProtocolFeatures::default()
+        );
+        assert_eq!(
+            ProtocolFeatures::parse("compr=none,lz4;vprr=0;unknown=1"),
+            ProtocolFeatures {
+                compression: true,
+                ..ProtocolFeatures::NONE
+            }
+        );
+    }
+
+    #[test]
+    fn header_edits_are_applied_in_order() {
+        let cfg = HandshakeCfg {
+            http_unexpected_extra_field_and_value: Some("Extra: 1".into()),
+            http_header_edits: vec![
+                HeaderEdit::Remove("public-key".into()),
+                HeaderEdit::Duplicate("Connect-As".into()),
+                HeaderEdit::Append("Public-Key".into(), "n9".into()),
+                HeaderEdit::AppendRaw("no colon".into()),
+            ],
+            ..Default::default()
+        };
+        let message = cfg.build_http_message(
+            "GET / HTTP/1.1".into(),
+            vec![
+                "Connect-As: Peer".into(),
+                "Public-Key: n9K".into(),
+                "Crawl: public".into(),
+            ],
+        );
+
+        assert_eq!(
+            message,
+            "GET / HTTP/1.1\r\nConnect-As: Peer\r\nConnect-As: Peer\r\nCrawl: public\r\n\
+             Extra: 1\r\nPublic-Key: n9\r\nno colon\r\n\r\n"
+        );
+    }
+
+    #[test]
+    fn replayed_signature_is_captured_once() {
+        let crypto = Crypto {
+            key: ValidatorKey::generate(KeyType::Secp256k1),
+        };
+        let replayed = SessionSignature::replayed();
+        let first = replayed.clone().create(&crypto, &[1; 32]);
+        assert_eq!(first, SessionSignature::Valid.create(&crypto, &[1; 32]));
+        assert_eq!(replayed.create(&crypto, &[2; 32]), first);
+
+        let forged = SessionSignature::foreign_key().create(&crypto, &[1; 32]);
+        let public_key = encode_node_public_key(&crypto.key.public_key());
+        assert!(verify_session_signature(&public_key, &first, &[1; 32]).is_ok());
+        assert!(verify_session_signature(&public_key, &forged, &[1; 32]).is_err());
+    }
+
+    #[test]
+    fn redirect_lists_peer_ips() {
+        let rejection = |status, body: &str| HandshakeRejected {
+            status,
+            reason: String::new(),
+            headers: vec![],
+            body: body.to_owned(),
+        };
+
+        let redirect = rejection(
+            503,
+            r#"{"peer-ips": ["1.2.3.4:51235", "[::1]:2459", "invalid"]}"#,
+        );
+        assert_eq!(
+            redirect.peer_ips(),
+            Some(vec![
+                "1.2.3.4:51235".parse().unwrap(),
+                "[::1]:2459".parse().unwrap()
+            ])
+        );
+        assert_eq!(
+            rejection(503, r#"{"peer-ips": []}"#).peer_ips(),
+            Some(vec![])
+        );
+        assert_eq!(rejection(503, "").peer_ips(), None);
+        assert_eq!(rejection(400, r#"{"peer-ips": []}"#).peer_ips(), None);
+    }
+
+    #[tokio::test]
+    async fn request_is_paced() {
+        let req = b"GET / HTTP/1.1\r\nConnect-As: Peer\r\n\r\n";
+
+        for (pacing, expected) in [
+            (RequestPacing::Whole, &req[..]),
+            (RequestPacing::Trickle(Duration::ZERO), &req[..]),
+            (RequestPacing::Unterminated, &req[..req.len() - 2]),
+        ] {
+            let mut written = Vec::new();
+            let mut bytes_sent = 0;
+            pacing
+                .write(&mut written, req, &mut bytes_sent)
+                .await
+                .unwrap();
+            assert_eq!(written, expected);
+            assert_eq!(bytes_sent, expected.len());
+        }
+    }
 }
@@ -0,0 +1,122 @@
+//! Ledger headers, as sent in ledger replay deltas and ledger base data.
+//!
+//! A header is serialized as the ledger's sequence, its total drops, the parent, transaction and
+//! state tree hashes, the close times, the close time resolution and the close flags, all
+//! big-endian. The ledger's hash is the hash of the header.
+
+use thiserror::Error;
+
+use crate::tools::validator::sha512_half;
+
+/// The size of a serialized ledger header, without the ledger's hash.
+pub const LEDGER_HEADER_SIZE: usize = 118;
+
+/// The prefix used when hashing ledger headers.
+pub const LEDGER_PREFIX: &[u8] = b"LWR\x00";
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum LedgerHeaderError {
+    #[error("the ledger header is {0} bytes long, expected {LEDGER_HEADER_SIZE}")]
+    InvalidLength(usize),
+}
+
+/// The fields of a ledger header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LedgerHeader {
+    pub seq: u32,
+    /// The total XRP in existence, in drops.
+    pub drops: u64,
+    pub parent_hash: [u8; 32],
+    /// The root hash of the ledger's transaction tree.
+    pub tx_hash: [u8; 32],
+    /// The root hash of the ledger's state tree.
+    pub account_hash: [u8; 32],
+    /// The parent's close time, in seconds since the Ripple epoch.
+    pub parent_close_time: u32,
+    /// The close time, in seconds since the Ripple epoch.
+    pub close_time: u32,
+    pub close_time_resolution: u8,
+    pub close_flags: u8,
+}
+
+impl LedgerHeader {
+    pub fn parse(bytes: &[u8]) -> Result<Self, LedgerHeaderError> {
+        if bytes.len() != LEDGER_HEADER_SIZE {
+            return Err(LedgerHeaderError::InvalidLength(bytes.len()));
+        }
+        let (seq, rest) = bytes.split_at(4);
+        let (drops, rest) = rest.split_at(8);
+        let (parent_hash, rest) = rest.split_at(32);
+        let (tx_hash, rest) = rest.split_at(32);
+        let (account_hash, rest) = rest.split_at(32);
+        let (parent_close_time, rest) = rest.split_at(4);
+        let (close_time, rest) = rest.split_at(4);
+
+        // The slices have the right lengths, the header's length was checked.
+        Ok(Self {
+            seq: u32::from_be_bytes(seq.try_into().unwrap()),
+            drops: u64::from_be_bytes(drops.try_into().unwrap()),
+            parent_hash: parent_hash.try_into().unwrap(),
+            tx_hash: tx_hash.try_into().unwrap(),
+            account_hash: account_hash.try_into().unwrap(),
+            parent_close_time: u32::from_be_bytes(parent_close_time.try_into().unwrap()),
+            close_time: u32::from_be_bytes(close_time.try_into().unwrap()),
+            close_time_resolution: rest[0],
+            close_flags: rest[1],
+        })
+    }
+
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(LEDGER_HEADER_SIZE);
+        bytes.extend_from_slice(&self.seq.to_be_bytes());
+        bytes.extend_from_slice(&self.drops.to_be_bytes());
+        bytes.extend_from_slice(&self.parent_hash);
+        bytes.extend_from_slice(&self.tx_hash);
+        bytes.extend_from_slice(&self.account_hash);
+        bytes.extend_from_slice(&self.parent_close_time.to_be_bytes());
+        bytes.extend_from_slice(&self.close_time.to_be_bytes());
+        bytes.push(self.close_time_resolution);
+        bytes.push(self.close_flags);
+        bytes
+    }
+
+    /// Returns the ledger's hash.
+    pub fn hash(&self) -> [u8; 32] {
+        let mut buf = LEDGER_PREFIX.to_vec();
+        buf.extend_from_slice(&self.serialize());
+        sha512_half(&buf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn header_survives_serialization() {
+        let header = LedgerHeader {
+            seq: 7,
+            drops: 100_000_000_000_000_000,
+            parent_hash: [1; 32],
+            tx_hash: [2; 32],
+            account_hash: [3; 32],
+            parent_close_time: 740_000_000,
+            close_time: 740_000_010,
+            close_time_resolution: 10,
+            close_flags: 0,
+        };
+        let bytes = header.serialize();
+        assert_eq!(bytes.len(), LEDGER_HEADER_SIZE);
+        assert_eq!(LedgerHeader::parse(&bytes), Ok(header.clone()));
+
+        let modified = LedgerHeader {
+            close_flags: 1,
+            ..header.clone()
+        };
+        assert_ne!(modified.hash(), header.hash());
+        assert_eq!(
+            LedgerHeader::parse(&bytes[1..]),
+            Err(LedgerHeaderError::InvalidLength(LEDGER_HEADER_SIZE - 1))
+        );
+    }
+}
@@ -0,0 +1,155 @@
+//! Validator manifests, binding a validator's ephemeral signing key to its master key.
+//!
+//! A manifest is a serialized object signed by both keys; revocations carry the maximum sequence
+//! and only the master signature.
+
+use thiserror::Error;
+
+use crate::{
+    protocol::stobject::{StObject, StObjectError, ST_BLOB},
+    tools::validator::verify,
+};
+
+// Field codes of the manifest fields, unique within their serialized type.
+pub const SF_SEQUENCE: u8 = 4;
+pub const SF_PUBLIC_KEY: u8 = 1;
+pub const SF_SIGNING_PUB_KEY: u8 = 3;
+pub const SF_SIGNATURE: u8 = 6;
+pub const SF_DOMAIN: u8 = 7;
+pub const SF_MASTER_SIGNATURE: u8 = 18;
+
+/// The prefix used when hashing manifests for signing.
+pub const MANIFEST_PREFIX: &[u8] = b"MAN\x00";
+
+/// The sequence of a manifest revoking the master key.
+pub const REVOKED_SEQUENCE: u32 = u32::MAX;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ManifestError {
+    #[error("invalid manifest object: {0}")]
+    Object(#[from] StObjectError),
+
+    #[error("the manifest has no {0}")]
+    MissingField(&'static str),
+
+    #[error("invalid master signature")]
+    InvalidMasterSignature,
+
+    #[error("invalid signature")]
+    InvalidSignature,
+}
+
+/// The fields of a decoded manifest.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Manifest {
+    pub sequence: u32,
+    /// The validator's master public key.
+    pub public_key: Vec<u8>,
+    /// The key signing on behalf of the master key, missing in revocations.
+    pub signing_pub_key: Option<Vec<u8>>,
+    /// The domain the validator claims, if any.
+    pub domain: Option<Vec<u8>>,
+    pub master_signature: Vec<u8>,
+    /// The signature made by the signing key, missing in revocations.
+    pub signature: Option<Vec<u8>>,
+    /// The serialized manifest without its signatures, as signed by both keys.
+    signed_data: Vec<u8>,
+}
+
+impl Manifest {
+    /// Decodes a serialized manifest and verifies both of its signatures.
+    pub fn parse(manifest: &[u8]) -> Result<Self, ManifestError> {
+        let manifest = Self::decode(manifest)?;
+        manifest.verify()?;
+        Ok(manifest)
+    }
+
+    /// Decodes a serialized manifest, without checking its signatures.
+    pub fn decode(manifest: &[u8]) -> Result<Self, ManifestError> {
+        let manifest = StObject::deserialize(manifest)?;
+        let blob = |field_code| manifest.blob(field_code).map(<[u8]>::to_vec);
+
+        Ok(Self {
+            sequence: manifest
+                .u32(SF_SEQUENCE)
+                .ok_or(ManifestError::MissingField("sequence"))?,
+            public_key: blob(SF_PUBLIC_KEY).ok_or(ManifestError::MissingField("public key"))?,
+            signing_pub_key: blob(SF_SIGNING_PUB_KEY),
+            domain: blob(SF_DOMAIN),
+            master_signature: blob(SF_MASTER_SIGNATURE)
+                .ok_or(ManifestError::MissingField("master signature"))?,
+            signature: blob(SF_SIGNATURE),
+            signed_data: manifest
+                .serialize_without(&[(ST_BLOB, SF_SIGNATURE), (ST_BLOB, SF_MASTER_SIGNATURE)]),
+        })
+    }
+
+    /// Checks the master signature and, unless the manifest is a revocation, the signature of
+    /// the signing key.
+    pub fn verify(&self) -> Result<(), ManifestError> {
+        let mut message = MANIFEST_PREFIX.to_vec();
+        message.extend_from_slice(&self.signed_data);
+
+        if !verify(&self.public_key, &message, &self.master_signature) {
+            return Err(ManifestError::InvalidMasterSignature);
+        }
+
+        if self.is_revocation() {
+            return Ok(());
+        }
+
+        let signing_pub_key = self
+            .signing_pub_key
+            .as_ref()
+            .ok_or(ManifestError::MissingField("signing public key"))?;
+        let signature = self
+            .signature
+            .as_ref()
+            .ok_or(ManifestError::MissingField("signature"))?;
+        if !verify(signing_pub_key, &message, signature) {
+            return Err(ManifestError::InvalidSignature);
+        }
+
+        Ok(())
+    }
+
+    /// Returns `true` if the manifest permanently revokes the master key.
+    pub fn is_revocation(&self) -> bool {
+        self.sequence == REVOKED_SEQUENCE
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        protocol::stobject::Field,
+        tools::validator::{create_manifest, KeyType, ValidatorKey},
+    };
+
+    #[test]
+    fn created_manifest_is_valid() {
+        let master = ValidatorKey::generate(KeyType::Ed25519);
+        let signing = ValidatorKey::generate(KeyType::Secp256k1);
+
+        let manifest = Manifest::parse(&create_manifest(3, &master, &signing)).unwrap();
+        assert_eq!(manifest.sequence, 3);
+        assert_eq!(manifest.public_key, master.public_key());
+        assert_eq!(manifest.signing_pub_key, Some(signing.public_key()));
+        assert!(!manifest.is_revocation());
+    }
+
+    #[test]
+    fn tampered_manifest_is_rejected() {
+        let master = ValidatorKey::generate(KeyType::Secp256k1);
+        let signing = ValidatorKey::generate(KeyType::Secp256k1);
+
+        let mut manifest = StObject::deserialize(&create_manifest(1, &master, &signing)).unwrap();
+        manifest.set(Field::uint32(SF_SEQUENCE, 2));
+
+        assert_eq!(
+            Manifest::parse(&manifest.serialize()),
+            Err(ManifestError::InvalidMasterSignature)
+        );
+    }
+}
@@ -2,6 +2,12 @@
 
 pub mod codecs;
 pub mod handshake;
+pub mod ledger;
+pub mod manifest;
 pub mod proto;
 pub mod reading;
+pub mod shamap;
+pub mod stobject;
+pub mod unl;
+pub mod version;
 pub mod writing;
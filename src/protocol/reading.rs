@@ -1,10 +1,17 @@
 use std::{io, net::SocketAddr};
 
-use pea2pea::{protocols::Reading, ConnectionSide, Pea2Pea};
+use pea2pea::{
+    protocols::{Reading, Writing},
+    ConnectionSide, Pea2Pea,
+};
 use tracing::*;
 
 use crate::{
-    protocol::codecs::message::{BinaryMessage, MessageCodec},
+    protocol::{
+        codecs::message::{BinaryMessage, MessageCodec, Payload},
+        proto::{tm_ping::PingType, TmPing},
+        writing::MessageOrBytes,
+    },
     tools::inner_node::InnerNode,
 };
 
@@ -15,10 +22,32 @@ impl Reading for InnerNode {
 
     fn codec(&self, _addr: SocketAddr, _side: ConnectionSide) -> Self::Codec {
         Self::Codec::new(self.node().span().clone())
+            .with_allowed_types(self.allowed_message_types.clone())
     }
 
     async fn process_message(&self, source: SocketAddr, message: Self::Message) -> io::Result<()> {
         debug!(parent: self.node().span(), "read a message from {}: {:?}", source, message.payload);
+        self.message_stats.lock().unwrap().record(
+            source,
+            message.header.message_type(),
+            message.header.total_wire_size(),
+        );
+        if let Some(peer_status) = &self.peer_status {
+            peer_status.lock().unwrap().record(source, &message.payload);
+        }
+        if self.keepalive.is_some() {
+            if let Payload::TmPing(ping) = &message.payload {
+                if ping.r#type == PingType::PtPing as i32 {
+                    let pong = TmPing {
+                        r#type: PingType::PtPong as i32,
+                        ..ping.clone()
+                    };
+                    // The reply isn't awaited, a failure means the connection is going away.
+                    let _ = self.unicast(source, MessageOrBytes::Payload(Payload::TmPing(pong)));
+                }
+                return Ok(());
+            }
+        }
         debug!(
             parent: self.node().span(),
             "sending the message to the node's inbound queue"
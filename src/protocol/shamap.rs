@@ -0,0 +1,825 @@
+//! SHAMaps, the radix-16 Merkle trees holding a ledger's state objects and transactions.
+//!
+//! An inner node has up to 16 children, the branch taken at a depth being the key's nibble at
+//! that depth, and is hashed over the hashes of all of its branches, empty ones being zero. A leaf
+//! holds an item and is hashed over the item, and its key for most kinds of leaves. The roots'
+//! hashes are the state and transaction tree hashes of the ledger's header.
+//!
+//! Peers exchange the nodes in their wire format: the node's contents followed by a byte telling
+//! the type of the node, see [ShaMapNode::from_wire]. The node store keeps them in the prefix
+//! format instead, the hash prefix followed by the node's contents, which is also how the objects
+//! are exchanged by hash, see [ShaMapNode::from_prefixed].
+//!
+//! A node's position in a tree is identified by a [ShaMapNodeId], the nodes sent in
+//! [TmLedgerData](crate::protocol::proto::TmLedgerData) can be put back together as a
+//! [PartialShaMap], checking every node against its parent.
+
+use std::collections::BTreeMap;
+
+use thiserror::Error;
+
+use crate::{
+    protocol::{
+        ledger::{LedgerHeader, LedgerHeaderError},
+        proto::{TmLedgerMapType, TmLedgerNode, TmProofPathResponse, TmReplyError},
+    },
+    tools::{tx::TX_ID_PREFIX, validator::sha512_half},
+};
+
+/// The prefix used when hashing inner nodes.
+pub const INNER_NODE_PREFIX: &[u8] = b"MIN\x00";
+/// The prefix used when hashing state object leaves.
+pub const LEAF_NODE_PREFIX: &[u8] = b"MLN\x00";
+/// The prefix used when hashing transaction leaves with metadata.
+pub const TX_NODE_PREFIX: &[u8] = b"SND\x00";
+
+/// The number of children of an inner node.
+pub const BRANCH_COUNT: usize = 16;
+/// The depth of the deepest leaves, there's a level per nibble of the keys.
+pub const MAX_DEPTH: usize = 64;
+
+const HASH_LEN: usize = 32;
+/// The size of a branch of a compressed inner node: the child's hash and its branch.
+const COMPRESSED_BRANCH_LEN: usize = HASH_LEN + 1;
+/// Inner nodes with fewer children are sent compressed.
+const MIN_FULL_BRANCHES: usize = 12;
+
+// The wire types, trailing the nodes.
+const WIRE_TYPE_TRANSACTION: u8 = 0;
+const WIRE_TYPE_ACCOUNT_STATE: u8 = 1;
+const WIRE_TYPE_INNER: u8 = 2;
+const WIRE_TYPE_COMPRESSED_INNER: u8 = 3;
+const WIRE_TYPE_TRANSACTION_WITH_META: u8 = 4;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ShaMapError {
+    #[error("the node is empty")]
+    EmptyNode,
+
+    #[error("unknown wire type {0}")]
+    UnknownWireType(u8),
+
+    #[error("invalid {kind} node of {len} bytes")]
+    InvalidLength { kind: &'static str, len: usize },
+
+    #[error("branch {0} is out of range")]
+    InvalidBranch(u8),
+
+    #[error("the peer replied with {0:?}")]
+    Refused(TmReplyError),
+
+    #[error("the reply has no ledger header")]
+    MissingHeader,
+
+    #[error("invalid ledger header: {0}")]
+    Header(#[from] LedgerHeaderError),
+
+    #[error("the ledger header doesn't hash to the ledger's hash")]
+    LedgerHashMismatch,
+
+    #[error("the proof path is empty")]
+    EmptyPath,
+
+    #[error("the proof path has {0} nodes, more than the tree's depth")]
+    PathTooLong(usize),
+
+    #[error("the node at depth {0} doesn't hash to its parent's branch")]
+    HashMismatch(usize),
+
+    #[error("the proof path ends at depth {0}, before reaching a leaf")]
+    MissingLeaf(usize),
+
+    #[error("the proof path continues past the leaf at depth {0}")]
+    TrailingNodes(usize),
+
+    #[error("the leaf holds another key")]
+    KeyMismatch,
+
+    #[error("unknown hash prefix {0:02x?}")]
+    UnknownPrefix([u8; 4]),
+
+    #[error("invalid node ID {0}")]
+    InvalidNodeId(String),
+
+    #[error("the node has no node ID")]
+    MissingNodeId,
+
+    #[error("the parent of node {0} is unknown")]
+    MissingParent(ShaMapNodeId),
+}
+
+/// The position of a node in a SHAMap: its depth and the path to it, i.e. the nibbles of the keys
+/// below it up to the depth, the rest being zero.
+///
+/// The depth is the first field, so the IDs are ordered by depth first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ShaMapNodeId {
+    pub depth: u8,
+    pub id: [u8; 32],
+}
+
+impl std::fmt::Display for ShaMapNodeId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.depth, hex::encode_upper(self.id))
+    }
+}
+
+impl ShaMapNodeId {
+    /// The ID of the root node.
+    pub const ROOT: Self = Self {
+        depth: 0,
+        id: [0; 32],
+    };
+
+    /// Returns the ID of the node at the depth on the path to the key.
+    pub fn for_key(key: &[u8; 32], depth: u8) -> Self {
+        let depth = depth.min(MAX_DEPTH as u8);
+        let mut id = [0u8; 32];
+        let full_bytes = usize::from(depth / 2);
+        id[..full_bytes].copy_from_slice(&key[..full_bytes]);
+        if depth % 2 == 1 {
+            id[full_bytes] = key[full_bytes] & 0xf0;
+        }
+        Self { depth, id }
+    }
+
+    /// Decodes the ID as sent on the wire: the path followed by the depth.
+    pub fn from_wire(bytes: &[u8]) -> Result<Self, ShaMapError> {
+        let invalid = || ShaMapError::InvalidNodeId(hex::encode_upper(bytes));
+        let (&depth, id) = bytes.split_last().ok_or_else(invalid)?;
+        let id: [u8; 32] = id.try_into().map_err(|_| invalid())?;
+        if usize::from(depth) > MAX_DEPTH {
+            return Err(invalid());
+        }
+
+        // The path's nibbles below the depth are zero.
+        let node_id = Self::for_key(&id, depth);
+        if node_id.id != id {
+            return Err(invalid());
+        }
+        Ok(node_id)
+    }
+
+    pub fn to_wire(&self) -> Vec<u8> {
+        [self.id.as_slice(), &[self.depth]].concat()
+    }
+
+    /// Returns the ID of the child on the branch.
+    pub fn child(&self, branch: usize) -> Self {
+        let mut id = self.id;
+        let depth = usize::from(self.depth);
+        let nibble = branch as u8 & 0x0f;
+        id[depth / 2] |= match depth % 2 {
+            0 => nibble << 4,
+            _ => nibble,
+        };
+        Self {
+            depth: self.depth + 1,
+            id,
+        }
+    }
+
+    /// Returns the ID of the parent, `None` for the root.
+    pub fn parent(&self) -> Option<(Self, usize)> {
+        let depth = self.depth.checked_sub(1)?;
+        Some((
+            Self::for_key(&self.id, depth),
+            select_branch(&self.id, usize::from(depth)),
+        ))
+    }
+}
+
+/// A node of a SHAMap.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ShaMapNode {
+    /// The hashes of the children by branch, zero for empty branches.
+    Inner {
+        branches: Box<[[u8; 32]; BRANCH_COUNT]>,
+    },
+    /// A serialized state object and its key.
+    AccountState { data: Vec<u8>, key: [u8; 32] },
+    /// A serialized transaction, keyed by its ID.
+    Transaction { data: Vec<u8> },
+    /// A serialized transaction followed by its metadata, keyed by the transaction's ID.
+    TransactionWithMeta { data: Vec<u8>, key: [u8; 32] },
+}
+
+impl ShaMapNode {
+    /// Decodes a node in the wire format, as sent in proof paths and ledger data.
+    pub fn from_wire(bytes: &[u8]) -> Result<Self, ShaMapError> {
+        let (&wire_type, body) = bytes.split_last().ok_or(ShaMapError::EmptyNode)?;
+        let invalid_length = |kind| ShaMapError::InvalidLength {
+            kind,
+            len: bytes.len(),
+        };
+
+        match wire_type {
+            WIRE_TYPE_TRANSACTION => Ok(Self::Transaction {
+                data: body.to_vec(),
+            }),
+            WIRE_TYPE_ACCOUNT_STATE | WIRE_TYPE_TRANSACTION_WITH_META => {
+                if body.len() < HASH_LEN {
+                    return Err(invalid_length("leaf"));
+                }
+                let (data, key) = body.split_at(body.len() - HASH_LEN);
+                let data = data.to_vec();
+                let key = key.try_into().unwrap();
+                Ok(match wire_type {
+                    WIRE_TYPE_ACCOUNT_STATE => Self::AccountState { data, key },
+                    _ => Self::TransactionWithMeta { data, key },
+                })
+            }
+            WIRE_TYPE_INNER => {
+                if body.len() != BRANCH_COUNT * HASH_LEN {
+                    return Err(invalid_length("inner"));
+                }
+                let mut branches = [[0u8; 32]; BRANCH_COUNT];
+                for (branch, hash) in branches.iter_mut().zip(body.chunks_exact(HASH_LEN)) {
+                    branch.copy_from_slice(hash);
+                }
+                Ok(Self::Inner {
+                    branches: Box::new(branches),
+                })
+            }
+            WIRE_TYPE_COMPRESSED_INNER => {
+                if body.len() % COMPRESSED_BRANCH_LEN != 0 {
+                    return Err(invalid_length("compressed inner"));
+                }
+                let mut branches = [[0u8; 32]; BRANCH_COUNT];
+                for entry in body.chunks_exact(COMPRESSED_BRANCH_LEN) {
+                    let (hash, branch) = (&entry[..HASH_LEN], entry[HASH_LEN]);
+                    branches
+                        .get_mut(usize::from(branch))
+                        .ok_or(ShaMapError::InvalidBranch(branch))?
+                        .copy_from_slice(hash);
+                }
+                Ok(Self::Inner {
+                    branches: Box::new(branches),
+                })
+            }
+            wire_type => Err(ShaMapError::UnknownWireType(wire_type)),
+        }
+    }
+
+    /// Decodes a node in the prefix format, as kept by the node store.
+    pub fn from_prefixed(bytes: &[u8]) -> Result<Self, ShaMapError> {
+        if bytes.len() < 4 {
+            return Err(ShaMapError::InvalidLength {
+                kind: "prefixed",
+                len: bytes.len(),
+            });
+        }
+        let (prefix, body) = bytes.split_at(4);
+        let wire_type = match prefix {
+            INNER_NODE_PREFIX => WIRE_TYPE_INNER,
+            LEAF_NODE_PREFIX => WIRE_TYPE_ACCOUNT_STATE,
+            TX_NODE_PREFIX => WIRE_TYPE_TRANSACTION_WITH_META,
+            TX_ID_PREFIX => WIRE_TYPE_TRANSACTION,
+            _ => return Err(ShaMapError::UnknownPrefix(prefix.try_into().unwrap())),
+        };
+        // Apart from the prefix, the contents are laid out as in the full wire format.
+        Self::from_wire(&[body, &[wire_type]].concat())
+    }
+
+    /// Encodes the node in the prefix format, i.e. the data its hash is computed over.
+    pub fn to_prefixed(&self) -> Vec<u8> {
+        match self {
+            Self::Inner { branches } => [INNER_NODE_PREFIX, &branches.concat()].concat(),
+            Self::AccountState { data, key } => [LEAF_NODE_PREFIX, data, key].concat(),
+            Self::Transaction { data } => [TX_ID_PREFIX, data].concat(),
+            Self::TransactionWithMeta { data, key } => [TX_NODE_PREFIX, data, key].concat(),
+        }
+    }
+
+    /// Encodes the node in the wire format, inner nodes with few children are compressed.
+    pub fn to_wire(&self) -> Vec<u8> {
+        match self {
+            Self::Inner { branches } => {
+                let children = branches
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, hash)| **hash != [0; 32])
+                    .collect::<Vec<_>>();
+                if children.len() < MIN_FULL_BRANCHES {
+                    let mut bytes = Vec::with_capacity(children.len() * COMPRESSED_BRANCH_LEN + 1);
+                    for (branch, hash) in children {
+                        bytes.extend_from_slice(hash);
+                        bytes.push(branch as u8);
+                    }
+                    bytes.push(WIRE_TYPE_COMPRESSED_INNER);
+                    bytes
+                } else {
+                    let mut bytes = branches.concat();
+                    bytes.push(WIRE_TYPE_INNER);
+                    bytes
+                }
+            }
+            Self::AccountState { data, key } => {
+                [data.as_slice(), key, &[WIRE_TYPE_ACCOUNT_STATE]].concat()
+            }
+            Self::Transaction { data } => [data.as_slice(), &[WIRE_TYPE_TRANSACTION]].concat(),
+            Self::TransactionWithMeta { data, key } => {
+                [data.as_slice(), key, &[WIRE_TYPE_TRANSACTION_WITH_META]].concat()
+            }
+        }
+    }
+
+    /// Returns the node's hash, as referenced by its parent.
+    pub fn hash(&self) -> [u8; 32] {
+        // Like rippled, an inner node without children hashes to zero.
+        if let Self::Inner { branches } = self {
+            if branches.iter().all(|hash| *hash == [0; 32]) {
+                return [0; 32];
+            }
+        }
+        sha512_half(&self.to_prefixed())
+    }
+
+    pub fn is_inner(&self) -> bool {
+        matches!(self, Self::Inner { .. })
+    }
+
+    /// Returns the hash of the child on the branch, `None` for leaves and empty branches.
+    pub fn child(&self, branch: usize) -> Option<[u8; 32]> {
+        match self {
+            Self::Inner { branches } => branches
+                .get(branch)
+                .filter(|hash| **hash != [0; 32])
+                .copied(),
+            _ => None,
+        }
+    }
+
+    /// Returns the key of a leaf's item, `None` for inner nodes.
+    pub fn key(&self) -> Option<[u8; 32]> {
+        match self {
+            Self::Inner { .. } => None,
+            Self::AccountState { key, .. } | Self::TransactionWithMeta { key, .. } => Some(*key),
+            // The ID of a transaction is the hash of its leaf.
+            Self::Transaction { .. } => Some(self.hash()),
+        }
+    }
+}
+
+/// Decodes a node of a [TmLedgerData](crate::protocol::proto::TmLedgerData) along with its ID.
+///
+/// The nodes of tree requests are in the wire format and carry their IDs, unlike the ledger header
+/// and the roots sent in reply to requests for a ledger's base.
+pub fn parse_ledger_node(node: &TmLedgerNode) -> Result<(ShaMapNodeId, ShaMapNode), ShaMapError> {
+    let id = node.nodeid.as_deref().ok_or(ShaMapError::MissingNodeId)?;
+    Ok((
+        ShaMapNodeId::from_wire(id)?,
+        ShaMapNode::from_wire(&node.nodedata)?,
+    ))
+}
+
+/// Some of the nodes of a SHAMap, each one checked against its parent, down from the root.
+#[derive(Debug, Clone, Default)]
+pub struct PartialShaMap {
+    root_hash: [u8; 32],
+    nodes: BTreeMap<ShaMapNodeId, ShaMapNode>,
+}
+
+impl PartialShaMap {
+    /// Creates an empty tree for the root hash, e.g. the state tree hash of a ledger header.
+    pub fn new(root_hash: [u8; 32]) -> Self {
+        Self {
+            root_hash,
+            nodes: BTreeMap::new(),
+        }
+    }
+
+    pub fn root_hash(&self) -> [u8; 32] {
+        self.root_hash
+    }
+
+    /// Adds the node if it hashes to the root hash, or to its parent's branch.
+    ///
+    /// The parent has to be added first, see [insert_all](Self::insert_all).
+    pub fn insert(&mut self, id: ShaMapNodeId, node: ShaMapNode) -> Result<(), ShaMapError> {
+        let expected_hash = match id.parent() {
+            None => Some(self.root_hash),
+            Some((parent, branch)) => self
+                .nodes
+                .get(&parent)
+                .ok_or(ShaMapError::MissingParent(id))?
+                .child(branch),
+        };
+        if expected_hash != Some(node.hash()) {
+            return Err(ShaMapError::HashMismatch(usize::from(id.depth)));
+        }
+        // The deepest nodes are leaves, the keys have no more nibbles to branch on.
+        if node.is_inner() && usize::from(id.depth) == MAX_DEPTH {
+            return Err(ShaMapError::InvalidNodeId(id.to_string()));
+        }
+
+        self.nodes.insert(id, node);
+        Ok(())
+    }
+
+    /// Adds the nodes, in any order, parents being added before their children.
+    pub fn insert_all(
+        &mut self,
+        nodes: impl IntoIterator<Item = (ShaMapNodeId, ShaMapNode)>,
+    ) -> Result<(), ShaMapError> {
+        let mut nodes = nodes.into_iter().collect::<Vec<_>>();
+        nodes.sort_by_key(|(id, _)| *id);
+        nodes
+            .into_iter()
+            .try_for_each(|(id, node)| self.insert(id, node))
+    }
+
+    pub fn get(&self, id: &ShaMapNodeId) -> Option<&ShaMapNode> {
+        self.nodes.get(id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Iterates over the nodes, by depth.
+    pub fn nodes(&self) -> impl Iterator<Item = (&ShaMapNodeId, &ShaMapNode)> {
+        self.nodes.iter()
+    }
+
+    /// Iterates over the leaves, by depth.
+    pub fn leaves(&self) -> impl Iterator<Item = &ShaMapNode> {
+        self.nodes.values().filter(|node| !node.is_inner())
+    }
+
+    /// Returns the IDs and hashes of the nodes still missing below the added ones, the root's if
+    /// none were added.
+    pub fn missing(&self) -> Vec<(ShaMapNodeId, [u8; 32])> {
+        if self.nodes.is_empty() {
+            // An empty tree has no root.
+            if self.root_hash == [0; 32] {
+                return vec![];
+            }
+            return vec![(ShaMapNodeId::ROOT, self.root_hash)];
+        }
+
+        let mut missing = Vec::new();
+        for (id, node) in &self.nodes {
+            for branch in 0..BRANCH_COUNT {
+                let Some(hash) = node.child(branch) else {
+                    continue;
+                };
+                let child = id.child(branch);
+                if !self.nodes.contains_key(&child) {
+                    missing.push((child, hash));
+                }
+            }
+        }
+        missing
+    }
+
+    /// Returns `true` if all of the tree's nodes were added.
+    pub fn is_complete(&self) -> bool {
+        self.missing().is_empty()
+    }
+}
+
+/// Returns the branch taken at the depth towards the key, i.e. the key's nibble at the depth.
+pub fn select_branch(key: &[u8; 32], depth: usize) -> usize {
+    let byte = key[depth / 2];
+    match depth % 2 {
+        0 => usize::from(byte >> 4),
+        _ => usize::from(byte & 0x0f),
+    }
+}
+
+/// Verifies the path proves the key is in the tree with the root hash and returns the key's leaf.
+///
+/// The path is made of the nodes from the leaf up to the root, in the wire format, as sent in a
+/// [TmProofPathResponse].
+pub fn verify_proof_path(
+    root_hash: &[u8; 32],
+    key: &[u8; 32],
+    path: &[Vec<u8>],
+) -> Result<ShaMapNode, ShaMapError> {
+    if path.is_empty() {
+        return Err(ShaMapError::EmptyPath);
+    }
+    if path.len() > MAX_DEPTH + 1 {
+        return Err(ShaMapError::PathTooLong(path.len()));
+    }
+
+    let mut expected_hash = *root_hash;
+    for (depth, node) in path.iter().rev().enumerate() {
+        let node = ShaMapNode::from_wire(node)?;
+        if node.hash() != expected_hash {
+            return Err(ShaMapError::HashMismatch(depth));
+        }
+
+        if node.is_inner() {
+            if depth == MAX_DEPTH {
+                return Err(ShaMapError::MissingLeaf(depth));
+            }
+            expected_hash = node
+                .child(select_branch(key, depth))
+                .ok_or(ShaMapError::MissingLeaf(depth))?;
+            continue;
+        }
+
+        if depth + 1 != path.len() {
+            return Err(ShaMapError::TrailingNodes(depth));
+        }
+        if node.key() != Some(*key) {
+            return Err(ShaMapError::KeyMismatch);
+        }
+        return Ok(node);
+    }
+
+    Err(ShaMapError::MissingLeaf(path.len() - 1))
+}
+
+/// Verifies the proof path of the response against the ledger header it's sent along with, and
+/// the header against the ledger's hash, then returns the proven leaf.
+pub fn verify_proof_response(response: &TmProofPathResponse) -> Result<ShaMapNode, ShaMapError> {
+    if let Some(error) = response.error {
+        return Err(ShaMapError::Refused(
+            TmReplyError::from_i32(error).unwrap_or(TmReplyError::ReBadRequest),
+        ));
+    }
+
+    let header = response
+        .ledger_header
+        .as_deref()
+        .ok_or(ShaMapError::MissingHeader)?;
+    let header = LedgerHeader::parse(header)?;
+    if header.hash().as_slice() != response.ledger_hash {
+        return Err(ShaMapError::LedgerHashMismatch);
+    }
+
+    let root_hash = match TmLedgerMapType::from_i32(response.r#type) {
+        Some(TmLedgerMapType::LmTranasction) => header.tx_hash,
+        _ => header.account_hash,
+    };
+    let key = response
+        .key
+        .as_slice()
+        .try_into()
+        .map_err(|_| ShaMapError::KeyMismatch)?;
+
+    verify_proof_path(&root_hash, &key, &response.path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leaf(key: [u8; 32]) -> ShaMapNode {
+        ShaMapNode::AccountState {
+            data: vec![key[31]; 40],
+            key,
+        }
+    }
+
+    fn inner(children: &[(usize, &ShaMapNode)]) -> ShaMapNode {
+        let mut branches = [[0u8; 32]; BRANCH_COUNT];
+        for (branch, child) in children {
+            branches[*branch] = child.hash();
+        }
+        ShaMapNode::Inner {
+            branches: Box::new(branches),
+        }
+    }
+
+    #[test]
+    fn wire_format_roundtrip() {
+        let leaf = leaf([1; 32]);
+        let sparse = inner(&[(3, &leaf)]);
+        let full = ShaMapNode::Inner {
+            branches: Box::new([[2; 32]; BRANCH_COUNT]),
+        };
+        let tx = ShaMapNode::Transaction { data: vec![3; 50] };
+
+        for node in [&leaf, &sparse, &full, &tx] {
+            assert_eq!(ShaMapNode::from_wire(&node.to_wire()).as_ref(), Ok(node));
+        }
+        assert_eq!(sparse.to_wire().len(), COMPRESSED_BRANCH_LEN + 1);
+        assert_eq!(full.to_wire().len(), BRANCH_COUNT * HASH_LEN + 1);
+        assert_eq!(sparse.child(3), Some(leaf.hash()));
+        assert_eq!(sparse.child(4), None);
+        assert_eq!(tx.key(), Some(tx.hash()));
+
+        assert_eq!(
+            ShaMapNode::from_wire(&[0; 33]),
+            Ok(ShaMapNode::Transaction { data: vec![0; 32] })
+        );
+        assert_eq!(
+            ShaMapNode::from_wire(&[[7; 32].as_slice(), &[16, 3]].concat()),
+            Err(ShaMapError::InvalidBranch(16))
+        );
+        assert_eq!(
+            ShaMapNode::from_wire(&[9]),
+            Err(ShaMapError::UnknownWireType(9))
+        );
+    }
+
+    #[test]
+    fn prefix_format_roundtrip() {
+        let leaf = leaf([1; 32]);
+        let inner = inner(&[(3, &leaf)]);
+        let tx = ShaMapNode::TransactionWithMeta {
+            data: vec![3; 50],
+            key: [4; 32],
+        };
+        for node in [&leaf, &inner, &tx] {
+            let prefixed = node.to_prefixed();
+            assert_eq!(ShaMapNode::from_prefixed(&prefixed).as_ref(), Ok(node));
+            assert_eq!(node.hash(), sha512_half(&prefixed));
+        }
+        assert_eq!(
+            ShaMapNode::from_prefixed(b"LWR\x00"),
+            Err(ShaMapError::UnknownPrefix(*b"LWR\x00"))
+        );
+    }
+
+    #[test]
+    fn node_ids_follow_the_keys() {
+        let key = [0xab; 32];
+        let id = ShaMapNodeId::for_key(&key, 3);
+        assert_eq!(id.id[..2], [0xab, 0xa0]);
+        assert_eq!(id.id[2..], [0; 30]);
+        assert_eq!(ShaMapNodeId::ROOT.child(0xa).child(0xb).child(0xa), id);
+        assert_eq!(id.parent(), Some((ShaMapNodeId::for_key(&key, 2), 0xa)));
+        assert_eq!(ShaMapNodeId::ROOT.parent(), None);
+
+        assert_eq!(ShaMapNodeId::from_wire(&id.to_wire()), Ok(id));
+        assert_eq!(ShaMapNodeId::from_wire(&[0; 33]), Ok(ShaMapNodeId::ROOT));
+        // Nibbles below the depth, a depth beyond the keys' and a truncated ID.
+        let mut below = id.to_wire();
+        below[2] = 1;
+        assert!(ShaMapNodeId::from_wire(&below).is_err());
+        assert!(ShaMapNodeId::from_wire(&[[0; 32].as_slice(), &[65]].concat()).is_err());
+        assert!(ShaMapNodeId::from_wire(&[0; 32]).is_err());
+    }
+
+    #[test]
+    fn partial_trees_are_checked() {
+        let mut key_a = [0x11; 32];
+        let mut key_b = [0x12; 32];
+        let key_c = [0xf0; 32];
+        key_a[31] = 1;
+        key_b[31] = 2;
+        let (a, b, c) = (leaf(key_a), leaf(key_b), leaf(key_c));
+        let split = inner(&[(1, &a), (2, &b)]);
+        let root = inner(&[(1, &split), (15, &c)]);
+        let split_id = ShaMapNodeId::ROOT.child(1);
+
+        let mut tree = PartialShaMap::new(root.hash());
+        assert_eq!(tree.missing(), [(ShaMapNodeId::ROOT, root.hash())]);
+
+        // The nodes sent in a TmLedgerData, out of order.
+        let ledger_nodes = [
+            (split_id, &split),
+            (ShaMapNodeId::ROOT, &root),
+            (ShaMapNodeId::ROOT.child(15), &c),
+        ]
+        .map(|(id, node)| TmLedgerNode {
+            nodedata: node.to_wire(),
+            nodeid: Some(id.to_wire()),
+        });
+        let nodes = ledger_nodes
+            .iter()
+            .map(parse_ledger_node)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        tree.insert_all(nodes).unwrap();
+        assert_eq!(tree.len(), 3);
+        assert_eq!(tree.leaves().collect::<Vec<_>>(), [&c]);
+        assert_eq!(
+            tree.missing(),
+            [(split_id.child(1), a.hash()), (split_id.child(2), b.hash())]
+        );
+
+        // A node which doesn't match its parent's branch, and an orphan.
+        assert_eq!(
+            tree.insert(split_id.child(1), b.clone()),
+            Err(ShaMapError::HashMismatch(2))
+        );
+        let orphan = ShaMapNodeId::ROOT.child(2).child(1);
+        assert_eq!(
+            tree.insert(orphan, a.clone()),
+            Err(ShaMapError::MissingParent(orphan))
+        );
+
+        tree.insert(split_id.child(1), a).unwrap();
+        tree.insert(split_id.child(2), b).unwrap();
+        assert!(tree.is_complete());
+        assert!(PartialShaMap::new([0; 32]).is_complete());
+
+        assert_eq!(
+            parse_ledger_node(&TmLedgerNode {
+                nodedata: root.to_wire(),
+                nodeid: None,
+            }),
+            Err(ShaMapError::MissingNodeId)
+        );
+    }
+
+    #[test]
+    fn proof_paths_are_verified() {
+        // Two of the keys share the first nibble, so they're split at depth 1.
+        let mut key_a = [0x11; 32];
+        let mut key_b = [0x12; 32];
+        let key_c = [0xf0; 32];
+        key_a[31] = 1;
+        key_b[31] = 2;
+        let (a, b, c) = (leaf(key_a), leaf(key_b), leaf(key_c));
+        let split = inner(&[(1, &a), (2, &b)]);
+        let root = inner(&[(1, &split), (15, &c)]);
+        let root_hash = root.hash();
+
+        let path = vec![a.to_wire(), split.to_wire(), root.to_wire()];
+        assert_eq!(verify_proof_path(&root_hash, &key_a, &path), Ok(a.clone()));
+        let path_c = vec![c.to_wire(), root.to_wire()];
+        assert_eq!(verify_proof_path(&root_hash, &key_c, &path_c), Ok(c));
+
+        // The leaf of another key.
+        assert_eq!(
+            verify_proof_path(&root_hash, &key_b, &path),
+            Err(ShaMapError::HashMismatch(2))
+        );
+        // A key without a leaf.
+        assert_eq!(
+            verify_proof_path(&root_hash, &[0x13; 32], &path),
+            Err(ShaMapError::MissingLeaf(1))
+        );
+        // A modified leaf.
+        let mut forged = path.clone();
+        forged[0][0] ^= 1;
+        assert_eq!(
+            verify_proof_path(&root_hash, &key_a, &forged),
+            Err(ShaMapError::HashMismatch(2))
+        );
+        // A truncated path.
+        assert_eq!(
+            verify_proof_path(&root_hash, &key_a, &path[1..]),
+            Err(ShaMapError::MissingLeaf(1))
+        );
+        assert_eq!(
+            verify_proof_path(&root_hash, &key_a, &[]),
+            Err(ShaMapError::EmptyPath)
+        );
+    }
+
+    #[test]
+    fn proof_responses_are_verified() {
+        let key = [0x42; 32];
+        let leaf = leaf(key);
+        let root = inner(&[(4, &leaf)]);
+        let header = LedgerHeader {
+            seq: 3,
+            drops: 1_000,
+            parent_hash: [1; 32],
+            tx_hash: [0; 32],
+            account_hash: root.hash(),
+            parent_close_time: 10,
+            close_time: 20,
+            close_time_resolution: 30,
+            close_flags: 0,
+        };
+        let response = TmProofPathResponse {
+            key: key.to_vec(),
+            ledger_hash: header.hash().to_vec(),
+            r#type: TmLedgerMapType::LmAccountState as i32,
+            ledger_header: Some(header.serialize()),
+            path: vec![leaf.to_wire(), root.to_wire()],
+            error: None,
+        };
+        assert_eq!(verify_proof_response(&response), Ok(leaf));
+
+        let forged = TmProofPathResponse {
+            ledger_hash: vec![9; 32],
+            ..response.clone()
+        };
+        assert_eq!(
+            verify_proof_response(&forged),
+            Err(ShaMapError::LedgerHashMismatch)
+        );
+
+        let transaction = TmProofPathResponse {
+            r#type: TmLedgerMapType::LmTranasction as i32,
+            ..response.clone()
+        };
+        assert_eq!(
+            verify_proof_response(&transaction),
+            Err(ShaMapError::HashMismatch(0))
+        );
+
+        let refused = TmProofPathResponse {
+            error: Some(TmReplyError::ReNoLedger as i32),
+            ..response
+        };
+        assert_eq!(
+            verify_proof_response(&refused),
+            Err(ShaMapError::Refused(TmReplyError::ReNoLedger))
+        );
+    }
+}
@@ -0,0 +1,560 @@
+//! Ripple's canonical binary format of serialized objects, as used by transactions, validations
+//! and manifests.
+//!
+//! An object is a list of fields sorted by their type code and then by their field code, each
+//! field prefixed by an ID packing both codes. Variable length fields are prefixed by their
+//! length, nested objects and arrays are terminated by an end marker.
+
+use bytes::{BufMut, BytesMut};
+use thiserror::Error;
+
+// Serialized type codes.
+pub const ST_UINT16: u8 = 1;
+pub const ST_UINT32: u8 = 2;
+pub const ST_UINT64: u8 = 3;
+pub const ST_HASH128: u8 = 4;
+pub const ST_HASH256: u8 = 5;
+pub const ST_AMOUNT: u8 = 6;
+pub const ST_BLOB: u8 = 7;
+pub const ST_ACCOUNT: u8 = 8;
+pub const ST_OBJECT: u8 = 14;
+pub const ST_ARRAY: u8 = 15;
+pub const ST_UINT8: u8 = 16;
+pub const ST_HASH160: u8 = 17;
+pub const ST_PATHSET: u8 = 18;
+pub const ST_VECTOR256: u8 = 19;
+
+/// The field code of the end markers of nested objects and arrays.
+const END_MARKER_FIELD: u8 = 1;
+
+/// The bit set in the first byte of issued currency amounts, XRP amounts have it cleared.
+const AMOUNT_NOT_XRP_BIT: u8 = 0x80;
+const XRP_AMOUNT_SIZE: usize = 8;
+const ISSUED_AMOUNT_SIZE: usize = 48;
+
+const ACCOUNT_ID_SIZE: usize = 20;
+
+// A path set is a list of paths separated by boundaries, each path a list of hops. A hop starts
+// with a byte flagging which of its 20-byte fields follow.
+const PATHSET_END: u8 = 0x00;
+const PATH_BOUNDARY: u8 = 0xff;
+const PATH_HOP_FLAGS: [u8; 3] = [0x01, 0x10, 0x20];
+const PATH_HOP_FIELD_SIZE: usize = 20;
+
+/// The longest value a variable length prefix can encode.
+pub const MAX_VL_LENGTH: usize = 918744;
+
+/// The deepest nesting of objects and arrays accepted when decoding, as in rippled.
+pub const MAX_NESTING_DEPTH: usize = 10;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum StObjectError {
+    #[error("unexpected end of data")]
+    UnexpectedEnd,
+
+    #[error("unsupported type code {0}")]
+    UnsupportedType(u8),
+
+    #[error("invalid variable length prefix {0}")]
+    InvalidLength(u8),
+
+    #[error("unexpected end marker")]
+    UnexpectedEndMarker,
+
+    #[error("objects and arrays nested deeper than {MAX_NESTING_DEPTH} levels")]
+    TooDeep,
+}
+
+/// A serialized field of an object.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Field {
+    pub type_code: u8,
+    pub field_code: u8,
+    /// The serialized value, without the length prefix of variable length fields and without the
+    /// end marker of nested objects and arrays. The end byte of path sets is kept.
+    pub value: Vec<u8>,
+}
+
+impl Field {
+    pub fn new(type_code: u8, field_code: u8, value: Vec<u8>) -> Self {
+        Self {
+            type_code,
+            field_code,
+            value,
+        }
+    }
+
+    pub fn uint8(field_code: u8, value: u8) -> Self {
+        Self::new(ST_UINT8, field_code, vec![value])
+    }
+
+    pub fn uint16(field_code: u8, value: u16) -> Self {
+        Self::new(ST_UINT16, field_code, value.to_be_bytes().to_vec())
+    }
+
+    pub fn uint32(field_code: u8, value: u32) -> Self {
+        Self::new(ST_UINT32, field_code, value.to_be_bytes().to_vec())
+    }
+
+    pub fn uint64(field_code: u8, value: u64) -> Self {
+        Self::new(ST_UINT64, field_code, value.to_be_bytes().to_vec())
+    }
+
+    pub fn hash256(field_code: u8, value: &[u8; 32]) -> Self {
+        Self::new(ST_HASH256, field_code, value.to_vec())
+    }
+
+    pub fn blob(field_code: u8, value: &[u8]) -> Self {
+        Self::new(ST_BLOB, field_code, value.to_vec())
+    }
+
+    /// Creates an account field out of a 20-byte account ID.
+    pub fn account(field_code: u8, account_id: &[u8]) -> Self {
+        Self::new(ST_ACCOUNT, field_code, account_id.to_vec())
+    }
+
+    /// Returns `true` if the field's value is prefixed by its length.
+    pub fn is_variable_length(&self) -> bool {
+        matches!(self.type_code, ST_BLOB | ST_ACCOUNT | ST_VECTOR256)
+    }
+
+    pub fn as_u8(&self) -> Option<u8> {
+        if self.type_code != ST_UINT8 {
+            return None;
+        }
+        self.value[..].try_into().ok().map(u8::from_be_bytes)
+    }
+
+    pub fn as_u16(&self) -> Option<u16> {
+        if self.type_code != ST_UINT16 {
+            return None;
+        }
+        self.value[..].try_into().ok().map(u16::from_be_bytes)
+    }
+
+    pub fn as_u32(&self) -> Option<u32> {
+        if self.type_code != ST_UINT32 {
+            return None;
+        }
+        self.value[..].try_into().ok().map(u32::from_be_bytes)
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        if self.type_code != ST_UINT64 {
+            return None;
+        }
+        self.value[..].try_into().ok().map(u64::from_be_bytes)
+    }
+
+    pub fn as_hash256(&self) -> Option<[u8; 32]> {
+        if self.type_code != ST_HASH256 {
+            return None;
+        }
+        self.value[..].try_into().ok()
+    }
+
+    /// Returns the value of a blob or an account field.
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        self.is_variable_length().then_some(&self.value[..])
+    }
+
+    /// Returns the fields of a nested object.
+    pub fn as_object(&self) -> Option<Result<StObject, StObjectError>> {
+        (self.type_code == ST_OBJECT).then(|| StObject::deserialize(&self.value))
+    }
+}
+
+/// An object's fields, in their canonical order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StObject {
+    fields: Vec<Field>,
+}
+
+impl StObject {
+    /// Creates an object out of the fields, sorting them into their canonical order.
+    pub fn new(mut fields: Vec<Field>) -> Self {
+        fields.sort_by_key(|field| (field.type_code, field.field_code));
+        Self { fields }
+    }
+
+    /// Decodes a serialized object.
+    pub fn deserialize(mut bytes: &[u8]) -> Result<Self, StObjectError> {
+        let mut fields = Vec::new();
+        while !bytes.is_empty() {
+            let field = read_field(&mut bytes, 0)?;
+            if is_end_marker(field.type_code, field.field_code) {
+                return Err(StObjectError::UnexpectedEndMarker);
+            }
+            fields.push(field);
+        }
+
+        Ok(Self { fields })
+    }
+
+    /// Encodes the object.
+    ///
+    /// Panics if a variable length field is longer than [MAX_VL_LENGTH].
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut buf = BytesMut::new();
+        for field in &self.fields {
+            put_field(&mut buf, field);
+        }
+        buf.to_vec()
+    }
+
+    /// Encodes the object without the fields excluded from its signature, panicking as
+    /// [StObject::serialize] does.
+    pub fn serialize_without(&self, excluded: &[(u8, u8)]) -> Vec<u8> {
+        let mut buf = BytesMut::new();
+        for field in &self.fields {
+            if !excluded.contains(&(field.type_code, field.field_code)) {
+                put_field(&mut buf, field);
+            }
+        }
+        buf.to_vec()
+    }
+
+    pub fn fields(&self) -> &[Field] {
+        &self.fields
+    }
+
+    /// Returns the field with the given codes.
+    pub fn get(&self, type_code: u8, field_code: u8) -> Option<&Field> {
+        self.fields
+            .iter()
+            .find(|field| field.type_code == type_code && field.field_code == field_code)
+    }
+
+    pub fn u32(&self, field_code: u8) -> Option<u32> {
+        self.get(ST_UINT32, field_code)?.as_u32()
+    }
+
+    pub fn u64(&self, field_code: u8) -> Option<u64> {
+        self.get(ST_UINT64, field_code)?.as_u64()
+    }
+
+    pub fn hash256(&self, field_code: u8) -> Option<[u8; 32]> {
+        self.get(ST_HASH256, field_code)?.as_hash256()
+    }
+
+    pub fn blob(&self, field_code: u8) -> Option<&[u8]> {
+        self.get(ST_BLOB, field_code)?.as_bytes()
+    }
+
+    /// Returns the 20-byte account ID of an account field.
+    pub fn account(&self, field_code: u8) -> Option<&[u8]> {
+        self.get(ST_ACCOUNT, field_code)?.as_bytes()
+    }
+
+    /// Adds or replaces a field, keeping the canonical order.
+    pub fn set(&mut self, field: Field) {
+        self.fields
+            .retain(|f| (f.type_code, f.field_code) != (field.type_code, field.field_code));
+        let idx = self
+            .fields
+            .partition_point(|f| (f.type_code, f.field_code) < (field.type_code, field.field_code));
+        self.fields.insert(idx, field);
+    }
+}
+
+/// Sorts the fields into their canonical order and serializes them.
+///
+/// Panics if a variable length field is longer than [MAX_VL_LENGTH].
+pub fn serialize(fields: &mut [Field]) -> Vec<u8> {
+    fields.sort_by_key(|field| (field.type_code, field.field_code));
+
+    let mut buf = BytesMut::new();
+    for field in fields.iter() {
+        put_field(&mut buf, field);
+    }
+    buf.to_vec()
+}
+
+fn put_field(buf: &mut BytesMut, field: &Field) {
+    put_field_id(buf, field.type_code, field.field_code);
+    if field.is_variable_length() {
+        buf.extend_from_slice(&encode_vl_length(field.value.len()));
+    }
+    buf.extend_from_slice(&field.value);
+    if matches!(field.type_code, ST_OBJECT | ST_ARRAY) {
+        put_field_id(buf, field.type_code, END_MARKER_FIELD);
+    }
+}
+
+// Codes lower than 16 are packed into a single byte, others take a byte of their own.
+fn put_field_id(buf: &mut BytesMut, type_code: u8, field_code: u8) {
+    match (type_code < 16, field_code < 16) {
+        (true, true) => buf.put_u8(type_code << 4 | field_code),
+        (true, false) => {
+            buf.put_u8(type_code << 4);
+            buf.put_u8(field_code);
+        }
+        (false, true) => {
+            buf.put_u8(field_code);
+            buf.put_u8(type_code);
+        }
+        (false, false) => {
+            buf.put_u8(0);
+            buf.put_u8(type_code);
+            buf.put_u8(field_code);
+        }
+    }
+}
+
+fn is_end_marker(type_code: u8, field_code: u8) -> bool {
+    matches!(type_code, ST_OBJECT | ST_ARRAY) && field_code == END_MARKER_FIELD
+}
+
+fn take<'a>(bytes: &mut &'a [u8], len: usize) -> Result<&'a [u8], StObjectError> {
+    if bytes.len() < len {
+        return Err(StObjectError::UnexpectedEnd);
+    }
+    let (taken, rest) = bytes.split_at(len);
+    *bytes = rest;
+    Ok(taken)
+}
+
+fn read_u8(bytes: &mut &[u8]) -> Result<u8, StObjectError> {
+    Ok(take(bytes, 1)?[0])
+}
+
+fn read_field_id(bytes: &mut &[u8]) -> Result<(u8, u8), StObjectError> {
+    let first = read_u8(bytes)?;
+    let mut type_code = first >> 4;
+    let mut field_code = first & 0x0f;
+    if type_code == 0 {
+        type_code = read_u8(bytes)?;
+    }
+    if field_code == 0 {
+        field_code = read_u8(bytes)?;
+    }
+    Ok((type_code, field_code))
+}
+
+// Reads a field at the given nesting depth, the top level object's fields being at depth 0.
+fn read_field(bytes: &mut &[u8], depth: usize) -> Result<Field, StObjectError> {
+    let (type_code, field_code) = read_field_id(bytes)?;
+    if is_end_marker(type_code, field_code) {
+        return Ok(Field::new(type_code, field_code, Vec::new()));
+    }
+
+    let len = match type_code {
+        ST_UINT8 => 1,
+        ST_UINT16 => 2,
+        ST_UINT32 => 4,
+        ST_UINT64 => 8,
+        ST_HASH128 => 16,
+        ST_HASH160 => ACCOUNT_ID_SIZE,
+        ST_HASH256 => 32,
+        ST_AMOUNT => match bytes.first() {
+            Some(byte) if byte & AMOUNT_NOT_XRP_BIT != 0 => ISSUED_AMOUNT_SIZE,
+            _ => XRP_AMOUNT_SIZE,
+        },
+        ST_BLOB | ST_ACCOUNT | ST_VECTOR256 => decode_vl_length(bytes)?,
+        ST_PATHSET => path_set_len(bytes)?,
+        ST_OBJECT | ST_ARRAY => return read_nested(bytes, type_code, field_code, depth + 1),
+        _ => return Err(StObjectError::UnsupportedType(type_code)),
+    };
+
+    Ok(Field::new(
+        type_code,
+        field_code,
+        take(bytes, len)?.to_vec(),
+    ))
+}
+
+// Reads the fields of a nested object or array up to its end marker.
+fn read_nested(
+    bytes: &mut &[u8],
+    type_code: u8,
+    field_code: u8,
+    depth: usize,
+) -> Result<Field, StObjectError> {
+    if depth > MAX_NESTING_DEPTH {
+        return Err(StObjectError::TooDeep);
+    }
+
+    let start = *bytes;
+    loop {
+        let before = bytes.len();
+        let field = read_field(bytes, depth)?;
+        if is_end_marker(field.type_code, field.field_code) {
+            if field.type_code != type_code {
+                return Err(StObjectError::UnexpectedEndMarker);
+            }
+            let value = start[..start.len() - before].to_vec();
+            return Ok(Field::new(type_code, field_code, value));
+        }
+    }
+}
+
+// Returns the length of the path set at the start of the bytes, up to and including its end.
+fn path_set_len(bytes: &[u8]) -> Result<usize, StObjectError> {
+    let mut len = 0;
+    loop {
+        let hop_type = *bytes.get(len).ok_or(StObjectError::UnexpectedEnd)?;
+        len += 1;
+        match hop_type {
+            PATHSET_END => return Ok(len),
+            PATH_BOUNDARY => {}
+            _ => {
+                let fields = PATH_HOP_FLAGS
+                    .iter()
+                    .filter(|&&flag| hop_type & flag != 0)
+                    .count();
+                len += fields * PATH_HOP_FIELD_SIZE;
+            }
+        }
+    }
+}
+
+/// Encodes the length prefix of a variable length field.
+///
+/// # Panics
+///
+/// Panics if `len` is above [MAX_VL_LENGTH], which the format can't encode.
+pub fn encode_vl_length(len: usize) -> Vec<u8> {
+    assert!(
+        len <= MAX_VL_LENGTH,
+        "variable length field of {len} bytes exceeds {MAX_VL_LENGTH}"
+    );
+
+    if len <= 192 {
+        vec![len as u8]
+    } else if len <= 12480 {
+        let len = len - 193;
+        vec![193 + (len >> 8) as u8, (len & 0xff) as u8]
+    } else {
+        let len = len - 12481;
+        vec![
+            241 + (len >> 16) as u8,
+            ((len >> 8) & 0xff) as u8,
+            (len & 0xff) as u8,
+        ]
+    }
+}
+
+/// Decodes the length prefix of a variable length field, advancing past it.
+pub fn decode_vl_length(bytes: &mut &[u8]) -> Result<usize, StObjectError> {
+    let first = read_u8(bytes)?;
+    match first {
+        0..=192 => Ok(first as usize),
+        193..=240 => {
+            let second = read_u8(bytes)? as usize;
+            Ok(193 + (first as usize - 193) * 256 + second)
+        }
+        241..=254 => {
+            let rest = take(bytes, 2)?;
+            let len =
+                12481 + (first as usize - 241) * 65536 + rest[0] as usize * 256 + rest[1] as usize;
+            if len > MAX_VL_LENGTH {
+                return Err(StObjectError::InvalidLength(first));
+            }
+            Ok(len)
+        }
+        _ => Err(StObjectError::InvalidLength(first)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vl_length_encoding() {
+        assert_eq!(encode_vl_length(192), [192]);
+        assert_eq!(encode_vl_length(193), [193, 0]);
+        assert_eq!(encode_vl_length(12480), [240, 255]);
+        assert_eq!(encode_vl_length(12481), [241, 0, 0]);
+
+        assert_eq!(encode_vl_length(MAX_VL_LENGTH), [254, 0xd4, 0x17]);
+
+        for len in [0, 192, 193, 12480, 12481, MAX_VL_LENGTH] {
+            let encoded = encode_vl_length(len);
+            assert_eq!(decode_vl_length(&mut &encoded[..]), Ok(len));
+        }
+        assert_eq!(
+            decode_vl_length(&mut &[254, 0xd4, 0x18][..]),
+            Err(StObjectError::InvalidLength(254))
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn vl_length_above_max_is_not_encoded() {
+        encode_vl_length(MAX_VL_LENGTH + 1);
+    }
+
+    #[test]
+    fn accessors_check_value_length() {
+        assert_eq!(Field::uint32(4, 1).as_u32(), Some(1));
+        assert_eq!(Field::new(ST_UINT8, 1, Vec::new()).as_u8(), None);
+        assert_eq!(Field::new(ST_UINT32, 4, vec![0; 3]).as_u32(), None);
+        assert_eq!(Field::new(ST_HASH256, 1, vec![0; 33]).as_hash256(), None);
+    }
+
+    #[test]
+    fn object_roundtrip() {
+        let nested = StObject::new(vec![Field::uint32(4, 7)]).serialize();
+        let object = StObject::new(vec![
+            Field::blob(3, &[0xab; 200]),
+            Field::uint32(4, 1),
+            Field::new(ST_AMOUNT, 8, 10u64.to_be_bytes().to_vec()),
+            Field::account(1, &[1; 20]),
+            Field::hash256(1, &[2; 32]),
+            Field::uint8(16, 3),
+            Field::new(ST_OBJECT, 16, nested),
+            // A path of an account hop and a path of a currency and issuer hop.
+            Field::new(
+                ST_PATHSET,
+                1,
+                [&[0x01][..], &[3; 20], &[0xff, 0x30], &[4; 40], &[0x00]].concat(),
+            ),
+        ]);
+
+        let bytes = object.serialize();
+        let decoded = StObject::deserialize(&bytes).unwrap();
+        assert_eq!(decoded, object);
+        assert_eq!(decoded.u32(4), Some(1));
+        assert_eq!(decoded.blob(3), Some(&[0xab; 200][..]));
+        assert_eq!(decoded.account(1), Some(&[1; 20][..]));
+        assert_eq!(decoded.hash256(1), Some([2; 32]));
+
+        let nested = decoded.get(ST_OBJECT, 16).unwrap().as_object().unwrap();
+        assert_eq!(nested.unwrap().u32(4), Some(7));
+    }
+
+    #[test]
+    fn truncated_object_is_rejected() {
+        let bytes = StObject::new(vec![Field::hash256(1, &[2; 32])]).serialize();
+        assert_eq!(
+            StObject::deserialize(&bytes[..20]),
+            Err(StObjectError::UnexpectedEnd)
+        );
+    }
+
+    #[test]
+    fn mismatched_end_marker_is_rejected() {
+        // An array (0xf2) ended by an object's end marker (0xe1).
+        assert_eq!(
+            StObject::deserialize(&[0xf2, 0xe1]),
+            Err(StObjectError::UnexpectedEndMarker)
+        );
+    }
+
+    #[test]
+    fn deep_nesting_is_rejected() {
+        // 0xe2 opens a nested object, 0xe1 ends it.
+        let nested = |depth| [vec![0xe2; depth], vec![0xe1; depth]].concat();
+
+        assert!(StObject::deserialize(&nested(MAX_NESTING_DEPTH)).is_ok());
+        assert_eq!(
+            StObject::deserialize(&nested(MAX_NESTING_DEPTH + 1)),
+            Err(StObjectError::TooDeep)
+        );
+        assert_eq!(
+            StObject::deserialize(&[0xe2; 100_000]),
+            Err(StObjectError::TooDeep)
+        );
+    }
+}
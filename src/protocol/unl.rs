@@ -0,0 +1,449 @@
+//! Validator lists (UNLs), the lists of trusted validators signed by their publishers.
+//!
+//! A list is a JSON blob signed by the publisher's signing key, which the publisher's manifest
+//! binds to its master key. The same encoding is used by [TmValidatorList] messages and the lists
+//! served at `/vl/<publisher key>`: the manifest and the blob in base64, the signature in hex.
+//!
+//! Lists are signed with [UnlBuilder] and checked with [verify]. Version 2 peers exchange several
+//! lists of a publisher at once in [TmValidatorListCollection] messages, e.g. the current list and
+//! the ones taking effect later, see [collection] and [verify_collection].
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    protocol::{
+        manifest::{Manifest, ManifestError},
+        proto::{TmValidatorList, TmValidatorListCollection, ValidatorBlobInfo},
+    },
+    tools::{
+        ripple_time,
+        validator::{
+            create_manifest, verify as verify_signature, KeyType, Validator, ValidatorKey,
+            ValidatorList,
+        },
+    },
+};
+
+/// The version of validator lists carried by [TmValidatorList] messages.
+pub const LIST_VERSION_1: u32 = 1;
+
+/// The version of validator lists carried by [TmValidatorListCollection] messages.
+pub const LIST_VERSION_2: u32 = 2;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum UnlError {
+    #[error("invalid {0} encoding")]
+    Encoding(&'static str),
+
+    #[error("invalid publisher manifest: {0}")]
+    Manifest(#[from] ManifestError),
+
+    #[error("the publisher's master key is revoked")]
+    Revoked,
+
+    #[error("invalid blob signature")]
+    InvalidSignature,
+
+    #[error("invalid blob: {0}")]
+    Blob(String),
+
+    #[error("the list is published by {found}, expected {expected}")]
+    PublisherMismatch { expected: String, found: String },
+
+    #[error("the list has no blob")]
+    MissingBlob,
+}
+
+/// A validator list whose blob was verified against its publisher's manifest.
+#[derive(Debug, Clone)]
+pub struct VerifiedList {
+    /// The publisher's manifest, its master key identifies the publisher.
+    pub publisher: Manifest,
+    pub list: ValidatorList,
+}
+
+impl VerifiedList {
+    /// Returns the upper-case hex-encoded master key of the publisher.
+    pub fn publisher_key_hex(&self) -> String {
+        hex::encode_upper(&self.publisher.public_key)
+    }
+
+    /// Returns `true` if the list's expiration has passed.
+    pub fn is_expired(&self) -> bool {
+        self.list.expiration <= ripple_time::now()
+    }
+}
+
+/// Verifies a validator list in its wire encoding.
+///
+/// The manifest's signatures are checked, then the blob's signature against the manifest's
+/// signing key. The list's expiration isn't checked.
+pub fn verify(manifest: &[u8], blob: &[u8], signature: &[u8]) -> Result<VerifiedList, UnlError> {
+    let manifest = STANDARD
+        .decode(manifest)
+        .map_err(|_| UnlError::Encoding("manifest"))?;
+    let blob = STANDARD
+        .decode(blob)
+        .map_err(|_| UnlError::Encoding("blob"))?;
+    let signature = hex::decode(signature).map_err(|_| UnlError::Encoding("signature"))?;
+
+    let publisher = Manifest::parse(&manifest)?;
+    // Revocations have no signing key, the check below would fail anyway.
+    let signing_pub_key = match &publisher.signing_pub_key {
+        Some(key) if !publisher.is_revocation() => key,
+        _ => return Err(UnlError::Revoked),
+    };
+    if !verify_signature(signing_pub_key, &blob, &signature) {
+        return Err(UnlError::InvalidSignature);
+    }
+
+    let list = serde_json::from_slice(&blob).map_err(|e| UnlError::Blob(e.to_string()))?;
+    Ok(VerifiedList { publisher, list })
+}
+
+/// Verifies the validator list carried by the message.
+pub fn verify_message(message: &TmValidatorList) -> Result<VerifiedList, UnlError> {
+    verify(&message.manifest, &message.blob, &message.signature)
+}
+
+/// Verifies each list of the collection, in the order they're carried.
+///
+/// A blob's own manifest takes precedence over the collection's.
+pub fn verify_collection(
+    collection: &TmValidatorListCollection,
+) -> Vec<Result<VerifiedList, UnlError>> {
+    collection
+        .blobs
+        .iter()
+        .map(|blob| {
+            let manifest = blob.manifest.as_ref().unwrap_or(&collection.manifest);
+            verify(manifest, &blob.blob, &blob.signature)
+        })
+        .collect()
+}
+
+/// Returns the collection carrying the lists, in the order given.
+///
+/// The collection's manifest is the first list's, the lists signed under another manifest carry
+/// theirs along with their blobs.
+pub fn collection(lists: &[SignedList]) -> TmValidatorListCollection {
+    let manifest = lists
+        .first()
+        .map(|list| list.manifest.clone())
+        .unwrap_or_default();
+    let blobs = lists
+        .iter()
+        .map(|list| ValidatorBlobInfo {
+            manifest: (list.manifest != manifest).then(|| list.manifest.clone()),
+            blob: list.blob.clone(),
+            signature: list.signature.clone(),
+        })
+        .collect();
+
+    TmValidatorListCollection {
+        version: LIST_VERSION_2,
+        manifest,
+        blobs,
+    }
+}
+
+/// A validator list signed by its publisher, in its wire encoding.
+///
+/// The fields are public so tests can tamper with them after signing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignedList {
+    /// The publisher's manifest, base64-encoded.
+    pub manifest: Vec<u8>,
+    /// The list's JSON, base64-encoded.
+    pub blob: Vec<u8>,
+    /// The signature of the JSON by the publisher's signing key, hex-encoded.
+    pub signature: Vec<u8>,
+}
+
+impl SignedList {
+    /// Returns the version 1 message carrying the list.
+    pub fn to_message(&self) -> TmValidatorList {
+        TmValidatorList {
+            manifest: self.manifest.clone(),
+            blob: self.blob.clone(),
+            signature: self.signature.clone(),
+            version: LIST_VERSION_1,
+        }
+    }
+
+    pub fn verify(&self) -> Result<VerifiedList, UnlError> {
+        verify(&self.manifest, &self.blob, &self.signature)
+    }
+}
+
+/// Builds validator lists signed by a publisher.
+///
+/// The list's sequence is 1 and it expires a year from now unless set otherwise.
+#[derive(Clone)]
+pub struct UnlBuilder {
+    master: ValidatorKey,
+    signing: ValidatorKey,
+    manifest_sequence: u32,
+    list: ValidatorList,
+}
+
+impl UnlBuilder {
+    /// Creates a builder for the publisher with the master and signing keys.
+    pub fn new(master: ValidatorKey, signing: ValidatorKey) -> Self {
+        Self {
+            master,
+            signing,
+            manifest_sequence: 1,
+            list: ValidatorList::new(1, Vec::new()),
+        }
+    }
+
+    /// Sets the sequence of the publisher's manifest, 1 by default.
+    pub fn manifest_sequence(mut self, sequence: u32) -> Self {
+        self.manifest_sequence = sequence;
+        self
+    }
+
+    pub fn sequence(mut self, sequence: u32) -> Self {
+        self.list.sequence = sequence;
+        self
+    }
+
+    /// Sets the expiration, in seconds since the Ripple epoch.
+    pub fn expiration(mut self, expiration: u32) -> Self {
+        self.list.expiration = expiration;
+        self
+    }
+
+    /// Sets when the list takes effect, in seconds since the Ripple epoch.
+    pub fn effective(mut self, effective: u32) -> Self {
+        self.list.effective = Some(effective);
+        self
+    }
+
+    /// Lists the validator with the master key, with a manifest for the signing key.
+    pub fn validator(self, master: &ValidatorKey, signing: &ValidatorKey) -> Self {
+        let manifest = create_manifest(1, master, signing);
+        self.validator_entry(Validator::new(&master.public_key_hex(), &manifest))
+    }
+
+    /// Lists the entry as is, e.g. one with an invalid manifest.
+    pub fn validator_entry(mut self, validator: Validator) -> Self {
+        self.list.validators.push(validator);
+        self
+    }
+
+    /// Returns the upper-case hex-encoded master key of the publisher.
+    pub fn publisher_key_hex(&self) -> String {
+        self.master.public_key_hex()
+    }
+
+    /// Signs the list with the publisher's signing key.
+    pub fn build(&self) -> SignedList {
+        let manifest = create_manifest(self.manifest_sequence, &self.master, &self.signing);
+        let json = self.list.to_json();
+        let signature = self.signing.sign(json.as_bytes());
+
+        SignedList {
+            manifest: STANDARD.encode(manifest).into_bytes(),
+            blob: STANDARD.encode(json).into_bytes(),
+            signature: hex::encode_upper(signature).into_bytes(),
+        }
+    }
+}
+
+impl Default for UnlBuilder {
+    /// A builder for a random publisher, with an ed25519 master key and a secp256k1 signing key.
+    fn default() -> Self {
+        Self::new(
+            ValidatorKey::generate(KeyType::Ed25519),
+            ValidatorKey::generate(KeyType::Secp256k1),
+        )
+    }
+}
+
+/// A blob of a [PublishedList], along with its signature.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PublishedBlob {
+    pub blob: String,
+    pub signature: String,
+    /// A newer manifest of the publisher, the list's manifest is used if not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub manifest: Option<String>,
+}
+
+/// A validator list as served at `/vl/<publisher key>`.
+///
+/// Version 1 lists hold a single blob, version 2 lists hold several in `blobs_v2`, e.g. the
+/// current list and the ones taking effect later.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PublishedList {
+    /// The publisher's master key, hex-encoded.
+    pub public_key: String,
+    pub manifest: String,
+    pub version: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blob: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub blobs_v2: Vec<PublishedBlob>,
+}
+
+impl PublishedList {
+    /// Verifies every blob of the list and checks they're published by the advertised key.
+    pub fn verify(&self) -> Result<Vec<VerifiedList>, UnlError> {
+        let mut blobs = self.blobs_v2.clone();
+        if let (Some(blob), Some(signature)) = (&self.blob, &self.signature) {
+            blobs.push(PublishedBlob {
+                blob: blob.clone(),
+                signature: signature.clone(),
+                manifest: None,
+            });
+        }
+        if blobs.is_empty() {
+            return Err(UnlError::MissingBlob);
+        }
+
+        blobs
+            .iter()
+            .map(|blob| {
+                let manifest = blob.manifest.as_ref().unwrap_or(&self.manifest);
+                let verified = verify(
+                    manifest.as_bytes(),
+                    blob.blob.as_bytes(),
+                    blob.signature.as_bytes(),
+                )?;
+                if !verified
+                    .publisher_key_hex()
+                    .eq_ignore_ascii_case(&self.public_key)
+                {
+                    return Err(UnlError::PublisherMismatch {
+                        expected: self.public_key.clone(),
+                        found: verified.publisher_key_hex(),
+                    });
+                }
+                Ok(verified)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn published(public_key: String, signed: &SignedList) -> PublishedList {
+        let string = |bytes: &[u8]| String::from_utf8(bytes.to_vec()).unwrap();
+        PublishedList {
+            public_key,
+            manifest: string(&signed.manifest),
+            version: LIST_VERSION_1,
+            blob: Some(string(&signed.blob)),
+            signature: Some(string(&signed.signature)),
+            blobs_v2: vec![],
+        }
+    }
+
+    #[test]
+    fn built_lists_are_verified() {
+        let validator = ValidatorKey::generate(KeyType::Ed25519);
+        let builder = UnlBuilder::default()
+            .sequence(7)
+            .validator(&validator, &validator);
+        let signed = builder.build();
+
+        let verified = signed.verify().unwrap();
+        assert_eq!(verified.list.sequence, 7);
+        assert_eq!(verified.publisher_key_hex(), builder.publisher_key_hex());
+        assert_eq!(
+            verified.list.validators[0].validation_public_key,
+            validator.public_key_hex()
+        );
+        assert!(!verified.is_expired());
+        assert!(builder
+            .clone()
+            .expiration(1)
+            .build()
+            .verify()
+            .unwrap()
+            .is_expired());
+
+        let mut tampered = signed.clone();
+        tampered.signature = hex::encode_upper(vec![0x30; 70]).into_bytes();
+        assert_eq!(tampered.verify().unwrap_err(), UnlError::InvalidSignature);
+
+        let revoked = builder.clone().manifest_sequence(u32::MAX).build();
+        assert_eq!(revoked.verify().unwrap_err(), UnlError::Revoked);
+    }
+
+    #[test]
+    fn collections_carry_the_lists() {
+        let builder = UnlBuilder::default();
+        let lists = [
+            builder.clone().sequence(1).build(),
+            builder.clone().sequence(2).effective(100).build(),
+            builder.clone().manifest_sequence(2).sequence(3).build(),
+        ];
+        let collection = collection(&lists);
+        assert_eq!(collection.version, LIST_VERSION_2);
+        assert_eq!(collection.manifest, lists[0].manifest);
+        assert!(collection.blobs[1].manifest.is_none());
+        assert_eq!(
+            collection.blobs[2].manifest,
+            Some(lists[2].manifest.clone())
+        );
+
+        let verified = verify_collection(&collection)
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        let sequences = verified
+            .iter()
+            .map(|verified| verified.list.sequence)
+            .collect::<Vec<_>>();
+        assert_eq!(sequences, [1, 2, 3]);
+        assert_eq!(verified[1].list.effective, Some(100));
+        assert_eq!(verified[2].publisher.sequence, 2);
+    }
+
+    #[test]
+    fn published_lists_are_verified() {
+        let builder = UnlBuilder::default().sequence(7);
+        let signed = builder.build();
+        let published = published(builder.publisher_key_hex(), &signed);
+        let verified = published.verify().unwrap();
+        assert_eq!(verified.len(), 1);
+        assert_eq!(verified[0].list.sequence, 7);
+
+        // The blob is signed by a key the manifest doesn't name.
+        let forged = UnlBuilder::new(
+            ValidatorKey::generate(KeyType::Ed25519),
+            ValidatorKey::generate(KeyType::Ed25519),
+        )
+        .build();
+        let forged = PublishedList {
+            blob: None,
+            signature: None,
+            version: 2,
+            blobs_v2: vec![PublishedBlob {
+                blob: published.blob.clone().unwrap(),
+                signature: String::from_utf8(forged.signature).unwrap(),
+                manifest: None,
+            }],
+            ..published.clone()
+        };
+        assert_eq!(forged.verify().unwrap_err(), UnlError::InvalidSignature);
+
+        let misattributed = PublishedList {
+            public_key: UnlBuilder::default().publisher_key_hex(),
+            ..published
+        };
+        assert!(matches!(
+            misattributed.verify(),
+            Err(UnlError::PublisherMismatch { .. })
+        ));
+    }
+}
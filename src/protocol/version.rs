@@ -0,0 +1,104 @@
+//! XRPL overlay protocol versions, negotiated with the handshake's 'Upgrade' field.
+
+use std::{fmt, str::FromStr};
+
+/// A version of the peer protocol, e.g. `XRPL/2.2`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ProtocolVersion {
+    pub major: u16,
+    pub minor: u16,
+}
+
+impl ProtocolVersion {
+    pub const V2_0: Self = Self::new(2, 0);
+    pub const V2_1: Self = Self::new(2, 1);
+    pub const V2_2: Self = Self::new(2, 2);
+
+    /// The versions offered by default, oldest first.
+    pub const SUPPORTED: [Self; 3] = [Self::V2_0, Self::V2_1, Self::V2_2];
+
+    pub const fn new(major: u16, minor: u16) -> Self {
+        Self { major, minor }
+    }
+}
+
+impl fmt::Display for ProtocolVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "XRPL/{}.{}", self.major, self.minor)
+    }
+}
+
+impl FromStr for ProtocolVersion {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid protocol version: {s}");
+
+        let (major, minor) = s
+            .trim()
+            .strip_prefix("XRPL/")
+            .and_then(|version| version.split_once('.'))
+            .ok_or_else(invalid)?;
+        // Rejects signs, which `u16::from_str` would accept.
+        if !major
+            .bytes()
+            .chain(minor.bytes())
+            .all(|b| b.is_ascii_digit())
+        {
+            return Err(invalid());
+        }
+
+        Ok(Self::new(
+            major.parse().map_err(|_| invalid())?,
+            minor.parse().map_err(|_| invalid())?,
+        ))
+    }
+}
+
+/// Formats the versions as an 'Upgrade' field, e.g. `XRPL/2.1, XRPL/2.2`.
+pub fn format_versions(versions: &[ProtocolVersion]) -> String {
+    versions
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Parses the versions listed in an 'Upgrade' field, skipping the invalid ones like rippled does.
+pub fn parse_versions(field: &str) -> Vec<ProtocolVersion> {
+    let mut versions = field
+        .split(',')
+        .filter_map(|version| version.parse().ok())
+        .collect::<Vec<_>>();
+    versions.sort();
+    versions.dedup();
+    versions
+}
+
+/// Picks the highest version both sides support, as rippled does when responding to a handshake.
+pub fn negotiate(ours: &[ProtocolVersion], theirs: &[ProtocolVersion]) -> Option<ProtocolVersion> {
+    ours.iter()
+        .filter(|version| theirs.contains(version))
+        .max()
+        .copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn negotiates_the_highest_common_version() {
+        let theirs = parse_versions(" XRPL/2.1,XRPL/2.2, XRPL/-2.3, RTXP/1.2, XRPL/2.1");
+        assert_eq!(theirs, vec![ProtocolVersion::V2_1, ProtocolVersion::V2_2]);
+        assert_eq!(
+            negotiate(&ProtocolVersion::SUPPORTED, &theirs),
+            Some(ProtocolVersion::V2_2)
+        );
+        assert_eq!(negotiate(&[ProtocolVersion::V2_0], &theirs), None);
+        assert_eq!(
+            format_versions(&ProtocolVersion::SUPPORTED),
+            "XRPL/2.0, XRPL/2.1, XRPL/2.2"
+        );
+    }
+}
@@ -40,6 +40,6 @@ impl Writing for InnerNode {
     type Codec = MessageCodec;
 
     fn codec(&self, _addr: SocketAddr, _side: ConnectionSide) -> Self::Codec {
-        Self::Codec::new(self.node().span().clone())
+        Self::Codec::new(self.node().span().clone()).with_compression(self.compression)
     }
 }
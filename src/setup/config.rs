@@ -1,20 +1,22 @@
 //! Utilities for node configuration.
 
 use std::{
+    collections::{BTreeMap, HashSet},
     ffi::OsString,
-    fmt::Write,
+    fmt::{self, Write},
     fs,
     path::{Path, PathBuf},
 };
 
-use anyhow::Result;
+use anyhow::{ensure, Context, Result};
 use serde::Deserialize;
 
 use crate::setup::{
     constants::{
-        JSON_RPC_PORT, RIPPLED_DIR, RIPPLED_NODE_SEED, SYNTHETIC_NODE_PUBLIC_KEY,
-        VALIDATORS_FILE_NAME, ZIGGURAT_CONFIG,
+        JSON_RPC_PORT, RIPPLED_DIR, RIPPLED_LOG_FILE, RIPPLED_NODE_SEED, SYNTHETIC_NODE_PUBLIC_KEY,
+        VALIDATORS_FILE_NAME, WS_PORT, ZIGGURAT_CONFIG,
     },
+    docker::{DockerConfig, NodeBackend},
     node::NodeConfig,
 };
 
@@ -22,13 +24,20 @@ use crate::setup::{
 #[derive(Deserialize)]
 struct ConfigFile {
     /// The absolute path of where to run the start command.
+    #[serde(default)]
     path: PathBuf,
     /// The command to start the node.
+    #[serde(default)]
     start_command: String,
+    /// Runs the nodes in containers instead, see [NodeBackend::Docker].
+    docker: Option<DockerConfig>,
+    /// Further rippled binaries by name, e.g. `develop = "/path/to/rippled"`.
+    #[serde(default)]
+    binaries: BTreeMap<String, PathBuf>,
 }
 
 /// The node metadata read from Ziggurat's configuration file.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct NodeMetaData {
     /// The absolute path of where to run the start command.
     pub path: PathBuf,
@@ -36,6 +45,11 @@ pub struct NodeMetaData {
     pub start_command: OsString,
     /// The arguments to the start command of the node.
     pub start_args: Vec<OsString>,
+    /// Where the node runs, the start command is ignored by the Docker backend.
+    pub backend: NodeBackend,
+    /// The named rippled binaries, which nodes can run instead of the start command, see
+    /// [NodeBuilder::with_binary](crate::setup::node::NodeBuilder::with_binary).
+    pub binaries: BTreeMap<String, PathBuf>,
 }
 
 impl NodeMetaData {
@@ -50,16 +64,152 @@ impl NodeMetaData {
             command.split_whitespace().map(OsString::from).collect()
         };
 
+        let backend = match config_file.docker {
+            Some(docker) => NodeBackend::Docker(docker),
+            None => NodeBackend::Local,
+        };
+        if let NodeBackend::Docker(_) = backend {
+            return Ok(Self {
+                path: config_file.path,
+                start_command: "docker".into(),
+                start_args: vec![],
+                backend,
+                binaries: config_file.binaries,
+            });
+        }
+
         // Separate the start command from the args list.
         let mut start_args = args_from(&config_file.start_command);
+        ensure!(
+            !start_args.is_empty(),
+            "{ZIGGURAT_CONFIG} sets neither a start command nor a [docker] table"
+        );
         let start_command = start_args.remove(0);
 
         Ok(Self {
             path: config_file.path,
             start_command,
             start_args,
+            backend,
+            binaries: config_file.binaries,
         })
     }
+
+    /// Returns the path of the named binary.
+    pub fn binary(&self, name: &str) -> Result<&Path> {
+        self.binaries
+            .get(name)
+            .map(PathBuf::as_path)
+            .with_context(|| {
+                format!(
+                    "no binary named {name} in {ZIGGURAT_CONFIG}, known binaries: {:?}",
+                    self.binaries.keys().collect::<Vec<_>>()
+                )
+            })
+    }
+}
+
+/// The `[node_size]` presets, which tune the node's caches and thread pools.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeSize {
+    Tiny,
+    Small,
+    Medium,
+    Large,
+    Huge,
+}
+
+impl fmt::Display for NodeSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let size = match self {
+            Self::Tiny => "tiny",
+            Self::Small => "small",
+            Self::Medium => "medium",
+            Self::Large => "large",
+            Self::Huge => "huge",
+        };
+        f.write_str(size)
+    }
+}
+
+/// Sections written to rippled.cfg in place of the generated ones, or in addition to them.
+#[derive(Debug, Clone, Default)]
+pub struct ConfigOverrides {
+    sections: BTreeMap<String, Vec<String>>,
+}
+
+impl ConfigOverrides {
+    /// Replaces the section's lines.
+    pub fn set_section<S: Into<String>>(
+        &mut self,
+        section: impl Into<String>,
+        lines: impl IntoIterator<Item = S>,
+    ) {
+        self.sections
+            .insert(section.into(), lines.into_iter().map(Into::into).collect());
+    }
+
+    /// Sets a `key=value` line in the section, keeping the section's other lines.
+    pub fn set_value(&mut self, section: impl Into<String>, key: &str, value: impl fmt::Display) {
+        let lines = self.sections.entry(section.into()).or_default();
+        let line = format!("{key}={value}");
+
+        match lines
+            .iter_mut()
+            .find(|line| line.split('=').next().map(str::trim) == Some(key))
+        {
+            Some(existing) => *existing = line,
+            None => lines.push(line),
+        }
+    }
+
+    /// Returns the lines set for the section.
+    pub fn section(&self, section: &str) -> Option<&[String]> {
+        self.sections.get(section).map(Vec::as_slice)
+    }
+
+    /// Replaces the overridden sections in the config and appends the ones it doesn't contain.
+    fn apply(&self, config: &str) -> String {
+        let mut applied = HashSet::new();
+        let mut config_str = String::with_capacity(config.len());
+        let mut skip_section = false;
+
+        for line in config.lines() {
+            if let Some(section) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                skip_section = false;
+                if let Some(lines) = self.sections.get(section) {
+                    config_str.push_str(line);
+                    config_str.push('\n');
+                    for line in lines {
+                        config_str.push_str(line);
+                        config_str.push('\n');
+                    }
+                    applied.insert(section);
+                    skip_section = true;
+                    continue;
+                }
+            } else if skip_section && !line.is_empty() {
+                continue;
+            }
+
+            config_str.push_str(line);
+            config_str.push('\n');
+        }
+
+        for (section, lines) in &self.sections {
+            if applied.contains(section.as_str()) {
+                continue;
+            }
+            config_str.push_str(&format!("[{section}]\n"));
+            for line in lines {
+                config_str.push_str(line);
+                config_str.push('\n');
+            }
+            config_str.push('\n');
+        }
+
+        config_str
+    }
 }
 
 pub struct RippledConfigFile;
@@ -72,19 +222,33 @@ impl RippledConfigFile {
 
         writeln!(&mut config_str, "[server]")?;
         writeln!(&mut config_str, "port_rpc_admin_local")?;
+        if config.enable_ws_admin {
+            writeln!(&mut config_str, "port_ws_admin_local")?;
+        }
         writeln!(&mut config_str, "port_peer")?;
         writeln!(&mut config_str)?;
 
+        let ip = config.listen_ip.unwrap_or_else(|| config.local_addr.ip());
+
         writeln!(&mut config_str, "[port_rpc_admin_local]")?;
         writeln!(&mut config_str, "port = {JSON_RPC_PORT}")?;
-        writeln!(&mut config_str, "ip = {}", config.local_addr.ip())?;
-        writeln!(&mut config_str, "admin = {}", config.local_addr.ip())?;
+        writeln!(&mut config_str, "ip = {ip}")?;
+        writeln!(&mut config_str, "admin = {ip}")?;
         writeln!(&mut config_str, "protocol = http")?;
         writeln!(&mut config_str)?;
 
+        if config.enable_ws_admin {
+            writeln!(&mut config_str, "[port_ws_admin_local]")?;
+            writeln!(&mut config_str, "port = {WS_PORT}")?;
+            writeln!(&mut config_str, "ip = {ip}")?;
+            writeln!(&mut config_str, "admin = {ip}")?;
+            writeln!(&mut config_str, "protocol = ws")?;
+            writeln!(&mut config_str)?;
+        }
+
         writeln!(&mut config_str, "[port_peer]")?;
         writeln!(&mut config_str, "port = {}", config.local_addr.port())?;
-        writeln!(&mut config_str, "ip = {}", config.local_addr.ip())?;
+        writeln!(&mut config_str, "ip = {ip}")?;
         writeln!(&mut config_str, "protocol = peer")?;
         writeln!(&mut config_str)?;
 
@@ -192,7 +356,10 @@ impl RippledConfigFile {
         writeln!(
             &mut config_str,
             "{}",
-            path.join(RIPPLED_DIR).join("debug.log").to_str().unwrap()
+            path.join(RIPPLED_DIR)
+                .join(RIPPLED_LOG_FILE)
+                .to_str()
+                .unwrap()
         )?;
         writeln!(&mut config_str)?;
 
@@ -202,6 +369,51 @@ impl RippledConfigFile {
 
         // 10. Example settings
 
-        Ok(config_str)
+        Ok(config.overrides.apply(&config_str))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn named_binaries_are_read() {
+        let dir = TempDir::new().unwrap();
+        fs::write(
+            dir.path().join(ZIGGURAT_CONFIG),
+            "path = \"/tmp\"\n\
+             start_command = \"rippled --silent\"\n\
+             [binaries]\n\
+             develop = \"/opt/develop/rippled\"\n",
+        )
+        .unwrap();
+
+        let meta = NodeMetaData::new(dir.path().to_owned()).unwrap();
+        assert_eq!(meta.start_command, "rippled");
+        assert_eq!(meta.start_args, ["--silent"]);
+        assert_eq!(
+            meta.binary("develop").unwrap(),
+            Path::new("/opt/develop/rippled")
+        );
+        assert!(meta.binary("stable").is_err());
+    }
+
+    #[test]
+    fn overrides_replace_and_append_sections() {
+        let mut overrides = ConfigOverrides::default();
+        overrides.set_section("peers_max", ["21"]);
+        overrides.set_value("voting", "reference_fee", 10);
+        overrides.set_value("voting", "account_reserve", 1_000_000);
+        overrides.set_value("voting", "reference_fee", 20);
+
+        let config = "[server]\nport_peer\n\n[peers_max]\n0\n\n";
+        assert_eq!(
+            overrides.apply(config),
+            "[server]\nport_peer\n\n[peers_max]\n21\n\n\
+             [voting]\nreference_fee=20\naccount_reserve=1000000\n\n"
+        );
     }
 }
@@ -12,6 +12,19 @@ pub const RIPPLE_SETUP_DIR: &str = "setup";
 /// Configuration file with paths to start rippled.
 pub const ZIGGURAT_CONFIG: &str = "config.toml";
 
+/// Configuration profiles of the tests, see [ConfigProfile](crate::tools::config::ConfigProfile).
+pub const ZIGGURAT_PROFILES: &str = "ziggurat.toml";
+
+/// Environment variable pointing the tests at an already running node instead of starting one,
+/// e.g. `ZIGGURAT_REMOTE_NODE=127.0.0.1:51235`, see [RemoteNode](crate::setup::node::RemoteNode).
+pub const REMOTE_NODE_ENV: &str = "ZIGGURAT_REMOTE_NODE";
+
+/// Environment variable overriding the remote node's JSON-RPC URL.
+pub const REMOTE_RPC_URL_ENV: &str = "ZIGGURAT_REMOTE_RPC_URL";
+
+/// Environment variable setting the remote node's admin WebSocket URL.
+pub const REMOTE_WS_URL_ENV: &str = "ZIGGURAT_REMOTE_WS_URL";
+
 /// Validators file name.
 pub const VALIDATORS_FILE_NAME: &str = "validators.txt";
 
@@ -28,18 +41,25 @@ pub const VALIDATOR_IPS: [&str; STATEFUL_NODES_COUNT] = ["127.0.0.1", "127.0.0.2
 pub const RIPPLED_CONFIG: &str = "rippled.cfg";
 pub const RIPPLED_DIR: &str = "rippled";
 
+/// Rippled's log file name, placed in [RIPPLED_DIR].
+pub const RIPPLED_LOG_FILE: &str = "debug.log";
+
 /// Rippled's JSON RPC port
 pub const JSON_RPC_PORT: u32 = 5005;
 
+/// Rippled's admin WebSocket port
+pub const WS_PORT: u32 = 6006;
+
 /// The default port to start a Rippled node on.
 pub const DEFAULT_PORT: u16 = 8080;
 
-/// [TestNet](crate::setup::testnet::TestNet)'s network id. The number here doesn't have any significance, but cannot be 0 nor 255.
-pub const TESTNET_NETWORK_ID: u32 = 239048;
-
 /// Timeout when waiting for [Node](crate::setup::node::Node)'s start.
 pub const CONNECTION_TIMEOUT: Duration = Duration::from_secs(10);
 
+/// Timeout when waiting for [Node](crate::setup::node::Node) to exit after a graceful shutdown
+/// request, it's killed afterwards.
+pub const GRACEFUL_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+
 /// Timeout when waiting for [TestNet](crate::setup::testnet::TestNet) to start.
 pub const TESTNET_READY_TIMEOUT: Duration = Duration::from_secs(60);
 
@@ -0,0 +1,198 @@
+//! Running nodes in Docker containers instead of local processes.
+//!
+//! The backend is selected by a `[docker]` table in Ziggurat's configuration file, e.g.:
+//!
+//! ```toml
+//! [docker]
+//! image = "xrpllabsofficial/xrpld:1.12.0"
+//! ```
+//!
+//! The node's directory is mounted at the same path within the container, so the paths in the
+//! generated rippled.cfg stay valid, and the node's ports are mapped to its address.
+
+use std::{
+    ffi::OsString,
+    io,
+    net::SocketAddr,
+    path::Path,
+    process::{Command, Output, Stdio},
+};
+
+use serde::Deserialize;
+
+/// Where the nodes run.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum NodeBackend {
+    /// A local process running the start command of Ziggurat's configuration file.
+    #[default]
+    Local,
+    /// A container running rippled's image.
+    Docker(DockerConfig),
+}
+
+/// The `[docker]` table of Ziggurat's configuration file.
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+pub struct DockerConfig {
+    /// The image to run.
+    pub image: String,
+    /// The rippled binary within the image.
+    #[serde(default = "default_command")]
+    pub command: String,
+    /// The network the container joins, e.g. `host` for nodes connecting to synthetic nodes
+    /// listening on the host's loopback addresses. The node's ports are mapped if unset.
+    #[serde(default)]
+    pub network: Option<String>,
+}
+
+fn default_command() -> String {
+    "rippled".to_owned()
+}
+
+/// The container a node runs in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Container {
+    /// The container's name, unique per node address.
+    pub name: String,
+    config: DockerConfig,
+}
+
+impl Container {
+    pub fn new(config: DockerConfig, addr: SocketAddr) -> Self {
+        Self {
+            name: format!("ziggurat-{}-{}", addr.ip(), addr.port()),
+            config,
+        }
+    }
+
+    /// Returns `true` if the node's ports are mapped, in which case it has to listen on all of
+    /// the container's addresses.
+    pub fn maps_ports(&self) -> bool {
+        self.config.network.is_none()
+    }
+
+    /// Returns the `docker run` arguments running the node in the foreground with the args.
+    ///
+    /// The container runs as the directory's owner, so the files the node writes can be
+    /// cleaned up. The ports are mapped to the address' IP unless the container joins a network.
+    pub fn run_args(
+        &self,
+        dir: &Path,
+        owner: Option<(u32, u32)>,
+        addr: SocketAddr,
+        ports: &[u16],
+        args: &[OsString],
+    ) -> Vec<OsString> {
+        let mut run_args: Vec<OsString> = vec!["run".into(), "--rm".into()];
+        run_args.extend(["--name".into(), self.name.clone().into()]);
+
+        let mut volume = dir.as_os_str().to_owned();
+        volume.push(":");
+        volume.push(dir);
+        run_args.extend(["--volume".into(), volume]);
+
+        if let Some((uid, gid)) = owner {
+            run_args.extend(["--user".into(), format!("{uid}:{gid}").into()]);
+        }
+
+        match &self.config.network {
+            Some(network) => run_args.extend(["--network".into(), network.into()]),
+            None => {
+                for port in ports {
+                    let mapping = format!("{}:{port}:{port}", addr.ip());
+                    run_args.extend(["--publish".into(), mapping.into()]);
+                }
+            }
+        }
+
+        run_args.extend(["--entrypoint".into(), self.config.command.clone().into()]);
+        run_args.push(self.config.image.clone().into());
+        run_args.extend_from_slice(args);
+        run_args
+    }
+
+    /// Sends the signal to the node, e.g. `KILL` or `TERM`.
+    pub fn kill(&self, signal: &str) -> io::Result<()> {
+        docker(&["kill", "--signal", signal, &self.name])
+    }
+
+    /// Runs rippled with the args within the container.
+    pub fn exec(&self, args: &[OsString]) -> io::Result<Output> {
+        Command::new("docker")
+            .args(["exec", &self.name, &self.config.command])
+            .args(args)
+            .stdin(Stdio::null())
+            .output()
+    }
+
+    /// Removes the container, e.g. one left behind by an aborted test run.
+    pub fn remove(&self) {
+        // Removing a missing container fails, which is fine.
+        let _ = Command::new("docker")
+            .args(["rm", "--force", &self.name])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status();
+    }
+}
+
+fn docker(args: &[&str]) -> io::Result<()> {
+    let output = Command::new("docker")
+        .args(args)
+        .stdin(Stdio::null())
+        .output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "`docker {}` failed with {}: {}",
+            args.join(" "),
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ports_are_mapped_to_the_node_address() {
+        let config: DockerConfig = toml::from_str("image = \"rippled:test\"").unwrap();
+        let addr = "127.0.0.2:8080".parse().unwrap();
+        let container = Container::new(config.clone(), addr);
+        assert_eq!(container.name, "ziggurat-127.0.0.2-8080");
+        assert!(container.maps_ports());
+
+        let args = container.run_args(
+            Path::new("/tmp/node"),
+            Some((1000, 1000)),
+            addr,
+            &[8080, 5005],
+            &["--conf".into(), "/tmp/node/rippled.cfg".into()],
+        );
+        let args = args
+            .iter()
+            .map(|arg| arg.to_str().unwrap())
+            .collect::<Vec<_>>()
+            .join(" ");
+        assert_eq!(
+            args,
+            "run --rm --name ziggurat-127.0.0.2-8080 --volume /tmp/node:/tmp/node \
+             --user 1000:1000 --publish 127.0.0.2:8080:8080 --publish 127.0.0.2:5005:5005 \
+             --entrypoint rippled rippled:test --conf /tmp/node/rippled.cfg"
+        );
+
+        let container = Container::new(
+            DockerConfig {
+                network: Some("host".into()),
+                ..config
+            },
+            addr,
+        );
+        let args = container.run_args(Path::new("/tmp/node"), None, addr, &[8080], &[]);
+        assert!(!container.maps_ports());
+        assert!(args.contains(&"host".into()));
+        assert!(!args.contains(&"--publish".into()));
+    }
+}
@@ -0,0 +1,88 @@
+//! Validator keys, manifests and tokens generated on demand.
+//!
+//! The keys of the testnet's validators are derived from their index, so a validator keeps its
+//! identity between the run creating the stateful nodes' ledgers and the runs loading them,
+//! without any secrets checked into the repository.
+
+use crate::tools::validator::{
+    create_manifest, create_validator_token, sha512_half, KeyType, ValidatorKey,
+};
+
+/// The sequence of the generated manifests.
+const MANIFEST_SEQUENCE: u32 = 1;
+
+/// Prefix of the seeds the testnet's validator keys are derived from.
+const VALIDATOR_SEED_PREFIX: &str = "ziggurat-validator";
+
+/// A validator's long-term master key and the signing key it validates with.
+#[derive(Clone)]
+pub struct ValidatorKeys {
+    /// Identifies the validator, listed in validators.txt.
+    pub master: ValidatorKey,
+    /// Signs validations, always a secp256k1 key as rippled requires for tokens.
+    pub signing: ValidatorKey,
+}
+
+impl ValidatorKeys {
+    /// Generates random keys, an ed25519 master key like rippled's `validator-keys` tool does.
+    pub fn generate() -> Self {
+        Self {
+            master: ValidatorKey::generate(KeyType::Ed25519),
+            signing: ValidatorKey::generate(KeyType::Secp256k1),
+        }
+    }
+
+    /// Derives the keys from the seed, the same seed always gives the same keys.
+    pub fn from_seed(seed: &[u8]) -> Self {
+        let derive = |purpose: &[u8]| sha512_half(&[seed, purpose].concat());
+
+        let master = ed25519_dalek::SigningKey::from_bytes(&derive(b"master"));
+        let signing = secp256k1::SecretKey::from_slice(&derive(b"signing"))
+            .expect("the derived secret should be a valid secp256k1 key");
+
+        Self {
+            master: ValidatorKey::Ed25519(master),
+            signing: ValidatorKey::Secp256k1(signing),
+        }
+    }
+
+    /// Returns the keys of the testnet's validator.
+    pub fn for_validator(idx: usize) -> Self {
+        Self::from_seed(format!("{VALIDATOR_SEED_PREFIX}-{idx}").as_bytes())
+    }
+
+    /// Returns the master public key as listed in validators.txt, e.g. `nHUSqn9...`.
+    pub fn node_public_key(&self) -> String {
+        self.master.node_public_key()
+    }
+
+    /// Returns the manifest binding the signing key to the master key.
+    pub fn manifest(&self) -> Vec<u8> {
+        create_manifest(MANIFEST_SEQUENCE, &self.master, &self.signing)
+    }
+
+    /// Returns the contents of the `[validator_token]` stanza of rippled.cfg.
+    pub fn token(&self) -> String {
+        create_validator_token(MANIFEST_SEQUENCE, &self.master, &self.signing)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn derived_keys_are_stable_and_distinct() {
+        let keys = ValidatorKeys::for_validator(0);
+
+        assert_eq!(
+            keys.node_public_key(),
+            ValidatorKeys::for_validator(0).node_public_key()
+        );
+        assert_ne!(
+            keys.node_public_key(),
+            ValidatorKeys::for_validator(1).node_public_key()
+        );
+        assert!(keys.node_public_key().starts_with('n'));
+    }
+}
@@ -6,7 +6,11 @@ use crate::setup::constants::{RIPPLE_WORK_DIR, ZIGGURAT_DIR};
 
 pub mod config;
 pub mod constants;
+pub mod docker;
+pub mod keys;
+pub mod network;
 pub mod node;
+pub mod stateful;
 pub mod testnet;
 
 pub fn build_ripple_work_path() -> io::Result<PathBuf> {
@@ -0,0 +1,125 @@
+//! Known XRPL networks and the parameters needed to join or mimic them.
+
+use std::{fmt, io, net::SocketAddr, str::FromStr};
+
+use tokio::net::lookup_host;
+
+use crate::setup::constants::DEFAULT_PORT;
+
+/// Parameters of an XRPL network.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NetworkProfile {
+    /// Name of the network, as accepted by [NetworkProfile::from_str].
+    pub name: &'static str,
+    /// Network's id, set in the `[network_id]` stanza of rippled.cfg and the `Network-ID`
+    /// handshake field.
+    pub network_id: u32,
+    /// The port peers listen on by default.
+    pub peer_port: u16,
+    /// Published hubs new nodes connect to, in the `<host>:<port>` format.
+    pub seed_hosts: &'static [&'static str],
+    /// Site publishing the network's recommended validator list.
+    pub unl_url: Option<&'static str>,
+}
+
+impl NetworkProfile {
+    /// The XRP Ledger mainnet.
+    pub const MAINNET: Self = Self {
+        name: "mainnet",
+        network_id: 0,
+        peer_port: 51235,
+        seed_hosts: &[
+            "r.ripple.com:51235",
+            "sahyadri.isrdc.in:51235",
+            "hubs.xrpkuwait.com:51235",
+            "hub.xrpl-commons.org:51235",
+        ],
+        unl_url: Some("https://vl.ripple.com"),
+    };
+
+    /// The public testnet.
+    pub const TESTNET: Self = Self {
+        name: "testnet",
+        network_id: 1,
+        peer_port: 51235,
+        seed_hosts: &["s.altnet.rippletest.net:51235"],
+        unl_url: Some("https://vl.altnet.rippletest.net"),
+    };
+
+    /// The public devnet.
+    pub const DEVNET: Self = Self {
+        name: "devnet",
+        network_id: 2,
+        peer_port: 51235,
+        seed_hosts: &["s.devnet.rippletest.net:51235"],
+        unl_url: Some("https://vl.devnet.rippletest.net"),
+    };
+
+    /// The local network formed by [TestNet](crate::setup::testnet::TestNet) and the stateful
+    /// nodes. The network id doesn't have any significance, but cannot be 0 nor 255.
+    pub const LOCAL: Self = Self {
+        name: "local",
+        network_id: 239048,
+        peer_port: DEFAULT_PORT,
+        seed_hosts: &[],
+        unl_url: None,
+    };
+
+    /// All the known networks.
+    pub const ALL: [Self; 4] = [Self::MAINNET, Self::TESTNET, Self::DEVNET, Self::LOCAL];
+
+    /// Resolves the seed hosts, hosts which can't be resolved are skipped.
+    pub async fn seed_addrs(&self) -> io::Result<Vec<SocketAddr>> {
+        let mut addrs = Vec::new();
+        for host in self.seed_hosts {
+            if let Ok(resolved) = lookup_host(host).await {
+                addrs.extend(resolved);
+            }
+        }
+
+        if addrs.is_empty() && !self.seed_hosts.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("couldn't resolve any of the {} seed hosts", self.name),
+            ));
+        }
+        Ok(addrs)
+    }
+}
+
+impl fmt::Display for NetworkProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name)
+    }
+}
+
+impl FromStr for NetworkProfile {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|profile| profile.name.eq_ignore_ascii_case(name))
+            .ok_or_else(|| {
+                let names: Vec<_> = Self::ALL.iter().map(|profile| profile.name).collect();
+                format!(
+                    "unknown network {name}, expected one of: {}",
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn profiles_are_parsed_by_name() {
+        for profile in NetworkProfile::ALL {
+            assert_eq!(profile.to_string().parse(), Ok(profile));
+        }
+        assert_eq!("TestNet".parse(), Ok(NetworkProfile::TESTNET));
+        assert!("altnet".parse::<NetworkProfile>().is_err());
+    }
+}
@@ -1,26 +1,44 @@
 use std::{
     collections::HashSet,
+    env,
+    ffi::{OsStr, OsString},
     fs, io,
-    net::{Ipv4Addr, SocketAddr, SocketAddrV4},
+    net::{IpAddr, Ipv4Addr, SocketAddr, SocketAddrV4, ToSocketAddrs},
+    os::unix::fs::MetadataExt,
     path::{Path, PathBuf},
     process::{Child, Command, ExitStatus, Stdio},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex, MutexGuard,
+    },
+    thread,
+    time::Instant,
 };
 
-use anyhow::Result;
-use fs_extra::{dir, file};
-use tokio::{io::AsyncWriteExt, net::TcpStream, time::Duration};
-
-use crate::setup::{
-    build_ripple_work_path,
-    config::{NodeMetaData, RippledConfigFile},
-    constants::{
-        CONNECTION_TIMEOUT, DEFAULT_PORT, JSON_RPC_PORT, RIPPLED_CONFIG, RIPPLE_SETUP_DIR,
-        STATEFUL_NODES_COUNT, STATEFUL_NODES_DIR, TESTNET_NETWORK_ID, VALIDATORS_FILE_NAME,
-        VALIDATOR_IPS,
+use anyhow::{Context, Result};
+use fs_extra::file;
+use tokio::{io::AsyncWriteExt, net::TcpStream, task::JoinHandle, time::Duration};
+
+use crate::{
+    setup::{
+        build_ripple_work_path,
+        config::{ConfigOverrides, NodeMetaData, NodeSize, RippledConfigFile},
+        constants::{
+            CONNECTION_TIMEOUT, DEFAULT_PORT, GRACEFUL_SHUTDOWN_TIMEOUT, JSON_RPC_PORT,
+            REMOTE_NODE_ENV, REMOTE_RPC_URL_ENV, REMOTE_WS_URL_ENV, RIPPLED_CONFIG, RIPPLED_DIR,
+            RIPPLED_LOG_FILE, RIPPLE_SETUP_DIR, VALIDATORS_FILE_NAME, VALIDATOR_IPS, WS_PORT,
+        },
+        docker::{Container, NodeBackend},
+        network::NetworkProfile,
+        stateful::StatefulSlot,
+        testnet::get_validator_token,
     },
-    testnet::get_validator_token,
+    tools::node_log::LogWatcher,
 };
 
+/// How often a supervised node is checked for having crashed.
+const SUPERVISION_INTERVAL: Duration = Duration::from_millis(500);
+
 async fn wait_for_start(addr: SocketAddr) {
     tokio::time::timeout(CONNECTION_TIMEOUT, async {
         const SLEEP: Duration = Duration::from_millis(10);
@@ -44,7 +62,20 @@ pub enum ChildExitCode {
     ErrorCode(Option<i32>),
 }
 
+/// How [Node::stop] ends the node's process.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ShutdownMode {
+    /// Kills the process right away.
+    #[default]
+    Kill,
+    /// Sends `SIGTERM`, letting the node close its connections and databases.
+    Terminate,
+    /// Runs `rippled stop`, which asks the node to stop through the admin RPC.
+    StopCommand,
+}
+
 /// Node type is used to select different startup configurations.
+#[derive(Clone, Copy)]
 pub enum NodeType {
     /// A temporary node used to store ledger data for stateful nodes. Should not be used otherwise.
     Testnet,
@@ -54,27 +85,107 @@ pub enum NodeType {
     Stateful,
 }
 
+/// An already running node, e.g. in a container or a testnet deployment, which the tests are
+/// pointed at instead of starting a local one.
+///
+/// The node isn't managed by the tests: it's neither configured, nor stopped, and it has no local
+/// process or log.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteNode {
+    /// The node's peer protocol address.
+    pub addr: SocketAddr,
+    /// The node's JSON-RPC URL, on [JSON_RPC_PORT] of the node's host by default.
+    pub rpc_url: String,
+    /// The node's admin WebSocket URL, if it's reachable.
+    pub ws_url: Option<String>,
+}
+
+impl RemoteNode {
+    pub fn new(addr: SocketAddr) -> Self {
+        Self {
+            addr,
+            rpc_url: format!("http://{}:{JSON_RPC_PORT}", addr.ip()),
+            ws_url: None,
+        }
+    }
+
+    /// Reads the node's `host:port` from [REMOTE_NODE_ENV], and its URLs from
+    /// [REMOTE_RPC_URL_ENV] and [REMOTE_WS_URL_ENV] if set.
+    ///
+    /// Returns `None` if [REMOTE_NODE_ENV] isn't set.
+    pub fn from_env() -> Result<Option<Self>> {
+        let Ok(addr) = env::var(REMOTE_NODE_ENV) else {
+            return Ok(None);
+        };
+
+        Self::parse(
+            &addr,
+            env::var(REMOTE_RPC_URL_ENV).ok(),
+            env::var(REMOTE_WS_URL_ENV).ok(),
+        )
+        .map(Some)
+    }
+
+    fn parse(addr: &str, rpc_url: Option<String>, ws_url: Option<String>) -> Result<Self> {
+        // The host may be a name, e.g. of a container.
+        let addr = addr
+            .to_socket_addrs()
+            .with_context(|| format!("{REMOTE_NODE_ENV} should be host:port, got {addr}"))?
+            .next()
+            .with_context(|| format!("{addr} doesn't resolve to any address"))?;
+
+        let mut remote = Self::new(addr);
+        if let Some(rpc_url) = rpc_url {
+            remote.rpc_url = rpc_url;
+        }
+        remote.ws_url = ws_url;
+        Ok(remote)
+    }
+}
+
+/// Overrides the start command from Ziggurat's configuration file.
+#[derive(Debug, Clone)]
+enum Binary {
+    Path(PathBuf),
+    /// One of the named binaries of Ziggurat's configuration file.
+    Named(String),
+}
+
 pub struct NodeBuilder {
     /// Node's startup configuration.
     conf: NodeConfig,
     /// Node's process metadata read from Ziggurat configuration files.
     meta: NodeMetaData,
-    /// Counter for served stateful nodes.
-    stateful_nodes_counter: usize,
+    /// Overrides the start command from Ziggurat's configuration file.
+    binary: Option<Binary>,
+    /// Address stateless nodes bind to.
+    stateless_addr: SocketAddr,
+    /// The node returned instead of starting one.
+    remote: Option<RemoteNode>,
 }
 
 impl NodeBuilder {
     /// Creates new [NodeBuilder] which can handle stateless nodes.
+    ///
+    /// If [REMOTE_NODE_ENV] is set, the builder returns the remote node instead of starting one,
+    /// see [NodeBuilder::remote].
     pub fn stateless() -> anyhow::Result<Self> {
         let setup_path = build_ripple_work_path()?.join(RIPPLE_SETUP_DIR);
 
         let conf = NodeConfig::default();
-        let meta = NodeMetaData::new(setup_path)?;
+        let remote = RemoteNode::from_env()?;
+        // There may be no local setup to run a remote node's tests on.
+        let meta = match remote {
+            Some(_) => NodeMetaData::default(),
+            None => NodeMetaData::new(setup_path)?,
+        };
 
         Ok(Self {
             conf,
             meta,
-            stateful_nodes_counter: 0,
+            binary: None,
+            stateless_addr: SocketAddr::new(VALIDATOR_IPS[0].parse().unwrap(), DEFAULT_PORT),
+            remote,
         })
     }
 
@@ -82,36 +193,34 @@ impl NodeBuilder {
     pub fn stateful() -> anyhow::Result<Self> {
         Ok(Self::stateless()
             .expect("failed to create a node builder")
-            .network_id(TESTNET_NETWORK_ID))
+            .network(&NetworkProfile::LOCAL))
     }
 
     /// Creates [Node] according to configuration and starts its process.
+    ///
+    /// Returns the remote node as is if one is set, whatever the configuration and type.
     pub async fn start(&mut self, target: &Path, node_type: NodeType) -> Result<Node> {
+        if let Some(remote) = &self.remote {
+            return Ok(Node::remote(remote.clone(), self.conf.clone()));
+        }
+
         if !target.exists() {
             fs::create_dir_all(target)?;
         }
 
         let setup_path = build_ripple_work_path()?.join(RIPPLE_SETUP_DIR);
+        let mut stateful_slot = None;
 
         match node_type {
             NodeType::Stateful => {
-                let node_idx = self.stateful_nodes_counter;
-                self.stateful_nodes_counter += 1;
-                assert!(
-                    self.stateful_nodes_counter <= STATEFUL_NODES_COUNT,
-                    "Not enough stateful nodes available"
-                );
-
-                let source = get_stateful_node_path(node_idx)?;
-
-                let mut copy_options = dir::CopyOptions::new();
-                copy_options.content_only = true;
-                copy_options.overwrite = true;
-                dir::copy(source, target, &copy_options)?;
-
-                self.conf.local_addr =
-                    SocketAddr::new(VALIDATOR_IPS[node_idx].parse().unwrap(), DEFAULT_PORT);
-                self.conf.validator_token = Some(get_validator_token(node_idx));
+                let slot = StatefulSlot::acquire();
+                slot.copy_state(target)?;
+
+                self.conf.local_addr = SocketAddr::new(slot.ip(), DEFAULT_PORT);
+                self.conf.validator_token = slot
+                    .is_validator()
+                    .then(|| get_validator_token(slot.index()));
+                stateful_slot = Some(slot);
                 self.meta.start_args = vec![
                     "--valid".into(),
                     "--quorum".into(),
@@ -128,12 +237,22 @@ impl NodeBuilder {
 
                 self.conf.network_id = None;
                 self.conf.validator_token = None;
-                self.conf.local_addr =
-                    SocketAddr::new(VALIDATOR_IPS[0].parse().unwrap(), DEFAULT_PORT);
+                self.conf.local_addr = self.stateless_addr;
             }
             NodeType::Testnet => (),
         }
 
+        let container = match &self.meta.backend {
+            NodeBackend::Docker(docker) => {
+                let container = Container::new(docker.clone(), self.conf.local_addr);
+                self.conf.listen_ip = container
+                    .maps_ports()
+                    .then_some(Ipv4Addr::UNSPECIFIED.into());
+                Some(container)
+            }
+            NodeBackend::Local => None,
+        };
+
         let rippled_cfg = RippledConfigFile::generate(&self.conf, target)?;
         let rippled_cfg_path = target.join(RIPPLED_CONFIG);
         fs::write(rippled_cfg_path.clone(), rippled_cfg)?;
@@ -149,13 +268,63 @@ impl NodeBuilder {
         self.meta.start_args.push("--conf".into());
         self.meta.start_args.push(rippled_cfg_path.into());
 
-        let node = self.start_node();
+        if let Some(container) = &container {
+            // A container left behind by an aborted run would take the name.
+            container.remove();
+
+            let mut ports = vec![self.conf.local_addr.port(), JSON_RPC_PORT as u16];
+            if self.conf.enable_ws_admin {
+                ports.push(WS_PORT as u16);
+            }
+            let owner = fs::metadata(target).map(|meta| (meta.uid(), meta.gid()));
+            self.meta.start_args = container.run_args(
+                target,
+                owner.ok(),
+                self.conf.local_addr,
+                &ports,
+                &self.meta.start_args,
+            );
+            self.meta.path = target.to_owned();
+        }
+
+        let binary = match &self.binary {
+            Some(Binary::Path(path)) => Some(path.clone()),
+            Some(Binary::Named(name)) => Some(self.meta.binary(name)?.to_owned()),
+            None => None,
+        };
+        let mut node = self.start_node(target, binary);
+        node.container = container;
+        node.stateful_slot = stateful_slot;
         wait_for_start(node.config.local_addr).await;
+        if self.conf.supervised {
+            node.supervise();
+        }
 
         self.meta = NodeMetaData::new(setup_path)?; // Reset args
         Ok(node)
     }
 
+    /// Points the tests at an already running node instead of starting one, or starts local
+    /// nodes again if `None`.
+    pub fn remote(mut self, remote: Option<RemoteNode>) -> Self {
+        self.remote = remote;
+        self
+    }
+
+    /// Sets how the node is stopped, gracefully stopped nodes are killed if they don't exit in
+    /// time.
+    pub fn shutdown_mode(mut self, mode: ShutdownMode) -> Self {
+        self.conf.shutdown_mode = mode;
+        self
+    }
+
+    /// Restarts the node with the same configuration whenever it exits on its own, e.g. after a
+    /// crash, see [Node::restart_count].
+    pub fn supervised(mut self, supervised: bool) -> Self {
+        self.conf.supervised = supervised;
+        self
+    }
+
     /// Enables history sharding.
     pub fn enable_sharding(mut self, enabled: bool) -> Self {
         self.conf.enable_sharding = enabled;
@@ -168,12 +337,25 @@ impl NodeBuilder {
         self
     }
 
+    /// Opens the admin WebSocket port, see [Node::ws_url].
+    pub fn enable_ws_admin(mut self, enabled: bool) -> Self {
+        self.conf.enable_ws_admin = enabled;
+        self
+    }
+
     /// Sets address to bind to.
     pub fn set_addr(mut self, addr: SocketAddr) -> Self {
         self.conf.local_addr = addr;
         self
     }
 
+    /// Sets address stateless nodes bind to, the first validator's address by default.
+    /// Stateless nodes running side by side need distinct addresses.
+    pub fn stateless_addr(mut self, addr: SocketAddr) -> Self {
+        self.stateless_addr = addr;
+        self
+    }
+
     /// Sets initial peers for the node.
     pub fn initial_peers(mut self, addrs: Vec<SocketAddr>) -> Self {
         self.conf.initial_peers = addrs.into_iter().collect();
@@ -199,41 +381,133 @@ impl NodeBuilder {
         self
     }
 
+    /// Configures the node to join the network.
+    pub fn network(self, profile: &NetworkProfile) -> Self {
+        self.network_id(profile.network_id)
+    }
+
+    /// Sets the lines of a rippled.cfg section, replacing the generated ones if the section is
+    /// part of the default configuration.
+    pub fn config_section<S: Into<String>>(
+        mut self,
+        section: impl Into<String>,
+        lines: impl IntoIterator<Item = S>,
+    ) -> Self {
+        self.conf.overrides.set_section(section, lines);
+        self
+    }
+
+    /// Sets the transaction cost the node votes for, in drops.
+    pub fn fee_reference(mut self, drops: u64) -> Self {
+        self.conf
+            .overrides
+            .set_value("voting", "reference_fee", drops);
+        self
+    }
+
+    /// Sets the account reserve the node votes for, in drops.
+    pub fn fee_account_reserve(mut self, drops: u64) -> Self {
+        self.conf
+            .overrides
+            .set_value("voting", "account_reserve", drops);
+        self
+    }
+
+    /// Sets the owner reserve the node votes for, in drops.
+    pub fn fee_owner_reserve(mut self, drops: u64) -> Self {
+        self.conf
+            .overrides
+            .set_value("voting", "owner_reserve", drops);
+        self
+    }
+
+    /// Sets the number of past ledgers to acquire on startup. Mustn't exceed the `online_delete`
+    /// setting of the generated config.
+    pub fn ledger_history(mut self, ledgers: u32) -> Self {
+        self.conf
+            .overrides
+            .set_section("ledger_history", [ledgers.to_string()]);
+        self
+    }
+
+    /// Sets the node's size preset.
+    pub fn node_size(mut self, size: NodeSize) -> Self {
+        self.conf
+            .overrides
+            .set_section("node_size", [size.to_string()]);
+        self
+    }
+
+    /// Runs the given rippled binary instead of the one from Ziggurat's configuration file,
+    /// e.g. to start a different rippled version. Ignored by the Docker backend.
+    pub fn binary(mut self, path: impl Into<PathBuf>) -> Self {
+        self.binary = Some(Binary::Path(path.into()));
+        self
+    }
+
+    /// Runs the binary of the given name from the `[binaries]` table of Ziggurat's configuration
+    /// file, e.g. `develop`. Starting the node fails if there's no such binary. Ignored by the
+    /// Docker backend.
+    pub fn with_binary(mut self, name: impl Into<String>) -> Self {
+        self.binary = Some(Binary::Named(name.into()));
+        self
+    }
+
     /// Sets whether to log the node's output to Ziggurat's output stream.
     pub fn log_to_stdout(mut self, log_to_stdout: bool) -> Self {
         self.conf.log_to_stdout = log_to_stdout;
         self
     }
 
-    fn start_node(&self) -> Node {
-        let (stdout, stderr) = match self.conf.log_to_stdout {
-            true => (Stdio::inherit(), Stdio::inherit()),
-            false => (Stdio::null(), Stdio::null()),
+    fn start_node(&self, target: &Path, binary: Option<PathBuf>) -> Node {
+        let start_command = match (&binary, &self.meta.backend) {
+            (Some(binary), NodeBackend::Local) => binary.as_os_str(),
+            _ => &self.meta.start_command,
         };
 
-        let child = Command::new(&self.meta.start_command)
-            .current_dir(&self.meta.path)
-            .args(&self.meta.start_args)
-            .stdin(Stdio::null())
-            .stdout(stdout)
-            .stderr(stderr)
-            .spawn()
+        let child = spawn_node(start_command, &self.meta, self.conf.log_to_stdout)
             .expect("node failed to start");
 
         Node {
-            child,
+            child: Some(Arc::new(Mutex::new(child))),
+            remote: None,
+            container: None,
+            restarts: Default::default(),
+            supervisor: None,
             meta: self.meta.clone(),
             config: self.conf.clone(),
+            log_path: target.join(RIPPLED_DIR).join(RIPPLED_LOG_FILE),
+            command: start_command.to_owned(),
+            config_path: target.join(RIPPLED_CONFIG),
+            stateful_slot: None,
         }
     }
 }
 
+/// Starts the node's process.
+fn spawn_node(command: &OsStr, meta: &NodeMetaData, log_to_stdout: bool) -> io::Result<Child> {
+    let (stdout, stderr) = match log_to_stdout {
+        true => (Stdio::inherit(), Stdio::inherit()),
+        false => (Stdio::null(), Stdio::null()),
+    };
+
+    Command::new(command)
+        .current_dir(&meta.path)
+        .args(&meta.start_args)
+        .stdin(Stdio::null())
+        .stdout(stdout)
+        .stderr(stderr)
+        .spawn()
+}
+
 /// Startup configuration for the node.
 /// Some fields are written to the node's configuration file.
 #[derive(Debug, Clone)]
 pub struct NodeConfig {
     /// The socket address of the node.
     pub local_addr: SocketAddr,
+    /// The IP the node listens on, the address' IP if unset.
+    pub listen_ip: Option<IpAddr>,
     /// The initial peer set of the node.
     pub initial_peers: HashSet<SocketAddr>,
     /// The initial max number of peer connections to allow.
@@ -248,12 +522,21 @@ pub struct NodeConfig {
     pub enable_sharding: bool,
     /// Setting this option to true will enable clustering.
     pub enable_cluster: bool,
+    /// Setting this option to true will open the admin WebSocket port.
+    pub enable_ws_admin: bool,
+    /// Sections replacing or extending the generated configuration file.
+    pub overrides: ConfigOverrides,
+    /// How the node is stopped.
+    pub shutdown_mode: ShutdownMode,
+    /// Setting this option to true will restart the node whenever it exits on its own.
+    pub supervised: bool,
 }
 
 impl Default for NodeConfig {
     fn default() -> Self {
         Self {
             local_addr: SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::LOCALHOST, DEFAULT_PORT)),
+            listen_ip: None,
             initial_peers: Default::default(),
             max_peers: 0,
             validator_token: None,
@@ -261,15 +544,35 @@ impl Default for NodeConfig {
             log_to_stdout: false,
             enable_sharding: false,
             enable_cluster: false,
+            enable_ws_admin: false,
+            overrides: Default::default(),
+            shutdown_mode: Default::default(),
+            supervised: false,
         }
     }
 }
 
 pub struct Node {
-    child: Child,
+    /// The node's process, replaced by the supervisor when the node is restarted. Remote nodes
+    /// have none.
+    child: Option<Arc<Mutex<Child>>>,
+    /// Set if the node is a remote one, see [RemoteNode].
+    remote: Option<RemoteNode>,
+    /// The container the node runs in, the process is the `docker run` command then.
+    container: Option<Container>,
+    /// The number of times the supervisor restarted the node.
+    restarts: Arc<AtomicUsize>,
+    supervisor: Option<JoinHandle<()>>,
     config: NodeConfig,
-    #[allow(dead_code)]
     meta: NodeMetaData,
+    /// Path to the node's log file.
+    log_path: PathBuf,
+    /// The command the node was started with.
+    command: OsString,
+    /// Path to the node's configuration file.
+    config_path: PathBuf,
+    /// The stateful node's slot in the pool, released once the node is dropped.
+    stateful_slot: Option<StatefulSlot>,
 }
 
 impl Node {
@@ -279,13 +582,80 @@ impl Node {
             .unwrap()
     }
 
+    /// Returns a handle to the remote node, which only points the tests at it.
+    fn remote(remote: RemoteNode, mut config: NodeConfig) -> Self {
+        config.local_addr = remote.addr;
+
+        Self {
+            child: None,
+            remote: Some(remote),
+            container: None,
+            restarts: Default::default(),
+            supervisor: None,
+            config,
+            meta: Default::default(),
+            log_path: Default::default(),
+            command: Default::default(),
+            config_path: Default::default(),
+            stateful_slot: None,
+        }
+    }
+
+    /// Returns `true` if the node is a remote one, see [RemoteNode].
+    pub fn is_remote(&self) -> bool {
+        self.remote.is_some()
+    }
+
+    /// Stops the node the way it was configured to, see [NodeBuilder::shutdown_mode].
     pub fn stop(&mut self) -> io::Result<ChildExitCode> {
-        match self.child.try_wait()? {
-            None => self.child.kill()?,
-            Some(status) => return Ok(ChildExitCode::ErrorCode(status.code())),
+        self.stop_with(self.config.shutdown_mode)
+    }
+
+    /// Stops the node, a node which doesn't exit after a graceful shutdown request is killed
+    /// once [GRACEFUL_SHUTDOWN_TIMEOUT] passes.
+    ///
+    /// A remote node is left running.
+    pub fn stop_with(&mut self, mode: ShutdownMode) -> io::Result<ChildExitCode> {
+        if self.is_remote() {
+            return Ok(ChildExitCode::Success);
+        }
+
+        // The supervisor restarts the node under the lock, so once the lock is taken below the
+        // aborted supervisor can't restart the node anymore.
+        if let Some(supervisor) = self.supervisor.take() {
+            supervisor.abort();
         }
 
-        let exit_status = self.child.wait()?;
+        if let Some(status) = self.child().try_wait()? {
+            return Ok(ChildExitCode::ErrorCode(status.code()));
+        }
+
+        let requested = match mode {
+            ShutdownMode::Kill => Ok(()),
+            ShutdownMode::Terminate => self.terminate(),
+            ShutdownMode::StopCommand => self.run_stop_command(),
+        };
+        let exited = match requested {
+            Ok(()) if mode != ShutdownMode::Kill => {
+                self.wait_for_exit(GRACEFUL_SHUTDOWN_TIMEOUT)?
+            }
+            Ok(()) => false,
+            Err(e) => {
+                eprintln!("unable to stop the node gracefully, killing it instead: {e}");
+                false
+            }
+        };
+        if !exited {
+            // Killing `docker run` would leave the container running.
+            if let Some(container) = &self.container {
+                if let Err(e) = container.kill("KILL") {
+                    eprintln!("unable to kill the node's container: {e}");
+                }
+            }
+            self.child().kill()?;
+        }
+
+        let exit_status = self.child().wait()?;
 
         match exit_status.code() {
             None => Ok(ChildExitCode::Success),
@@ -294,7 +664,119 @@ impl Node {
         }
     }
 
+    fn terminate(&self) -> io::Result<()> {
+        if let Some(container) = &self.container {
+            return container.kill("TERM");
+        }
+
+        let status = Command::new("kill")
+            .args(["-TERM", &self.pid().to_string()])
+            .status()?;
+        if !status.success() {
+            return Err(io::Error::other(format!("`kill` failed with {status}")));
+        }
+        Ok(())
+    }
+
+    fn run_stop_command(&self) -> io::Result<()> {
+        let output = match &self.container {
+            Some(container) => container.exec(&[
+                "--conf".into(),
+                self.config_path.clone().into(),
+                "stop".into(),
+            ])?,
+            None => Command::new(&self.command)
+                .current_dir(&self.meta.path)
+                .arg("--conf")
+                .arg(&self.config_path)
+                .arg("stop")
+                .stdin(Stdio::null())
+                .output()?,
+        };
+        if !output.status.success() {
+            return Err(io::Error::other(format!(
+                "`rippled stop` failed with {}: {}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        Ok(())
+    }
+
+    /// Returns `true` if the process exited within the timeout.
+    fn wait_for_exit(&mut self, timeout: Duration) -> io::Result<bool> {
+        const SLEEP: Duration = Duration::from_millis(100);
+
+        let deadline = Instant::now() + timeout;
+        while Instant::now() < deadline {
+            if self.child().try_wait()?.is_some() {
+                return Ok(true);
+            }
+            thread::sleep(SLEEP);
+        }
+        Ok(self.child().try_wait()?.is_some())
+    }
+
+    fn child(&self) -> MutexGuard<'_, Child> {
+        self.child
+            .as_ref()
+            .expect("a remote node has no local process")
+            .lock()
+            .unwrap()
+    }
+
+    /// Starts restarting the node whenever its process exits.
+    fn supervise(&mut self) {
+        let Some(child) = self.child.clone() else {
+            return;
+        };
+        let restarts = self.restarts.clone();
+        let command = self.command.clone();
+        let meta = self.meta.clone();
+        let log_to_stdout = self.config.log_to_stdout;
+
+        self.supervisor = Some(tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(SUPERVISION_INTERVAL).await;
+
+                let mut child = child.lock().unwrap();
+                let status = match child.try_wait() {
+                    Ok(Some(status)) => status,
+                    Ok(None) => continue,
+                    Err(e) => {
+                        eprintln!("unable to check the supervised node: {e}");
+                        continue;
+                    }
+                };
+                match spawn_node(&command, &meta, log_to_stdout) {
+                    Ok(restarted) => {
+                        eprintln!("the supervised node exited with {status}, restarted it");
+                        *child = restarted;
+                        restarts.fetch_add(1, Ordering::Relaxed);
+                    }
+                    Err(e) => eprintln!("unable to restart the supervised node: {e}"),
+                }
+            }
+        }));
+    }
+
+    /// Returns the number of times the node was restarted after exiting on its own, see
+    /// [NodeBuilder::supervised].
+    pub fn restart_count(&self) -> usize {
+        self.restarts.load(Ordering::Relaxed)
+    }
+
+    /// Returns `true` if the node's process hasn't exited yet.
+    ///
+    /// A supervised node which crashed is reported as running again once it's restarted. A remote
+    /// node is assumed to be running.
+    pub fn is_running(&mut self) -> bool {
+        self.is_remote() || matches!(self.child().try_wait(), Ok(None))
+    }
+
     /// Non-blocking function which periodically checks the node's status code.
+    ///
+    /// Panics for a remote node.
     pub async fn wait_until_exit(&mut self) -> ExitStatus {
         // Once the async Drop trait support is introduced in Rust,
         // we can remove the loop and use a non-blocking tokio::process::Command
@@ -307,7 +789,8 @@ impl Node {
         //
         // So looping with a non-blocking try_wait() is the alternative solution.
         loop {
-            match self.child.try_wait().expect("waiting try failed") {
+            let status = self.child().try_wait().expect("waiting try failed");
+            match status {
                 None => {
                     tokio::time::sleep(Duration::from_millis(500)).await;
                     continue;
@@ -321,13 +804,53 @@ impl Node {
         self.config.local_addr
     }
 
+    /// Returns the OS-assigned process identifier of the node.
+    ///
+    /// The identifier changes whenever a supervised node is restarted. Panics for a remote node.
+    /// For a node running in a container, it's the identifier of the `docker run` command.
+    pub fn pid(&self) -> u32 {
+        self.child().id()
+    }
+
+    /// Returns the path to the node's log file, empty for a remote node.
+    pub fn log_path(&self) -> &Path {
+        &self.log_path
+    }
+
+    /// Watches the lines the node logs from now on.
+    pub async fn log_watcher(&self) -> LogWatcher {
+        LogWatcher::tail(&self.log_path).await
+    }
+
     pub fn rpc_url(&self) -> String {
+        if let Some(remote) = &self.remote {
+            return remote.rpc_url.clone();
+        }
+
         format!(
             "http://{addr}:{port}",
             addr = self.config.local_addr.ip(),
             port = JSON_RPC_PORT
         )
     }
+
+    /// Returns the URL of the node's admin WebSocket port, see [WsClient](crate::tools::rpc::ws::WsClient).
+    ///
+    /// Returns `None` unless the port was opened with [NodeBuilder::enable_ws_admin], or a remote
+    /// node's URL was set.
+    pub fn ws_url(&self) -> Option<String> {
+        if let Some(remote) = &self.remote {
+            return remote.ws_url.clone();
+        }
+
+        self.config.enable_ws_admin.then(|| {
+            format!(
+                "ws://{addr}:{port}",
+                addr = self.config.local_addr.ip(),
+                port = WS_PORT
+            )
+        })
+    }
 }
 
 impl Drop for Node {
@@ -339,24 +862,44 @@ impl Drop for Node {
     }
 }
 
-fn get_stateful_node_path(node_dir: usize) -> io::Result<PathBuf> {
-    let ziggurat_path = build_ripple_work_path()?;
-    Ok(ziggurat_path
-        .join(STATEFUL_NODES_DIR)
-        .join(node_dir.to_string()))
-}
-
 #[cfg(test)]
 mod test {
     use tempfile::TempDir;
-    use tokio::time::sleep;
+    use tokio::time::{sleep, timeout};
 
     use super::*;
+    use crate::{
+        setup::constants::STATEFUL_NODES_COUNT,
+        tools::{
+            constants::EXPECTED_RESULT_TIMEOUT,
+            rpc::ws::{StreamEvent, SubscriptionStream, WsClient},
+        },
+    };
 
     const STATELESS_NODE_CNT: usize = 3; // Any number should work
 
     const SLEEP: Duration = Duration::from_millis(100);
 
+    #[test]
+    fn remote_node_urls_default_to_its_host() {
+        let remote = RemoteNode::parse("127.0.0.2:51235", None, None).unwrap();
+        assert_eq!(remote.addr, "127.0.0.2:51235".parse().unwrap());
+        assert_eq!(remote.rpc_url, "http://127.0.0.2:5005");
+        assert_eq!(remote.ws_url, None);
+
+        let remote = RemoteNode::parse(
+            "localhost:51235",
+            Some("http://rpc:1234".into()),
+            Some("ws://ws:4321".into()),
+        )
+        .unwrap();
+        assert_eq!(remote.addr.port(), 51235);
+        assert_eq!(remote.rpc_url, "http://rpc:1234");
+        assert_eq!(remote.ws_url.as_deref(), Some("ws://ws:4321"));
+
+        assert!(RemoteNode::parse("51235", None, None).is_err());
+    }
+
     #[tokio::test]
     #[ignore = "use only when changing src/setup files"]
     async fn run_stateless_nodes_in_parallel() {
@@ -418,22 +961,59 @@ mod test {
 
     #[tokio::test]
     #[ignore = "use only when changing src/setup files"]
-    #[should_panic]
-    async fn run_too_many_stateful_nodes_sequentially() {
+    async fn run_more_stateful_nodes_than_validators_in_parallel() {
         let mut builder = NodeBuilder::stateful().expect("Can't build a stateful node");
+        let targets = (0..STATEFUL_NODES_COUNT + 2)
+            .map(|_| TempDir::new().expect("Can't build tmp dir"))
+            .collect::<Vec<_>>();
+        let mut nodes = Vec::with_capacity(targets.len());
 
-        for _ in 0..STATEFUL_NODES_COUNT + 1 {
-            let target = TempDir::new().expect("Can't build tmp dir");
-            let mut node = builder
+        for target in &targets {
+            let node = builder
                 .start(target.path(), NodeType::Stateful)
                 .await
                 .expect("Unable to start node");
+            nodes.push(node);
+        }
 
-            sleep(SLEEP).await;
+        let addrs = nodes.iter().map(Node::addr).collect::<HashSet<_>>();
+        assert_eq!(addrs.len(), nodes.len());
+
+        sleep(SLEEP).await;
+
+        for mut node in nodes {
             node.stop().unwrap();
         }
     }
 
+    #[tokio::test]
+    #[ignore = "use only when changing src/setup files"]
+    async fn subscribe_to_ledgers_over_ws_admin() {
+        let target = TempDir::new().expect("Can't build tmp dir");
+        let mut node = NodeBuilder::stateful()
+            .expect("Can't build a stateful node")
+            .enable_ws_admin(true)
+            .start(target.path(), NodeType::Stateful)
+            .await
+            .expect("Unable to start node");
+
+        let mut client = WsClient::connect(&node.ws_url().unwrap())
+            .await
+            .expect("Unable to connect to the WebSocket port");
+        client
+            .subscribe(&[SubscriptionStream::Ledger])
+            .await
+            .unwrap();
+
+        let event = timeout(EXPECTED_RESULT_TIMEOUT, client.next_event())
+            .await
+            .expect("no ledger closed in time")
+            .unwrap();
+        assert!(matches!(event, StreamEvent::LedgerClosed(_)));
+
+        node.stop().unwrap();
+    }
+
     #[tokio::test]
     #[ignore = "use only when changing src/setup files"]
     async fn run_stateful_nodes_in_parallel() {
@@ -456,4 +1036,48 @@ mod test {
             node.stop().unwrap();
         }
     }
+
+    #[tokio::test]
+    #[ignore = "use only when changing src/setup files"]
+    async fn stop_stateless_nodes_gracefully() {
+        let mut builder = NodeBuilder::stateless().expect("Can't build a stateless node");
+
+        for mode in [ShutdownMode::Terminate, ShutdownMode::StopCommand] {
+            let target = TempDir::new().expect("Can't build tmp dir");
+
+            let mut node = builder
+                .start(target.path(), NodeType::Stateless)
+                .await
+                .expect("Unable to start node");
+
+            sleep(SLEEP).await;
+            assert_eq!(node.stop_with(mode).unwrap(), ChildExitCode::Success);
+        }
+    }
+
+    #[tokio::test]
+    #[ignore = "use only when changing src/setup files"]
+    async fn restart_crashed_supervised_node() {
+        let target = TempDir::new().expect("Can't build tmp dir");
+        let mut node = NodeBuilder::stateless()
+            .expect("Can't build a stateless node")
+            .supervised(true)
+            .start(target.path(), NodeType::Stateless)
+            .await
+            .expect("Unable to start node");
+
+        let pid = node.pid();
+        Command::new("kill")
+            .args(["-KILL", &pid.to_string()])
+            .status()
+            .unwrap();
+        sleep(SUPERVISION_INTERVAL * 3).await;
+
+        assert_eq!(node.restart_count(), 1);
+        assert_ne!(node.pid(), pid);
+        assert!(node.is_running());
+
+        node.stop().unwrap();
+        assert_eq!(node.restart_count(), 1);
+    }
 }
@@ -0,0 +1,235 @@
+//! Provisioning of stateful nodes.
+//!
+//! The ledger state of the testnet's validators is saved once into [STATEFUL_NODES_COUNT]
+//! template directories with [generate_state]. Every stateful node gets a copy of one of the
+//! templates, the first [STATEFUL_NODES_COUNT] nodes run as the testnet's validators while any
+//! further nodes follow them from a copy of the same state.
+
+use std::{
+    collections::BTreeSet,
+    fmt, fs, io,
+    net::{IpAddr, Ipv4Addr},
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::Duration,
+};
+
+use anyhow::{anyhow, ensure};
+use fs_extra::dir;
+use tokio::time::{sleep, timeout};
+
+use crate::{
+    setup::{
+        build_ripple_work_path,
+        constants::{RIPPLED_CONFIG, STATEFUL_NODES_COUNT, STATEFUL_NODES_DIR, VALIDATOR_IPS},
+        testnet::TestNet,
+    },
+    tools::{
+        accounts::{Account, TEST_ACCOUNT},
+        rpc::{get_ledger_info, submit_transaction, wait_for_account_data},
+    },
+};
+
+/// The validated ledger index the testnet runs up to by default before its state is saved.
+pub const DEFAULT_TARGET_LEDGER: u32 = 128;
+
+/// The amount of drops the genesis account sends to [TEST_ACCOUNT].
+const TEST_ACCOUNT_FUNDING: u64 = 5_000_000_000;
+/// Time for the testnet to reach consensus and create the genesis ledger.
+const TESTNET_START_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+/// Time for a submitted transaction to make it into a ledger.
+const FUNDING_TIMEOUT: Duration = Duration::from_secs(60);
+/// Time for the testnet to reach the target ledger.
+const TARGET_LEDGER_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+/// The interval of polling the testnet's validated ledger.
+const LEDGER_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The slots in use by running stateful nodes, shared by all the builders in the process.
+static SLOTS_IN_USE: Mutex<BTreeSet<usize>> = Mutex::new(BTreeSet::new());
+
+/// The number of stateful nodes which can run at the same time, one per loopback address.
+const MAX_SLOTS: usize = 254;
+
+/// A stateful node's reserved slot in the pool, which determines the node's address and
+/// identity. The slot is released when dropped.
+#[derive(Debug)]
+pub struct StatefulSlot {
+    idx: usize,
+}
+
+impl StatefulSlot {
+    /// Reserves the lowest free slot.
+    ///
+    /// Panics if all the slots are in use.
+    pub fn acquire() -> Self {
+        let mut in_use = SLOTS_IN_USE.lock().unwrap();
+        let idx = (0..MAX_SLOTS)
+            .find(|idx| !in_use.contains(idx))
+            .expect("Not enough stateful nodes available");
+        in_use.insert(idx);
+
+        Self { idx }
+    }
+
+    /// Returns the slot's index.
+    pub fn index(&self) -> usize {
+        self.idx
+    }
+
+    /// Returns `true` if the node in this slot runs as one of the testnet's validators.
+    pub fn is_validator(&self) -> bool {
+        self.idx < STATEFUL_NODES_COUNT
+    }
+
+    /// Returns the address the node in this slot binds to.
+    pub fn ip(&self) -> IpAddr {
+        match VALIDATOR_IPS.get(self.idx) {
+            Some(ip) => ip.parse().unwrap(),
+            None => Ipv4Addr::new(127, 0, 0, self.idx as u8 + 1).into(),
+        }
+    }
+
+    /// Copies the slot's template state into the node's directory.
+    pub fn copy_state(&self, target: &Path) -> io::Result<()> {
+        let source = template_path(self.idx % STATEFUL_NODES_COUNT)?;
+
+        let mut copy_options = dir::CopyOptions::new();
+        copy_options.content_only = true;
+        copy_options.overwrite = true;
+        dir::copy(source, target, &copy_options).map_err(io::Error::other)?;
+
+        Ok(())
+    }
+}
+
+impl Drop for StatefulSlot {
+    fn drop(&mut self) {
+        SLOTS_IN_USE.lock().unwrap().remove(&self.idx);
+    }
+}
+
+/// The steps of [generate_state], reported as they happen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StateProgress {
+    /// The testnet's validators are starting.
+    StartingTestnet,
+    /// The genesis account is funding [TEST_ACCOUNT].
+    FundingTestAccount,
+    /// The testnet validated a new ledger.
+    LedgerValidated { current: u32, target: u32 },
+    /// The testnet stopped and its state is being copied into the template directories.
+    SavingState,
+}
+
+impl fmt::Display for StateProgress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::StartingTestnet => {
+                write!(f, "starting a testnet of {STATEFUL_NODES_COUNT} validators")
+            }
+            Self::FundingTestAccount => write!(f, "funding the test account {TEST_ACCOUNT}"),
+            Self::LedgerValidated { current, target } => {
+                write!(f, "validated ledger {current}/{target}")
+            }
+            Self::SavingState => write!(f, "saving the state of the validators"),
+        }
+    }
+}
+
+/// Runs a testnet until it validates `target_ledger` and saves its validators' state as the
+/// stateful node templates, replacing any existing ones.
+///
+/// The genesis account funds [TEST_ACCOUNT] on the way, so the state holds an account besides the
+/// genesis one.
+pub async fn generate_state(
+    target_ledger: u32,
+    mut progress: impl FnMut(StateProgress),
+) -> anyhow::Result<()> {
+    progress(StateProgress::StartingTestnet);
+    let mut testnet = TestNet::new()?;
+    testnet.start().await?;
+    let testnet_path = testnet.path().to_owned();
+
+    let result = run_testnet(&testnet, target_ledger, &mut progress).await;
+    testnet.stop().await?;
+    result?;
+
+    progress(StateProgress::SavingState);
+    let stateful_path = build_ripple_work_path()?.join(STATEFUL_NODES_DIR);
+    if stateful_path.exists() {
+        fs::remove_dir_all(&stateful_path)?;
+    }
+    fs::create_dir_all(&stateful_path)?;
+
+    let mut copy_options = dir::CopyOptions::new();
+    copy_options.content_only = true;
+    dir::copy(&testnet_path, &stateful_path, &copy_options)?;
+
+    // Stateful nodes get a freshly generated configuration.
+    for entry in fs::read_dir(&stateful_path)? {
+        let config = entry?.path().join(RIPPLED_CONFIG);
+        if config.exists() {
+            fs::remove_file(config)?;
+        }
+    }
+    fs::remove_dir_all(testnet_path)?;
+
+    Ok(())
+}
+
+// Funds the test account and waits for the target ledger to be validated.
+async fn run_testnet(
+    testnet: &TestNet,
+    target_ledger: u32,
+    progress: &mut impl FnMut(StateProgress),
+) -> anyhow::Result<()> {
+    let rpc_url = testnet.running[0].rpc_url();
+    let genesis = Account::genesis();
+
+    wait_for_account_data(&rpc_url, genesis.address(), TESTNET_START_TIMEOUT)
+        .await
+        .map_err(|_| anyhow!("the testnet didn't create the genesis ledger in time"))?;
+
+    progress(StateProgress::FundingTestAccount);
+    let tx = genesis
+        .payment(TEST_ACCOUNT, TEST_ACCOUNT_FUNDING)
+        .sequence(genesis.next_sequence(&rpc_url).await?)
+        .sign(genesis.key())?;
+    let response = submit_transaction(&rpc_url, tx.to_hex(), false).await?;
+    ensure!(response.result.accepted, "the funding wasn't accepted");
+    wait_for_account_data(&rpc_url, TEST_ACCOUNT, FUNDING_TIMEOUT)
+        .await
+        .map_err(|_| anyhow!("the test account wasn't funded in time"))?;
+
+    timeout(TARGET_LEDGER_TIMEOUT, async {
+        let mut last_reported = 0;
+        loop {
+            let current = match get_ledger_info(&rpc_url).await {
+                Ok(info) => info.result.ledger.ledger_index.parse().unwrap_or(0),
+                Err(_) => 0,
+            };
+
+            if current > last_reported {
+                progress(StateProgress::LedgerValidated {
+                    current,
+                    target: target_ledger,
+                });
+                last_reported = current;
+            }
+            if current >= target_ledger {
+                break;
+            }
+
+            sleep(LEDGER_POLL_INTERVAL).await;
+        }
+    })
+    .await
+    .map_err(|_| anyhow!("the testnet didn't reach ledger {target_ledger} in time"))
+}
+
+/// Returns the path to the template directory of the given validator's state.
+pub fn template_path(validator_idx: usize) -> io::Result<PathBuf> {
+    Ok(build_ripple_work_path()?
+        .join(STATEFUL_NODES_DIR)
+        .join(validator_idx.to_string()))
+}
@@ -1,44 +1,36 @@
-//! Utilities for setting up a testnet consisting of 3 nodes.
+//! Utilities for setting up a testnet of validators, 3 by default.
 
 use std::{
     fmt,
     fmt::Write,
     fs, io,
-    net::{IpAddr, SocketAddr},
+    net::{IpAddr, Ipv4Addr, SocketAddr},
     path::{Path, PathBuf},
 };
 
 use crate::setup::{
     build_ripple_work_path,
-    constants::{
-        DEFAULT_PORT, STATEFUL_NODES_COUNT, TESTNET_NETWORK_ID, VALIDATORS_FILE_NAME, VALIDATOR_IPS,
-    },
+    constants::{DEFAULT_PORT, STATEFUL_NODES_COUNT, VALIDATORS_FILE_NAME, VALIDATOR_IPS},
+    keys::ValidatorKeys,
+    network::NetworkProfile,
     node::{Node, NodeBuilder, NodeType},
 };
 
 /// Testnet's directory for nodes' configs.
 const TESTNET_DIR: &str = "testnet";
 
-const VALIDATOR_KEYS: [&str; STATEFUL_NODES_COUNT] = [
-    "nHUSqn9qjEF7JJkVqvY7BFLMKdqP5KLLEjo5oB4QH43ADDndRawB",
-    "nHUEsvSFTf1Snr7ZUdLxjcMW6PKcMrwwXCGZBg6xb1ePG8R4C3TS",
-    "nHUuYdS49cPfRmCXPTwu7MVVFZFFmfG7y5sRttirVMhwuD7xStQp",
-];
+/// The largest testnet, its nodes take up the 127.0.0.1-127.0.0.254 addresses.
+pub const MAX_TESTNET_SIZE: usize = 254;
 
 /// Get validator token.
 pub fn get_validator_token(stateful_node_idx: usize) -> String {
-    match stateful_node_idx {
-        0 => include_str!("validator_token0.txt").into(),
-        1 => include_str!("validator_token1.txt").into(),
-        2 => include_str!("validator_token2.txt").into(),
-        _ => panic!("validator token file does not exist"),
-    }
+    ValidatorKeys::for_validator(stateful_node_idx).token()
 }
 
 /// A struct to conveniently start and stop a small testnet.
 pub struct TestNet {
     // Setup information for each node. Used for writing configuration.
-    pub setups: [NodeSetup; VALIDATOR_KEYS.len()],
+    pub setups: Vec<NodeSetup>,
     // Running nodes. Used to stop the testnet.
     pub running: Vec<Node>,
     // Sets whether to log the node's output to Ziggurat's output stream.
@@ -48,32 +40,43 @@ pub struct TestNet {
 }
 
 impl TestNet {
-    /// Creates a new TestNet (without starting it).
+    /// Creates a new TestNet of 3 validators (without starting it).
     pub fn new() -> io::Result<Self> {
+        Self::with_size(STATEFUL_NODES_COUNT)
+    }
+
+    /// Creates a new TestNet of `size` validators (without starting it).
+    ///
+    /// Validators past the first 3 listen on further loopback addresses, which need to be
+    /// configured on platforms other than Linux.
+    pub fn with_size(size: usize) -> io::Result<Self> {
+        assert!(
+            (1..=MAX_TESTNET_SIZE).contains(&size),
+            "the testnet size should be between 1 and {MAX_TESTNET_SIZE}"
+        );
+
         Ok(Self {
-            setups: [
-                NodeSetup::new(
-                    VALIDATOR_IPS[0].parse().unwrap(),
-                    VALIDATOR_KEYS[0].into(),
-                    get_validator_token(0),
-                ),
-                NodeSetup::new(
-                    VALIDATOR_IPS[1].parse().unwrap(),
-                    VALIDATOR_KEYS[1].into(),
-                    get_validator_token(1),
-                ),
-                NodeSetup::new(
-                    VALIDATOR_IPS[2].parse().unwrap(),
-                    VALIDATOR_KEYS[2].into(),
-                    get_validator_token(2),
-                ),
-            ],
+            setups: (0..size).map(NodeSetup::for_validator).collect(),
             running: vec![],
             use_stdout: false,
             path: build_testnet_path()?,
         })
     }
 
+    /// Runs the nodes on the named binaries in order, e.g. to mix rippled versions, see
+    /// [NodeBuilder::with_binary]. Nodes past the list run the default binary.
+    pub fn with_binaries<S: Into<String>>(mut self, binaries: impl IntoIterator<Item = S>) -> Self {
+        for (setup, binary) in self.setups.iter_mut().zip(binaries) {
+            setup.binary = Some(binary.into());
+        }
+        self
+    }
+
+    /// Returns the directory the testnet's nodes run in.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
     /// Starts a testnet.
     pub async fn start(&mut self) -> anyhow::Result<()> {
         self.cleanup().await?;
@@ -132,17 +135,21 @@ impl TestNet {
         }
 
         write_validators_file(&target_path, validators_contents).await?;
-        NodeBuilder::stateless()?
+        let mut builder = NodeBuilder::stateless()?;
+        if let Some(binary) = &setup.binary {
+            builder = builder.with_binary(binary);
+        }
+        builder
             .initial_peers(self.collect_other_peers(setup))
             .set_addr(SocketAddr::new(setup.ip, DEFAULT_PORT))
             .validator_token(setup.validator_token.clone())
-            .network_id(TESTNET_NETWORK_ID)
+            .network(&NetworkProfile::LOCAL)
             .log_to_stdout(self.use_stdout)
             .start(&target_path, NodeType::Testnet)
             .await
     }
 
-    // Builds a list of peers for the node. Each node has two peers (the other nodes in the testnet).
+    // Builds a list of peers for the node, all the other nodes in the testnet.
     fn collect_other_peers(&self, setup: &NodeSetup) -> Vec<SocketAddr> {
         self.setups
             .iter()
@@ -176,6 +183,8 @@ pub struct NodeSetup {
     validator_key: String,
     // The node's validator token to be put in the rippled.cfg file.
     pub validator_token: String,
+    // The named binary the node runs, the default one if unset.
+    pub binary: Option<String>,
 }
 
 impl NodeSetup {
@@ -184,16 +193,41 @@ impl NodeSetup {
             ip,
             validator_key,
             validator_token,
+            binary: None,
         }
     }
+
+    fn for_validator(idx: usize) -> Self {
+        let ip = match VALIDATOR_IPS.get(idx) {
+            Some(ip) => ip.parse().unwrap(),
+            None => IpAddr::V4(Ipv4Addr::new(127, 0, 0, idx as u8 + 1)),
+        };
+        let keys = ValidatorKeys::for_validator(idx);
+
+        Self::new(ip, keys.node_public_key(), keys.token())
+    }
 }
 
 #[cfg(test)]
 mod test {
-    use std::time::Duration;
+    use std::{collections::HashSet, time::Duration};
 
     use crate::setup::testnet::TestNet;
 
+    #[test]
+    fn validators_are_distinct() {
+        let testnet = TestNet::with_size(5).unwrap();
+
+        let ips: HashSet<_> = testnet.setups.iter().map(|setup| setup.ip).collect();
+        let keys: HashSet<_> = testnet
+            .setups
+            .iter()
+            .map(|setup| setup.validator_key.clone())
+            .collect();
+        assert_eq!(ips.len(), 5);
+        assert_eq!(keys.len(), 5);
+    }
+
     #[ignore = "used to set up a small testnet that can be used to procure node state"]
     #[tokio::test]
     async fn run_testnet() {
@@ -1,4 +1,6 @@
 mod cluster;
 mod haveset;
+mod proposal;
 mod squelch;
 mod transaction;
+mod validation;
@@ -0,0 +1,128 @@
+//! Contains tests for ledger proposals sent to the node.
+//!
+//! The node checks the signature of every proposal it receives and relays the proposals of the
+//! validators it trusts to its other peers.
+//!
+//!     <> with the proposing synthetic node and the observing synthetic node
+//!     <- mtPROPOSE_LEDGER (the node's previous ledger)
+//!     -> mtMANIFESTS (synthetic validator's manifest)
+//!     -> mtPROPOSE_LEDGER (P)
+//!
+//!     Assert: The observing synthetic node receives P only if it's validly signed by a trusted
+//!     validator.
+
+use tempfile::TempDir;
+
+use crate::{
+    protocol::{
+        codecs::message::Payload,
+        proto::{TmManifest, TmManifests, TmProposeSet},
+    },
+    setup::{
+        keys::ValidatorKeys,
+        node::{Node, NodeType},
+    },
+    tools::{
+        matchers::{is_kind, Matcher},
+        proposal::ProposalBuilder,
+        rpc::wait_for_state,
+        synth_node::SyntheticNode,
+        validator::{KeyType, ValidatorKey},
+    },
+};
+
+/// A validator trusted by the stateful node, but not running.
+const TRUSTED_VALIDATOR_IDX: usize = 1;
+
+#[tokio::test]
+#[allow(non_snake_case)]
+async fn c030_t1_TM_PROPOSE_LEDGER_node_should_relay_trusted_proposal() {
+    // ZG-CONFORMANCE-030
+
+    let keys = ValidatorKeys::for_validator(TRUSTED_VALIDATOR_IDX);
+    assert!(is_proposal_relayed(&keys.signing, |_| ()).await);
+}
+
+#[tokio::test]
+#[allow(non_snake_case)]
+async fn c030_t2_TM_PROPOSE_LEDGER_node_should_drop_forged_proposal() {
+    // ZG-CONFORMANCE-030
+
+    let keys = ValidatorKeys::for_validator(TRUSTED_VALIDATOR_IDX);
+    let forge = |proposal: &mut TmProposeSet| {
+        // Change the proposed close time, the signature doesn't cover it anymore.
+        proposal.close_time += 1;
+    };
+    assert!(!is_proposal_relayed(&keys.signing, forge).await);
+}
+
+#[tokio::test]
+#[allow(non_snake_case)]
+async fn c030_t3_TM_PROPOSE_LEDGER_node_should_not_relay_untrusted_proposal() {
+    // ZG-CONFORMANCE-030
+
+    let key = ValidatorKey::generate(KeyType::Secp256k1);
+    assert!(!is_proposal_relayed(&key, |_| ()).await);
+}
+
+/// Sends a proposal signed with the key and modified by `tamper` to the node and returns whether
+/// the node relayed it to another peer.
+async fn is_proposal_relayed(key: &ValidatorKey, tamper: impl FnOnce(&mut TmProposeSet)) -> bool {
+    // The stateful node validates as the first validator.
+    let target = TempDir::new().expect("Couldn't create a temporary directory");
+    let mut node = Node::builder()
+        .start(target.path(), NodeType::Stateful)
+        .await
+        .expect("Unable to start the stateful node");
+    wait_for_state(&node.rpc_url(), "proposing".into()).await;
+
+    let mut proposer = SyntheticNode::new(&Default::default()).await;
+    proposer
+        .connect(node.addr())
+        .await
+        .expect("Unable to connect");
+    let mut observer = SyntheticNode::new(&Default::default()).await;
+    observer
+        .connect(node.addr())
+        .await
+        .expect("Unable to connect");
+
+    // Proposals are only considered for the ledger the node is currently building on.
+    let previous_ledger = match proposer
+        .expect_matching(&is_kind("TmProposeLedger"))
+        .await
+        .unwrap_or_else(|e| panic!("{e}"))
+        .payload
+    {
+        Payload::TmProposeLedger(proposal) => proposal.previousledger,
+        _ => unreachable!(),
+    };
+
+    // Bind the signing key to the trusted validator's master key.
+    let manifest = ValidatorKeys::for_validator(TRUSTED_VALIDATOR_IDX).manifest();
+    let manifests = Payload::TmManifests(TmManifests {
+        list: vec![TmManifest { stobject: manifest }],
+        ..Default::default()
+    });
+    proposer.unicast(node.addr(), manifests).unwrap();
+
+    let mut proposal =
+        ProposalBuilder::new(previous_ledger.try_into().unwrap(), rand::random()).sign(key);
+    tamper(&mut proposal);
+    let signature = proposal.signature.clone();
+    proposer
+        .unicast(node.addr(), Payload::TmProposeLedger(proposal))
+        .unwrap();
+
+    let matcher = Matcher::new(
+        "the proposal sent by the proposer",
+        move |payload| matches!(payload, Payload::TmProposeLedger(proposal) if proposal.signature == signature),
+    );
+    let relayed = observer.expect_matching(&matcher).await.is_ok();
+
+    proposer.shut_down().await;
+    observer.shut_down().await;
+    node.stop().unwrap();
+
+    relayed
+}
@@ -22,7 +22,7 @@
 //!     after squelching node public keys belonging to nodes 2 and 3 (B).
 
 use tempfile::TempDir;
-use tokio::time::{sleep, timeout, Duration};
+use tokio::time::{timeout, Duration};
 
 use crate::{
     protocol::{
@@ -39,7 +39,6 @@ use crate::{
 // Time we shall wait for a TmProposeLedger message.
 const WAIT_MSG_TIMEOUT: Duration = Duration::from_secs(7);
 const SQUELCH_DURATION_SECS: u32 = 6 * 60; // Six minutes should be an ample time value.
-const HANDLE_REMAINING_PROPOSE_MSGS: Duration = Duration::from_millis(300);
 
 #[tokio::test]
 #[allow(non_snake_case)]
@@ -74,28 +73,20 @@ async fn c009_TM_SQUELCH_cannot_squelch_peer_ledger_proposals() {
     });
     synth_node.unicast(node.addr(), msg).unwrap();
 
-    // Ensure all incoming TmProposeLedger messages are handled before the node processes the squelch message.
-    sleep(HANDLE_REMAINING_PROPOSE_MSGS).await;
+    // Drop the TmProposeLedger messages of the current round, sent before the node processed the squelch message.
+    synth_node
+        .wait_for_ledger_advance(node.addr(), 1, WAIT_MSG_TIMEOUT)
+        .await
+        .expect("The node didn't close a ledger in time");
 
     // Check that the squelch message had no effect and that we will continue to receive TmProposeLedger messages from the node.
-    timeout(WAIT_MSG_TIMEOUT, async {
-        loop {
-            if let (
-                _,
-                BinaryMessage {
-                    payload: Payload::TmProposeLedger(TmProposeSet { node_pub_key, .. }),
-                    ..
-                },
-            ) = synth_node.recv_message().await
-            {
-                if validator_pub_key == node_pub_key {
-                    break;
-                }
-            }
-        }
-    })
-    .await
-    .expect("TmProposeLedger not received in time");
+    let received = synth_node
+        .count_messages_from(&validator_pub_key, WAIT_MSG_TIMEOUT)
+        .await;
+    assert!(
+        received.proposals > 0,
+        "TmProposeLedger not received in time"
+    );
 
     synth_node.shut_down().await;
     node.stop().expect("Unable to stop the stateful node");
@@ -153,24 +144,18 @@ async fn c016_TM_SQUELCH_squelch_distant_validators() {
     }
 
     // Collect validation keys for distant nodes.
-    let mut distant_node_keys = vec![];
-    timeout(WAIT_MSG_TIMEOUT, async {
-        loop {
-            let node_pub_key = wait_for_validator_key_in_propose_msg(&mut synth_node).await;
-            if node_pub_key == peer_node_validator_key {
-                continue;
-            }
-
-            if !distant_node_keys.contains(&node_pub_key) {
-                distant_node_keys.push(node_pub_key);
-                if distant_node_keys.len() == DISTANT_NODES_CNT {
-                    break;
-                }
-            }
-        }
-    })
-    .await
-    .expect("TmProposeLedger not received in time");
+    let distant_node_keys = synth_node
+        .count_validator_messages(WAIT_MSG_TIMEOUT)
+        .await
+        .into_iter()
+        .filter(|(key, received)| *key != peer_node_validator_key && received.proposals > 0)
+        .map(|(key, _)| key)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        distant_node_keys.len(),
+        DISTANT_NODES_CNT,
+        "TmProposeLedger not received in time"
+    );
 
     // Squelch distant nodes.
     for key in distant_node_keys.iter() {
@@ -182,28 +167,21 @@ async fn c016_TM_SQUELCH_squelch_distant_validators() {
         synth_node.unicast(peer_node.addr(), msg).unwrap();
     }
 
-    // Ensure all incoming TmProposeLedger messages are handled before nodes process the squelch message.
-    sleep(HANDLE_REMAINING_PROPOSE_MSGS).await;
+    // Drop the TmProposeLedger messages of the current round, sent before nodes processed the squelch message.
+    synth_node
+        .wait_for_ledger_advance(peer_node.addr(), 1, WAIT_MSG_TIMEOUT)
+        .await
+        .expect("The node didn't close a ledger in time");
 
     // Verify we are not receiving TmProposeLedger messages from distant nodes.
-    let expect_timeout_err_for_squelched_nodes = timeout(WAIT_MSG_TIMEOUT, async {
-        loop {
-            if let (
-                _,
-                BinaryMessage {
-                    payload: Payload::TmProposeLedger(TmProposeSet { node_pub_key, .. }),
-                    ..
-                },
-            ) = synth_node.recv_message().await
-            {
-                if distant_node_keys.contains(&node_pub_key) {
-                    panic!("It shouldn't be possible to receive proposing ledgers from squelched nodes.");
-                }
-            }
-        }
-    }).await;
-
-    assert!(expect_timeout_err_for_squelched_nodes.is_err());
+    let received = synth_node.count_validator_messages(WAIT_MSG_TIMEOUT).await;
+    for key in &distant_node_keys {
+        assert_eq!(
+            received.get(key).map_or(0, |received| received.proposals),
+            0,
+            "It shouldn't be possible to receive proposing ledgers from squelched nodes."
+        );
+    }
 
     synth_node.shut_down().await;
     peer_node.stop().expect("Unable to stop the stateful node");
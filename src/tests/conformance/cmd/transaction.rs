@@ -1,9 +1,14 @@
 use crate::{
-    protocol::{
-        codecs::message::{BinaryMessage, Payload},
-        proto::TransactionStatus::TsCurrent,
+    protocol::{codecs::message::Payload, proto::TransactionStatus::TsCurrent},
+    setup::{constants::TESTNET_READY_TIMEOUT, testnet::TestNet},
+    tests::conformance::{create_test_payment, perform_testnet_transaction_check},
+    tools::{
+        accounts::{Account, DEFAULT_FUNDING_AMOUNT},
+        matchers::Matcher,
+        rpc::wait_for_account_data,
+        synth_node::SyntheticNode,
+        validator::KeyType,
     },
-    tests::conformance::{perform_testnet_transaction_check, TRANSACTION_BLOB},
 };
 
 #[tokio::test]
@@ -12,7 +17,69 @@ async fn c019_MT_TRANSACTION_node_should_broadcast_transaction_to_all_peers() {
     // ZG-CONFORMANCE-019
 
     // Ensure that the synthetic node connected to the testnet received the transaction.
-    let blob_bytes = hex::decode(TRANSACTION_BLOB).unwrap();
-    let check = |m: &BinaryMessage| matches!(&m.payload, Payload::TmTransaction(tm_transaction) if tm_transaction.raw_transaction == blob_bytes && tm_transaction.status == TsCurrent as i32 && tm_transaction.deferred == Some(false));
-    perform_testnet_transaction_check(&check).await;
+    let transaction = create_test_payment();
+    let blob = transaction.blob.clone();
+    let matcher = Matcher::new(
+        "the submitted TmTransaction, current and not deferred",
+        move |payload| matches!(payload, Payload::TmTransaction(tm_transaction) if tm_transaction.raw_transaction == blob && tm_transaction.status == TsCurrent as i32 && tm_transaction.deferred == Some(false)),
+    );
+    perform_testnet_transaction_check(&transaction, &matcher).await;
+}
+
+#[tokio::test]
+#[allow(non_snake_case)]
+async fn c029_MT_TRANSACTION_node_should_relay_transaction_received_from_peer() {
+    // ZG-CONFORMANCE-029
+
+    // Start a testnet.
+    let mut testnet = TestNet::new().unwrap();
+    testnet.start().await.unwrap();
+    let rpc_url = testnet.running[0].rpc_url();
+    let genesis = Account::genesis();
+    wait_for_account_data(&rpc_url, genesis.address(), TESTNET_READY_TIMEOUT)
+        .await
+        .expect("Unable to get the account data.");
+
+    // The sender is connected to the first node, the receiver to the second one.
+    let sender = SyntheticNode::new(&Default::default()).await;
+    sender
+        .connect(testnet.running[0].addr())
+        .await
+        .expect("Unable to connect to the first node");
+    let mut receiver = SyntheticNode::new(&Default::default()).await;
+    receiver
+        .connect(testnet.running[1].addr())
+        .await
+        .expect("Unable to connect to the second node");
+
+    // Fund a new account, so the transaction can't have been seen before.
+    let destination = Account::generate(KeyType::Ed25519);
+    let transaction = genesis
+        .payment(destination.address(), DEFAULT_FUNDING_AMOUNT)
+        .sequence(genesis.next_sequence(&rpc_url).await.unwrap())
+        .sign(genesis.key())
+        .unwrap();
+
+    sender
+        .unicast(testnet.running[0].addr(), transaction.to_payload())
+        .unwrap();
+
+    // The transaction is applied and relayed through the testnet.
+    let blob = transaction.blob.clone();
+    let matcher = Matcher::new(
+        "the TmTransaction sent by the sender",
+        move |payload| matches!(payload, Payload::TmTransaction(tm_transaction) if tm_transaction.raw_transaction == blob),
+    );
+    receiver
+        .expect_matching(&matcher)
+        .await
+        .unwrap_or_else(|e| panic!("{e}"));
+    wait_for_account_data(&rpc_url, destination.address(), TESTNET_READY_TIMEOUT)
+        .await
+        .expect("The destination account wasn't funded.");
+
+    // Shutdown.
+    testnet.stop().await.expect("Unable to stop the testnet.");
+    sender.shut_down().await;
+    receiver.shut_down().await;
 }
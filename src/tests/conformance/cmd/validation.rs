@@ -0,0 +1,150 @@
+//! Contains tests for validations sent to the node.
+//!
+//! The node checks the signature of every validation it receives. It relays the current
+//! validations for its network and, configured with `[relay_validations] trusted`, only the ones
+//! of the validators it trusts.
+//!
+//!     <> with the validating synthetic node and the observing synthetic node
+//!     -> mtMANIFESTS (synthetic validator's manifest)
+//!     -> mtVALIDATION (V of the last validated ledger)
+//!
+//!     Assert: The observing synthetic node receives V only if it's validly signed by a trusted
+//!     validator for the node's network.
+
+use tempfile::TempDir;
+
+use crate::{
+    protocol::{
+        codecs::message::Payload,
+        proto::{TmManifest, TmManifests, TmValidation},
+    },
+    setup::{
+        keys::ValidatorKeys,
+        network::NetworkProfile,
+        node::{Node, NodeType},
+    },
+    tools::{
+        matchers::Matcher,
+        rpc::{wait_for_ledger_info, wait_for_state},
+        synth_node::SyntheticNode,
+        validation::ValidationBuilder,
+        validator::{KeyType, ValidatorKey},
+    },
+};
+
+/// A validator trusted by the stateful node, but not running.
+const TRUSTED_VALIDATOR_IDX: usize = 1;
+
+// The offset of the ledger hash in a validation without a network ID.
+const LEDGER_HASH_OFFSET: usize = 16;
+
+#[tokio::test]
+#[allow(non_snake_case)]
+async fn c031_t1_TM_VALIDATION_node_should_relay_trusted_validation() {
+    // ZG-CONFORMANCE-031
+
+    let key = ValidatorKeys::for_validator(TRUSTED_VALIDATOR_IDX).signing;
+    assert!(is_validation_relayed(|builder| builder.sign(&key)).await);
+}
+
+#[tokio::test]
+#[allow(non_snake_case)]
+async fn c031_t2_TM_VALIDATION_node_should_drop_forged_validation() {
+    // ZG-CONFORMANCE-031
+
+    let key = ValidatorKeys::for_validator(TRUSTED_VALIDATOR_IDX).signing;
+    let forge = |builder: ValidationBuilder| {
+        // Change the validated ledger, the signature doesn't cover it anymore.
+        let mut validation = builder.sign(&key);
+        validation[LEDGER_HASH_OFFSET + 1] ^= 0xff;
+        validation
+    };
+    assert!(!is_validation_relayed(forge).await);
+}
+
+#[tokio::test]
+#[allow(non_snake_case)]
+async fn c031_t3_TM_VALIDATION_node_should_not_relay_untrusted_validation() {
+    // ZG-CONFORMANCE-031
+
+    let key = ValidatorKey::generate(KeyType::Secp256k1);
+    assert!(!is_validation_relayed(|builder| builder.sign(&key)).await);
+}
+
+#[tokio::test]
+#[allow(non_snake_case)]
+async fn c031_t4_TM_VALIDATION_node_should_drop_validation_for_other_network() {
+    // ZG-CONFORMANCE-031
+
+    let key = ValidatorKeys::for_validator(TRUSTED_VALIDATOR_IDX).signing;
+    let other_network = NetworkProfile::LOCAL.network_id + 1;
+    assert!(!is_validation_relayed(|builder| builder.network_id(other_network).sign(&key)).await);
+}
+
+/// Sends the validation created by `create` to the node and returns whether the node relayed it
+/// to another peer.
+async fn is_validation_relayed(create: impl FnOnce(ValidationBuilder) -> Vec<u8>) -> bool {
+    // The stateful node validates as the first validator.
+    let target = TempDir::new().expect("Couldn't create a temporary directory");
+    let mut node = Node::builder()
+        .config_section("relay_validations", ["trusted"])
+        .start(target.path(), NodeType::Stateful)
+        .await
+        .expect("Unable to start the stateful node");
+    wait_for_state(&node.rpc_url(), "proposing".into()).await;
+
+    let validator = SyntheticNode::new(&Default::default()).await;
+    validator
+        .connect(node.addr())
+        .await
+        .expect("Unable to connect");
+    let mut observer = SyntheticNode::new(&Default::default()).await;
+    observer
+        .connect(node.addr())
+        .await
+        .expect("Unable to connect");
+
+    // Bind the signing key to the trusted validator's master key.
+    let manifest = ValidatorKeys::for_validator(TRUSTED_VALIDATOR_IDX).manifest();
+    let manifests = Payload::TmManifests(TmManifests {
+        list: vec![TmManifest { stobject: manifest }],
+        ..Default::default()
+    });
+    validator.unicast(node.addr(), manifests).unwrap();
+
+    // Validate the node's last validated ledger.
+    let ledger = wait_for_ledger_info(&node.rpc_url())
+        .await
+        .expect("Unable to get the validated ledger")
+        .result
+        .ledger;
+    let ledger_hash = hex::decode(&ledger.ledger_hash)
+        .unwrap()
+        .try_into()
+        .unwrap();
+    let validation = create(ValidationBuilder::new(
+        ledger_hash,
+        ledger.ledger_index.parse().unwrap(),
+    ));
+    validator
+        .unicast(
+            node.addr(),
+            Payload::TmValidation(TmValidation {
+                validation: validation.clone(),
+                ..Default::default()
+            }),
+        )
+        .unwrap();
+
+    let matcher = Matcher::new(
+        "the validation sent by the validator",
+        move |payload| matches!(payload, Payload::TmValidation(relayed) if relayed.validation == validation),
+    );
+    let relayed = observer.expect_matching(&matcher).await.is_ok();
+
+    validator.shut_down().await;
+    observer.shut_down().await;
+    node.stop().unwrap();
+
+    relayed
+}
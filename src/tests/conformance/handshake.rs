@@ -1,13 +1,17 @@
 use tempfile::TempDir;
+use tokio::time::{sleep, Duration};
 
 use crate::{
-    protocol::codecs::message::BinaryMessage,
+    protocol::{
+        handshake::{HandshakeRejected, ProtocolFeatures},
+        version::ProtocolVersion,
+    },
     setup::{
         constants::CONNECTION_TIMEOUT,
         node::{Node, NodeType},
     },
     tests::conformance::{perform_expected_message_test, TestConfig},
-    tools::synth_node::SyntheticNode,
+    tools::{matchers::any, rpc::get_peers, synth_node::SyntheticNode},
     wait_until,
 };
 
@@ -32,6 +36,19 @@ async fn c001_handshake_when_node_receives_connection() {
     assert_eq!(synth_node.num_connected(), 1);
     assert!(synth_node.is_connected(node.addr()));
 
+    // The node's session signature was verified against its public key.
+    let info = synth_node
+        .peer_handshake_info(node.addr())
+        .expect("no handshake info for the node");
+    assert!(info.public_key.starts_with('n'));
+    assert_eq!(info.connect_as.as_deref(), Some("Peer"));
+    assert!(info.ident.unwrap_or_default().starts_with("rippled-"));
+
+    // The node lists the synthetic node as its only peer.
+    let peers = get_peers(&node.rpc_url()).await.unwrap().result.peers;
+    assert_eq!(peers.len(), 1);
+    assert_eq!(peers[0].public_key, synth_node.node_public_key());
+
     // Shutdown both nodes
     synth_node.shut_down().await;
     node.stop().unwrap();
@@ -61,18 +78,196 @@ async fn c002_handshake_when_node_initiates_connection() {
     wait_until!(CONNECTION_TIMEOUT, synth_node.num_connected() == 1);
     assert!(synth_node.is_connected_ip(node.addr().ip()));
 
+    // The node's session signature was verified against its public key.
+    let info = synth_node
+        .peer_handshake_info(synth_node.connected_addrs()[0])
+        .expect("no handshake info for the node");
+    assert!(info.public_key.starts_with('n'));
+    assert_eq!(info.connect_as.as_deref(), Some("Peer"));
+    assert!(info.ident.unwrap_or_default().starts_with("rippled-"));
+
     // Shutdown both nodes
     synth_node.shut_down().await;
     node.stop().unwrap();
 }
 
+#[allow(non_snake_case)]
+#[tokio::test]
+async fn c027_t1_HANDSHAKE_node_selects_highest_offered_version() {
+    // ZG-CONFORMANCE-027
+
+    // Build and start the Ripple node
+    let target = TempDir::new().expect("Can't build tmp dir");
+    let mut node = Node::builder()
+        .start(target.path(), NodeType::Stateless)
+        .await
+        .expect("Unable to start node");
+
+    for (offered, expected) in [
+        (
+            &[ProtocolVersion::V2_1, ProtocolVersion::V2_2][..],
+            ProtocolVersion::V2_2,
+        ),
+        (&[ProtocolVersion::V2_2], ProtocolVersion::V2_2),
+        (&[ProtocolVersion::V2_1], ProtocolVersion::V2_1),
+    ] {
+        let synth_node = SyntheticNode::builder()
+            .protocol_versions(offered)
+            .build()
+            .await;
+        synth_node.connect(node.addr()).await.unwrap();
+
+        assert_eq!(
+            synth_node.peer_protocol_version(node.addr()),
+            Some(expected),
+            "unexpected version selected from {offered:?}"
+        );
+        synth_node.shut_down().await;
+    }
+
+    node.stop().unwrap();
+}
+
+#[allow(non_snake_case)]
+#[tokio::test]
+async fn c027_t2_HANDSHAKE_node_accepts_downgraded_version() {
+    // ZG-CONFORMANCE-027
+
+    // Start synthetic node accepting only the older version.
+    let synth_node = SyntheticNode::builder()
+        .protocol_versions(&[ProtocolVersion::V2_1])
+        .build()
+        .await;
+    let listening_addr = synth_node
+        .start_listening()
+        .await
+        .expect("unable to start listening");
+
+    // Build and start the Ripple node and set the synth node as an initial peer.
+    let target = TempDir::new().expect("Can't build tmp dir");
+    let mut node = Node::builder()
+        .initial_peers(vec![listening_addr])
+        .start(target.path(), NodeType::Stateless)
+        .await
+        .expect("Unable to start node");
+
+    wait_until!(CONNECTION_TIMEOUT, synth_node.num_connected() == 1);
+    let node_addr = synth_node.connected_addrs()[0];
+    assert_eq!(
+        synth_node.peer_protocol_version(node_addr),
+        Some(ProtocolVersion::V2_1)
+    );
+
+    // The node keeps the connection after the handshake.
+    sleep(Duration::from_secs(1)).await;
+    assert!(synth_node.is_connected(node_addr));
+
+    // Shutdown both nodes
+    synth_node.shut_down().await;
+    node.stop().unwrap();
+}
+
+#[allow(non_snake_case)]
+#[tokio::test]
+async fn c034_HANDSHAKE_node_enables_only_requested_features() {
+    // ZG-CONFORMANCE-034
+
+    // Build and start the Ripple node, configured with ledger replay and transaction relay
+    // reduction but without compression and validation/proposal relay reduction.
+    let target = TempDir::new().expect("Can't build tmp dir");
+    let mut node = Node::builder()
+        .start(target.path(), NodeType::Stateless)
+        .await
+        .expect("Unable to start node");
+
+    let ledger_replay = ProtocolFeatures {
+        ledger_replay: true,
+        ..ProtocolFeatures::NONE
+    };
+    for (requested, expected) in [
+        (ProtocolFeatures::ALL, ProtocolFeatures::default()),
+        (ProtocolFeatures::NONE, ProtocolFeatures::NONE),
+        (ledger_replay, ledger_replay),
+    ] {
+        let mut synth_node = SyntheticNode::builder()
+            .protocol_features(requested)
+            .build()
+            .await;
+        synth_node.connect(node.addr()).await.unwrap();
+
+        let info = synth_node
+            .peer_handshake_info(node.addr())
+            .expect("no handshake info for the node");
+        assert_eq!(
+            info.features(),
+            expected,
+            "unexpected features enabled for {requested:?}"
+        );
+
+        // The node only answers replay delta requests with ledger replay enabled, unknown
+        // ledgers included.
+        let response = synth_node
+            .request_replay_delta(node.addr(), vec![0xab; 32])
+            .await;
+        assert_eq!(
+            response.is_ok(),
+            expected.ledger_replay,
+            "unexpected replay delta response for {requested:?}: {response:?}"
+        );
+
+        synth_node.shut_down().await;
+    }
+
+    node.stop().unwrap();
+}
+
+#[allow(non_snake_case)]
+#[tokio::test]
+async fn c035_HANDSHAKE_full_node_redirects_to_other_peers() {
+    // ZG-CONFORMANCE-035
+
+    const MAX_PEERS: usize = 10;
+
+    // Build and start the Ripple node with few peer slots.
+    let target = TempDir::new().expect("Can't build tmp dir");
+    let mut node = Node::builder()
+        .max_peers(MAX_PEERS)
+        .start(target.path(), NodeType::Stateless)
+        .await
+        .expect("Unable to start node");
+
+    // Connect until the node runs out of slots for inbound peers.
+    let mut synth_nodes = Vec::new();
+    let mut rejection = None;
+    for _ in 0..=MAX_PEERS {
+        let synth_node = SyntheticNode::builder().build().await;
+        let result = synth_node.connect(node.addr()).await;
+        synth_nodes.push(synth_node);
+        if let Err(error) = result {
+            rejection = Some(error);
+            break;
+        }
+    }
+
+    let error = rejection.expect("the node accepted more peers than its maximum");
+    let rejection = HandshakeRejected::from_error(&error)
+        .unwrap_or_else(|| panic!("the handshake failed without a response: {error}"));
+    assert_eq!(rejection.status, 503, "{rejection}");
+    let peer_ips = rejection
+        .peer_ips()
+        .unwrap_or_else(|| panic!("no peer-ips listed: {rejection}"));
+    assert!(!peer_ips.contains(&node.addr()), "{peer_ips:?}");
+
+    for synth_node in synth_nodes {
+        synth_node.shut_down().await;
+    }
+    node.stop().unwrap();
+}
+
 #[tokio::test]
 #[should_panic]
 #[allow(non_snake_case)]
 async fn c006_node_should_not_send_any_messages_if_no_handshake() {
     // ZG-CONFORMANCE-006
-    let response_check = |_: &BinaryMessage| true;
-
-    perform_expected_message_test(TestConfig::default().with_handshake(None), &response_check)
-        .await;
+    perform_expected_message_test(TestConfig::default().with_handshake(None), &any()).await;
 }
@@ -1,20 +1,16 @@
-use tempfile::TempDir;
+use std::env;
 
 use crate::{
-    protocol::{
-        codecs::message::{BinaryMessage, Payload},
-        handshake::HandshakeCfg,
-    },
-    setup::{
-        constants::TESTNET_READY_TIMEOUT,
-        node::{Node, NodeType},
-        testnet::TestNet,
-    },
+    protocol::{codecs::message::Payload, handshake::HandshakeCfg},
+    setup::{constants::TESTNET_READY_TIMEOUT, testnet::TestNet},
     tools::{
-        config::SynthNodeCfg,
-        constants::GENESIS_ACCOUNT,
+        accounts::{Account, TEST_ACCOUNT},
+        config::{SynthNodeCfg, PROFILE_ENV},
+        harness::TestHarness,
+        matchers::Matcher,
         rpc::{submit_transaction, wait_for_account_data},
         synth_node::SyntheticNode,
+        tx::SignedTransaction,
     },
 };
 
@@ -31,14 +27,17 @@ pub const PUBLIC_KEY_TYPES: &[u8] = &[
     0x03, // secp256k1 again as this type key has two correct magic bytes.
 ];
 
-/// Ripple epoch starts at Jan-1-2000. The number here equals number of seconds since unix epoch (Jan-1-1970)
-pub const RIPPLE_EPOCH: u32 = 946684800;
-
-// A transaction blob representing a signed transaction. Extracted by executing `tools/transfer.py` and listening with `tcpdump -A -i lo dst port 5005 or src port 5005`.
-pub const TRANSACTION_BLOB: &str = "12000022000000002400000001201B0000001E61400000012A05F20068400000000000000A73210330E7FC9D56BB25D6893BA3F317AE5BCF33B3291BD63DB32654A313222F7FD020744630440220297389244D36AF12115296F409C446D9A5D808880DC7FF323AA207ED529CE6C802207AAC5D2A96CB102CBDE85D2A4BA814253CA133AC9277041CAE2E1A349FB233FF8114B5F762798A53D543A014CAF8B297CFF8F2F937E883149193D6AED0CBBC25790ADE05D020C9C6D9201DCF";
+/// Creates a payment from the genesis account to the [TEST_ACCOUNT], valid in a freshly started testnet.
+pub fn create_test_payment() -> SignedTransaction {
+    let genesis = Account::genesis();
+    genesis
+        .payment(TEST_ACCOUNT, 5_000_000_000)
+        .last_ledger_sequence(30)
+        .sign(genesis.key())
+        .expect("unable to sign the payment")
+}
 
 /// Test configuration for tests using the below helper test function.
-#[derive(Default)]
 struct TestConfig {
     /// An initial message to be sent to the rippled node.
     pub initial_message: Option<Payload>,
@@ -47,7 +46,32 @@ struct TestConfig {
     pub synth_node_cfg: SynthNodeCfg,
 }
 
+/// The default configuration, with the profile named by [PROFILE_ENV] applied if set.
+impl Default for TestConfig {
+    fn default() -> Self {
+        match env::var(PROFILE_ENV) {
+            Ok(name) => Self::from_profile(&name),
+            Err(_) => Self {
+                initial_message: None,
+                synth_node_cfg: Default::default(),
+            },
+        }
+    }
+}
+
 impl TestConfig {
+    /// The default configuration with the named profile applied, see
+    /// [ConfigProfile](crate::tools::config::ConfigProfile).
+    pub fn from_profile(name: &str) -> Self {
+        let synth_node_cfg = SynthNodeCfg::from_profile(name)
+            .unwrap_or_else(|e| panic!("couldn't load the {name} profile: {e:?}"));
+
+        Self {
+            initial_message: None,
+            synth_node_cfg,
+        }
+    }
+
     /// Configure an initial message for the test.
     pub fn with_initial_message(mut self, payload: Payload) -> Self {
         self.initial_message = Some(payload);
@@ -67,32 +91,29 @@ impl TestConfig {
 /// 2. Connect a SyntheticNode to the rippled node.
 /// 3. Optional: send a message to the rippled node (configured via [TestConfig]).
 /// 4. Assert that the SyntheticNode received the required message.
-async fn perform_expected_message_test(
-    config: TestConfig,
-    response_check: &dyn Fn(&BinaryMessage) -> bool,
-) {
-    // Build and start Ripple node
-    let target = TempDir::new().expect("Unable to create TempDir");
-    let mut node = Node::builder()
-        .start(target.path(), NodeType::Stateless)
+async fn perform_expected_message_test(config: TestConfig, matcher: &Matcher) {
+    // Build and start Ripple node, start synth node and connect to Ripple
+    let mut harness = TestHarness::builder()
+        .synth_node_cfg(config.synth_node_cfg)
+        .build()
         .await
         .unwrap();
-
-    // Start synth node and connect to Ripple
-    let mut synth_node = SyntheticNode::new(&config.synth_node_cfg).await;
-    synth_node.connect(node.addr()).await.unwrap();
+    let node_addr = harness.node.addr();
+    let synth_node = harness.synth_node_mut(0);
 
     // Send the query message (if present)
     config
         .initial_message
-        .map(|message| synth_node.unicast(node.addr(), message).unwrap());
+        .map(|message| synth_node.unicast(node_addr, message).unwrap());
 
     // Wait for a response and perform the given check for it
-    assert!(synth_node.expect_message(response_check).await);
+    synth_node
+        .expect_matching(matcher)
+        .await
+        .unwrap_or_else(|e| panic!("{e}"));
 
     // Shutdown both nodes
-    synth_node.shut_down().await;
-    node.stop().unwrap();
+    harness.shut_down().await.unwrap();
 }
 
 /// Performs a check for the required message after a new transaction in the testnet.
@@ -101,7 +122,7 @@ async fn perform_expected_message_test(
 /// 2. Connect a SyntheticNode to the second rippled node in the testnet.
 /// 3. Submit a transaction via RPC call to the first rippled node in the testnet.
 /// 4. Assert that the SyntheticNode received the required message.
-pub async fn perform_testnet_transaction_check(check: &dyn Fn(&BinaryMessage) -> bool) {
+pub async fn perform_testnet_transaction_check(transaction: &SignedTransaction, matcher: &Matcher) {
     const NODE_IDS: [usize; 2] = [0, 1];
 
     // Start a testnet.
@@ -109,7 +130,7 @@ pub async fn perform_testnet_transaction_check(check: &dyn Fn(&BinaryMessage) ->
     testnet.start().await.unwrap();
     wait_for_account_data(
         &testnet.running[NODE_IDS[0]].rpc_url(),
-        GENESIS_ACCOUNT,
+        Account::genesis().address(),
         TESTNET_READY_TIMEOUT,
     )
     .await
@@ -125,7 +146,7 @@ pub async fn perform_testnet_transaction_check(check: &dyn Fn(&BinaryMessage) ->
     // Submit a transaction to the first node via RPC.
     let transaction = submit_transaction(
         &testnet.running[NODE_IDS[0]].rpc_url(),
-        TRANSACTION_BLOB.into(),
+        transaction.to_hex(),
         false,
     )
     .await
@@ -135,7 +156,10 @@ pub async fn perform_testnet_transaction_check(check: &dyn Fn(&BinaryMessage) ->
     assert!(transaction.result.broadcast);
 
     // Ensure that the synthetic node connected to the second node received the required message.
-    assert!(synth_node.expect_message(&check).await);
+    synth_node
+        .expect_matching(matcher)
+        .await
+        .unwrap_or_else(|e| panic!("{e}"));
 
     // Shutdown.
     testnet.stop().await.expect("Unable to stop the testnet.");
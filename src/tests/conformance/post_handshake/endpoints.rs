@@ -1,14 +1,136 @@
+//! Contains tests for the gossiping of peer addresses.
+//!
+//! Peers advertise the addresses of other peers in mtENDPOINTS messages, along with the number of
+//! hops the advertised peer is away. The node keeps the advertised addresses for a while and
+//! passes them on to the peers it sends mtENDPOINTS to, one hop further away.
+//!
+//!     <> with the advertising synthetic node
+//!     -> mtENDPOINTS (A, hops)
+//!     <> with the observing synthetic node
+//!     <- mtENDPOINTS
+//!
+//!     Assert: The observing synthetic node is advertised A at hops + 1 if A is a public address
+//!     within the hop limit.
+
+use std::net::SocketAddr;
+
+use tokio::time::{sleep, Duration};
+
 use crate::{
-    protocol::codecs::message::{BinaryMessage, Payload},
+    protocol::codecs::message::Payload,
     tests::conformance::perform_expected_message_test,
+    tools::{
+        endpoints::{parse_endpoints, Endpoint, MAX_HOPS},
+        harness::TestHarness,
+        matchers::is_kind,
+    },
 };
 
+/// Time for the node to process the advertised endpoints.
+const PROCESSING_DELAY: Duration = Duration::from_secs(1);
+
 #[tokio::test]
 #[allow(non_snake_case)]
 async fn c018_TM_ENDPOINTS_node_should_send_endpoints_after_handshake() {
     // ZG-CONFORMANCE-018
 
     // Check for a TmEndpoints message.
-    let check = |m: &BinaryMessage| matches!(&m.payload, Payload::TmEndpoints(..));
-    perform_expected_message_test(Default::default(), &check).await;
+    perform_expected_message_test(Default::default(), &is_kind("TmEndpoints")).await;
+}
+
+#[tokio::test]
+#[allow(non_snake_case)]
+async fn c028_t1_TM_ENDPOINTS_node_should_relay_public_ipv4_endpoint() {
+    // ZG-CONFORMANCE-028
+
+    let endpoint = Endpoint::new("198.51.100.7:51235".parse().unwrap(), 1);
+
+    let relayed = advertise_and_observe(&[endpoint]).await;
+    assert_eq!(hops_of(&relayed, endpoint.addr), Some(endpoint.hops + 1));
+}
+
+#[tokio::test]
+#[allow(non_snake_case)]
+async fn c028_t2_TM_ENDPOINTS_node_should_relay_public_ipv6_endpoint() {
+    // ZG-CONFORMANCE-028
+
+    let endpoint = Endpoint::new("[2001:db8::7]:51235".parse().unwrap(), 1);
+
+    let relayed = advertise_and_observe(&[endpoint]).await;
+    assert_eq!(hops_of(&relayed, endpoint.addr), Some(endpoint.hops + 1));
+}
+
+#[tokio::test]
+#[allow(non_snake_case)]
+async fn c028_t3_TM_ENDPOINTS_node_should_not_relay_private_endpoints() {
+    // ZG-CONFORMANCE-028
+
+    let endpoints = [
+        "10.0.0.7:51235",
+        "172.16.0.7:51235",
+        "192.168.0.7:51235",
+        "127.0.0.7:51235",
+    ]
+    .map(|addr| Endpoint::new(addr.parse().unwrap(), 1));
+
+    let relayed = advertise_and_observe(&endpoints).await;
+    for endpoint in endpoints {
+        assert_eq!(
+            hops_of(&relayed, endpoint.addr),
+            None,
+            "the private address {} was relayed",
+            endpoint.addr
+        );
+    }
+}
+
+#[tokio::test]
+#[allow(non_snake_case)]
+async fn c028_t4_TM_ENDPOINTS_node_should_not_relay_endpoints_beyond_hop_limit() {
+    // ZG-CONFORMANCE-028
+
+    let endpoint = Endpoint::new("198.51.100.8:51235".parse().unwrap(), MAX_HOPS + 1);
+
+    let relayed = advertise_and_observe(&[endpoint]).await;
+    assert_eq!(hops_of(&relayed, endpoint.addr), None);
+}
+
+/// Advertises the endpoints to the node from one synthetic node and returns the endpoints the
+/// node advertises to another synthetic node connecting afterwards.
+async fn advertise_and_observe(endpoints: &[Endpoint]) -> Vec<Endpoint> {
+    let mut harness = TestHarness::builder().build().await.unwrap();
+    let node_addr = harness.node.addr();
+
+    // The node accepts a single mtENDPOINTS per peer every few minutes, the first one is
+    // accepted right away.
+    harness
+        .synth_node(0)
+        .advertise_endpoints(node_addr, endpoints)
+        .unwrap()
+        .await
+        .unwrap()
+        .unwrap();
+    sleep(PROCESSING_DELAY).await;
+
+    // The node advertises the endpoints it knows of right after the handshake.
+    let observer = harness.connect_new_synth_node().await.unwrap();
+    let message = harness
+        .synth_node_mut(observer)
+        .expect_matching(&is_kind("TmEndpoints"))
+        .await
+        .unwrap_or_else(|e| panic!("{e}"));
+
+    harness.shut_down().await.unwrap();
+
+    match message.payload {
+        Payload::TmEndpoints(endpoints) => parse_endpoints(&endpoints),
+        _ => unreachable!(),
+    }
+}
+
+fn hops_of(endpoints: &[Endpoint], addr: SocketAddr) -> Option<u32> {
+    endpoints
+        .iter()
+        .find(|endpoint| endpoint.addr == addr)
+        .map(|endpoint| endpoint.hops)
 }
@@ -1,6 +1,13 @@
+use tempfile::TempDir;
+
 use crate::{
-    protocol::codecs::message::{BinaryMessage, Payload},
+    protocol::{codecs::message::Payload, manifest::Manifest},
+    setup::{
+        keys::ValidatorKeys,
+        node::{Node, NodeType},
+    },
     tests::conformance::perform_expected_message_test,
+    tools::{matchers::is_non_empty_manifests, synth_node::SyntheticNode},
 };
 
 #[tokio::test]
@@ -9,11 +16,51 @@ async fn c017_TM_MANIFEST_node_should_send_manifest_after_handshake() {
     // ZG-CONFORMANCE-017
 
     // Check for a TmManifests message.
-    let check = |m: &BinaryMessage| {
-        if let Payload::TmManifests(manifests) = &m.payload {
-            return !manifests.list.is_empty() && !manifests.list[0].stobject.is_empty();
-        }
-        false
+    perform_expected_message_test(Default::default(), &is_non_empty_manifests()).await;
+}
+
+#[tokio::test]
+#[allow(non_snake_case)]
+async fn c032_TM_MANIFEST_node_should_send_valid_manifests() {
+    // ZG-CONFORMANCE-032
+
+    // The stateful node validates as the first validator.
+    let target = TempDir::new().expect("Couldn't create a temporary directory");
+    let mut node = Node::builder()
+        .start(target.path(), NodeType::Stateful)
+        .await
+        .expect("Unable to start the stateful node");
+
+    let mut synth_node = SyntheticNode::new(&Default::default()).await;
+    synth_node
+        .connect(node.addr())
+        .await
+        .expect("Unable to connect");
+
+    let message = synth_node
+        .expect_matching(&is_non_empty_manifests())
+        .await
+        .unwrap_or_else(|e| panic!("{e}"));
+    let Payload::TmManifests(manifests) = message.payload else {
+        unreachable!("the matcher only accepts manifests");
     };
-    perform_expected_message_test(Default::default(), &check).await;
+
+    // Every manifest must be signed by both of its keys.
+    let manifests = manifests
+        .list
+        .iter()
+        .map(|manifest| {
+            Manifest::parse(&manifest.stobject).unwrap_or_else(|e| panic!("invalid manifest: {e}"))
+        })
+        .collect::<Vec<_>>();
+
+    // The node must announce its own validator identity.
+    let keys = ValidatorKeys::for_validator(0);
+    assert!(manifests.iter().any(|manifest| {
+        manifest.public_key == keys.master.public_key()
+            && manifest.signing_pub_key == Some(keys.signing.public_key())
+    }));
+
+    synth_node.shut_down().await;
+    node.stop().unwrap();
 }
@@ -1,7 +1,4 @@
-use crate::{
-    protocol::codecs::message::{BinaryMessage, Payload},
-    tests::conformance::perform_expected_message_test,
-};
+use crate::{tests::conformance::perform_expected_message_test, tools::matchers::is_kind};
 
 #[tokio::test]
 #[allow(non_snake_case)]
@@ -9,6 +6,5 @@ async fn c021_TM_VALIDATION_node_should_send_validation_after_handshake() {
     // ZG-CONFORMANCE-021
 
     // Check for a TmValidation message.
-    let check = |m: &BinaryMessage| matches!(&m.payload, Payload::TmValidation(..));
-    perform_expected_message_test(Default::default(), &check).await;
+    perform_expected_message_test(Default::default(), &is_kind("TmValidation")).await;
 }
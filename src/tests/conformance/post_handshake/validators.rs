@@ -1,57 +1,30 @@
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::collections::{BTreeMap, HashSet};
 
 use base64::{engine::general_purpose::STANDARD, Engine};
-use bytes::{BufMut, BytesMut};
-use secp256k1::{constants::PUBLIC_KEY_SIZE, Message, Secp256k1, SecretKey};
-use serde::{Deserialize, Serialize};
-use sha2::{Digest, Sha512};
-use tempfile::TempDir;
-use tokio::time::timeout;
-use ziggurat_core_utils::err_constants::{
-    ERR_NODE_BUILD, ERR_NODE_STOP, ERR_SYNTH_CONNECT, ERR_SYNTH_UNICAST, ERR_TEMPDIR_NEW,
-};
-
-// serialization type field constants from rippled
-const ST_TAG_SEQUENCE: u8 = 0x24;
-const ST_TAG_VARIABLE_LENGTH_BASE: u8 = 0x70;
-const ST_TAG_PUBLIC_KEY: u8 = 0x71;
-const ST_TAG_SIGNING_PUBLIC_KEY: u8 = 0x73;
-const ST_TAG_SIGNATURE: u8 = 0x76;
-const ST_TAG_MASTER_SIGNATURE: u8 = 0x12;
-
-const ONE_YEAR: u32 = 86400 * 365;
-const RAND_SEQUENCE_NUMBER: u32 = 2022102584;
-const MANIFEST_PREFIX: &[u8] = b"MAN\x00";
-const WAIT_MSG_TIMEOUT: Duration = Duration::from_secs(5);
-
-// The master public key should be in the validators.txt file, in ~/.ziggurat/ripple/setup
-const MASTER_SECRET: &str = "8484781AE8EEB87D8A5AA38483B5CBBCCE6AD66B4185BB193DDDFAD5C1F4FC06";
-const MASTER_PUBLIC: &str = "02ED521B8124454DD5B7769C813BD40E8D36E134DD51ACED873B49E165327F6DF2";
-const SIGNING_SECRET: &str = "00F963180681C0D1D51D1128096B8FF8668AFDC41CBDED511D12D390105EFDDC";
-const SIGNING_PUBLIC: &str = "03859B76317C8AA64F2D253D3547831E413F2663AE2568F7A17E85B283CC8861E4";
+use secp256k1::constants::PUBLIC_KEY_SIZE;
+use ziggurat_core_utils::err_constants::{ERR_NODE_BUILD, ERR_SYNTH_UNICAST};
 
 use crate::{
     protocol::{
-        codecs::message::{BinaryMessage, Payload},
-        proto::TmValidatorList,
+        codecs::message::Payload,
+        unl::{collection, verify_collection, SignedList, UnlBuilder},
+    },
+    tests::conformance::{perform_expected_message_test, PUBLIC_KEY_TYPES},
+    tools::{
+        harness::TestHarness,
+        matchers::{is_validator_list_where, Matcher},
+        ripple_time,
+        validator::{KeyType, ValidatorKey, ValidatorList},
     },
-    setup::node::{Node, NodeType},
-    tests::conformance::{perform_expected_message_test, PUBLIC_KEY_TYPES, RIPPLE_EPOCH},
-    tools::synth_node::SyntheticNode,
 };
 
-#[derive(Deserialize, Serialize)]
-struct Validator {
-    validation_public_key: String,
-    manifest: String,
-}
+const RAND_SEQUENCE_NUMBER: u32 = 2022102584;
 
-#[derive(Deserialize, Serialize)]
-struct ValidatorList {
-    sequence: u32,
-    expiration: u32,
-    validators: Vec<Validator>,
-}
+// The master public keys should be in the validators.txt file, in ~/.ziggurat/ripple/setup
+const MASTER_SECRET: &str = "8484781AE8EEB87D8A5AA38483B5CBBCCE6AD66B4185BB193DDDFAD5C1F4FC06";
+const SIGNING_SECRET: &str = "00F963180681C0D1D51D1128096B8FF8668AFDC41CBDED511D12D390105EFDDC";
+const ED25519_MASTER_SECRET: &str =
+    "9D61B19DEFFD5A60BA844AF492EC2CC44449C5697B326919703BAC031CAE7F60";
 
 #[tokio::test]
 #[allow(non_snake_case)]
@@ -59,8 +32,8 @@ async fn c015_TM_VALIDATOR_LIST_COLLECTION_node_should_send_validator_list() {
     // ZG-CONFORMANCE-015
 
     // Check for a TmValidatorListCollection message.
-    let check = |m: &BinaryMessage| {
-        if let Payload::TmValidatorListCollection(validator_list_collection) = &m.payload {
+    let matcher = Matcher::new("a non-empty TmValidatorListCollection", |payload| {
+        if let Payload::TmValidatorListCollection(validator_list_collection) = payload {
             if let Some(blob_info) = validator_list_collection.blobs.first() {
                 let decoded_blob = STANDARD
                     .decode(&blob_info.blob)
@@ -89,219 +62,191 @@ async fn c015_TM_VALIDATOR_LIST_COLLECTION_node_should_send_validator_list() {
             }
         }
         false
-    };
-    perform_expected_message_test(Default::default(), &check).await;
-}
-
-fn create_sha512_half_digest(buffer: &[u8]) -> [u8; 32] {
-    let mut hasher = Sha512::new();
-    hasher.update(buffer);
-    let result = hasher.finalize();
-
-    // we return 32 bytes of 64-byte result
-    let mut signature = [0u8; 32];
-    signature.copy_from_slice(&result[..32]);
-    signature
+    });
+    perform_expected_message_test(Default::default(), &matcher).await;
 }
 
-fn get_expiration() -> u32 {
-    // expiration  = now + 1 year.
-    // however, validator blob uses delta from Jan 1 2000,
-    // and not 1970, per Unix epoch time
-    // so we subtract time for January 1 2000
-    let start = SystemTime::now();
-    let epoch = start
-        .duration_since(UNIX_EPOCH)
-        .expect("time went backwards");
-    let now = epoch.as_secs() as u32;
-    let year: u32 = ONE_YEAR;
-    now + year - RIPPLE_EPOCH
-}
+/// Sends a validator list signed by the given publisher keys and checks that it gets relayed.
+async fn send_validator_list(master: ValidatorKey, signing: ValidatorKey) {
+    let mut harness = TestHarness::builder()
+        .synth_nodes(2)
+        .build()
+        .await
+        .expect(ERR_NODE_BUILD);
+    let node_addr = harness.node.addr();
+
+    // The publisher lists itself, with its own manifest.
+    let list = UnlBuilder::new(master.clone(), signing.clone())
+        .sequence(RAND_SEQUENCE_NUMBER)
+        .validator(&master, &signing)
+        .build();
+    harness
+        .synth_node(0)
+        .unicast(node_addr, Payload::TmValidatorList(list.to_message()))
+        .expect(ERR_SYNTH_UNICAST);
 
-fn create_validator_list_json(manifest: &[u8], public_key: &str) -> String {
-    let validator = Validator {
-        validation_public_key: public_key.to_string(),
-        manifest: STANDARD.encode(manifest),
-    };
+    // Only our list has a single validator, so the others are skipped.
+    let master_public = master.public_key_hex();
+    let matcher = is_validator_list_where("with the sent sequence", move |list| {
+        list.validators.len() == 1
+            && list.sequence == RAND_SEQUENCE_NUMBER
+            && list.validators[0].validation_public_key == master_public
+    });
+    harness
+        .synth_node_mut(1)
+        .expect_matching(&matcher)
+        .await
+        .expect("valid TmValidatorListCollection not received in time");
 
-    let validator_list = ValidatorList {
-        sequence: RAND_SEQUENCE_NUMBER,
-        expiration: get_expiration(),
-        validators: vec![validator],
-    };
-    serde_json::to_string(&validator_list).unwrap()
+    harness.shut_down().await.unwrap();
 }
 
-fn create_manifest(sequence: u32, public_key: &[u8], signing_pub_key: &[u8]) -> BytesMut {
-    let mut buf = BytesMut::with_capacity(1024);
-
-    buf.put_u8(ST_TAG_SEQUENCE);
-    buf.put_u32(sequence);
-
-    // serialize public key
-    buf.put_u8(ST_TAG_PUBLIC_KEY);
-    buf.put_u8(PUBLIC_KEY_SIZE as u8);
-    buf.extend_from_slice(public_key);
+#[tokio::test]
+#[allow(non_snake_case)]
+async fn c026_t1_TM_VALIDATOR_LIST_send_validator_list_secp256k1() {
+    // ZG-CONFORMANCE-026
 
-    // serialize signing public key
-    buf.put_u8(ST_TAG_SIGNING_PUBLIC_KEY);
-    buf.put_u8(PUBLIC_KEY_SIZE as u8);
-    buf.extend_from_slice(signing_pub_key);
+    // Both master and signing key pairs have been previously generated.
+    let master = ValidatorKey::from_hex(KeyType::Secp256k1, MASTER_SECRET).unwrap();
+    let signing = ValidatorKey::from_hex(KeyType::Secp256k1, SIGNING_SECRET).unwrap();
 
-    buf
+    send_validator_list(master, signing).await;
 }
 
-fn sign_manifest(mut manifest: BytesMut, master_signature: &[u8], signature: &[u8]) -> BytesMut {
-    // serialize signature
-    manifest.put_u8(ST_TAG_SIGNATURE);
-    manifest.put_u8(signature.len() as u8);
-    manifest.extend_from_slice(signature);
+#[tokio::test]
+#[allow(non_snake_case)]
+async fn c026_t2_TM_VALIDATOR_LIST_send_validator_list_ed25519_signing_key() {
+    // ZG-CONFORMANCE-026
 
-    // serialize master signature
-    manifest.put_u8(ST_TAG_VARIABLE_LENGTH_BASE);
-    manifest.put_u8(ST_TAG_MASTER_SIGNATURE);
-    manifest.put_u8(master_signature.len() as u8);
-    manifest.extend_from_slice(master_signature);
+    let master = ValidatorKey::from_hex(KeyType::Secp256k1, MASTER_SECRET).unwrap();
+    let signing = ValidatorKey::generate(KeyType::Ed25519);
 
-    manifest
+    send_validator_list(master, signing).await;
 }
 
-fn sign_buffer(secret_key: &SecretKey, buffer: &[u8]) -> Vec<u8> {
-    let engine = Secp256k1::new();
-    let digest = create_sha512_half_digest(buffer);
-    let message = Message::from_slice(&digest).unwrap();
-    let signature = engine.sign_ecdsa(&message, secret_key).serialize_der();
-    let signature_b64 = STANDARD.encode(signature);
+#[tokio::test]
+#[allow(non_snake_case)]
+async fn c026_t3_TM_VALIDATOR_LIST_send_validator_list_ed25519() {
+    // ZG-CONFORMANCE-026
+
+    let master = ValidatorKey::from_hex(KeyType::Ed25519, ED25519_MASTER_SECRET).unwrap();
+    let signing = ValidatorKey::generate(KeyType::Ed25519);
 
-    STANDARD
-        .decode(signature_b64)
-        .expect("unable to decode a blob")
+    send_validator_list(master, signing).await;
 }
 
-fn sign_buffer_with_prefix(hash_prefix: &[u8], secret_key: &SecretKey, buffer: &[u8]) -> Vec<u8> {
-    let mut prefixed_buffer = BytesMut::with_capacity(1024);
-    prefixed_buffer.put(hash_prefix);
-    prefixed_buffer.extend_from_slice(buffer);
+// A list of the publisher trusted by the node, listing the publisher itself.
+fn trusted_list(sequence: u32) -> UnlBuilder {
+    let master = ValidatorKey::from_hex(KeyType::Secp256k1, MASTER_SECRET).unwrap();
+    let signing = ValidatorKey::from_hex(KeyType::Secp256k1, SIGNING_SECRET).unwrap();
 
-    sign_buffer(secret_key, &prefixed_buffer)
+    UnlBuilder::new(master.clone(), signing.clone())
+        .sequence(sequence)
+        .validator(&master, &signing)
 }
 
-#[tokio::test]
-#[allow(non_snake_case)]
-async fn c026_TM_VALIDATOR_LIST_send_validator_list() {
-    // Create stateful node.
-    let target = TempDir::new().expect(ERR_TEMPDIR_NEW);
-    let mut node = Node::builder()
-        .start(target.path(), NodeType::Stateless)
+/// Sends the lists in a collection and returns the sent lists the node relays, by sequence.
+///
+/// The relayed lists are taken from the first collection the node sends with any of them.
+async fn relay_collection(lists: &[SignedList]) -> BTreeMap<u32, ValidatorList> {
+    let mut harness = TestHarness::builder()
+        .synth_nodes(2)
+        .build()
         .await
         .expect(ERR_NODE_BUILD);
+    let node_addr = harness.node.addr();
+
+    let sent = lists
+        .iter()
+        .map(|list| list.verify().expect("invalid list").list.sequence)
+        .collect::<HashSet<_>>();
+    harness
+        .synth_node(0)
+        .unicast(
+            node_addr,
+            Payload::TmValidatorListCollection(collection(lists)),
+        )
+        .expect(ERR_SYNTH_UNICAST);
 
-    // create & connect two synth nodes
-    let synth_node1 = SyntheticNode::new(&Default::default()).await;
-    synth_node1
-        .connect(node.addr())
-        .await
-        .expect(ERR_SYNTH_CONNECT);
-    let mut synth_node2 = SyntheticNode::new(&Default::default()).await;
-    synth_node2
-        .connect(node.addr())
+    let matcher = {
+        let sent = sent.clone();
+        is_validator_list_where("with a sent sequence", move |list| {
+            sent.contains(&list.sequence)
+        })
+    };
+    let message = harness
+        .synth_node_mut(1)
+        .expect_matching(&matcher)
         .await
-        .expect(ERR_SYNTH_CONNECT);
+        .expect("the lists weren't relayed in time");
+    harness.shut_down().await.unwrap();
 
-    // 1. Setup keys & prefix.  Both master and signing key pairs have been previously generated.
-    let master_secret = hex::decode(MASTER_SECRET).expect("unable to decode hex");
-    let master_public = hex::decode(MASTER_PUBLIC).expect("unable to decode hex");
-    let master_secret_key =
-        SecretKey::from_slice(master_secret.as_slice()).expect("unable to create secret key");
+    let Payload::TmValidatorListCollection(relayed) = message.payload else {
+        panic!("the lists were relayed in a {}", message.payload.name());
+    };
+    verify_collection(&relayed)
+        .into_iter()
+        .map(|verified| verified.expect("the node relayed an invalid list").list)
+        .filter(|list| sent.contains(&list.sequence))
+        .map(|list| (list.sequence, list))
+        .collect()
+}
 
-    let signing_secret = hex::decode(SIGNING_SECRET).expect("unable to decode hex");
-    let signing_public = hex::decode(SIGNING_PUBLIC).expect("unable to decode hex");
-    let signing_secret_key =
-        SecretKey::from_slice(signing_secret.as_slice()).expect("unable to create secret key");
+#[tokio::test]
+#[allow(non_snake_case)]
+async fn c036_t1_TM_VALIDATOR_LIST_COLLECTION_latest_effective_list_is_relayed() {
+    // ZG-CONFORMANCE-036
 
-    // 2. Create manifest with sequence, public key, signing public key (without signatures)
-    assert_eq!(
-        master_public.len(),
-        PUBLIC_KEY_SIZE,
-        "invalid master public key length: {}",
-        master_public.len()
-    );
+    let lists = (0..3)
+        .map(|i| trusted_list(RAND_SEQUENCE_NUMBER + i).build())
+        .collect::<Vec<_>>();
+
+    let relayed = relay_collection(&lists).await;
     assert_eq!(
-        signing_public.len(),
-        PUBLIC_KEY_SIZE,
-        "invalid signing public key length: {}",
-        signing_public.len()
+        relayed.keys().copied().collect::<Vec<_>>(),
+        [RAND_SEQUENCE_NUMBER + 2]
     );
-    let manifest = create_manifest(1, &master_public, &signing_public);
-
-    // 3. Sign the manifest with master secret key, get master signature
-    let master_signature_bytes =
-        sign_buffer_with_prefix(MANIFEST_PREFIX, &master_secret_key, &manifest);
-
-    // 4. Sign it with signing private key, get signature
-    let signature_bytes = sign_buffer_with_prefix(MANIFEST_PREFIX, &signing_secret_key, &manifest);
-
-    // 5. Create signed manifest with sequence, public key, signing public key, master signature, signature
-    let signed_manifest = sign_manifest(manifest, &master_signature_bytes, &signature_bytes);
-
-    // 6. Create Validator blob.
-    let blob = create_validator_list_json(&signed_manifest, MASTER_PUBLIC);
-
-    // 7.  Get signature for blob using master private key
-    let signature = sign_buffer(&signing_secret_key, blob.as_bytes());
-
-    // 8. Setup payload, send it
-    let manifest = STANDARD.encode(signed_manifest).as_bytes().to_vec();
-    let signature = hex::encode_upper(signature).as_bytes().to_vec();
-    let blob = STANDARD.encode(&blob).as_bytes().to_vec();
-
-    let payload = Payload::TmValidatorList(TmValidatorList {
-        manifest,
-        blob,
-        signature,
-        version: 1,
-    });
-    synth_node1
-        .unicast(node.addr(), payload)
-        .expect(ERR_SYNTH_UNICAST);
-
-    let check = |m: &BinaryMessage| {
-        if let Payload::TmValidatorListCollection(validator_list_collection) = &m.payload {
-            if let Some(blob_info) = validator_list_collection.blobs.first() {
-                let decoded_blob = STANDARD
-                    .decode(&blob_info.blob)
-                    .expect("unable to decode a blob");
+}
 
-                let text = String::from_utf8(decoded_blob)
-                    .expect("unable to convert decoded blob to a string");
+#[tokio::test]
+#[allow(non_snake_case)]
+async fn c036_t2_TM_VALIDATOR_LIST_COLLECTION_expired_list_is_dropped() {
+    // ZG-CONFORMANCE-036
 
-                let validator_list = serde_json::from_str::<ValidatorList>(&text)
-                    .expect("unable to deserialize a validator list");
+    let lists = [
+        trusted_list(RAND_SEQUENCE_NUMBER).build(),
+        trusted_list(RAND_SEQUENCE_NUMBER + 1)
+            .expiration(ripple_time::now() - 60)
+            .build(),
+    ];
 
-                // Only our message has a single validator, so we skip the others
-                if validator_list.validators.len() == 1 {
-                    assert_eq!(validator_list.sequence, RAND_SEQUENCE_NUMBER);
-                    assert_eq!(
-                        validator_list.validators[0].validation_public_key,
-                        MASTER_PUBLIC
-                    );
-                    return true;
-                }
-            }
-        }
-        false
-    };
+    let relayed = relay_collection(&lists).await;
+    assert_eq!(
+        relayed.keys().copied().collect::<Vec<_>>(),
+        [RAND_SEQUENCE_NUMBER]
+    );
+}
 
-    timeout(WAIT_MSG_TIMEOUT, async {
-        while !synth_node2.expect_message(&check).await {
-            continue;
-        }
-    })
-    .await
-    .expect("valid TmValidatorListCollection not received in time");
-
-    // Shutdown.
-    synth_node1.shut_down().await;
-    synth_node2.shut_down().await;
-    node.stop().expect(ERR_NODE_STOP);
+#[tokio::test]
+#[allow(non_snake_case)]
+async fn c036_t3_TM_VALIDATOR_LIST_COLLECTION_future_list_is_relayed_as_pending() {
+    // ZG-CONFORMANCE-036
+
+    let effective = ripple_time::now() + 60 * 60;
+    let lists = [
+        trusted_list(RAND_SEQUENCE_NUMBER).build(),
+        trusted_list(RAND_SEQUENCE_NUMBER + 1)
+            .effective(effective)
+            .build(),
+    ];
+
+    let relayed = relay_collection(&lists).await;
+    assert_eq!(
+        relayed.keys().copied().collect::<Vec<_>>(),
+        [RAND_SEQUENCE_NUMBER, RAND_SEQUENCE_NUMBER + 1]
+    );
+    assert_eq!(
+        relayed[&(RAND_SEQUENCE_NUMBER + 1)].effective,
+        Some(effective)
+    );
 }
@@ -10,7 +10,8 @@ use crate::{
     },
     setup::node::{Node, NodeType},
     tools::{
-        constants::{EXPECTED_RESULT_TIMEOUT, TEST_ACCOUNT},
+        accounts::TEST_ACCOUNT,
+        constants::EXPECTED_RESULT_TIMEOUT,
         rpc::{get_transaction_info, wait_for_account_data, wait_for_state},
         synth_node::SyntheticNode,
     },
@@ -5,10 +5,13 @@
 
 use crate::{
     protocol::{
-        codecs::message::{BinaryMessage, Payload},
+        codecs::message::Payload,
+        ledger::LedgerHeader,
         proto::{TmGetLedger, TmLedgerInfoType, TmLedgerType},
+        shamap::{PartialShaMap, ShaMapNodeId},
     },
     tests::conformance::{perform_expected_message_test, TestConfig},
+    tools::{harness::TestHarness, matchers::is_kind},
 };
 
 #[tokio::test]
@@ -47,8 +50,53 @@ async fn c004_t2_TM_GET_LEDGER_LiAsNode_get_account_state_info() {
     check_for_ledger_data_response(payload).await;
 }
 
+#[tokio::test]
+#[allow(non_snake_case)]
+async fn c004_t3_TM_GET_LEDGER_LiAsNode_get_account_state_by_ledger_hash() {
+    // ZG-CONFORMANCE-004
+    let mut harness = TestHarness::builder().build().await.unwrap();
+    let node_addr = harness.node.addr();
+    let synth_node = harness.synth_node_mut(0);
+
+    let base = synth_node
+        .request_ledger(node_addr, TmLedgerInfoType::LiBase, None, None)
+        .await
+        .unwrap_or_else(|e| panic!("{e}"));
+    assert_eq!(base.itype, TmLedgerInfoType::LiBase);
+    assert!(base.error.is_none());
+    assert!(!base.nodes.is_empty(), "the ledger header is missing");
+    let header = LedgerHeader::parse(&base.nodes[0].nodedata).expect("invalid ledger header");
+    assert_eq!(header.hash().as_slice(), base.ledger_hash);
+
+    // Ask for the same ledger by its hash, the reply must concern that ledger.
+    let state = synth_node
+        .request_ledger(
+            node_addr,
+            TmLedgerInfoType::LiAsNode,
+            Some(base.ledger_hash.clone()),
+            Some(1),
+        )
+        .await
+        .unwrap_or_else(|e| panic!("{e}"));
+    assert_eq!(state.itype, TmLedgerInfoType::LiAsNode);
+    assert_eq!(state.ledger_hash, base.ledger_hash);
+    assert_eq!(state.ledger_seq, base.ledger_seq);
+    assert!(state.error.is_none());
+
+    // The nodes must form the top of the ledger's state tree.
+    let nodes = state.tree_nodes().expect("invalid state tree nodes");
+    let mut tree = PartialShaMap::new(header.account_hash);
+    tree.insert_all(nodes)
+        .unwrap_or_else(|e| panic!("the nodes don't belong to the state tree: {e}"));
+    assert!(tree.get(&ShaMapNodeId::ROOT).is_some());
+
+    harness.shut_down().await.unwrap();
+}
+
 async fn check_for_ledger_data_response(payload: Payload) {
-    let check = |m: &BinaryMessage| matches!(&m.payload, Payload::TmLedgerData(..));
-    perform_expected_message_test(TestConfig::default().with_initial_message(payload), &check)
-        .await;
+    perform_expected_message_test(
+        TestConfig::default().with_initial_message(payload),
+        &is_kind("TmLedgerData"),
+    )
+    .await;
 }
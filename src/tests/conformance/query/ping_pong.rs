@@ -5,21 +5,19 @@
 
 use std::time::Duration;
 
-use rand::{thread_rng, RngCore};
-use tempfile::TempDir;
+use rand::RngCore;
 use tokio::time::{sleep, Instant};
-use ziggurat_core_utils::err_constants::{
-    ERR_NODE_BUILD, ERR_SYNTH_CONNECT, ERR_SYNTH_UNICAST, ERR_TEMPDIR_NEW,
-};
+use ziggurat_core_utils::err_constants::{ERR_NODE_BUILD, ERR_SYNTH_UNICAST};
 
 use crate::{
+    fuzzing::seeded_rng,
     protocol::{
-        codecs::message::{BinaryMessage, Payload},
+        codecs::message::Payload,
         proto::{tm_ping::PingType, TmPing},
     },
-    setup::node::{Node, NodeType},
+    setup::node::NodeType,
     tests::conformance::{perform_expected_message_test, TestConfig},
-    tools::synth_node::SyntheticNode,
+    tools::{harness::TestHarness, matchers::is_pong_with_seq},
 };
 
 const EXPECTED_PING_MESSAGE_TIMEOUT: Duration = Duration::from_secs(62);
@@ -29,7 +27,7 @@ const EXPECTED_PING_MESSAGE_TIMEOUT: Duration = Duration::from_secs(62);
 async fn c003_t1_TM_PING_expect_pong() {
     // ZG-CONFORMANCE-003
     // Send `ping` message
-    let seq = thread_rng().next_u32();
+    let seq = seeded_rng().next_u32();
 
     let payload = Payload::TmPing(TmPing {
         r#type: PingType::PtPing as i32,
@@ -37,20 +35,12 @@ async fn c003_t1_TM_PING_expect_pong() {
         ping_time: None,
         net_time: None,
     });
-    let check = |m: &BinaryMessage| {
-        matches!(
-            &m.payload,
-            // proto file defines 'pong' message as `TmPing` with `r#type` set to [PingType::PtPong]
-            Payload::TmPing(TmPing {
-                r#type: r_type,
-                seq: Some(s),
-                ..
-            }) if *s == seq && *r_type == PingType::PtPong as i32
-        )
-    };
     // Wait for reply
-    perform_expected_message_test(TestConfig::default().with_initial_message(payload), &check)
-        .await;
+    perform_expected_message_test(
+        TestConfig::default().with_initial_message(payload),
+        &is_pong_with_seq(seq),
+    )
+    .await;
 }
 
 #[tokio::test]
@@ -58,19 +48,14 @@ async fn c003_t1_TM_PING_expect_pong() {
 async fn c003_t2_TM_PING_expect_ping() {
     // ZG-CONFORMANCE-003
 
-    // Create a rippled node.
-    let target = TempDir::new().expect(ERR_TEMPDIR_NEW);
-    let mut node = Node::builder()
-        .start(target.path(), NodeType::Stateful)
+    // Create a rippled node and connect a synthetic node to it.
+    let mut harness = TestHarness::builder()
+        .node_type(NodeType::Stateful)
+        .build()
         .await
         .expect(ERR_NODE_BUILD);
-
-    // Create a synthetic node and connect it to the node.
-    let mut synth_node = SyntheticNode::new(&Default::default()).await;
-    synth_node
-        .connect(node.addr())
-        .await
-        .expect(ERR_SYNTH_CONNECT);
+    let node_addr = harness.node.addr();
+    let synth_node = harness.synth_node_mut(0);
 
     // Wait for ping message so that we can respond with correct `pong`.
     let start = Instant::now();
@@ -101,16 +86,15 @@ async fn c003_t2_TM_PING_expect_ping() {
         net_time: None,
     });
     synth_node
-        .unicast(node.addr(), response)
+        .unicast(node_addr, response)
         .expect(ERR_SYNTH_UNICAST);
 
     // Assert that we're still connected after given timeout.
     sleep(EXPECTED_PING_MESSAGE_TIMEOUT).await;
-    assert!(synth_node.is_connected(node.addr()));
+    assert!(synth_node.is_connected(node_addr));
 
     // Shutdown both nodes
-    synth_node.shut_down().await;
-    node.stop().unwrap();
+    harness.shut_down().await.unwrap();
 }
 
 #[tokio::test]
@@ -118,24 +102,19 @@ async fn c003_t2_TM_PING_expect_ping() {
 async fn c003_t3_TM_PING_send_pong() {
     // ZG-CONFORMANCE-003
 
-    // Create a rippled node.
-    let target = TempDir::new().expect(ERR_TEMPDIR_NEW);
-    let mut node = Node::builder()
-        .start(target.path(), NodeType::Stateful)
+    // Create a rippled node and connect a synthetic node to it.
+    let harness = TestHarness::builder()
+        .node_type(NodeType::Stateful)
+        .build()
         .await
         .expect(ERR_NODE_BUILD);
-
-    // Create a synthetic node and connect it to rippled.
-    let synth_node = SyntheticNode::new(&Default::default()).await;
-    synth_node
-        .connect(node.addr())
-        .await
-        .expect(ERR_SYNTH_CONNECT);
+    let node_addr = harness.node.addr();
+    let synth_node = harness.synth_node(0);
 
     // Send unsolicited `pong` response.
     synth_node
         .unicast(
-            node.addr(),
+            node_addr,
             Payload::TmPing(TmPing {
                 r#type: PingType::PtPong as i32,
                 seq: Some(42),
@@ -145,9 +124,8 @@ async fn c003_t3_TM_PING_send_pong() {
         )
         .expect(ERR_SYNTH_UNICAST);
     sleep(2 * EXPECTED_PING_MESSAGE_TIMEOUT).await;
-    assert!(!synth_node.is_connected(node.addr()));
+    assert!(!synth_node.is_connected(node_addr));
 
     // Shutdown both nodes
-    synth_node.shut_down().await;
-    node.stop().unwrap();
+    harness.shut_down().await.unwrap();
 }
@@ -2,11 +2,16 @@ use tempfile::TempDir;
 
 use crate::{
     protocol::{
-        codecs::message::{BinaryMessage, Payload},
-        proto::{TmLedgerMapType, TmProofPathRequest, TmProofPathResponse},
+        codecs::message::Payload,
+        proto::{TmLedgerMapType, TmProofPathRequest},
+        shamap::{verify_proof_response, ShaMapNode},
     },
     setup::node::{Node, NodeType},
-    tools::{rpc::wait_for_ledger_info, synth_node::SyntheticNode},
+    tools::{
+        matchers::Matcher,
+        rpc::{get_all_ledger_data, wait_for_ledger_info},
+        synth_node::SyntheticNode,
+    },
 };
 
 #[tokio::test]
@@ -23,7 +28,11 @@ async fn c025_TM_PROOF_PATH_REQUEST_TM_PROOF_PATH_RESPONSE_send_req_expect_rsp()
     let ledger_info = wait_for_ledger_info(&node.rpc_url())
         .await
         .expect("unable to get ledger info");
-    assert!(!ledger_info.result.ledger.account_state.is_empty());
+    let ledger_hash = ledger_info.result.ledger.ledger_hash;
+    let objects = get_all_ledger_data(&node.rpc_url(), &ledger_hash)
+        .await
+        .expect("unable to get the ledger's state objects");
+    assert!(!objects.is_empty());
 
     // Create a synthetic node and connect it to rippled.
     let mut synth_node = SyntheticNode::new(&Default::default()).await;
@@ -32,11 +41,11 @@ async fn c025_TM_PROOF_PATH_REQUEST_TM_PROOF_PATH_RESPONSE_send_req_expect_rsp()
         .await
         .expect("unable to connect");
 
-    // Query for proof_path for every account_state.
-    let ledger_hash =
-        hex::decode(ledger_info.result.ledger.ledger_hash).expect("unable to decode ledger hash");
-    for state in ledger_info.result.ledger.account_state {
-        get_proof_path_for_state(&node, &mut synth_node, &ledger_hash, &state).await;
+    // Query for proof_path for every state object.
+    let ledger_hash = hex::decode(ledger_hash).expect("unable to decode ledger hash");
+    for object in objects {
+        let key = object.key().expect("unable to decode the object's key");
+        get_proof_path_for_state(&node, &mut synth_node, &ledger_hash, key).await;
     }
 
     // Shutdown.
@@ -48,12 +57,10 @@ async fn get_proof_path_for_state(
     node: &Node,
     synth_node: &mut SyntheticNode,
     ledger_hash: &[u8],
-    state: &str,
+    key: [u8; 32],
 ) {
-    // Use `state` as the key to query for.
-    let key = hex::decode(state).expect("unable to decode the account state");
     let payload = Payload::TmProofPathRequest(TmProofPathRequest {
-        key: key.clone(),
+        key: key.to_vec(),
         ledger_hash: ledger_hash.to_vec(),
         r#type: TmLedgerMapType::LmAccountState as i32,
     });
@@ -64,11 +71,23 @@ async fn get_proof_path_for_state(
         .expect("unable to send the message");
 
     // Ensure that the synthetic node receives TmProofPathResponse.
-    let check = |m: &BinaryMessage| {
-        matches!(&m.payload, Payload::TmProofPathResponse(
-        TmProofPathResponse{key: response_key, ledger_hash: response_ledger_hash, path, ..})
-            if *response_key == key && response_ledger_hash == ledger_hash && !path.is_empty()
-        )
+    let expected_ledger_hash = ledger_hash.to_vec();
+    let matcher = Matcher::new(
+        format!("a TmProofPathResponse for key {}", hex::encode(key)),
+        move |payload| {
+            matches!(payload, Payload::TmProofPathResponse(response)
+            if response.key == key && response.ledger_hash == expected_ledger_hash)
+        },
+    );
+    let response = match synth_node.expect_matching(&matcher).await {
+        Ok(message) => match message.payload {
+            Payload::TmProofPathResponse(response) => response,
+            _ => unreachable!("the matcher only accepts proof path responses"),
+        },
+        Err(e) => panic!("{e}"),
     };
-    assert!(synth_node.expect_message(&check).await);
+
+    // Ensure the path proves the object is in the ledger's state tree.
+    let leaf = verify_proof_response(&response).expect("invalid proof path");
+    assert!(matches!(leaf, ShaMapNode::AccountState { .. }));
 }
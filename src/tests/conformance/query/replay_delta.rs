@@ -1,12 +1,18 @@
+use std::collections::HashSet;
+
 use tempfile::TempDir;
 
 use crate::{
     protocol::{
         codecs::message::{BinaryMessage, Payload},
-        proto::TmReplayDeltaRequest,
+        proto::{TmReplayDeltaRequest, TmReplyError},
     },
     setup::node::{Node, NodeType},
-    tools::{rpc::wait_for_ledger_info, synth_node::SyntheticNode},
+    tools::{
+        replay_delta::{ReplayDelta, ReplayDeltaError},
+        rpc::{get_ledger_transactions, wait_for_ledger_info},
+        synth_node::SyntheticNode,
+    },
 };
 
 #[tokio::test]
@@ -52,3 +58,67 @@ async fn c022_TM_REPLAY_DELTA_REQUEST_TM_REPLAY_DELTA_RESPONSE_node_should_respo
     synth_node.shut_down().await;
     node.stop().expect("Unable to stop the rippled node.");
 }
+
+#[tokio::test]
+#[allow(non_snake_case)]
+async fn c033_TM_REPLAY_DELTA_RESPONSE_node_should_send_the_ledger_header_and_transactions() {
+    // ZG-CONFORMANCE-033
+
+    // Create a rippled node.
+    let target = TempDir::new().expect("Unable to create TempDir.");
+    let mut node = Node::builder()
+        .start(target.path(), NodeType::Stateful)
+        .await
+        .expect("Unable to start the rippled node.");
+
+    // Create a synthetic node.
+    let mut synth_node = SyntheticNode::new(&Default::default()).await;
+    synth_node
+        .connect(node.addr())
+        .await
+        .expect("Unable to connect.");
+
+    // Get the last validated ledger and its transactions via RPC.
+    let ledger_info = wait_for_ledger_info(&node.rpc_url())
+        .await
+        .expect("Unable to get ledger info.");
+    let ledger = get_ledger_transactions(&node.rpc_url(), &ledger_info.result.ledger.ledger_hash)
+        .await
+        .expect("Unable to get the ledger's transactions.")
+        .result
+        .ledger;
+    let ledger_hash = hex::decode(&ledger.ledger_hash).expect("Unable to decode ledger hash.");
+
+    // Case 1: the ledger is known.
+    let response = synth_node
+        .request_replay_delta(node.addr(), ledger_hash)
+        .await
+        .expect("No replay delta response.");
+    let delta = ReplayDelta::from_response(&response).expect("Invalid replay delta.");
+    assert_eq!(delta.header.seq.to_string(), ledger.ledger_index);
+    let tx_hashes = delta
+        .transaction_hashes()
+        .iter()
+        .map(hex::encode_upper)
+        .collect::<HashSet<_>>();
+    let expected = ledger
+        .transactions
+        .iter()
+        .map(|tx| tx.to_uppercase())
+        .collect::<HashSet<_>>();
+    assert_eq!(tx_hashes, expected);
+
+    // Case 2: the ledger is unknown.
+    let response = synth_node
+        .request_replay_delta(node.addr(), vec![0xab; 32])
+        .await
+        .expect("No replay delta response.");
+    assert_eq!(
+        ReplayDelta::from_response(&response),
+        Err(ReplayDeltaError::Refused(TmReplyError::ReNoLedger))
+    );
+
+    // Shutdown.
+    synth_node.shut_down().await;
+    node.stop().expect("Unable to stop the rippled node.");
+}
@@ -5,7 +5,8 @@ use tempfile::TempDir;
 use crate::{
     setup::node::{Node, NodeType},
     tools::{
-        constants::{EXPECTED_RESULT_TIMEOUT, TEST_ACCOUNT},
+        accounts::TEST_ACCOUNT,
+        constants::EXPECTED_RESULT_TIMEOUT,
         rpc::{wait_for_account_data, wait_for_state},
     },
 };
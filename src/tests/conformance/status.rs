@@ -1,31 +1,31 @@
-use tempfile::TempDir;
+use tokio::time::timeout;
 
 use crate::{
-    protocol::{
-        codecs::message::{BinaryMessage, Payload},
-        proto::TmStatusChange,
+    setup::node::NodeType,
+    tools::{
+        config::SynthNodeCfg, constants::EXPECTED_RESULT_TIMEOUT, harness::TestHarness,
+        peer_status::LedgerId, rpc::wait_for_ledger_info,
     },
-    setup::node::{Node, NodeType},
-    tools::{rpc::wait_for_ledger_info, synth_node::SyntheticNode},
 };
 
 #[tokio::test]
 #[allow(non_snake_case)]
 async fn c010_TM_STATUS_CHANGE_node_should_send_ledger_information_using_status_change() {
-    let target = TempDir::new().expect("unable to create TempDir");
-
-    // Create a stateful node.
-    let mut node = Node::builder()
-        .start(target.path(), NodeType::Stateful)
+    // Create a stateful node and connect a synth node tracking the node's status.
+    let mut harness = TestHarness::builder()
+        .node_type(NodeType::Stateful)
+        .synth_node_cfg(SynthNodeCfg {
+            track_peer_status: true,
+            ..Default::default()
+        })
+        .build()
         .await
         .expect("unable to start stateful node");
-
-    // Connect synth node.
-    let mut sn = SyntheticNode::new(&Default::default()).await;
-    sn.connect(node.addr()).await.unwrap();
+    let rpc_url = harness.node.rpc_url();
+    let node_addr = harness.node.addr();
 
     // Get ledger information via RPC.
-    let info = wait_for_ledger_info(&node.rpc_url())
+    let info = wait_for_ledger_info(&rpc_url)
         .await
         .expect("no ledger info within the specified time limit");
     let rpc_ledger_index = info
@@ -42,22 +42,35 @@ async fn c010_TM_STATUS_CHANGE_node_should_send_ledger_information_using_status_
     let mut rpc_ledger_hash = [0u8; 32];
     hex::decode_to_slice(&info.result.ledger.ledger_hash, &mut rpc_ledger_hash[..])
         .expect("unable to decode ledger hash");
-
-    // Wait for TmStatusChange message.
-    let check = |m: &BinaryMessage| {
-        matches!(
-            &m.payload,
-            Payload::TmStatusChange(TmStatusChange {
-                ledger_seq: Some(ledger_seq),
-                ledger_hash: Some(ledger_hash),
-                ..
-            })
-            if ledger_seq == &rpc_ledger_index && ledger_hash.as_slice() == rpc_ledger_hash
-        )
+    let rpc_ledger = LedgerId {
+        seq: rpc_ledger_index,
+        hash: rpc_ledger_hash,
     };
-    assert!(sn.expect_message(&check).await);
+
+    // Wait for the ledger to be announced in a TmStatusChange message.
+    let synth_node = harness.synth_node_mut(0);
+    let announced = timeout(EXPECTED_RESULT_TIMEOUT, async {
+        loop {
+            synth_node.recv_message().await;
+            let status = synth_node
+                .peer_status(node_addr)
+                .expect("the node's status isn't tracked");
+            if status.ledgers().contains(&rpc_ledger) {
+                return status;
+            }
+        }
+    })
+    .await
+    .expect("the ledger wasn't announced within the specified time limit");
+
+    // The announced ledgers only move forward.
+    let seqs = announced
+        .ledgers()
+        .iter()
+        .map(|ledger| ledger.seq)
+        .collect::<Vec<_>>();
+    assert!(seqs.windows(2).all(|pair| pair[0] <= pair[1]), "{seqs:?}");
 
     // Cleanup.
-    sn.shut_down().await;
-    node.stop().expect("unable to stop stateful node");
+    harness.shut_down().await.unwrap();
 }
@@ -0,0 +1,53 @@
+use tempfile::TempDir;
+use tokio::time::{sleep, Duration};
+use ziggurat_core_utils::err_constants::{ERR_NODE_BUILD, ERR_NODE_STOP, ERR_TEMPDIR_NEW};
+
+use crate::{
+    protocol::codecs::message::Payload,
+    setup::node::{Node, NodeType},
+    tools::{
+        config::SynthNodeCfg,
+        proxy::{Direction, MitmProxy},
+    },
+};
+
+/// Time given to the nodes to exchange their initial messages.
+const OBSERVATION_TIME: Duration = Duration::from_secs(10);
+
+#[tokio::test]
+async fn mitm_proxy_relays_messages_in_both_directions() {
+    let mut builder = Node::builder();
+
+    let target_dir = TempDir::new().expect(ERR_TEMPDIR_NEW);
+    let mut target = builder
+        .start(target_dir.path(), NodeType::Stateful)
+        .await
+        .expect(ERR_NODE_BUILD);
+
+    let proxy = MitmProxy::new(target.addr(), &SynthNodeCfg::default())
+        .await
+        .expect("unable to start the proxy");
+
+    let source_dir = TempDir::new().expect(ERR_TEMPDIR_NEW);
+    let mut builder = builder.initial_peers(vec![proxy.listening_addr()]);
+    let mut source = builder
+        .start(source_dir.path(), NodeType::Stateful)
+        .await
+        .expect(ERR_NODE_BUILD);
+
+    sleep(OBSERVATION_TIME).await;
+
+    let to_target = proxy.messages_in(Direction::SourceToTarget);
+    let to_source = proxy.messages_in(Direction::TargetToSource);
+    proxy.shut_down().await;
+
+    source.stop().expect(ERR_NODE_STOP);
+    target.stop().expect(ERR_NODE_STOP);
+
+    assert!(!to_target.is_empty(), "no messages from the source node");
+    assert!(!to_source.is_empty(), "no messages from the target node");
+    // Both nodes are validators, so they should share their manifests.
+    assert!(to_source
+        .iter()
+        .any(|message| matches!(message.payload, Payload::TmManifests(_))));
+}
@@ -1,4 +1,5 @@
 mod conformance;
 mod idle_node_in_the_background;
+mod mitm_proxy;
 mod performance;
 mod resistance;
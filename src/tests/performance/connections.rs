@@ -1,22 +1,21 @@
 use std::{
-    net::{IpAddr, Ipv4Addr, SocketAddr},
-    str::FromStr,
+    net::SocketAddr,
     time::{Duration, Instant},
 };
 
 use tabled::Table;
 use tempfile::TempDir;
 use tokio::{net::TcpSocket, sync::mpsc::Sender, task::JoinSet};
-use ziggurat_core_metrics::{
-    connection_tables::ConnectionStats, recorder::TestMetrics, tables::fmt_table,
-};
-use ziggurat_core_utils::err_constants::{
-    ERR_NODE_BUILD, ERR_NODE_STOP, ERR_SOCKET_BIND, ERR_TEMPDIR_NEW,
-};
+use ziggurat_core_metrics::{connection_tables::ConnectionStats, tables::fmt_table};
+use ziggurat_core_utils::err_constants::{ERR_NODE_BUILD, ERR_NODE_STOP, ERR_TEMPDIR_NEW};
 
 use crate::{
     setup::node::{Node, NodeType},
-    tools::{config::SynthNodeCfg, ips::ips, synth_node::SyntheticNode},
+    tests::performance::harness::{bind_sockets, LoadMatrix},
+    tools::{
+        config::SynthNodeCfg, inner_node::ConnectionEvent, ips::IpPool,
+        metrics::recorder::TestMetrics, synth_node::SyntheticNode,
+    },
 };
 
 const METRIC_ACCEPTED: &str = "perf_conn_accepted";
@@ -120,52 +119,41 @@ async fn p002_connections_load() {
     // │         100 │     100 │           21 │           79 │            7 │            0 │            0 │      19.41 │
     // └─────────────┴─────────┴──────────────┴──────────────┴──────────────┴──────────────┴──────────────┴────────────┘
 
-    // maximum time allowed for a single iteration of the test
+    // maximum time allowed for a single iteration of the test, unless overridden
     const MAX_ITER_TIME: Duration = Duration::from_secs(25);
 
-    /// maximum peers to configure node with
-    const MAX_PEERS: u16 = 100;
-
-    let synth_counts = vec![1, 5, 10, 20, 30, 50, 100];
+    let load = LoadMatrix {
+        peer_counts: vec![1, 5, 10, 20, 30, 50, 100],
+        requests: 0,
+        // Peers only handshake, so the whole iteration waits for their responses.
+        response_timeout: MAX_ITER_TIME,
+        max_peers: 100,
+        monitor_resources: false,
+    }
+    .for_test("p002");
 
+    let pool = IpPool::acquire();
     let mut all_stats = Vec::new();
 
-    for synth_count in synth_counts {
+    for &synth_count in &load.peer_counts {
         let target = TempDir::new().expect(ERR_TEMPDIR_NEW);
         // start node
         let mut node = Node::builder()
-            .max_peers(MAX_PEERS as usize)
+            .max_peers(load.max_peers)
             .start(target.path(), NodeType::Stateless)
             .await
             .expect(ERR_NODE_BUILD);
         let node_addr = node.addr();
 
-        let mut synth_sockets = Vec::with_capacity(synth_count);
-        let mut ips = ips();
-
-        for _ in 0..synth_count {
-            // If there is address for our thread in the pool we can use it.
-            // Otherwise we'll not set bound_addr and use local IP addr (127.0.0.1).
-            let ip = ips.pop().unwrap_or("127.0.0.1".to_string());
-
-            let ip = SocketAddr::new(IpAddr::V4(Ipv4Addr::from_str(&ip).unwrap()), 0);
-            let socket = TcpSocket::new_v4().unwrap();
-
-            // Make sure we can reuse the address and port
-            socket.set_reuseaddr(true).unwrap();
-            socket.set_reuseport(true).unwrap();
-
-            socket.bind(ip).expect(ERR_SOCKET_BIND);
-            synth_sockets.push(socket);
-        }
+        let synth_sockets = bind_sockets(pool.ips(), synth_count);
 
         // setup metrics recorder
         let test_metrics = TestMetrics::default();
         // register metrics
-        metrics::register_counter!(METRIC_ACCEPTED);
-        metrics::register_counter!(METRIC_TERMINATED);
-        metrics::register_counter!(METRIC_REJECTED);
-        metrics::register_counter!(METRIC_ERROR);
+        test_metrics.register_counter(METRIC_ACCEPTED);
+        test_metrics.register_counter(METRIC_TERMINATED);
+        test_metrics.register_counter(METRIC_REJECTED);
+        test_metrics.register_counter(METRIC_ERROR);
 
         let mut synth_handles = JoinSet::new();
         let mut synth_exits = Vec::with_capacity(synth_count);
@@ -190,7 +178,7 @@ async fn p002_connections_load() {
 
         // Wait for all peers to indicate that they've completed the handshake portion
         // or the iteration timeout is exceeded.
-        let _ = tokio::time::timeout(MAX_ITER_TIME, async move {
+        let _ = tokio::time::timeout(load.response_timeout, async move {
             for _ in 0..synth_count {
                 handshake_rx.recv().await.unwrap();
             }
@@ -207,7 +195,7 @@ async fn p002_connections_load() {
         while (synth_handles.join_next().await).is_some() {}
 
         // Collect stats for this run
-        let mut stats = ConnectionStats::new(MAX_PEERS, synth_count as u16);
+        let mut stats = ConnectionStats::new(load.max_peers as u16, synth_count as u16);
         stats.time = test_start.elapsed().as_secs_f64();
         {
             let snapshot = test_metrics.take_snapshot();
@@ -233,8 +221,8 @@ async fn p002_connections_load() {
         // No connection should be terminated.
         assert_eq!(stats.terminated, 0, "Stats: {stats:?}");
 
-        // We expect to have at least `MAX_PEERS` connections.
-        assert!(stats.accepted <= MAX_PEERS, "Stats: {stats:?}");
+        // We expect to have at most `max_peers` connections.
+        assert!(stats.accepted <= load.max_peers as u16, "Stats: {stats:?}");
 
         // The rest of the peers should be rejected.
         assert_eq!(
@@ -253,6 +241,7 @@ async fn simulate_peer(node_addr: SocketAddr, handshake_complete: Sender<()>, so
     let config = SynthNodeCfg::default();
 
     let mut synth_node = SyntheticNode::new(&config).await;
+    let mut events = synth_node.subscribe_connection_events();
 
     // Establish peer connection
     let handshake_result = synth_node.connect_from(node_addr, socket).await;
@@ -267,19 +256,17 @@ async fn simulate_peer(node_addr: SocketAddr, handshake_complete: Sender<()>, so
         }
     };
 
-    // Keep connection alive by consuming messages
+    // Keep connection alive by consuming messages until the node terminates it
     loop {
-        match synth_node
-            .recv_message_timeout(Duration::from_millis(100))
-            .await
-        {
-            Ok(_) => continue, // consume every message ignoring it
-            Err(_timeout) => {
-                // check for broken connection
-                if !synth_node.is_connected(node_addr) {
-                    metrics::counter!(METRIC_TERMINATED, 1);
-                    synth_node.shut_down().await;
-                    return;
+        tokio::select! {
+            _ = synth_node.recv_message() => {}, // consume every message ignoring it
+            event = events.recv() => {
+                if let Ok(ConnectionEvent::Disconnected { addr, .. }) = event {
+                    if addr == node_addr {
+                        metrics::counter!(METRIC_TERMINATED, 1);
+                        synth_node.shut_down().await;
+                        return;
+                    }
                 }
             }
         }
@@ -0,0 +1,279 @@
+use std::{collections::BTreeMap, fmt, io::ErrorKind, net::SocketAddr, sync::Arc, time::Duration};
+
+use tokio::{net::TcpSocket, task::JoinSet, time::Instant};
+
+use crate::{
+    protocol::{codecs::message::Payload, proto::TmLedgerInfoType},
+    setup::node::{Node, NodeType},
+    tests::performance::harness::{run_iterations, Iteration, LoadMatrix},
+    tools::{
+        matchers::Matcher,
+        metrics::{
+            latency_tables::{LatencyRequestStats, LatencyRequestsTable},
+            ledger_requests::{LedgerRequestStats, LedgerRequestsTable, PeerLedgerRequests},
+            resource_monitor::{
+                ResourceMonitor, ResourceUsageStats, ResourceUsageTable, DEFAULT_SAMPLE_INTERVAL,
+            },
+        },
+        rpc::wait_for_state,
+        synth_node::{ledger_request, SyntheticNode},
+    },
+};
+
+const METRIC_LATENCY: &str = "get_ledger_test_latency";
+
+/// The depths of the state tree requested below its root, the node serves at most 3 levels.
+const QUERY_DEPTHS: [u32; 4] = [0, 1, 2, 3];
+
+#[cfg_attr(
+    not(feature = "performance"),
+    ignore = "run this test with the 'performance' feature enabled"
+)]
+#[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+#[allow(non_snake_case)]
+async fn p004_t1_GET_LEDGER_flood() {
+    // ZG-PERFORMANCE-004, Ledger request flood
+    //
+    // The node keeps serving ledger data, or throttles the requesting peers, under a flood of
+    // ledger requests.
+    //
+    // Each peer requests the state tree of the last closed ledger over and over, cycling through
+    // the query depths, and waits for each reply before sending the next request. A latency
+    // table is displayed per depth, followed by a table of how the node answered:
+    //
+    // - data replies and error replies, the latter refusing the request,
+    // - cookie mismatches, replies without the cookie of the pending request,
+    // - timeouts, connection errors and broken pipes, as in the latency tests,
+    // - throttled peers, which had a request fail, and the first request which failed.
+    //
+    // rippled charges the peers for the ledger data they request, so the heavier loads are
+    // expected to end with requests dropped and peers disconnected rather than with slower
+    // replies.
+    //
+    // *NOTE* run with `cargo test --release tests::performance::get_ledger -- --nocapture`
+    // Set ZIGGURAT_PERF_PARALLELISM to run the iterations against up to 3 nodes in parallel.
+    // Set ZIGGURAT_PERF_LOADS to a TOML file to override the loads, see PERF.md.
+    // Running as root, the IP aliases of the peers are created automatically, otherwise generate
+    // them with tools/ips.py first, see PERF.md.
+
+    let load = LoadMatrix {
+        peer_counts: vec![1, 10, 20, 50, 75, 100],
+        requests: 100,
+        response_timeout: Duration::from_secs(5),
+        max_peers: 100,
+        monitor_resources: false,
+    }
+    .for_test("p004_t1");
+
+    let builder = Node::builder().max_peers(load.max_peers);
+    let iteration_load = load.clone();
+    let rows = run_iterations(
+        builder,
+        NodeType::Stateful,
+        &load.peer_counts,
+        move |iteration| run_flood_iteration(iteration, iteration_load.clone()),
+    )
+    .await;
+
+    let mut report = GetLedgerReport {
+        latencies: BTreeMap::new(),
+        requests: LedgerRequestsTable::default(),
+        resources: load.monitor_resources.then(ResourceUsageTable::default),
+    };
+    for row in rows {
+        for (depth, latency_row) in row.latencies {
+            report
+                .latencies
+                .entry(depth)
+                .or_default()
+                .add_row(latency_row);
+        }
+        report.requests.add_row(row.requests);
+        if let (Some(table), Some(row)) = (&mut report.resources, row.resources) {
+            table.add_row(row);
+        }
+    }
+
+    // Display results tables
+    println!("\r\n{report}");
+}
+
+/// The tables of the test: the latencies per query depth, how the node answered the requests
+/// and the node's resource usage, if it was monitored.
+struct GetLedgerReport {
+    latencies: BTreeMap<u32, LatencyRequestsTable>,
+    requests: LedgerRequestsTable,
+    resources: Option<ResourceUsageTable>,
+}
+
+impl fmt::Display for GetLedgerReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (depth, latencies) in &self.latencies {
+            write!(f, "query depth {depth}:\r\n{latencies}\r\n\r\n")?;
+        }
+        write!(f, "{}", self.requests)?;
+        if let Some(resources) = &self.resources {
+            write!(f, "\r\n\r\n{resources}")?;
+        }
+        Ok(())
+    }
+}
+
+/// The rows of a single iteration.
+struct FloodRows {
+    latencies: Vec<(u32, LatencyRequestStats)>,
+    requests: LedgerRequestStats,
+    resources: Option<ResourceUsageStats>,
+}
+
+async fn run_flood_iteration(iteration: Iteration, load: LoadMatrix) -> FloodRows {
+    // Wait for the node to close ledgers, this returns immediately once the node is set up.
+    wait_for_state(&iteration.rpc_url, "proposing".into()).await;
+
+    let peers = iteration.synth_count as u16;
+    let monitor = load
+        .monitor_resources
+        .then(|| ResourceMonitor::start(iteration.node_pid, DEFAULT_SAMPLE_INTERVAL));
+
+    // register the metrics, their names are unique to the iteration
+    let metrics = Arc::new(
+        QUERY_DEPTHS
+            .iter()
+            .map(|depth| {
+                let metric = iteration.metric_name(&format!("{METRIC_LATENCY}_depth_{depth}"));
+                iteration.metrics.register_histogram(metric.clone());
+                (*depth, metric)
+            })
+            .collect::<BTreeMap<_, _>>(),
+    );
+
+    let mut synth_handles = JoinSet::new();
+    let test_start = Instant::now();
+
+    for (peer, socket) in iteration.bind_sockets().into_iter().enumerate() {
+        synth_handles.spawn(simulate_flooding_peer(
+            iteration.node_addr,
+            socket,
+            peer,
+            load.clone(),
+            metrics.clone(),
+        ));
+    }
+
+    // wait for peers to complete, a panicked peer counts as a connection error
+    let mut peer_requests = Vec::with_capacity(iteration.synth_count);
+    while let Some(result) = synth_handles.join_next().await {
+        peer_requests.push(result.unwrap_or_else(|_| {
+            let mut requests = PeerLedgerRequests::default();
+            requests.record_error(0, QUERY_DEPTHS[0], &ErrorKind::ConnectionAborted.into());
+            requests
+        }));
+    }
+
+    let time_taken_secs = test_start.elapsed().as_secs_f64();
+
+    let snapshot = iteration.metrics.take_snapshot();
+    let latencies = metrics
+        .iter()
+        .filter_map(|(depth, metric)| {
+            let latencies = snapshot
+                .construct_histogram(metric)
+                .filter(|latencies| !latencies.is_empty())?;
+            let mut errors = Default::default();
+            for requests in &peer_requests {
+                errors += requests.errors.get(depth).copied().unwrap_or_default();
+            }
+            let stats = LatencyRequestStats::new(
+                peers,
+                requests_at_depth(load.requests, *depth),
+                latencies,
+                time_taken_secs,
+            )
+            .with_errors(errors);
+            Some((*depth, stats))
+        })
+        .collect();
+
+    let resources = match monitor {
+        Some(monitor) => Some(monitor.stop().await.with_peers(peers)),
+        None => None,
+    };
+
+    FloodRows {
+        latencies,
+        requests: LedgerRequestStats::new(load.requests, &peer_requests),
+        resources,
+    }
+}
+
+async fn simulate_flooding_peer(
+    node_addr: SocketAddr,
+    socket: TcpSocket,
+    peer: usize,
+    load: LoadMatrix,
+    metrics: Arc<BTreeMap<u32, String>>,
+) -> PeerLedgerRequests {
+    let mut synth_node = SyntheticNode::new(&Default::default()).await;
+
+    let mut requests = PeerLedgerRequests::default();
+
+    // Establish peer connection
+    if let Err(e) = synth_node.connect_from(node_addr, socket).await {
+        requests.record_error(0, QUERY_DEPTHS[0], &e);
+        synth_node.shut_down().await;
+        return requests;
+    }
+
+    // Any ledger data is taken as the reply, the cookie tells whether it's the pending request's.
+    let matcher = Matcher::new("a TmLedgerData", |payload| {
+        matches!(payload, Payload::TmLedgerData(_))
+    });
+
+    for request in 0..load.requests {
+        let depth = QUERY_DEPTHS[request as usize % QUERY_DEPTHS.len()];
+        // Unique across the peers, so the node can't mix up the replies.
+        let cookie = (peer as u32) << 16 | request as u32;
+        let payload = Payload::TmGetLedger(ledger_request(
+            TmLedgerInfoType::LiAsNode,
+            None,
+            Some(depth),
+            cookie,
+        ));
+
+        // A timed out request is counted and followed by the next one, any other error means
+        // the connection is gone.
+        match synth_node
+            .request(
+                node_addr,
+                payload,
+                &matcher,
+                load.response_timeout,
+                Some(&metrics[&depth]),
+            )
+            .await
+        {
+            Ok((_, reply)) => {
+                let Payload::TmLedgerData(data) = reply.payload else {
+                    unreachable!("the matcher only accepts ledger data");
+                };
+                requests.record_reply(cookie, &data);
+            }
+            Err(e) => {
+                requests.record_error(request, depth, &e);
+                if e.kind() != ErrorKind::TimedOut {
+                    break;
+                }
+            }
+        }
+    }
+
+    synth_node.shut_down().await;
+    requests
+}
+
+/// Returns the number of requests each peer sends at the depth, out of `requests`.
+fn requests_at_depth(requests: u16, depth: u32) -> u16 {
+    let idx = QUERY_DEPTHS.iter().position(|d| *d == depth).unwrap() as u16;
+    let depths = QUERY_DEPTHS.len() as u16;
+    requests / depths + u16::from(idx < requests % depths)
+}
@@ -0,0 +1,476 @@
+//! Shared machinery of the performance tests: the load matrix, the iterations spread over the
+//! nodes and a request/reply latency scenario.
+
+use std::{
+    collections::HashMap,
+    env, fmt, fs,
+    future::Future,
+    io::ErrorKind,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    num::NonZeroUsize,
+    str::FromStr,
+    sync::Arc,
+    thread,
+    time::Duration,
+};
+
+use serde::Deserialize;
+use tempfile::TempDir;
+use tokio::{net::TcpSocket, task::JoinSet, time::Instant};
+use ziggurat_core_utils::err_constants::{
+    ERR_NODE_BUILD, ERR_NODE_STOP, ERR_SOCKET_BIND, ERR_TEMPDIR_NEW,
+};
+
+use crate::{
+    protocol::codecs::message::Payload,
+    setup::{
+        constants::{DEFAULT_PORT, VALIDATOR_IPS},
+        node::{Node, NodeBuilder, NodeType},
+    },
+    tools::{
+        ips::IpPool,
+        matchers::Matcher,
+        metrics::{
+            latency_tables::{LatencyRequestStats, LatencyRequestsTable, RequestErrors},
+            recorder::TestMetrics,
+            resource_monitor::{ResourceMonitor, ResourceUsageTable, DEFAULT_SAMPLE_INTERVAL},
+        },
+        rpc::wait_for_peer_count,
+        synth_node::SyntheticNode,
+    },
+};
+
+/// Time given to the node to drop the peers of the previous iteration.
+const PEER_CLEANUP_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Environment variable setting the number of nodes the iterations of a test are spread over.
+/// The iterations run one after another against a single node by default.
+const PARALLELISM_ENV: &str = "ZIGGURAT_PERF_PARALLELISM";
+
+/// Environment variable naming a TOML file which overrides the loads of the tests.
+const LOADS_ENV: &str = "ZIGGURAT_PERF_LOADS";
+
+/// The loads a performance test runs with, one iteration per peer count.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoadMatrix {
+    /// The number of synthetic peers of each iteration.
+    pub peer_counts: Vec<usize>,
+    /// The number of requests sent by each peer.
+    pub requests: u16,
+    /// Time to wait for each reply.
+    pub response_timeout: Duration,
+    /// The `max_peers` setting of the node.
+    pub max_peers: usize,
+    /// Whether to sample the node's resource usage during each iteration.
+    pub monitor_resources: bool,
+}
+
+/// A test's table in the [LOADS_ENV] file, the missing values keep the test's defaults.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LoadOverrides {
+    peer_counts: Option<Vec<usize>>,
+    requests: Option<u16>,
+    response_timeout_ms: Option<u64>,
+    max_peers: Option<usize>,
+    monitor_resources: Option<bool>,
+}
+
+impl LoadMatrix {
+    /// Applies the overrides of the test from the file named by [LOADS_ENV], if set.
+    pub fn for_test(self, test: &str) -> Self {
+        let Ok(path) = env::var(LOADS_ENV) else {
+            return self;
+        };
+        let loads = fs::read_to_string(&path)
+            .unwrap_or_else(|e| panic!("couldn't read the {LOADS_ENV} file {path}: {e}"));
+
+        self.with_overrides(test, &loads)
+            .unwrap_or_else(|e| panic!("invalid {LOADS_ENV} file {path}: {e}"))
+    }
+
+    /// Applies the test's table of the TOML document.
+    fn with_overrides(mut self, test: &str, loads: &str) -> Result<Self, toml::de::Error> {
+        let mut loads: HashMap<String, LoadOverrides> = toml::from_str(loads)?;
+        let overrides = loads.remove(test).unwrap_or_default();
+
+        if let Some(peer_counts) = overrides.peer_counts {
+            self.peer_counts = peer_counts;
+        }
+        if let Some(requests) = overrides.requests {
+            self.requests = requests;
+        }
+        if let Some(timeout) = overrides.response_timeout_ms {
+            self.response_timeout = Duration::from_millis(timeout);
+        }
+        if let Some(max_peers) = overrides.max_peers {
+            self.max_peers = max_peers;
+        }
+        if let Some(monitor_resources) = overrides.monitor_resources {
+            self.monitor_resources = monitor_resources;
+        }
+        Ok(self)
+    }
+}
+
+/// Waits until the node dropped all the synthetic peers, so the node can be reused for the next
+/// iteration of a test.
+async fn wait_for_peer_cleanup(node: &Node) {
+    wait_for_peer_count(&node.rpc_url(), 0, PEER_CLEANUP_TIMEOUT)
+        .await
+        .expect("the node didn't drop the peers of the previous iteration");
+}
+
+/// A single row of a performance test, run against one of the nodes.
+pub struct Iteration {
+    pub synth_count: usize,
+    pub node_addr: SocketAddr,
+    pub rpc_url: String,
+    /// The process ID of the node, e.g. to sample its resource usage.
+    pub node_pid: u32,
+    /// Source IP aliases reserved for the synthetic peers of the node.
+    pub ips: Vec<String>,
+    pub metrics: Arc<TestMetrics>,
+}
+
+impl Iteration {
+    /// Returns the metric name for this iteration, iterations running side by side share the
+    /// metrics scope so each needs its own.
+    pub fn metric_name(&self, name: &str) -> String {
+        format!("{name}_{}", self.synth_count)
+    }
+
+    /// Creates a socket for each synthetic peer, bound to one of the reserved IP aliases.
+    pub fn bind_sockets(&self) -> Vec<TcpSocket> {
+        bind_sockets(self.ips.clone(), self.synth_count)
+    }
+}
+
+/// Creates `count` sockets, bound to the IP aliases while there are any left.
+pub fn bind_sockets(mut ips: Vec<String>, count: usize) -> Vec<TcpSocket> {
+    (0..count)
+        .map(|_| {
+            // If there is address for our thread in the pool we can use it.
+            // Otherwise we'll not set bound_addr and use local IP addr (127.0.0.1).
+            let ip = ips.pop().unwrap_or("127.0.0.1".to_string());
+
+            let ip = SocketAddr::new(IpAddr::V4(Ipv4Addr::from_str(&ip).unwrap()), 0);
+            let socket = TcpSocket::new_v4().unwrap();
+
+            // Make sure we can reuse the address and port
+            socket.set_reuseaddr(true).unwrap();
+            socket.set_reuseport(true).unwrap();
+
+            socket.bind(ip).expect(ERR_SOCKET_BIND);
+            socket
+        })
+        .collect()
+}
+
+/// Returns the number of nodes to spread the iterations over, as set by [PARALLELISM_ENV] and
+/// bounded by the addresses available to the nodes and the number of cores.
+fn parallelism() -> usize {
+    let requested = match env::var(PARALLELISM_ENV) {
+        Ok(value) => value
+            .parse()
+            .unwrap_or_else(|_| panic!("{PARALLELISM_ENV} should be a number, got {value}")),
+        Err(_) => 1,
+    };
+    let cores = thread::available_parallelism().map_or(1, NonZeroUsize::get);
+
+    requested.clamp(1, VALIDATOR_IPS.len().min(cores))
+}
+
+/// Runs an iteration for each of the synth counts and returns the results in the same order.
+///
+/// The iterations are spread over [parallelism] nodes running side by side, each node running
+/// its share of the iterations one after another. The nodes bind to distinct addresses and the
+/// IP aliases are split between them, so iterations running at the same time don't share
+/// source addresses.
+pub async fn run_iterations<T, F, Fut>(
+    mut builder: NodeBuilder,
+    node_type: NodeType,
+    synth_counts: &[usize],
+    iteration: F,
+) -> Vec<T>
+where
+    T: Send + 'static,
+    F: Fn(Iteration) -> Fut + Clone + Send + 'static,
+    Fut: Future<Output = T> + Send,
+{
+    let nodes = parallelism().min(synth_counts.len()).max(1);
+    // Keeps the aliases assigned until all the iterations are done.
+    let pool = IpPool::acquire();
+    let ips = pool.ips();
+    let ips_per_node = ips.len() / nodes;
+    // Shared by all the iterations, which register their own metric names.
+    let metrics = Arc::new(TestMetrics::default());
+
+    let mut handles = JoinSet::new();
+    for node_idx in 0..nodes {
+        if matches!(node_type, NodeType::Stateless) {
+            let addr = SocketAddr::new(VALIDATOR_IPS[node_idx].parse().unwrap(), DEFAULT_PORT);
+            builder = builder.stateless_addr(addr);
+        }
+
+        let target = TempDir::new().expect(ERR_TEMPDIR_NEW);
+        let mut node = builder
+            .start(target.path(), node_type)
+            .await
+            .expect(ERR_NODE_BUILD);
+
+        let node_ips = ips[node_idx * ips_per_node..(node_idx + 1) * ips_per_node].to_vec();
+        let node_synth_counts: Vec<(usize, usize)> = synth_counts
+            .iter()
+            .copied()
+            .enumerate()
+            .skip(node_idx)
+            .step_by(nodes)
+            .collect();
+        let iteration = iteration.clone();
+        let metrics = metrics.clone();
+
+        handles.spawn(async move {
+            let mut results = Vec::with_capacity(node_synth_counts.len());
+            for (idx, synth_count) in node_synth_counts {
+                let result = iteration(Iteration {
+                    synth_count,
+                    node_addr: node.addr(),
+                    rpc_url: node.rpc_url(),
+                    node_pid: node.pid(),
+                    ips: node_ips.clone(),
+                    metrics: metrics.clone(),
+                })
+                .await;
+                results.push((idx, result));
+
+                wait_for_peer_cleanup(&node).await;
+            }
+
+            node.stop().expect(ERR_NODE_STOP);
+            drop(target);
+            results
+        });
+    }
+
+    let mut results = Vec::with_capacity(synth_counts.len());
+    while let Some(node_results) = handles.join_next().await {
+        results.extend(node_results.expect("a performance test iteration panicked"));
+    }
+    results.sort_by_key(|(idx, _)| *idx);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+/// The results of [run_latency_test], displayed as a table of latencies followed by a table of
+/// the node's resource usage, if it was monitored.
+pub struct LatencyReport {
+    pub latencies: LatencyRequestsTable,
+    pub resources: Option<ResourceUsageTable>,
+}
+
+impl fmt::Display for LatencyReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.latencies)?;
+        if let Some(resources) = &self.resources {
+            write!(f, "\r\n\r\n{resources}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Measures the latency of the requests created by the workload, one table row per peer count.
+///
+/// Before each iteration, `prepare` is given the node's RPC URL and returns the workload, which
+/// creates the `request`-th request of the `peer`-th synthetic peer along with the matcher of
+/// its reply. Each peer sends its requests one after another and moves on to the next one after
+/// a timeout, but stops once the connection is gone. If [LoadMatrix::monitor_resources] is set,
+/// the node's resource usage is summarized per peer count as well.
+pub async fn run_latency_test<P, PFut, W>(
+    builder: NodeBuilder,
+    node_type: NodeType,
+    load: &LoadMatrix,
+    metric: &'static str,
+    prepare: P,
+) -> LatencyReport
+where
+    P: Fn(String) -> PFut + Clone + Send + Sync + 'static,
+    PFut: Future<Output = W> + Send,
+    W: Fn(usize, u16) -> (Payload, Matcher) + Send + Sync + 'static,
+{
+    let builder = builder.max_peers(load.max_peers);
+    let iteration_load = load.clone();
+    let rows = run_iterations(builder, node_type, &load.peer_counts, move |iteration| {
+        let prepare = prepare.clone();
+        let load = iteration_load.clone();
+        async move {
+            let workload = Arc::new(prepare(iteration.rpc_url.clone()).await);
+            let peers = iteration.synth_count as u16;
+            let monitor = load
+                .monitor_resources
+                .then(|| ResourceMonitor::start(iteration.node_pid, DEFAULT_SAMPLE_INTERVAL));
+            let latencies = run_latency_iteration(iteration, load, metric, workload).await;
+            let resources = match monitor {
+                Some(monitor) => Some(monitor.stop().await.with_peers(peers)),
+                None => None,
+            };
+            (latencies, resources)
+        }
+    })
+    .await;
+
+    let mut latencies = LatencyRequestsTable::default();
+    let mut resources = load.monitor_resources.then(ResourceUsageTable::default);
+    for (latency_row, resource_row) in rows {
+        if let Some(row) = latency_row {
+            latencies.add_row(row);
+        }
+        if let (Some(table), Some(row)) = (&mut resources, resource_row) {
+            table.add_row(row);
+        }
+    }
+    LatencyReport {
+        latencies,
+        resources,
+    }
+}
+
+async fn run_latency_iteration<W>(
+    iteration: Iteration,
+    load: LoadMatrix,
+    metric: &str,
+    workload: Arc<W>,
+) -> Option<LatencyRequestStats>
+where
+    W: Fn(usize, u16) -> (Payload, Matcher) + Send + Sync + 'static,
+{
+    let synth_sockets = iteration.bind_sockets();
+
+    // register the metric, its name is unique to the iteration
+    let metric = iteration.metric_name(metric);
+    iteration.metrics.register_histogram(metric.clone());
+
+    let mut synth_handles = JoinSet::new();
+    let test_start = Instant::now();
+
+    for (peer, socket) in synth_sockets.into_iter().enumerate() {
+        synth_handles.spawn(simulate_latency_peer(
+            iteration.node_addr,
+            socket,
+            peer,
+            load.clone(),
+            metric.clone(),
+            workload.clone(),
+        ));
+    }
+
+    // wait for peers to complete, a panicked peer counts as a connection error
+    let mut errors = RequestErrors::default();
+    while let Some(result) = synth_handles.join_next().await {
+        errors += result.unwrap_or(RequestErrors {
+            connection_errors: 1,
+            ..Default::default()
+        });
+    }
+
+    let time_taken_secs = test_start.elapsed().as_secs_f64();
+
+    let snapshot = iteration.metrics.take_snapshot();
+    let latencies = snapshot
+        .construct_histogram(&metric)
+        .filter(|latencies| !latencies.is_empty())?;
+
+    Some(
+        LatencyRequestStats::new(
+            iteration.synth_count as u16,
+            load.requests,
+            latencies,
+            time_taken_secs,
+        )
+        .with_errors(errors),
+    )
+}
+
+async fn simulate_latency_peer<W>(
+    node_addr: SocketAddr,
+    socket: TcpSocket,
+    peer: usize,
+    load: LoadMatrix,
+    metric: String,
+    workload: Arc<W>,
+) -> RequestErrors
+where
+    W: Fn(usize, u16) -> (Payload, Matcher),
+{
+    let mut synth_node = SyntheticNode::new(&Default::default()).await;
+
+    let mut errors = RequestErrors::default();
+
+    // Establish peer connection
+    if synth_node.connect_from(node_addr, socket).await.is_err() {
+        errors.connection_errors += 1;
+        synth_node.shut_down().await;
+        return errors;
+    }
+
+    for request in 0..load.requests {
+        let (payload, matcher) = workload(peer, request);
+
+        // A timed out request is counted and followed by the next one, any other error means
+        // the connection is gone.
+        if let Err(e) = synth_node
+            .request_reply(
+                node_addr,
+                payload,
+                &matcher,
+                load.response_timeout,
+                Some(&metric),
+            )
+            .await
+        {
+            errors.record(&e);
+            if e.kind() != ErrorKind::TimedOut {
+                break;
+            }
+        }
+    }
+
+    synth_node.shut_down().await;
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overrides_apply_to_the_named_test() {
+        let defaults = LoadMatrix {
+            peer_counts: vec![1, 10],
+            requests: 100,
+            response_timeout: Duration::from_secs(5),
+            max_peers: 100,
+            monitor_resources: false,
+        };
+        let loads = r#"
+            [p001_t1]
+            peer_counts = [2]
+            response_timeout_ms = 500
+
+            [p003_t1]
+            requests = 1
+            monitor_resources = true
+        "#;
+
+        let load = defaults.clone().with_overrides("p001_t1", loads).unwrap();
+        assert_eq!(load.peer_counts, vec![2]);
+        assert_eq!(load.requests, 100);
+        assert_eq!(load.response_timeout, Duration::from_millis(500));
+
+        let load = defaults.clone().with_overrides("p002", loads).unwrap();
+        assert_eq!(load, defaults);
+
+        assert!(defaults
+            .with_overrides("p001_t1", "[p001_t1]\nrequest = 1")
+            .is_err());
+    }
+}
@@ -1,3 +1,5 @@
 mod connections;
+mod get_ledger;
 mod get_trans;
+mod harness;
 mod ping_pong;
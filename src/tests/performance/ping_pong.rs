@@ -1,21 +1,4 @@
-use std::{
-    net::{IpAddr, Ipv4Addr, SocketAddr},
-    str::FromStr,
-    time::{Duration, Instant},
-};
-
-use rand::{thread_rng, RngCore};
-use tempfile::TempDir;
-use tokio::{net::TcpSocket, task::JoinSet, time::timeout};
-use ziggurat_core_metrics::{
-    latency_tables::{LatencyRequestStats, LatencyRequestsTable},
-    recorder::TestMetrics,
-    tables::duration_as_ms,
-};
-use ziggurat_core_utils::err_constants::{
-    ERR_NODE_BUILD, ERR_NODE_STOP, ERR_SOCKET_BIND, ERR_SYNTH_CONNECT, ERR_SYNTH_UNICAST,
-    ERR_TEMPDIR_NEW,
-};
+use std::time::Duration;
 
 use crate::{
     protocol::{
@@ -23,13 +6,11 @@ use crate::{
         proto::{tm_ping::PingType, TmPing},
     },
     setup::node::{Node, NodeType},
-    tools::{config::SynthNodeCfg, ips::ips, synth_node::SyntheticNode},
+    tests::performance::harness::{run_latency_test, LoadMatrix},
+    tools::matchers::{is_pong_with_seq, Matcher},
 };
 
-const MAX_PEERS: usize = 100;
-const PINGS: u16 = 1000;
 const METRIC_LATENCY: &str = "ping_perf_latency";
-const RESPONSE_TIMEOUT: Duration = Duration::from_secs(5);
 
 #[cfg_attr(
     not(feature = "performance"),
@@ -62,6 +43,8 @@ async fn p001_t1_PING_PONG_throughput() {
     //          connections cannot be established and other ones are closed during the test. However, amount of nodes and ping count
     //          does not affect the latency and rippled responses have similar std time.
     //
+    //          The errors are now counted per row, in the timeouts, conn errors and broken pipes columns.
+    //
     // Example test result (with percentile latencies) - 1000 pings per node with max_peers set to 100:
     // ┌─────────┬────────────┬────────────┬────────────┬────────────────┬────────────┬────────────┬────────────┬────────────┬────────────┬────────────────┬────────────┬──────────────┐
     // │  peers  │  requests  │  min (ms)  │  max (ms)  │  std dev (ms)  │  10% (ms)  │  50% (ms)  │  75% (ms)  │  90% (ms)  │  99% (ms)  │  completion %  │  time (s)  │  requests/s  │
@@ -82,135 +65,46 @@ async fn p001_t1_PING_PONG_throughput() {
     // ├─────────┼────────────┼────────────┼────────────┼────────────────┼────────────┼────────────┼────────────┼────────────┼────────────┼────────────────┼────────────┼──────────────┤
     // │     150 │       1000 │          0 │        483 │             13 │          0 │          1 │          1 │          2 │         51 │          56.66 │      42.20 │      2014.08 │
     // └─────────┴────────────┴────────────┴────────────┴────────────────┴────────────┴────────────┴────────────┴────────────┴────────────┴────────────────┴────────────┴──────────────┘
+    // The example results above were recorded with millisecond resolution, latencies are now
+    // reported with microsecond resolution.
     // *NOTE* run with `cargo test --release tests::performance::ping_pong -- --nocapture`
-    // Before running test generate dummy devices with different ips using toos/ips.py
-
-    let synth_counts = vec![1, 10, 15, 20, 30, 50, 100, 150];
-
-    let mut table = LatencyRequestsTable::default();
-
-    for synth_count in synth_counts {
-        let target = TempDir::new().expect(ERR_TEMPDIR_NEW);
-        let mut node = Node::builder()
-            .max_peers(MAX_PEERS)
-            .start(target.path(), NodeType::Stateless)
-            .await
-            .expect(ERR_NODE_BUILD);
-        let node_addr = node.addr();
-
-        let mut synth_sockets = Vec::with_capacity(synth_count);
-        let mut ips = ips();
-
-        for _ in 0..synth_count {
-            // If there is address for our thread in the pool we can use it.
-            // Otherwise we'll not set bound_addr and use local IP addr (127.0.0.1).
-            let ip = ips.pop().unwrap_or("127.0.0.1".to_string());
-
-            let ip = SocketAddr::new(IpAddr::V4(Ipv4Addr::from_str(&ip).unwrap()), 0);
-            let socket = TcpSocket::new_v4().unwrap();
-
-            // Make sure we can reuse the address and port
-            socket.set_reuseaddr(true).unwrap();
-            socket.set_reuseport(true).unwrap();
-
-            socket.bind(ip).expect(ERR_SOCKET_BIND);
-            synth_sockets.push(socket);
-        }
-
-        // setup metrics recorder
-        let test_metrics = TestMetrics::default();
-        // clear metrics and register metrics
-        metrics::register_histogram!(METRIC_LATENCY);
-
-        let mut synth_handles = JoinSet::new();
-        let test_start = tokio::time::Instant::now();
-
-        for socket in synth_sockets {
-            synth_handles.spawn(simulate_peer(node_addr, socket));
-        }
-
-        // wait for peers to complete
-        while (synth_handles.join_next().await).is_some() {}
-
-        let time_taken_secs = test_start.elapsed().as_secs_f64();
-
-        let snapshot = test_metrics.take_snapshot();
-        if let Some(latencies) = snapshot.construct_histogram(METRIC_LATENCY) {
-            if latencies.entries() >= 1 {
-                // add stats to table display
-                table.add_row(LatencyRequestStats::new(
-                    synth_count as u16,
-                    PINGS,
-                    latencies,
-                    time_taken_secs,
-                ));
-            }
-        }
-
-        node.stop().expect(ERR_NODE_STOP);
+    // Set ZIGGURAT_PERF_PARALLELISM to run the iterations against up to 3 nodes in parallel.
+    // Set ZIGGURAT_PERF_LOADS to a TOML file to override the loads, see PERF.md.
+    // Running as root, the IP aliases of the peers are created automatically, otherwise generate
+    // them with tools/ips.py first, see PERF.md.
+
+    let load = LoadMatrix {
+        peer_counts: vec![1, 10, 15, 20, 30, 50, 100, 150],
+        requests: 1000,
+        response_timeout: Duration::from_secs(5),
+        max_peers: 100,
+        monitor_resources: false,
     }
+    .for_test("p001_t1");
+
+    // Each node is reused for its share of the iterations.
+    let table = run_latency_test(
+        Node::builder(),
+        NodeType::Stateless,
+        &load,
+        METRIC_LATENCY,
+        |_| async { ping },
+    )
+    .await;
 
     // Display results table
     println!("\r\n{table}");
 }
 
-#[allow(unused_must_use)] // just for result of the timeout
-async fn simulate_peer(node_addr: SocketAddr, socket: TcpSocket) {
-    let config = SynthNodeCfg::default();
-
-    let mut synth_node = SyntheticNode::new(&config).await;
-
-    // Establish peer connection
-    synth_node
-        .connect_from(node_addr, socket)
-        .await
-        .expect(ERR_SYNTH_CONNECT);
-
-    let mut seq;
-
-    for _ in 0..PINGS {
-        // Generate unique sequence for each ping
-        seq = thread_rng().next_u32();
-
-        let payload = Payload::TmPing(TmPing {
-            r#type: PingType::PtPing as i32,
-            seq: Some(seq),
-            ping_time: None,
-            net_time: None,
-        });
-
-        // Send Ping
-        if !synth_node.is_connected(node_addr) {
-            break;
-        }
-
-        synth_node
-            .unicast(node_addr, payload)
-            .expect(ERR_SYNTH_UNICAST);
-
-        let now = Instant::now();
-
-        // We can safely drop the result here because we don't care about it - if the message is
-        // received and it's our response we simply register it for histogram and break the loop.
-        // In every other case we simply move out and go to another request iteration.
-        timeout(RESPONSE_TIMEOUT, async {
-            loop {
-                let m = synth_node.recv_message().await;
-                if matches!(
-                    &m.1.payload,
-                    Payload::TmPing(TmPing {
-                    r#type: r_type,
-                    seq: Some(s),
-                    ..
-                    }) if *s == seq && *r_type == PingType::PtPong as i32
-                ) {
-                    metrics::histogram!(METRIC_LATENCY, duration_as_ms(now.elapsed()));
-                    break;
-                }
-            }
-        })
-        .await;
-    }
-
-    synth_node.shut_down().await
+/// Creates a ping with a sequence number unique to the peer's connection.
+fn ping(peer: usize, request: u16) -> (Payload, Matcher) {
+    let seq = (peer as u32) << 16 | request as u32;
+    let payload = Payload::TmPing(TmPing {
+        r#type: PingType::PtPing as i32,
+        seq: Some(seq),
+        ping_time: None,
+        net_time: None,
+    });
+
+    (payload, is_pong_with_seq(seq))
 }
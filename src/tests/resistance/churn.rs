@@ -0,0 +1,45 @@
+use tempfile::TempDir;
+use ziggurat_core_utils::err_constants::{ERR_NODE_BUILD, ERR_NODE_STOP, ERR_TEMPDIR_NEW};
+
+use crate::{
+    setup::node::{Node, NodeType},
+    tools::churn::{churn, ChurnCfg},
+};
+
+// Leeway for descriptors the node opens on its own, e.g. database files.
+const MAX_FD_GROWTH: isize = 10;
+// Allocators don't always return freed memory to the OS.
+const MAX_RSS_GROWTH: f64 = 1.5;
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn r009_node_must_not_leak_resources_under_connection_churn() {
+    // ZG-RESISTANCE-009
+
+    let cfg = ChurnCfg::default();
+
+    let target = TempDir::new().expect(ERR_TEMPDIR_NEW);
+    let mut node = Node::builder()
+        .max_peers(cfg.peers_per_round)
+        .start(target.path(), NodeType::Stateless)
+        .await
+        .expect(ERR_NODE_BUILD);
+
+    let report = churn(&node, &cfg)
+        .await
+        .expect("unable to sample the node's resources");
+    println!("{report:#?}");
+
+    node.stop().expect(ERR_NODE_STOP);
+
+    assert!(report.connections > 0, "no peer managed to connect");
+    assert!(
+        report.fd_growth() <= MAX_FD_GROWTH,
+        "open file descriptors grew by {}",
+        report.fd_growth()
+    );
+    assert!(
+        report.rss_growth() <= MAX_RSS_GROWTH,
+        "RSS grew by a factor of {:.2}",
+        report.rss_growth()
+    );
+}
@@ -0,0 +1,84 @@
+use std::time::Duration;
+
+use tempfile::TempDir;
+use ziggurat_core_utils::err_constants::{ERR_NODE_BUILD, ERR_NODE_STOP, ERR_TEMPDIR_NEW};
+
+use crate::{
+    setup::node::{ChildExitCode, Node, NodeType},
+    tools::{
+        connection_rate::{open_connections, ConnectionOutcome, ConnectionRateCfg},
+        synth_node::SyntheticNode,
+    },
+};
+
+#[allow(non_snake_case)]
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn r014_t1_CONNECTIONS_node_survives_connection_bursts() {
+    // ZG-RESISTANCE-014
+
+    let target = TempDir::new().expect(ERR_TEMPDIR_NEW);
+    let mut node = Node::builder()
+        .start(target.path(), NodeType::Stateless)
+        .await
+        .expect(ERR_NODE_BUILD);
+
+    let cfg = ConnectionRateCfg {
+        rate: 250.0,
+        burst: 25,
+        duration: Duration::from_secs(2),
+        hold_time: Duration::from_secs(2),
+        ..Default::default()
+    };
+    let report = open_connections(node.addr(), &cfg).await;
+    for outcome in [
+        ConnectionOutcome::Held,
+        ConnectionOutcome::Closed,
+        ConnectionOutcome::Reset,
+        ConnectionOutcome::TimedOut,
+    ] {
+        println!("{outcome:?}: {}", report.count(outcome));
+    }
+    println!("max connect time: {:?}", report.max_connect_time());
+    assert!(report.accepted() > 0, "the node accepted no connections");
+
+    // The node still accepts peers once the connections are gone.
+    let synth_node = SyntheticNode::builder().build().await;
+    synth_node
+        .connect(node.addr())
+        .await
+        .expect("the node didn't accept a peer after the bursts");
+    synth_node.shut_down().await;
+
+    assert_eq!(node.stop().expect(ERR_NODE_STOP), ChildExitCode::Success);
+}
+
+#[allow(non_snake_case)]
+#[tokio::test]
+async fn r014_t2_CONNECTIONS_node_drops_idle_connections() {
+    // ZG-RESISTANCE-014
+
+    let target = TempDir::new().expect(ERR_TEMPDIR_NEW);
+    let mut node = Node::builder()
+        .start(target.path(), NodeType::Stateless)
+        .await
+        .expect(ERR_NODE_BUILD);
+
+    // Connections which never start TLS must not hold on to the node's resources.
+    let cfg = ConnectionRateCfg {
+        rate: 10.0,
+        burst: 10,
+        duration: Duration::from_secs(1),
+        hold_time: Duration::from_secs(60),
+        ..Default::default()
+    };
+    let report = open_connections(node.addr(), &cfg).await;
+    assert_eq!(report.accepted(), cfg.attempts());
+    assert_eq!(
+        report.count(ConnectionOutcome::Held),
+        0,
+        "idle connections were kept open for {:?}",
+        cfg.hold_time
+    );
+
+    node.stop().expect(ERR_NODE_STOP);
+}
@@ -0,0 +1,66 @@
+use std::time::Duration;
+
+use tempfile::TempDir;
+use ziggurat_core_utils::err_constants::{
+    ERR_NODE_BUILD, ERR_NODE_STOP, ERR_SYNTH_CONNECT, ERR_SYNTH_UNICAST, ERR_TEMPDIR_NEW,
+};
+
+use crate::{
+    fuzzing::{
+        corrupt::{corrupt_payload, Corruption},
+        seeded_rng,
+    },
+    protocol::{
+        codecs::message::Payload,
+        proto::{tm_ping::PingType, TmPing},
+    },
+    setup::node::{Node, NodeType},
+    tools::synth_node::SyntheticNode,
+    wait_until,
+};
+
+const DISCONNECT_TIMEOUT: Duration = Duration::from_millis(500);
+
+#[tokio::test]
+async fn r006_node_must_disconnect_when_receiving_corrupted_fields() {
+    // ZG-RESISTANCE-006
+
+    let mut rng = seeded_rng();
+
+    let target = TempDir::new().expect(ERR_TEMPDIR_NEW);
+    let mut node = Node::builder()
+        .start(target.path(), NodeType::Stateless)
+        .await
+        .expect(ERR_NODE_BUILD);
+
+    // A ping with only its required field set, so every corruption hits the required field.
+    let ping = Payload::TmPing(TmPing {
+        r#type: PingType::PtPing as i32,
+        seq: None,
+        ping_time: None,
+        net_time: None,
+    });
+
+    for corruption in Corruption::ALL {
+        let frame = corrupt_payload(&mut rng, ping.clone(), corruption)
+            .expect("unable to corrupt the payload");
+
+        let synth_node = SyntheticNode::new(&Default::default()).await;
+        synth_node
+            .connect(node.addr())
+            .await
+            .expect(ERR_SYNTH_CONNECT);
+        synth_node
+            .unicast_bytes(node.addr(), frame)
+            .expect(ERR_SYNTH_UNICAST);
+
+        // Ensure that the node has disconnected.
+        wait_until!(
+            DISCONNECT_TIMEOUT,
+            !synth_node.is_connected_ip(node.addr().ip())
+        );
+        synth_node.shut_down().await;
+    }
+
+    node.stop().expect(ERR_NODE_STOP);
+}
@@ -0,0 +1,42 @@
+use std::time::Duration;
+
+use tempfile::TempDir;
+use ziggurat_core_utils::err_constants::{ERR_NODE_BUILD, ERR_NODE_STOP, ERR_TEMPDIR_NEW};
+
+use crate::{
+    setup::node::{Node, NodeType},
+    tools::flood::{flood, FloodCfg, FloodPayload},
+};
+
+const FLOODING_PEERS: usize = 10;
+const MAX_PEERS: usize = FLOODING_PEERS + 1;
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn r007_node_must_stay_responsive_during_message_flood() {
+    // ZG-RESISTANCE-007
+
+    let target = TempDir::new().expect(ERR_TEMPDIR_NEW);
+    let mut node = Node::builder()
+        .max_peers(MAX_PEERS)
+        .start(target.path(), NodeType::Stateless)
+        .await
+        .expect(ERR_NODE_BUILD);
+
+    let cfg = FloodCfg {
+        peers: FLOODING_PEERS,
+        rate: 100,
+        duration: Duration::from_secs(10),
+        payload: FloodPayload::RandomMix,
+        ..Default::default()
+    };
+    let report = flood(node.addr(), &cfg).await;
+    println!("{report:#?}");
+
+    assert!(!report.probe_disconnected, "the probe was disconnected");
+    assert!(
+        !report.ping_rtts.is_empty(),
+        "the node didn't reply to any pings"
+    );
+
+    node.stop().expect(ERR_NODE_STOP);
+}
@@ -0,0 +1,30 @@
+use tempfile::TempDir;
+use ziggurat_core_utils::err_constants::{ERR_NODE_BUILD, ERR_NODE_STOP, ERR_TEMPDIR_NEW};
+
+use crate::{
+    fuzzing::{payload::random_payloads, runner::stream_payloads, seeded_rng},
+    setup::node::{Node, NodeType},
+};
+
+const ITERATIONS: usize = 500;
+
+#[tokio::test]
+async fn r005_node_must_survive_fuzzed_messages() {
+    // ZG-RESISTANCE-005
+
+    let mut rng = seeded_rng();
+    let payloads = random_payloads(&mut rng, ITERATIONS);
+
+    let target = TempDir::new().expect(ERR_TEMPDIR_NEW);
+    let mut node = Node::builder()
+        .start(target.path(), NodeType::Stateless)
+        .await
+        .expect(ERR_NODE_BUILD);
+
+    let report = stream_payloads(&mut node, payloads, &Default::default()).await;
+    println!("{report}");
+
+    assert!(!report.crashed, "the node crashed");
+
+    node.stop().expect(ERR_NODE_STOP);
+}
@@ -1,8 +1,3 @@
-use std::{
-    net::{IpAddr, Ipv4Addr},
-    time::SystemTime,
-};
-
 use pea2pea::{
     ConnectionSide,
     ConnectionSide::{Initiator, Responder},
@@ -12,15 +7,18 @@ use tokio::time::{sleep, Duration};
 use ziggurat_core_utils::err_constants::{ERR_NODE_BUILD, ERR_NODE_STOP, ERR_TEMPDIR_NEW};
 
 use crate::{
-    protocol::{codecs::message::BinaryMessage, handshake::HandshakeCfg},
+    protocol::{
+        codecs::message::BinaryMessage,
+        handshake::{HandshakeRejected, HeaderEdit, SessionSignature},
+    },
     setup::{
         constants::CONNECTION_TIMEOUT,
         node::{ChildExitCode, Node, NodeType},
     },
-    tests::conformance::RIPPLE_EPOCH,
     tools::{
-        config::SynthNodeCfg,
-        synth_node::{self, SyntheticNode},
+        ripple_time,
+        synth_node::{self, SyntheticListeners, SyntheticNode, SyntheticNodeBuilder},
+        validator::KeyType,
     },
     wait_until,
 };
@@ -42,13 +40,10 @@ async fn r001_t1_HANDSHAKE_reject_if_user_agent_too_long() {
         .expect("unable to start the node");
 
     // Start the first synthetic node with a 'User-Agent' header that's too long.
-    let mut cfg = SynthNodeCfg::default();
-    cfg.handshake = cfg.handshake.map(|mut hs_cfg| {
-        hs_cfg.http_ident = format!("{:8192}", 0);
-        hs_cfg
-    });
-
-    let synth_node1 = SyntheticNode::new(&cfg).await;
+    let synth_node1 = SyntheticNode::builder()
+        .ident(format!("{:8192}", 0))
+        .build()
+        .await;
     // Ensure this connection was rejected by the node.
     assert!(synth_node1.connect(node.addr()).await.is_err());
     assert_eq!(synth_node1.num_connected(), 0);
@@ -72,33 +67,22 @@ async fn r001_t1_HANDSHAKE_reject_if_user_agent_too_long() {
 async fn r001_t2_HANDSHAKE_reject_if_server_too_long() {
     // ZG-RESISTANCE-001
 
-    // Start the first synthetic node. Set identification ('Server' header) for the value that's too long.
-    let mut cfg = SynthNodeCfg::default();
-    cfg.pea2pea_config.listener_ip = Some(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 2)));
-    cfg.handshake = cfg.handshake.map(|mut hs_cfg| {
-        hs_cfg.http_ident = format!("{:8192}", 0);
-        hs_cfg
-    });
-
-    let synth_node1 = SyntheticNode::new(&cfg).await;
-    let sn1_listening_addr = synth_node1
-        .start_listening()
-        .await
-        .expect("unable to start listening");
-
-    // Start the second synthetic node with the default 'Server' header.
-    let mut cfg2 = SynthNodeCfg::default();
-    cfg2.pea2pea_config.listener_ip = Some(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 3)));
-    let synth_node2 = SyntheticNode::new(&cfg2).await;
-    let sn2_listening_addr = synth_node2
-        .start_listening()
-        .await
-        .expect("unable to start listening");
+    // Start two listening synthetic nodes, the first one with identification ('Server' header)
+    // that's too long, the second one with the default 'Server' header.
+    let listeners = SyntheticListeners::start([
+        SyntheticNode::builder().ident(format!("{:8192}", 0)),
+        SyntheticNode::builder(),
+    ])
+    .await
+    .expect("unable to start listening");
+    let [synth_node1, synth_node2] = listeners.nodes() else {
+        unreachable!();
+    };
 
     // Build and start the Ripple node. Configure its peers such that it connects to the synthetic node above.
     let target = TempDir::new().expect("couldn't create a temporary directory");
     let mut node = Node::builder()
-        .initial_peers(vec![sn1_listening_addr, sn2_listening_addr])
+        .initial_peers(listeners.addrs().to_vec())
         .start(target.path(), NodeType::Stateless)
         .await
         .expect("unable to start the node");
@@ -110,8 +94,7 @@ async fn r001_t2_HANDSHAKE_reject_if_server_too_long() {
     wait_until!(CONNECTION_TIMEOUT, synth_node1.num_connected() == 0);
 
     // Shutdown all nodes.
-    synth_node1.shut_down().await;
-    synth_node2.shut_down().await;
+    listeners.shut_down().await;
     node.stop().unwrap();
 }
 
@@ -123,16 +106,13 @@ enum Debug {
 }
 
 impl Debug {
-    // This API exists just so we could enable the synth tracing once,
-    // because calling that function twice would break the test.
     fn enable() -> Self {
         synth_node::enable_tracing();
         Self::On
     }
 
     fn disable() -> Self {
-        // We should use something like synth_node::disable_tracing here (still unimplemented),
-        // but we'll never use it anyway so this is good enough
+        synth_node::disable_tracing();
         Self::Off
     }
 
@@ -147,7 +127,7 @@ impl Debug {
 
 // Runs the handshake request test with a given handshake configuration.
 // Returns the truthful fact about the relationship with the node.
-async fn run_handshake_req_test_with_cfg(cfg: SynthNodeCfg, debug: Debug) -> bool {
+async fn run_handshake_req_test_with_cfg(builder: SyntheticNodeBuilder, debug: Debug) -> bool {
     // Spin up a node instance.
     let target = TempDir::new().expect(ERR_TEMPDIR_NEW);
     let mut node = Node::builder()
@@ -157,7 +137,7 @@ async fn run_handshake_req_test_with_cfg(cfg: SynthNodeCfg, debug: Debug) -> boo
         .expect(ERR_NODE_BUILD);
 
     // Create a synthetic node and enable handshaking.
-    let mut synthetic_node = SyntheticNode::new(&cfg).await;
+    let mut synthetic_node = builder.build().await;
 
     // Connect to the node and initiate the handshake.
     let handshake_established = if synthetic_node.connect(node.addr()).await.is_err() {
@@ -188,7 +168,7 @@ async fn normal_handshake() {
 
     // Basically, a copy of the C001 test.
     assert!(
-        run_handshake_req_test_with_cfg(Default::default(), debug).await,
+        run_handshake_req_test_with_cfg(SyntheticNode::builder(), debug).await,
         "a default configuration doesn't work"
     );
 }
@@ -206,13 +186,7 @@ async fn r001_t3_HANDSHAKE_connection_field() {
 
     let debug = Debug::disable();
 
-    let gen_cfg = |connection: String| SynthNodeCfg {
-        handshake: Some(HandshakeCfg {
-            http_connection: connection,
-            ..Default::default()
-        }),
-        ..Default::default()
-    };
+    let gen_cfg = |connection: String| SyntheticNode::builder().connection(connection);
 
     // Valid scenarios:
 
@@ -255,13 +229,7 @@ async fn r001_t5_HANDSHAKE_crawl_field() {
 
     let debug = Debug::disable();
 
-    let gen_cfg = |crawl: String| SynthNodeCfg {
-        handshake: Some(HandshakeCfg {
-            http_crawl: Some(crawl),
-            ..Default::default()
-        }),
-        ..Default::default()
-    };
+    let gen_cfg = |crawl: String| SyntheticNode::builder().crawl(crawl);
 
     // Valid scenarios:
 
@@ -289,13 +257,7 @@ async fn r001_t4_HANDSHAKE_connect_as_field() {
 
     let debug = Debug::disable();
 
-    let gen_cfg = |connect_as: String| SynthNodeCfg {
-        handshake: Some(HandshakeCfg {
-            http_connect_as: connect_as,
-            ..Default::default()
-        }),
-        ..Default::default()
-    };
+    let gen_cfg = |connect_as: String| SyntheticNode::builder().connect_as(connect_as);
 
     // Valid scenarios:
 
@@ -337,13 +299,7 @@ async fn r001_t6_HANDSHAKE_x_protocol_ctl_field() {
 
     let debug = Debug::disable();
 
-    let gen_cfg = |protocol: String| SynthNodeCfg {
-        handshake: Some(HandshakeCfg {
-            http_x_protocol_ctl: protocol,
-            ..Default::default()
-        }),
-        ..Default::default()
-    };
+    let gen_cfg = |protocol: String| SyntheticNode::builder().x_protocol_ctl(protocol);
 
     // These are also valid, but should they be?
     let cfg = gen_cfg("leDgErrEpLay=하나;TXRR=да;".to_owned());
@@ -376,19 +332,9 @@ async fn r001_t7_HANDSHAKE_network_time_field() {
 
     let debug = Debug::disable();
 
-    let gen_cfg = |time: String| SynthNodeCfg {
-        handshake: Some(HandshakeCfg {
-            http_network_time: Some(time),
-            ..Default::default()
-        }),
-        ..Default::default()
-    };
+    let gen_cfg = |time: String| SyntheticNode::builder().network_time(time);
 
-    let time_now = SystemTime::now()
-        .duration_since(SystemTime::UNIX_EPOCH)
-        .unwrap()
-        .as_secs()
-        - RIPPLE_EPOCH as u64;
+    let time_now = ripple_time::now();
     // Valid value for Network-Time
     let cfg = gen_cfg(format!("{time_now}"));
     assert!(run_handshake_req_test_with_cfg(cfg, debug).await);
@@ -426,13 +372,7 @@ async fn r001_t8_HANDSHAKE_upgrade_req_field() {
 
     let debug = Debug::disable();
 
-    let gen_cfg = |version: String| SynthNodeCfg {
-        handshake: Some(HandshakeCfg {
-            http_upgrade_req: version,
-            ..Default::default()
-        }),
-        ..Default::default()
-    };
+    let gen_cfg = |version: String| SyntheticNode::builder().upgrade_req(version);
 
     // Valid value for Upgrade
     let cfg = gen_cfg("XRPL/2.2".to_owned());
@@ -472,13 +412,7 @@ async fn r001_t9_HANDSHAKE_user_agent_field() {
 
     let debug = Debug::disable();
 
-    let gen_cfg = |ident: String| SynthNodeCfg {
-        handshake: Some(HandshakeCfg {
-            http_ident: ident,
-            ..Default::default()
-        }),
-        ..Default::default()
-    };
+    let gen_cfg = |ident: String| SyntheticNode::builder().ident(ident);
 
     // Valid value for User-Agent
     let cfg = gen_cfg("Ziggurat/1.0".to_owned());
@@ -508,13 +442,7 @@ async fn r001_t10_HANDSHAKE_closed_ledger_field() {
 
     let debug = Debug::disable();
 
-    let gen_cfg = |ledger: String| SynthNodeCfg {
-        handshake: Some(HandshakeCfg {
-            http_closed_ledger: Some(ledger),
-            ..Default::default()
-        }),
-        ..Default::default()
-    };
+    let gen_cfg = |ledger: String| SyntheticNode::builder().closed_ledger(ledger);
 
     // Valid value for Closed-Ledger
     let cfg = gen_cfg("X72fvYvkYwPj7iFsE4OTiSwSd5Okz40P+eBRwsOXo4g=".to_owned());
@@ -549,13 +477,7 @@ async fn r001_t11_HANDSHAKE_previous_ledger_field() {
 
     let debug = Debug::disable();
 
-    let gen_cfg = |ledger: String| SynthNodeCfg {
-        handshake: Some(HandshakeCfg {
-            http_prev_ledger: Some(ledger),
-            ..Default::default()
-        }),
-        ..Default::default()
-    };
+    let gen_cfg = |ledger: String| SyntheticNode::builder().prev_ledger(ledger);
 
     // Valid value for Previous-Ledger but unable to do handshake
     let cfg = gen_cfg("6bsyOYDSAux+Ubqyqo41NT+ce9q1m/FzeOrdTQSG758=".to_owned());
@@ -575,13 +497,7 @@ async fn r001_t12_HANDSHAKE_extra_field() {
 
     let debug = Debug::disable();
 
-    let gen_cfg = |value: String| SynthNodeCfg {
-        handshake: Some(HandshakeCfg {
-            http_unexpected_extra_field_and_value: Some(value),
-            ..Default::default()
-        }),
-        ..Default::default()
-    };
+    let gen_cfg = |value: String| SyntheticNode::builder().extra_field(value);
 
     // Repeat a field with different value. "Connect-As: Peer" is always present during a handshake"
     let cfg = gen_cfg("Connect-As: NonPear".to_owned());
@@ -594,14 +510,10 @@ async fn r003_t1_HANDSHAKE_reject_if_public_key_has_bit_flipped() {
     // ZG-RESISTANCE-003
 
     // Prepare config for a synthetic node. Flip bit in the public_key.
-    let mut cfg = SynthNodeCfg::default();
-    cfg.handshake = cfg.handshake.map(|mut hs_cfg| {
-        hs_cfg.bitflip_pub_key = true;
-        hs_cfg
-    });
-
-    run_and_assert_handshake_failure(&cfg, Responder).await;
-    run_and_assert_handshake_failure(&cfg, Initiator).await;
+    let builder = SyntheticNode::builder().bitflip_pub_key(true);
+
+    run_and_assert_handshake_failure(&builder, Responder).await;
+    run_and_assert_handshake_failure(&builder, Initiator).await;
 }
 
 #[allow(non_snake_case)]
@@ -610,19 +522,252 @@ async fn r003_t2_HANDSHAKE_reject_if_shared_value_has_bit_flipped() {
     // ZG-RESISTANCE-003
 
     // Prepare config for a synthetic node. Flip bit in the shared_value.
-    let mut cfg = SynthNodeCfg::default();
-    cfg.handshake = cfg.handshake.map(|mut hs_cfg| {
-        hs_cfg.bitflip_shared_val = true;
-        hs_cfg
-    });
-
-    run_and_assert_handshake_failure(&cfg, Responder).await;
-    run_and_assert_handshake_failure(&cfg, Initiator).await;
+    let builder = SyntheticNode::builder().bitflip_shared_val(true);
+
+    run_and_assert_handshake_failure(&builder, Responder).await;
+    run_and_assert_handshake_failure(&builder, Initiator).await;
+}
+
+#[allow(non_snake_case)]
+#[tokio::test]
+async fn r003_t3_HANDSHAKE_reject_if_public_key_is_ed25519() {
+    // ZG-RESISTANCE-003
+
+    // Prepare config for a synthetic node identifying with a valid ed25519 key.
+    let builder = SyntheticNode::builder().key_type(KeyType::Ed25519);
+
+    run_and_assert_handshake_failure(&builder, Responder).await;
+    run_and_assert_handshake_failure(&builder, Initiator).await;
+}
+
+#[allow(non_snake_case)]
+#[tokio::test]
+async fn r003_t4_HANDSHAKE_reject_if_signed_with_foreign_key() {
+    // ZG-RESISTANCE-003
+
+    // Sign the shared value with a key other than the advertised one.
+    let builder = SyntheticNode::builder().session_signature(SessionSignature::foreign_key());
+
+    run_and_assert_handshake_failure(&builder, Responder).await;
+    run_and_assert_handshake_rejected(&builder, SIGNATURE_MISMATCH).await;
+}
+
+#[allow(non_snake_case)]
+#[tokio::test]
+async fn r003_t5_HANDSHAKE_reject_if_signature_is_replayed() {
+    // ZG-RESISTANCE-003
+
+    let target = TempDir::new().expect(ERR_TEMPDIR_NEW);
+    let mut node = Node::builder()
+        .start(target.path(), NodeType::Stateless)
+        .await
+        .expect(ERR_NODE_BUILD);
+
+    // The first session is valid and its signature is captured.
+    let synth_node = SyntheticNode::builder()
+        .session_signature(SessionSignature::replayed())
+        .build()
+        .await;
+    synth_node
+        .connect(node.addr())
+        .await
+        .expect("the first session should be accepted");
+    assert!(synth_node.disconnect(node.addr()).await);
+    wait_until!(CONNECTION_TIMEOUT, !synth_node.is_connected(node.addr()));
+
+    // The signature doesn't match the shared value of the next session.
+    let error = synth_node
+        .connect(node.addr())
+        .await
+        .expect_err("the replayed signature was accepted");
+    let rejection = HandshakeRejected::from_error(&error).expect("no rejection response");
+    assert_eq!(rejection.status, 400, "{rejection}");
+    assert!(rejection.reason.contains(SIGNATURE_MISMATCH), "{rejection}");
+
+    synth_node.shut_down().await;
+    node.stop().expect(ERR_NODE_STOP);
+}
+
+#[allow(non_snake_case)]
+#[tokio::test]
+async fn r003_t6_HANDSHAKE_reject_if_signature_is_not_base64() {
+    // ZG-RESISTANCE-003
+
+    for signature in ["not base64!", "", "MEUCIQ"] {
+        let builder =
+            SyntheticNode::builder().session_signature(SessionSignature::Raw(signature.into()));
+
+        run_and_assert_handshake_failure(&builder, Responder).await;
+        run_and_assert_handshake_rejected(&builder, SIGNATURE_MISMATCH).await;
+    }
+}
+
+#[allow(non_snake_case)]
+#[tokio::test]
+async fn r012_t1_HANDSHAKE_network_time_skew_tolerance() {
+    // ZG-RESISTANCE-012
+    // The node accepts peers whose clock is off by less than 20 seconds.
+
+    let debug = Debug::disable();
+
+    for skew in [-10, 10] {
+        let cfg = SyntheticNode::builder().network_time_skew(skew);
+        assert!(
+            run_handshake_req_test_with_cfg(cfg, debug).await,
+            "a peer with its clock off by {skew}s was rejected"
+        );
+    }
+
+    for skew in [-30, 30, -24 * 60 * 60, 24 * 60 * 60] {
+        let cfg = SyntheticNode::builder().network_time_skew(skew);
+        assert!(
+            !run_handshake_req_test_with_cfg(cfg, debug).await,
+            "a peer with its clock off by {skew}s was accepted"
+        );
+    }
+}
+
+#[allow(non_snake_case)]
+#[tokio::test]
+async fn r012_t2_HANDSHAKE_reject_skewed_network_time_from_responder() {
+    // ZG-RESISTANCE-012
+    // The node connecting to a peer with a skewed clock drops the connection.
+
+    for skew in [-30, 30] {
+        let builder = SyntheticNode::builder().network_time_skew(skew);
+        run_and_assert_handshake_failure(&builder, Responder).await;
+    }
+}
+
+#[allow(non_snake_case)]
+#[tokio::test]
+async fn r013_t1_HANDSHAKE_request_line() {
+    // ZG-RESISTANCE-013
+    // The node only upgrades HTTP/1.1 requests with a well-formed request line.
+
+    let debug = Debug::disable();
+
+    let gen_cfg = |method: &str, path: &str, version: &str| {
+        SyntheticNode::builder().request_line(method, path, version)
+    };
+
+    let cfg = gen_cfg("GET", "/", "HTTP/1.1");
+    assert!(run_handshake_req_test_with_cfg(cfg, debug).await);
+
+    // HTTP/1.0 has no upgrade mechanism.
+    let cfg = gen_cfg("GET", "/", "HTTP/1.0");
+    assert!(!run_handshake_req_test_with_cfg(cfg, debug).await);
+
+    // Malformed request lines.
+    let cfg = gen_cfg("GET", "/", "HTTP/1.x");
+    assert!(!run_handshake_req_test_with_cfg(cfg, debug).await);
+    let cfg = gen_cfg("G@T", "/", "HTTP/1.1");
+    assert!(!run_handshake_req_test_with_cfg(cfg, debug).await);
+    let cfg = gen_cfg("GET", "", "HTTP/1.1");
+    assert!(!run_handshake_req_test_with_cfg(cfg, debug).await);
+    let cfg = gen_cfg("GET", "/ /", "HTTP/1.1");
+    assert!(!run_handshake_req_test_with_cfg(cfg, debug).await);
+}
+
+#[allow(non_snake_case)]
+#[tokio::test]
+async fn r013_t2_HANDSHAKE_reject_missing_mandatory_fields() {
+    // ZG-RESISTANCE-013
+    // The node rejects handshakes missing any of the fields identifying a peer.
+
+    let debug = Debug::disable();
+
+    for field in [
+        "Upgrade",
+        "Connection",
+        "Connect-As",
+        "Public-Key",
+        "Session-Signature",
+    ] {
+        let cfg = SyntheticNode::builder().header_edit(HeaderEdit::Remove(field.into()));
+        assert!(
+            !run_handshake_req_test_with_cfg(cfg, debug).await,
+            "a handshake without the '{field}' field was accepted"
+        );
+    }
+}
+
+#[allow(non_snake_case)]
+#[tokio::test]
+async fn r013_t3_HANDSHAKE_duplicated_fields() {
+    // ZG-RESISTANCE-013
+    // Repeating a field with the same value doesn't change its meaning.
+
+    let debug = Debug::disable();
+
+    for field in [
+        "User-Agent",
+        "Connect-As",
+        "Public-Key",
+        "Session-Signature",
+    ] {
+        let cfg = SyntheticNode::builder().header_edit(HeaderEdit::Duplicate(field.into()));
+        assert!(
+            run_handshake_req_test_with_cfg(cfg, debug).await,
+            "a handshake with a duplicated '{field}' field was rejected"
+        );
+    }
+}
+
+#[allow(non_snake_case)]
+#[tokio::test]
+async fn r013_t4_HANDSHAKE_extra_fields() {
+    // ZG-RESISTANCE-013
+    // Unknown fields are ignored, while lines which aren't fields break the request.
+
+    let debug = Debug::disable();
+
+    let cfg = SyntheticNode::builder()
+        .header_edit(HeaderEdit::Append("X-Ziggurat".into(), "1".into()))
+        .header_edit(HeaderEdit::Append("X-Empty".into(), String::new()));
+    assert!(run_handshake_req_test_with_cfg(cfg, debug).await);
+
+    for line in ["Ziggurat", ": no name", "Bad Name: 1"] {
+        let cfg = SyntheticNode::builder().header_edit(HeaderEdit::AppendRaw(line.into()));
+        assert!(
+            !run_handshake_req_test_with_cfg(cfg, debug).await,
+            "a handshake with the '{line}' line was accepted"
+        );
+    }
+}
+
+// The reason rippled gives for a session signature not matching the public key.
+const SIGNATURE_MISMATCH: &str = "Failed to verify session";
+
+// Connects to the node and asserts it rejects the handshake with `400 Bad Request`, explaining
+// the rejection with the reason.
+async fn run_and_assert_handshake_rejected(builder: &SyntheticNodeBuilder, reason: &str) {
+    let target = TempDir::new().expect(ERR_TEMPDIR_NEW);
+    let mut node = Node::builder()
+        .start(target.path(), NodeType::Stateless)
+        .await
+        .expect(ERR_NODE_BUILD);
+
+    let synth_node = builder.build().await;
+    let error = synth_node
+        .connect(node.addr())
+        .await
+        .expect_err("the handshake was accepted");
+    let rejection = HandshakeRejected::from_error(&error)
+        .unwrap_or_else(|| panic!("the handshake failed without a response: {error}"));
+    assert_eq!(rejection.status, 400, "{rejection}");
+    assert!(rejection.reason.contains(reason), "{rejection}");
+
+    synth_node.shut_down().await;
+    node.stop().expect(ERR_NODE_STOP);
 }
 
-async fn run_and_assert_handshake_failure(config: &SynthNodeCfg, connection_side: ConnectionSide) {
+async fn run_and_assert_handshake_failure(
+    builder: &SyntheticNodeBuilder,
+    connection_side: ConnectionSide,
+) {
     // Start a SyntheticNode with the required config.
-    let synth_node = SyntheticNode::new(config).await;
+    let synth_node = builder.build().await;
     let listening_addr = synth_node
         .start_listening()
         .await
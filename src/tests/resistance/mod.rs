@@ -1,2 +1,12 @@
+mod churn;
+mod connection_rate;
+mod corrupt_fields;
+mod flood;
+mod fuzzing;
 mod handshake;
+mod mutated;
+mod oversized;
+mod protocol_error;
 mod random_bytes;
+mod slow_handshake;
+mod soak;
@@ -0,0 +1,34 @@
+use tempfile::TempDir;
+use ziggurat_core_utils::err_constants::{ERR_NODE_BUILD, ERR_NODE_STOP, ERR_TEMPDIR_NEW};
+
+use crate::{
+    fuzzing::{
+        mutate::random_mutations, payload::random_payloads, runner::stream_frames, seeded_rng,
+    },
+    setup::node::{Node, NodeType},
+};
+
+const ITERATIONS: usize = 500;
+
+#[tokio::test]
+async fn r011_node_must_survive_mutated_messages() {
+    // ZG-RESISTANCE-011
+
+    let mut rng = seeded_rng();
+    let payloads = random_payloads(&mut rng, ITERATIONS);
+    let frames = random_mutations(&mut rng, &payloads, ITERATIONS);
+
+    let target = TempDir::new().expect(ERR_TEMPDIR_NEW);
+    let mut node = Node::builder()
+        .start(target.path(), NodeType::Stateless)
+        .await
+        .expect(ERR_NODE_BUILD);
+
+    let report = stream_frames(&mut node, frames, &Default::default()).await;
+    println!("{report}");
+
+    assert!(!report.crashed, "the node crashed");
+    assert!(!report.reconnect_refused, "the node refused to reconnect");
+
+    node.stop().expect(ERR_NODE_STOP);
+}
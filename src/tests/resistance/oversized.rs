@@ -0,0 +1,235 @@
+use std::time::Duration;
+
+use tempfile::TempDir;
+use ziggurat_core_utils::err_constants::{
+    ERR_NODE_BUILD, ERR_NODE_STOP, ERR_SYNTH_CONNECT, ERR_SYNTH_UNICAST, ERR_TEMPDIR_NEW,
+};
+
+use crate::{
+    fuzzing::oversized::{
+        just_under_limit_frame, mismatched_lz4_frame, oversized_frame, FrameHeader, Oversize,
+    },
+    protocol::{
+        codecs::message::{Payload, COMPRESSED_SIZE_MASK, MESSAGE_TYPES, UNCOMPRESSED_SIZE_MASK},
+        proto::{tm_ping::PingType, MessageType, TmPing},
+    },
+    setup::node::{Node, NodeType},
+    tools::{
+        matchers::is_pong_with_seq, metrics::resources::ResourceSample, synth_node::SyntheticNode,
+    },
+    wait_until,
+};
+
+const DISCONNECT_TIMEOUT: Duration = Duration::from_millis(500);
+
+// A few bytes of the body, the node shouldn't wait for the rest.
+const BODY: [u8; 16] = [0; 16];
+
+// The peers declaring the largest body allowed without sending it.
+const STALLED_PEERS: usize = 16;
+
+// Far less than the declared bodies of all the stalled peers, had the node allocated them.
+const MAX_RSS_GROWTH_KIB: u64 = 256 * 1024;
+
+fn ping(seq: u32) -> TmPing {
+    TmPing {
+        r#type: PingType::PtPing as i32,
+        seq: Some(seq),
+        ping_time: None,
+        net_time: None,
+    }
+}
+
+// Sends the frame from a new peer and asserts the node disconnects it.
+async fn assert_frame_disconnects(node: &Node, frame: Vec<u8>) {
+    let synth_node = SyntheticNode::new(&Default::default()).await;
+    synth_node
+        .connect(node.addr())
+        .await
+        .expect(ERR_SYNTH_CONNECT);
+    synth_node
+        .unicast_bytes(node.addr(), frame)
+        .expect(ERR_SYNTH_UNICAST);
+
+    wait_until!(
+        DISCONNECT_TIMEOUT,
+        !synth_node.is_connected_ip(node.addr().ip())
+    );
+    synth_node.shut_down().await;
+}
+
+#[tokio::test]
+#[allow(non_snake_case)]
+async fn r010_t1_OVERSIZED_node_must_disconnect_when_size_is_over_the_limit() {
+    // ZG-RESISTANCE-010
+
+    let target = TempDir::new().expect(ERR_TEMPDIR_NEW);
+    let mut node = Node::builder()
+        .start(target.path(), NodeType::Stateless)
+        .await
+        .expect(ERR_NODE_BUILD);
+
+    for message_type in MESSAGE_TYPES {
+        for oversize in Oversize::ALL {
+            let synth_node = SyntheticNode::new(&Default::default()).await;
+            synth_node
+                .connect(node.addr())
+                .await
+                .expect(ERR_SYNTH_CONNECT);
+            synth_node
+                .unicast_bytes(node.addr(), oversized_frame(message_type, oversize, &BODY))
+                .expect(ERR_SYNTH_UNICAST);
+
+            // Ensure that the node has disconnected.
+            wait_until!(
+                DISCONNECT_TIMEOUT,
+                !synth_node.is_connected_ip(node.addr().ip())
+            );
+            synth_node.shut_down().await;
+        }
+    }
+
+    node.stop().expect(ERR_NODE_STOP);
+}
+
+#[tokio::test]
+#[allow(non_snake_case)]
+async fn r010_t2_OVERSIZED_node_must_accept_size_just_under_the_limit() {
+    // ZG-RESISTANCE-010
+
+    let target = TempDir::new().expect(ERR_TEMPDIR_NEW);
+    let mut node = Node::builder()
+        .start(target.path(), NodeType::Stateless)
+        .await
+        .expect(ERR_NODE_BUILD);
+
+    let mut synth_node = SyntheticNode::new(&Default::default()).await;
+    synth_node
+        .connect(node.addr())
+        .await
+        .expect(ERR_SYNTH_CONNECT);
+
+    let seq = 1;
+    let ping = Payload::TmPing(TmPing {
+        r#type: PingType::PtPing as i32,
+        seq: Some(seq),
+        ping_time: None,
+        net_time: None,
+    });
+    let frame = just_under_limit_frame(ping).expect("unable to pad the ping");
+    synth_node
+        .unicast_bytes(node.addr(), frame)
+        .expect(ERR_SYNTH_UNICAST);
+
+    synth_node
+        .expect_matching(&is_pong_with_seq(seq))
+        .await
+        .unwrap_or_else(|e| panic!("{e}"));
+    assert!(synth_node.is_connected_ip(node.addr().ip()));
+
+    synth_node.shut_down().await;
+    node.stop().expect(ERR_NODE_STOP);
+}
+
+#[tokio::test]
+#[allow(non_snake_case)]
+async fn r010_t3_OVERSIZED_node_must_disconnect_when_size_field_is_maxed() {
+    // ZG-RESISTANCE-010
+
+    let target = TempDir::new().expect(ERR_TEMPDIR_NEW);
+    let mut node = Node::builder()
+        .start(target.path(), NodeType::Stateless)
+        .await
+        .expect(ERR_NODE_BUILD);
+
+    for uncompressed_size in [BODY.len() as u32, u32::MAX] {
+        let header = FrameHeader::Compressed {
+            message_type: MessageType::MtPing as u16,
+            wire_size: COMPRESSED_SIZE_MASK,
+            uncompressed_size,
+        };
+        assert_frame_disconnects(&node, header.frame(&BODY)).await;
+    }
+
+    node.stop().expect(ERR_NODE_STOP);
+}
+
+#[tokio::test]
+#[allow(non_snake_case)]
+async fn r010_t4_OVERSIZED_node_must_disconnect_on_mismatched_uncompressed_size() {
+    // ZG-RESISTANCE-010
+
+    let target = TempDir::new().expect(ERR_TEMPDIR_NEW);
+    let mut node = Node::builder()
+        .start(target.path(), NodeType::Stateless)
+        .await
+        .expect(ERR_NODE_BUILD);
+
+    let actual_size = prost::Message::encoded_len(&ping(1)) as u32;
+    for uncompressed_size in [0, 1, actual_size - 1, actual_size + 1, actual_size * 100] {
+        let frame = mismatched_lz4_frame(Payload::TmPing(ping(1)), uncompressed_size)
+            .expect("unable to encode the ping");
+        assert_frame_disconnects(&node, frame).await;
+    }
+
+    node.stop().expect(ERR_NODE_STOP);
+}
+
+#[tokio::test]
+#[allow(non_snake_case)]
+async fn r010_t5_OVERSIZED_node_must_not_allocate_declared_sizes() {
+    // ZG-RESISTANCE-010
+
+    let target = TempDir::new().expect(ERR_TEMPDIR_NEW);
+    let mut node = Node::builder()
+        .max_peers(STALLED_PEERS + 11)
+        .start(target.path(), NodeType::Stateless)
+        .await
+        .expect(ERR_NODE_BUILD);
+
+    let mut probe = SyntheticNode::new(&Default::default()).await;
+    probe.connect(node.addr()).await.expect(ERR_SYNTH_CONNECT);
+    let baseline = ResourceSample::take(node.pid()).expect("unable to sample the node");
+
+    // Every peer declares the largest body the uncompressed header allows, just under the limit,
+    // and only sends a few bytes of it.
+    let header = FrameHeader::Uncompressed {
+        message_type: MessageType::MtPing as u16,
+        size: UNCOMPRESSED_SIZE_MASK,
+    };
+    let mut stalled_peers = Vec::with_capacity(STALLED_PEERS);
+    for _ in 0..STALLED_PEERS {
+        let synth_node = SyntheticNode::new(&Default::default()).await;
+        synth_node
+            .connect(node.addr())
+            .await
+            .expect(ERR_SYNTH_CONNECT);
+        synth_node
+            .unicast_bytes(node.addr(), header.frame(&BODY))
+            .expect(ERR_SYNTH_UNICAST);
+        stalled_peers.push(synth_node);
+    }
+
+    // The node stays responsive while waiting for the bodies.
+    let seq = 2;
+    probe
+        .unicast(node.addr(), Payload::TmPing(ping(seq)))
+        .expect(ERR_SYNTH_UNICAST);
+    probe
+        .expect_matching(&is_pong_with_seq(seq))
+        .await
+        .unwrap_or_else(|e| panic!("{e}"));
+
+    let sample = ResourceSample::take(node.pid()).expect("unable to sample the node");
+    let growth = sample.rss_kib.saturating_sub(baseline.rss_kib);
+    assert!(
+        growth < MAX_RSS_GROWTH_KIB,
+        "the node's RSS grew by {growth} KiB for {STALLED_PEERS} stalled peers"
+    );
+
+    for synth_node in stalled_peers {
+        synth_node.shut_down().await;
+    }
+    probe.shut_down().await;
+    node.stop().expect(ERR_NODE_STOP);
+}
@@ -0,0 +1,93 @@
+use std::time::Duration;
+
+use prost::Message;
+use tempfile::TempDir;
+use ziggurat_core_utils::err_constants::{
+    ERR_NODE_BUILD, ERR_NODE_STOP, ERR_SYNTH_CONNECT, ERR_SYNTH_UNICAST, ERR_TEMPDIR_NEW,
+};
+
+use crate::{
+    protocol::{
+        codecs::message::Payload,
+        proto::{tm_ping::PingType, MessageType, TmPing},
+    },
+    setup::node::{Node, NodeType},
+    tools::{matchers::is_pong_with_seq, synth_node::SyntheticNode},
+    wait_until,
+};
+
+const DISCONNECT_TIMEOUT: Duration = Duration::from_millis(500);
+
+// The compression algorithm bits sent along with the protocol error bits.
+const LZ4: u8 = 0x90;
+const UNKNOWN_ALGORITHM: u8 = 0xf0;
+
+fn ping(seq: u32) -> TmPing {
+    TmPing {
+        r#type: PingType::PtPing as i32,
+        seq: Some(seq),
+        ping_time: None,
+        net_time: None,
+    }
+}
+
+#[tokio::test]
+#[allow(non_snake_case)]
+async fn r016_t1_PROTOCOL_ERROR_node_must_disconnect_on_protocol_error_frame() {
+    // ZG-RESISTANCE-016
+
+    let target = TempDir::new().expect(ERR_TEMPDIR_NEW);
+    let mut node = Node::builder()
+        .start(target.path(), NodeType::Stateless)
+        .await
+        .expect(ERR_NODE_BUILD);
+
+    // A valid ping body, the node mustn't decode it anyway.
+    let body = ping(1).encode_to_vec();
+    let frames = [
+        (LZ4, Vec::new()),
+        (LZ4, body.clone()),
+        (UNKNOWN_ALGORITHM, body.clone()),
+    ];
+    for (flags, raw_bytes) in frames {
+        let synth_node = SyntheticNode::new(&Default::default()).await;
+        synth_node
+            .connect(node.addr())
+            .await
+            .expect(ERR_SYNTH_CONNECT);
+
+        let error = Payload::ProtocolError {
+            flags,
+            message_type: MessageType::MtPing as u16,
+            uncompressed_size: raw_bytes.len() as u32,
+            raw_bytes,
+        };
+        synth_node
+            .unicast(node.addr(), error)
+            .expect(ERR_SYNTH_UNICAST);
+
+        wait_until!(
+            DISCONNECT_TIMEOUT,
+            !synth_node.is_connected_ip(node.addr().ip())
+        );
+        synth_node.shut_down().await;
+    }
+
+    // The node keeps serving other peers.
+    let mut synth_node = SyntheticNode::new(&Default::default()).await;
+    synth_node
+        .connect(node.addr())
+        .await
+        .expect(ERR_SYNTH_CONNECT);
+    let seq = 2;
+    synth_node
+        .unicast(node.addr(), Payload::TmPing(ping(seq)))
+        .expect(ERR_SYNTH_UNICAST);
+    synth_node
+        .expect_matching(&is_pong_with_seq(seq))
+        .await
+        .unwrap_or_else(|e| panic!("{e}"));
+
+    synth_node.shut_down().await;
+    node.stop().expect(ERR_NODE_STOP);
+}
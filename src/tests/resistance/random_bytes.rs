@@ -5,7 +5,7 @@ use tempfile::TempDir;
 use crate::{
     fuzzing::{random_bytes, seeded_rng},
     setup::node::{Node, NodeType},
-    tools::{config::SynthNodeCfg, synth_node::SyntheticNode},
+    tools::synth_node::SyntheticNode,
     wait_until,
 };
 
@@ -56,13 +56,10 @@ async fn r004_node_must_disconnect_when_receiving_random_bytes_pre_handshake() {
         .await
         .expect("unable to start the node");
 
-    let cfg = SynthNodeCfg {
-        handshake: None, // Disable handshake.
-        ..Default::default()
-    };
+    let builder = SyntheticNode::builder().skip_handshake(true);
 
     for payload in payloads {
-        let synth_node = SyntheticNode::new(&cfg).await;
+        let synth_node = builder.build().await;
         synth_node.connect(node.addr()).await.unwrap();
         synth_node.unicast_bytes(node.addr(), payload).unwrap();
 
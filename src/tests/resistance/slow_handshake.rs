@@ -0,0 +1,64 @@
+use std::time::Duration;
+
+use tempfile::TempDir;
+use ziggurat_core_utils::err_constants::{ERR_NODE_BUILD, ERR_NODE_STOP, ERR_TEMPDIR_NEW};
+
+use crate::{
+    protocol::handshake::{HeldHandshake, HoldEnd, RequestPacing},
+    setup::node::{Node, NodeType},
+    tools::synth_node::SyntheticNode,
+};
+
+// Far longer than any timeout the node should apply to a pending handshake.
+const HOLD_LIMIT: Duration = Duration::from_secs(120);
+
+// Starts a node and holds a handshake with it, sending the request with the pacing.
+async fn hold_handshake(pacing: RequestPacing) -> HeldHandshake {
+    let target = TempDir::new().expect(ERR_TEMPDIR_NEW);
+    let mut node = Node::builder()
+        .start(target.path(), NodeType::Stateless)
+        .await
+        .expect(ERR_NODE_BUILD);
+
+    let synth_node = SyntheticNode::builder()
+        .request_pacing(pacing)
+        .build()
+        .await;
+    let held = synth_node
+        .hold_handshake(node.addr(), HOLD_LIMIT)
+        .await
+        .expect("unable to connect to the node");
+    println!("{pacing:?}: {held:?}");
+
+    synth_node.shut_down().await;
+    node.stop().expect(ERR_NODE_STOP);
+    held
+}
+
+#[allow(non_snake_case)]
+#[tokio::test]
+async fn r015_t1_HANDSHAKE_slow_request_is_answered() {
+    // ZG-RESISTANCE-015
+
+    let held = hold_handshake(RequestPacing::Trickle(Duration::from_millis(5))).await;
+    assert_eq!(held.end, HoldEnd::Responded(Some(101)));
+}
+
+#[allow(non_snake_case)]
+#[tokio::test]
+async fn r015_t2_HANDSHAKE_unterminated_request_is_dropped() {
+    // ZG-RESISTANCE-015
+
+    let held = hold_handshake(RequestPacing::Unterminated).await;
+    assert_eq!(held.end, HoldEnd::Closed);
+}
+
+#[allow(non_snake_case)]
+#[tokio::test]
+async fn r015_t3_HANDSHAKE_trickled_request_is_dropped() {
+    // ZG-RESISTANCE-015
+
+    // A byte per second keeps the connection busy while the request takes several minutes.
+    let held = hold_handshake(RequestPacing::Trickle(Duration::from_secs(1))).await;
+    assert_eq!(held.end, HoldEnd::Closed);
+}
@@ -0,0 +1,32 @@
+use tempfile::TempDir;
+use ziggurat_core_utils::err_constants::{ERR_NODE_BUILD, ERR_NODE_STOP, ERR_TEMPDIR_NEW};
+
+use crate::{
+    setup::node::{Node, NodeType},
+    tools::soak::{soak, SoakCfg},
+};
+
+#[cfg_attr(
+    not(feature = "soak"),
+    ignore = "run this test with the 'soak' feature enabled"
+)]
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn r008_node_must_stay_healthy_during_a_soak() {
+    // ZG-RESISTANCE-008
+    //
+    // Runs for an hour by default.
+    // *NOTE* run with `cargo test --features soak r008 -- --nocapture`
+
+    let target = TempDir::new().expect(ERR_TEMPDIR_NEW);
+    let mut node = Node::builder()
+        .start(target.path(), NodeType::Stateful)
+        .await
+        .expect(ERR_NODE_BUILD);
+
+    let report = soak(&mut node, &SoakCfg::default()).await;
+    println!("{report}");
+
+    assert!(report.anomalies.is_empty(), "anomalies detected");
+
+    node.stop().expect(ERR_NODE_STOP);
+}
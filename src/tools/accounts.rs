@@ -0,0 +1,282 @@
+//! Test accounts, their keys and funding.
+//!
+//! Account keys are derived from seeds the same way rippled and the client libraries do it, so
+//! a seed can be used to recreate an account or to inspect it with other tools.
+
+use std::{fs, path::Path};
+
+use anyhow::{anyhow, ensure, Result};
+use ripemd::Ripemd160;
+use secp256k1::{Scalar, Secp256k1};
+use sha2::{Digest, Sha256};
+
+use crate::tools::{
+    rpc::{get_account_info, submit_transaction},
+    tx::TxBuilder,
+    validator::{sha512_half, KeyType, ValidatorKey},
+};
+
+/// The seed of Ripple's genesis account. This is an account that holds all XRP when rippled starts from scratch.
+pub const GENESIS_SEED: &str = "snoPBrXtMeMyMHUVTgbuqAfg1SUTb";
+
+/// A random but valid account that will be created in tests/setup by sending XRP from the genesis account.
+pub const TEST_ACCOUNT: &str = "rNGknFCRBZguXcPqC63k6xTZnonSe6ZuWt";
+
+/// Amount of drops sent to a new account when funding it. Well above the base reserve.
+pub const DEFAULT_FUNDING_AMOUNT: u64 = 1_000_000_000;
+
+const SEED_SIZE: usize = 16;
+const SECP256K1_SEED_PREFIX: &[u8] = &[0x21];
+const ED25519_SEED_PREFIX: &[u8] = &[0x01, 0xE1, 0x4B];
+const ACCOUNT_ID_PREFIX: u8 = 0x00;
+
+/// An account together with the seed its key was derived from.
+#[derive(Clone)]
+pub struct Account {
+    seed: [u8; SEED_SIZE],
+    key: ValidatorKey,
+    address: String,
+}
+
+impl Account {
+    /// Returns the genesis account.
+    pub fn genesis() -> Self {
+        Self::from_seed(GENESIS_SEED).expect("invalid genesis seed")
+    }
+
+    /// Generates a new account with a random seed.
+    pub fn generate(key_type: KeyType) -> Self {
+        Self::from_raw_seed(rand::random(), key_type)
+    }
+
+    /// Recreates an account from its base58-encoded seed.
+    ///
+    /// The key type is determined by the seed's encoding, ed25519 seeds start with `sEd`.
+    pub fn from_seed(seed: &str) -> Result<Self> {
+        let bytes = bs58::decode(seed)
+            .with_alphabet(bs58::Alphabet::RIPPLE)
+            .with_check(None)
+            .into_vec()
+            .map_err(|e| anyhow!("unable to decode the seed: {e}"))?;
+
+        let (prefix, key_type) = if bytes.starts_with(ED25519_SEED_PREFIX) {
+            (ED25519_SEED_PREFIX, KeyType::Ed25519)
+        } else {
+            (SECP256K1_SEED_PREFIX, KeyType::Secp256k1)
+        };
+        ensure!(
+            bytes.len() == prefix.len() + SEED_SIZE && bytes.starts_with(prefix),
+            "invalid seed"
+        );
+
+        let mut raw_seed = [0u8; SEED_SIZE];
+        raw_seed.copy_from_slice(&bytes[prefix.len()..]);
+        Ok(Self::from_raw_seed(raw_seed, key_type))
+    }
+
+    fn from_raw_seed(seed: [u8; SEED_SIZE], key_type: KeyType) -> Self {
+        let key = derive_key(&seed, key_type);
+        let address = address_from_public_key(&key.public_key());
+
+        Self { seed, key, address }
+    }
+
+    /// Returns the account's classic address.
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    /// Returns the account's key, used to sign its transactions.
+    pub fn key(&self) -> &ValidatorKey {
+        &self.key
+    }
+
+    /// Returns the base58-encoded seed.
+    pub fn seed(&self) -> String {
+        let prefix = match self.key.key_type() {
+            KeyType::Secp256k1 => SECP256K1_SEED_PREFIX,
+            KeyType::Ed25519 => ED25519_SEED_PREFIX,
+        };
+        let mut payload = prefix.to_vec();
+        payload.extend_from_slice(&self.seed);
+
+        bs58::encode(payload)
+            .with_alphabet(bs58::Alphabet::RIPPLE)
+            .with_check()
+            .into_string()
+    }
+
+    /// Creates a payment of `amount` drops from this account to the destination.
+    pub fn payment(&self, destination: &str, amount: u64) -> TxBuilder {
+        TxBuilder::payment(&self.address, destination, amount)
+    }
+
+    /// Queries the node for the sequence number to be used by the account's next transaction.
+    pub async fn next_sequence(&self, rpc_url: &str) -> Result<u32> {
+        let response = get_account_info(rpc_url, &self.address).await?;
+        Ok(response.result.account_data.sequence)
+    }
+}
+
+/// A funding account and the test accounts it created.
+pub struct Wallet {
+    funder: Account,
+    accounts: Vec<Account>,
+}
+
+impl Default for Wallet {
+    fn default() -> Self {
+        Self::new(Account::genesis())
+    }
+}
+
+impl Wallet {
+    /// Creates a wallet funding new accounts from the given account.
+    pub fn new(funder: Account) -> Self {
+        Self {
+            funder,
+            accounts: Vec::new(),
+        }
+    }
+
+    /// Returns the funding account.
+    pub fn funder(&self) -> &Account {
+        &self.funder
+    }
+
+    /// Returns the test accounts, in creation order.
+    pub fn accounts(&self) -> &[Account] {
+        &self.accounts
+    }
+
+    /// Generates `count` new accounts and funds each of them with `amount` drops.
+    ///
+    /// The payments are only submitted, callers should wait for the accounts to appear in a
+    /// validated ledger before using them.
+    pub async fn create_funded_accounts(
+        &mut self,
+        rpc_url: &str,
+        count: usize,
+        key_type: KeyType,
+        amount: u64,
+    ) -> Result<&[Account]> {
+        let mut sequence = self.funder.next_sequence(rpc_url).await?;
+        let first_new = self.accounts.len();
+
+        for _ in 0..count {
+            let account = Account::generate(key_type);
+            let tx = self
+                .funder
+                .payment(account.address(), amount)
+                .sequence(sequence)
+                .sign(self.funder.key())?;
+
+            let response = submit_transaction(rpc_url, tx.to_hex(), false).await?;
+            ensure!(
+                response.result.accepted,
+                "funding {} wasn't accepted",
+                account.address()
+            );
+
+            sequence += 1;
+            self.accounts.push(account);
+        }
+
+        Ok(&self.accounts[first_new..])
+    }
+
+    /// Saves the test accounts' seeds to a file, one per line.
+    pub fn save_seeds(&self, path: &Path) -> Result<()> {
+        let seeds = self
+            .accounts
+            .iter()
+            .map(|account| account.seed())
+            .collect::<Vec<_>>();
+        fs::write(path, seeds.join("\n"))?;
+        Ok(())
+    }
+
+    /// Loads previously saved test accounts, which are assumed to be funded already.
+    pub fn load_seeds(&mut self, path: &Path) -> Result<()> {
+        for seed in fs::read_to_string(path)?.lines() {
+            self.accounts.push(Account::from_seed(seed)?);
+        }
+        Ok(())
+    }
+}
+
+/// Returns the classic address of the account owning the public key.
+pub fn address_from_public_key(public_key: &[u8]) -> String {
+    let account_id = Ripemd160::digest(Sha256::digest(public_key));
+
+    let mut payload = vec![ACCOUNT_ID_PREFIX];
+    payload.extend_from_slice(&account_id);
+
+    bs58::encode(payload)
+        .with_alphabet(bs58::Alphabet::RIPPLE)
+        .with_check()
+        .into_string()
+}
+
+fn derive_key(seed: &[u8; SEED_SIZE], key_type: KeyType) -> ValidatorKey {
+    match key_type {
+        KeyType::Secp256k1 => {
+            // The root key is the account key generator. Account keys are derived by adding a
+            // scalar derived from the root public key and the account index (always 0).
+            let root = derive_secp256k1_scalar(seed, None);
+            let root_public = root.public_key(&Secp256k1::new()).serialize();
+            let tweak = derive_secp256k1_scalar(&root_public, Some(0));
+
+            let key = root
+                .add_tweak(&Scalar::from(tweak))
+                .expect("invalid derived key");
+            ValidatorKey::Secp256k1(key)
+        }
+        KeyType::Ed25519 => {
+            ValidatorKey::Ed25519(ed25519_dalek::SigningKey::from_bytes(&sha512_half(seed)))
+        }
+    }
+}
+
+// Hashes the input with an incrementing counter until the result is a valid secret key.
+fn derive_secp256k1_scalar(input: &[u8], account_index: Option<u32>) -> secp256k1::SecretKey {
+    (0u32..)
+        .find_map(|counter| {
+            let mut buf = input.to_vec();
+            if let Some(index) = account_index {
+                buf.extend_from_slice(&index.to_be_bytes());
+            }
+            buf.extend_from_slice(&counter.to_be_bytes());
+
+            secp256k1::SecretKey::from_slice(&sha512_half(&buf)).ok()
+        })
+        .expect("unable to derive a key")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn genesis_account_derivation() {
+        let genesis = Account::genesis();
+
+        assert_eq!(genesis.address(), "rHb9CJAWyB4rj91VRWn96DkukG4bwdtyTh");
+        assert_eq!(
+            genesis.key().public_key_hex(),
+            "0330E7FC9D56BB25D6893BA3F317AE5BCF33B3291BD63DB32654A313222F7FD020"
+        );
+        assert_eq!(genesis.seed(), GENESIS_SEED);
+    }
+
+    #[test]
+    fn generated_accounts_roundtrip_through_seed() {
+        for key_type in [KeyType::Secp256k1, KeyType::Ed25519] {
+            let account = Account::generate(key_type);
+            let restored = Account::from_seed(&account.seed()).unwrap();
+
+            assert_eq!(restored.address(), account.address());
+            assert_eq!(restored.key().key_type(), key_type);
+        }
+    }
+}
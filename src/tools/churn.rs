@@ -0,0 +1,126 @@
+//! Connection churn with leak detection.
+//!
+//! Synthetic peers repeatedly connect to the node and disconnect, while the node's open file
+//! descriptors and memory are sampled. Once the peers are gone, both should return to roughly
+//! where they were before the churn.
+
+use std::{io, time::Duration};
+
+use tokio::{task::JoinSet, time::sleep};
+
+use crate::{
+    setup::node::Node,
+    tools::{config::SynthNodeCfg, metrics::resources::ResourceSample, synth_node::SyntheticNode},
+};
+
+/// Churn configuration.
+#[derive(Clone)]
+pub struct ChurnCfg {
+    /// The number of connect/disconnect rounds.
+    pub rounds: usize,
+    /// The number of peers connecting in each round.
+    pub peers_per_round: usize,
+    /// How long the peers stay connected in each round.
+    pub hold_time: Duration,
+    /// Time given to the node to clean up after the peers disconnect.
+    pub settle_time: Duration,
+    /// Configuration of the synthetic peers.
+    pub synth_node_cfg: SynthNodeCfg,
+}
+
+impl Default for ChurnCfg {
+    fn default() -> Self {
+        Self {
+            rounds: 20,
+            peers_per_round: 20,
+            hold_time: Duration::from_millis(500),
+            settle_time: Duration::from_secs(2),
+            synth_node_cfg: Default::default(),
+        }
+    }
+}
+
+/// The outcome of a churn run.
+#[derive(Debug)]
+pub struct ChurnReport {
+    /// Sampled after a warm-up round, before the churn.
+    pub baseline: ResourceSample,
+    /// Sampled after each round, once the node had time to settle.
+    pub rounds: Vec<ResourceSample>,
+    /// The number of successful connections.
+    pub connections: usize,
+    /// The number of failed connection attempts.
+    pub connection_failures: usize,
+}
+
+impl ChurnReport {
+    /// Returns the last sample, or the baseline if no round completed.
+    pub fn last(&self) -> ResourceSample {
+        self.rounds.last().copied().unwrap_or(self.baseline)
+    }
+
+    /// Returns the number of file descriptors left open compared to the baseline.
+    pub fn fd_growth(&self) -> isize {
+        self.last().open_fds as isize - self.baseline.open_fds as isize
+    }
+
+    /// Returns the ratio between the last and the baseline RSS.
+    pub fn rss_growth(&self) -> f64 {
+        self.last().rss_kib as f64 / self.baseline.rss_kib as f64
+    }
+}
+
+/// Churns connections to the node and samples its resource usage after each round.
+pub async fn churn(node: &Node, cfg: &ChurnCfg) -> io::Result<ChurnReport> {
+    // The first connections allocate long-lived state, so they aren't part of the baseline.
+    run_round(node, cfg).await;
+    sleep(cfg.settle_time).await;
+    let baseline = ResourceSample::take(node.pid())?;
+
+    let mut report = ChurnReport {
+        baseline,
+        rounds: Vec::with_capacity(cfg.rounds),
+        connections: 0,
+        connection_failures: 0,
+    };
+
+    for _ in 0..cfg.rounds {
+        let (connections, failures) = run_round(node, cfg).await;
+        report.connections += connections;
+        report.connection_failures += failures;
+
+        sleep(cfg.settle_time).await;
+        report.rounds.push(ResourceSample::take(node.pid())?);
+    }
+
+    Ok(report)
+}
+
+// Returns the number of successful and failed connections.
+async fn run_round(node: &Node, cfg: &ChurnCfg) -> (usize, usize) {
+    let mut peers = JoinSet::new();
+    for _ in 0..cfg.peers_per_round {
+        let node_addr = node.addr();
+        let synth_node_cfg = cfg.synth_node_cfg.clone();
+        let hold_time = cfg.hold_time;
+
+        peers.spawn(async move {
+            let synth_node = SyntheticNode::new(&synth_node_cfg).await;
+            let connected = synth_node.connect(node_addr).await.is_ok();
+            if connected {
+                sleep(hold_time).await;
+            }
+            synth_node.shut_down().await;
+            connected
+        });
+    }
+
+    let (mut connections, mut failures) = (0, 0);
+    while let Some(result) = peers.join_next().await {
+        match result {
+            Ok(true) => connections += 1,
+            _ => failures += 1,
+        }
+    }
+    (connections, failures)
+}
@@ -1,6 +1,25 @@
-use std::net::{IpAddr, Ipv4Addr};
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    net::{IpAddr, Ipv4Addr},
+    time::Duration,
+};
 
-use crate::protocol::handshake::HandshakeCfg;
+use anyhow::{anyhow, Context};
+use serde::Deserialize;
+
+use crate::{
+    protocol::{codecs::message::Lz4Compression, handshake::HandshakeCfg},
+    setup::{build_ripple_work_path, constants::ZIGGURAT_PROFILES},
+    tools::{
+        constants::{EXPECTED_RESULT_TIMEOUT, SYNTH_NODE_QUEUE_DEPTH},
+        validator::KeyType,
+    },
+};
+
+/// Environment variable naming the profile applied to the tests' default configurations, see
+/// [ConfigProfile].
+pub const PROFILE_ENV: &str = "ZIGGURAT_PROFILE";
 
 /// Synthetic Node Configuration.
 #[derive(Clone)]
@@ -8,21 +27,76 @@ pub struct SynthNodeCfg {
     /// Whether or not to generate new keys for a handshake.
     pub generate_new_keys: bool,
 
+    /// The algorithm of the node's identity key, only secp256k1 is available if the keys aren't
+    /// generated.
+    pub key_type: KeyType,
+
     /// Handshake configuration.
     ///
     /// If not set, the handshake will be skipped.
     pub handshake: Option<HandshakeCfg>,
 
+    /// Compression of sent messages.
+    ///
+    /// If not set, messages are sent uncompressed.
+    pub compression: Option<Lz4Compression>,
+
+    /// The types of received messages which are decoded, the others are kept raw.
+    ///
+    /// If not set, all the known message types are decoded.
+    pub allowed_message_types: Option<HashSet<u16>>,
+
+    /// Keeps idle connections alive.
+    ///
+    /// If not set, pings are left to the test to answer.
+    pub keepalive: Option<Keepalive>,
+
+    /// Whether to track the state each peer reports, see
+    /// [PeerStatus](crate::tools::peer_status::PeerStatus).
+    pub track_peer_status: bool,
+
+    /// Capacity of the inbound message queue.
+    pub queue_depth: usize,
+
+    /// How long to wait for an expected message.
+    pub expected_result_timeout: Duration,
+
     /// Pea2Pea configuration.
     pub pea2pea_config: pea2pea::Config,
 }
 
+impl SynthNodeCfg {
+    /// Creates the default configuration with the named profile applied, see [ConfigProfile].
+    pub fn from_profile(name: &str) -> anyhow::Result<Self> {
+        let mut config = Self::default();
+        ConfigProfile::load(name)?.apply(&mut config);
+        Ok(config)
+    }
+}
+
+/// Keepalive of a synthetic node's connections.
+///
+/// Pings are answered as they're read, and neither they nor the pongs to the node's own pings
+/// reach the inbound queue, so a test which doesn't read it won't stall the connections.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Keepalive {
+    /// If set, the node pings all its peers this often, too.
+    pub ping_interval: Option<Duration>,
+}
+
 impl Default for SynthNodeCfg {
     fn default() -> Self {
         let ip_addr = IpAddr::V4(Ipv4Addr::LOCALHOST);
         Self {
             generate_new_keys: true,
+            key_type: KeyType::Secp256k1,
             handshake: Some(Default::default()),
+            compression: None,
+            allowed_message_types: None,
+            keepalive: None,
+            track_peer_status: false,
+            queue_depth: SYNTH_NODE_QUEUE_DEPTH,
+            expected_result_timeout: EXPECTED_RESULT_TIMEOUT,
             pea2pea_config: pea2pea::Config {
                 listener_ip: Some(ip_addr),
                 ..Default::default()
@@ -30,3 +104,151 @@ impl Default for SynthNodeCfg {
         }
     }
 }
+
+/// Named overrides of the synthetic nodes' defaults, so different machines can use different
+/// timings without recompiling.
+///
+/// The profiles are the tables of [ZIGGURAT_PROFILES] in Ziggurat's work directory, the missing
+/// values keep the defaults, e.g.:
+/// ```toml
+/// [ci]
+/// expected_result_timeout_ms = 60000
+/// queue_depth = 1000
+/// listener_ips = ["127.0.0.2", "127.0.0.3"]
+///
+/// [ci.handshake]
+/// ident = "rippled-1.10.0"
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigProfile {
+    pub expected_result_timeout_ms: Option<u64>,
+    /// Capacity of the inbound message queue.
+    pub queue_depth: Option<usize>,
+    pub max_connections: Option<u16>,
+    /// The IPs the synthetic nodes listen on, the first one is used by single nodes.
+    pub listener_ips: Option<Vec<IpAddr>>,
+    pub handshake: Option<HandshakeProfile>,
+}
+
+/// The handshake fields of a [ConfigProfile].
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HandshakeProfile {
+    pub ident: Option<String>,
+    pub connection: Option<String>,
+    pub connect_as: Option<String>,
+    pub x_protocol_ctl: Option<String>,
+    pub crawl: Option<String>,
+    pub network_id: Option<String>,
+    pub network_time_skew: Option<i64>,
+}
+
+impl ConfigProfile {
+    /// Reads the named profile from [ZIGGURAT_PROFILES].
+    pub fn load(name: &str) -> anyhow::Result<Self> {
+        let path = build_ripple_work_path()?.join(ZIGGURAT_PROFILES);
+        let profiles = fs::read_to_string(&path)
+            .with_context(|| format!("couldn't read the profiles from {}", path.display()))?;
+
+        Self::parse(name, &profiles).with_context(|| format!("invalid {}", path.display()))
+    }
+
+    /// Reads the named profile from the TOML document.
+    fn parse(name: &str, profiles: &str) -> anyhow::Result<Self> {
+        let mut profiles: HashMap<String, ConfigProfile> = toml::from_str(profiles)?;
+        profiles
+            .remove(name)
+            .ok_or_else(|| anyhow!("no profile named {name}"))
+    }
+
+    /// Overrides the configuration's values set in the profile.
+    pub fn apply(&self, config: &mut SynthNodeCfg) {
+        if let Some(timeout) = self.expected_result_timeout_ms {
+            config.expected_result_timeout = Duration::from_millis(timeout);
+        }
+        if let Some(depth) = self.queue_depth {
+            config.queue_depth = depth;
+        }
+        if let Some(max_connections) = self.max_connections {
+            config.pea2pea_config.max_connections = max_connections;
+        }
+        if let Some(&ip) = self.listener_ips.iter().flatten().next() {
+            config.pea2pea_config.listener_ip = Some(ip);
+        }
+        if let (Some(profile), Some(handshake)) = (&self.handshake, &mut config.handshake) {
+            profile.apply(handshake);
+        }
+    }
+}
+
+impl HandshakeProfile {
+    /// Overrides the handshake fields set in the profile.
+    pub fn apply(&self, handshake: &mut HandshakeCfg) {
+        if let Some(ident) = &self.ident {
+            handshake.http_ident = ident.clone();
+        }
+        if let Some(connection) = &self.connection {
+            handshake.http_connection = connection.clone();
+        }
+        if let Some(connect_as) = &self.connect_as {
+            handshake.http_connect_as = connect_as.clone();
+        }
+        if let Some(x_protocol_ctl) = &self.x_protocol_ctl {
+            handshake.http_x_protocol_ctl = x_protocol_ctl.clone();
+        }
+        if self.crawl.is_some() {
+            handshake.http_crawl = self.crawl.clone();
+        }
+        if self.network_id.is_some() {
+            handshake.http_network_id = self.network_id.clone();
+        }
+        if self.network_time_skew.is_some() {
+            handshake.http_network_time_skew = self.network_time_skew;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROFILES: &str = r#"
+        [dev]
+
+        [ci]
+        expected_result_timeout_ms = 60000
+        queue_depth = 1000
+        listener_ips = ["127.0.0.2", "127.0.0.3"]
+
+        [ci.handshake]
+        ident = "rippled-1.10.0"
+        network_id = "21338"
+    "#;
+
+    #[test]
+    fn profile_overrides_the_defaults() {
+        let mut config = SynthNodeCfg::default();
+        ConfigProfile::parse("ci", PROFILES)
+            .unwrap()
+            .apply(&mut config);
+
+        assert_eq!(config.expected_result_timeout, Duration::from_secs(60));
+        assert_eq!(config.queue_depth, 1000);
+        assert_eq!(
+            config.pea2pea_config.listener_ip,
+            Some("127.0.0.2".parse().unwrap())
+        );
+        let handshake = config.handshake.unwrap();
+        assert_eq!(handshake.http_ident, "rippled-1.10.0");
+        assert_eq!(handshake.http_network_id.as_deref(), Some("21338"));
+        assert_eq!(
+            handshake.http_connect_as,
+            HandshakeCfg::default().http_connect_as
+        );
+
+        let dev = ConfigProfile::parse("dev", PROFILES).unwrap();
+        assert_eq!(dev, ConfigProfile::default());
+        assert!(ConfigProfile::parse("nightly", PROFILES).is_err());
+    }
+}
@@ -0,0 +1,274 @@
+//! Raw TCP connections opened at a controlled rate, for probing the node's connection throttling.
+//!
+//! The connections are opened in bursts, spaced so the configured rate is kept on average, and
+//! never start TLS or the handshake. Each connection is then held open for a while to see
+//! whether the node accepts it, resets it, closes it or doesn't answer at all.
+
+use std::{
+    io,
+    net::{IpAddr, SocketAddr},
+    time::Duration,
+};
+
+use tokio::{
+    io::AsyncReadExt,
+    net::{TcpSocket, TcpStream},
+    task::JoinSet,
+    time::{interval_at, timeout, Instant, MissedTickBehavior},
+};
+
+/// Connection rate configuration.
+#[derive(Clone)]
+pub struct ConnectionRateCfg {
+    /// The number of connections opened per second, on average.
+    pub rate: f64,
+    /// The number of connections opened at once.
+    pub burst: usize,
+    /// How long connections keep being opened.
+    pub duration: Duration,
+    /// How long a connection attempt may take before it's counted as timed out.
+    pub connect_timeout: Duration,
+    /// How long an accepted connection is held open, unless the node closes it first.
+    pub hold_time: Duration,
+    /// Source IPs for the connections, used in turn. The connections are opened from the default
+    /// local address if empty.
+    pub source_ips: Vec<IpAddr>,
+}
+
+impl Default for ConnectionRateCfg {
+    fn default() -> Self {
+        Self {
+            rate: 100.0,
+            burst: 10,
+            duration: Duration::from_secs(5),
+            connect_timeout: Duration::from_secs(5),
+            hold_time: Duration::from_secs(1),
+            source_ips: Vec::new(),
+        }
+    }
+}
+
+impl ConnectionRateCfg {
+    /// Returns the number of connections opened over the duration.
+    pub fn attempts(&self) -> usize {
+        (self.rate * self.duration.as_secs_f64()).round() as usize
+    }
+
+    // The time between two bursts.
+    fn burst_interval(&self) -> Duration {
+        Duration::from_secs_f64(self.burst as f64 / self.rate)
+    }
+}
+
+/// What happened to a connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionOutcome {
+    /// The node accepted the connection and kept it open for the hold time.
+    Held,
+    /// The node accepted the connection, then closed it gracefully.
+    Closed,
+    /// The node refused or reset the connection.
+    Reset,
+    /// The node didn't accept the connection in time, e.g. its backlog is full.
+    TimedOut,
+    /// The connection failed otherwise, e.g. the local ports ran out.
+    Failed(io::ErrorKind),
+}
+
+/// A single connection attempt.
+#[derive(Debug, Clone, Copy)]
+pub struct ConnectionAttempt {
+    /// When the attempt started, relative to the first one.
+    pub started: Duration,
+    /// How long the node took to accept the connection, if it did.
+    pub connect_time: Option<Duration>,
+    pub outcome: ConnectionOutcome,
+}
+
+/// The outcome of a connection rate run.
+#[derive(Debug, Default)]
+pub struct ConnectionRateReport {
+    /// The attempts, in the order they were started.
+    pub attempts: Vec<ConnectionAttempt>,
+}
+
+impl ConnectionRateReport {
+    /// Returns the number of attempts with the outcome.
+    pub fn count(&self, outcome: ConnectionOutcome) -> usize {
+        self.attempts
+            .iter()
+            .filter(|attempt| attempt.outcome == outcome)
+            .count()
+    }
+
+    /// Returns the number of connections the node accepted.
+    pub fn accepted(&self) -> usize {
+        self.attempts
+            .iter()
+            .filter(|attempt| attempt.connect_time.is_some())
+            .count()
+    }
+
+    /// Returns the longest time the node took to accept a connection.
+    pub fn max_connect_time(&self) -> Option<Duration> {
+        self.attempts
+            .iter()
+            .filter_map(|attempt| attempt.connect_time)
+            .max()
+    }
+}
+
+/// Opens connections to the node at the configured rate and waits for all of them to finish.
+pub async fn open_connections(
+    node_addr: SocketAddr,
+    cfg: &ConnectionRateCfg,
+) -> ConnectionRateReport {
+    let start = Instant::now();
+    // The bursts are kept on schedule, however long the previous ones take to connect.
+    let mut ticker = interval_at(start, cfg.burst_interval());
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Burst);
+
+    let mut connections = JoinSet::new();
+    let mut remaining = cfg.attempts();
+    let mut idx = 0;
+    while remaining > 0 {
+        ticker.tick().await;
+        for _ in 0..cfg.burst.min(remaining) {
+            let source_ip = match cfg.source_ips.is_empty() {
+                true => None,
+                false => Some(cfg.source_ips[idx % cfg.source_ips.len()]),
+            };
+            connections.spawn(open_connection(
+                node_addr,
+                source_ip,
+                cfg.clone(),
+                idx,
+                start,
+            ));
+            idx += 1;
+        }
+        remaining = remaining.saturating_sub(cfg.burst);
+    }
+
+    let mut attempts = Vec::with_capacity(idx);
+    while let Some(attempt) = connections.join_next().await {
+        attempts.push(attempt.expect("a connection task panicked"));
+    }
+    attempts.sort_by_key(|(idx, _)| *idx);
+
+    ConnectionRateReport {
+        attempts: attempts.into_iter().map(|(_, attempt)| attempt).collect(),
+    }
+}
+
+async fn open_connection(
+    node_addr: SocketAddr,
+    source_ip: Option<IpAddr>,
+    cfg: ConnectionRateCfg,
+    idx: usize,
+    start: Instant,
+) -> (usize, ConnectionAttempt) {
+    let started = start.elapsed();
+    let connect_start = Instant::now();
+
+    let (connect_time, outcome) =
+        match timeout(cfg.connect_timeout, connect(node_addr, source_ip)).await {
+            Err(_) => (None, ConnectionOutcome::TimedOut),
+            Ok(Err(e)) => (None, outcome_of_error(e)),
+            Ok(Ok(stream)) => (
+                Some(connect_start.elapsed()),
+                hold(stream, cfg.hold_time).await,
+            ),
+        };
+
+    let attempt = ConnectionAttempt {
+        started,
+        connect_time,
+        outcome,
+    };
+    (idx, attempt)
+}
+
+async fn connect(node_addr: SocketAddr, source_ip: Option<IpAddr>) -> io::Result<TcpStream> {
+    let socket = match node_addr {
+        SocketAddr::V4(_) => TcpSocket::new_v4()?,
+        SocketAddr::V6(_) => TcpSocket::new_v6()?,
+    };
+    if let Some(ip) = source_ip {
+        socket.bind(SocketAddr::new(ip, 0))?;
+    }
+    socket.connect(node_addr).await
+}
+
+// Waits for the node to close the connection, anything the node sends is ignored.
+async fn hold(mut stream: TcpStream, hold_time: Duration) -> ConnectionOutcome {
+    let mut buf = [0u8; 1024];
+    let held = timeout(hold_time, async {
+        loop {
+            match stream.read(&mut buf).await {
+                Ok(0) => return ConnectionOutcome::Closed,
+                Ok(_) => continue,
+                Err(e) => return outcome_of_error(e),
+            }
+        }
+    });
+
+    held.await.unwrap_or(ConnectionOutcome::Held)
+}
+
+fn outcome_of_error(error: io::Error) -> ConnectionOutcome {
+    match error.kind() {
+        io::ErrorKind::ConnectionRefused | io::ErrorKind::ConnectionReset => {
+            ConnectionOutcome::Reset
+        }
+        io::ErrorKind::TimedOut => ConnectionOutcome::TimedOut,
+        kind => ConnectionOutcome::Failed(kind),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn outcomes_are_recorded() {
+        let cfg = ConnectionRateCfg {
+            rate: 100.0,
+            burst: 2,
+            duration: Duration::from_millis(40),
+            connect_timeout: Duration::from_secs(1),
+            hold_time: Duration::from_millis(100),
+            source_ips: Vec::new(),
+        };
+        assert_eq!(cfg.attempts(), 4);
+
+        // The listener closes every other connection right away and keeps the rest open.
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let mut kept = Vec::new();
+            for idx in 0.. {
+                let (stream, _) = listener.accept().await.unwrap();
+                if idx % 2 == 0 {
+                    kept.push(stream);
+                }
+            }
+        });
+
+        let report = open_connections(addr, &cfg).await;
+        assert_eq!(report.attempts.len(), 4);
+        assert_eq!(report.accepted(), 4);
+        assert_eq!(report.count(ConnectionOutcome::Held), 2);
+        assert_eq!(report.count(ConnectionOutcome::Closed), 2);
+        // The second burst started an interval after the first.
+        assert!(report.attempts[2].started >= Duration::from_millis(20));
+
+        // Nothing listens on the port anymore.
+        server.abort();
+        let _ = server.await;
+        let report = open_connections(addr, &cfg).await;
+        assert_eq!(report.count(ConnectionOutcome::Reset), 4);
+    }
+}
@@ -1 +0,0 @@
-crawler/crawl.rs
\ No newline at end of file
@@ -0,0 +1,307 @@
+//! Differential testing of two rippled versions.
+//!
+//! The same message sequence is sent to a baseline and a candidate node, and the behavioral
+//! outcomes are compared: which messages each node replied with after every step, whether it
+//! dropped the connection and whether it survived the sequence. Message contents aren't
+//! compared, as they differ between any two nodes (keys, ledgers, timestamps).
+
+use std::{
+    collections::{BTreeSet, HashSet},
+    fmt,
+    time::Duration,
+};
+
+use anyhow::Result;
+use tempfile::TempDir;
+use tokio::time::Instant;
+
+use crate::{
+    protocol::codecs::message::Payload,
+    setup::node::{NodeBuilder, NodeType},
+    tools::{config::SynthNodeCfg, synth_node::SyntheticNode},
+};
+
+/// How long replies to a single message are collected by default.
+const DEFAULT_REPLY_WINDOW: Duration = Duration::from_secs(1);
+
+/// Messages the node sends on its own schedule, which would only add noise to the comparison.
+const UNSOLICITED_MESSAGES: [&str; 6] = [
+    "TmEndpoints",
+    "TmManifests",
+    "TmStatusChange",
+    "TmValidation",
+    "TmProposeLedger",
+    "TmValidatorListCollection",
+];
+
+/// What a single node did in response to the message sequence.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Outcome {
+    /// The kinds of messages received after each step.
+    pub replies: Vec<BTreeSet<String>>,
+    /// The step after which the node dropped the connection.
+    pub disconnected_at: Option<usize>,
+    /// Whether the node's process exited during the run.
+    pub crashed: bool,
+}
+
+/// A behavioral difference between the two nodes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Difference {
+    /// The nodes replied with different messages at the given step.
+    Replies {
+        step: usize,
+        baseline: BTreeSet<String>,
+        candidate: BTreeSet<String>,
+    },
+    /// The nodes dropped the connection at different steps, if at all.
+    Disconnect {
+        baseline: Option<usize>,
+        candidate: Option<usize>,
+    },
+    /// Only one of the nodes crashed.
+    Crash { baseline: bool, candidate: bool },
+}
+
+impl fmt::Display for Difference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Replies {
+                step,
+                baseline,
+                candidate,
+            } => write!(
+                f,
+                "step {step}: baseline replied with {baseline:?}, candidate with {candidate:?}"
+            ),
+            Self::Disconnect {
+                baseline,
+                candidate,
+            } => write!(
+                f,
+                "disconnected after step: baseline {baseline:?}, candidate {candidate:?}"
+            ),
+            Self::Crash {
+                baseline,
+                candidate,
+            } => write!(f, "crashed: baseline {baseline}, candidate {candidate}"),
+        }
+    }
+}
+
+/// The outcomes of both nodes and the differences between them.
+#[derive(Debug)]
+pub struct DiffReport {
+    pub baseline: Outcome,
+    pub candidate: Outcome,
+    pub differences: Vec<Difference>,
+}
+
+impl DiffReport {
+    /// Returns whether both nodes behaved the same.
+    pub fn is_identical(&self) -> bool {
+        self.differences.is_empty()
+    }
+}
+
+/// Runs message sequences against two differently configured nodes, e.g. using different
+/// binaries, see [NodeBuilder::with_binary].
+pub struct DifferentialHarness {
+    baseline: NodeBuilder,
+    candidate: NodeBuilder,
+    node_type: NodeType,
+    synth_node_cfg: SynthNodeCfg,
+    reply_window: Duration,
+    ignored: HashSet<String>,
+}
+
+impl DifferentialHarness {
+    /// Creates a harness starting stateless nodes from the given builders.
+    pub fn new(baseline: NodeBuilder, candidate: NodeBuilder) -> Self {
+        Self {
+            baseline,
+            candidate,
+            node_type: NodeType::Stateless,
+            synth_node_cfg: Default::default(),
+            reply_window: DEFAULT_REPLY_WINDOW,
+            ignored: UNSOLICITED_MESSAGES.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+
+    /// Sets the node type of both nodes.
+    pub fn node_type(mut self, node_type: NodeType) -> Self {
+        self.node_type = node_type;
+        self
+    }
+
+    /// Sets the configuration of the synthetic node sending the sequence.
+    pub fn synth_node_cfg(mut self, cfg: SynthNodeCfg) -> Self {
+        self.synth_node_cfg = cfg;
+        self
+    }
+
+    /// Sets how long replies are collected after each message.
+    pub fn reply_window(mut self, reply_window: Duration) -> Self {
+        self.reply_window = reply_window;
+        self
+    }
+
+    /// Sets the kinds of messages (e.g. `TmPing`) left out of the comparison.
+    pub fn ignored(mut self, kinds: &[&str]) -> Self {
+        self.ignored = kinds.iter().map(|s| s.to_string()).collect();
+        self
+    }
+
+    /// Sends the sequence to both nodes, one after the other, and compares their behavior.
+    pub async fn run(&mut self, sequence: &[Payload]) -> Result<DiffReport> {
+        let baseline = run_sequence(
+            &mut self.baseline,
+            self.node_type,
+            &self.synth_node_cfg,
+            sequence,
+            self.reply_window,
+            &self.ignored,
+        )
+        .await?;
+        let candidate = run_sequence(
+            &mut self.candidate,
+            self.node_type,
+            &self.synth_node_cfg,
+            sequence,
+            self.reply_window,
+            &self.ignored,
+        )
+        .await?;
+
+        let differences = diff_outcomes(&baseline, &candidate);
+        Ok(DiffReport {
+            baseline,
+            candidate,
+            differences,
+        })
+    }
+}
+
+async fn run_sequence(
+    builder: &mut NodeBuilder,
+    node_type: NodeType,
+    synth_node_cfg: &SynthNodeCfg,
+    sequence: &[Payload],
+    reply_window: Duration,
+    ignored: &HashSet<String>,
+) -> Result<Outcome> {
+    let target = TempDir::new()?;
+    let mut node = builder.start(target.path(), node_type).await?;
+
+    let mut synth_node = SyntheticNode::new(synth_node_cfg).await;
+    synth_node.connect(node.addr()).await?;
+
+    let mut outcome = Outcome::default();
+    for (step, payload) in sequence.iter().enumerate() {
+        if !synth_node.is_connected(node.addr()) {
+            outcome.disconnected_at = Some(step.saturating_sub(1));
+            break;
+        }
+
+        let _ = synth_node.unicast(node.addr(), payload.clone());
+
+        let mut replies = BTreeSet::new();
+        let window_end = Instant::now() + reply_window;
+        while let Some(remaining) = window_end.checked_duration_since(Instant::now()) {
+            let Ok((_, message)) = synth_node.recv_message_timeout(remaining).await else {
+                break;
+            };
+            let kind = message.payload.name().to_owned();
+            if !ignored.contains(&kind) {
+                replies.insert(kind);
+            }
+        }
+        outcome.replies.push(replies);
+    }
+    if outcome.disconnected_at.is_none() && !synth_node.is_connected(node.addr()) {
+        outcome.disconnected_at = Some(sequence.len().saturating_sub(1));
+    }
+
+    synth_node.shut_down().await;
+    outcome.crashed = !node.is_running();
+    if !outcome.crashed {
+        node.stop()?;
+    }
+
+    Ok(outcome)
+}
+
+fn diff_outcomes(baseline: &Outcome, candidate: &Outcome) -> Vec<Difference> {
+    let mut differences = Vec::new();
+
+    // Steps after a disconnect have no replies, which is covered by the disconnect difference.
+    for (step, (baseline, candidate)) in baseline
+        .replies
+        .iter()
+        .zip(candidate.replies.iter())
+        .enumerate()
+    {
+        if baseline != candidate {
+            differences.push(Difference::Replies {
+                step,
+                baseline: baseline.clone(),
+                candidate: candidate.clone(),
+            });
+        }
+    }
+
+    if baseline.disconnected_at != candidate.disconnected_at {
+        differences.push(Difference::Disconnect {
+            baseline: baseline.disconnected_at,
+            candidate: candidate.disconnected_at,
+        });
+    }
+
+    if baseline.crashed != candidate.crashed {
+        differences.push(Difference::Crash {
+            baseline: baseline.crashed,
+            candidate: candidate.crashed,
+        });
+    }
+
+    differences
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn replies(kinds: &[&str]) -> BTreeSet<String> {
+        kinds.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn outcomes_are_diffed_per_step() {
+        let baseline = Outcome {
+            replies: vec![replies(&["TmPing"]), replies(&["TmLedgerData"])],
+            disconnected_at: None,
+            crashed: false,
+        };
+        let candidate = Outcome {
+            replies: vec![replies(&["TmPing"]), replies(&[])],
+            disconnected_at: Some(1),
+            crashed: false,
+        };
+
+        assert!(diff_outcomes(&baseline, &baseline).is_empty());
+        assert_eq!(
+            diff_outcomes(&baseline, &candidate),
+            vec![
+                Difference::Replies {
+                    step: 1,
+                    baseline: replies(&["TmLedgerData"]),
+                    candidate: replies(&[]),
+                },
+                Difference::Disconnect {
+                    baseline: None,
+                    candidate: Some(1),
+                },
+            ]
+        );
+    }
+}
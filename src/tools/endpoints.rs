@@ -0,0 +1,74 @@
+//! Builders and parsers for the peer addresses gossiped in [TmEndpoints] messages.
+
+use std::net::SocketAddr;
+
+use crate::protocol::{
+    codecs::message::Payload,
+    proto::{tm_endpoints::TmEndpointv2, TmEndpoints},
+};
+
+/// The only message version rippled accepts, older versions carried IPv4 addresses only.
+pub const ENDPOINTS_VERSION: u32 = 2;
+
+/// The largest hop count rippled keeps, endpoints advertised from further away are dropped.
+pub const MAX_HOPS: u32 = 6;
+
+/// An advertised peer address and the number of hops it's away from the sender.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Endpoint {
+    pub addr: SocketAddr,
+    pub hops: u32,
+}
+
+impl Endpoint {
+    pub fn new(addr: SocketAddr, hops: u32) -> Self {
+        Self { addr, hops }
+    }
+}
+
+/// Builds a [TmEndpoints] message advertising the endpoints.
+pub fn endpoints_payload(endpoints: &[Endpoint]) -> Payload {
+    Payload::TmEndpoints(TmEndpoints {
+        version: ENDPOINTS_VERSION,
+        endpoints_v2: endpoints
+            .iter()
+            .map(|endpoint| TmEndpointv2 {
+                endpoint: endpoint.addr.to_string(),
+                hops: endpoint.hops,
+            })
+            .collect(),
+    })
+}
+
+/// Returns the endpoints advertised in the message, skipping the ones that aren't addresses.
+///
+/// The sender's own endpoint (0 hops) lists only a port and is skipped as well.
+pub fn parse_endpoints(endpoints: &TmEndpoints) -> Vec<Endpoint> {
+    endpoints
+        .endpoints_v2
+        .iter()
+        .filter_map(|endpoint| {
+            let addr = endpoint.endpoint.parse().ok()?;
+            Some(Endpoint::new(addr, endpoint.hops))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn endpoints_roundtrip() {
+        let endpoints = vec![
+            Endpoint::new("198.51.100.7:51235".parse().unwrap(), 1),
+            Endpoint::new("[2001:db8::7]:51235".parse().unwrap(), 3),
+        ];
+
+        let Payload::TmEndpoints(message) = endpoints_payload(&endpoints) else {
+            unreachable!();
+        };
+        assert_eq!(message.version, ENDPOINTS_VERSION);
+        assert_eq!(parse_endpoints(&message), endpoints);
+    }
+}
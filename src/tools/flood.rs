@@ -0,0 +1,285 @@
+//! A message flood generator which measures the node's responsiveness during the flood.
+//!
+//! Flooding peers send messages at a fixed rate while a separate probe peer keeps pinging the
+//! node, so the node's responsiveness to well-behaved peers can be observed under load.
+
+use std::{
+    net::{IpAddr, SocketAddr},
+    time::Duration,
+};
+
+use rand::{prelude::Rng, RngCore};
+use rand_chacha::ChaCha8Rng;
+use tokio::{
+    net::TcpSocket,
+    task::JoinSet,
+    time::{interval, sleep_until, timeout, Instant},
+};
+
+use crate::{
+    fuzzing::{payload::random_payload, seeded_rng, seeded_rng_stream},
+    protocol::{
+        codecs::message::Payload,
+        proto::{tm_ping::PingType, TmHaveTransactions, TmPing},
+    },
+    tools::{config::SynthNodeCfg, synth_node::SyntheticNode},
+};
+
+/// The size of a transaction hash in `mtHAVE_TRANSACTIONS`.
+const HASH_SIZE: usize = 32;
+
+/// The messages sent by the flooding peers.
+#[derive(Clone)]
+pub enum FloodPayload {
+    /// The same message over and over.
+    Fixed(Payload),
+    /// Structurally valid messages of random types, see [random_payload].
+    RandomMix,
+    /// `mtHAVE_TRANSACTIONS` messages with random hashes, roughly of the given size in bytes.
+    Sized(usize),
+}
+
+impl FloodPayload {
+    fn next(&self, rng: &mut ChaCha8Rng) -> Payload {
+        match self {
+            Self::Fixed(payload) => payload.clone(),
+            Self::RandomMix => random_payload(rng),
+            Self::Sized(size) => {
+                let hashes = (0..size.div_ceil(HASH_SIZE))
+                    .map(|_| {
+                        let mut hash = vec![0u8; HASH_SIZE];
+                        rng.fill_bytes(&mut hash);
+                        hash
+                    })
+                    .collect();
+                Payload::TmHaveTransactions(TmHaveTransactions { hashes })
+            }
+        }
+    }
+}
+
+/// Flood configuration.
+#[derive(Clone)]
+pub struct FloodCfg {
+    /// The number of flooding peers.
+    pub peers: usize,
+    /// The number of messages each peer sends per second.
+    pub rate: u32,
+    /// How long the flood lasts.
+    pub duration: Duration,
+    /// The messages sent by the flooding peers.
+    pub payload: FloodPayload,
+    /// Source IPs for the flooding peers, assigned in order. Peers without a source IP connect
+    /// from the default local address.
+    pub source_ips: Vec<IpAddr>,
+    /// How often the probe peer pings the node.
+    pub probe_interval: Duration,
+    /// How long the probe peer waits for a pong before counting the ping as lost.
+    pub probe_timeout: Duration,
+    /// Configuration of all the synthetic nodes.
+    pub synth_node_cfg: SynthNodeCfg,
+}
+
+impl Default for FloodCfg {
+    fn default() -> Self {
+        Self {
+            peers: 10,
+            rate: 100,
+            duration: Duration::from_secs(10),
+            payload: FloodPayload::RandomMix,
+            source_ips: Vec::new(),
+            probe_interval: Duration::from_millis(100),
+            probe_timeout: Duration::from_secs(1),
+            synth_node_cfg: Default::default(),
+        }
+    }
+}
+
+/// The outcome of a flood.
+#[derive(Debug, Default)]
+pub struct FloodReport {
+    /// The number of messages sent by all the flooding peers.
+    pub sent: u64,
+    /// The number of flooding peers which failed to connect.
+    pub connection_failures: usize,
+    /// The number of flooding peers the node disconnected during the flood.
+    pub disconnects: usize,
+    /// Round-trip times of the probe's pings which got a reply.
+    pub ping_rtts: Vec<Duration>,
+    /// The number of the probe's pings which got no reply in time.
+    pub pings_lost: usize,
+    /// Whether the node disconnected the probe peer.
+    pub probe_disconnected: bool,
+}
+
+impl FloodReport {
+    /// Returns the longest ping round-trip time.
+    pub fn max_rtt(&self) -> Option<Duration> {
+        self.ping_rtts.iter().max().copied()
+    }
+
+    /// Returns the mean ping round-trip time.
+    pub fn mean_rtt(&self) -> Option<Duration> {
+        if self.ping_rtts.is_empty() {
+            return None;
+        }
+        Some(self.ping_rtts.iter().sum::<Duration>() / self.ping_rtts.len() as u32)
+    }
+}
+
+// The outcome for a single flooding peer.
+enum PeerOutcome {
+    ConnectionFailed,
+    Finished { sent: u64, disconnected: bool },
+}
+
+/// Floods the node and probes its responsiveness until the flood ends.
+pub async fn flood(node_addr: SocketAddr, cfg: &FloodCfg) -> FloodReport {
+    let mut report = FloodReport::default();
+
+    // Connect the probe first so the flood can't prevent it from connecting.
+    let mut probe = SyntheticNode::new(&cfg.synth_node_cfg).await;
+    if probe.connect(node_addr).await.is_err() {
+        report.probe_disconnected = true;
+    }
+
+    let end = Instant::now() + cfg.duration;
+    let mut peers = JoinSet::new();
+    for idx in 0..cfg.peers {
+        peers.spawn(flood_peer(
+            node_addr,
+            cfg.clone(),
+            cfg.source_ips.get(idx).copied(),
+            // The probe uses the first sequence.
+            seeded_rng_stream(idx as u64 + 1),
+            end,
+        ));
+    }
+
+    if !report.probe_disconnected {
+        probe_node(&mut probe, node_addr, cfg, end, &mut report).await;
+    }
+    probe.shut_down().await;
+
+    while let Some(outcome) = peers.join_next().await {
+        match outcome {
+            Ok(PeerOutcome::Finished { sent, disconnected }) => {
+                report.sent += sent;
+                report.disconnects += disconnected as usize;
+            }
+            Ok(PeerOutcome::ConnectionFailed) | Err(_) => report.connection_failures += 1,
+        }
+    }
+
+    report
+}
+
+async fn probe_node(
+    probe: &mut SyntheticNode,
+    node_addr: SocketAddr,
+    cfg: &FloodCfg,
+    end: Instant,
+    report: &mut FloodReport,
+) {
+    let mut rng = seeded_rng();
+    let mut ticker = interval(cfg.probe_interval);
+
+    while Instant::now() < end {
+        ticker.tick().await;
+        if !probe.is_connected(node_addr) {
+            report.probe_disconnected = true;
+            return;
+        }
+
+        let seq = rng.gen();
+        let ping = Payload::TmPing(TmPing {
+            r#type: PingType::PtPing as i32,
+            seq: Some(seq),
+            ping_time: None,
+            net_time: None,
+        });
+        if probe.unicast(node_addr, ping).is_err() {
+            continue;
+        }
+
+        let sent_at = Instant::now();
+        let pong = timeout(cfg.probe_timeout, async {
+            loop {
+                let (_, message) = probe.recv_message().await;
+                if matches!(
+                    &message.payload,
+                    Payload::TmPing(TmPing { r#type, seq: Some(s), .. })
+                        if *s == seq && *r#type == PingType::PtPong as i32
+                ) {
+                    break;
+                }
+            }
+        })
+        .await;
+
+        match pong {
+            Ok(()) => report.ping_rtts.push(sent_at.elapsed()),
+            Err(_) => report.pings_lost += 1,
+        }
+    }
+}
+
+async fn flood_peer(
+    node_addr: SocketAddr,
+    cfg: FloodCfg,
+    source_ip: Option<IpAddr>,
+    mut rng: ChaCha8Rng,
+    end: Instant,
+) -> PeerOutcome {
+    let mut synth_node = SyntheticNode::new(&cfg.synth_node_cfg).await;
+
+    let connected = match source_ip {
+        Some(ip) => match bind_socket(ip) {
+            Ok(socket) => synth_node.connect_from(node_addr, socket).await,
+            Err(e) => Err(e),
+        },
+        None => synth_node.connect(node_addr).await,
+    };
+    if connected.is_err() {
+        synth_node.shut_down().await;
+        return PeerOutcome::ConnectionFailed;
+    }
+
+    let mut ticker = interval(Duration::from_secs(1) / cfg.rate.max(1));
+    let end_of_flood = sleep_until(end);
+    tokio::pin!(end_of_flood);
+
+    let mut sent = 0;
+    let mut disconnected = false;
+    loop {
+        tokio::select! {
+            _ = &mut end_of_flood => break,
+            _ = ticker.tick() => {
+                if !synth_node.is_connected(node_addr) {
+                    disconnected = true;
+                    break;
+                }
+                if synth_node.unicast(node_addr, cfg.payload.next(&mut rng)).is_ok() {
+                    sent += 1;
+                }
+            }
+            // Drain the node's replies so the inbound queue never fills up.
+            _ = synth_node.recv_message() => {}
+        }
+    }
+
+    synth_node.shut_down().await;
+    PeerOutcome::Finished { sent, disconnected }
+}
+
+fn bind_socket(ip: IpAddr) -> std::io::Result<TcpSocket> {
+    let socket = match ip {
+        IpAddr::V4(_) => TcpSocket::new_v4()?,
+        IpAddr::V6(_) => TcpSocket::new_v6()?,
+    };
+    // Make sure we can reuse the address and port
+    socket.set_reuseaddr(true)?;
+    socket.set_reuseport(true)?;
+    socket.bind(SocketAddr::new(ip, 0))?;
+    Ok(socket)
+}
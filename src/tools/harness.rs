@@ -0,0 +1,181 @@
+//! A shared test harness bundling a Ripple node with a set of synthetic nodes.
+//!
+//! Most tests follow the same pattern: create a temporary directory, start a node, create
+//! synthetic nodes, connect them to the node and shut everything down at the end. The harness
+//! takes care of that setup and teardown so tests can focus on their assertions.
+
+use anyhow::Result;
+use tempfile::TempDir;
+
+use crate::{
+    setup::node::{Node, NodeBuilder, NodeType},
+    tools::{config::SynthNodeCfg, synth_node::SyntheticNode},
+};
+
+/// A running node and its connected synthetic nodes.
+///
+/// Everything is torn down on drop, but [`TestHarness::shut_down`] should be preferred as it
+/// awaits the synthetic nodes' shutdown.
+pub struct TestHarness {
+    /// The running Ripple node.
+    pub node: Node,
+    /// The synthetic nodes created by the harness, in creation order.
+    pub synth_nodes: Vec<SyntheticNode>,
+    // The configuration used for every synthetic node.
+    synth_node_cfg: SynthNodeCfg,
+    // Keeps the node's directory alive for as long as the harness lives.
+    _target: TempDir,
+}
+
+impl TestHarness {
+    /// Creates a new [`TestHarnessBuilder`].
+    pub fn builder() -> TestHarnessBuilder {
+        TestHarnessBuilder::default()
+    }
+
+    /// Returns the synthetic node at the given index.
+    ///
+    /// Panics if there's no such synthetic node.
+    pub fn synth_node(&self, idx: usize) -> &SyntheticNode {
+        &self.synth_nodes[idx]
+    }
+
+    /// Returns the synthetic node at the given index as mutable.
+    ///
+    /// Panics if there's no such synthetic node.
+    pub fn synth_node_mut(&mut self, idx: usize) -> &mut SyntheticNode {
+        &mut self.synth_nodes[idx]
+    }
+
+    /// Creates another synthetic node and connects it to the node, e.g. to join after the other
+    /// synthetic nodes have set up the node's state.
+    ///
+    /// Returns the new synthetic node's index.
+    pub async fn connect_new_synth_node(&mut self) -> Result<usize> {
+        let synth_node = SyntheticNode::new(&self.synth_node_cfg).await;
+        synth_node.connect(self.node.addr()).await?;
+        self.synth_nodes.push(synth_node);
+
+        Ok(self.synth_nodes.len() - 1)
+    }
+
+    /// Gracefully shuts down all the synthetic nodes and stops the node.
+    ///
+    /// Fails if the node couldn't be stopped, e.g. as it crashed during the test.
+    pub async fn shut_down(mut self) -> Result<()> {
+        for synth_node in self.synth_nodes.drain(..) {
+            synth_node.shut_down().await;
+        }
+
+        self.node.stop()?;
+        Ok(())
+    }
+}
+
+impl Drop for TestHarness {
+    fn drop(&mut self) {
+        // The node is stopped by its own `Drop` implementation, so only synthetic nodes which
+        // weren't shut down explicitly need to be handled here.
+        if self.synth_nodes.is_empty() {
+            return;
+        }
+
+        let synth_nodes = std::mem::take(&mut self.synth_nodes);
+        if let Ok(handle) = tokio::runtime::Handle::try_current() {
+            handle.spawn(async move {
+                for synth_node in synth_nodes {
+                    synth_node.shut_down().await;
+                }
+            });
+        }
+    }
+}
+
+/// Builder for the [`TestHarness`].
+pub struct TestHarnessBuilder {
+    /// The builder used to start the node.
+    node_builder: Option<NodeBuilder>,
+    /// The type of the node to start.
+    node_type: NodeType,
+    /// The number of synthetic nodes to create.
+    synth_node_count: usize,
+    /// The configuration used for every synthetic node.
+    synth_node_cfg: SynthNodeCfg,
+    /// Whether or not the synthetic nodes should connect to the node.
+    connect: bool,
+}
+
+impl Default for TestHarnessBuilder {
+    fn default() -> Self {
+        Self {
+            node_builder: None,
+            node_type: NodeType::Stateless,
+            synth_node_count: 1,
+            synth_node_cfg: Default::default(),
+            connect: true,
+        }
+    }
+}
+
+impl TestHarnessBuilder {
+    /// Sets a custom [`NodeBuilder`], e.g. to change the number of max peers.
+    ///
+    /// [`Node::builder`] is used if not set.
+    pub fn node_builder(mut self, node_builder: NodeBuilder) -> Self {
+        self.node_builder = Some(node_builder);
+        self
+    }
+
+    /// Sets the type of the node to start.
+    pub fn node_type(mut self, node_type: NodeType) -> Self {
+        self.node_type = node_type;
+        self
+    }
+
+    /// Sets the number of synthetic nodes to create.
+    pub fn synth_nodes(mut self, count: usize) -> Self {
+        self.synth_node_count = count;
+        self
+    }
+
+    /// Sets the configuration used for every synthetic node.
+    pub fn synth_node_cfg(mut self, cfg: SynthNodeCfg) -> Self {
+        self.synth_node_cfg = cfg;
+        self
+    }
+
+    /// Sets whether the synthetic nodes should connect to the node once created.
+    pub fn connect(mut self, connect: bool) -> Self {
+        self.connect = connect;
+        self
+    }
+
+    /// Starts the node, creates the synthetic nodes and (optionally) connects them to the node.
+    pub async fn build(self) -> Result<TestHarness> {
+        let target = TempDir::new()?;
+        let mut node_builder = self.node_builder.unwrap_or_else(Node::builder);
+        let node = node_builder.start(target.path(), self.node_type).await?;
+
+        let mut synth_nodes = Vec::with_capacity(self.synth_node_count);
+        for _ in 0..self.synth_node_count {
+            let synth_node = SyntheticNode::new(&self.synth_node_cfg).await;
+            if self.connect {
+                if let Err(e) = synth_node.connect(node.addr()).await {
+                    synth_nodes.push(synth_node);
+                    for synth_node in synth_nodes {
+                        synth_node.shut_down().await;
+                    }
+                    return Err(e.into());
+                }
+            }
+            synth_nodes.push(synth_node);
+        }
+
+        Ok(TestHarness {
+            node,
+            synth_nodes,
+            synth_node_cfg: self.synth_node_cfg,
+            _target: target,
+        })
+    }
+}
@@ -1,21 +1,35 @@
 use std::{
+    collections::{HashMap, HashSet},
     io,
     net::{IpAddr, SocketAddr},
-    sync::Arc,
+    sync::{Arc, Mutex},
 };
 
 use openssl::ssl::{SslAcceptor, SslConnector, SslMethod, SslVerifyMode};
-use pea2pea::{Node, Pea2Pea};
+use pea2pea::{protocols::Disconnect, Node, Pea2Pea};
 use secp256k1::{
     constants::{PUBLIC_KEY_SIZE, SECRET_KEY_SIZE},
-    PublicKey, Secp256k1, SecretKey,
+    SecretKey,
+};
+use tokio::{
+    net::TcpSocket,
+    sync::{broadcast, mpsc::Sender},
 };
-use tokio::{net::TcpSocket, sync::mpsc::Sender};
 
 use crate::{
-    protocol::{codecs::message::BinaryMessage, handshake::HandshakeCfg},
+    protocol::{
+        codecs::message::{BinaryMessage, Lz4Compression},
+        handshake::{HandshakeCfg, HandshakeInfo},
+    },
     setup::constants::{SYNTHETIC_NODE_PRIVATE_KEY, SYNTHETIC_NODE_PUBLIC_KEY},
-    tools::{config::SynthNodeCfg, tls_cert},
+    tools::{
+        config::{Keepalive, SynthNodeCfg},
+        constants::CONNECTION_EVENTS_CAPACITY,
+        message_stats::MessageStats,
+        peer_status::PeerStatusTracker,
+        tls_cert,
+        validator::{KeyType, ValidatorKey},
+    },
 };
 
 // A synthetic node adhering to Ripple's network protocol.
@@ -26,6 +40,40 @@ pub struct InnerNode {
     pub crypto: Arc<Crypto>,
     pub tls: Tls,
     pub handshake_cfg: Option<HandshakeCfg>,
+    pub compression: Option<Lz4Compression>,
+    pub allowed_message_types: Option<HashSet<u16>>,
+    pub keepalive: Option<Keepalive>,
+    // The handshake details of each peer, as of the latest handshake with the address.
+    pub(crate) peer_handshakes: Arc<Mutex<HashMap<SocketAddr, HandshakeInfo>>>,
+    // Notifies the subscribers about connections being established and severed.
+    pub(crate) events: broadcast::Sender<ConnectionEvent>,
+    // The peers this node is disconnecting from on its own.
+    local_disconnects: Arc<Mutex<HashSet<SocketAddr>>>,
+    // The messages received from each peer.
+    pub(crate) message_stats: Arc<Mutex<MessageStats>>,
+    // The state reported by each peer, if tracked.
+    pub(crate) peer_status: Option<Arc<Mutex<PeerStatusTracker>>>,
+}
+
+/// A change in the state of a connection of a synthetic node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionEvent {
+    /// The handshake with the peer has completed, in either direction.
+    Connected(SocketAddr),
+    /// The connection with the peer was severed.
+    Disconnected {
+        addr: SocketAddr,
+        reason: DisconnectReason,
+    },
+}
+
+/// Why a connection was severed, as far as the synthetic node can tell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DisconnectReason {
+    /// The synthetic node disconnected or shut down.
+    Local,
+    /// The peer closed the connection, or it broke, e.g. due to an invalid message.
+    Remote,
 }
 
 // An object containing TLS handlers.
@@ -37,9 +85,8 @@ pub struct Tls {
 
 // An object dedicated to cryptographic functionalities.
 pub struct Crypto {
-    pub engine: Secp256k1<secp256k1::All>,
-    pub private_key: SecretKey,
-    pub public_key: PublicKey,
+    // The node's identity, either a secp256k1 or an ed25519 key.
+    pub key: ValidatorKey,
 }
 
 impl Pea2Pea for InnerNode {
@@ -50,19 +97,19 @@ impl Pea2Pea for InnerNode {
 
 impl InnerNode {
     pub async fn new(cfg: &SynthNodeCfg, sender: Sender<(SocketAddr, BinaryMessage)>) -> Self {
-        // generate the keypair and prepare the crypto engine
+        // generate the node's identity key
 
-        let engine = Secp256k1::new();
-        let (private_key, public_key) = if cfg.generate_new_keys {
-            engine.generate_keypair(&mut secp256k1::rand::thread_rng())
+        let key = if cfg.generate_new_keys {
+            ValidatorKey::generate(cfg.key_type)
         } else {
-            decode_predefined_keys().expect("invalid predefined keys")
+            assert_eq!(
+                cfg.key_type,
+                KeyType::Secp256k1,
+                "the predefined key is a secp256k1 key"
+            );
+            decode_predefined_key().expect("invalid predefined keys")
         };
-        let crypto = Arc::new(Crypto {
-            engine,
-            private_key,
-            public_key,
-        });
+        let crypto = Arc::new(Crypto { key });
 
         // TLS acceptor
 
@@ -89,7 +136,38 @@ impl InnerNode {
                 connector,
             },
             handshake_cfg: cfg.handshake.clone(),
+            compression: cfg.compression,
+            allowed_message_types: cfg.allowed_message_types.clone(),
+            keepalive: cfg.keepalive,
+            peer_handshakes: Default::default(),
+            events: broadcast::channel(CONNECTION_EVENTS_CAPACITY).0,
+            local_disconnects: Default::default(),
+            message_stats: Default::default(),
+            peer_status: cfg.track_peer_status.then(Default::default),
+        }
+    }
+
+    /// Publishes the event, it's dropped if nobody is subscribed.
+    pub(crate) fn notify(&self, event: ConnectionEvent) {
+        if let (ConnectionEvent::Connected(addr), Some(peer_status)) = (event, &self.peer_status) {
+            peer_status.lock().unwrap().connected(addr);
+        }
+        let _ = self.events.send(event);
+    }
+
+    /// Disconnects from the peer, reporting it as a local disconnect.
+    pub async fn disconnect(&self, addr: SocketAddr) -> bool {
+        self.local_disconnects.lock().unwrap().insert(addr);
+        let disconnected = self.node.disconnect(addr).await;
+        if !disconnected {
+            self.local_disconnects.lock().unwrap().remove(&addr);
         }
+        disconnected
+    }
+
+    /// Returns the identity the peer claimed in the latest handshake with the address.
+    pub fn peer_handshake_info(&self, addr: SocketAddr) -> Option<HandshakeInfo> {
+        self.peer_handshakes.lock().unwrap().get(&addr).cloned()
     }
 
     pub fn is_connected_ip(&self, ip: IpAddr) -> bool {
@@ -112,10 +190,26 @@ impl InnerNode {
 
     /// Gracefully shuts down the node.
     pub async fn shut_down(&self) {
+        self.local_disconnects
+            .lock()
+            .unwrap()
+            .extend(self.node.connected_addrs());
         self.node.shut_down().await
     }
 }
 
+#[async_trait::async_trait]
+impl Disconnect for InnerNode {
+    async fn handle_disconnect(&self, addr: SocketAddr) {
+        let reason = if self.local_disconnects.lock().unwrap().remove(&addr) {
+            DisconnectReason::Local
+        } else {
+            DisconnectReason::Remote
+        };
+        self.notify(ConnectionEvent::Disconnected { addr, reason });
+    }
+}
+
 fn decode_to_vec(base58str: &str, size: usize) -> bs58::decode::Result<Vec<u8>> {
     let mut bytes = bs58::decode(base58str)
         .with_alphabet(bs58::Alphabet::RIPPLE)
@@ -127,14 +221,18 @@ fn decode_to_vec(base58str: &str, size: usize) -> bs58::decode::Result<Vec<u8>>
     Ok(bytes)
 }
 
-fn decode_predefined_keys() -> Result<(SecretKey, PublicKey), secp256k1::Error> {
+fn decode_predefined_key() -> Result<ValidatorKey, secp256k1::Error> {
     let bytes = decode_to_vec(SYNTHETIC_NODE_PRIVATE_KEY, SECRET_KEY_SIZE)
         .expect("unable to decode the private key");
-    let private_key = SecretKey::from_slice(bytes.as_slice())?;
+    let key = ValidatorKey::Secp256k1(SecretKey::from_slice(bytes.as_slice())?);
 
     let bytes = decode_to_vec(SYNTHETIC_NODE_PUBLIC_KEY, PUBLIC_KEY_SIZE)
         .expect("unable to decode the public key");
-    let public_key = PublicKey::from_slice(bytes.as_slice())?;
+    assert_eq!(
+        key.public_key(),
+        bytes,
+        "the predefined keys don't form a keypair"
+    );
 
-    Ok((private_key, public_key))
+    Ok(key)
 }
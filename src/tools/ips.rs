@@ -1,42 +1,250 @@
-// If the IPS array is empty then it means that source IP addresses have not been
-// generated and assigned to dummy devices. In such case tests should not bound to any
-// specific address and use local pool instead.
-
-/// Reference to a static array of IP addresses (represented as str).
-/// If array is empty generate new addresses using eg.: for Linux:
-/// sudo python3 ./tools/ips.py --subnet 1.1.1.0/24 --file src/tools/ips.rs --dev_prefix test_zeth
-/// for MacOS:
-/// sudo python3 ./tools/ips.py --subnet 1.1.1.0/24 --file src/tools/ips.rs --dev lo0
-/// For more information read the documentation of the ips.py script.
-use std::fs;
+//! Source IP addresses for the synthetic peers of the performance tests.
+//!
+//! The addresses are listed in a file generated by the ips.py script, e.g. for Linux:
+//! sudo python3 ./tools/ips.py --subnet 1.1.1.0/24 --file tools/ips_list.json --dev_prefix test_zeth
+//! for MacOS:
+//! sudo python3 ./tools/ips.py --subnet 1.1.1.0/24 --file tools/ips_list.json --dev lo0
+//! For more information read the documentation of the ips.py script.
+//!
+//! Without the list, [IpPool::acquire] assigns the aliases to the loopback device itself, which
+//! requires `CAP_NET_ADMIN` on Linux, and removes them once the pool is dropped.
+
+use std::{
+    fmt, fs, io,
+    net::{IpAddr, Ipv4Addr, SocketAddr, TcpListener},
+    path::Path,
+    process::Command,
+};
 
 use serde::Deserialize;
+use tracing::warn;
+
+use crate::tools::netem::LOOPBACK_DEVICE;
 
 const IPS_LIST_PATH: &str = "./tools/ips_list.json";
 
+/// The subnet the aliases are created in, the same one the ips.py examples use.
+const ALIAS_SUBNET: [u8; 3] = [1, 1, 1];
+/// The number of aliases created, all the host addresses of the subnet.
+const ALIAS_COUNT: u8 = 254;
+
+/// The bit of `CAP_NET_ADMIN` in the capability sets of `/proc/self/status`.
+const CAP_NET_ADMIN: u32 = 12;
+
 #[derive(Default, Clone, Deserialize, Debug)]
 struct IpsList {
     pub nodes: Vec<String>,
 }
 
-/// Read in the node array generated by the ips.py
-/// script. If file does not exist, return an empty
-/// array.
-fn load_ips_nodes(filepath: &str) -> Vec<String> {
-    let result = fs::read_to_string(filepath);
-    match result {
-        Ok(jstring) => {
-            let ips_list: IpsList = serde_json::from_str(&jstring).unwrap();
-            ips_list.nodes
+/// Read in the node array generated by the ips.py script.
+fn load_ips_nodes(filepath: &str) -> io::Result<Vec<String>> {
+    let jstring = fs::read_to_string(filepath)?;
+    let ips_list: IpsList = serde_json::from_str(&jstring)?;
+    Ok(ips_list.nodes)
+}
+
+/// Called by clients to obtain a list of nodes generated by the ips.py script.
+///
+/// Panics if the list is missing or lists addresses which aren't assigned to any device.
+pub fn ips() -> Vec<String> {
+    let ips = load_ips_nodes(IPS_LIST_PATH).unwrap_or_else(|e| {
+        panic!(
+            "Problem reading file: {IPS_LIST_PATH} ({e}). Confirm that you have run the ips.py \
+             script, as described in the readme.\n{}",
+            check_aliases(&[])
+        )
+    });
+
+    let report = check_aliases(&parse_ips(&ips));
+    assert!(
+        report.missing.is_empty(),
+        "{IPS_LIST_PATH} is out of date, run the ips.py script again.\n{report}"
+    );
+    ips
+}
+
+fn parse_ips(ips: &[String]) -> Vec<IpAddr> {
+    ips.iter()
+        .map(|ip| {
+            ip.parse()
+                .unwrap_or_else(|_| panic!("{IPS_LIST_PATH} lists an invalid address: {ip}"))
+        })
+        .collect()
+}
+
+/// The source addresses available to the tests, see the [module docs](self).
+pub struct IpPool {
+    ips: Vec<String>,
+    // Keeps the aliases assigned while the pool is used.
+    _aliases: Option<IpAliases>,
+}
+
+impl IpPool {
+    /// Uses the addresses listed by the ips.py script, or creates the aliases if there's no list.
+    ///
+    /// Panics with a [capability report](AliasReport) if neither is possible.
+    pub fn acquire() -> Self {
+        if Path::new(IPS_LIST_PATH).exists() {
+            return Self {
+                ips: ips(),
+                _aliases: None,
+            };
         }
-        Err(_) => {
-            panic!("Problem reading file: {filepath}.  Confirm that you have run the ips.py script, as described in the readme.");
+
+        let addrs = (1..=ALIAS_COUNT)
+            .map(|host| {
+                let [a, b, c] = ALIAS_SUBNET;
+                IpAddr::V4(Ipv4Addr::new(a, b, c, host))
+            })
+            .collect::<Vec<_>>();
+        let aliases = IpAliases::create(&addrs).unwrap_or_else(|e| {
+            panic!(
+                "{IPS_LIST_PATH} is missing and the aliases couldn't be created: {e}\n{}",
+                check_aliases(&addrs)
+            )
+        });
+
+        Self {
+            ips: addrs.iter().map(IpAddr::to_string).collect(),
+            _aliases: Some(aliases),
         }
     }
+
+    /// Returns the addresses as strings, the way [ips] does.
+    pub fn ips(&self) -> Vec<String> {
+        self.ips.clone()
+    }
 }
 
-/// Called by clients to obtain a list of
-/// nodes generated by the ips.py script.
-pub fn ips() -> Vec<String> {
-    load_ips_nodes(IPS_LIST_PATH)
+/// Loopback aliases assigned by the tests, removed when dropped.
+pub struct IpAliases {
+    /// The aliases which were assigned by this guard, existing ones are left alone.
+    created: Vec<IpAddr>,
+}
+
+impl IpAliases {
+    /// Assigns the addresses missing on the host to the loopback device.
+    pub fn create(addrs: &[IpAddr]) -> io::Result<Self> {
+        if !cfg!(target_os = "linux") {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "creating IP aliases is only supported on Linux",
+            ));
+        }
+
+        // Removes the aliases created so far in case of an error.
+        let mut aliases = Self {
+            created: Vec::new(),
+        };
+        for &addr in &check_aliases(addrs).missing {
+            ip_addr("add", addr)?;
+            aliases.created.push(addr);
+        }
+        Ok(aliases)
+    }
+}
+
+impl Drop for IpAliases {
+    fn drop(&mut self) {
+        for &addr in &self.created {
+            if let Err(e) = ip_addr("del", addr) {
+                warn!("unable to remove the IP alias {addr}: {e}");
+            }
+        }
+    }
+}
+
+fn ip_addr(action: &str, addr: IpAddr) -> io::Result<()> {
+    let prefix = if addr.is_ipv4() { 32 } else { 128 };
+    let addr = format!("{addr}/{prefix}");
+    let args = ["addr", action, &addr, "dev", LOOPBACK_DEVICE];
+
+    let output = Command::new("ip").args(args).output()?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!(
+            "`ip {}` failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )))
+    }
+}
+
+/// Which addresses the tests can bind to and whether the missing ones could be created.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AliasReport {
+    pub available: Vec<IpAddr>,
+    pub missing: Vec<IpAddr>,
+    /// Set if the process may assign addresses, i.e. on Linux with `CAP_NET_ADMIN`.
+    pub can_manage: bool,
+}
+
+impl fmt::Display for AliasReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} address(es) available, {} missing",
+            self.available.len(),
+            self.missing.len()
+        )?;
+        if let Some(addr) = self.missing.first() {
+            write!(f, " (e.g. {addr})")?;
+        }
+
+        if self.can_manage {
+            write!(f, ", the aliases can be created by the tests")
+        } else {
+            write!(
+                f,
+                ", creating the aliases needs CAP_NET_ADMIN on Linux, run the tests as root or \
+                 generate them with the ips.py script"
+            )
+        }
+    }
+}
+
+/// Checks which of the addresses are assigned to a device of the host.
+pub fn check_aliases(addrs: &[IpAddr]) -> AliasReport {
+    let (available, missing) = addrs.iter().partition(|&&addr| is_assigned(addr));
+
+    AliasReport {
+        available,
+        missing,
+        can_manage: cfg!(target_os = "linux") && has_net_admin(),
+    }
+}
+
+/// Only addresses assigned to the host can be bound to.
+fn is_assigned(addr: IpAddr) -> bool {
+    !matches!(
+        TcpListener::bind(SocketAddr::new(addr, 0)),
+        Err(e) if e.kind() == io::ErrorKind::AddrNotAvailable
+    )
+}
+
+/// Reads the effective capabilities of the process.
+fn has_net_admin() -> bool {
+    let Ok(status) = fs::read_to_string("/proc/self/status") else {
+        return false;
+    };
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix("CapEff:"))
+        .and_then(|caps| u64::from_str_radix(caps.trim(), 16).ok())
+        .is_some_and(|caps| caps & (1 << CAP_NET_ADMIN) != 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loopback_is_available() {
+        let unassigned = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1));
+        let report = check_aliases(&[IpAddr::V4(Ipv4Addr::LOCALHOST), unassigned]);
+
+        assert_eq!(report.available, vec![IpAddr::V4(Ipv4Addr::LOCALHOST)]);
+        assert_eq!(report.missing, vec![unassigned]);
+    }
 }
@@ -0,0 +1,406 @@
+//! Ledger objects for synthetic nodes to serve, looked up by their hashes.
+//!
+//! The objects are loaded from JSON fixtures, or from the NuDB data file of a node's database,
+//! e.g. `db/nudb/nudb.dat` of the stateful node's state. Either way they're kept the way rippled
+//! stores them: the hash as the key and the serialized SHAMap node or ledger header as the data.
+
+use std::{collections::HashMap, fs, io, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    protocol::proto::{tm_get_object_by_hash::ObjectType, TmLedgerNode},
+    tools::objects::ObjectStore,
+};
+
+/// The kind of a stored object, as tagged by rippled's node store.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ObjectKind {
+    Unknown,
+    Ledger,
+    StateNode,
+    TransactionNode,
+}
+
+impl ObjectKind {
+    /// Maps rippled's `NodeObjectType`.
+    fn from_node_type(node_type: u8) -> Self {
+        match node_type {
+            1 => Self::Ledger,
+            3 => Self::StateNode,
+            4 => Self::TransactionNode,
+            _ => Self::Unknown,
+        }
+    }
+
+    /// Returns the type the object is queried with in a
+    /// [TmGetObjectByHash](crate::protocol::proto::TmGetObjectByHash).
+    pub fn object_type(self) -> ObjectType {
+        match self {
+            Self::Unknown => ObjectType::OtUnknown,
+            Self::Ledger => ObjectType::OtLedger,
+            Self::StateNode => ObjectType::OtStateNode,
+            Self::TransactionNode => ObjectType::OtTransactionNode,
+        }
+    }
+}
+
+/// A stored object.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LedgerObject {
+    pub kind: ObjectKind,
+    #[serde(with = "hex_bytes")]
+    pub data: Vec<u8>,
+}
+
+/// The JSON fixture format, the hashes and data are hex-encoded.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Fixture {
+    objects: Vec<FixtureObject>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct FixtureObject {
+    #[serde(with = "hex_bytes")]
+    hash: Vec<u8>,
+    #[serde(flatten)]
+    object: LedgerObject,
+}
+
+/// Ledger objects keyed by their hashes.
+#[derive(Debug, Default, Clone)]
+pub struct LedgerStore {
+    objects: HashMap<Vec<u8>, LedgerObject>,
+}
+
+impl LedgerStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads the objects of a JSON fixture, see [save_json](Self::save_json).
+    pub fn load_json(path: impl AsRef<Path>) -> io::Result<Self> {
+        let fixture: Fixture = serde_json::from_slice(&fs::read(path)?)?;
+
+        Ok(Self {
+            objects: fixture
+                .objects
+                .into_iter()
+                .map(|FixtureObject { hash, object }| (hash, object))
+                .collect(),
+        })
+    }
+
+    /// Saves the objects as a JSON fixture, ordered by their hashes.
+    pub fn save_json(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut objects = self
+            .objects
+            .iter()
+            .map(|(hash, object)| FixtureObject {
+                hash: hash.clone(),
+                object: object.clone(),
+            })
+            .collect::<Vec<_>>();
+        objects.sort_by(|a, b| a.hash.cmp(&b.hash));
+
+        fs::write(path, serde_json::to_vec_pretty(&Fixture { objects })?)
+    }
+
+    /// Loads the objects of a NuDB data file, e.g. `db/nudb/nudb.dat`.
+    pub fn load_nudb(path: impl AsRef<Path>) -> io::Result<Self> {
+        let mut store = Self::new();
+        for (hash, value) in nudb::read_records(&fs::read(path)?)? {
+            let (node_type, data) = nudb::decode_node_object(&value)?;
+            store.insert(
+                hash,
+                LedgerObject {
+                    kind: ObjectKind::from_node_type(node_type),
+                    data,
+                },
+            );
+        }
+        Ok(store)
+    }
+
+    /// Stores the object under the hash, which isn't checked against the data.
+    pub fn insert(&mut self, hash: impl Into<Vec<u8>>, object: LedgerObject) {
+        self.objects.insert(hash.into(), object);
+    }
+
+    pub fn get(&self, hash: &[u8]) -> Option<&LedgerObject> {
+        self.objects.get(hash)
+    }
+
+    pub fn len(&self) -> usize {
+        self.objects.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
+
+    /// Iterates over the hashes of the objects of the kind.
+    pub fn hashes(&self, kind: ObjectKind) -> impl Iterator<Item = &[u8]> {
+        self.objects
+            .iter()
+            .filter(move |(_, object)| object.kind == kind)
+            .map(|(hash, _)| hash.as_slice())
+    }
+
+    /// Returns the stored nodes among the hashes, to be sent in a
+    /// [TmLedgerData](crate::protocol::proto::TmLedgerData).
+    ///
+    /// The node IDs, i.e. the positions in the tree, aren't stored, so they're left out.
+    pub fn ledger_nodes<'a>(
+        &self,
+        hashes: impl IntoIterator<Item = &'a [u8]>,
+    ) -> Vec<TmLedgerNode> {
+        hashes
+            .into_iter()
+            .filter_map(|hash| self.get(hash))
+            .map(|object| TmLedgerNode {
+                nodedata: object.data.clone(),
+                nodeid: None,
+            })
+            .collect()
+    }
+}
+
+impl From<&LedgerStore> for ObjectStore {
+    fn from(store: &LedgerStore) -> Self {
+        let mut objects = ObjectStore::new();
+        for (hash, object) in &store.objects {
+            objects.insert(hash.clone(), object.data.clone());
+        }
+        objects
+    }
+}
+
+/// Serializes bytes as hex strings.
+mod hex_bytes {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub(super) fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&hex::encode_upper(bytes))
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<u8>, D::Error> {
+        hex::decode(String::deserialize(deserializer)?).map_err(D::Error::custom)
+    }
+}
+
+/// Reading of NuDB data files, as written by rippled's `NuDBFactory`.
+///
+/// The file starts with a header, followed by data records of a 48-bit size, the key and the
+/// value, and spill records of a zero size, a 16-bit size and a bucket, which are skipped.
+/// The values are node objects compressed by rippled's `nodeobject_compress`.
+mod nudb {
+    use std::io;
+
+    use crate::protocol::shamap::{BRANCH_COUNT, INNER_NODE_PREFIX};
+
+    /// The identifier at the start of the data file.
+    const FILE_TYPE: &[u8] = b"nudb.dat";
+    /// The type, version, UID, application number, key size and reserved bytes.
+    const HEADER_LEN: usize = 8 + 2 + 8 + 8 + 2 + 64;
+    const KEY_SIZE_OFFSET: usize = 8 + 2 + 8 + 8;
+    /// rippled's keys are the 256-bit hashes of the objects.
+    const KEY_SIZE: usize = 32;
+
+    /// The length of the prefix of decoded node objects: an unused ledger index and the type.
+    const NODE_OBJECT_PREFIX_LEN: usize = 9;
+    /// The type of decoded inner nodes, the actual type isn't part of the compressed form. The
+    /// compressed inner nodes leave out their hash prefix, too.
+    const NODE_TYPE_UNKNOWN: u8 = 0;
+    const HASH_LEN: usize = 32;
+
+    fn invalid(message: impl Into<String>) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData, message.into())
+    }
+
+    fn take<'a>(buf: &mut &'a [u8], len: usize) -> io::Result<&'a [u8]> {
+        if buf.len() < len {
+            return Err(invalid("the data ends abruptly"));
+        }
+        let (taken, rest) = buf.split_at(len);
+        *buf = rest;
+        Ok(taken)
+    }
+
+    fn read_uint(buf: &mut &[u8], len: usize) -> io::Result<u64> {
+        Ok(take(buf, len)?
+            .iter()
+            .fold(0, |value, &byte| (value << 8) | u64::from(byte)))
+    }
+
+    /// Returns the key and value of each data record.
+    pub(super) fn read_records(file: &[u8]) -> io::Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        let mut buf = file;
+        let header = take(&mut buf, HEADER_LEN)?;
+        if !header.starts_with(FILE_TYPE) {
+            return Err(invalid("not a NuDB data file"));
+        }
+        let key_size = usize::from(u16::from_be_bytes([
+            header[KEY_SIZE_OFFSET],
+            header[KEY_SIZE_OFFSET + 1],
+        ]));
+        if key_size != KEY_SIZE {
+            return Err(invalid(format!("unexpected key size {key_size}")));
+        }
+
+        let mut records = Vec::new();
+        while !buf.is_empty() {
+            let size = read_uint(&mut buf, 6)? as usize;
+            if size == 0 {
+                let bucket_size = read_uint(&mut buf, 2)? as usize;
+                take(&mut buf, bucket_size)?;
+                continue;
+            }
+            let key = take(&mut buf, key_size)?.to_vec();
+            let value = take(&mut buf, size)?.to_vec();
+            records.push((key, value));
+        }
+        Ok(records)
+    }
+
+    /// Reads a base-128 varint, least significant group first.
+    fn read_varint(buf: &mut &[u8]) -> io::Result<usize> {
+        let mut value = 0usize;
+        for shift in (0..usize::BITS).step_by(7) {
+            let byte = take(buf, 1)?[0];
+            value |= usize::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(invalid("the varint is too long"))
+    }
+
+    /// Decodes a compressed node object into its type and data.
+    pub(super) fn decode_node_object(value: &[u8]) -> io::Result<(u8, Vec<u8>)> {
+        let mut buf = value;
+        let encoded = match read_varint(&mut buf)? {
+            // Uncompressed.
+            0 => buf.to_vec(),
+            // LZ4 compressed.
+            1 => {
+                let size = read_varint(&mut buf)?;
+                lz4_flex::block::decompress(buf, size).map_err(|e| invalid(e.to_string()))?
+            }
+            // An inner node with the mask of its non-empty branches.
+            2 => {
+                let mask = read_uint(&mut buf, 2)? as u16;
+                let mut branches = vec![[0u8; HASH_LEN]; BRANCH_COUNT];
+                for (i, branch) in branches.iter_mut().enumerate() {
+                    if mask & (0x8000 >> i) != 0 {
+                        branch.copy_from_slice(take(&mut buf, HASH_LEN)?);
+                    }
+                }
+                inner_node(&branches.concat())
+            }
+            // An inner node with all of its branches.
+            3 => inner_node(take(&mut buf, BRANCH_COUNT * HASH_LEN)?),
+            kind => return Err(invalid(format!("unknown node object encoding {kind}"))),
+        };
+
+        if encoded.len() < NODE_OBJECT_PREFIX_LEN {
+            return Err(invalid("the node object is too short"));
+        }
+        let node_type = encoded[NODE_OBJECT_PREFIX_LEN - 1];
+        Ok((node_type, encoded[NODE_OBJECT_PREFIX_LEN..].to_vec()))
+    }
+
+    fn inner_node(branches: &[u8]) -> Vec<u8> {
+        let mut encoded = vec![0; NODE_OBJECT_PREFIX_LEN];
+        encoded[NODE_OBJECT_PREFIX_LEN - 1] = NODE_TYPE_UNKNOWN;
+        encoded.extend_from_slice(INNER_NODE_PREFIX);
+        encoded.extend_from_slice(branches);
+        encoded
+    }
+
+    #[cfg(test)]
+    pub(super) fn write_record(file: &mut Vec<u8>, key: &[u8], value: &[u8]) {
+        file.extend_from_slice(&(value.len() as u64).to_be_bytes()[2..]);
+        file.extend_from_slice(key);
+        file.extend_from_slice(value);
+    }
+
+    #[cfg(test)]
+    pub(super) fn header() -> Vec<u8> {
+        let mut header = vec![0; HEADER_LEN];
+        header[..FILE_TYPE.len()].copy_from_slice(FILE_TYPE);
+        header[KEY_SIZE_OFFSET..KEY_SIZE_OFFSET + 2]
+            .copy_from_slice(&(KEY_SIZE as u16).to_be_bytes());
+        header
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn json_fixture_roundtrip() {
+        let mut store = LedgerStore::new();
+        store.insert(
+            [1; 32],
+            LedgerObject {
+                kind: ObjectKind::StateNode,
+                data: vec![1, 2, 3],
+            },
+        );
+
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("fixture.json");
+        store.save_json(&path).unwrap();
+        let loaded = LedgerStore::load_json(&path).unwrap();
+
+        assert_eq!(loaded.get(&[1; 32]), store.get(&[1; 32]));
+        assert_eq!(loaded.hashes(ObjectKind::StateNode).count(), 1);
+    }
+
+    #[test]
+    fn nudb_records_are_decoded() {
+        let mut file = nudb::header();
+
+        // An uncompressed ledger header: the varint, the unused index, the type and the data.
+        let mut ledger = vec![0];
+        ledger.extend_from_slice(&[0; 8]);
+        ledger.push(1);
+        ledger.extend_from_slice(b"header");
+        nudb::write_record(&mut file, &[1; 32], &ledger);
+
+        // A spill record.
+        file.extend_from_slice(&[0; 6]);
+        file.extend_from_slice(&3u16.to_be_bytes());
+        file.extend_from_slice(&[9; 3]);
+
+        // An inner node with only the first branch set.
+        let mut inner = vec![2, 0x80, 0x00];
+        inner.extend_from_slice(&[7; 32]);
+        nudb::write_record(&mut file, &[2; 32], &inner);
+
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("nudb.dat");
+        fs::write(&path, file).unwrap();
+        let store = LedgerStore::load_nudb(&path).unwrap();
+
+        assert_eq!(store.len(), 2);
+        assert_eq!(
+            store.get(&[1; 32]),
+            Some(&LedgerObject {
+                kind: ObjectKind::Ledger,
+                data: b"header".to_vec(),
+            })
+        );
+        let inner = store.get(&[2; 32]).unwrap();
+        assert_eq!(&inner.data[..4], b"MIN\x00");
+        assert_eq!(&inner.data[4..36], &[7; 32]);
+        assert_eq!(inner.data.len(), 4 + 16 * 32);
+    }
+}
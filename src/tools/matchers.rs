@@ -0,0 +1,200 @@
+//! Composable checks for received messages.
+//!
+//! A [Matcher] pairs a check with a description of what it's looking for, so waiting for a
+//! message with [SyntheticNode::expect_matching](crate::tools::synth_node::SyntheticNode::expect_matching)
+//! can report what was expected and what was received instead.
+
+use std::{fmt, net::SocketAddr};
+
+use crate::{
+    protocol::{
+        codecs::message::{BinaryMessage, Payload},
+        proto::{tm_ping::PingType, TmPing},
+    },
+    tools::{endpoints::parse_endpoints, validator::ValidatorList},
+};
+
+type Check = Box<dyn Fn(&Payload) -> bool + Send + Sync>;
+
+/// A check for a received message and a description of the messages it accepts.
+pub struct Matcher {
+    description: String,
+    check: Check,
+}
+
+impl Matcher {
+    /// Creates a matcher, the description should complete the sentence "expected ...".
+    pub fn new(
+        description: impl Into<String>,
+        check: impl Fn(&Payload) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            description: description.into(),
+            check: Box::new(check),
+        }
+    }
+
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    pub fn matches(&self, message: &BinaryMessage) -> bool {
+        self.matches_payload(&message.payload)
+    }
+
+    pub fn matches_payload(&self, payload: &Payload) -> bool {
+        (self.check)(payload)
+    }
+
+    /// Matches messages accepted by both matchers.
+    pub fn and(self, other: Matcher) -> Matcher {
+        Matcher::new(
+            format!("{} and {}", self.description, other.description),
+            move |payload| (self.check)(payload) && (other.check)(payload),
+        )
+    }
+
+    /// Matches messages accepted by either matcher.
+    pub fn or(self, other: Matcher) -> Matcher {
+        Matcher::new(
+            format!("{} or {}", self.description, other.description),
+            move |payload| (self.check)(payload) || (other.check)(payload),
+        )
+    }
+}
+
+impl fmt::Display for Matcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.description)
+    }
+}
+
+/// Matches any message.
+pub fn any() -> Matcher {
+    Matcher::new("any message", |_| true)
+}
+
+/// Matches messages of the kind, see [Payload::name].
+pub fn is_kind(name: &'static str) -> Matcher {
+    Matcher::new(format!("a {name}"), move |payload| payload.name() == name)
+}
+
+/// Matches ping requests.
+pub fn is_ping() -> Matcher {
+    Matcher::new(
+        "a ping",
+        |payload| matches!(payload, Payload::TmPing(TmPing { r#type, .. }) if *r#type == PingType::PtPing as i32),
+    )
+}
+
+/// Matches a pong answering the ping with the sequence number.
+pub fn is_pong_with_seq(seq: u32) -> Matcher {
+    Matcher::new(format!("a pong with seq {seq}"), move |payload| {
+        matches!(
+            payload,
+            // proto file defines 'pong' message as `TmPing` with `r#type` set to [PingType::PtPong]
+            Payload::TmPing(TmPing { r#type, seq: Some(s), .. })
+                if *s == seq && *r#type == PingType::PtPong as i32
+        )
+    })
+}
+
+/// Matches manifests messages carrying at least one non-empty manifest.
+pub fn is_non_empty_manifests() -> Matcher {
+    Matcher::new("a non-empty TmManifests", |payload| {
+        matches!(payload, Payload::TmManifests(manifests)
+            if manifests.list.first().is_some_and(|manifest| !manifest.stobject.is_empty()))
+    })
+}
+
+/// Matches endpoints messages advertising the address, with any hop count.
+pub fn is_endpoints_containing(addr: SocketAddr) -> Matcher {
+    Matcher::new(
+        format!("a TmEndpoints advertising {addr}"),
+        move |payload| {
+            matches!(payload, Payload::TmEndpoints(endpoints)
+            if parse_endpoints(endpoints).iter().any(|endpoint| endpoint.addr == addr))
+        },
+    )
+}
+
+/// Matches validator list messages (v1 or v2) with a list satisfying the check.
+pub fn is_validator_list_where(
+    description: &str,
+    check: impl Fn(&ValidatorList) -> bool + Send + Sync + 'static,
+) -> Matcher {
+    Matcher::new(format!("a validator list {description}"), move |payload| {
+        let blobs: Vec<&[u8]> = match payload {
+            Payload::TmValidatorList(list) => vec![&list.blob],
+            Payload::TmValidatorListCollection(collection) => collection
+                .blobs
+                .iter()
+                .map(|blob_info| blob_info.blob.as_slice())
+                .collect(),
+            _ => return false,
+        };
+        blobs
+            .into_iter()
+            .filter_map(|blob| ValidatorList::from_blob(blob).ok())
+            .any(|list| check(&list))
+    })
+}
+
+/// Matches validator list messages (v1 or v2) listing the hex-encoded master public key.
+pub fn is_validator_list_containing(public_key_hex: &str) -> Matcher {
+    let key = public_key_hex.to_uppercase();
+    is_validator_list_where(&format!("containing {key}"), move |list| {
+        list.validators
+            .iter()
+            .any(|validator| validator.validation_public_key.to_uppercase() == key)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::proto::TmValidatorList;
+
+    fn ping(r#type: PingType, seq: u32) -> Payload {
+        Payload::TmPing(TmPing {
+            r#type: r#type as i32,
+            seq: Some(seq),
+            ping_time: None,
+            net_time: None,
+        })
+    }
+
+    #[test]
+    fn matchers_compose() {
+        let matcher = is_pong_with_seq(1).or(is_pong_with_seq(2));
+        assert_eq!(
+            matcher.to_string(),
+            "a pong with seq 1 or a pong with seq 2"
+        );
+        assert!(matcher.matches_payload(&ping(PingType::PtPong, 2)));
+        assert!(!matcher.matches_payload(&ping(PingType::PtPong, 3)));
+        assert!(!matcher.matches_payload(&ping(PingType::PtPing, 1)));
+
+        let matcher = is_kind("TmPing").and(is_ping());
+        assert!(matcher.matches_payload(&ping(PingType::PtPing, 1)));
+        assert!(!matcher.matches_payload(&ping(PingType::PtPong, 1)));
+    }
+
+    #[test]
+    fn validator_list_is_decoded() {
+        use base64::{engine::general_purpose::STANDARD, Engine};
+
+        use crate::tools::validator::Validator;
+
+        let list = ValidatorList::new(1, vec![Validator::new("ED0A0B", &[1, 2, 3])]);
+        let payload = Payload::TmValidatorList(TmValidatorList {
+            manifest: vec![],
+            blob: STANDARD.encode(list.to_json()).into_bytes(),
+            signature: vec![],
+            version: 1,
+        });
+
+        assert!(is_validator_list_containing("ed0a0b").matches_payload(&payload));
+        assert!(!is_validator_list_containing("ED0A0C").matches_payload(&payload));
+    }
+}
@@ -0,0 +1,124 @@
+//! Counts of the messages a synthetic node receives, per peer and message type.
+
+use std::{collections::HashMap, net::SocketAddr};
+
+use crate::protocol::proto::MessageType;
+
+/// The number of messages received and their size on the wire, headers included.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MessageCount {
+    pub count: u64,
+    pub bytes: u64,
+}
+
+impl MessageCount {
+    fn add(&mut self, other: MessageCount) {
+        self.count += other.count;
+        self.bytes += other.bytes;
+    }
+}
+
+/// The consensus messages signed by a validator, see
+/// [SyntheticNode::count_validator_messages](crate::tools::synth_node::SyntheticNode::count_validator_messages).
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ValidatorMessageCount {
+    /// The number of [TmProposeSet](crate::protocol::proto::TmProposeSet) messages.
+    pub proposals: usize,
+    /// The number of [TmValidation](crate::protocol::proto::TmValidation) messages.
+    pub validations: usize,
+}
+
+impl ValidatorMessageCount {
+    pub fn total(&self) -> usize {
+        self.proposals + self.validations
+    }
+}
+
+/// The messages received from each peer, keyed by the message type of the header.
+///
+/// Types unknown to the codec are counted too, under their raw value.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct MessageStats {
+    peers: HashMap<SocketAddr, HashMap<u16, MessageCount>>,
+}
+
+impl MessageStats {
+    pub(crate) fn record(&mut self, source: SocketAddr, message_type: u16, bytes: u32) {
+        self.peers
+            .entry(source)
+            .or_default()
+            .entry(message_type)
+            .or_default()
+            .add(MessageCount {
+                count: 1,
+                bytes: bytes.into(),
+            });
+    }
+
+    /// Returns the counts of the messages received from the peer, by raw message type.
+    pub fn peer(&self, addr: SocketAddr) -> Option<&HashMap<u16, MessageCount>> {
+        self.peers.get(&addr)
+    }
+
+    /// Returns the messages of the type received from the peer.
+    pub fn get(&self, addr: SocketAddr, message_type: MessageType) -> MessageCount {
+        self.peer(addr)
+            .and_then(|counts| counts.get(&(message_type as u16)))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Returns the messages of the type received from all the peers.
+    pub fn total(&self, message_type: MessageType) -> MessageCount {
+        let mut total = MessageCount::default();
+        for counts in self.peers.values() {
+            if let Some(count) = counts.get(&(message_type as u16)) {
+                total.add(*count);
+            }
+        }
+        total
+    }
+
+    /// Returns the counts of all the messages received, by raw message type.
+    pub fn totals(&self) -> HashMap<u16, MessageCount> {
+        let mut totals = HashMap::<u16, MessageCount>::new();
+        for (&message_type, &count) in self.peers.values().flatten() {
+            totals.entry(message_type).or_default().add(count);
+        }
+        totals
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_are_kept_per_peer_and_type() {
+        let a = "127.0.0.1:1".parse().unwrap();
+        let b = "127.0.0.1:2".parse().unwrap();
+
+        let mut stats = MessageStats::default();
+        stats.record(a, MessageType::MtPing as u16, 10);
+        stats.record(a, MessageType::MtPing as u16, 12);
+        stats.record(b, MessageType::MtPing as u16, 10);
+        stats.record(b, 999, 100);
+
+        assert_eq!(
+            stats.get(a, MessageType::MtPing),
+            MessageCount {
+                count: 2,
+                bytes: 22
+            }
+        );
+        assert_eq!(stats.get(b, MessageType::MtEndpoints), Default::default());
+        assert_eq!(
+            stats.total(MessageType::MtPing),
+            MessageCount {
+                count: 3,
+                bytes: 32
+            }
+        );
+        assert_eq!(stats.totals()[&999].bytes, 100);
+    }
+}
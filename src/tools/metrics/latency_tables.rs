@@ -0,0 +1,138 @@
+//! Latency statistics tables, with latencies recorded in microseconds and shown in milliseconds.
+
+use std::{fmt, io, ops::AddAssign};
+
+use hdrhistogram::Histogram;
+use tabled::{Table, Tabled};
+use ziggurat_core_metrics::tables::fmt_table;
+
+/// Latency statistics of a single test run.
+#[derive(Tabled, Default, Debug, Clone)]
+pub struct LatencyRequestStats {
+    #[tabled(rename = "peers")]
+    pub peers: u16,
+    #[tabled(rename = "requests")]
+    pub requests: u16,
+    #[tabled(rename = "min (ms)", display_with = "fmt_us_as_ms")]
+    pub latency_min: u64,
+    #[tabled(rename = "max (ms)", display_with = "fmt_us_as_ms")]
+    pub latency_max: u64,
+    #[tabled(rename = "std dev (ms)", display_with = "fmt_f64_us_as_ms")]
+    pub latency_std_dev: f64,
+    #[tabled(rename = "10% (ms)", display_with = "fmt_us_as_ms")]
+    pub latency_percentile_10: u64,
+    #[tabled(rename = "50% (ms)", display_with = "fmt_us_as_ms")]
+    pub latency_percentile_50: u64,
+    #[tabled(rename = "75% (ms)", display_with = "fmt_us_as_ms")]
+    pub latency_percentile_75: u64,
+    #[tabled(rename = "90% (ms)", display_with = "fmt_us_as_ms")]
+    pub latency_percentile_90: u64,
+    #[tabled(rename = "99% (ms)", display_with = "fmt_us_as_ms")]
+    pub latency_percentile_99: u64,
+    #[tabled(rename = "completion %", display_with = "fmt_f64")]
+    pub completion: f64,
+    #[tabled(rename = "time (s)", display_with = "fmt_f64")]
+    pub time: f64,
+    #[tabled(rename = "requests/s", display_with = "fmt_f64")]
+    pub throughput: f64,
+    #[tabled(rename = "timeouts")]
+    pub timeouts: u64,
+    #[tabled(rename = "conn errors")]
+    pub connection_errors: u64,
+    #[tabled(rename = "broken pipes")]
+    pub broken_pipes: u64,
+}
+
+/// Failed requests of a test run, by cause.
+#[derive(Default, Debug, Clone, Copy)]
+pub struct RequestErrors {
+    /// Requests without a reply in time.
+    pub timeouts: u64,
+    /// Peers which couldn't connect or were disconnected by the node.
+    pub connection_errors: u64,
+    /// Requests which couldn't be written as the node closed the connection.
+    pub broken_pipes: u64,
+}
+
+impl RequestErrors {
+    /// Counts the error returned by
+    /// [SyntheticNode::request_reply](crate::tools::synth_node::SyntheticNode::request_reply).
+    pub fn record(&mut self, error: &io::Error) {
+        match error.kind() {
+            io::ErrorKind::TimedOut => self.timeouts += 1,
+            io::ErrorKind::BrokenPipe => self.broken_pipes += 1,
+            _ => self.connection_errors += 1,
+        }
+    }
+}
+
+impl AddAssign for RequestErrors {
+    fn add_assign(&mut self, other: Self) {
+        self.timeouts += other.timeouts;
+        self.connection_errors += other.connection_errors;
+        self.broken_pipes += other.broken_pipes;
+    }
+}
+
+impl LatencyRequestStats {
+    /// Creates the statistics from latencies recorded in microseconds.
+    pub fn new(peers: u16, requests: u16, latencies: Histogram<u64>, time: f64) -> Self {
+        let total = peers as f64 * requests as f64;
+        let replies = latencies.len() as f64;
+
+        Self {
+            peers,
+            requests,
+            latency_min: latencies.min(),
+            latency_max: latencies.max(),
+            latency_std_dev: latencies.stdev(),
+            latency_percentile_10: latencies.value_at_quantile(0.10),
+            latency_percentile_50: latencies.value_at_quantile(0.50),
+            latency_percentile_75: latencies.value_at_quantile(0.75),
+            latency_percentile_90: latencies.value_at_quantile(0.90),
+            latency_percentile_99: latencies.value_at_quantile(0.99),
+            completion: replies / total * 100.0,
+            time,
+            throughput: replies / time,
+            ..Default::default()
+        }
+    }
+
+    /// Sets the error breakdown, explaining a lower completion.
+    pub fn with_errors(mut self, errors: RequestErrors) -> Self {
+        self.timeouts = errors.timeouts;
+        self.connection_errors = errors.connection_errors;
+        self.broken_pipes = errors.broken_pipes;
+        self
+    }
+}
+
+/// A table of latency statistics, one row per test run.
+#[derive(Default)]
+pub struct LatencyRequestsTable {
+    rows: Vec<LatencyRequestStats>,
+}
+
+impl LatencyRequestsTable {
+    pub fn add_row(&mut self, row: LatencyRequestStats) {
+        self.rows.push(row);
+    }
+}
+
+impl fmt::Display for LatencyRequestsTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&fmt_table(Table::new(&self.rows)))
+    }
+}
+
+fn fmt_us_as_ms(us: &u64) -> String {
+    fmt_f64_us_as_ms(&(*us as f64))
+}
+
+fn fmt_f64_us_as_ms(us: &f64) -> String {
+    format!("{:.3}", us / 1000.0)
+}
+
+fn fmt_f64(value: &f64) -> String {
+    format!("{value:.2}")
+}
@@ -0,0 +1,166 @@
+//! Ledger request statistics, showing how the node copes with and throttles floods of
+//! [TmGetLedger](crate::protocol::proto::TmGetLedger) requests.
+
+use std::{collections::BTreeMap, fmt, io};
+
+use tabled::{Table, Tabled};
+use ziggurat_core_metrics::tables::fmt_table;
+
+use crate::{protocol::proto::TmLedgerData, tools::metrics::latency_tables::RequestErrors};
+
+/// The requests of a single synthetic peer and how the node answered them.
+#[derive(Default, Debug, Clone)]
+pub struct PeerLedgerRequests {
+    /// Replies carrying ledger nodes.
+    pub data_replies: u64,
+    /// Replies refusing the request, e.g. as the node doesn't have the ledger.
+    pub error_replies: u64,
+    /// Replies which didn't echo the cookie of the pending request, e.g. late replies to timed
+    /// out requests.
+    pub cookie_mismatches: u64,
+    /// Failed requests, by query depth.
+    pub errors: BTreeMap<u32, RequestErrors>,
+    /// The first request which failed, the node is likely throttling the peer from then on.
+    pub first_failure: Option<u16>,
+}
+
+impl PeerLedgerRequests {
+    /// Counts the reply to the request with the cookie.
+    pub fn record_reply(&mut self, cookie: u32, reply: &TmLedgerData) {
+        if reply.request_cookie != Some(cookie) {
+            self.cookie_mismatches += 1;
+        }
+        match reply.error {
+            Some(_) => self.error_replies += 1,
+            None => self.data_replies += 1,
+        }
+    }
+
+    /// Counts the error returned for the request, see [RequestErrors::record].
+    pub fn record_error(&mut self, request: u16, depth: u32, error: &io::Error) {
+        self.errors.entry(depth).or_default().record(error);
+        self.first_failure.get_or_insert(request);
+    }
+
+    /// Returns the failed requests of all the depths.
+    pub fn total_errors(&self) -> RequestErrors {
+        let mut total = RequestErrors::default();
+        for errors in self.errors.values() {
+            total += *errors;
+        }
+        total
+    }
+}
+
+/// Ledger request statistics of a single test run.
+#[derive(Tabled, Default, Debug, Clone)]
+pub struct LedgerRequestStats {
+    #[tabled(rename = "peers")]
+    pub peers: u16,
+    #[tabled(rename = "requests")]
+    pub requests: u16,
+    #[tabled(rename = "data replies")]
+    pub data_replies: u64,
+    #[tabled(rename = "error replies")]
+    pub error_replies: u64,
+    #[tabled(rename = "cookie mismatches")]
+    pub cookie_mismatches: u64,
+    #[tabled(rename = "timeouts")]
+    pub timeouts: u64,
+    #[tabled(rename = "conn errors")]
+    pub connection_errors: u64,
+    #[tabled(rename = "broken pipes")]
+    pub broken_pipes: u64,
+    #[tabled(rename = "throttled peers")]
+    pub throttled_peers: u16,
+    #[tabled(rename = "first failure", display_with = "fmt_request")]
+    pub first_failure: Option<u16>,
+}
+
+impl LedgerRequestStats {
+    /// Sums up the requests of the peers, each sending `requests` requests.
+    pub fn new(requests: u16, peers: &[PeerLedgerRequests]) -> Self {
+        let mut stats = Self {
+            peers: peers.len() as u16,
+            requests,
+            ..Default::default()
+        };
+        for peer in peers {
+            let errors = peer.total_errors();
+            stats.data_replies += peer.data_replies;
+            stats.error_replies += peer.error_replies;
+            stats.cookie_mismatches += peer.cookie_mismatches;
+            stats.timeouts += errors.timeouts;
+            stats.connection_errors += errors.connection_errors;
+            stats.broken_pipes += errors.broken_pipes;
+            if peer.first_failure.is_some() {
+                stats.throttled_peers += 1;
+            }
+            stats.first_failure = match (stats.first_failure, peer.first_failure) {
+                (Some(first), Some(failure)) => Some(first.min(failure)),
+                (first, failure) => first.or(failure),
+            };
+        }
+        stats
+    }
+}
+
+/// A table of ledger request statistics, one row per test run.
+#[derive(Default)]
+pub struct LedgerRequestsTable {
+    rows: Vec<LedgerRequestStats>,
+}
+
+impl LedgerRequestsTable {
+    pub fn add_row(&mut self, row: LedgerRequestStats) {
+        self.rows.push(row);
+    }
+}
+
+impl fmt::Display for LedgerRequestsTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&fmt_table(Table::new(&self.rows)))
+    }
+}
+
+fn fmt_request(request: &Option<u16>) -> String {
+    request.map_or_else(|| "-".to_owned(), |request| request.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::proto::TmReplyError;
+
+    #[test]
+    fn peers_are_summed_up() {
+        let reply = |cookie, error: Option<TmReplyError>| TmLedgerData {
+            request_cookie: Some(cookie),
+            error: error.map(|error| error as i32),
+            ..Default::default()
+        };
+        let timed_out = io::Error::from(io::ErrorKind::TimedOut);
+
+        let mut throttled = PeerLedgerRequests::default();
+        throttled.record_reply(1, &reply(1, None));
+        throttled.record_error(1, 1, &timed_out);
+        // The late reply to the timed out request.
+        throttled.record_reply(3, &reply(2, None));
+        throttled.record_error(3, 1, &io::Error::from(io::ErrorKind::BrokenPipe));
+
+        let mut refused = PeerLedgerRequests::default();
+        refused.record_reply(1, &reply(1, Some(TmReplyError::ReNoLedger)));
+        refused.record_error(2, 0, &timed_out);
+
+        let stats = LedgerRequestStats::new(4, &[throttled, refused, Default::default()]);
+        assert_eq!(stats.peers, 3);
+        assert_eq!(stats.data_replies, 2);
+        assert_eq!(stats.error_replies, 1);
+        assert_eq!(stats.cookie_mismatches, 1);
+        assert_eq!(stats.timeouts, 2);
+        assert_eq!(stats.broken_pipes, 1);
+        assert_eq!(stats.connection_errors, 0);
+        assert_eq!(stats.throttled_peers, 2);
+        assert_eq!(stats.first_failure, Some(1));
+    }
+}
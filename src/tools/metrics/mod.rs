@@ -0,0 +1,9 @@
+//! Metrics recording and reporting for the performance tests.
+//!
+//! Histograms are recorded in microseconds, sub-millisecond latencies would otherwise be lost.
+
+pub mod latency_tables;
+pub mod ledger_requests;
+pub mod recorder;
+pub mod resource_monitor;
+pub mod resources;
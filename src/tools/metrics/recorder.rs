@@ -0,0 +1,254 @@
+//! A global metrics recorder backed by HDR histograms, collecting metrics into scopes.
+
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, Once, OnceLock, Weak,
+    },
+    time::Duration,
+};
+
+use hdrhistogram::Histogram;
+use metrics::{Counter, CounterFn, Gauge, HistogramFn, Key, KeyName, Recorder, SharedString, Unit};
+
+/// The number of significant decimal digits kept by the histograms.
+const HISTOGRAM_SIGFIGS: u8 = 3;
+
+/// Converts the duration to the unit histograms are recorded in.
+pub fn duration_as_us(duration: Duration) -> f64 {
+    duration.as_micros() as f64
+}
+
+/// A scope collecting the metrics registered with it.
+///
+/// The metrics crate only supports a single global recorder, which forwards each metric to the
+/// scope it was registered with and drops unregistered ones. Scopes are independent, so tests
+/// running in the same process don't clear or mix each other's metrics as long as they register
+/// different names. The names are released when the scope is dropped.
+#[derive(Default)]
+pub struct TestMetrics {
+    counters: Mutex<HashMap<String, Arc<CounterHandle>>>,
+    histograms: Mutex<HashMap<String, Arc<HistogramHandle>>>,
+}
+
+impl TestMetrics {
+    /// Collects the counter's increments in this scope.
+    ///
+    /// Panics if the name is already registered with another live scope.
+    pub fn register_counter(&self, name: impl Into<String>) {
+        let name = name.into();
+        let handle = Arc::<CounterHandle>::default();
+        register(&install_recorder().counters, &name, &handle);
+        self.counters.lock().unwrap().insert(name, handle);
+    }
+
+    /// Collects the histogram's values in this scope.
+    ///
+    /// Panics if the name is already registered with another live scope.
+    pub fn register_histogram(&self, name: impl Into<String>) {
+        let name = name.into();
+        let handle = Arc::<HistogramHandle>::default();
+        register(&install_recorder().histograms, &name, &handle);
+        self.histograms.lock().unwrap().insert(name, handle);
+    }
+
+    /// Returns a copy of the metrics recorded so far.
+    pub fn take_snapshot(&self) -> Snapshot {
+        let counters = self
+            .counters
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(name, counter)| (name.clone(), counter.0.load(Ordering::Acquire)))
+            .collect();
+        let histograms = self
+            .histograms
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(name, histogram)| (name.clone(), histogram.0.lock().unwrap().clone()))
+            .collect();
+
+        Snapshot {
+            counters,
+            histograms,
+        }
+    }
+}
+
+impl Drop for TestMetrics {
+    fn drop(&mut self) {
+        let recorder = install_recorder();
+        unregister(&recorder.counters, self.counters.get_mut().unwrap());
+        unregister(&recorder.histograms, self.histograms.get_mut().unwrap());
+    }
+}
+
+/// The recorded metrics at a point in time.
+pub struct Snapshot {
+    counters: HashMap<String, u64>,
+    histograms: HashMap<String, Histogram<u64>>,
+}
+
+impl Snapshot {
+    /// Returns the counter's value, zero if it was never incremented.
+    pub fn get_counter(&self, name: &str) -> u64 {
+        self.counters.get(name).copied().unwrap_or_default()
+    }
+
+    /// Returns the histogram, if it was registered.
+    pub fn construct_histogram(&self, name: &str) -> Option<Histogram<u64>> {
+        self.histograms.get(name).cloned()
+    }
+}
+
+// The recorder can only be installed once per process, so it's shared by all the scopes.
+fn install_recorder() -> &'static HdrRecorder {
+    static RECORDER: OnceLock<HdrRecorder> = OnceLock::new();
+    static INSTALL: Once = Once::new();
+
+    let recorder = RECORDER.get_or_init(HdrRecorder::default);
+    INSTALL.call_once(|| {
+        metrics::set_recorder(recorder).expect("unable to install the metrics recorder");
+    });
+    recorder
+}
+
+type Registry<T> = Mutex<HashMap<String, Weak<T>>>;
+
+fn register<T>(registry: &Registry<T>, name: &str, handle: &Arc<T>) {
+    let mut registry = registry.lock().unwrap();
+    if registry
+        .get(name)
+        .is_some_and(|registered| registered.strong_count() > 0)
+    {
+        panic!("the metric {name} is already registered by another scope");
+    }
+    registry.insert(name.to_owned(), Arc::downgrade(handle));
+}
+
+fn unregister<T>(registry: &Registry<T>, handles: &HashMap<String, Arc<T>>) {
+    let mut registry = registry.lock().unwrap();
+    for (name, handle) in handles {
+        if registry
+            .get(name)
+            .is_some_and(|registered| registered.ptr_eq(&Arc::downgrade(handle)))
+        {
+            registry.remove(name);
+        }
+    }
+}
+
+fn lookup<T>(registry: &Registry<T>, key: &Key) -> Option<Arc<T>> {
+    registry
+        .lock()
+        .unwrap()
+        .get(key.name())
+        .and_then(Weak::upgrade)
+}
+
+/// Forwards the metrics to the scopes they're registered with.
+#[derive(Default)]
+struct HdrRecorder {
+    counters: Registry<CounterHandle>,
+    histograms: Registry<HistogramHandle>,
+}
+
+impl Recorder for HdrRecorder {
+    fn describe_counter(&self, _key: KeyName, _unit: Option<Unit>, _description: SharedString) {}
+
+    fn describe_gauge(&self, _key: KeyName, _unit: Option<Unit>, _description: SharedString) {}
+
+    fn describe_histogram(&self, _key: KeyName, _unit: Option<Unit>, _description: SharedString) {}
+
+    fn register_counter(&self, key: &Key) -> Counter {
+        lookup(&self.counters, key).map_or_else(Counter::noop, Counter::from_arc)
+    }
+
+    fn register_gauge(&self, _key: &Key) -> Gauge {
+        // Gauges aren't used by any of the tests.
+        Gauge::noop()
+    }
+
+    fn register_histogram(&self, key: &Key) -> metrics::Histogram {
+        lookup(&self.histograms, key)
+            .map_or_else(metrics::Histogram::noop, metrics::Histogram::from_arc)
+    }
+}
+
+#[derive(Default)]
+struct CounterHandle(AtomicU64);
+
+impl CounterFn for CounterHandle {
+    fn increment(&self, value: u64) {
+        self.0.fetch_add(value, Ordering::Release);
+    }
+
+    fn absolute(&self, value: u64) {
+        self.0.fetch_max(value, Ordering::AcqRel);
+    }
+}
+
+struct HistogramHandle(Mutex<Histogram<u64>>);
+
+impl Default for HistogramHandle {
+    fn default() -> Self {
+        // Auto-resizing, so there's no upper bound on the recorded values.
+        let histogram = Histogram::new(HISTOGRAM_SIGFIGS).expect("invalid histogram precision");
+        Self(Mutex::new(histogram))
+    }
+}
+
+impl HistogramFn for HistogramHandle {
+    fn record(&self, value: f64) {
+        self.0
+            .lock()
+            .unwrap()
+            .saturating_record(value.round() as u64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn histograms_keep_sub_millisecond_values() {
+        let handle = HistogramHandle::default();
+        for us in [120, 250, 480] {
+            handle.record(duration_as_us(Duration::from_micros(us)));
+        }
+
+        let histogram = handle.0.lock().unwrap();
+        assert_eq!(histogram.len(), 3);
+        assert_eq!(histogram.min(), 120);
+        assert!(histogram.max() >= 480 && histogram.max() < 481);
+    }
+
+    #[test]
+    fn scopes_collect_their_own_metrics() {
+        let first = TestMetrics::default();
+        let second = TestMetrics::default();
+        first.register_counter("recorder_test_first");
+        second.register_counter("recorder_test_second");
+
+        metrics::counter!("recorder_test_first", 1);
+        metrics::counter!("recorder_test_second", 2);
+        metrics::counter!("recorder_test_unregistered", 3);
+
+        assert_eq!(first.take_snapshot().get_counter("recorder_test_first"), 1);
+        assert_eq!(first.take_snapshot().get_counter("recorder_test_second"), 0);
+        assert_eq!(
+            second.take_snapshot().get_counter("recorder_test_second"),
+            2
+        );
+
+        // The name can be registered again once its scope is dropped.
+        drop(first);
+        let third = TestMetrics::default();
+        third.register_counter("recorder_test_first");
+        metrics::counter!("recorder_test_first", 1);
+        assert_eq!(third.take_snapshot().get_counter("recorder_test_first"), 1);
+    }
+}
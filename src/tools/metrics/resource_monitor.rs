@@ -0,0 +1,205 @@
+//! Background sampling of the node's resource usage while a test runs, summarized per test run
+//! to correlate latencies with the node running out of resources.
+
+use std::{fmt, time::Duration};
+
+use tabled::{Table, Tabled};
+use tokio::{
+    sync::oneshot,
+    task::JoinHandle,
+    time::{interval, Instant, MissedTickBehavior},
+};
+use ziggurat_core_metrics::tables::fmt_table;
+
+use crate::tools::metrics::resources::ResourceSample;
+
+/// How often the node is sampled by default.
+pub const DEFAULT_SAMPLE_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Samples a process's resource usage until stopped.
+pub struct ResourceMonitor {
+    stop: oneshot::Sender<()>,
+    sampler: JoinHandle<Vec<(Duration, ResourceSample)>>,
+}
+
+impl ResourceMonitor {
+    /// Starts sampling the process every interval, the first sample is taken right away.
+    pub fn start(pid: u32, sample_interval: Duration) -> Self {
+        let (stop, mut stopped) = oneshot::channel();
+        let sampler = tokio::spawn(async move {
+            let start = Instant::now();
+            let mut ticks = interval(sample_interval);
+            ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+            let mut samples = Vec::new();
+            loop {
+                tokio::select! {
+                    _ = &mut stopped => break,
+                    _ = ticks.tick() => {
+                        // The process is gone, there's nothing left to sample.
+                        let Ok(sample) = ResourceSample::take(pid) else {
+                            break;
+                        };
+                        samples.push((start.elapsed(), sample));
+                    }
+                }
+            }
+            samples
+        });
+
+        Self { stop, sampler }
+    }
+
+    /// Stops sampling and summarizes the samples.
+    pub async fn stop(self) -> ResourceUsageStats {
+        let _ = self.stop.send(());
+        let samples = self.sampler.await.unwrap_or_default();
+        ResourceUsageStats::new(&samples)
+    }
+}
+
+/// Resource usage of the node during a single test run.
+#[derive(Tabled, Default, Debug, Clone, PartialEq)]
+pub struct ResourceUsageStats {
+    #[tabled(rename = "peers")]
+    pub peers: u16,
+    #[tabled(rename = "samples")]
+    pub samples: usize,
+    #[tabled(rename = "avg CPU %", display_with = "fmt_f64")]
+    pub cpu_avg: f64,
+    #[tabled(rename = "max CPU %", display_with = "fmt_f64")]
+    pub cpu_max: f64,
+    #[tabled(rename = "avg RSS (MiB)", display_with = "fmt_kib_as_mib")]
+    pub rss_avg_kib: u64,
+    #[tabled(rename = "max RSS (MiB)", display_with = "fmt_kib_as_mib")]
+    pub rss_max_kib: u64,
+    #[tabled(rename = "max open fds")]
+    pub open_fds_max: usize,
+}
+
+impl ResourceUsageStats {
+    /// Summarizes the samples taken at the given times since the start of the test run.
+    ///
+    /// The CPU usage is the share of a core used between consecutive samples, so it may exceed
+    /// 100% for a node running on several cores.
+    pub fn new(samples: &[(Duration, ResourceSample)]) -> Self {
+        let cpu_usages = samples
+            .windows(2)
+            .filter_map(|pair| {
+                let [(prev_time, prev), (time, sample)] = pair else {
+                    unreachable!();
+                };
+                let wall = time.checked_sub(*prev_time)?.as_secs_f64();
+                let cpu = sample.cpu_time.checked_sub(prev.cpu_time)?.as_secs_f64();
+                (wall > 0.0).then(|| cpu / wall * 100.0)
+            })
+            .collect::<Vec<_>>();
+        let rss_total = samples
+            .iter()
+            .map(|(_, sample)| sample.rss_kib)
+            .sum::<u64>();
+
+        Self {
+            peers: 0,
+            samples: samples.len(),
+            cpu_avg: average(&cpu_usages),
+            cpu_max: cpu_usages.iter().copied().fold(0.0, f64::max),
+            rss_avg_kib: rss_total
+                .checked_div(samples.len() as u64)
+                .unwrap_or_default(),
+            rss_max_kib: samples
+                .iter()
+                .map(|(_, sample)| sample.rss_kib)
+                .max()
+                .unwrap_or_default(),
+            open_fds_max: samples
+                .iter()
+                .map(|(_, sample)| sample.open_fds)
+                .max()
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Sets the number of peers of the test run, shown in the first column.
+    pub fn with_peers(mut self, peers: u16) -> Self {
+        self.peers = peers;
+        self
+    }
+}
+
+/// A table of resource usage statistics, one row per test run.
+#[derive(Default)]
+pub struct ResourceUsageTable {
+    rows: Vec<ResourceUsageStats>,
+}
+
+impl ResourceUsageTable {
+    pub fn add_row(&mut self, row: ResourceUsageStats) {
+        self.rows.push(row);
+    }
+}
+
+impl fmt::Display for ResourceUsageTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&fmt_table(Table::new(&self.rows)))
+    }
+}
+
+fn average(values: &[f64]) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+fn fmt_kib_as_mib(kib: &u64) -> String {
+    format!("{:.1}", *kib as f64 / 1024.0)
+}
+
+fn fmt_f64(value: &f64) -> String {
+    format!("{value:.2}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(secs: u64, cpu_ms: u64, rss_kib: u64, open_fds: usize) -> (Duration, ResourceSample) {
+        (
+            Duration::from_secs(secs),
+            ResourceSample {
+                rss_kib,
+                open_fds,
+                cpu_time: Duration::from_millis(cpu_ms),
+            },
+        )
+    }
+
+    #[test]
+    fn samples_are_summarized() {
+        let stats = ResourceUsageStats::new(&[
+            sample(0, 0, 1000, 10),
+            sample(1, 500, 3000, 30),
+            sample(2, 1500, 2000, 20),
+        ]);
+
+        assert_eq!(stats.samples, 3);
+        assert_eq!(stats.cpu_avg, 75.0);
+        assert_eq!(stats.cpu_max, 100.0);
+        assert_eq!(stats.rss_avg_kib, 2000);
+        assert_eq!(stats.rss_max_kib, 3000);
+        assert_eq!(stats.open_fds_max, 30);
+
+        assert_eq!(ResourceUsageStats::new(&[]), ResourceUsageStats::default());
+    }
+
+    #[tokio::test]
+    async fn own_process_is_monitored() {
+        let monitor = ResourceMonitor::start(std::process::id(), Duration::from_millis(10));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let stats = monitor.stop().await;
+        assert!(stats.samples > 1);
+        assert!(stats.rss_max_kib > 0);
+    }
+}
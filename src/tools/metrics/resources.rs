@@ -0,0 +1,74 @@
+//! Sampling of a process's resource usage, read from `/proc` (Linux only).
+
+use std::{fs, io, time::Duration};
+
+/// The kernel's clock tick rate used in `/proc/<pid>/stat`, fixed at 100 on Linux.
+const CLOCK_TICKS_PER_SEC: u64 = 100;
+
+/// Resource usage of a process at a point in time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResourceSample {
+    /// Resident set size in KiB.
+    pub rss_kib: u64,
+    /// The number of open file descriptors, including sockets.
+    pub open_fds: usize,
+    /// CPU time used since the process started, in both user and kernel mode.
+    pub cpu_time: Duration,
+}
+
+impl ResourceSample {
+    /// Samples the resource usage of the process.
+    pub fn take(pid: u32) -> io::Result<Self> {
+        let proc_dir = format!("/proc/{pid}");
+
+        let status = fs::read_to_string(format!("{proc_dir}/status"))?;
+        let rss_kib = parse_rss_kib(&status).ok_or_else(|| invalid_data("VmRSS"))?;
+
+        let open_fds = fs::read_dir(format!("{proc_dir}/fd"))?.count();
+
+        let stat = fs::read_to_string(format!("{proc_dir}/stat"))?;
+        let cpu_ticks = parse_cpu_ticks(&stat).ok_or_else(|| invalid_data("stat"))?;
+
+        Ok(Self {
+            rss_kib,
+            open_fds,
+            cpu_time: Duration::from_millis(cpu_ticks * 1000 / CLOCK_TICKS_PER_SEC),
+        })
+    }
+}
+
+fn invalid_data(what: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("unable to parse {what}"),
+    )
+}
+
+fn parse_rss_kib(status: &str) -> Option<u64> {
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    line.split_whitespace().nth(1)?.parse().ok()
+}
+
+// Returns utime + stime. The process name can contain spaces, so the fields are counted from
+// its closing parenthesis.
+fn parse_cpu_ticks(stat: &str) -> Option<u64> {
+    let mut fields = stat[stat.rfind(')')? + 1..].split_whitespace();
+    // utime and stime are the 14th and 15th fields, the name is the 2nd.
+    let utime: u64 = fields.nth(11)?.parse().ok()?;
+    let stime: u64 = fields.next()?.parse().ok()?;
+    Some(utime + stime)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn proc_files_are_parsed() {
+        let status = "Name:\trippled\nVmPeak:\t  900000 kB\nVmRSS:\t  123456 kB\n";
+        assert_eq!(parse_rss_kib(status), Some(123456));
+
+        let stat = "4242 (rip pled) S 1 4242 4242 0 -1 4194560 100 0 0 0 250 50 0 0 20 0 12";
+        assert_eq!(parse_cpu_ticks(stat), Some(300));
+    }
+}
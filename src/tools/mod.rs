@@ -1,17 +1,38 @@
 //! Utilities for network testing.
 
+pub mod accounts;
+pub mod churn;
 pub mod config;
+pub mod connection_rate;
 pub mod constants;
-// This mod belongs to the tools/crawler and we are using a sym
-// link to get it here.
-// This is a workaround solution in this repo for this case,
-// in future Ziggurat repos, we will handle this differently.
 pub mod crawl;
+pub mod differential;
+pub mod endpoints;
+pub mod flood;
+pub mod harness;
 pub mod inner_node;
 pub mod ips;
+pub mod ledger_store;
+pub mod matchers;
+pub mod message_stats;
+pub mod metrics;
+pub mod netem;
+pub mod node_log;
+pub mod objects;
+pub mod overlay;
+pub mod pcap;
+pub mod peer_status;
+pub mod proposal;
+pub mod proxy;
+pub mod replay_delta;
+pub mod ripple_time;
 pub mod rpc;
+pub mod soak;
 pub mod synth_node;
 pub mod tls_cert;
+pub mod tx;
+pub mod validation;
+pub mod validator;
 
 /// Waits until an expression is true or times out.
 ///
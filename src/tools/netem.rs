@@ -0,0 +1,166 @@
+//! Network impairment of the loopback aliases used by the tests (Linux only).
+//!
+//! Traffic to and from the given addresses is routed through a `netem` qdisc adding latency,
+//! jitter, packet loss and a bandwidth cap. Other loopback traffic is left alone. The rules are
+//! removed when the returned guard is dropped.
+//!
+//! Changing qdiscs requires `CAP_NET_ADMIN`, so tests using this have to run as root.
+
+use std::{io, net::IpAddr, process::Command, time::Duration};
+
+use tracing::warn;
+
+/// The device the loopback aliases are assigned to.
+pub const LOOPBACK_DEVICE: &str = "lo";
+
+// The default prio qdisc uses bands 1-3, an extra band carries the impaired traffic.
+const IMPAIRED_BAND: &str = "1:4";
+const PRIO_BANDS: &str = "4";
+
+/// Network conditions applied to the impaired traffic.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Impairment {
+    /// Added one-way latency.
+    pub delay: Option<Duration>,
+    /// Random variation of the delay, only applied together with the delay.
+    pub jitter: Option<Duration>,
+    /// Percentage of dropped packets.
+    pub loss: Option<f64>,
+    /// Bandwidth cap in kbit/s.
+    pub rate_kbit: Option<u64>,
+}
+
+impl Impairment {
+    fn netem_args(&self) -> Vec<String> {
+        let mut args = vec!["netem".to_owned()];
+        if let Some(delay) = self.delay {
+            args.push("delay".into());
+            args.push(format!("{}us", delay.as_micros()));
+            if let Some(jitter) = self.jitter {
+                args.push(format!("{}us", jitter.as_micros()));
+            }
+        }
+        if let Some(loss) = self.loss {
+            args.push("loss".into());
+            args.push(format!("{loss}%"));
+        }
+        if let Some(rate) = self.rate_kbit {
+            args.push("rate".into());
+            args.push(format!("{rate}kbit"));
+        }
+        args
+    }
+}
+
+/// Impairs the traffic of the given addresses until dropped.
+pub struct Netem {
+    device: String,
+}
+
+impl Netem {
+    /// Impairs all traffic to and from the addresses on the loopback device.
+    pub fn apply(addrs: &[IpAddr], impairment: &Impairment) -> io::Result<Self> {
+        Self::apply_on(LOOPBACK_DEVICE, addrs, impairment)
+    }
+
+    /// Impairs all traffic to and from the addresses on the given device.
+    ///
+    /// Replaces the device's root qdisc, which is deleted again on drop.
+    pub fn apply_on(device: &str, addrs: &[IpAddr], impairment: &Impairment) -> io::Result<Self> {
+        if !cfg!(target_os = "linux") {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "network impairment is only supported on Linux",
+            ));
+        }
+
+        // Clean up rules left over by an earlier run which didn't get to drop its guard.
+        let _ = tc(&["qdisc", "del", "dev", device, "root"]);
+
+        // Only construct the guard once there's something to clean up.
+        tc(&[
+            "qdisc", "add", "dev", device, "root", "handle", "1:", "prio", "bands", PRIO_BANDS,
+        ])?;
+        let netem = Self {
+            device: device.to_owned(),
+        };
+
+        let mut args = vec!["qdisc", "add", "dev", device, "parent", IMPAIRED_BAND];
+        let netem_args = impairment.netem_args();
+        args.extend(netem_args.iter().map(String::as_str));
+        tc(&args)?;
+
+        for addr in addrs {
+            let (protocol, matcher, prefix) = match addr {
+                IpAddr::V4(_) => ("ip", "ip", 32),
+                IpAddr::V6(_) => ("ipv6", "ip6", 128),
+            };
+            let addr = format!("{addr}/{prefix}");
+            for direction in ["src", "dst"] {
+                tc(&[
+                    "filter",
+                    "add",
+                    "dev",
+                    device,
+                    "protocol",
+                    protocol,
+                    "parent",
+                    "1:0",
+                    "prio",
+                    "1",
+                    "u32",
+                    "match",
+                    matcher,
+                    direction,
+                    &addr,
+                    "flowid",
+                    IMPAIRED_BAND,
+                ])?;
+            }
+        }
+
+        Ok(netem)
+    }
+}
+
+impl Drop for Netem {
+    fn drop(&mut self) {
+        if let Err(e) = tc(&["qdisc", "del", "dev", &self.device, "root"]) {
+            warn!("unable to remove the impairment from {}: {e}", self.device);
+        }
+    }
+}
+
+fn tc(args: &[&str]) -> io::Result<()> {
+    let output = Command::new("tc").args(args).output()?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!(
+            "`tc {}` failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn netem_args() {
+        let impairment = Impairment {
+            delay: Some(Duration::from_millis(50)),
+            jitter: Some(Duration::from_millis(5)),
+            loss: Some(0.5),
+            rate_kbit: Some(1000),
+        };
+
+        assert_eq!(
+            impairment.netem_args().join(" "),
+            "netem delay 50000us 5000us loss 0.5% rate 1000kbit"
+        );
+        assert_eq!(Impairment::default().netem_args(), vec!["netem"]);
+    }
+}
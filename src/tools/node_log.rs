@@ -0,0 +1,287 @@
+//! Tailing and parsing of the node's log, for assertions on behavior invisible on the wire.
+//!
+//! rippled logs one entry per line, e.g.:
+//! `2023-Mar-01 10:00:00.123456789 UTC Peer:WRN [002] Handshake: Bad public key`.
+
+use std::{
+    fmt, io,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
+
+use futures_util::{stream, Stream};
+use regex::Regex;
+use tokio::{
+    fs::File,
+    io::{AsyncBufReadExt, AsyncSeekExt, BufReader, SeekFrom},
+    time::{sleep, timeout, timeout_at, Instant},
+};
+
+/// How often the log is checked for new lines.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// The severity of a log entry, as abbreviated by rippled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Trace,
+    Debug,
+    Info,
+    Warning,
+    Error,
+    Fatal,
+}
+
+impl FromStr for Severity {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "TRC" => Ok(Self::Trace),
+            "DBG" => Ok(Self::Debug),
+            "NFO" => Ok(Self::Info),
+            "WRN" => Ok(Self::Warning),
+            "ERR" => Ok(Self::Error),
+            "FTL" => Ok(Self::Fatal),
+            _ => Err(()),
+        }
+    }
+}
+
+/// A line of the node's log.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogLine {
+    /// The date and time, as logged.
+    pub timestamp: Option<String>,
+    /// The partition, i.e. the subsystem, which logged the entry, e.g. `Peer`.
+    pub partition: Option<String>,
+    pub severity: Option<Severity>,
+    /// The logged message.
+    ///
+    /// Lines continuing a multi-line entry have neither a timestamp, a partition nor a severity,
+    /// the whole line is the message.
+    pub message: String,
+}
+
+impl LogLine {
+    /// Parses a line of the log, without the line break.
+    pub fn parse(line: &str) -> Self {
+        Self::parse_entry(line).unwrap_or_else(|| Self {
+            timestamp: None,
+            partition: None,
+            severity: None,
+            message: line.to_owned(),
+        })
+    }
+
+    fn parse_entry(line: &str) -> Option<Self> {
+        let (timestamp, rest) = line.split_once(" UTC ")?;
+        let (source, message) = rest.split_once(' ').unwrap_or((rest, ""));
+        let (partition, severity) = source.split_once(':')?;
+
+        Some(Self {
+            timestamp: Some(timestamp.to_owned()),
+            partition: Some(partition.to_owned()),
+            severity: Some(severity.parse().ok()?),
+            message: message.to_owned(),
+        })
+    }
+}
+
+impl fmt::Display for LogLine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.partition, self.severity) {
+            (Some(partition), Some(severity)) => {
+                write!(f, "{partition}:{severity:?} {}", self.message)
+            }
+            _ => f.write_str(&self.message),
+        }
+    }
+}
+
+/// Reads the lines of the node's log as they're written, see
+/// [Node::log_watcher](crate::setup::node::Node::log_watcher).
+pub struct LogWatcher {
+    path: PathBuf,
+    // Opened once the node has created the log.
+    reader: Option<BufReader<File>>,
+    // Where to start reading once the log is opened.
+    start: SeekFrom,
+    // The part of a line written so far.
+    partial: String,
+}
+
+impl LogWatcher {
+    /// Watches the lines appended to the log after this call.
+    pub async fn tail(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        // A missing log is created by the node later, so all of its lines are new.
+        let start = match tokio::fs::metadata(&path).await {
+            Ok(metadata) => SeekFrom::Start(metadata.len()),
+            Err(_) => SeekFrom::Start(0),
+        };
+
+        Self::new(path, start)
+    }
+
+    /// Watches all the lines of the log, including the ones already written.
+    pub fn from_start(path: impl Into<PathBuf>) -> Self {
+        Self::new(path.into(), SeekFrom::Start(0))
+    }
+
+    fn new(path: PathBuf, start: SeekFrom) -> Self {
+        Self {
+            path,
+            reader: None,
+            start,
+            partial: String::new(),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Waits for the next complete line of the log.
+    pub async fn next_line(&mut self) -> io::Result<LogLine> {
+        loop {
+            if let Some(reader) = &mut self.reader {
+                if reader.read_line(&mut self.partial).await? > 0 && self.partial.ends_with('\n') {
+                    let line = LogLine::parse(self.partial.trim_end_matches(['\r', '\n']));
+                    self.partial.clear();
+                    return Ok(line);
+                }
+            } else if let Ok(file) = File::open(&self.path).await {
+                let mut reader = BufReader::new(file);
+                reader.seek(self.start).await?;
+                self.reader = Some(reader);
+                continue;
+            }
+
+            sleep(POLL_INTERVAL).await;
+        }
+    }
+
+    /// Waits for a line matching the regex, the lines before it are skipped.
+    ///
+    /// Fails with [io::ErrorKind::TimedOut] if no line matches in time.
+    pub async fn expect_line(&mut self, regex: &Regex, wait: Duration) -> io::Result<LogLine> {
+        self.expect(wait, |line| regex.is_match(&line.message))
+            .await
+            .map_err(|e| match e.kind() {
+                io::ErrorKind::TimedOut => io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    format!("no log line matched `{regex}` in {wait:?}"),
+                ),
+                _ => e,
+            })
+    }
+
+    /// Waits for a line the check accepts, the lines before it are skipped.
+    ///
+    /// Fails with [io::ErrorKind::TimedOut] if no line is accepted in time.
+    pub async fn expect(
+        &mut self,
+        wait: Duration,
+        check: impl Fn(&LogLine) -> bool,
+    ) -> io::Result<LogLine> {
+        timeout(wait, async {
+            loop {
+                let line = self.next_line().await?;
+                if check(&line) {
+                    return Ok(line);
+                }
+            }
+        })
+        .await
+        .unwrap_or_else(|_| {
+            Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                format!("no expected log line in {wait:?}"),
+            ))
+        })
+    }
+
+    /// Returns the lines read within the duration.
+    pub async fn collect_for(&mut self, duration: Duration) -> io::Result<Vec<LogLine>> {
+        let deadline = Instant::now() + duration;
+        let mut lines = Vec::new();
+        while let Ok(line) = timeout_at(deadline, self.next_line()).await {
+            lines.push(line?);
+        }
+        Ok(lines)
+    }
+
+    /// Turns the watcher into a stream of the log's lines, ending at the first read error.
+    pub fn into_stream(self) -> impl Stream<Item = LogLine> {
+        stream::unfold(self, |mut watcher| async move {
+            let line = watcher.next_line().await.ok()?;
+            Some((line, watcher))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+    use tokio::io::AsyncWriteExt;
+
+    use super::*;
+
+    #[test]
+    fn lines_are_parsed() {
+        let line =
+            LogLine::parse("2023-Mar-01 10:00:00.123456789 UTC Peer:WRN [002] Bad public key");
+        assert_eq!(
+            line.timestamp.as_deref(),
+            Some("2023-Mar-01 10:00:00.123456789")
+        );
+        assert_eq!(line.partition.as_deref(), Some("Peer"));
+        assert_eq!(line.severity, Some(Severity::Warning));
+        assert_eq!(line.message, "[002] Bad public key");
+
+        let continuation = LogLine::parse("  \"key\": 1");
+        assert_eq!(continuation.severity, None);
+        assert_eq!(continuation.message, "  \"key\": 1");
+    }
+
+    #[tokio::test]
+    async fn appended_lines_are_watched() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("debug.log");
+        tokio::fs::write(&path, "2023-Mar-01 10:00:00 UTC Peer:NFO old\n")
+            .await
+            .unwrap();
+
+        let mut watcher = LogWatcher::tail(&path).await;
+        let mut file = tokio::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .await
+            .unwrap();
+        // A line written in parts is only read once complete.
+        file.write_all(b"2023-Mar-01 10:00:01 UTC Overlay:ERR ne")
+            .await
+            .unwrap();
+        file.flush().await.unwrap();
+        let writing = tokio::spawn(async move {
+            sleep(POLL_INTERVAL * 2).await;
+            file.write_all(b"w\n").await.unwrap();
+            file.flush().await.unwrap();
+        });
+
+        let line = watcher
+            .expect_line(&Regex::new("^new$").unwrap(), Duration::from_secs(1))
+            .await
+            .unwrap();
+        assert_eq!(line.partition.as_deref(), Some("Overlay"));
+        assert_eq!(line.severity, Some(Severity::Error));
+        writing.await.unwrap();
+
+        let error = watcher
+            .expect_line(&Regex::new("old").unwrap(), Duration::from_millis(100))
+            .await
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::TimedOut);
+    }
+}
@@ -0,0 +1,102 @@
+//! Serving ledger objects in reply to [TmGetObjectByHash] queries, as a peer holding them would.
+//!
+//! The objects are stored under the hash the test provides, which isn't checked against the
+//! data, so mismatched or malformed objects can be served as well.
+
+use std::collections::HashMap;
+
+use crate::protocol::proto::{TmGetObjectByHash, TmIndexedObject};
+
+/// The objects a synthetic node serves, keyed by their hashes.
+#[derive(Debug, Default, Clone)]
+pub struct ObjectStore {
+    objects: HashMap<Vec<u8>, Vec<u8>>,
+}
+
+impl ObjectStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stores the data under the hash, replacing any object stored under it before.
+    pub fn insert(&mut self, hash: impl Into<Vec<u8>>, data: impl Into<Vec<u8>>) {
+        self.objects.insert(hash.into(), data.into());
+    }
+
+    pub fn get(&self, hash: &[u8]) -> Option<&[u8]> {
+        self.objects.get(hash).map(Vec::as_slice)
+    }
+
+    /// Builds the reply to the query, with the requested objects found in the store.
+    ///
+    /// Like rippled, objects which aren't found are left out of the reply. Returns `None` if the
+    /// message isn't a query.
+    pub fn reply(&self, query: &TmGetObjectByHash) -> Option<TmGetObjectByHash> {
+        if !query.query {
+            return None;
+        }
+
+        let objects = query
+            .objects
+            .iter()
+            .filter_map(|object| {
+                let data = self.get(object.hash.as_deref()?)?;
+                Some(TmIndexedObject {
+                    data: Some(data.to_vec()),
+                    ..object.clone()
+                })
+            })
+            .collect();
+
+        Some(TmGetObjectByHash {
+            r#type: query.r#type,
+            query: false,
+            seq: query.seq,
+            ledger_hash: query.ledger_hash.clone(),
+            fat: None,
+            objects,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::proto::tm_get_object_by_hash::ObjectType;
+
+    fn requested(hash: &[u8]) -> TmIndexedObject {
+        TmIndexedObject {
+            hash: Some(hash.to_vec()),
+            node_id: None,
+            index: None,
+            data: None,
+            ledger_seq: Some(5),
+        }
+    }
+
+    #[test]
+    fn reply_contains_the_stored_objects() {
+        let mut store = ObjectStore::new();
+        store.insert([1; 32], b"first".to_vec());
+
+        let query = TmGetObjectByHash {
+            r#type: ObjectType::OtStateNode as i32,
+            query: true,
+            seq: Some(9),
+            ledger_hash: Some(vec![7; 32]),
+            fat: Some(true),
+            objects: vec![requested(&[1; 32]), requested(&[2; 32])],
+        };
+        let reply = store.reply(&query).unwrap();
+
+        assert!(!reply.query);
+        assert_eq!(reply.seq, Some(9));
+        assert_eq!(reply.ledger_hash, query.ledger_hash);
+        assert_eq!(reply.objects.len(), 1);
+        assert_eq!(reply.objects[0].hash, Some(vec![1; 32]));
+        assert_eq!(reply.objects[0].data, Some(b"first".to_vec()));
+        assert_eq!(reply.objects[0].ledger_seq, Some(5));
+
+        assert!(store.reply(&reply).is_none());
+    }
+}
@@ -0,0 +1,312 @@
+//! A small overlay made of synthetic nodes.
+//!
+//! Synthetic nodes connect to each other (doing the handshake on both sides) according to a
+//! topology, and some of them also connect to a rippled node. Every member records the messages
+//! it receives and relays the selected kinds to all its other peers, so the propagation of
+//! messages (hops, timing, squelching) can be studied with full control over every participant
+//! except the rippled node.
+
+use std::{
+    collections::{hash_map::DefaultHasher, HashSet},
+    hash::{Hash, Hasher},
+    io,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use bytes::BytesMut;
+use tokio::{
+    sync::{mpsc, oneshot},
+    task::JoinHandle,
+    time::Instant,
+};
+use tokio_util::codec::Encoder;
+use tracing::{warn, Span};
+
+use crate::{
+    protocol::codecs::message::{MessageCodec, Payload},
+    tools::{config::SynthNodeCfg, synth_node::SyntheticNode},
+};
+
+/// How the members are connected to each other.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Topology {
+    /// Every member is connected to every other member.
+    FullMesh,
+    /// Each member is connected to the next one, the last one to the first.
+    Ring,
+    /// Each member is connected to the next one.
+    Line,
+}
+
+impl Topology {
+    // Returns the pairs of members to connect, the first one initiates the connection.
+    fn edges(&self, size: usize) -> Vec<(usize, usize)> {
+        match self {
+            Self::FullMesh => (0..size)
+                .flat_map(|i| (i + 1..size).map(move |j| (i, j)))
+                .collect(),
+            Self::Ring if size > 2 => (0..size).map(|i| (i, (i + 1) % size)).collect(),
+            Self::Ring | Self::Line => (1..size).map(|i| (i - 1, i)).collect(),
+        }
+    }
+}
+
+/// Overlay configuration.
+#[derive(Clone)]
+pub struct OverlayCfg {
+    /// The number of synthetic members.
+    pub size: usize,
+    pub topology: Topology,
+    /// Members connecting to the rippled node.
+    pub gateways: Vec<usize>,
+    /// Kinds of messages relayed by the members (see [Payload::name]), others are only recorded.
+    pub relayed: Vec<&'static str>,
+    /// Configuration of all the members.
+    pub synth_node_cfg: SynthNodeCfg,
+}
+
+impl Default for OverlayCfg {
+    fn default() -> Self {
+        Self {
+            size: 3,
+            topology: Topology::FullMesh,
+            gateways: vec![0],
+            relayed: vec![
+                "TmTransaction",
+                "TmTransactions",
+                "TmHaveTransactions",
+                "TmProposeLedger",
+                "TmValidation",
+                "TmManifests",
+            ],
+            synth_node_cfg: Default::default(),
+        }
+    }
+}
+
+/// A message received by one of the members.
+#[derive(Debug, Clone)]
+pub struct OverlayEvent {
+    /// The index of the receiving member.
+    pub member: usize,
+    /// The sender, either another member or the rippled node.
+    pub from: SocketAddr,
+    /// Time since the overlay was started.
+    pub elapsed: Duration,
+    /// Identifies the same message across members.
+    pub digest: u64,
+    pub payload: Payload,
+}
+
+struct Member {
+    listening_addr: SocketAddr,
+    commands: mpsc::UnboundedSender<Payload>,
+    stop: oneshot::Sender<()>,
+    task: JoinHandle<()>,
+}
+
+pub struct SyntheticOverlay {
+    members: Vec<Member>,
+    events: Arc<Mutex<Vec<OverlayEvent>>>,
+}
+
+impl SyntheticOverlay {
+    /// Starts the members, connects them according to the topology and connects the gateways
+    /// to the rippled node, if one is given.
+    pub async fn new(cfg: &OverlayCfg, node_addr: Option<SocketAddr>) -> io::Result<Self> {
+        let mut nodes = Vec::with_capacity(cfg.size);
+        let mut listening_addrs = Vec::with_capacity(cfg.size);
+        for _ in 0..cfg.size {
+            let synth_node = SyntheticNode::new(&cfg.synth_node_cfg).await;
+            listening_addrs.push(synth_node.start_listening().await?);
+            nodes.push(synth_node);
+        }
+
+        for (from, to) in cfg.topology.edges(cfg.size) {
+            nodes[from].connect(listening_addrs[to]).await?;
+        }
+        if let Some(node_addr) = node_addr {
+            for &gateway in &cfg.gateways {
+                nodes[gateway].connect(node_addr).await?;
+            }
+        }
+
+        let start = Instant::now();
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let relayed: Arc<HashSet<&'static str>> = Arc::new(cfg.relayed.iter().copied().collect());
+
+        let members = nodes
+            .into_iter()
+            .zip(listening_addrs)
+            .enumerate()
+            .map(|(idx, (synth_node, listening_addr))| {
+                let (commands, commands_rx) = mpsc::unbounded_channel();
+                let (stop, stop_rx) = oneshot::channel();
+                let task = tokio::spawn(run_member(
+                    idx,
+                    synth_node,
+                    start,
+                    relayed.clone(),
+                    events.clone(),
+                    commands_rx,
+                    stop_rx,
+                ));
+
+                Member {
+                    listening_addr,
+                    commands,
+                    stop,
+                    task,
+                }
+            })
+            .collect();
+
+        Ok(Self { members, events })
+    }
+
+    /// Returns the members' listening addresses, e.g. to be used as the rippled node's initial
+    /// peers.
+    pub fn listening_addrs(&self) -> Vec<SocketAddr> {
+        self.members
+            .iter()
+            .map(|member| member.listening_addr)
+            .collect()
+    }
+
+    /// Sends the message from the member to all its peers, as if the member originated it.
+    pub fn inject(&self, member: usize, payload: Payload) -> io::Result<()> {
+        self.members
+            .get(member)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no such member"))?
+            .commands
+            .send(payload)
+            .map_err(|_| io::ErrorKind::BrokenPipe.into())
+    }
+
+    /// Returns all the messages received by the members so far.
+    pub fn events(&self) -> Vec<OverlayEvent> {
+        self.events.lock().unwrap().clone()
+    }
+
+    /// Returns the first time each member received the message, ordered by time.
+    pub fn propagation(&self, payload: &Payload) -> Vec<(usize, Duration)> {
+        let digest = digest(payload);
+        let mut first_seen: Vec<(usize, Duration)> = Vec::new();
+        for event in self.events.lock().unwrap().iter() {
+            if event.digest == digest && !first_seen.iter().any(|(m, _)| *m == event.member) {
+                first_seen.push((event.member, event.elapsed));
+            }
+        }
+        first_seen.sort_by_key(|(_, elapsed)| *elapsed);
+        first_seen
+    }
+
+    /// Stops all the members.
+    pub async fn shut_down(self) {
+        for member in self.members {
+            let _ = member.stop.send(());
+            let _ = member.task.await;
+        }
+    }
+}
+
+async fn run_member(
+    idx: usize,
+    mut synth_node: SyntheticNode,
+    start: Instant,
+    relayed: Arc<HashSet<&'static str>>,
+    events: Arc<Mutex<Vec<OverlayEvent>>>,
+    mut commands: mpsc::UnboundedReceiver<Payload>,
+    mut stop: oneshot::Receiver<()>,
+) {
+    // Each message is relayed once, which also stops it from circling around the topology.
+    let mut seen = HashSet::new();
+
+    loop {
+        tokio::select! {
+            _ = &mut stop => break,
+            Some(payload) = commands.recv() => {
+                seen.insert(digest(&payload));
+                broadcast(&synth_node, None, &payload);
+            }
+            (from, message) = synth_node.recv_message() => {
+                let digest = digest(&message.payload);
+                if seen.insert(digest) && relayed.contains(message.payload.name()) {
+                    broadcast(&synth_node, Some(from), &message.payload);
+                }
+
+                events.lock().unwrap().push(OverlayEvent {
+                    member: idx,
+                    from,
+                    elapsed: start.elapsed(),
+                    digest,
+                    payload: message.payload,
+                });
+            }
+        }
+    }
+
+    synth_node.shut_down().await;
+}
+
+fn broadcast(synth_node: &SyntheticNode, except: Option<SocketAddr>, payload: &Payload) {
+    for addr in synth_node.connected_addrs() {
+        if Some(addr) == except {
+            continue;
+        }
+        if let Err(e) = synth_node.unicast(addr, payload.clone()) {
+            warn!("unable to relay a message to {addr}: {e}");
+        }
+    }
+}
+
+fn digest(payload: &Payload) -> u64 {
+    let mut bytes = BytesMut::new();
+    let _ = MessageCodec::new(Span::none()).encode(payload.clone(), &mut bytes);
+
+    let mut hasher = DefaultHasher::new();
+    bytes.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::time::sleep;
+
+    use super::*;
+    use crate::protocol::proto::TmHaveTransactions;
+
+    #[test]
+    fn topology_edges() {
+        assert_eq!(Topology::Line.edges(3), vec![(0, 1), (1, 2)]);
+        assert_eq!(Topology::Ring.edges(3), vec![(0, 1), (1, 2), (2, 0)]);
+        assert_eq!(Topology::FullMesh.edges(3), vec![(0, 1), (0, 2), (1, 2)]);
+    }
+
+    #[tokio::test]
+    async fn injected_message_propagates_along_a_line() {
+        let cfg = OverlayCfg {
+            size: 4,
+            topology: Topology::Line,
+            ..Default::default()
+        };
+        let overlay = SyntheticOverlay::new(&cfg, None).await.unwrap();
+
+        let payload = Payload::TmHaveTransactions(TmHaveTransactions {
+            hashes: vec![vec![1u8; 32]],
+        });
+        overlay.inject(0, payload.clone()).unwrap();
+        sleep(Duration::from_secs(1)).await;
+
+        let members = overlay
+            .propagation(&payload)
+            .into_iter()
+            .map(|(member, _)| member)
+            .collect::<Vec<_>>();
+        overlay.shut_down().await;
+
+        assert_eq!(members, vec![1, 2, 3]);
+    }
+}
@@ -0,0 +1,374 @@
+//! Offline decoding of captured peer traffic.
+//!
+//! Reads a classic pcap capture, reassembles its TCP streams and decodes them with the
+//! [MessageCodec]. Peer connections are encrypted with TLS, so the capture has to contain the
+//! decrypted streams, e.g. exported by Wireshark with the session keys, or traffic captured
+//! in front of a plaintext proxy.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt, fs, io,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    path::Path,
+};
+
+use bytes::BytesMut;
+use tokio_util::codec::Decoder;
+use tracing::Span;
+
+use crate::protocol::codecs::message::{MessageCodec, Payload};
+
+const PCAP_HEADER_LEN: usize = 24;
+const RECORD_HEADER_LEN: usize = 16;
+
+const MAGIC_MICROS: u32 = 0xa1b2c3d4;
+const MAGIC_NANOS: u32 = 0xa1b23c4d;
+
+// Link-layer header types.
+const LINKTYPE_NULL: u32 = 0;
+const LINKTYPE_ETHERNET: u32 = 1;
+const LINKTYPE_RAW: u32 = 101;
+const LINKTYPE_LINUX_SLL: u32 = 113;
+
+const ETHERTYPE_IPV4: u16 = 0x0800;
+const ETHERTYPE_IPV6: u16 = 0x86dd;
+const IP_PROTOCOL_TCP: u8 = 6;
+
+/// The end of the HTTP upgrade request or response preceding the peer messages.
+const HTTP_HEADER_END: &[u8] = b"\r\n\r\n";
+
+/// The payload of one direction of a TCP connection, in sequence order.
+#[derive(Debug, Clone)]
+pub struct TcpStream {
+    pub src: SocketAddr,
+    pub dst: SocketAddr,
+    pub data: Vec<u8>,
+}
+
+/// A message decoded from a stream, together with its raw frame.
+#[derive(Debug, Clone)]
+pub struct DecodedMessage {
+    pub payload: Payload,
+    pub frame: Vec<u8>,
+}
+
+/// The messages decoded from a single stream.
+#[derive(Debug)]
+pub struct DecodedStream {
+    pub src: SocketAddr,
+    pub dst: SocketAddr,
+    pub messages: Vec<DecodedMessage>,
+    /// Set if the stream couldn't be decoded to the end.
+    pub error: Option<String>,
+}
+
+impl DecodedStream {
+    /// Writes each message's frame to its own file in the directory, named after its index and
+    /// type, e.g. `0003_TmPing.bin`.
+    pub fn write_fixtures(&self, dir: &Path) -> io::Result<()> {
+        fs::create_dir_all(dir)?;
+        for (idx, message) in self.messages.iter().enumerate() {
+            let name = format!("{idx:04}_{}.bin", message.payload.name());
+            fs::write(dir.join(name), &message.frame)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for DecodedStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} -> {}", self.src, self.dst)?;
+        for message in &self.messages {
+            writeln!(f, "  {:?}", message.payload)?;
+        }
+        if let Some(error) = &self.error {
+            writeln!(f, "  error: {error}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Reads the capture and decodes every TCP stream in it.
+pub fn decode_pcap(path: &Path) -> io::Result<Vec<DecodedStream>> {
+    let streams = parse_pcap(&fs::read(path)?)?;
+    Ok(streams.iter().map(decode_stream).collect())
+}
+
+/// Decodes the peer messages in the stream, skipping the HTTP upgrade headers if present.
+pub fn decode_stream(stream: &TcpStream) -> DecodedStream {
+    let mut data = stream.data.as_slice();
+    if data.starts_with(b"GET ") || data.starts_with(b"HTTP/") {
+        data = match find(data, HTTP_HEADER_END) {
+            Some(end) => &data[end + HTTP_HEADER_END.len()..],
+            None => &[],
+        };
+    }
+
+    let mut codec = MessageCodec::new(Span::none());
+    let mut buf = BytesMut::from(data);
+    let mut messages = Vec::new();
+    let mut error = None;
+
+    loop {
+        let remaining = buf.len();
+        match codec.decode(&mut buf) {
+            Ok(Some(message)) => {
+                let consumed = remaining - buf.len();
+                let start = data.len() - remaining;
+                messages.push(DecodedMessage {
+                    payload: message.payload,
+                    frame: data[start..start + consumed].to_vec(),
+                });
+            }
+            Ok(None) => {
+                if !buf.is_empty() {
+                    error = Some(format!("{} trailing bytes", buf.len()));
+                }
+                break;
+            }
+            Err(e) => {
+                error = Some(e.to_string());
+                break;
+            }
+        }
+    }
+
+    DecodedStream {
+        src: stream.src,
+        dst: stream.dst,
+        messages,
+        error,
+    }
+}
+
+/// Parses a classic pcap capture and reassembles the TCP streams in it.
+///
+/// Segments are ordered by their sequence numbers and retransmitted data is dropped, sequence
+/// number wraparound isn't handled. Streams are returned in the order of their first segment.
+pub fn parse_pcap(capture: &[u8]) -> io::Result<Vec<TcpStream>> {
+    if capture.len() < PCAP_HEADER_LEN {
+        return Err(invalid_data("the capture is too short"));
+    }
+
+    let magic = u32::from_le_bytes(capture[0..4].try_into().unwrap());
+    let little_endian = match magic {
+        MAGIC_MICROS | MAGIC_NANOS => true,
+        _ if magic.swap_bytes() == MAGIC_MICROS || magic.swap_bytes() == MAGIC_NANOS => false,
+        _ => return Err(invalid_data("not a pcap capture")),
+    };
+    let read_u32 = |bytes: &[u8]| {
+        let bytes = bytes[..4].try_into().unwrap();
+        if little_endian {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        }
+    };
+    let link_type = read_u32(&capture[20..24]);
+
+    let mut segments: HashMap<(SocketAddr, SocketAddr), BTreeMap<u32, &[u8]>> = HashMap::new();
+    let mut order = Vec::new();
+
+    let mut pos = PCAP_HEADER_LEN;
+    while pos + RECORD_HEADER_LEN <= capture.len() {
+        let captured_len = read_u32(&capture[pos + 8..]) as usize;
+        pos += RECORD_HEADER_LEN;
+        let Some(packet) = capture.get(pos..pos + captured_len) else {
+            return Err(invalid_data("truncated packet record"));
+        };
+        pos += captured_len;
+
+        let Some(segment) = parse_packet(link_type, packet) else {
+            continue;
+        };
+        if segment.payload.is_empty() {
+            continue;
+        }
+
+        let key = (segment.src, segment.dst);
+        let stream = segments.entry(key).or_insert_with(|| {
+            order.push(key);
+            BTreeMap::new()
+        });
+        stream.entry(segment.seq).or_insert(segment.payload);
+    }
+
+    Ok(order
+        .into_iter()
+        .map(|(src, dst)| {
+            let mut data = Vec::new();
+            let mut next_seq: Option<u64> = None;
+            for (seq, payload) in segments.remove(&(src, dst)).unwrap() {
+                let (seq, end) = (seq as u64, seq as u64 + payload.len() as u64);
+                // Skip data which was already appended, e.g. retransmissions.
+                let skip = next_seq.map_or(0, |next| next.saturating_sub(seq)) as usize;
+                if skip < payload.len() {
+                    data.extend_from_slice(&payload[skip..]);
+                }
+                next_seq = Some(next_seq.map_or(end, |next| next.max(end)));
+            }
+            TcpStream { src, dst, data }
+        })
+        .collect())
+}
+
+struct TcpSegment<'a> {
+    src: SocketAddr,
+    dst: SocketAddr,
+    seq: u32,
+    payload: &'a [u8],
+}
+
+fn parse_packet(link_type: u32, packet: &[u8]) -> Option<TcpSegment<'_>> {
+    let ip_packet = match link_type {
+        LINKTYPE_ETHERNET => {
+            let ethertype = u16::from_be_bytes(packet.get(12..14)?.try_into().ok()?);
+            match ethertype {
+                ETHERTYPE_IPV4 | ETHERTYPE_IPV6 => packet.get(14..)?,
+                _ => return None,
+            }
+        }
+        LINKTYPE_LINUX_SLL => packet.get(16..)?,
+        LINKTYPE_NULL => packet.get(4..)?,
+        LINKTYPE_RAW => packet,
+        _ => return None,
+    };
+
+    let (src_ip, dst_ip, tcp) = match ip_packet.first()? >> 4 {
+        4 => {
+            let header_len = ((ip_packet[0] & 0x0f) as usize) * 4;
+            let total_len = u16::from_be_bytes(ip_packet.get(2..4)?.try_into().ok()?) as usize;
+            if *ip_packet.get(9)? != IP_PROTOCOL_TCP {
+                return None;
+            }
+            let src: [u8; 4] = ip_packet.get(12..16)?.try_into().ok()?;
+            let dst: [u8; 4] = ip_packet.get(16..20)?.try_into().ok()?;
+            // Ethernet frames can be padded past the end of the IP packet.
+            let end = total_len.min(ip_packet.len());
+            (
+                IpAddr::V4(Ipv4Addr::from(src)),
+                IpAddr::V4(Ipv4Addr::from(dst)),
+                ip_packet.get(header_len..end)?,
+            )
+        }
+        6 => {
+            // Extension headers aren't supported.
+            if *ip_packet.get(6)? != IP_PROTOCOL_TCP {
+                return None;
+            }
+            let payload_len = u16::from_be_bytes(ip_packet.get(4..6)?.try_into().ok()?) as usize;
+            let src: [u8; 16] = ip_packet.get(8..24)?.try_into().ok()?;
+            let dst: [u8; 16] = ip_packet.get(24..40)?.try_into().ok()?;
+            let end = (40 + payload_len).min(ip_packet.len());
+            (
+                IpAddr::V6(Ipv6Addr::from(src)),
+                IpAddr::V6(Ipv6Addr::from(dst)),
+                ip_packet.get(40..end)?,
+            )
+        }
+        _ => return None,
+    };
+
+    let src_port = u16::from_be_bytes(tcp.get(0..2)?.try_into().ok()?);
+    let dst_port = u16::from_be_bytes(tcp.get(2..4)?.try_into().ok()?);
+    let seq = u32::from_be_bytes(tcp.get(4..8)?.try_into().ok()?);
+    let data_offset = ((tcp.get(12)? >> 4) as usize) * 4;
+
+    Some(TcpSegment {
+        src: SocketAddr::new(src_ip, src_port),
+        dst: SocketAddr::new(dst_ip, dst_port),
+        seq,
+        payload: tcp.get(data_offset..)?,
+    })
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio_util::codec::Encoder;
+
+    use super::*;
+    use crate::protocol::proto::{tm_ping::PingType, TmPing};
+
+    fn ping_frame(seq: u32) -> Vec<u8> {
+        let mut frame = BytesMut::new();
+        MessageCodec::new(Span::none())
+            .encode(
+                Payload::TmPing(TmPing {
+                    r#type: PingType::PtPing as i32,
+                    seq: Some(seq),
+                    ping_time: None,
+                    net_time: None,
+                }),
+                &mut frame,
+            )
+            .unwrap();
+        frame.to_vec()
+    }
+
+    // Builds a raw IPv4 packet with a TCP segment.
+    fn packet(seq: u32, payload: &[u8]) -> Vec<u8> {
+        let mut packet = vec![0u8; 40];
+        packet[0] = 0x45;
+        packet[2..4].copy_from_slice(&((40 + payload.len()) as u16).to_be_bytes());
+        packet[9] = IP_PROTOCOL_TCP;
+        packet[12..16].copy_from_slice(&[127, 0, 0, 2]);
+        packet[16..20].copy_from_slice(&[127, 0, 0, 1]);
+        packet[20..22].copy_from_slice(&51235u16.to_be_bytes());
+        packet[22..24].copy_from_slice(&40000u16.to_be_bytes());
+        packet[24..28].copy_from_slice(&seq.to_be_bytes());
+        packet[32] = 5 << 4;
+        packet.extend_from_slice(payload);
+        packet
+    }
+
+    fn capture(packets: &[Vec<u8>]) -> Vec<u8> {
+        let mut capture = Vec::new();
+        capture.extend_from_slice(&MAGIC_MICROS.to_le_bytes());
+        capture.extend_from_slice(&[2, 0, 4, 0]);
+        capture.extend_from_slice(&[0; 12]);
+        capture.extend_from_slice(&LINKTYPE_RAW.to_le_bytes());
+        for packet in packets {
+            capture.extend_from_slice(&[0; 8]);
+            capture.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+            capture.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+            capture.extend_from_slice(packet);
+        }
+        capture
+    }
+
+    #[test]
+    fn reassembles_and_decodes_out_of_order_segments() {
+        let http = b"HTTP/1.1 101 Switching Protocols\r\nUpgrade: XRPL/2.2\r\n\r\n".to_vec();
+        let mut stream = http.clone();
+        stream.extend(ping_frame(1));
+        stream.extend(ping_frame(2));
+
+        let (first, second) = stream.split_at(http.len() + 5);
+        let second_seq = 1000 + first.len() as u32;
+        // Out of order, with the first segment retransmitted.
+        let packets = [
+            packet(second_seq, second),
+            packet(1000, first),
+            packet(1000, first),
+        ];
+
+        let streams = parse_pcap(&capture(&packets)).unwrap();
+        assert_eq!(streams.len(), 1);
+        assert_eq!(streams[0].data, stream);
+
+        let decoded = decode_stream(&streams[0]);
+        assert!(decoded.error.is_none(), "{:?}", decoded.error);
+        assert_eq!(decoded.messages.len(), 2);
+        assert_eq!(decoded.messages[1].frame, ping_frame(2));
+    }
+}
@@ -0,0 +1,302 @@
+//! The protocol state of a synthetic node's peers, as gleaned from the messages they send.
+//!
+//! Each connection keeps the [TmStatusChange] messages in the order they were received, so tests
+//! can assert on the transitions as well as on the latest state, along with the latest ledger
+//! the peer's validations refer to.
+//!
+//! The same messages tell when the peer closes ledgers, see [LedgerAdvance].
+
+use std::{collections::HashMap, net::SocketAddr};
+
+use crate::{
+    protocol::{
+        codecs::message::Payload,
+        proto::{NodeEvent, NodeStatus, TmStatusChange},
+    },
+    tools::validation::validation_ledger,
+};
+
+/// A ledger, identified by its sequence and hash.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LedgerId {
+    pub seq: u32,
+    pub hash: [u8; 32],
+}
+
+/// The state a peer reported over a connection.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct PeerStatus {
+    /// The status changes received, oldest first.
+    pub changes: Vec<TmStatusChange>,
+    /// The ledger of the latest validation relayed by the peer, by sequence.
+    pub validated_ledger: Option<LedgerId>,
+}
+
+impl PeerStatus {
+    /// Returns the latest status change.
+    pub fn last_change(&self) -> Option<&TmStatusChange> {
+        self.changes.last()
+    }
+
+    /// Returns the latest status the peer announced, the status is optional in a change.
+    pub fn status(&self) -> Option<NodeStatus> {
+        self.changes
+            .iter()
+            .rev()
+            .find_map(|change| change.new_status.and_then(NodeStatus::from_i32))
+    }
+
+    /// Returns the statuses announced, in order, without repeating consecutive ones.
+    pub fn status_transitions(&self) -> Vec<NodeStatus> {
+        let mut transitions = self
+            .changes
+            .iter()
+            .filter_map(|change| change.new_status.and_then(NodeStatus::from_i32))
+            .collect::<Vec<_>>();
+        transitions.dedup();
+        transitions
+    }
+
+    /// Returns the events announced, in order.
+    pub fn events(&self) -> Vec<NodeEvent> {
+        self.changes
+            .iter()
+            .filter_map(|change| change.new_event.and_then(NodeEvent::from_i32))
+            .collect()
+    }
+
+    /// Returns the ledgers announced in the status changes, in order.
+    pub fn ledgers(&self) -> Vec<LedgerId> {
+        self.changes
+            .iter()
+            .filter_map(|change| {
+                Some(LedgerId {
+                    seq: change.ledger_seq?,
+                    hash: change.ledger_hash.as_deref()?.try_into().ok()?,
+                })
+            })
+            .collect()
+    }
+
+    /// Returns the latest ledger announced in a status change.
+    pub fn ledger(&self) -> Option<LedgerId> {
+        self.ledgers().pop()
+    }
+
+    /// Returns the sequence of the latest ledger the peer announced or validated.
+    pub fn latest_ledger_seq(&self) -> Option<u32> {
+        let announced = self.ledger().map(|ledger| ledger.seq);
+        let validated = self.validated_ledger.map(|ledger| ledger.seq);
+        announced.max(validated)
+    }
+
+    /// Returns the range of ledgers the peer announced it has, as of the latest status change
+    /// carrying one.
+    pub fn ledger_range(&self) -> Option<(u32, u32)> {
+        self.changes
+            .iter()
+            .rev()
+            .find_map(|change| Some((change.first_seq?, change.last_seq?)))
+    }
+
+    fn record(&mut self, payload: &Payload) {
+        match payload {
+            Payload::TmStatusChange(change) => self.changes.push(change.clone()),
+            Payload::TmValidation(validation) => {
+                if let Some((seq, hash)) = validation_ledger(&validation.validation) {
+                    if self.validated_ledger.is_none_or(|ledger| ledger.seq <= seq) {
+                        self.validated_ledger = Some(LedgerId { seq, hash });
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The ledgers a peer closed since a starting ledger, as gleaned from its messages.
+///
+/// A ledger's sequence is taken from both the status changes and the validations, whichever
+/// arrives first, so the count doesn't depend on the peer being a validator.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct LedgerAdvance {
+    start: Option<u32>,
+    latest: Option<u32>,
+}
+
+impl LedgerAdvance {
+    /// Starts counting from the ledger, or from the first one seen if unknown.
+    pub fn new(start: Option<u32>) -> Self {
+        Self {
+            start,
+            latest: start,
+        }
+    }
+
+    /// Takes the ledger the message refers to into account, if any.
+    pub fn record(&mut self, payload: &Payload) {
+        if let Some(seq) = ledger_seq(payload) {
+            self.start.get_or_insert(seq);
+            self.latest = self.latest.max(Some(seq));
+        }
+    }
+
+    /// Returns the number of ledgers closed since the start.
+    pub fn advanced(&self) -> u32 {
+        match (self.start, self.latest) {
+            (Some(start), Some(latest)) => latest.saturating_sub(start),
+            _ => 0,
+        }
+    }
+
+    /// Returns the sequence of the latest ledger seen.
+    pub fn latest(&self) -> Option<u32> {
+        self.latest
+    }
+}
+
+/// Returns the sequence of the ledger a status change or a validation refers to.
+pub fn ledger_seq(payload: &Payload) -> Option<u32> {
+    match payload {
+        Payload::TmStatusChange(change) => change.ledger_seq,
+        Payload::TmValidation(validation) => {
+            validation_ledger(&validation.validation).map(|(seq, _)| seq)
+        }
+        _ => None,
+    }
+}
+
+/// The state of each connected peer, see [PeerStatus].
+#[derive(Debug, Default, Clone)]
+pub struct PeerStatusTracker {
+    peers: HashMap<SocketAddr, PeerStatus>,
+}
+
+impl PeerStatusTracker {
+    /// Starts tracking a new connection with the peer, forgetting the previous one.
+    pub(crate) fn connected(&mut self, addr: SocketAddr) {
+        self.peers.insert(addr, Default::default());
+    }
+
+    pub(crate) fn record(&mut self, source: SocketAddr, payload: &Payload) {
+        if matches!(
+            payload,
+            Payload::TmStatusChange(_) | Payload::TmValidation(_)
+        ) {
+            self.peers.entry(source).or_default().record(payload);
+        }
+    }
+
+    /// Returns the state of the latest connection with the peer.
+    pub fn get(&self, addr: SocketAddr) -> Option<&PeerStatus> {
+        self.peers.get(&addr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        protocol::proto::TmValidation,
+        tools::{
+            validation::ValidationBuilder,
+            validator::{KeyType, ValidatorKey},
+        },
+    };
+
+    fn status_change(status: NodeStatus, event: NodeEvent, seq: u32) -> Payload {
+        Payload::TmStatusChange(TmStatusChange {
+            new_status: Some(status as i32),
+            new_event: Some(event as i32),
+            ledger_seq: Some(seq),
+            ledger_hash: Some(vec![seq as u8; 32]),
+            first_seq: Some(1),
+            last_seq: Some(seq),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn transitions_are_tracked_per_connection() {
+        let addr = "127.0.0.1:51235".parse().unwrap();
+        let mut tracker = PeerStatusTracker::default();
+        tracker.connected(addr);
+
+        let key = ValidatorKey::generate(KeyType::Secp256k1);
+        for payload in [
+            status_change(NodeStatus::NsConnected, NodeEvent::NeSwitchedLedger, 2),
+            ValidationBuilder::new([5; 32], 5).payload(&key),
+            status_change(NodeStatus::NsConnected, NodeEvent::NeClosingLedger, 3),
+            status_change(NodeStatus::NsValidating, NodeEvent::NeAcceptedLedger, 4),
+            // An older ledger's validation doesn't replace the latest one.
+            ValidationBuilder::new([3; 32], 3).payload(&key),
+            Payload::TmValidation(TmValidation::default()),
+        ] {
+            tracker.record(addr, &payload);
+        }
+
+        let status = tracker.get(addr).unwrap();
+        assert_eq!(status.changes.len(), 3);
+        assert_eq!(status.status(), Some(NodeStatus::NsValidating));
+        assert_eq!(
+            status.status_transitions(),
+            [NodeStatus::NsConnected, NodeStatus::NsValidating]
+        );
+        assert_eq!(
+            status.events(),
+            [
+                NodeEvent::NeSwitchedLedger,
+                NodeEvent::NeClosingLedger,
+                NodeEvent::NeAcceptedLedger
+            ]
+        );
+        assert_eq!(
+            status.ledger(),
+            Some(LedgerId {
+                seq: 4,
+                hash: [4; 32]
+            })
+        );
+        assert_eq!(status.ledgers().len(), 3);
+        assert_eq!(status.ledger_range(), Some((1, 4)));
+        assert_eq!(
+            status.validated_ledger,
+            Some(LedgerId {
+                seq: 5,
+                hash: [5; 32]
+            })
+        );
+
+        assert_eq!(status.latest_ledger_seq(), Some(5));
+
+        // A new connection starts afresh.
+        tracker.connected(addr);
+        assert_eq!(tracker.get(addr), Some(&PeerStatus::default()));
+    }
+
+    #[test]
+    fn ledger_advance_is_counted() {
+        let key = ValidatorKey::generate(KeyType::Secp256k1);
+        let mut advance = LedgerAdvance::new(None);
+        assert_eq!(advance.advanced(), 0);
+
+        advance.record(&status_change(
+            NodeStatus::NsConnected,
+            NodeEvent::NeAcceptedLedger,
+            7,
+        ));
+        advance.record(&ValidationBuilder::new([9; 32], 9).payload(&key));
+        // Late messages about older ledgers don't count.
+        advance.record(&status_change(
+            NodeStatus::NsConnected,
+            NodeEvent::NeClosingLedger,
+            8,
+        ));
+        assert_eq!(advance.advanced(), 2);
+        assert_eq!(advance.latest(), Some(9));
+
+        let mut advance = LedgerAdvance::new(Some(10));
+        advance.record(&ValidationBuilder::new([9; 32], 9).payload(&key));
+        assert_eq!(advance.advanced(), 0);
+    }
+}
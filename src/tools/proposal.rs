@@ -0,0 +1,118 @@
+//! Signed ledger proposals, as sent by validators during consensus.
+//!
+//! A proposal is signed over the SHA512-Half digest of the proposal prefix followed by the
+//! proposal's sequence, close time, previous ledger and proposed transaction set.
+
+use bytes::{BufMut, BytesMut};
+
+use crate::{
+    protocol::{codecs::message::Payload, proto::TmProposeSet},
+    tools::{
+        ripple_time,
+        validator::{sha512_half, verify_digest, ValidatorKey},
+    },
+};
+
+/// The prefix used when hashing a proposal for signing.
+const PROPOSAL_PREFIX: &[u8] = b"PRP\x00";
+
+/// Builder for a [TmProposeSet], signed with [ProposalBuilder::sign].
+#[derive(Debug, Clone)]
+pub struct ProposalBuilder {
+    propose_seq: u32,
+    close_time: u32,
+    previous_ledger: [u8; 32],
+    position: [u8; 32],
+}
+
+impl ProposalBuilder {
+    /// Creates a builder for the first proposal of the transaction set `position` on top of
+    /// `previous_ledger`, closing now.
+    pub fn new(previous_ledger: [u8; 32], position: [u8; 32]) -> Self {
+        Self {
+            propose_seq: 0,
+            close_time: ripple_time::now(),
+            previous_ledger,
+            position,
+        }
+    }
+
+    /// Sets the proposal's sequence, which grows as the validator changes its position.
+    pub fn propose_seq(mut self, propose_seq: u32) -> Self {
+        self.propose_seq = propose_seq;
+        self
+    }
+
+    /// Sets the proposed close time in seconds since the Ripple epoch.
+    pub fn close_time(mut self, close_time: u32) -> Self {
+        self.close_time = close_time;
+        self
+    }
+
+    /// Returns the digest signed by the validator.
+    pub fn signing_hash(&self) -> [u8; 32] {
+        let mut buf = BytesMut::with_capacity(PROPOSAL_PREFIX.len() + 4 + 4 + 32 + 32);
+        buf.put(PROPOSAL_PREFIX);
+        buf.put_u32(self.propose_seq);
+        buf.put_u32(self.close_time);
+        buf.put(&self.previous_ledger[..]);
+        buf.put(&self.position[..]);
+
+        sha512_half(&buf)
+    }
+
+    /// Signs the proposal with the validator's signing key, which rippled requires to be a
+    /// secp256k1 key.
+    pub fn sign(&self, key: &ValidatorKey) -> TmProposeSet {
+        TmProposeSet {
+            propose_seq: self.propose_seq,
+            current_tx_hash: self.position.to_vec(),
+            node_pub_key: key.public_key(),
+            close_time: self.close_time,
+            signature: key.sign_digest(&self.signing_hash()),
+            previousledger: self.previous_ledger.to_vec(),
+            ..Default::default()
+        }
+    }
+
+    /// Signs the proposal and wraps it in a payload.
+    pub fn payload(&self, key: &ValidatorKey) -> Payload {
+        Payload::TmProposeLedger(self.sign(key))
+    }
+}
+
+/// Checks the proposal's signature against its public key.
+pub fn verify_proposal(proposal: &TmProposeSet) -> bool {
+    let (Ok(previous_ledger), Ok(position)) = (
+        proposal.previousledger.as_slice().try_into(),
+        proposal.current_tx_hash.as_slice().try_into(),
+    ) else {
+        return false;
+    };
+
+    let signing_hash = ProposalBuilder::new(previous_ledger, position)
+        .propose_seq(proposal.propose_seq)
+        .close_time(proposal.close_time)
+        .signing_hash();
+
+    verify_digest(&proposal.node_pub_key, &signing_hash, &proposal.signature)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::validator::KeyType;
+
+    #[test]
+    fn signed_proposal_verifies() {
+        let key = ValidatorKey::generate(KeyType::Secp256k1);
+        let mut proposal = ProposalBuilder::new([1; 32], [2; 32])
+            .propose_seq(3)
+            .sign(&key);
+        assert!(verify_proposal(&proposal));
+
+        // The signature covers the proposed position.
+        proposal.current_tx_hash = vec![3; 32];
+        assert!(!verify_proposal(&proposal));
+    }
+}
@@ -0,0 +1,136 @@
+//! A man-in-the-middle proxy observing the traffic between two rippled nodes.
+//!
+//! The proxy accepts a connection from the source node and connects to the target node, doing
+//! a separate handshake on each side with its own identity. Every message is decoded, recorded
+//! and relayed to the other side, so both nodes behave as if they were talking to a real peer.
+
+use std::{
+    io,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use tokio::{sync::oneshot, task::JoinHandle, time::Instant};
+use tracing::warn;
+
+use crate::{
+    protocol::codecs::message::Payload,
+    tools::{config::SynthNodeCfg, synth_node::SyntheticNode},
+};
+
+/// The direction a message travelled in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// From the node connected to the proxy to the target node.
+    SourceToTarget,
+    /// From the target node to the node connected to the proxy.
+    TargetToSource,
+}
+
+/// A message relayed by the proxy.
+#[derive(Debug, Clone)]
+pub struct ObservedMessage {
+    pub direction: Direction,
+    /// Time since the proxy was started.
+    pub elapsed: Duration,
+    pub payload: Payload,
+}
+
+pub struct MitmProxy {
+    listening_addr: SocketAddr,
+    messages: Arc<Mutex<Vec<ObservedMessage>>>,
+    stop: oneshot::Sender<()>,
+    relay: JoinHandle<()>,
+}
+
+impl MitmProxy {
+    /// Starts a proxy relaying the traffic of the node which connects to it to the target.
+    ///
+    /// The proxy connects to the target once the source node sends its first message.
+    pub async fn new(target: SocketAddr, cfg: &SynthNodeCfg) -> io::Result<Self> {
+        let synth_node = SyntheticNode::new(cfg).await;
+        let listening_addr = synth_node.start_listening().await?;
+
+        let messages = Arc::new(Mutex::new(Vec::new()));
+        let (stop, stop_rx) = oneshot::channel();
+        let relay = tokio::spawn(relay(synth_node, target, messages.clone(), stop_rx));
+
+        Ok(Self {
+            listening_addr,
+            messages,
+            stop,
+            relay,
+        })
+    }
+
+    /// Returns the address the source node should connect to.
+    pub fn listening_addr(&self) -> SocketAddr {
+        self.listening_addr
+    }
+
+    /// Returns the messages relayed so far, in the order they were received.
+    pub fn messages(&self) -> Vec<ObservedMessage> {
+        self.messages.lock().unwrap().clone()
+    }
+
+    /// Returns the messages relayed so far in the given direction.
+    pub fn messages_in(&self, direction: Direction) -> Vec<ObservedMessage> {
+        self.messages
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|message| message.direction == direction)
+            .cloned()
+            .collect()
+    }
+
+    /// Stops relaying and disconnects from both nodes.
+    pub async fn shut_down(self) {
+        let _ = self.stop.send(());
+        let _ = self.relay.await;
+    }
+}
+
+async fn relay(
+    mut synth_node: SyntheticNode,
+    target: SocketAddr,
+    messages: Arc<Mutex<Vec<ObservedMessage>>>,
+    mut stop: oneshot::Receiver<()>,
+) {
+    let start = Instant::now();
+    let mut source = None;
+
+    loop {
+        let (addr, message) = tokio::select! {
+            _ = &mut stop => break,
+            message = synth_node.recv_message() => message,
+        };
+
+        let (direction, destination) = if addr == target {
+            (Direction::TargetToSource, source)
+        } else {
+            source = Some(addr);
+            if !synth_node.is_connected(target) {
+                if let Err(e) = synth_node.connect(target).await {
+                    warn!("the proxy is unable to connect to {target}: {e}");
+                }
+            }
+            (Direction::SourceToTarget, Some(target))
+        };
+
+        if let Some(destination) = destination {
+            if let Err(e) = synth_node.unicast(destination, message.payload.clone()) {
+                warn!("the proxy is unable to relay a message to {destination}: {e}");
+            }
+        }
+
+        messages.lock().unwrap().push(ObservedMessage {
+            direction,
+            elapsed: start.elapsed(),
+            payload: message.payload,
+        });
+    }
+
+    synth_node.shut_down().await;
+}
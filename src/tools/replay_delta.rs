@@ -0,0 +1,239 @@
+//! Ledger replay deltas, i.e. a ledger's header and transactions, as exchanged by peers with the
+//! `ledgerreplay` feature enabled.
+//!
+//! Each transaction of a [TmReplayDeltaResponse] is the serialized transaction followed by its
+//! metadata, both prefixed by their lengths.
+
+use std::collections::HashMap;
+
+use thiserror::Error;
+
+use crate::{
+    protocol::{
+        ledger::{LedgerHeader, LedgerHeaderError},
+        proto::{TmReplayDeltaRequest, TmReplayDeltaResponse, TmReplyError},
+        stobject::{decode_vl_length, encode_vl_length, StObjectError},
+    },
+    tools::{tx::TX_ID_PREFIX, validator::sha512_half},
+};
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ReplayDeltaError {
+    #[error("the peer replied with {0:?}")]
+    Refused(TmReplyError),
+
+    #[error("the reply has no ledger header")]
+    MissingHeader,
+
+    #[error("invalid ledger header: {0}")]
+    Header(#[from] LedgerHeaderError),
+
+    #[error("the ledger header doesn't hash to the ledger's hash")]
+    HashMismatch,
+
+    #[error("invalid transaction: {0}")]
+    Transaction(#[from] StObjectError),
+}
+
+/// A transaction of a ledger, along with its metadata.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplayTransaction {
+    pub tx: Vec<u8>,
+    pub meta: Vec<u8>,
+}
+
+impl ReplayTransaction {
+    pub fn parse(mut bytes: &[u8]) -> Result<Self, StObjectError> {
+        let tx = read_vl(&mut bytes)?;
+        let meta = read_vl(&mut bytes)?;
+        Ok(Self { tx, meta })
+    }
+
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut bytes = encode_vl_length(self.tx.len());
+        bytes.extend_from_slice(&self.tx);
+        bytes.extend_from_slice(&encode_vl_length(self.meta.len()));
+        bytes.extend_from_slice(&self.meta);
+        bytes
+    }
+
+    /// Returns the transaction's ID.
+    pub fn hash(&self) -> [u8; 32] {
+        let mut buf = TX_ID_PREFIX.to_vec();
+        buf.extend_from_slice(&self.tx);
+        sha512_half(&buf)
+    }
+}
+
+/// The header and transactions of a ledger.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplayDelta {
+    pub header: LedgerHeader,
+    pub transactions: Vec<ReplayTransaction>,
+}
+
+impl ReplayDelta {
+    /// Decodes the response and checks its header hashes to the ledger hash it's sent for.
+    ///
+    /// The transactions aren't checked against the header's transaction tree hash.
+    pub fn from_response(response: &TmReplayDeltaResponse) -> Result<Self, ReplayDeltaError> {
+        if let Some(error) = response.error {
+            return Err(ReplayDeltaError::Refused(
+                TmReplyError::from_i32(error).unwrap_or(TmReplyError::ReBadRequest),
+            ));
+        }
+
+        let header = response
+            .ledger_header
+            .as_deref()
+            .ok_or(ReplayDeltaError::MissingHeader)?;
+        let header = LedgerHeader::parse(header)?;
+        if header.hash().as_slice() != response.ledger_hash {
+            return Err(ReplayDeltaError::HashMismatch);
+        }
+
+        let transactions = response
+            .transaction
+            .iter()
+            .map(|tx| ReplayTransaction::parse(tx))
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            header,
+            transactions,
+        })
+    }
+
+    /// Returns the ledger's hash.
+    pub fn hash(&self) -> [u8; 32] {
+        self.header.hash()
+    }
+
+    /// Returns the IDs of the ledger's transactions, in the order they were sent.
+    pub fn transaction_hashes(&self) -> Vec<[u8; 32]> {
+        self.transactions
+            .iter()
+            .map(ReplayTransaction::hash)
+            .collect()
+    }
+
+    pub fn to_response(&self) -> TmReplayDeltaResponse {
+        TmReplayDeltaResponse {
+            ledger_hash: self.hash().to_vec(),
+            ledger_header: Some(self.header.serialize()),
+            transaction: self
+                .transactions
+                .iter()
+                .map(ReplayTransaction::serialize)
+                .collect(),
+            error: None,
+        }
+    }
+}
+
+/// The replay deltas a synthetic node serves, keyed by their ledger hashes.
+#[derive(Debug, Default, Clone)]
+pub struct ReplayDeltaStore {
+    deltas: HashMap<Vec<u8>, ReplayDelta>,
+}
+
+impl ReplayDeltaStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stores the delta under its ledger's hash.
+    pub fn insert(&mut self, delta: ReplayDelta) {
+        self.deltas.insert(delta.hash().to_vec(), delta);
+    }
+
+    pub fn get(&self, ledger_hash: &[u8]) -> Option<&ReplayDelta> {
+        self.deltas.get(ledger_hash)
+    }
+
+    /// Builds the reply to the request, like rippled it's an error if the ledger isn't known.
+    pub fn reply(&self, request: &TmReplayDeltaRequest) -> TmReplayDeltaResponse {
+        match self.get(&request.ledger_hash) {
+            Some(delta) => delta.to_response(),
+            None => TmReplayDeltaResponse {
+                ledger_hash: request.ledger_hash.clone(),
+                ledger_header: None,
+                transaction: vec![],
+                error: Some(TmReplyError::ReNoLedger as i32),
+            },
+        }
+    }
+}
+
+fn read_vl(bytes: &mut &[u8]) -> Result<Vec<u8>, StObjectError> {
+    let len = decode_vl_length(bytes)?;
+    if bytes.len() < len {
+        return Err(StObjectError::UnexpectedEnd);
+    }
+    let (data, rest) = bytes.split_at(len);
+    *bytes = rest;
+    Ok(data.to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn delta() -> ReplayDelta {
+        ReplayDelta {
+            header: LedgerHeader {
+                seq: 3,
+                drops: 1_000,
+                parent_hash: [1; 32],
+                tx_hash: [2; 32],
+                account_hash: [3; 32],
+                parent_close_time: 10,
+                close_time: 20,
+                close_time_resolution: 30,
+                close_flags: 0,
+            },
+            transactions: vec![ReplayTransaction {
+                tx: vec![4; 200],
+                meta: vec![5; 10],
+            }],
+        }
+    }
+
+    #[test]
+    fn served_delta_is_verified() {
+        let delta = delta();
+        let mut store = ReplayDeltaStore::new();
+        store.insert(delta.clone());
+
+        let response = store.reply(&TmReplayDeltaRequest {
+            ledger_hash: delta.hash().to_vec(),
+        });
+        assert_eq!(ReplayDelta::from_response(&response), Ok(delta.clone()));
+
+        let unknown = store.reply(&TmReplayDeltaRequest {
+            ledger_hash: vec![9; 32],
+        });
+        assert_eq!(
+            ReplayDelta::from_response(&unknown),
+            Err(ReplayDeltaError::Refused(TmReplyError::ReNoLedger))
+        );
+
+        let forged = TmReplayDeltaResponse {
+            ledger_hash: vec![9; 32],
+            ..response.clone()
+        };
+        assert_eq!(
+            ReplayDelta::from_response(&forged),
+            Err(ReplayDeltaError::HashMismatch)
+        );
+
+        let truncated = TmReplayDeltaResponse {
+            transaction: vec![response.transaction[0][..100].to_vec()],
+            ..response
+        };
+        assert_eq!(
+            ReplayDelta::from_response(&truncated),
+            Err(ReplayDeltaError::Transaction(StObjectError::UnexpectedEnd))
+        );
+    }
+}
@@ -0,0 +1,63 @@
+//! Conversions between Unix time and the Ripple epoch used on the wire.
+//!
+//! Network times (validator list expirations, validation signing times, ledger close times) are
+//! counted in seconds since Jan-1-2000.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Ripple epoch starts at Jan-1-2000. The number here equals number of seconds since unix epoch (Jan-1-1970)
+pub const RIPPLE_EPOCH: u32 = 946684800;
+
+/// Close time resolutions a ledger can use, in seconds.
+pub const CLOSE_TIME_RESOLUTIONS: [u32; 6] = [10, 20, 30, 60, 90, 120];
+
+/// The close time resolution of the genesis ledger, in seconds.
+pub const DEFAULT_CLOSE_TIME_RESOLUTION: u32 = 30;
+
+/// Returns the current time in seconds since the Ripple epoch.
+pub fn now() -> u32 {
+    to_ripple_time(SystemTime::now())
+}
+
+/// Converts the time to seconds since the Ripple epoch, times before the epoch become zero.
+pub fn to_ripple_time(time: SystemTime) -> u32 {
+    let unix_secs = time
+        .duration_since(UNIX_EPOCH)
+        .expect("time went backwards")
+        .as_secs();
+    unix_secs.saturating_sub(RIPPLE_EPOCH as u64) as u32
+}
+
+/// Converts seconds since the Ripple epoch to the time.
+pub fn from_ripple_time(secs: u32) -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(RIPPLE_EPOCH as u64 + secs as u64)
+}
+
+/// Rounds the close time to the nearest multiple of the resolution, the same way rippled does
+/// when closing a ledger. Zero means the close time is unknown and is left as is.
+pub fn round_close_time(close_time: u32, resolution: u32) -> u32 {
+    if close_time == 0 {
+        return 0;
+    }
+    let close_time = close_time + resolution / 2;
+    close_time - close_time % resolution
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conversions_and_rounding() {
+        // 2023-01-01T00:00:00Z.
+        let time = UNIX_EPOCH + Duration::from_secs(1_672_531_200);
+        assert_eq!(to_ripple_time(time), 725_846_400);
+        assert_eq!(from_ripple_time(725_846_400), time);
+        assert_eq!(to_ripple_time(UNIX_EPOCH), 0);
+
+        assert_eq!(round_close_time(0, 30), 0);
+        assert_eq!(round_close_time(44, 30), 30);
+        assert_eq!(round_close_time(45, 30), 60);
+        assert_eq!(round_close_time(60, 30), 60);
+    }
+}
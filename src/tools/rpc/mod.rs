@@ -0,0 +1,528 @@
+use std::time::Duration;
+
+use reqwest::{
+    header::{ACCEPT, CONTENT_TYPE},
+    Client, RequestBuilder,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tokio::time::{error::Elapsed, sleep, timeout};
+
+use crate::tools::constants::EXPECTED_RESULT_TIMEOUT;
+
+pub mod ws;
+
+const API_VERSION: u32 = 1;
+
+/// The most objects the node returns per `ledger_data` or `account_objects` page, rippled caps the
+/// limits of non-admin requests to lower values.
+pub const MAX_PAGE_SIZE: u32 = 2048;
+
+pub async fn wait_for_state(rpc_url: &str, state: String) {
+    tokio::time::timeout(EXPECTED_RESULT_TIMEOUT, async move {
+        loop {
+            if let Ok(response) = get_server_info(rpc_url).await {
+                if response.result.info.server_state == state {
+                    break;
+                }
+            }
+            sleep(Duration::from_millis(100)).await;
+        }
+    })
+    .await
+    .unwrap()
+}
+
+pub async fn wait_for_account_data(
+    rpc_url: &str,
+    account: &str,
+    timeout: Duration,
+) -> Result<RpcResponse<AccountInfoResponse>, Elapsed> {
+    tokio::time::timeout(timeout, async move {
+        loop {
+            if let Ok(account_data) = get_account_info(rpc_url, account).await {
+                return account_data;
+            }
+            sleep(Duration::from_millis(100)).await;
+        }
+    })
+    .await
+}
+
+pub async fn wait_for_ledger_info(
+    rpc_url: &str,
+) -> Result<RpcResponse<LedgerInfoResponse>, Elapsed> {
+    timeout(EXPECTED_RESULT_TIMEOUT, async {
+        loop {
+            if let Ok(info) = get_ledger_info(rpc_url).await {
+                return info;
+            }
+            sleep(Duration::from_millis(250)).await;
+        }
+    })
+    .await
+}
+
+/// Waits until the node reports the given number of connected peers.
+pub async fn wait_for_peer_count(
+    rpc_url: &str,
+    peers: u32,
+    timeout: Duration,
+) -> Result<(), Elapsed> {
+    tokio::time::timeout(timeout, async move {
+        loop {
+            if let Ok(response) = get_server_info(rpc_url).await {
+                if response.result.info.peers == peers {
+                    break;
+                }
+            }
+            sleep(Duration::from_millis(100)).await;
+        }
+    })
+    .await
+}
+
+async fn execute_rpc<T: for<'a> Deserialize<'a>>(
+    rpc_url: &str,
+    body: &impl Serialize,
+) -> anyhow::Result<T> {
+    let response = build_json_request(rpc_url, body).send().await?;
+    Ok(response.error_for_status()?.json::<T>().await?)
+}
+
+pub async fn get_account_info(
+    rpc_url: &str,
+    account: &str,
+) -> anyhow::Result<RpcResponse<AccountInfoResponse>> {
+    execute_rpc(rpc_url, &build_account_info_request(account)).await
+}
+
+async fn get_server_info(rpc_url: &str) -> anyhow::Result<RpcResponse<ResultResponse>> {
+    let request: RpcRequest<Option<()>> = RpcRequest {
+        id: String::from("1"),
+        method: String::from("server_info"),
+        api_version: API_VERSION,
+        params: None,
+    };
+    execute_rpc(rpc_url, &request).await
+}
+
+/// Fetches the node's connected peers with the `peers` admin method.
+pub async fn get_peers(rpc_url: &str) -> anyhow::Result<RpcResponse<PeersResponse>> {
+    let request: RpcRequest<Option<()>> = RpcRequest {
+        id: String::from("1"),
+        method: String::from("peers"),
+        api_version: API_VERSION,
+        params: None,
+    };
+    execute_rpc(rpc_url, &request).await
+}
+
+pub async fn get_transaction_info(
+    rpc_url: &str,
+    transaction: String,
+) -> anyhow::Result<RpcResponse<TransactionInfoResponse>> {
+    execute_rpc(rpc_url, &build_transaction_info_request(transaction)).await
+}
+
+pub async fn get_ledger_info(rpc_url: &str) -> anyhow::Result<RpcResponse<LedgerInfoResponse>> {
+    let request = RpcRequest {
+        id: String::from("1"),
+        method: String::from("ledger"),
+        api_version: API_VERSION,
+        params: vec![LedgerInfoRequest {
+            ledger_index: "validated".to_string(),
+            accounts: true,
+            full: false,
+            transactions: false,
+            expand: false,
+            owner_funds: false,
+        }],
+    };
+    execute_rpc(rpc_url, &request).await
+}
+
+/// Fetches the IDs of the transactions of the ledger with the hash.
+pub async fn get_ledger_transactions(
+    rpc_url: &str,
+    ledger_hash: &str,
+) -> anyhow::Result<RpcResponse<LedgerTransactionsResponse>> {
+    let request = RpcRequest {
+        id: String::from("1"),
+        method: String::from("ledger"),
+        api_version: API_VERSION,
+        params: vec![LedgerTransactionsRequest {
+            ledger_hash: ledger_hash.to_owned(),
+            transactions: true,
+            expand: false,
+        }],
+    };
+    execute_rpc(rpc_url, &request).await
+}
+
+/// Fetches a page of the state objects of the ledger with the hash.
+///
+/// The first page is fetched without a marker, the next ones with the marker of the previous page.
+pub async fn get_ledger_data(
+    rpc_url: &str,
+    ledger_hash: &str,
+    limit: u32,
+    marker: Option<Value>,
+) -> anyhow::Result<RpcResponse<LedgerDataResponse>> {
+    let request = RpcRequest {
+        id: String::from("1"),
+        method: String::from("ledger_data"),
+        api_version: API_VERSION,
+        params: vec![LedgerDataRequest {
+            ledger_hash: ledger_hash.to_owned(),
+            binary: false,
+            limit,
+            marker,
+        }],
+    };
+    execute_rpc(rpc_url, &request).await
+}
+
+/// Fetches all the state objects of the ledger with the hash, paging through the `ledger_data`
+/// results.
+pub async fn get_all_ledger_data(
+    rpc_url: &str,
+    ledger_hash: &str,
+) -> anyhow::Result<Vec<LedgerObject>> {
+    let mut objects = Vec::new();
+    let mut marker = None;
+    loop {
+        let page = get_ledger_data(rpc_url, ledger_hash, MAX_PAGE_SIZE, marker)
+            .await?
+            .result;
+        objects.extend(page.state);
+        match page.marker {
+            Some(next) => marker = Some(next),
+            None => return Ok(objects),
+        }
+    }
+}
+
+/// Fetches the objects owned by the account in the ledger with the hash, e.g. its offers and
+/// trust lines but not its account root.
+///
+/// Only objects of the type are returned if set, e.g. `offer` or `state`. The objects are paged
+/// through like [get_all_ledger_data] does.
+pub async fn get_account_objects(
+    rpc_url: &str,
+    account: &str,
+    ledger_hash: &str,
+    object_type: Option<&str>,
+) -> anyhow::Result<Vec<LedgerObject>> {
+    let mut objects = Vec::new();
+    let mut marker = None;
+    loop {
+        let request = RpcRequest {
+            id: String::from("1"),
+            method: String::from("account_objects"),
+            api_version: API_VERSION,
+            params: vec![AccountObjectsRequest {
+                account: account.to_owned(),
+                ledger_hash: ledger_hash.to_owned(),
+                r#type: object_type.map(str::to_owned),
+                limit: MAX_PAGE_SIZE,
+                marker,
+            }],
+        };
+        let page: RpcResponse<AccountObjectsResponse> = execute_rpc(rpc_url, &request).await?;
+        objects.extend(page.result.account_objects);
+        match page.result.marker {
+            Some(next) => marker = Some(next),
+            None => return Ok(objects),
+        }
+    }
+}
+
+pub async fn submit_transaction(
+    rpc_url: &str,
+    tx_blob: String,
+    fail_hard: bool,
+) -> anyhow::Result<RpcResponse<SubmitTransactionResponse>> {
+    let request = build_submit_transaction_request(tx_blob, fail_hard);
+    execute_rpc(rpc_url, &request).await
+}
+
+#[derive(Serialize)]
+struct LedgerInfoRequest {
+    ledger_index: String,
+    accounts: bool,
+    full: bool,
+    transactions: bool,
+    expand: bool,
+    owner_funds: bool,
+}
+
+#[derive(Serialize)]
+struct LedgerTransactionsRequest {
+    ledger_hash: String,
+    transactions: bool,
+    expand: bool,
+}
+
+#[derive(Serialize)]
+struct LedgerDataRequest {
+    ledger_hash: String,
+    binary: bool,
+    limit: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    marker: Option<Value>,
+}
+
+#[derive(Serialize)]
+struct AccountObjectsRequest {
+    account: String,
+    ledger_hash: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    r#type: Option<String>,
+    limit: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    marker: Option<Value>,
+}
+
+fn build_transaction_info_request(transaction: String) -> RpcRequest<Vec<TransactionInfoRequest>> {
+    RpcRequest {
+        id: String::from("1"),
+        method: String::from("tx"),
+        api_version: API_VERSION,
+        params: vec![TransactionInfoRequest {
+            transaction,
+            binary: false,
+        }],
+    }
+}
+
+fn build_submit_transaction_request(
+    tx_blob: String,
+    fail_hard: bool,
+) -> RpcRequest<Vec<SubmitTransactionRequest>> {
+    RpcRequest {
+        id: String::from("1"),
+        method: String::from("submit"),
+        api_version: API_VERSION,
+        params: vec![SubmitTransactionRequest { tx_blob, fail_hard }],
+    }
+}
+
+fn build_json_request(rpc_url: &str, request: &impl Serialize) -> RequestBuilder {
+    Client::new()
+        .post(rpc_url)
+        .header(CONTENT_TYPE, "application/json")
+        .header(ACCEPT, "application/json")
+        .json(request)
+}
+
+fn build_account_info_request(account: &str) -> RpcRequest<Vec<AccountInfoRequestParams>> {
+    RpcRequest {
+        id: String::from("1"),
+        method: String::from("account_info"),
+        api_version: API_VERSION,
+        params: vec![AccountInfoRequestParams {
+            account: String::from(account),
+            ledger_index: String::new(),
+            queue: false,
+            signer_lists: false,
+            strict: false,
+        }],
+    }
+}
+
+#[derive(Serialize)]
+struct SubmitTransactionRequest {
+    tx_blob: String,
+    fail_hard: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SubmitTransactionResponse {
+    pub accepted: bool,
+    pub applied: bool,
+    pub broadcast: bool,
+}
+
+#[derive(Serialize)]
+struct TransactionInfoRequest {
+    transaction: String,
+    binary: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TransactionInfoResponse {
+    // Empty struct as we aren't interested in content
+}
+
+#[derive(Serialize)]
+struct AccountInfoRequestParams {
+    account: String,
+    ledger_index: String,
+    queue: bool,
+    signer_lists: bool,
+    strict: bool,
+}
+
+#[derive(Serialize)]
+struct RpcRequest<T> {
+    id: String,
+    method: String,
+    api_version: u32,
+    params: T,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RpcResponse<T> {
+    pub result: T,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ResultResponse {
+    pub info: ServerInfoResponse,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ServerInfoResponse {
+    pub server_state: String,
+    #[serde(default)]
+    pub peers: u32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PeersResponse {
+    #[serde(default)]
+    pub peers: Vec<PeerResponse>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PeerResponse {
+    /// The peer's address as seen by the node.
+    pub address: String,
+    /// The peer's node public key, encoded in base58.
+    pub public_key: String,
+    /// The connection's uptime, in seconds.
+    #[serde(default)]
+    pub uptime: u32,
+    /// Only reported if the peer's ledger isn't in sync with the node's, e.g. `insane` or `unknown`.
+    #[serde(default)]
+    pub sanity: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AccountInfoResponse {
+    pub account_data: AccountDataResponse,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AccountDataResponse {
+    #[serde(rename(deserialize = "Balance"))]
+    pub balance: String,
+
+    #[serde(rename(deserialize = "Sequence"))]
+    pub sequence: u32,
+
+    #[allow(dead_code)]
+    #[serde(rename(deserialize = "PreviousTxnID"))]
+    pub previous_transaction: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LedgerInfoResponse {
+    pub ledger: LedgerResponseData,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LedgerTransactionsResponse {
+    pub ledger: LedgerTransactionsData,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LedgerTransactionsData {
+    pub ledger_hash: String,
+    pub ledger_index: String,
+    /// The hex-encoded transaction IDs.
+    #[serde(default)]
+    pub transactions: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LedgerResponseData {
+    pub ledger_hash: String,
+    pub ledger_index: String,
+    #[serde(rename = "accountState")]
+    pub account_state: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LedgerDataResponse {
+    pub ledger_hash: String,
+    pub ledger_index: u32,
+    pub state: Vec<LedgerObject>,
+    /// Set if there are more objects, to be passed on to fetch the next page.
+    #[serde(default)]
+    pub marker: Option<Value>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AccountObjectsResponse {
+    pub account: String,
+    pub account_objects: Vec<LedgerObject>,
+    /// Set if there are more objects, to be passed on to fetch the next page.
+    #[serde(default)]
+    pub marker: Option<Value>,
+}
+
+/// An object of a ledger's state tree.
+#[derive(Debug, Clone, Deserialize)]
+pub struct LedgerObject {
+    /// The hex-encoded key of the object in the state tree.
+    pub index: String,
+    /// The type of the object, e.g. `AccountRoot` or `Offer`.
+    #[serde(rename = "LedgerEntryType")]
+    pub ledger_entry_type: String,
+    /// The object's other fields, as rendered by the node.
+    #[serde(flatten)]
+    pub fields: Map<String, Value>,
+}
+
+impl LedgerObject {
+    /// Returns the object's key in the state tree.
+    pub fn key(&self) -> anyhow::Result<[u8; 32]> {
+        let mut key = [0u8; 32];
+        hex::decode_to_slice(&self.index, &mut key)?;
+        Ok(key)
+    }
+
+    /// Returns the account the object belongs to, if it has one.
+    pub fn account(&self) -> Option<&str> {
+        self.fields.get("Account")?.as_str()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_ledger_data_pages() {
+        let page = r#"{"ledger_hash":"AB","ledger_index":7,"marker":"0F","state":[
+            {"Account":"rNGknFCRBZguXcPqC63k6xTZnonSe6ZuWt","Balance":"5000000000",
+             "LedgerEntryType":"AccountRoot","index":"2B6AC232AA4C4BE41BF49D2459FA4A0347E1B543A4C92FCEE0821C0201E2E9A8"},
+            {"Amendments":[],"LedgerEntryType":"Amendments","index":"7DB0788C020F02780A673DC74757F23823FA3014C1866E72CC4CD8B226CD6EF4"}
+        ]}"#;
+        let page: LedgerDataResponse = serde_json::from_str(page).unwrap();
+        assert_eq!(page.marker, Some(Value::from("0F")));
+        assert_eq!(page.state.len(), 2);
+
+        let root = &page.state[0];
+        assert_eq!(root.ledger_entry_type, "AccountRoot");
+        assert_eq!(root.account(), Some("rNGknFCRBZguXcPqC63k6xTZnonSe6ZuWt"));
+        assert_eq!(root.key().unwrap()[..2], [0x2b, 0x6a]);
+        assert_eq!(page.state[1].account(), None);
+
+        let last = r#"{"account":"rNGknFCRBZguXcPqC63k6xTZnonSe6ZuWt","account_objects":[]}"#;
+        let last: AccountObjectsResponse = serde_json::from_str(last).unwrap();
+        assert!(last.marker.is_none());
+    }
+}
@@ -0,0 +1,233 @@
+//! A client for rippled's WebSocket `subscribe` API.
+//!
+//! The node pushes an event for every closed ledger, transaction and validation on the streams a
+//! client subscribed to. The [WsClient] reconnects and resubscribes if the connection drops, events
+//! sent by the node in the meantime are lost.
+
+use std::time::Duration;
+
+use anyhow::{anyhow, bail};
+use futures_util::{stream, SinkExt, Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::{net::TcpStream, time::sleep};
+use tokio_tungstenite::{connect_async, tungstenite::Message, MaybeTlsStream, WebSocketStream};
+
+/// The number of attempts to reconnect after the connection drops.
+const RECONNECT_ATTEMPTS: usize = 5;
+/// The delay between reconnection attempts.
+const RECONNECT_DELAY: Duration = Duration::from_millis(500);
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// The streams a [WsClient] can subscribe to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SubscriptionStream {
+    /// Sends a [LedgerClosedEvent] whenever the node closes a ledger.
+    Ledger,
+    /// Sends a [TransactionEvent] for every transaction applied to a closed ledger.
+    Transactions,
+    /// Sends a [ValidationEvent] for every validation the node receives.
+    Validations,
+}
+
+/// An event pushed by the node on one of the subscribed streams.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type")]
+pub enum StreamEvent {
+    #[serde(rename = "ledgerClosed")]
+    LedgerClosed(LedgerClosedEvent),
+    #[serde(rename = "transaction")]
+    Transaction(TransactionEvent),
+    #[serde(rename = "validationReceived")]
+    Validation(ValidationEvent),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct LedgerClosedEvent {
+    pub ledger_index: u32,
+    pub ledger_hash: String,
+    /// The close time in seconds since the Ripple epoch.
+    pub ledger_time: u32,
+    #[serde(default)]
+    pub txn_count: u32,
+    /// The range of ledgers the node has available, e.g. `1-42`.
+    #[serde(default)]
+    pub validated_ledgers: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TransactionEvent {
+    pub engine_result: String,
+    pub validated: bool,
+    #[serde(default)]
+    pub ledger_index: Option<u32>,
+    /// The transaction's fields as JSON.
+    pub transaction: Value,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ValidationEvent {
+    pub ledger_hash: String,
+    pub ledger_index: String,
+    pub validation_public_key: String,
+    pub signature: String,
+    #[serde(default)]
+    pub full: bool,
+}
+
+#[derive(Serialize)]
+struct SubscribeRequest<'a> {
+    id: u32,
+    command: &'static str,
+    streams: &'a [SubscriptionStream],
+}
+
+/// A WebSocket client for the node's subscription streams.
+pub struct WsClient {
+    url: String,
+    socket: Socket,
+    streams: Vec<SubscriptionStream>,
+    next_id: u32,
+}
+
+impl WsClient {
+    /// Connects to the node's WebSocket port, e.g. `ws://127.0.0.1:6006`.
+    pub async fn connect(url: &str) -> anyhow::Result<Self> {
+        let (socket, _) = connect_async(url).await?;
+
+        Ok(Self {
+            url: url.to_owned(),
+            socket,
+            streams: Vec::new(),
+            next_id: 1,
+        })
+    }
+
+    /// Subscribes to the streams and waits for the node to confirm the subscription.
+    pub async fn subscribe(&mut self, streams: &[SubscriptionStream]) -> anyhow::Result<()> {
+        self.send_subscribe(streams).await?;
+
+        for stream in streams {
+            if !self.streams.contains(stream) {
+                self.streams.push(*stream);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Waits for the next event on the subscribed streams, reconnecting if the connection drops.
+    pub async fn next_event(&mut self) -> anyhow::Result<StreamEvent> {
+        loop {
+            match self.socket.next().await {
+                Some(Ok(Message::Text(text))) => {
+                    let value: Value = serde_json::from_str(&text)?;
+                    // Responses to requests and unknown events are skipped.
+                    if let Ok(event) = serde_json::from_value(value) {
+                        return Ok(event);
+                    }
+                }
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => self.reconnect().await?,
+                // Pings are answered by the socket itself.
+                Some(Ok(_)) => (),
+            }
+        }
+    }
+
+    /// Turns the client into a stream of events, which ends after the first error.
+    pub fn into_stream(self) -> impl Stream<Item = anyhow::Result<StreamEvent>> {
+        stream::unfold(Some(self), |client| async move {
+            let mut client = client?;
+            match client.next_event().await {
+                Ok(event) => Some((Ok(event), Some(client))),
+                Err(e) => Some((Err(e), None)),
+            }
+        })
+    }
+
+    /// Closes the connection.
+    pub async fn close(mut self) -> anyhow::Result<()> {
+        Ok(self.socket.close(None).await?)
+    }
+
+    async fn send_subscribe(&mut self, streams: &[SubscriptionStream]) -> anyhow::Result<()> {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let request = SubscribeRequest {
+            id,
+            command: "subscribe",
+            streams,
+        };
+        self.socket
+            .send(Message::Text(serde_json::to_string(&request)?))
+            .await?;
+
+        // Events may already arrive before the response, they're dropped here.
+        while let Some(message) = self.socket.next().await {
+            let Message::Text(text) = message? else {
+                continue;
+            };
+            let value: Value = serde_json::from_str(&text)?;
+            if value["type"] != "response" || value["id"] != id {
+                continue;
+            }
+
+            return match value["status"].as_str() {
+                Some("success") => Ok(()),
+                _ => Err(anyhow!("subscription failed: {}", value["error"])),
+            };
+        }
+
+        bail!("connection closed before the subscription was confirmed")
+    }
+
+    async fn reconnect(&mut self) -> anyhow::Result<()> {
+        for _ in 0..RECONNECT_ATTEMPTS {
+            sleep(RECONNECT_DELAY).await;
+
+            let Ok((socket, _)) = connect_async(self.url.as_str()).await else {
+                continue;
+            };
+            self.socket = socket;
+
+            let streams = self.streams.clone();
+            if self.send_subscribe(&streams).await.is_ok() {
+                return Ok(());
+            }
+        }
+
+        bail!("unable to reconnect to {}", self.url)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_stream_events() {
+        let ledger = r#"{"type":"ledgerClosed","fee_base":10,"ledger_hash":"AB","ledger_index":7,
+            "ledger_time":750000000,"txn_count":2,"validated_ledgers":"1-7"}"#;
+        assert!(matches!(
+            serde_json::from_str(ledger).unwrap(),
+            StreamEvent::LedgerClosed(LedgerClosedEvent {
+                ledger_index: 7,
+                txn_count: 2,
+                ..
+            })
+        ));
+
+        let validation = r#"{"type":"validationReceived","ledger_hash":"AB","ledger_index":"7",
+            "validation_public_key":"n9","signature":"30","full":true}"#;
+        assert!(matches!(
+            serde_json::from_str(validation).unwrap(),
+            StreamEvent::Validation(ValidationEvent { full: true, .. })
+        ));
+
+        let response = r#"{"id":1,"result":{},"status":"success","type":"response"}"#;
+        assert!(serde_json::from_str::<StreamEvent>(response).is_err());
+    }
+}
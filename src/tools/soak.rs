@@ -0,0 +1,382 @@
+//! A long-running soak harness.
+//!
+//! Keeps a node busy with several synthetic peers for a long time, periodically pinging it,
+//! querying its ledger and submitting transactions while sampling its resource usage. The
+//! summary lists the anomalies detected during the run.
+
+use std::{fmt, time::Duration};
+
+use rand::Rng;
+use tokio::time::{interval, sleep_until, timeout, Instant, MissedTickBehavior};
+
+use crate::{
+    fuzzing::seeded_rng,
+    protocol::{
+        codecs::message::Payload,
+        proto::{tm_ping::PingType, TmPing},
+    },
+    setup::node::Node,
+    tools::{
+        accounts::{Account, TEST_ACCOUNT},
+        config::SynthNodeCfg,
+        metrics::resources::ResourceSample,
+        rpc::{get_ledger_info, submit_transaction},
+        synth_node::SyntheticNode,
+    },
+};
+
+/// Amount of drops sent by each soak transaction.
+const PAYMENT_AMOUNT: u64 = 1_000;
+
+/// Soak configuration.
+#[derive(Clone)]
+pub struct SoakCfg {
+    /// How long the soak lasts.
+    pub duration: Duration,
+    /// The number of synthetic peers kept connected to the node.
+    pub peers: usize,
+    /// How often one of the peers pings the node.
+    pub ping_interval: Duration,
+    /// Pongs slower than this are reported as anomalies.
+    pub max_ping_rtt: Duration,
+    /// How often the node's validated ledger is queried.
+    pub ledger_interval: Duration,
+    /// How long the validated ledger may stay the same before it's reported as stalled.
+    pub ledger_stall_timeout: Duration,
+    /// How often a payment is submitted, `None` disables transactions.
+    pub tx_interval: Option<Duration>,
+    /// How often the node's resource usage is sampled.
+    pub sample_interval: Duration,
+    /// The largest allowed ratio between the last and the first RSS sample.
+    pub max_rss_growth: f64,
+    /// The largest allowed increase in open file descriptors between the first and last sample.
+    pub max_fd_growth: usize,
+    /// Configuration of the synthetic peers.
+    pub synth_node_cfg: SynthNodeCfg,
+}
+
+impl Default for SoakCfg {
+    fn default() -> Self {
+        Self {
+            duration: Duration::from_secs(60 * 60),
+            peers: 5,
+            ping_interval: Duration::from_secs(1),
+            max_ping_rtt: Duration::from_secs(1),
+            ledger_interval: Duration::from_secs(10),
+            ledger_stall_timeout: Duration::from_secs(60),
+            tx_interval: Some(Duration::from_secs(10)),
+            sample_interval: Duration::from_secs(30),
+            max_rss_growth: 2.0,
+            max_fd_growth: 100,
+            synth_node_cfg: Default::default(),
+        }
+    }
+}
+
+/// A resource sample taken during the soak.
+#[derive(Debug, Clone, Copy)]
+pub struct TimedSample {
+    /// Time since the start of the soak.
+    pub elapsed: Duration,
+    pub sample: ResourceSample,
+}
+
+/// The outcome of a soak.
+#[derive(Debug, Default)]
+pub struct SoakReport {
+    pub duration: Duration,
+    pub pings_sent: usize,
+    pub pings_lost: usize,
+    pub max_ping_rtt: Duration,
+    pub ledger_queries: usize,
+    pub ledger_query_failures: usize,
+    /// The last validated ledger index seen.
+    pub ledger_index: Option<u64>,
+    pub txs_submitted: usize,
+    pub txs_rejected: usize,
+    /// The number of times the node dropped one of the peers.
+    pub peer_disconnects: usize,
+    pub crashed: bool,
+    pub samples: Vec<TimedSample>,
+    pub anomalies: Vec<String>,
+}
+
+impl fmt::Display for SoakReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "duration: {:.0}s", self.duration.as_secs_f64())?;
+        writeln!(
+            f,
+            "pings: {} sent, {} lost, max rtt {:.3}ms",
+            self.pings_sent,
+            self.pings_lost,
+            self.max_ping_rtt.as_secs_f64() * 1000.0
+        )?;
+        writeln!(
+            f,
+            "ledger queries: {} ({} failed), last validated ledger: {:?}",
+            self.ledger_queries, self.ledger_query_failures, self.ledger_index
+        )?;
+        writeln!(
+            f,
+            "transactions: {} submitted, {} rejected",
+            self.txs_submitted, self.txs_rejected
+        )?;
+        writeln!(f, "peer disconnects: {}", self.peer_disconnects)?;
+        writeln!(f, "crashed: {}", self.crashed)?;
+        if let (Some(first), Some(last)) = (self.samples.first(), self.samples.last()) {
+            writeln!(
+                f,
+                "rss: {} KiB -> {} KiB, open fds: {} -> {}, cpu time: {:.1}s",
+                first.sample.rss_kib,
+                last.sample.rss_kib,
+                first.sample.open_fds,
+                last.sample.open_fds,
+                last.sample.cpu_time.as_secs_f64()
+            )?;
+        }
+        writeln!(f, "anomalies: {}", self.anomalies.len())?;
+        for anomaly in &self.anomalies {
+            writeln!(f, "  {anomaly}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Soaks the node until the configured duration elapses or the node crashes.
+///
+/// Transactions are payments from the genesis account, so they require a stateful node.
+pub async fn soak(node: &mut Node, cfg: &SoakCfg) -> SoakReport {
+    let mut report = SoakReport::default();
+    let start = Instant::now();
+    let end = start + cfg.duration;
+    let rpc_url = node.rpc_url();
+    let genesis = Account::genesis();
+
+    let mut peers = Vec::with_capacity(cfg.peers);
+    for _ in 0..cfg.peers {
+        peers.push(connect_peer(node, cfg).await);
+    }
+
+    let mut ping_ticker = ticker(cfg.ping_interval);
+    let mut ledger_ticker = ticker(cfg.ledger_interval);
+    let mut tx_ticker = cfg.tx_interval.map(ticker);
+    let mut sample_ticker = ticker(cfg.sample_interval);
+    let end_of_soak = sleep_until(end);
+    tokio::pin!(end_of_soak);
+
+    let mut next_peer = 0;
+    let mut last_ledger_change = Instant::now();
+    let mut rng = seeded_rng();
+
+    loop {
+        tokio::select! {
+            _ = &mut end_of_soak => break,
+            _ = ping_ticker.tick() => {
+                if !node.is_running() {
+                    report.crashed = true;
+                    break;
+                }
+
+                reconnect_peers(node, cfg, &mut peers, &mut report).await;
+                for peer in peers.iter_mut().flatten() {
+                    drain(peer).await;
+                }
+
+                if let Some(peer) = peers.get_mut(next_peer).and_then(Option::as_mut) {
+                    report.pings_sent += 1;
+                    match ping(peer, node, rng.gen(), cfg.max_ping_rtt * 2).await {
+                        Some(rtt) => {
+                            if rtt > cfg.max_ping_rtt {
+                                report.anomalies.push(format!(
+                                    "{:?}: slow pong, {:.3}ms",
+                                    start.elapsed(),
+                                    rtt.as_secs_f64() * 1000.0
+                                ));
+                            }
+                            report.max_ping_rtt = report.max_ping_rtt.max(rtt);
+                        }
+                        None => report.pings_lost += 1,
+                    }
+                }
+                next_peer = (next_peer + 1) % cfg.peers.max(1);
+            }
+            _ = ledger_ticker.tick() => {
+                report.ledger_queries += 1;
+                let index = get_ledger_info(&rpc_url)
+                    .await
+                    .ok()
+                    .and_then(|info| info.result.ledger.ledger_index.parse::<u64>().ok());
+
+                match index {
+                    Some(index) if Some(index) != report.ledger_index => {
+                        report.ledger_index = Some(index);
+                        last_ledger_change = Instant::now();
+                    }
+                    Some(_) => (),
+                    None => report.ledger_query_failures += 1,
+                }
+
+                if last_ledger_change.elapsed() > cfg.ledger_stall_timeout {
+                    report.anomalies.push(format!(
+                        "{:?}: the validated ledger hasn't advanced for {:?}",
+                        start.elapsed(),
+                        last_ledger_change.elapsed()
+                    ));
+                    // Report each stall once.
+                    last_ledger_change = Instant::now();
+                }
+            }
+            _ = async { tx_ticker.as_mut().unwrap().tick().await }, if tx_ticker.is_some() => {
+                report.txs_submitted += 1;
+                if !submit_payment(&genesis, &rpc_url).await {
+                    report.txs_rejected += 1;
+                }
+            }
+            _ = sample_ticker.tick() => {
+                match ResourceSample::take(node.pid()) {
+                    Ok(sample) => report.samples.push(TimedSample {
+                        elapsed: start.elapsed(),
+                        sample,
+                    }),
+                    Err(e) => report
+                        .anomalies
+                        .push(format!("{:?}: unable to sample resources: {e}", start.elapsed())),
+                }
+            }
+        }
+    }
+
+    for peer in peers.into_iter().flatten() {
+        peer.shut_down().await;
+    }
+
+    report.duration = start.elapsed();
+    report.crashed |= !node.is_running();
+    detect_anomalies(&mut report, cfg);
+    report
+}
+
+fn ticker(period: Duration) -> tokio::time::Interval {
+    let mut ticker = interval(period);
+    // A slow node shouldn't cause a burst of catch-up requests.
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    ticker
+}
+
+async fn connect_peer(node: &Node, cfg: &SoakCfg) -> Option<SyntheticNode> {
+    let peer = SyntheticNode::new(&cfg.synth_node_cfg).await;
+    if peer.connect(node.addr()).await.is_ok() {
+        Some(peer)
+    } else {
+        peer.shut_down().await;
+        None
+    }
+}
+
+async fn reconnect_peers(
+    node: &Node,
+    cfg: &SoakCfg,
+    peers: &mut [Option<SyntheticNode>],
+    report: &mut SoakReport,
+) {
+    for slot in peers.iter_mut() {
+        let connected = matches!(slot, Some(peer) if peer.is_connected(node.addr()));
+        if connected {
+            continue;
+        }
+
+        if let Some(peer) = slot.take() {
+            report.peer_disconnects += 1;
+            peer.shut_down().await;
+        }
+        *slot = connect_peer(node, cfg).await;
+    }
+}
+
+// Keeps the peer's inbound queue from filling up.
+async fn drain(peer: &mut SyntheticNode) {
+    while peer.recv_message_timeout(Duration::ZERO).await.is_ok() {}
+}
+
+async fn ping(peer: &mut SyntheticNode, node: &Node, seq: u32, wait: Duration) -> Option<Duration> {
+    let payload = Payload::TmPing(TmPing {
+        r#type: PingType::PtPing as i32,
+        seq: Some(seq),
+        ping_time: None,
+        net_time: None,
+    });
+    peer.unicast(node.addr(), payload).ok()?;
+
+    let sent_at = Instant::now();
+    timeout(wait, async {
+        loop {
+            let (_, message) = peer.recv_message().await;
+            if matches!(
+                &message.payload,
+                Payload::TmPing(TmPing { r#type, seq: Some(s), .. })
+                    if *s == seq && *r#type == PingType::PtPong as i32
+            ) {
+                return sent_at.elapsed();
+            }
+        }
+    })
+    .await
+    .ok()
+}
+
+async fn submit_payment(account: &Account, rpc_url: &str) -> bool {
+    let Ok(sequence) = account.next_sequence(rpc_url).await else {
+        return false;
+    };
+    let Ok(tx) = account
+        .payment(TEST_ACCOUNT, PAYMENT_AMOUNT)
+        .sequence(sequence)
+        .sign(account.key())
+    else {
+        return false;
+    };
+
+    matches!(
+        submit_transaction(rpc_url, tx.to_hex(), false).await,
+        Ok(response) if response.result.accepted
+    )
+}
+
+fn detect_anomalies(report: &mut SoakReport, cfg: &SoakCfg) {
+    if report.crashed {
+        report.anomalies.push("the node crashed".into());
+    }
+    if report.pings_lost > 0 {
+        report
+            .anomalies
+            .push(format!("{} pings got no reply", report.pings_lost));
+    }
+    if report.txs_rejected > 0 {
+        report.anomalies.push(format!(
+            "{} transactions were rejected",
+            report.txs_rejected
+        ));
+    }
+    if report.peer_disconnects > 0 {
+        report.anomalies.push(format!(
+            "the node dropped the peers {} times",
+            report.peer_disconnects
+        ));
+    }
+
+    if let (Some(first), Some(last)) = (report.samples.first(), report.samples.last()) {
+        let (first, last) = (first.sample, last.sample);
+        if last.rss_kib as f64 > first.rss_kib as f64 * cfg.max_rss_growth {
+            report.anomalies.push(format!(
+                "rss grew from {} KiB to {} KiB",
+                first.rss_kib, last.rss_kib
+            ));
+        }
+        if last.open_fds > first.open_fds + cfg.max_fd_growth {
+            report.anomalies.push(format!(
+                "open fds grew from {} to {}",
+                first.open_fds, last.open_fds
+            ));
+        }
+    }
+}
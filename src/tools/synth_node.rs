@@ -1,50 +1,580 @@
 use std::{
+    collections::{BTreeMap, HashMap},
     io,
-    net::{IpAddr, SocketAddr},
-    time::Duration,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    sync::OnceLock,
+    time::{Duration, Instant},
 };
 
+use futures_util::future::join_all;
 use pea2pea::{
-    protocols::{Handshake, Reading, Writing},
+    protocols::{Disconnect, Handshake, Reading, Writing},
     Pea2Pea,
 };
 use tokio::{
     net::TcpSocket,
-    sync::{mpsc, mpsc::Receiver, oneshot},
-    time::timeout,
+    sync::{broadcast, mpsc, mpsc::Receiver, oneshot},
+    task::JoinHandle,
+    time::{interval, timeout, MissedTickBehavior},
 };
-use tracing::trace;
+use tracing::{trace, Level};
+use tracing_subscriber::{filter::LevelFilter, fmt, prelude::*, reload, EnvFilter, Registry};
 
 use crate::{
     protocol::{
-        codecs::message::{BinaryMessage, Payload},
+        codecs::message::{BinaryMessage, Lz4Compression, Payload},
+        handshake::{
+            HandshakeCfg, HandshakeInfo, HeaderEdit, HeldHandshake, ProtocolFeatures,
+            RequestPacing, SessionSignature,
+        },
+        proto::{
+            tm_ping::PingType, MessageType, TmGetLedger, TmGetObjectByHash, TmLedgerData,
+            TmLedgerInfoType, TmLedgerNode, TmLedgerType, TmPing, TmReplayDeltaRequest,
+            TmReplayDeltaResponse, TmReplyError,
+        },
+        shamap::{parse_ledger_node, ShaMapError, ShaMapNode, ShaMapNodeId},
+        version::ProtocolVersion,
         writing::MessageOrBytes,
     },
+    setup::network::NetworkProfile,
     tools::{
-        config::SynthNodeCfg,
-        constants::{EXPECTED_RESULT_TIMEOUT, SYNTH_NODE_QUEUE_DEPTH},
-        inner_node::InnerNode,
+        config::{ConfigProfile, Keepalive, SynthNodeCfg},
+        endpoints::{endpoints_payload, Endpoint},
+        inner_node::{ConnectionEvent, InnerNode},
+        matchers::Matcher,
+        message_stats::{MessageStats, ValidatorMessageCount},
+        metrics::recorder::duration_as_us,
+        objects::ObjectStore,
+        peer_status::{LedgerAdvance, PeerStatus},
+        replay_delta::ReplayDeltaStore,
+        validation::validation_signing_key,
+        validator::KeyType,
     },
 };
 
-/// Enables tracing for all [`SyntheticNode`] instances (usually scoped by test).
+type TracingFilter = reload::Handle<EnvFilter, Registry>;
+
+// The subscriber is installed once per process, afterwards only its filter is swapped.
+static TRACING_FILTER: OnceLock<Option<TracingFilter>> = OnceLock::new();
+
+/// Enables tracing for all [`SyntheticNode`] instances (usually scoped by test), filtered by the
+/// `RUST_LOG` environment variable.
+///
+/// The filter is shared by the whole process, so it applies to tests running side by side too.
+/// Can be called any number of times.
 pub fn enable_tracing() {
-    use tracing_subscriber::{fmt, EnvFilter};
+    set_tracing_filter(EnvFilter::from_default_env());
+}
+
+/// Disables tracing enabled by [enable_tracing] or [with_level].
+pub fn disable_tracing() {
+    // Nothing to disable if tracing was never enabled.
+    if let Some(Some(handle)) = TRACING_FILTER.get() {
+        reload_tracing_filter(handle, EnvFilter::new("off"));
+    }
+}
+
+/// Enables tracing for all [`SyntheticNode`] instances at the level and above, regardless of
+/// the `RUST_LOG` environment variable.
+pub fn with_level(level: Level) {
+    set_tracing_filter(EnvFilter::default().add_directive(LevelFilter::from_level(level).into()));
+}
+
+fn set_tracing_filter(filter: EnvFilter) {
+    let handle = TRACING_FILTER.get_or_init(|| {
+        let (filter, handle) = reload::Layer::new(EnvFilter::new("off"));
+        tracing_subscriber::registry()
+            .with(filter)
+            .with(fmt::layer().with_test_writer())
+            .try_init()
+            // Someone else installed a global subscriber, leave it be.
+            .ok()
+            .map(|_| handle)
+    });
+
+    if let Some(handle) = handle {
+        reload_tracing_filter(handle, filter);
+    }
+}
 
-    fmt()
-        .with_test_writer()
-        .with_env_filter(EnvFilter::from_default_env())
-        .init();
+fn reload_tracing_filter(handle: &TracingFilter, filter: EnvFilter) {
+    handle
+        .reload(filter)
+        .expect("the tracing subscriber should be alive");
+}
+
+/// Configures and builds [`SyntheticNode`] instances.
+#[derive(Clone)]
+pub struct SyntheticNodeBuilder {
+    /// Node's configuration, the handshake is kept apart so it can be adjusted even if skipped.
+    conf: SynthNodeCfg,
+    /// Handshake configuration.
+    handshake: HandshakeCfg,
+    /// Whether to skip the handshake.
+    skip_handshake: bool,
+}
+
+impl Default for SyntheticNodeBuilder {
+    fn default() -> Self {
+        Self {
+            conf: SynthNodeCfg {
+                handshake: None,
+                ..Default::default()
+            },
+            handshake: Default::default(),
+            skip_handshake: false,
+        }
+    }
+}
+
+impl SyntheticNodeBuilder {
+    /// Creates [SyntheticNode] according to configuration.
+    ///
+    /// The builder can be reused to create more nodes with the same configuration.
+    pub async fn build(&self) -> SyntheticNode {
+        SyntheticNode::new(&self.config()).await
+    }
+
+    /// Returns the configuration of the built nodes.
+    pub fn config(&self) -> SynthNodeCfg {
+        SynthNodeCfg {
+            handshake: (!self.skip_handshake).then(|| self.handshake.clone()),
+            ..self.conf.clone()
+        }
+    }
+
+    /// Skips the handshake, so messages can be exchanged right after connecting.
+    pub fn skip_handshake(mut self, skip: bool) -> Self {
+        self.skip_handshake = skip;
+        self
+    }
+
+    /// Sets whether to generate new keys for the handshake.
+    pub fn generate_new_keys(mut self, generate: bool) -> Self {
+        self.conf.generate_new_keys = generate;
+        self
+    }
+
+    /// Sets the algorithm of the node's identity key.
+    pub fn key_type(mut self, key_type: KeyType) -> Self {
+        self.conf.key_type = key_type;
+        self
+    }
+
+    /// Sets the IP address to listen on and connect from.
+    pub fn listener_ip(mut self, ip: IpAddr) -> Self {
+        self.conf.pea2pea_config.listener_ip = Some(ip);
+        self
+    }
+
+    /// Sets Pea2Pea configuration.
+    pub fn pea2pea_config(mut self, config: pea2pea::Config) -> Self {
+        self.conf.pea2pea_config = config;
+        self
+    }
+
+    /// Compresses sent messages of at least `threshold` bytes with LZ4.
+    pub fn lz4_compression(mut self, threshold: u32) -> Self {
+        self.conf.compression = Some(Lz4Compression { threshold });
+        self
+    }
+
+    /// Decodes only the received messages of the types, others arrive as [Payload::Unknown].
+    pub fn allowed_message_types(mut self, types: impl IntoIterator<Item = MessageType>) -> Self {
+        self.conf.allowed_message_types = Some(
+            types
+                .into_iter()
+                .map(|message_type| message_type as u16)
+                .collect(),
+        );
+        self
+    }
+
+    /// Answers pings in the background and, if the interval is set, pings the peers periodically.
+    ///
+    /// See [Keepalive] for how this affects the inbound queue.
+    pub fn keepalive(mut self, ping_interval: Option<Duration>) -> Self {
+        self.conf.keepalive = Some(Keepalive { ping_interval });
+        self
+    }
+
+    /// Tracks the state each peer reports, see [SyntheticNode::peer_status].
+    pub fn track_peer_status(mut self, track: bool) -> Self {
+        self.conf.track_peer_status = track;
+        self
+    }
+
+    /// Sets the capacity of the inbound message queue.
+    pub fn queue_depth(mut self, depth: usize) -> Self {
+        self.conf.queue_depth = depth;
+        self
+    }
+
+    /// Flips a random bit of the public key sent in the handshake.
+    pub fn bitflip_pub_key(mut self, enabled: bool) -> Self {
+        self.handshake.bitflip_pub_key = enabled;
+        self
+    }
+
+    /// Flips a random bit of the shared value signed in the handshake.
+    pub fn bitflip_shared_val(mut self, enabled: bool) -> Self {
+        self.handshake.bitflip_shared_val = enabled;
+        self
+    }
+
+    /// Sets how the 'Session-Signature' handshake field is produced, e.g. to forge it.
+    pub fn session_signature(mut self, signature: SessionSignature) -> Self {
+        self.handshake.session_signature = signature;
+        self
+    }
+
+    /// Sets the 'User-Agent' or 'Server' handshake field, depending on the connection side.
+    pub fn ident(mut self, ident: impl Into<String>) -> Self {
+        self.handshake.http_ident = ident.into();
+        self
+    }
+
+    /// Sets the 'Connection' handshake field.
+    pub fn connection(mut self, connection: impl Into<String>) -> Self {
+        self.handshake.http_connection = connection.into();
+        self
+    }
+
+    /// Sets the protocol versions offered to the node, or accepted from its offer.
+    pub fn protocol_versions(mut self, versions: &[ProtocolVersion]) -> Self {
+        self.handshake.protocol_versions = versions.to_vec();
+        self
+    }
+
+    /// Sets the 'Upgrade' handshake field of the request.
+    pub fn upgrade_req(mut self, upgrade: impl Into<String>) -> Self {
+        self.handshake.http_upgrade_req = Some(upgrade.into());
+        self
+    }
+
+    /// Sets the 'Upgrade' handshake field of the response.
+    pub fn upgrade_rsp(mut self, upgrade: impl Into<String>) -> Self {
+        self.handshake.http_upgrade_rsp = Some(upgrade.into());
+        self
+    }
+
+    /// Sets the 'Connect-As' handshake field.
+    pub fn connect_as(mut self, connect_as: impl Into<String>) -> Self {
+        self.handshake.http_connect_as = connect_as.into();
+        self
+    }
+
+    /// Sets the 'X-Protocol-Ctl' handshake field.
+    pub fn x_protocol_ctl(mut self, protocol_ctl: impl Into<String>) -> Self {
+        self.handshake.http_x_protocol_ctl = protocol_ctl.into();
+        self
+    }
+
+    /// Requests the features, see [ProtocolFeatures].
+    pub fn protocol_features(mut self, features: ProtocolFeatures) -> Self {
+        self.handshake = self.handshake.with_features(features);
+        self
+    }
+
+    /// Sets the optional 'Crawl' handshake field.
+    pub fn crawl(mut self, crawl: impl Into<String>) -> Self {
+        self.handshake.http_crawl = Some(crawl.into());
+        self
+    }
+
+    /// Sets the optional 'Network-Time' handshake field.
+    pub fn network_time(mut self, time: impl Into<String>) -> Self {
+        self.handshake.http_network_time = Some(time.into());
+        self
+    }
+
+    /// Sends the current network time shifted by the number of seconds in the 'Network-Time'
+    /// handshake field, e.g. to appear as a peer with a skewed clock.
+    pub fn network_time_skew(mut self, skew_secs: i64) -> Self {
+        self.handshake.http_network_time_skew = Some(skew_secs);
+        self
+    }
+
+    /// Sets the optional 'Network-ID' handshake field.
+    pub fn network_id(mut self, network_id: impl Into<String>) -> Self {
+        self.handshake.http_network_id = Some(network_id.into());
+        self
+    }
+
+    /// Presents the node as a member of the network in the handshake.
+    pub fn network(self, profile: &NetworkProfile) -> Self {
+        self.network_id(profile.network_id.to_string())
+    }
+
+    /// Sets the optional 'Closed-Ledger' handshake field.
+    pub fn closed_ledger(mut self, ledger: impl Into<String>) -> Self {
+        self.handshake.http_closed_ledger = Some(ledger.into());
+        self
+    }
+
+    /// Sets the optional 'Previous-Ledger' handshake field.
+    pub fn prev_ledger(mut self, ledger: impl Into<String>) -> Self {
+        self.handshake.http_prev_ledger = Some(ledger.into());
+        self
+    }
+
+    /// Appends a raw 'Name: value' line to the handshake.
+    pub fn extra_field(mut self, field: impl Into<String>) -> Self {
+        self.handshake.http_unexpected_extra_field_and_value = Some(field.into());
+        self
+    }
+
+    /// Sets the method, path and HTTP version of the handshake request, e.g. `POST / HTTP/1.0`.
+    pub fn request_line(
+        mut self,
+        method: impl Into<String>,
+        path: impl Into<String>,
+        version: impl Into<String>,
+    ) -> Self {
+        self.handshake.http_method = method.into();
+        self.handshake.http_path = path.into();
+        self.handshake.http_version = version.into();
+        self
+    }
+
+    /// Changes the handshake's header fields, after the ones above are set, see [HeaderEdit].
+    pub fn header_edit(mut self, edit: HeaderEdit) -> Self {
+        self.handshake.http_header_edits.push(edit);
+        self
+    }
+
+    /// Sets how the handshake request is sent, e.g. byte by byte, see [SyntheticNode::hold_handshake].
+    pub fn request_pacing(mut self, pacing: RequestPacing) -> Self {
+        self.handshake.http_request_pacing = pacing;
+        self
+    }
+
+    /// Replaces the whole handshake configuration.
+    pub fn handshake(mut self, handshake: HandshakeCfg) -> Self {
+        self.handshake = handshake;
+        self
+    }
+
+    /// Overrides the values set in the profile, see [ConfigProfile].
+    pub fn profile(mut self, profile: &ConfigProfile) -> Self {
+        profile.apply(&mut self.conf);
+        if let Some(handshake) = &profile.handshake {
+            handshake.apply(&mut self.handshake);
+        }
+        self
+    }
+}
+
+/// The first of the loopback addresses assigned to the listeners, `127.0.0.1` is left to the node.
+const FIRST_LISTENER_IP: Ipv4Addr = Ipv4Addr::new(127, 0, 0, 2);
+
+/// Synthetic nodes listening on distinct loopback addresses, for the node to connect to.
+pub struct SyntheticListeners {
+    nodes: Vec<SyntheticNode>,
+    addrs: Vec<SocketAddr>,
+}
+
+impl SyntheticListeners {
+    /// Builds a listening node for each builder, each on its own loopback address.
+    ///
+    /// The nodes are started concurrently, the listener IPs of the builders are overridden.
+    pub async fn start(
+        builders: impl IntoIterator<Item = SyntheticNodeBuilder>,
+    ) -> io::Result<Self> {
+        let first_ip = u32::from(FIRST_LISTENER_IP);
+        let ips = (0..).map(|i| IpAddr::V4(Ipv4Addr::from(first_ip + i)));
+        Self::start_on(ips, builders).await
+    }
+
+    /// Builds a listening node for each builder, each on the next IP of the pool, e.g. the
+    /// [listener_ips](ConfigProfile::listener_ips) of a profile.
+    ///
+    /// Fails if the pool has fewer IPs than there are builders.
+    pub async fn start_on(
+        ips: impl IntoIterator<Item = IpAddr>,
+        builders: impl IntoIterator<Item = SyntheticNodeBuilder>,
+    ) -> io::Result<Self> {
+        let mut ips = ips.into_iter();
+        let builders = builders
+            .into_iter()
+            .map(|builder| {
+                let ip = ips.next().ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::AddrNotAvailable,
+                        "more listeners than IPs in the pool",
+                    )
+                })?;
+                Ok(builder.listener_ip(ip))
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+
+        let listeners = builders.into_iter().map(|builder| async move {
+            let node = builder.build().await;
+            let addr = node.start_listening().await;
+            (node, addr)
+        });
+
+        let mut nodes = Vec::new();
+        let mut addrs = Vec::new();
+        let mut error = None;
+        for (node, addr) in join_all(listeners).await {
+            match addr {
+                Ok(addr) => addrs.push(addr),
+                Err(e) => error = error.or(Some(e)),
+            }
+            nodes.push(node);
+        }
+
+        let listeners = Self { nodes, addrs };
+        match error {
+            Some(e) => {
+                listeners.shut_down().await;
+                Err(e)
+            }
+            None => Ok(listeners),
+        }
+    }
+
+    /// Returns the listening addresses, in the order of the builders.
+    pub fn addrs(&self) -> &[SocketAddr] {
+        &self.addrs
+    }
+
+    /// Returns the nodes, in the order of the builders.
+    pub fn nodes(&self) -> &[SyntheticNode] {
+        &self.nodes
+    }
+
+    /// Returns the nodes, e.g. to read their messages.
+    pub fn nodes_mut(&mut self) -> &mut [SyntheticNode] {
+        &mut self.nodes
+    }
+
+    /// Returns the number of listeners with at least one connection.
+    pub fn num_connected(&self) -> usize {
+        self.nodes
+            .iter()
+            .filter(|node| node.num_connected() > 0)
+            .count()
+    }
+
+    /// Shuts all the nodes down.
+    pub async fn shut_down(self) {
+        join_all(self.nodes.iter().map(SyntheticNode::shut_down)).await;
+    }
+}
+
+/// Pings all the connected peers every interval, the pongs are dropped by the reading protocol.
+async fn ping_periodically(node: InnerNode, ping_interval: Duration) {
+    let mut ticks = interval(ping_interval);
+    ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    // The first tick completes right away.
+    ticks.tick().await;
+
+    for seq in 1.. {
+        ticks.tick().await;
+        let ping = Payload::TmPing(TmPing {
+            r#type: PingType::PtPing as i32,
+            seq: Some(seq),
+            ping_time: None,
+            net_time: None,
+        });
+        for addr in node.node().connected_addrs() {
+            let _ = node.unicast(addr, MessageOrBytes::Payload(ping.clone()));
+        }
+    }
+}
+
+/// A reply to [SyntheticNode::request_ledger].
+#[derive(Debug, Clone)]
+pub struct LedgerDataReply {
+    pub ledger_hash: Vec<u8>,
+    pub ledger_seq: u32,
+    pub itype: TmLedgerInfoType,
+    /// The requested nodes, for [TmLedgerInfoType::LiBase] the first one is the ledger header.
+    pub nodes: Vec<TmLedgerNode>,
+    /// Set if the node couldn't serve the request.
+    pub error: Option<TmReplyError>,
+}
+
+impl LedgerDataReply {
+    /// Decodes the tree nodes of the reply, see [parse_ledger_node].
+    ///
+    /// Not for [TmLedgerInfoType::LiBase] replies, their nodes have no IDs.
+    pub fn tree_nodes(&self) -> Result<Vec<(ShaMapNodeId, ShaMapNode)>, ShaMapError> {
+        self.nodes.iter().map(parse_ledger_node).collect()
+    }
+}
+
+impl TryFrom<TmLedgerData> for LedgerDataReply {
+    type Error = io::Error;
+
+    fn try_from(data: TmLedgerData) -> io::Result<Self> {
+        let itype = TmLedgerInfoType::from_i32(data.r#type).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unknown ledger info type {}", data.r#type),
+            )
+        })?;
+
+        Ok(Self {
+            ledger_hash: data.ledger_hash,
+            ledger_seq: data.ledger_seq,
+            itype,
+            nodes: data.nodes,
+            error: data.error.and_then(TmReplyError::from_i32),
+        })
+    }
+}
+
+/// Builds a request for ledger data, see [SyntheticNode::request_ledger].
+///
+/// Without a hash, the last closed ledger is requested. Apart from [TmLedgerInfoType::LiBase],
+/// the root node of the tree is requested along with `depth` levels below it. The cookie is
+/// echoed in the reply.
+pub fn ledger_request(
+    itype: TmLedgerInfoType,
+    ledger_hash: Option<Vec<u8>>,
+    depth: Option<u32>,
+    cookie: u32,
+) -> TmGetLedger {
+    let ltype = ledger_hash
+        .is_none()
+        .then_some(TmLedgerType::LtClosed as i32);
+    let node_i_ds = match itype {
+        TmLedgerInfoType::LiBase => vec![],
+        _ => vec![ShaMapNodeId::ROOT.to_wire()],
+    };
+    TmGetLedger {
+        itype: itype as i32,
+        ltype,
+        ledger_hash,
+        ledger_seq: None,
+        node_i_ds,
+        request_cookie: Some(cookie.into()),
+        query_type: None,
+        query_depth: depth,
+    }
 }
 
 pub struct SyntheticNode {
     inner: InnerNode,
     receiver: Receiver<(SocketAddr, BinaryMessage)>,
+    /// Cookie of the next ledger request, used to match the replies.
+    next_request_cookie: u32,
+    /// The task pinging the peers, if the keepalive pings are enabled.
+    pinger: Option<JoinHandle<()>>,
+    /// How long to wait for an expected message.
+    expected_result_timeout: Duration,
 }
 
 impl SyntheticNode {
+    pub fn builder() -> SyntheticNodeBuilder {
+        SyntheticNodeBuilder::default()
+    }
+
     pub async fn new(config: &SynthNodeCfg) -> Self {
-        let (sender, receiver) = mpsc::channel(SYNTH_NODE_QUEUE_DEPTH);
+        let (sender, receiver) = mpsc::channel(config.queue_depth);
         let inner = InnerNode::new(config, sender).await;
 
         if config.handshake.is_some() {
@@ -52,8 +582,20 @@ impl SyntheticNode {
         }
         inner.enable_reading().await;
         inner.enable_writing().await;
+        inner.enable_disconnect().await;
+
+        let pinger = config
+            .keepalive
+            .and_then(|keepalive| keepalive.ping_interval)
+            .map(|ping_interval| tokio::spawn(ping_periodically(inner.clone(), ping_interval)));
 
-        Self { inner, receiver }
+        Self {
+            inner,
+            receiver,
+            next_request_cookie: 1,
+            pinger,
+            expected_result_timeout: config.expected_result_timeout,
+        }
     }
 
     /// Starts listening for inbound connections.
@@ -73,6 +615,33 @@ impl SyntheticNode {
         self.inner.connect_from(target, socket).await
     }
 
+    /// Sends the handshake request paced as configured and holds the connection for up to the
+    /// time limit, see [InnerNode::hold_handshake].
+    ///
+    /// The connection isn't registered with the node, whatever the outcome.
+    pub async fn hold_handshake(
+        &self,
+        target: SocketAddr,
+        time_limit: Duration,
+    ) -> io::Result<HeldHandshake> {
+        self.inner.hold_handshake(target, time_limit).await
+    }
+
+    /// Disconnects from the peer.
+    ///
+    /// Returns `false` if the node wasn't connected to the peer.
+    pub async fn disconnect(&self, addr: SocketAddr) -> bool {
+        self.inner.disconnect(addr).await
+    }
+
+    /// Subscribes to the node's [ConnectionEvent]s.
+    ///
+    /// Only the events occurring after the subscription are received, so subscribe before
+    /// connecting.
+    pub fn subscribe_connection_events(&self) -> broadcast::Receiver<ConnectionEvent> {
+        self.inner.events.subscribe()
+    }
+
     pub fn unicast(
         &self,
         addr: SocketAddr,
@@ -91,6 +660,48 @@ impl SyntheticNode {
         self.inner.unicast(addr, MessageOrBytes::Bytes(bytes))
     }
 
+    /// Advertises the endpoints to the peer in a [TmEndpoints](crate::protocol::proto::TmEndpoints)
+    /// message, as if they were reachable through this node.
+    pub fn advertise_endpoints(
+        &self,
+        addr: SocketAddr,
+        endpoints: &[Endpoint],
+    ) -> io::Result<oneshot::Receiver<io::Result<()>>> {
+        self.unicast(addr, endpoints_payload(endpoints))
+    }
+
+    /// Sends the message to every connected peer, see [`multicast`](Self::multicast).
+    pub async fn broadcast(&self, message: Payload) -> Vec<(SocketAddr, io::Result<()>)> {
+        self.multicast(&self.connected_addrs(), message).await
+    }
+
+    /// Sends the message to each of the peers and waits until it's delivered to all of them.
+    ///
+    /// Returns each peer's send result, in the order of the given addresses.
+    pub async fn multicast(
+        &self,
+        addrs: &[SocketAddr],
+        message: Payload,
+    ) -> Vec<(SocketAddr, io::Result<()>)> {
+        let deliveries = addrs.iter().map(|&addr| {
+            let delivery = self.unicast(addr, message.clone());
+            async move {
+                let result = match delivery {
+                    Ok(delivery) => delivery.await.unwrap_or_else(|_| {
+                        Err(io::Error::new(
+                            io::ErrorKind::BrokenPipe,
+                            "the connection was dropped before the message was sent",
+                        ))
+                    }),
+                    Err(e) => Err(e),
+                };
+                (addr, result)
+            }
+        });
+
+        join_all(deliveries).await
+    }
+
     /// Reads a message from the inbound (internal) queue of the node.
     ///
     /// Messages are sent to the queue when unfiltered by the message filter.
@@ -119,8 +730,51 @@ impl SyntheticNode {
         }
     }
 
+    /// Waits for the peer to close `n_ledgers` ledgers, as announced in its status changes and
+    /// validations, and returns the sequence of the latest one.
+    ///
+    /// The ledgers are counted from the latest one the peer reported if its status is tracked, see
+    /// [SyntheticNodeBuilder::track_peer_status], otherwise from the first one seen. Messages
+    /// read from the inbound queue meanwhile are dropped.
+    pub async fn wait_for_ledger_advance(
+        &mut self,
+        addr: SocketAddr,
+        n_ledgers: u32,
+        duration: Duration,
+    ) -> io::Result<u32> {
+        let start = self
+            .peer_status(addr)
+            .and_then(|status| status.latest_ledger_seq());
+        let mut advance = LedgerAdvance::new(start);
+
+        let result = timeout(duration, async {
+            while advance.advanced() < n_ledgers || advance.latest().is_none() {
+                let (source, message) = self.recv_message().await;
+                if source == addr {
+                    advance.record(&message.payload);
+                }
+            }
+        })
+        .await;
+
+        match (result, advance.latest()) {
+            (Ok(()), Some(latest)) => Ok(latest),
+            _ => Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                format!(
+                    "{addr} closed {} of {n_ledgers} ledgers within {:.3}s",
+                    advance.advanced(),
+                    duration.as_secs_f64()
+                ),
+            )),
+        }
+    }
+
     /// Gracefully shuts down the node.
     pub async fn shut_down(&self) {
+        if let Some(pinger) = &self.pinger {
+            pinger.abort();
+        }
         self.inner.shut_down().await
     }
 
@@ -128,10 +782,54 @@ impl SyntheticNode {
         self.inner.node().listening_addr()
     }
 
+    /// Returns the synthetic node's public key in the base58 form the node reports for its peers.
+    pub fn node_public_key(&self) -> String {
+        self.inner.crypto.key.node_public_key()
+    }
+
     pub fn is_connected(&self, addr: SocketAddr) -> bool {
         self.inner.node().is_connected(addr)
     }
 
+    /// Returns the addresses of all the connected peers.
+    pub fn connected_addrs(&self) -> Vec<SocketAddr> {
+        self.inner.node().connected_addrs()
+    }
+
+    /// Returns the identity the peer claimed in the latest handshake with the address.
+    pub fn peer_handshake_info(&self, addr: SocketAddr) -> Option<HandshakeInfo> {
+        self.inner.peer_handshake_info(addr)
+    }
+
+    /// Returns the counts of the messages received so far, per peer and message type.
+    pub fn message_stats(&self) -> MessageStats {
+        self.inner.message_stats.lock().unwrap().clone()
+    }
+
+    /// Clears the counts of the received messages, e.g. to observe a period after a warm-up.
+    pub fn reset_message_stats(&self) {
+        *self.inner.message_stats.lock().unwrap() = Default::default();
+    }
+
+    /// Returns the state reported by the peer over the latest connection with the address.
+    ///
+    /// The state is recorded as messages are read, before they reach the inbound queue. Returns
+    /// `None` unless the node tracks it, see [SyntheticNodeBuilder::track_peer_status].
+    pub fn peer_status(&self, addr: SocketAddr) -> Option<PeerStatus> {
+        self.inner
+            .peer_status
+            .as_ref()?
+            .lock()
+            .unwrap()
+            .get(addr)
+            .cloned()
+    }
+
+    /// Returns the protocol version negotiated in the latest handshake with the address.
+    pub fn peer_protocol_version(&self, addr: SocketAddr) -> Option<ProtocolVersion> {
+        self.peer_handshake_info(addr)?.protocol_version
+    }
+
     pub fn num_connected(&self) -> usize {
         self.inner.node().num_connected()
     }
@@ -141,7 +839,7 @@ impl SyntheticNode {
     }
 
     pub async fn expect_message(&mut self, check: &dyn Fn(&BinaryMessage) -> bool) -> bool {
-        timeout(EXPECTED_RESULT_TIMEOUT, async {
+        timeout(self.expected_result_timeout, async {
             loop {
                 let (_, message) = self.recv_message().await;
                 if check(&message) {
@@ -152,4 +850,687 @@ impl SyntheticNode {
         .await
         .is_ok()
     }
+
+    /// Requests ledger data from the peer and waits for the reply carrying the request's cookie.
+    ///
+    /// Without a hash, the last closed ledger is requested. Apart from
+    /// [TmLedgerInfoType::LiBase], the root node of the tree is requested along with `depth`
+    /// levels below it. Other messages received meanwhile are dropped.
+    pub async fn request_ledger(
+        &mut self,
+        addr: SocketAddr,
+        itype: TmLedgerInfoType,
+        ledger_hash: Option<Vec<u8>>,
+        depth: Option<u32>,
+    ) -> io::Result<LedgerDataReply> {
+        let cookie = self.next_request_cookie;
+        self.next_request_cookie += 1;
+
+        self.unicast(
+            addr,
+            Payload::TmGetLedger(ledger_request(itype, ledger_hash, depth, cookie)),
+        )?;
+
+        let matcher = Matcher::new(
+            format!("a TmLedgerData with request cookie {cookie}"),
+            move |payload| matches!(payload, Payload::TmLedgerData(data) if data.request_cookie == Some(cookie)),
+        );
+        match self.expect_matching(&matcher).await?.payload {
+            Payload::TmLedgerData(data) => data.try_into(),
+            _ => unreachable!("the matcher only accepts ledger data"),
+        }
+    }
+
+    /// Answers the [TmGetObjectByHash] queries received for the duration with the objects in the
+    /// store, see [ObjectStore::reply].
+    ///
+    /// Returns the number of queries answered. Other messages received meanwhile are dropped.
+    pub async fn serve_objects(&mut self, store: &ObjectStore, duration: Duration) -> usize {
+        self.answer_object_queries(duration, |query| store.reply(query))
+            .await
+    }
+
+    /// Answers the [TmGetObjectByHash] queries received for the duration with the replies built,
+    /// e.g. to serve malformed objects. Queries without a reply are ignored.
+    ///
+    /// Returns the number of queries answered. Other messages received meanwhile are dropped.
+    pub async fn answer_object_queries(
+        &mut self,
+        duration: Duration,
+        reply: impl Fn(&TmGetObjectByHash) -> Option<TmGetObjectByHash>,
+    ) -> usize {
+        let mut answered = 0;
+        let _ = timeout(duration, async {
+            loop {
+                let (source, message) = self.recv_message().await;
+                let Payload::TmGetObjectByHash(query) = &message.payload else {
+                    continue;
+                };
+                let Some(reply) = reply(query) else {
+                    continue;
+                };
+                if self
+                    .unicast(source, Payload::TmGetObjectByHash(reply))
+                    .is_ok()
+                {
+                    answered += 1;
+                }
+            }
+        })
+        .await;
+        answered
+    }
+
+    /// Requests the replay delta of the ledger from the peer and waits for the reply for the same
+    /// ledger, see [ReplayDelta::from_response](crate::tools::replay_delta::ReplayDelta::from_response)
+    /// to verify it.
+    ///
+    /// Other messages received meanwhile are dropped.
+    pub async fn request_replay_delta(
+        &mut self,
+        addr: SocketAddr,
+        ledger_hash: Vec<u8>,
+    ) -> io::Result<TmReplayDeltaResponse> {
+        self.unicast(
+            addr,
+            Payload::TmReplayDeltaRequest(TmReplayDeltaRequest {
+                ledger_hash: ledger_hash.clone(),
+            }),
+        )?;
+
+        let matcher = Matcher::new(
+            format!(
+                "a TmReplayDeltaResponse for ledger {}",
+                hex::encode(&ledger_hash)
+            ),
+            move |payload| matches!(payload, Payload::TmReplayDeltaResponse(response) if response.ledger_hash == ledger_hash),
+        );
+        match self.expect_matching(&matcher).await?.payload {
+            Payload::TmReplayDeltaResponse(response) => Ok(response),
+            _ => unreachable!("the matcher only accepts replay delta responses"),
+        }
+    }
+
+    /// Answers the [TmReplayDeltaRequest]s received for the duration with the deltas in the
+    /// store, see [ReplayDeltaStore::reply].
+    ///
+    /// Returns the number of requests answered. Other messages received meanwhile are dropped.
+    pub async fn serve_replay_deltas(
+        &mut self,
+        store: &ReplayDeltaStore,
+        duration: Duration,
+    ) -> usize {
+        let mut answered = 0;
+        let _ = timeout(duration, async {
+            loop {
+                let (source, message) = self.recv_message().await;
+                let Payload::TmReplayDeltaRequest(request) = &message.payload else {
+                    continue;
+                };
+                if self
+                    .unicast(source, Payload::TmReplayDeltaResponse(store.reply(request)))
+                    .is_ok()
+                {
+                    answered += 1;
+                }
+            }
+        })
+        .await;
+        answered
+    }
+
+    /// Counts the proposals and validations received for the duration, keyed by the public key
+    /// of the validator which signed them, i.e. the key a squelch refers to.
+    ///
+    /// Other messages received meanwhile are dropped, as are validations which can't be decoded.
+    pub async fn count_validator_messages(
+        &mut self,
+        duration: Duration,
+    ) -> HashMap<Vec<u8>, ValidatorMessageCount> {
+        let mut counts = HashMap::<Vec<u8>, ValidatorMessageCount>::new();
+        let _ = timeout(duration, async {
+            loop {
+                let (_, message) = self.recv_message().await;
+                match message.payload {
+                    Payload::TmProposeLedger(proposal) => {
+                        counts.entry(proposal.node_pub_key).or_default().proposals += 1;
+                    }
+                    Payload::TmValidation(validation) => {
+                        if let Some(key) = validation_signing_key(&validation.validation) {
+                            counts.entry(key).or_default().validations += 1;
+                        }
+                    }
+                    _ => (),
+                }
+            }
+        })
+        .await;
+        counts
+    }
+
+    /// Counts the proposals and validations signed by the validator received for the duration,
+    /// see [SyntheticNode::count_validator_messages].
+    pub async fn count_messages_from(
+        &mut self,
+        validator_key: &[u8],
+        duration: Duration,
+    ) -> ValidatorMessageCount {
+        self.count_validator_messages(duration)
+            .await
+            .remove(validator_key)
+            .unwrap_or_default()
+    }
+
+    /// Sends the request to the peer and waits for its reply accepted by the matcher.
+    ///
+    /// Returns the time elapsed until the reply, which is also recorded in the histogram if
+    /// named. Other messages received meanwhile are dropped. Fails with
+    /// [io::ErrorKind::TimedOut] if there's no reply in time, or with the error of writing the
+    /// request if the connection was closed.
+    pub async fn request_reply(
+        &mut self,
+        addr: SocketAddr,
+        payload: Payload,
+        matcher: &Matcher,
+        reply_timeout: Duration,
+        histogram: Option<&str>,
+    ) -> io::Result<Duration> {
+        self.request(addr, payload, matcher, reply_timeout, histogram)
+            .await
+            .map(|(elapsed, _)| elapsed)
+    }
+
+    /// Like [SyntheticNode::request_reply], but returns the reply along with the time elapsed,
+    /// e.g. to inspect replies the matcher accepts regardless of their content.
+    pub async fn request(
+        &mut self,
+        addr: SocketAddr,
+        payload: Payload,
+        matcher: &Matcher,
+        reply_timeout: Duration,
+        histogram: Option<&str>,
+    ) -> io::Result<(Duration, BinaryMessage)> {
+        if !self.is_connected(addr) {
+            return Err(io::Error::new(
+                io::ErrorKind::NotConnected,
+                format!("not connected to {addr}"),
+            ));
+        }
+        let mut delivery = self.unicast(addr, payload)?;
+        let start = Instant::now();
+
+        let reply = timeout(reply_timeout, async {
+            loop {
+                let (source, message) = self.recv_message().await;
+                if source == addr && matcher.matches(&message) {
+                    return (start.elapsed(), message);
+                }
+            }
+        })
+        .await;
+
+        // The request was written by now, unless the peer closed the connection.
+        if let Ok(Err(e)) = delivery.try_recv() {
+            return Err(e);
+        }
+        let (elapsed, message) = reply.map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::TimedOut,
+                format!(
+                    "expected {matcher} within {:.3}s",
+                    reply_timeout.as_secs_f64()
+                ),
+            )
+        })?;
+
+        if let Some(histogram) = histogram {
+            metrics::histogram!(histogram.to_owned(), duration_as_us(elapsed));
+        }
+        Ok((elapsed, message))
+    }
+
+    /// Waits for a message accepted by the matcher.
+    ///
+    /// On timeout, the error describes the expected message and lists the kinds of messages
+    /// received instead.
+    pub async fn expect_matching(&mut self, matcher: &Matcher) -> io::Result<BinaryMessage> {
+        let mut received: BTreeMap<&'static str, usize> = BTreeMap::new();
+        let result = timeout(self.expected_result_timeout, async {
+            loop {
+                let (_, message) = self.recv_message().await;
+                if matcher.matches(&message) {
+                    return message;
+                }
+                *received.entry(message.payload.name()).or_default() += 1;
+            }
+        })
+        .await;
+
+        result.map_err(|_| {
+            let received = if received.is_empty() {
+                "nothing".to_owned()
+            } else {
+                received
+                    .iter()
+                    .map(|(name, count)| format!("{name} ({count})"))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            };
+            io::Error::new(
+                io::ErrorKind::TimedOut,
+                format!(
+                    "expected {matcher} within {:.3}s, received {received}",
+                    self.expected_result_timeout.as_secs_f64()
+                ),
+            )
+        })
+    }
+}
+
+impl Drop for SyntheticNode {
+    fn drop(&mut self) {
+        if let Some(pinger) = &self.pinger {
+            pinger.abort();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::{
+        protocol::{
+            handshake::HoldEnd,
+            ledger::LedgerHeader,
+            proto::{tm_get_object_by_hash::ObjectType, TmHaveTransactions, TmIndexedObject},
+        },
+        tools::{
+            inner_node::DisconnectReason,
+            matchers::{is_kind, is_pong_with_seq},
+            proposal::ProposalBuilder,
+            replay_delta::{ReplayDelta, ReplayTransaction},
+            validation::ValidationBuilder,
+            validator::ValidatorKey,
+        },
+        wait_until,
+    };
+
+    #[tokio::test]
+    async fn validator_messages_are_counted_per_key() {
+        let mut receiver = SyntheticNode::builder().build().await;
+        let addr = receiver.start_listening().await.unwrap();
+        let sender = SyntheticNode::builder().build().await;
+        sender.connect(addr).await.unwrap();
+
+        let keys = [(); 2].map(|_| ValidatorKey::generate(KeyType::Secp256k1));
+        let proposal = ProposalBuilder::new([1; 32], [2; 32]);
+        for _ in 0..2 {
+            sender.unicast(addr, proposal.payload(&keys[0])).unwrap();
+        }
+        sender
+            .unicast(addr, ValidationBuilder::new([3; 32], 4).payload(&keys[0]))
+            .unwrap();
+        sender.unicast(addr, proposal.payload(&keys[1])).unwrap();
+
+        let counts = receiver
+            .count_validator_messages(Duration::from_millis(500))
+            .await;
+        assert_eq!(
+            counts[&keys[0].public_key()],
+            ValidatorMessageCount {
+                proposals: 2,
+                validations: 1
+            }
+        );
+        assert_eq!(counts[&keys[1].public_key()].total(), 1);
+
+        sender.unicast(addr, proposal.payload(&keys[1])).unwrap();
+        let count = receiver
+            .count_messages_from(&keys[0].public_key(), Duration::from_millis(200))
+            .await;
+        assert_eq!(count, ValidatorMessageCount::default());
+
+        sender.shut_down().await;
+        receiver.shut_down().await;
+    }
+
+    #[tokio::test]
+    async fn multicast_reports_each_peer() {
+        let sender = SyntheticNode::builder().build().await;
+        let mut receivers = Vec::new();
+        for _ in 0..2 {
+            let receiver = SyntheticNode::builder().build().await;
+            let addr = receiver.start_listening().await.unwrap();
+            sender.connect(addr).await.unwrap();
+            receivers.push(receiver);
+        }
+
+        let payload = Payload::TmHaveTransactions(TmHaveTransactions {
+            hashes: vec![vec![1u8; 32]],
+        });
+        let results = sender.broadcast(payload.clone()).await;
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|(_, result)| result.is_ok()));
+        for receiver in &mut receivers {
+            let (_, message) = receiver
+                .recv_message_timeout(Duration::from_secs(1))
+                .await
+                .unwrap();
+            assert!(matches!(message.payload, Payload::TmHaveTransactions(_)));
+        }
+
+        let unknown = "127.0.0.1:1".parse().unwrap();
+        let results = sender.multicast(&[unknown], payload).await;
+        assert_eq!(results[0].0, unknown);
+        assert!(results[0].1.is_err());
+
+        sender.shut_down().await;
+        for receiver in receivers {
+            receiver.shut_down().await;
+        }
+    }
+
+    #[tokio::test]
+    async fn ed25519_identities_verify_each_other() {
+        let listener = SyntheticNode::builder()
+            .key_type(KeyType::Ed25519)
+            .build()
+            .await;
+        let addr = listener.start_listening().await.unwrap();
+        let connector = SyntheticNode::builder()
+            .key_type(KeyType::Ed25519)
+            .build()
+            .await;
+        connector.connect(addr).await.unwrap();
+
+        let info = connector.peer_handshake_info(addr).unwrap();
+        assert_eq!(info.public_key, listener.inner.crypto.key.node_public_key());
+
+        connector.shut_down().await;
+        listener.shut_down().await;
+    }
+
+    #[tokio::test]
+    async fn held_handshakes_end_with_the_listener_timeout() {
+        let listener = SyntheticNode::builder().build().await;
+        let addr = listener.start_listening().await.unwrap();
+
+        let connector = SyntheticNode::builder()
+            .request_pacing(RequestPacing::Trickle(Duration::from_micros(100)))
+            .build()
+            .await;
+        let held = connector
+            .hold_handshake(addr, Duration::from_secs(10))
+            .await
+            .unwrap();
+        assert_eq!(held.end, HoldEnd::Responded(Some(101)));
+        connector.shut_down().await;
+
+        // The listener gives up on the request after its handshake timeout.
+        let connector = SyntheticNode::builder()
+            .request_pacing(RequestPacing::Unterminated)
+            .build()
+            .await;
+        let held = connector
+            .hold_handshake(addr, Duration::from_secs(10))
+            .await
+            .unwrap();
+        assert_eq!(held.end, HoldEnd::Closed);
+        assert!(held.held_for < Duration::from_secs(10));
+        assert!(held.bytes_sent > 0);
+        assert_eq!(listener.num_connected(), 0);
+
+        connector.shut_down().await;
+        listener.shut_down().await;
+    }
+
+    #[tokio::test]
+    async fn connection_events_report_disconnect_reasons() {
+        let listener = SyntheticNode::builder().build().await;
+        let addr = listener.start_listening().await.unwrap();
+        let mut listener_events = listener.subscribe_connection_events();
+        let connector = SyntheticNode::builder().build().await;
+        let mut connector_events = connector.subscribe_connection_events();
+
+        connector.connect(addr).await.unwrap();
+        assert_eq!(
+            connector_events.recv().await.unwrap(),
+            ConnectionEvent::Connected(addr)
+        );
+        let ConnectionEvent::Connected(connector_addr) = listener_events.recv().await.unwrap()
+        else {
+            panic!("expected the listener to report the connection");
+        };
+
+        assert!(connector.disconnect(addr).await);
+        assert_eq!(
+            connector_events.recv().await.unwrap(),
+            ConnectionEvent::Disconnected {
+                addr,
+                reason: DisconnectReason::Local
+            }
+        );
+        let event = timeout(Duration::from_secs(1), listener_events.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            event,
+            ConnectionEvent::Disconnected {
+                addr: connector_addr,
+                reason: DisconnectReason::Remote
+            }
+        );
+
+        connector.shut_down().await;
+        listener.shut_down().await;
+    }
+
+    #[tokio::test]
+    async fn request_reply_measures_the_reply() {
+        let mut responder = SyntheticNode::builder().build().await;
+        let addr = responder.start_listening().await.unwrap();
+        let mut requester = SyntheticNode::builder().build().await;
+        requester.connect(addr).await.unwrap();
+
+        // Echo the first request back.
+        let echo = tokio::spawn(async move {
+            let (source, message) = responder.recv_message().await;
+            responder.unicast(source, message.payload).unwrap();
+            responder
+        });
+
+        let payload = Payload::TmHaveTransactions(TmHaveTransactions {
+            hashes: vec![vec![1u8; 32]],
+        });
+        let matcher = is_kind("TmHaveTransactions");
+        requester
+            .request_reply(
+                addr,
+                payload.clone(),
+                &matcher,
+                Duration::from_secs(1),
+                None,
+            )
+            .await
+            .unwrap();
+
+        let responder = echo.await.unwrap();
+        let error = requester
+            .request_reply(addr, payload, &matcher, Duration::from_millis(100), None)
+            .await
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::TimedOut);
+
+        requester.shut_down().await;
+        responder.shut_down().await;
+    }
+
+    #[tokio::test]
+    async fn keepalive_answers_and_sends_pings() {
+        let listener = SyntheticNode::builder()
+            .keepalive(Some(Duration::from_millis(50)))
+            .build()
+            .await;
+        let addr = listener.start_listening().await.unwrap();
+        let mut connector = SyntheticNode::builder().build().await;
+        connector.connect(addr).await.unwrap();
+
+        let ping = Payload::TmPing(TmPing {
+            r#type: PingType::PtPing as i32,
+            seq: Some(7),
+            ping_time: None,
+            net_time: None,
+        });
+        connector
+            .request_reply(
+                addr,
+                ping,
+                &is_pong_with_seq(7),
+                Duration::from_secs(1),
+                None,
+            )
+            .await
+            .unwrap();
+
+        let (_, message) = connector
+            .recv_message_timeout(Duration::from_secs(1))
+            .await
+            .unwrap();
+        assert!(matches!(
+            message.payload,
+            Payload::TmPing(TmPing { r#type, .. }) if r#type == PingType::PtPing as i32
+        ));
+
+        connector.shut_down().await;
+        listener.shut_down().await;
+    }
+
+    #[tokio::test]
+    async fn listeners_get_distinct_ips() {
+        let listeners = SyntheticListeners::start((0..3).map(|_| SyntheticNode::builder()))
+            .await
+            .unwrap();
+        let ips = listeners
+            .addrs()
+            .iter()
+            .map(SocketAddr::ip)
+            .collect::<HashSet<_>>();
+        assert_eq!(ips.len(), 3);
+
+        let connector = SyntheticNode::builder().build().await;
+        connector.connect(listeners.addrs()[1]).await.unwrap();
+        wait_until!(Duration::from_secs(1), listeners.num_connected() == 1);
+
+        connector.shut_down().await;
+        listeners.shut_down().await;
+
+        let pool = [IpAddr::V4(Ipv4Addr::LOCALHOST)];
+        let too_many = SyntheticListeners::start_on(pool, (0..2).map(|_| SyntheticNode::builder()));
+        assert!(matches!(
+            too_many.await,
+            Err(e) if e.kind() == io::ErrorKind::AddrNotAvailable
+        ));
+    }
+
+    #[tokio::test]
+    async fn objects_are_served_from_the_store() {
+        let mut server = SyntheticNode::builder().build().await;
+        let addr = server.start_listening().await.unwrap();
+        let mut client = SyntheticNode::builder().build().await;
+        client.connect(addr).await.unwrap();
+
+        let mut store = ObjectStore::new();
+        store.insert([1; 32], vec![2; 10]);
+        let serving = tokio::spawn(async move {
+            let answered = server.serve_objects(&store, Duration::from_secs(1)).await;
+            (server, answered)
+        });
+
+        let query = Payload::TmGetObjectByHash(TmGetObjectByHash {
+            r#type: ObjectType::OtLedger as i32,
+            query: true,
+            seq: Some(1),
+            ledger_hash: None,
+            fat: None,
+            objects: vec![TmIndexedObject {
+                hash: Some(vec![1; 32]),
+                node_id: None,
+                index: None,
+                data: None,
+                ledger_seq: None,
+            }],
+        });
+        client.unicast(addr, query).unwrap();
+        let reply = client
+            .expect_matching(&Matcher::new(
+                "a reply",
+                |payload| matches!(payload, Payload::TmGetObjectByHash(reply) if !reply.query),
+            ))
+            .await
+            .unwrap();
+        let Payload::TmGetObjectByHash(reply) = reply.payload else {
+            unreachable!();
+        };
+        assert_eq!(reply.objects[0].data, Some(vec![2; 10]));
+
+        let (server, answered) = serving.await.unwrap();
+        assert_eq!(answered, 1);
+
+        client.shut_down().await;
+        server.shut_down().await;
+    }
+
+    #[tokio::test]
+    async fn replay_deltas_are_served_from_the_store() {
+        let mut server = SyntheticNode::builder().build().await;
+        let addr = server.start_listening().await.unwrap();
+        let mut client = SyntheticNode::builder().build().await;
+        client.connect(addr).await.unwrap();
+
+        let delta = ReplayDelta {
+            header: LedgerHeader {
+                seq: 2,
+                drops: 1_000,
+                parent_hash: [1; 32],
+                tx_hash: [2; 32],
+                account_hash: [3; 32],
+                parent_close_time: 10,
+                close_time: 20,
+                close_time_resolution: 10,
+                close_flags: 0,
+            },
+            transactions: vec![ReplayTransaction {
+                tx: vec![4; 50],
+                meta: vec![5; 50],
+            }],
+        };
+        let mut store = ReplayDeltaStore::new();
+        store.insert(delta.clone());
+        let serving = tokio::spawn(async move {
+            let answered = server
+                .serve_replay_deltas(&store, Duration::from_secs(1))
+                .await;
+            (server, answered)
+        });
+
+        let response = client
+            .request_replay_delta(addr, delta.hash().to_vec())
+            .await
+            .unwrap();
+        assert_eq!(ReplayDelta::from_response(&response), Ok(delta));
+        let response = client
+            .request_replay_delta(addr, vec![6; 32])
+            .await
+            .unwrap();
+        assert_eq!(response.error, Some(TmReplyError::ReNoLedger as i32));
+
+        let (server, answered) = serving.await.unwrap();
+        assert_eq!(answered, 2);
+
+        client.shut_down().await;
+        server.shut_down().await;
+    }
 }
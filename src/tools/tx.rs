@@ -0,0 +1,205 @@
+//! A builder for signed transactions which can be submitted to a node.
+//!
+//! Only the fields needed by the supported transaction types are serialized, using rippled's
+//! canonical binary format.
+
+use anyhow::{anyhow, ensure, Context, Result};
+
+use crate::{
+    protocol::{
+        codecs::message::Payload,
+        proto::{TmTransaction, TransactionStatus},
+        stobject::{serialize, Field, ST_AMOUNT},
+    },
+    tools::validator::{sha512_half, ValidatorKey},
+};
+
+/// The prefix used when hashing a transaction for signing.
+const TX_SIGN_PREFIX: &[u8] = b"STX\x00";
+/// The prefix used when hashing a signed transaction to get its ID.
+pub const TX_ID_PREFIX: &[u8] = b"TXN\x00";
+
+/// Fee in drops used unless set explicitly.
+pub const DEFAULT_FEE: u64 = 10;
+
+const ACCOUNT_ID_SIZE: usize = 20;
+const ACCOUNT_ID_VERSION: u8 = 0;
+
+// A bit set for XRP amounts which are not negative.
+const XRP_AMOUNT_POSITIVE_BIT: u64 = 0x4000_0000_0000_0000;
+
+// Field codes, unique within their serialized type.
+const SF_TRANSACTION_TYPE: u8 = 2;
+const SF_FLAGS: u8 = 2;
+const SF_SEQUENCE: u8 = 4;
+const SF_LAST_LEDGER_SEQUENCE: u8 = 27;
+const SF_AMOUNT: u8 = 1;
+const SF_FEE: u8 = 8;
+const SF_SIGNING_PUB_KEY: u8 = 3;
+const SF_TXN_SIGNATURE: u8 = 4;
+const SF_ACCOUNT: u8 = 1;
+const SF_DESTINATION: u8 = 3;
+
+/// Supported transaction types.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u16)]
+pub enum TransactionType {
+    Payment = 0,
+}
+
+/// Builder for a transaction, signed with [`TxBuilder::sign`].
+#[derive(Debug, Clone)]
+pub struct TxBuilder {
+    tx_type: TransactionType,
+    account: String,
+    destination: String,
+    amount: u64,
+    sequence: u32,
+    fee: u64,
+    flags: u32,
+    last_ledger_sequence: Option<u32>,
+}
+
+impl TxBuilder {
+    /// Creates a builder for an XRP payment of `amount` drops from `account` to `destination`.
+    ///
+    /// Both accounts are classic addresses, e.g. `rHb9CJAWyB4rj91VRWn96DkukG4bwdtyTh`.
+    pub fn payment(account: &str, destination: &str, amount: u64) -> Self {
+        Self {
+            tx_type: TransactionType::Payment,
+            account: account.to_owned(),
+            destination: destination.to_owned(),
+            amount,
+            sequence: 1,
+            fee: DEFAULT_FEE,
+            flags: 0,
+            last_ledger_sequence: None,
+        }
+    }
+
+    /// Sets the sending account's sequence number, which must match the account's next sequence.
+    pub fn sequence(mut self, sequence: u32) -> Self {
+        self.sequence = sequence;
+        self
+    }
+
+    /// Sets the fee in drops.
+    pub fn fee(mut self, fee: u64) -> Self {
+        self.fee = fee;
+        self
+    }
+
+    /// Sets the transaction flags.
+    pub fn flags(mut self, flags: u32) -> Self {
+        self.flags = flags;
+        self
+    }
+
+    /// Sets the last ledger in which the transaction can be included.
+    pub fn last_ledger_sequence(mut self, sequence: u32) -> Self {
+        self.last_ledger_sequence = Some(sequence);
+        self
+    }
+
+    /// Serializes and signs the transaction with the account's key.
+    pub fn sign(&self, key: &ValidatorKey) -> Result<SignedTransaction> {
+        let account = decode_account_id(&self.account).context("invalid account")?;
+        let destination = decode_account_id(&self.destination).context("invalid destination")?;
+
+        // Fields are serialized sorted by type code first and then by field code.
+        let mut fields = vec![
+            Field::uint16(SF_TRANSACTION_TYPE, self.tx_type as u16),
+            Field::uint32(SF_FLAGS, self.flags),
+            Field::uint32(SF_SEQUENCE, self.sequence),
+            Field::new(ST_AMOUNT, SF_AMOUNT, encode_xrp_amount(self.amount)?),
+            Field::new(ST_AMOUNT, SF_FEE, encode_xrp_amount(self.fee)?),
+            Field::blob(SF_SIGNING_PUB_KEY, &key.public_key()),
+            Field::account(SF_ACCOUNT, &account),
+            Field::account(SF_DESTINATION, &destination),
+        ];
+        if let Some(sequence) = self.last_ledger_sequence {
+            fields.push(Field::uint32(SF_LAST_LEDGER_SEQUENCE, sequence));
+        }
+
+        let signature = key.sign_with_prefix(TX_SIGN_PREFIX, &serialize(&mut fields));
+        fields.push(Field::blob(SF_TXN_SIGNATURE, &signature));
+
+        Ok(SignedTransaction {
+            blob: serialize(&mut fields),
+        })
+    }
+}
+
+/// A serialized and signed transaction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignedTransaction {
+    pub blob: Vec<u8>,
+}
+
+impl SignedTransaction {
+    /// Returns the upper-case hex-encoded blob, as expected by the `submit` RPC call.
+    pub fn to_hex(&self) -> String {
+        hex::encode_upper(&self.blob)
+    }
+
+    /// Wraps the transaction in a [TmTransaction], the way a peer relays a transaction it hasn't
+    /// applied yet.
+    pub fn to_payload(&self) -> Payload {
+        Payload::TmTransaction(TmTransaction {
+            raw_transaction: self.blob.clone(),
+            status: TransactionStatus::TsNew as i32,
+            receive_timestamp: None,
+            deferred: None,
+        })
+    }
+
+    /// Returns the transaction's ID.
+    pub fn hash(&self) -> [u8; 32] {
+        let mut buf = Vec::with_capacity(TX_ID_PREFIX.len() + self.blob.len());
+        buf.extend_from_slice(TX_ID_PREFIX);
+        buf.extend_from_slice(&self.blob);
+        sha512_half(&buf)
+    }
+}
+
+fn encode_xrp_amount(drops: u64) -> Result<Vec<u8>> {
+    ensure!(drops < XRP_AMOUNT_POSITIVE_BIT, "XRP amount out of range");
+    Ok((drops | XRP_AMOUNT_POSITIVE_BIT).to_be_bytes().to_vec())
+}
+
+/// Decodes a classic address into a 20-byte account ID.
+pub fn decode_account_id(address: &str) -> Result<Vec<u8>> {
+    let mut bytes = bs58::decode(address)
+        .with_alphabet(bs58::Alphabet::RIPPLE)
+        .with_check(Some(ACCOUNT_ID_VERSION))
+        .into_vec()
+        .map_err(|e| anyhow!("unable to decode the address: {e}"))?;
+    ensure!(
+        bytes.len() == ACCOUNT_ID_SIZE + 1,
+        "invalid account ID length: {}",
+        bytes.len()
+    );
+    // Remove the version byte.
+    bytes.remove(0);
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::accounts::{Account, TEST_ACCOUNT};
+
+    // A payment from the genesis account to the test account, signed with the xrpl-py library.
+    const PAYMENT_BLOB: &str = "12000022000000002400000001201B0000001E61400000012A05F20068400000000000000A73210330E7FC9D56BB25D6893BA3F317AE5BCF33B3291BD63DB32654A313222F7FD020744630440220297389244D36AF12115296F409C446D9A5D808880DC7FF323AA207ED529CE6C802207AAC5D2A96CB102CBDE85D2A4BA814253CA133AC9277041CAE2E1A349FB233FF8114B5F762798A53D543A014CAF8B297CFF8F2F937E883149193D6AED0CBBC25790ADE05D020C9C6D9201DCF";
+
+    #[test]
+    fn payment_matches_reference_blob() {
+        let genesis = Account::genesis();
+        let tx = TxBuilder::payment(genesis.address(), TEST_ACCOUNT, 5_000_000_000)
+            .last_ledger_sequence(30)
+            .sign(genesis.key())
+            .unwrap();
+
+        assert_eq!(tx.to_hex(), PAYMENT_BLOB);
+    }
+}
@@ -0,0 +1,148 @@
+//! Signed validations, as sent by validators once they built a ledger.
+//!
+//! A validation is a serialized object signed over the SHA512-Half digest of the validation
+//! prefix followed by all of the object's fields except the signature.
+
+use crate::{
+    protocol::{
+        codecs::message::Payload,
+        proto::TmValidation,
+        stobject::{serialize, Field, StObject},
+    },
+    tools::{
+        ripple_time,
+        validator::{sha512_half, ValidatorKey},
+    },
+};
+
+/// The prefix used when hashing a validation for signing.
+const VALIDATION_PREFIX: &[u8] = b"VAL\x00";
+
+/// Set for validations of a fully built ledger, as opposed to partial ones.
+pub const VF_FULL_VALIDATION: u32 = 0x0000_0001;
+/// Set if the signature is fully canonical, rippled rejects other secp256k1 signatures.
+pub const VF_FULLY_CANONICAL_SIG: u32 = 0x8000_0000;
+
+// Field codes, unique within their serialized type.
+const SF_NETWORK_ID: u8 = 1;
+const SF_FLAGS: u8 = 2;
+const SF_LEDGER_SEQUENCE: u8 = 6;
+const SF_SIGNING_TIME: u8 = 9;
+const SF_LEDGER_HASH: u8 = 1;
+const SF_SIGNING_PUB_KEY: u8 = 3;
+const SF_SIGNATURE: u8 = 6;
+
+/// Builder for a validation, signed with [ValidationBuilder::sign].
+#[derive(Debug, Clone)]
+pub struct ValidationBuilder {
+    ledger_hash: [u8; 32],
+    ledger_sequence: u32,
+    signing_time: u32,
+    flags: u32,
+    network_id: Option<u32>,
+}
+
+impl ValidationBuilder {
+    /// Creates a builder for a full validation of the ledger, signed now.
+    pub fn new(ledger_hash: [u8; 32], ledger_sequence: u32) -> Self {
+        Self {
+            ledger_hash,
+            ledger_sequence,
+            signing_time: ripple_time::now(),
+            flags: VF_FULL_VALIDATION | VF_FULLY_CANONICAL_SIG,
+            network_id: None,
+        }
+    }
+
+    /// Sets the signing time in seconds since the Ripple epoch. Nodes ignore validations signed
+    /// a few minutes away from their own clock.
+    pub fn signing_time(mut self, signing_time: u32) -> Self {
+        self.signing_time = signing_time;
+        self
+    }
+
+    /// Sets the validation flags.
+    pub fn flags(mut self, flags: u32) -> Self {
+        self.flags = flags;
+        self
+    }
+
+    /// Sets the network the validation is meant for, nodes drop validations of other networks.
+    pub fn network_id(mut self, network_id: u32) -> Self {
+        self.network_id = Some(network_id);
+        self
+    }
+
+    /// Serializes and signs the validation with the validator's signing key.
+    pub fn sign(&self, key: &ValidatorKey) -> Vec<u8> {
+        let mut fields = vec![
+            Field::uint32(SF_FLAGS, self.flags),
+            Field::uint32(SF_LEDGER_SEQUENCE, self.ledger_sequence),
+            Field::uint32(SF_SIGNING_TIME, self.signing_time),
+            Field::hash256(SF_LEDGER_HASH, &self.ledger_hash),
+            Field::blob(SF_SIGNING_PUB_KEY, &key.public_key()),
+        ];
+        if let Some(network_id) = self.network_id {
+            fields.push(Field::uint32(SF_NETWORK_ID, network_id));
+        }
+
+        let mut signed = VALIDATION_PREFIX.to_vec();
+        signed.extend_from_slice(&serialize(&mut fields));
+        let signature = key.sign_digest(&sha512_half(&signed));
+        fields.push(Field::blob(SF_SIGNATURE, &signature));
+
+        serialize(&mut fields)
+    }
+
+    /// Signs the validation and wraps it in a payload.
+    pub fn payload(&self, key: &ValidatorKey) -> Payload {
+        Payload::TmValidation(TmValidation {
+            validation: self.sign(key),
+            ..Default::default()
+        })
+    }
+}
+
+/// Returns the signing public key of the serialized validation, `None` if it can't be decoded.
+pub fn validation_signing_key(validation: &[u8]) -> Option<Vec<u8>> {
+    let validation = StObject::deserialize(validation).ok()?;
+    validation.blob(SF_SIGNING_PUB_KEY).map(<[u8]>::to_vec)
+}
+
+/// Returns the sequence and hash of the ledger the serialized validation is for, `None` if it
+/// can't be decoded.
+pub fn validation_ledger(validation: &[u8]) -> Option<(u32, [u8; 32])> {
+    let validation = StObject::deserialize(validation).ok()?;
+    Some((
+        validation.u32(SF_LEDGER_SEQUENCE)?,
+        validation.hash256(SF_LEDGER_HASH)?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::validator::KeyType;
+
+    #[test]
+    fn validation_fields_are_sorted() {
+        let key = ValidatorKey::generate(KeyType::Secp256k1);
+        let validation = ValidationBuilder::new([7; 32], 42)
+            .signing_time(1)
+            .network_id(2)
+            .sign(&key);
+
+        // NetworkID, Flags, LedgerSequence and SigningTime come first, in this order.
+        assert_eq!(
+            validation[..20],
+            [0x21, 0, 0, 0, 2, 0x22, 0x80, 0, 0, 1, 0x26, 0, 0, 0, 42, 0x29, 0, 0, 0, 1]
+        );
+        // Followed by LedgerHash and the key.
+        assert_eq!(validation[20], 0x51);
+        assert_eq!(validation[21..53], [7; 32]);
+        assert_eq!(validation[53..55], [0x73, 33]);
+
+        assert_eq!(validation_signing_key(&validation), Some(key.public_key()));
+        assert_eq!(validation_ledger(&validation), Some((42, [7; 32])));
+    }
+}
@@ -0,0 +1,377 @@
+//! Utilities for acting as a validator or a validator list publisher.
+//!
+//! Both secp256k1 and ed25519 keys are supported, matching the key types rippled accepts for
+//! master and signing (ephemeral) keys.
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use bytes::{BufMut, BytesMut};
+use ed25519_dalek::Signer;
+use secp256k1::{Message, Secp256k1};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha512};
+
+use crate::{
+    protocol::{
+        manifest::{
+            MANIFEST_PREFIX, SF_MASTER_SIGNATURE, SF_PUBLIC_KEY, SF_SEQUENCE, SF_SIGNATURE,
+            SF_SIGNING_PUB_KEY,
+        },
+        stobject::{Field, StObject},
+    },
+    tools::ripple_time,
+};
+
+/// The first byte of a serialized ed25519 public key.
+pub const ED25519_KEY_PREFIX: u8 = 0xED;
+
+/// The base58 token type of node public keys, such as the keys listed in validators.txt.
+pub const TOKEN_NODE_PUBLIC: u8 = 28;
+
+const ONE_YEAR: u32 = 86400 * 365;
+
+/// The signature algorithm of a [ValidatorKey].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyType {
+    Secp256k1,
+    Ed25519,
+}
+
+/// A secret key used to sign manifests, validator lists and transactions.
+#[derive(Clone)]
+pub enum ValidatorKey {
+    Secp256k1(secp256k1::SecretKey),
+    Ed25519(ed25519_dalek::SigningKey),
+}
+
+impl ValidatorKey {
+    /// Generates a new random key of the given type.
+    pub fn generate(key_type: KeyType) -> Self {
+        let mut rng = rand::thread_rng();
+        match key_type {
+            KeyType::Secp256k1 => Self::Secp256k1(secp256k1::SecretKey::new(&mut rng)),
+            KeyType::Ed25519 => Self::Ed25519(ed25519_dalek::SigningKey::generate(&mut rng)),
+        }
+    }
+
+    /// Creates a key of the given type from a hex-encoded secret.
+    pub fn from_hex(key_type: KeyType, secret: &str) -> anyhow::Result<Self> {
+        let secret = hex::decode(secret)?;
+        let key = match key_type {
+            KeyType::Secp256k1 => Self::Secp256k1(secp256k1::SecretKey::from_slice(&secret)?),
+            KeyType::Ed25519 => Self::Ed25519(ed25519_dalek::SigningKey::from_bytes(
+                secret.as_slice().try_into()?,
+            )),
+        };
+        Ok(key)
+    }
+
+    /// Returns the key's type.
+    pub fn key_type(&self) -> KeyType {
+        match self {
+            Self::Secp256k1(_) => KeyType::Secp256k1,
+            Self::Ed25519(_) => KeyType::Ed25519,
+        }
+    }
+
+    /// Returns the 33-byte serialized public key.
+    ///
+    /// Ed25519 keys are prefixed with [ED25519_KEY_PREFIX] to match rippled's representation.
+    pub fn public_key(&self) -> Vec<u8> {
+        match self {
+            Self::Secp256k1(key) => key.public_key(&Secp256k1::new()).serialize().to_vec(),
+            Self::Ed25519(key) => {
+                let mut public_key = vec![ED25519_KEY_PREFIX];
+                public_key.extend_from_slice(key.verifying_key().as_bytes());
+                public_key
+            }
+        }
+    }
+
+    /// Returns the upper-case hex-encoded public key.
+    pub fn public_key_hex(&self) -> String {
+        hex::encode_upper(self.public_key())
+    }
+
+    /// Returns the base58-encoded node public key, e.g. `nHUSqn9...`.
+    pub fn node_public_key(&self) -> String {
+        encode_node_public_key(&self.public_key())
+    }
+
+    /// Returns the upper-case hex-encoded secret key.
+    pub fn secret_key_hex(&self) -> String {
+        match self {
+            Self::Secp256k1(key) => hex::encode_upper(key.secret_bytes()),
+            Self::Ed25519(key) => hex::encode_upper(key.to_bytes()),
+        }
+    }
+
+    /// Signs the buffer the same way rippled does for the key type.
+    ///
+    /// Secp256k1 keys sign the SHA512-Half digest of the buffer and produce a DER signature,
+    /// while ed25519 keys sign the buffer itself.
+    pub fn sign(&self, buffer: &[u8]) -> Vec<u8> {
+        match self {
+            Self::Secp256k1(key) => {
+                let digest = sha512_half(buffer);
+                let message = Message::from_slice(&digest).unwrap();
+                Secp256k1::new()
+                    .sign_ecdsa(&message, key)
+                    .serialize_der()
+                    .to_vec()
+            }
+            Self::Ed25519(key) => key.sign(buffer).to_bytes().to_vec(),
+        }
+    }
+
+    /// Signs the 32-byte digest as is, e.g. the shared value of a peer handshake.
+    ///
+    /// Ed25519 keys don't sign digests, they sign the digest's bytes as the message.
+    pub fn sign_digest(&self, digest: &[u8]) -> Vec<u8> {
+        match self {
+            Self::Secp256k1(key) => {
+                let message = Message::from_slice(digest).unwrap();
+                Secp256k1::new()
+                    .sign_ecdsa(&message, key)
+                    .serialize_der()
+                    .to_vec()
+            }
+            Self::Ed25519(key) => key.sign(digest).to_bytes().to_vec(),
+        }
+    }
+
+    /// Signs the buffer preceded by the hash prefix.
+    pub fn sign_with_prefix(&self, hash_prefix: &[u8], buffer: &[u8]) -> Vec<u8> {
+        let mut prefixed_buffer = BytesMut::with_capacity(hash_prefix.len() + buffer.len());
+        prefixed_buffer.put(hash_prefix);
+        prefixed_buffer.extend_from_slice(buffer);
+
+        self.sign(&prefixed_buffer)
+    }
+}
+
+/// Encodes the 33-byte serialized public key the way rippled displays node public keys.
+pub fn encode_node_public_key(public_key: &[u8]) -> String {
+    let mut payload = vec![TOKEN_NODE_PUBLIC];
+    payload.extend_from_slice(public_key);
+
+    bs58::encode(payload)
+        .with_alphabet(bs58::Alphabet::RIPPLE)
+        .with_check()
+        .into_string()
+}
+
+/// Checks a signature made by [ValidatorKey::sign_digest] against the 33-byte serialized public key.
+pub fn verify_digest(public_key: &[u8], digest: &[u8], signature: &[u8]) -> bool {
+    match public_key.first() {
+        Some(&ED25519_KEY_PREFIX) => {
+            let Ok(public_key) = public_key[1..].try_into() else {
+                return false;
+            };
+            let (Ok(verifying_key), Ok(signature)) = (
+                ed25519_dalek::VerifyingKey::from_bytes(public_key),
+                ed25519_dalek::Signature::from_slice(signature),
+            ) else {
+                return false;
+            };
+            verifying_key.verify_strict(digest, &signature).is_ok()
+        }
+        _ => {
+            let (Ok(public_key), Ok(signature), Ok(message)) = (
+                secp256k1::PublicKey::from_slice(public_key),
+                secp256k1::ecdsa::Signature::from_der(signature),
+                Message::from_slice(digest),
+            ) else {
+                return false;
+            };
+            Secp256k1::verification_only()
+                .verify_ecdsa(&message, &signature, &public_key)
+                .is_ok()
+        }
+    }
+}
+
+/// Checks a signature made by [ValidatorKey::sign] against the 33-byte serialized public key.
+pub fn verify(public_key: &[u8], buffer: &[u8], signature: &[u8]) -> bool {
+    match public_key.first() {
+        Some(&ED25519_KEY_PREFIX) => verify_digest(public_key, buffer, signature),
+        _ => verify_digest(public_key, &sha512_half(buffer), signature),
+    }
+}
+
+/// Returns the first half of the SHA512 digest, used by rippled for most hashing.
+pub fn sha512_half(buffer: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha512::new();
+    hasher.update(buffer);
+    let result = hasher.finalize();
+
+    let mut half = [0u8; 32];
+    half.copy_from_slice(&result[..32]);
+    half
+}
+
+/// Creates a manifest binding the signing key to the master key, signed by both keys.
+pub fn create_manifest(sequence: u32, master: &ValidatorKey, signing: &ValidatorKey) -> Vec<u8> {
+    let mut manifest = StObject::new(vec![
+        Field::uint32(SF_SEQUENCE, sequence),
+        Field::blob(SF_PUBLIC_KEY, &master.public_key()),
+        Field::blob(SF_SIGNING_PUB_KEY, &signing.public_key()),
+    ]);
+
+    let unsigned = manifest.serialize();
+    manifest.set(Field::blob(
+        SF_MASTER_SIGNATURE,
+        &master.sign_with_prefix(MANIFEST_PREFIX, &unsigned),
+    ));
+    manifest.set(Field::blob(
+        SF_SIGNATURE,
+        &signing.sign_with_prefix(MANIFEST_PREFIX, &unsigned),
+    ));
+
+    manifest.serialize()
+}
+
+/// The contents of a `[validator_token]` stanza.
+#[derive(Deserialize, Serialize)]
+struct ValidatorToken {
+    manifest: String,
+    validation_secret_key: String,
+}
+
+/// Creates a validator token, as generated by rippled's `validator-keys` tool, letting a node
+/// validate with the signing key on behalf of the master key.
+///
+/// rippled only accepts secp256k1 signing keys in tokens.
+pub fn create_validator_token(
+    sequence: u32,
+    master: &ValidatorKey,
+    signing: &ValidatorKey,
+) -> String {
+    assert_eq!(
+        signing.key_type(),
+        KeyType::Secp256k1,
+        "validator tokens need a secp256k1 signing key"
+    );
+
+    let token = ValidatorToken {
+        manifest: STANDARD.encode(create_manifest(sequence, master, signing)),
+        validation_secret_key: signing.secret_key_hex(),
+    };
+    STANDARD.encode(serde_json::to_string(&token).unwrap())
+}
+
+/// A validator entry in a validator list blob.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Validator {
+    pub validation_public_key: String,
+    pub manifest: String,
+}
+
+impl Validator {
+    /// Creates a validator entry out of the validator's master public key and its manifest.
+    pub fn new(public_key_hex: &str, manifest: &[u8]) -> Self {
+        Self {
+            validation_public_key: public_key_hex.to_string(),
+            manifest: STANDARD.encode(manifest),
+        }
+    }
+}
+
+/// The validator list blob contents.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ValidatorList {
+    pub sequence: u32,
+    pub expiration: u32,
+    /// When the list takes effect, in seconds since the Ripple epoch. Only version 2 lists may
+    /// take effect later than they're published.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub effective: Option<u32>,
+    pub validators: Vec<Validator>,
+}
+
+impl ValidatorList {
+    /// Creates a validator list which expires a year from now.
+    pub fn new(sequence: u32, validators: Vec<Validator>) -> Self {
+        Self {
+            sequence,
+            expiration: get_expiration(),
+            effective: None,
+            validators,
+        }
+    }
+
+    /// Serializes the list to the JSON blob which gets signed by the publisher.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+
+    /// Deserializes the list from a base64-encoded blob, as carried by validator list messages.
+    pub fn from_blob(blob: &[u8]) -> anyhow::Result<Self> {
+        Ok(serde_json::from_slice(&STANDARD.decode(blob)?)?)
+    }
+}
+
+/// Returns the expiration time a year from now, in seconds since the Ripple epoch.
+pub fn get_expiration() -> u32 {
+    ripple_time::now() + ONE_YEAR
+}
+
+#[cfg(test)]
+mod tests {
+    use ed25519_dalek::{Signature, Verifier};
+
+    use super::*;
+    use crate::protocol::manifest::Manifest;
+
+    // Test vector 1 from RFC 8032.
+    const ED25519_SECRET: &str = "9D61B19DEFFD5A60BA844AF492EC2CC44449C5697B326919703BAC031CAE7F60";
+    const ED25519_PUBLIC: &str = "D75A980182B10AB7D54BFED3C964073A0EE172F3DAA62325AF021A68F707511A";
+
+    #[test]
+    fn ed25519_public_key_is_prefixed() {
+        let key = ValidatorKey::from_hex(KeyType::Ed25519, ED25519_SECRET).unwrap();
+
+        assert_eq!(key.public_key_hex(), format!("ED{ED25519_PUBLIC}"));
+    }
+
+    #[test]
+    fn ed25519_signs_buffer_directly() {
+        let key =
+            ValidatorKey::Ed25519(ed25519_dalek::SigningKey::generate(&mut rand::thread_rng()));
+        let signature = key.sign(b"blob");
+
+        let public_key = &key.public_key()[1..];
+        let verifying_key =
+            ed25519_dalek::VerifyingKey::from_bytes(public_key.try_into().unwrap()).unwrap();
+        let signature = Signature::from_slice(&signature).unwrap();
+        assert!(verifying_key.verify(b"blob", &signature).is_ok());
+    }
+
+    #[test]
+    fn validator_token_names_the_master_key() {
+        fn master_key(token: &str) -> String {
+            let token: ValidatorToken =
+                serde_json::from_slice(&STANDARD.decode(token.replace('\n', "")).unwrap()).unwrap();
+            let manifest = STANDARD.decode(token.manifest).unwrap();
+            encode_node_public_key(&Manifest::decode(&manifest).unwrap().public_key)
+        }
+
+        let master = ValidatorKey::generate(KeyType::Ed25519);
+        let signing = ValidatorKey::generate(KeyType::Secp256k1);
+        let token = create_validator_token(1, &master, &signing);
+        assert_eq!(master_key(&token), master.node_public_key());
+    }
+
+    #[test]
+    fn secp256k1_signs_sha512_half() {
+        let key = ValidatorKey::generate(KeyType::Secp256k1);
+        let signature = key.sign(b"blob");
+
+        let engine = Secp256k1::new();
+        let public_key = secp256k1::PublicKey::from_slice(&key.public_key()).unwrap();
+        let message = Message::from_slice(&sha512_half(b"blob")).unwrap();
+        let signature = secp256k1::ecdsa::Signature::from_der(&signature).unwrap();
+        assert!(engine
+            .verify_ecdsa(&message, &signature, &public_key)
+            .is_ok());
+    }
+}
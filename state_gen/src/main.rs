@@ -0,0 +1,45 @@
+//! Procures the ledger state loaded by stateful nodes.
+//!
+//! Runs a testnet of validators until it validates the target ledger and saves the validators'
+//! databases into `~/.ziggurat/ripple/stateful`, replacing the existing state.
+//!
+//! Example:
+//! ```
+//!    cargo run --release -p state_gen -- --target-ledger 256
+//! ```
+use std::process::ExitCode;
+
+use clap::Parser;
+use ziggurat_xrpl::setup::stateful::{generate_state, StateProgress, DEFAULT_TARGET_LEDGER};
+
+/// Generates the ledger state for stateful nodes.
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+struct CmdArgs {
+    /// The validated ledger index to run the testnet up to.
+    #[arg(short = 'l', long, default_value_t = DEFAULT_TARGET_LEDGER)]
+    target_ledger: u32,
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let args = CmdArgs::parse();
+
+    let result = generate_state(args.target_ledger, |progress| match progress {
+        // Validated ledgers are reported in place.
+        StateProgress::LedgerValidated { .. } => print!("\r--- {progress}"),
+        _ => println!("--- {progress}"),
+    })
+    .await;
+
+    match result {
+        Ok(()) => {
+            println!("\n--- State saved");
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("\nUnable to generate the state: {e:?}");
+            ExitCode::FAILURE
+        }
+    }
+}
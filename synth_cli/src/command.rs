@@ -0,0 +1,119 @@
+//! The commands accepted at the prompt.
+
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, Result};
+use ziggurat_xrpl::protocol::{
+    codecs::message::Payload,
+    proto::{tm_ping::PingType, TmGetLedger, TmLedgerInfoType, TmLedgerType, TmPing, TmSquelch},
+};
+
+pub const HELP: &str = "\
+Commands:
+  ping                         send a ping
+  getledger [closed|<seq>]     request the base info of a ledger (the last closed by default)
+  squelch <key> [secs]         squelch the validator with the hex-encoded public key
+  unsquelch <key>              unsquelch the validator with the hex-encoded public key
+  raw <hex>                    send raw bytes, e.g. a frame printed by `xrpl-decode`
+  hide <name>...               stop printing received messages of the kinds, e.g. TmValidation
+  show <name>...               print received messages of the kinds again
+  help                         print this help
+  quit                         disconnect and exit";
+
+pub enum Command {
+    Send(Payload),
+    Raw(Vec<u8>),
+    Hide(Vec<String>),
+    Show(Vec<String>),
+    Help,
+    Quit,
+}
+
+impl FromStr for Command {
+    type Err = anyhow::Error;
+
+    fn from_str(line: &str) -> Result<Self> {
+        let mut words = line.split_whitespace();
+        let command = words.next().ok_or_else(|| anyhow!("empty command"))?;
+        let args: Vec<&str> = words.collect();
+
+        let command = match (command, args.as_slice()) {
+            ("ping", []) => Self::Send(ping(PingType::PtPing, 0)),
+            ("getledger", []) | ("getledger", ["closed"]) => Self::Send(get_ledger(None)),
+            ("getledger", [seq]) => Self::Send(get_ledger(Some(seq.parse()?))),
+            ("squelch", [key]) => Self::Send(squelch(true, key, None)?),
+            ("squelch", [key, secs]) => Self::Send(squelch(true, key, Some(secs.parse()?))?),
+            ("unsquelch", [key]) => Self::Send(squelch(false, key, None)?),
+            ("raw", [bytes]) => Self::Raw(hex::decode(bytes)?),
+            ("hide", names) if !names.is_empty() => Self::Hide(to_owned(names)),
+            ("show", names) if !names.is_empty() => Self::Show(to_owned(names)),
+            ("help", []) => Self::Help,
+            ("quit", []) | ("exit", []) => Self::Quit,
+            _ => bail!("invalid command, type `help` to list the commands"),
+        };
+        Ok(command)
+    }
+}
+
+/// Builds a ping or a pong.
+pub fn ping(r#type: PingType, seq: u32) -> Payload {
+    Payload::TmPing(TmPing {
+        r#type: r#type as i32,
+        seq: Some(seq),
+        ping_time: None,
+        net_time: None,
+    })
+}
+
+fn get_ledger(seq: Option<u32>) -> Payload {
+    Payload::TmGetLedger(TmGetLedger {
+        itype: TmLedgerInfoType::LiBase as i32,
+        ltype: seq.is_none().then_some(TmLedgerType::LtClosed as i32),
+        ledger_hash: None,
+        ledger_seq: seq,
+        node_i_ds: vec![],
+        request_cookie: None,
+        query_type: None,
+        query_depth: None,
+    })
+}
+
+fn squelch(squelch: bool, key: &str, secs: Option<u32>) -> Result<Payload> {
+    Ok(Payload::TmSquelch(TmSquelch {
+        squelch,
+        validator_pub_key: hex::decode(key)?,
+        squelch_duration: secs,
+    }))
+}
+
+fn to_owned(names: &[&str]) -> Vec<String> {
+    names.iter().map(|name| name.to_string()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commands_are_parsed() {
+        assert!(matches!(
+            "getledger 5".parse(),
+            Ok(Command::Send(Payload::TmGetLedger(TmGetLedger {
+                ledger_seq: Some(5),
+                ltype: None,
+                ..
+            })))
+        ));
+        assert!(matches!(
+            "squelch 0a0b 60".parse(),
+            Ok(Command::Send(Payload::TmSquelch(TmSquelch {
+                squelch: true,
+                squelch_duration: Some(60),
+                ..
+            })))
+        ));
+        assert!(matches!("raw 0102".parse(), Ok(Command::Raw(bytes)) if bytes == [1, 2]));
+        assert!("ping 1".parse::<Command>().is_err());
+        assert!("squelch xyz".parse::<Command>().is_err());
+    }
+}
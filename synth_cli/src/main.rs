@@ -0,0 +1,149 @@
+//! An interactive synthetic node, useful to explore how a node reacts to messages.
+//!
+//! The synthetic node connects to the node and sends canned payloads typed at the prompt, while
+//! the received messages are printed as they arrive. Pings from the node are answered
+//! automatically to keep the connection alive.
+//!
+//! Example:
+//! ```
+//!    ./synth-cli --node-addr 127.0.0.1:51235 --hide TmValidation --hide TmProposeLedger
+//! ```
+use std::{
+    collections::HashSet,
+    io::{self, Write},
+    net::SocketAddr,
+    process::ExitCode,
+    time::Duration,
+};
+
+use anyhow::Result;
+use clap::Parser;
+use tokio::{
+    io::{AsyncBufReadExt, BufReader},
+    time::interval,
+};
+use ziggurat_xrpl::{
+    protocol::{codecs::message::Payload, proto::tm_ping::PingType},
+    tools::{config::SynthNodeCfg, synth_node::SyntheticNode},
+};
+
+use crate::command::{ping, Command, HELP};
+
+mod command;
+
+const PROMPT: &str = "> ";
+const CONNECTION_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// An interactive synthetic node connecting to the XRPL node.
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+struct CmdArgs {
+    /// An address of the node in the <ip>:<port> format.
+    #[arg(short = 'i', long)]
+    node_addr: SocketAddr,
+
+    /// Kinds of received messages which aren't printed, e.g. TmValidation.
+    #[arg(long, default_values_t = ["TmPing".to_owned()])]
+    hide: Vec<String>,
+
+    /// Enable tracing.
+    #[arg(short = 't', long, default_value_t = false)]
+    tracing: bool,
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let args = CmdArgs::parse();
+
+    if args.tracing {
+        use tracing_subscriber::{fmt, EnvFilter};
+
+        fmt()
+            .with_writer(io::stderr)
+            .with_env_filter(EnvFilter::from_default_env())
+            .init();
+    }
+
+    match run(args).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("The synthetic node stopped: {e:?}.");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run(args: CmdArgs) -> Result<()> {
+    let mut synth_node = SyntheticNode::new(&SynthNodeCfg::default()).await;
+    synth_node.connect(args.node_addr).await?;
+    println!(
+        "Connected to {}, type `help` to list the commands.",
+        args.node_addr
+    );
+
+    let mut hidden: HashSet<String> = args.hide.into_iter().collect();
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+    let mut connection_check = interval(CONNECTION_CHECK_INTERVAL);
+    prompt();
+
+    loop {
+        tokio::select! {
+            line = lines.next_line() => {
+                // Stop on the end of the input.
+                let Some(line) = line? else {
+                    break;
+                };
+                if line.trim().is_empty() {
+                    prompt();
+                    continue;
+                }
+
+                match line.parse() {
+                    Ok(Command::Send(payload)) => {
+                        synth_node.unicast(args.node_addr, payload)?;
+                    }
+                    Ok(Command::Raw(bytes)) => {
+                        synth_node.unicast_bytes(args.node_addr, bytes)?;
+                    }
+                    Ok(Command::Hide(names)) => hidden.extend(names),
+                    Ok(Command::Show(names)) => {
+                        for name in names {
+                            hidden.remove(&name);
+                        }
+                    }
+                    Ok(Command::Help) => println!("{HELP}"),
+                    Ok(Command::Quit) => break,
+                    Err(e) => println!("{e}"),
+                }
+                prompt();
+            }
+            (_, message) = synth_node.recv_message() => {
+                if let Payload::TmPing(ping_message) = &message.payload {
+                    if ping_message.r#type == PingType::PtPing as i32 {
+                        let pong = ping(PingType::PtPong, ping_message.seq.unwrap_or_default());
+                        synth_node.unicast(args.node_addr, pong)?;
+                    }
+                }
+
+                if !hidden.contains(message.payload.name()) {
+                    println!("\n{:#?}", message.payload);
+                    prompt();
+                }
+            }
+            _ = connection_check.tick() => {
+                if !synth_node.is_connected(args.node_addr) {
+                    println!("\nThe node disconnected.");
+                    break;
+                }
+            }
+        }
+    }
+
+    synth_node.shut_down().await;
+    Ok(())
+}
+
+fn prompt() {
+    print!("{PROMPT}");
+    let _ = io::stdout().flush();
+}
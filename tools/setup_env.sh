@@ -33,58 +33,15 @@ setup_config_file() {
     echo
 }
 
-query_account_info() {
-    if [ $(uname) == "Linux" ]; then
-        TIMEOUT_CMD="timeout"
-    elif [ $(uname) == "Darwin" ]; then
-        TIMEOUT_CMD="gtimeout"
-    fi
-
-    # Run account query until it responds with "ResponseStatus.SUCCESS" or MAX_ATTEMPTS is reached
-    TIMEOUT_SEC=5
-    MAX_ATTEMPTS=5
-    NUM_ATTEMPTS=0
-
-    sleep $TIMEOUT_SEC
-    until [ $NUM_ATTEMPTS -gt $(($MAX_ATTEMPTS-1)) ] \
-        || $TIMEOUT_CMD $TIMEOUT_SEC python3 tools/account_info.py | grep "ResponseStatus.SUCCESS"; do
-        ((NUM_ATTEMPTS++))
-        echo "Query failed, number of attempts made: $NUM_ATTEMPTS"
-        echo "Retrying..."
-        sleep $TIMEOUT_SEC
-    done
-    if [ $NUM_ATTEMPTS -gt $(($MAX_ATTEMPTS-1)) ]; then
-        echo "Could not establish a connection with the genesis account. Please try again."
-        exit 1
-    fi
-    echo "Established a connection with the genesis account"
-    return 0
-}
-
 setup_stateful_nodes() {
-    # Query only after a long delay to account for compilation times and network preparation work
-    ACCOUNT_QUERY_DELAY_SEC=300
-
     echo "--- Setting up initial node state, takes at least 5 minutes"
     echo
-    echo "Spinning up a node instance, please be patient"
-    cargo t setup::testnet::test::run_testnet -- --ignored &
-    echo
-    sleep $ACCOUNT_QUERY_DELAY_SEC
-    echo "--- Querying account info"
-    query_account_info
-    echo
-    echo "--- Executing transfer script"
-    python3 tools/transfer.py
-    # Copy the node's files to directory referenced by constant pub const STATEFUL_NODES_DIR
-    cp -a $ZIGGURAT_RIPPLED_TESTNET_DIR $ZIGGURAT_RIPPLED_STATEFUL_DIR
-    echo
-    echo "--- Gracefully stopping the network"
-    kill %1
-    echo "--- Performing cleanup"
-    # Remove unneeded and temporary files
-    rm $ZIGGURAT_RIPPLED_STATEFUL_DIR/*/rippled.cfg
-    rm -rf $ZIGGURAT_RIPPLED_TESTNET_DIR
+    # Runs a testnet, funds the test account and saves the state to the directory referenced by
+    # the STATEFUL_NODES_DIR constant.
+    if ! cargo run --release -p state_gen; then
+        echo "Could not generate the node state. Please try again."
+        exit 1
+    fi
     echo
 }
 
@@ -0,0 +1,154 @@
+//! Decodes peer frames and serialized objects given as hex or base64, e.g. copied from a failing
+//! test's output or a log.
+//!
+//! Examples:
+//! ```
+//!    echo 00000004000308001001 | xrpl-decode              // A peer frame (TmPing)
+//!    echo JAAAAAFxIe0... | xrpl-decode --kind stobject    // A base64 manifest
+//! ```
+//!
+//! Objects carried by manifests, validations and transactions are decoded as well.
+use std::{
+    io::{self, Read},
+    process::ExitCode,
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use bytes::BytesMut;
+use clap::{Parser, ValueEnum};
+use tokio_util::codec::Decoder;
+use tracing::Span;
+use ziggurat_xrpl::protocol::codecs::message::{
+    MessageCodec, Payload, HEADER_LEN_UNCOMPRESSED, UNCOMPRESSED_SIZE_MASK,
+};
+
+mod stobject;
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Encoding {
+    /// Hex if the input is valid hex, base64 otherwise.
+    Auto,
+    Hex,
+    Base64,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Kind {
+    /// A frame if the input starts with a matching uncompressed header, an object otherwise.
+    Auto,
+    /// A peer protocol frame, including the header.
+    Frame,
+    /// An object in the canonical binary format (manifest, validation, transaction...).
+    Stobject,
+}
+
+/// Decodes a peer frame or a serialized object read from stdin, or from the argument if given.
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+struct CmdArgs {
+    /// The encoded input, read from stdin if missing.
+    input: Option<String>,
+
+    /// The encoding of the input.
+    #[arg(short, long, value_enum, default_value_t = Encoding::Auto)]
+    encoding: Encoding,
+
+    /// What the input contains.
+    #[arg(short, long, value_enum, default_value_t = Kind::Auto)]
+    kind: Kind,
+}
+
+fn main() -> ExitCode {
+    let args = CmdArgs::parse();
+
+    match run(args) {
+        Ok(output) => {
+            print!("{output}");
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("Unable to decode the input: {e:#}.");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(args: CmdArgs) -> Result<String> {
+    let input = match args.input {
+        Some(input) => input,
+        None => {
+            let mut input = String::new();
+            io::stdin().read_to_string(&mut input)?;
+            input
+        }
+    };
+    let bytes = decode_input(&input, args.encoding)?;
+
+    let is_frame = match args.kind {
+        Kind::Auto => is_uncompressed_frame(&bytes),
+        kind => kind == Kind::Frame,
+    };
+    if is_frame {
+        decode_frame(&bytes)
+    } else {
+        stobject::pretty(&bytes)
+    }
+}
+
+fn decode_input(input: &str, encoding: Encoding) -> Result<Vec<u8>> {
+    // Allow the input to be wrapped or grouped.
+    let input: String = input.split_whitespace().collect();
+
+    match encoding {
+        Encoding::Hex => Ok(hex::decode(input)?),
+        Encoding::Base64 => Ok(STANDARD.decode(input)?),
+        Encoding::Auto => hex::decode(&input).or_else(|_| Ok(STANDARD.decode(&input)?)),
+    }
+}
+
+fn is_uncompressed_frame(bytes: &[u8]) -> bool {
+    let Some(header) = bytes.get(..4) else {
+        return false;
+    };
+    let size = u32::from_be_bytes(header.try_into().unwrap());
+    size & !UNCOMPRESSED_SIZE_MASK == 0 && size as usize + HEADER_LEN_UNCOMPRESSED == bytes.len()
+}
+
+fn decode_frame(bytes: &[u8]) -> Result<String> {
+    let mut buf = BytesMut::from(bytes);
+    let message = MessageCodec::new(Span::none())
+        .decode(&mut buf)?
+        .ok_or_else(|| anyhow!("incomplete frame"))?;
+    if !buf.is_empty() {
+        bail!("{} bytes left after the frame", buf.len());
+    }
+
+    let mut output = format!("{:#?}\n", message.payload);
+    for (name, object) in carried_objects(&message.payload) {
+        let fields = stobject::pretty(object).with_context(|| format!("invalid {name}"))?;
+        output.push_str(&format!("\n{name}:\n{fields}"));
+    }
+    Ok(output)
+}
+
+// Returns the serialized objects carried by the payload.
+fn carried_objects(payload: &Payload) -> Vec<(&'static str, &[u8])> {
+    match payload {
+        Payload::TmManifests(manifests) => manifests
+            .list
+            .iter()
+            .map(|manifest| ("manifest", manifest.stobject.as_slice()))
+            .collect(),
+        Payload::TmValidation(validation) => vec![("validation", &validation.validation)],
+        Payload::TmTransaction(transaction) => {
+            vec![("transaction", &transaction.raw_transaction)]
+        }
+        Payload::TmTransactions(transactions) => transactions
+            .transactions
+            .iter()
+            .map(|transaction| ("transaction", transaction.raw_transaction.as_slice()))
+            .collect(),
+        _ => vec![],
+    }
+}
@@ -0,0 +1,142 @@
+//! Prints objects in Ripple's canonical binary format (manifests, validations, transactions,
+//! ledger entries), as decoded by [ziggurat_xrpl::protocol::stobject].
+//!
+//! Fields are printed with their names where known, their type and field codes otherwise. The
+//! values aren't interpreted beyond their type, e.g. account IDs are printed as hex.
+
+use std::fmt::Write;
+
+use anyhow::Result;
+use ziggurat_xrpl::protocol::stobject::{
+    Field, StObject, ST_ACCOUNT, ST_AMOUNT, ST_ARRAY, ST_BLOB, ST_HASH256, ST_OBJECT, ST_UINT16,
+    ST_UINT32, ST_UINT64, ST_UINT8, ST_VECTOR256,
+};
+
+// XRP amounts have this bit set if they're positive.
+const XRP_AMOUNT_POSITIVE_BIT: u64 = 0x4000_0000_0000_0000;
+
+const HASH256_SIZE: usize = 32;
+
+// Names of the common fields, by type and field code.
+const FIELD_NAMES: &[(u8, u8, &str)] = &[
+    (ST_UINT16, 1, "LedgerEntryType"),
+    (ST_UINT16, 2, "TransactionType"),
+    (ST_UINT32, 1, "NetworkID"),
+    (ST_UINT32, 2, "Flags"),
+    (ST_UINT32, 3, "SourceTag"),
+    (ST_UINT32, 4, "Sequence"),
+    (ST_UINT32, 6, "LedgerSequence"),
+    (ST_UINT32, 7, "CloseTime"),
+    (ST_UINT32, 9, "SigningTime"),
+    (ST_UINT32, 14, "DestinationTag"),
+    (ST_UINT32, 24, "LoadFee"),
+    (ST_UINT32, 27, "LastLedgerSequence"),
+    (ST_UINT32, 31, "ReserveBase"),
+    (ST_UINT32, 32, "ReserveIncrement"),
+    (ST_UINT64, 5, "BaseFee"),
+    (ST_UINT64, 10, "Cookie"),
+    (ST_UINT64, 11, "ServerVersion"),
+    (ST_HASH256, 1, "LedgerHash"),
+    (ST_HASH256, 2, "ParentHash"),
+    (ST_HASH256, 23, "ConsensusHash"),
+    (ST_HASH256, 25, "ValidatedHash"),
+    (ST_AMOUNT, 1, "Amount"),
+    (ST_AMOUNT, 8, "Fee"),
+    (ST_BLOB, 1, "PublicKey"),
+    (ST_BLOB, 3, "SigningPubKey"),
+    (ST_BLOB, 4, "TxnSignature"),
+    (ST_BLOB, 6, "Signature"),
+    (ST_BLOB, 7, "Domain"),
+    (ST_BLOB, 18, "MasterSignature"),
+    (ST_ACCOUNT, 1, "Account"),
+    (ST_ACCOUNT, 3, "Destination"),
+    (ST_VECTOR256, 3, "Amendments"),
+];
+
+/// Decodes a serialized object and prints its fields, one per line, nesting objects and arrays.
+pub fn pretty(bytes: &[u8]) -> Result<String> {
+    let mut output = String::new();
+    write_fields(&mut output, &StObject::deserialize(bytes)?, 0)?;
+    Ok(output)
+}
+
+fn write_fields(output: &mut String, object: &StObject, depth: usize) -> Result<()> {
+    let indent = "  ".repeat(depth);
+    for field in object.fields() {
+        let name = field_name(field);
+        match field.type_code {
+            ST_UINT8 | ST_UINT16 | ST_UINT32 | ST_UINT64 => {
+                let value = field
+                    .value
+                    .iter()
+                    .fold(0, |acc, &byte| acc << 8 | byte as u64);
+                writeln!(output, "{indent}{name}: {value}")?;
+            }
+            ST_AMOUNT => match xrp_drops(&field.value) {
+                Some(drops) => writeln!(output, "{indent}{name}: {drops} drops")?,
+                None => writeln!(
+                    output,
+                    "{indent}{name}: {}",
+                    hex::encode_upper(&field.value)
+                )?,
+            },
+            ST_VECTOR256 => {
+                writeln!(output, "{indent}{name}:")?;
+                for hash in field.value.chunks(HASH256_SIZE) {
+                    writeln!(output, "{indent}  {}", hex::encode_upper(hash))?;
+                }
+            }
+            ST_OBJECT | ST_ARRAY => {
+                writeln!(output, "{indent}{name}:")?;
+                write_fields(output, &StObject::deserialize(&field.value)?, depth + 1)?;
+            }
+            _ => writeln!(
+                output,
+                "{indent}{name}: {}",
+                hex::encode_upper(&field.value)
+            )?,
+        }
+    }
+    Ok(())
+}
+
+fn field_name(field: &Field) -> String {
+    FIELD_NAMES
+        .iter()
+        .find(|(type_code, field_code, _)| {
+            (*type_code, *field_code) == (field.type_code, field.field_code)
+        })
+        .map(|(_, _, name)| name.to_string())
+        .unwrap_or_else(|| format!("Field({}, {})", field.type_code, field.field_code))
+}
+
+// Returns the drops of an XRP amount, issued currency amounts being longer than 8 bytes.
+fn xrp_drops(amount: &[u8]) -> Option<i64> {
+    let amount = u64::from_be_bytes(amount.try_into().ok()?);
+    let drops = (amount & !XRP_AMOUNT_POSITIVE_BIT) as i64;
+    Some(if amount & XRP_AMOUNT_POSITIVE_BIT != 0 {
+        drops
+    } else {
+        -drops
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use ziggurat_xrpl::tools::validator::{create_manifest, KeyType, ValidatorKey};
+
+    use super::*;
+
+    #[test]
+    fn manifest_is_decoded() {
+        let master = ValidatorKey::generate(KeyType::Ed25519);
+        let signing = ValidatorKey::generate(KeyType::Secp256k1);
+        let manifest = create_manifest(7, &master, &signing);
+
+        let output = pretty(&manifest).unwrap();
+
+        assert!(output.starts_with("Sequence: 7\n"));
+        assert!(output.contains(&format!("PublicKey: {}", master.public_key_hex())));
+        assert!(output.contains("MasterSignature: "));
+    }
+}